@@ -0,0 +1,247 @@
+use actix_web::{web, HttpResponse, Responder};
+use crate::jobs::registry::JOBS;
+use crate::database::MongoDB;
+use crate::middleware::auth::Claims;
+use crate::services::strategy_service;
+use crate::utils::clock_skew::CLOCK_SKEW;
+use crate::utils::log_level;
+use crate::utils::runtime_config::RuntimeConfig;
+use crate::utils::trading_switch::TRADING_SWITCH;
+use serde::Deserialize;
+
+/// Resposta 403 quando o usuário não tem a role "admin"
+pub fn forbidden_unless_admin(user: &Claims, action: &str) -> Option<HttpResponse> {
+    if user.is_admin() {
+        return None;
+    }
+    log::warn!("🔒 User {} tried to {} without admin role", user.sub, action);
+    Some(HttpResponse::Forbidden().json(serde_json::json!({
+        "success": false,
+        "error": "Admin role required"
+    })))
+}
+
+/// GET /api/v1/admin/jobs - Status dos jobs em background
+pub async fn list_jobs(user: web::ReqData<Claims>) -> impl Responder {
+    if let Some(forbidden) = forbidden_unless_admin(&user, "list jobs") {
+        return forbidden;
+    }
+
+    let jobs = JOBS.statuses();
+    HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "count": jobs.len(),
+        "jobs": jobs
+    }))
+}
+
+/// GET /api/v1/admin/health - Estado interno (jobs e clock skew por exchange)
+pub async fn admin_health(user: web::ReqData<Claims>) -> impl Responder {
+    if let Some(forbidden) = forbidden_unless_admin(&user, "read admin health") {
+        return forbidden;
+    }
+
+    let jobs = JOBS.statuses();
+    let failing_jobs = jobs.iter().filter(|j| j.last_error.is_some()).count();
+    HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "timestamp": chrono::Utc::now().timestamp(),
+        "trading_enabled": TRADING_SWITCH.is_enabled(),
+        "jobs": { "count": jobs.len(), "failing": failing_jobs },
+        "clock_skew": {
+            "threshold_ms": CLOCK_SKEW.threshold_ms(),
+            "exchanges": CLOCK_SKEW.snapshot()
+        }
+    }))
+}
+
+/// GET /api/v1/admin/config - Configuração efetiva carregada do ambiente (segredos mascarados)
+pub async fn get_runtime_config(user: web::ReqData<Claims>) -> impl Responder {
+    if let Some(forbidden) = forbidden_unless_admin(&user, "read runtime config") {
+        return forbidden;
+    }
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "trading_enabled": TRADING_SWITCH.is_enabled(),
+        "config": RuntimeConfig::from_env()
+    }))
+}
+
+/// POST /api/v1/admin/jobs/{name}/trigger - Executa o job imediatamente
+pub async fn trigger_job(
+    user: web::ReqData<Claims>,
+    path: web::Path<String>,
+) -> impl Responder {
+    if let Some(forbidden) = forbidden_unless_admin(&user, "trigger a job") {
+        return forbidden;
+    }
+
+    let name = path.into_inner();
+    log::info!("▶️ POST /admin/jobs/{}/trigger - by {}", name, user.sub);
+
+    if JOBS.status(&name).is_none() {
+        return HttpResponse::NotFound().json(serde_json::json!({
+            "success": false,
+            "error": format!("Job not found: {}", name)
+        }));
+    }
+
+    match JOBS.trigger(&name).await {
+        Ok(job) => HttpResponse::Ok().json(serde_json::json!({
+            "success": job.last_error.is_none(),
+            "job": job
+        })),
+        Err(e) => HttpResponse::Conflict().json(serde_json::json!({
+            "success": false,
+            "error": e
+        })),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetLogLevelRequest {
+    /// Módulo (ex: "trading_service::services::strategy_service")
+    pub target: String,
+    /// off/error/warn/info/debug/trace; null remove o override
+    pub level: Option<String>,
+}
+
+/// GET /api/v1/admin/log-level - Overrides de nível de log ativos
+pub async fn get_log_levels(user: web::ReqData<Claims>) -> impl Responder {
+    if let Some(forbidden) = forbidden_unless_admin(&user, "read log levels") {
+        return forbidden;
+    }
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "max_level": log::max_level().to_string().to_lowercase(),
+        "overrides": log_level::LOG_LEVELS.overrides()
+    }))
+}
+
+/// PUT /api/v1/admin/log-level - Ajusta o nível de log de um módulo sem reiniciar
+pub async fn set_log_level(
+    user: web::ReqData<Claims>,
+    body: web::Json<SetLogLevelRequest>,
+) -> impl Responder {
+    if let Some(forbidden) = forbidden_unless_admin(&user, "change log level") {
+        return forbidden;
+    }
+
+    let level = match body.level.as_deref().map(log_level::parse_level).transpose() {
+        Ok(level) => level,
+        Err(e) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({ "success": false, "error": e }));
+        }
+    };
+    if let Err(e) = log_level::validate_target(&body.target) {
+        return HttpResponse::BadRequest().json(serde_json::json!({ "success": false, "error": e }));
+    }
+
+    log_level::set_module_level(&body.target, level);
+    log::warn!("🔊 Log level for {} set to {:?} by {}", body.target, level, user.sub);
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "overrides": log_level::LOG_LEVELS.overrides()
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetTradingEnabledRequest {
+    pub enabled: bool,
+}
+
+/// GET /api/v1/admin/trading-enabled - Estado do kill switch de trading
+pub async fn get_trading_enabled(user: web::ReqData<Claims>) -> impl Responder {
+    if let Some(forbidden) = forbidden_unless_admin(&user, "read trading switch") {
+        return forbidden;
+    }
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "trading_enabled": TRADING_SWITCH.is_enabled()
+    }))
+}
+
+/// PUT /api/v1/admin/trading-enabled - Liga/desliga todas as ordens reais sem reiniciar
+pub async fn set_trading_enabled(
+    user: web::ReqData<Claims>,
+    body: web::Json<SetTradingEnabledRequest>,
+) -> impl Responder {
+    if let Some(forbidden) = forbidden_unless_admin(&user, "toggle trading") {
+        return forbidden;
+    }
+
+    let previous = TRADING_SWITCH.set_enabled(body.enabled);
+    if body.enabled {
+        log::warn!("🟢 Live trading ENABLED by {} (was {})", user.sub, previous);
+    } else {
+        log::warn!("🛑 Live trading DISABLED by {} (was {})", user.sub, previous);
+    }
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "trading_enabled": body.enabled,
+        "previous": previous
+    }))
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct PauseExchangeStrategiesRequest {
+    /// Motivo mostrado ao usuário (ex: "exchange outage")
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+/// POST /api/v1/admin/exchanges/{ccxt_id}/pause-strategies - Pausa só as estratégias da exchange
+pub async fn pause_exchange_strategies(
+    user: web::ReqData<Claims>,
+    db: web::Data<MongoDB>,
+    path: web::Path<String>,
+    body: Option<web::Json<PauseExchangeStrategiesRequest>>,
+) -> impl Responder {
+    if let Some(forbidden) = forbidden_unless_admin(&user, "pause exchange strategies") {
+        return forbidden;
+    }
+
+    let ccxt_id = path.into_inner();
+    let reason = body.and_then(|b| b.into_inner().reason)
+        .map(|r| r.trim().to_string())
+        .filter(|r| !r.is_empty())
+        .unwrap_or_else(|| "exchange degraded".to_string());
+    log::warn!("⏸️ POST /admin/exchanges/{}/pause-strategies by {}: {}", ccxt_id, user.sub, reason);
+
+    match strategy_service::pause_exchange_strategies(&db, &ccxt_id, &reason).await {
+        Ok(summary) => HttpResponse::Ok().json(serde_json::json!({
+            "success": summary.errors.is_empty(),
+            "paused": summary.strategy_ids.len(),
+            "result": summary
+        })),
+        Err(e) => HttpResponse::BadRequest().json(serde_json::json!({ "success": false, "error": e })),
+    }
+}
+
+/// POST /api/v1/admin/exchanges/{ccxt_id}/resume-strategies - Reativa as pausadas pela exchange
+pub async fn resume_exchange_strategies(
+    user: web::ReqData<Claims>,
+    db: web::Data<MongoDB>,
+    path: web::Path<String>,
+) -> impl Responder {
+    if let Some(forbidden) = forbidden_unless_admin(&user, "resume exchange strategies") {
+        return forbidden;
+    }
+
+    let ccxt_id = path.into_inner();
+    log::info!("▶️ POST /admin/exchanges/{}/resume-strategies by {}", ccxt_id, user.sub);
+
+    match strategy_service::resume_exchange_strategies(&db, &ccxt_id).await {
+        Ok(summary) => HttpResponse::Ok().json(serde_json::json!({
+            "success": summary.errors.is_empty(),
+            "resumed": summary.strategy_ids.len(),
+            "result": summary
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({ "success": false, "error": e })),
+    }
+}
@@ -1,6 +1,8 @@
 use actix_web::{web, HttpResponse, HttpRequest};
 use crate::{database::MongoDB, services::auth_service};
 use crate::services::auth_service::{LoginRequest, RegisterRequest, AuthResponse, UserInfo};
+use crate::services::api_key_service::{self, CreateApiKeyRequest};
+use crate::middleware::auth::Claims;
 use base64::Engine;
 
 #[utoipa::path(
@@ -411,3 +413,154 @@ pub async fn delete_account(
         "error": "No valid Authorization header"
     }))
 }
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/auth/export",
+    tag = "Auth",
+    responses(
+        (status = 200, description = "User data export (JSON attachment)"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "User not found")
+    ),
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn export_data(
+    db: web::Data<MongoDB>,
+    req: HttpRequest,
+) -> HttpResponse {
+    log::info!("📦 GET /auth/export");
+
+    let token = req.headers().get("Authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|s| s.strip_prefix("Bearer "));
+
+    let Some(token) = token else {
+        log::warn!("❌ No valid Authorization header");
+        return HttpResponse::Unauthorized().json(serde_json::json!({
+            "success": false,
+            "error": "No valid Authorization header"
+        }));
+    };
+
+    let claims = match auth_service::verify_token(token) {
+        Ok(claims) => claims,
+        Err(e) => {
+            log::warn!("❌ Invalid token: {}", e);
+            return HttpResponse::Unauthorized().json(serde_json::json!({
+                "success": false,
+                "error": "Invalid or expired token"
+            }));
+        }
+    };
+
+    match auth_service::export_user_data(&db, &claims.sub).await {
+        Ok(export) => {
+            let filename = format!("trading-service-export-{}.json", claims.sub);
+            HttpResponse::Ok()
+                .content_type("application/json")
+                .insert_header((
+                    "Content-Disposition",
+                    format!("attachment; filename=\"{}\"", filename),
+                ))
+                .json(export)
+        }
+        Err(e) if e.contains("not found") => HttpResponse::NotFound().json(serde_json::json!({
+            "success": false,
+            "error": e
+        })),
+        Err(e) => {
+            log::error!("❌ Failed to export data for {}: {}", claims.sub, e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "error": format!("Failed to export data: {}", e)
+            }))
+        }
+    }
+}
+
+// ==================== API KEYS ====================
+// Rotas protegidas pelo AuthMiddleware (JWT ou X-API-Key). Criar e revogar exigem
+// o JWT interativo — uma API key não gerencia chaves.
+
+fn forbidden_for_api_key(user: &Claims) -> Option<HttpResponse> {
+    api_key_service::is_api_key_session(user).then(|| HttpResponse::Forbidden().json(serde_json::json!({
+        "success": false,
+        "error": "API keys can't manage API keys. Use your login session."
+    })))
+}
+
+/// POST /api/v1/auth/api-keys - Gera uma API key (a chave só aparece nesta resposta)
+pub async fn create_api_key(
+    user: web::ReqData<Claims>,
+    db: web::Data<MongoDB>,
+    body: web::Json<CreateApiKeyRequest>,
+) -> HttpResponse {
+    log::info!("🔑 POST /auth/api-keys - user {}", user.sub);
+    if let Some(forbidden) = forbidden_for_api_key(&user) {
+        return forbidden;
+    }
+
+    match api_key_service::create_api_key(&db, &user.sub, &body).await {
+        Ok((api_key, key)) => HttpResponse::Created().json(serde_json::json!({
+            "success": true,
+            "api_key": api_key,
+            "key": key,
+            "message": "Store this key now: it won't be shown again"
+        })),
+        Err(e) if e.starts_with("Database error") || e.starts_with("Failed") => {
+            log::error!("❌ Failed to create API key: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({ "success": false, "error": e }))
+        }
+        Err(e) => HttpResponse::BadRequest().json(serde_json::json!({ "success": false, "error": e })),
+    }
+}
+
+/// GET /api/v1/auth/api-keys - Lista as API keys do usuário (sem a chave)
+pub async fn list_api_keys(
+    user: web::ReqData<Claims>,
+    db: web::Data<MongoDB>,
+) -> HttpResponse {
+    log::info!("🔑 GET /auth/api-keys - user {}", user.sub);
+
+    match api_key_service::list_api_keys(&db, &user.sub).await {
+        Ok(api_keys) => HttpResponse::Ok().json(serde_json::json!({
+            "success": true,
+            "count": api_keys.len(),
+            "api_keys": api_keys
+        })),
+        Err(e) => {
+            log::error!("❌ Failed to list API keys: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({ "success": false, "error": e }))
+        }
+    }
+}
+
+/// DELETE /api/v1/auth/api-keys/{key_id} - Revoga a API key
+pub async fn revoke_api_key(
+    user: web::ReqData<Claims>,
+    db: web::Data<MongoDB>,
+    key_id: web::Path<String>,
+) -> HttpResponse {
+    log::info!("🔑 DELETE /auth/api-keys/{} - user {}", key_id, user.sub);
+    if let Some(forbidden) = forbidden_for_api_key(&user) {
+        return forbidden;
+    }
+
+    match api_key_service::revoke_api_key(&db, &user.sub, &key_id).await {
+        Ok(true) => HttpResponse::Ok().json(serde_json::json!({
+            "success": true,
+            "message": "API key revoked"
+        })),
+        Ok(false) => HttpResponse::NotFound().json(serde_json::json!({
+            "success": false,
+            "error": "API key not found or already revoked"
+        })),
+        Err(e) => {
+            log::error!("❌ Failed to revoke API key: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({ "success": false, "error": e }))
+        }
+    }
+}
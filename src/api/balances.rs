@@ -1,6 +1,6 @@
 use actix_web::{web, HttpResponse, Responder};
 use serde::{Deserialize, Serialize};
-use crate::{database::MongoDB, services::balance_service, middleware::auth::Claims};
+use crate::{database::MongoDB, services::{balance_service, user_settings_service}, middleware::auth::Claims};
 
 #[derive(Debug, Deserialize)]
 pub struct BalanceQuery {
@@ -75,6 +75,8 @@ pub async fn post_balances(
             api_secret: e.api_secret.clone(),
             passphrase: e.passphrase.clone(),
             is_active: true,
+            can_trade: None,
+            sub_account: None,
         }
     }).collect();
     
@@ -122,6 +124,7 @@ pub async fn post_balances_secure(
             match balance_service::fetch_balances_from_exchanges(exchanges).await {
                 Ok(response) => {
                     log::info!("✅ Balances fetched: {} exchanges", response.exchanges.len());
+                    crate::services::credential_health_service::report_balance_results(&db, user_id, &response.exchanges).await;
                     HttpResponse::Ok().json(response)
                 }
                 Err(e) => {
@@ -143,6 +146,117 @@ pub async fn post_balances_secure(
     }
 }
 
+// Request body para POST /balances/sweep
+#[derive(Debug, Deserialize)]
+pub struct SweepDustRequest {
+    pub exchange_id: String,
+    /// Padrão: `default_quote_currency` das preferências do usuário
+    pub target: Option<String>,
+    /// Padrão: `dust_threshold_usd` das preferências do usuário
+    pub threshold_usd: Option<f64>,
+    /// Confirmação explícita — o sweep executa ordens reais a mercado
+    #[serde(default)]
+    pub confirm: bool,
+}
+
+// /api/v1/balances/sweep (POST) - 🧹 Converte poeira em `target` (JWT + confirm obrigatórios)
+pub async fn sweep_dust(
+    user: web::ReqData<Claims>,
+    db: web::Data<MongoDB>,
+    body: web::Json<SweepDustRequest>,
+) -> HttpResponse {
+    let user_id = &user.sub;
+    log::info!("🧹 POST /balances/sweep - user {} exchange {} → {:?}", user_id, body.exchange_id, body.target);
+
+    if !body.confirm {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "success": false,
+            "error": "Dust sweep places real market orders. Resend with \"confirm\": true to proceed."
+        }));
+    }
+
+    let settings = match user_settings_service::get_user_settings(&db, user_id).await {
+        Ok(settings) => settings,
+        Err(e) => {
+            log::error!("❌ Error loading user settings: {}", e);
+            return HttpResponse::InternalServerError().json(serde_json::json!({ "success": false, "error": e }));
+        }
+    };
+    let target = body.target.clone().unwrap_or(settings.default_quote_currency);
+    let threshold = body.threshold_usd.unwrap_or(settings.dust_threshold_usd);
+    if threshold <= 0.0 {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "success": false,
+            "error": "threshold_usd must be greater than 0"
+        }));
+    }
+
+    match balance_service::sweep_dust(&db, user_id, &body.exchange_id, &target, threshold).await {
+        Ok(result) => HttpResponse::Ok().json(result),
+        Err(e) if e.starts_with("Trade permission required") => {
+            HttpResponse::Forbidden().json(serde_json::json!({ "success": false, "error": e }))
+        }
+        Err(e) if e.contains("not found") => {
+            HttpResponse::NotFound().json(serde_json::json!({ "success": false, "error": e }))
+        }
+        Err(e) => {
+            log::error!("❌ Dust sweep failed: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({ "success": false, "error": e }))
+        }
+    }
+}
+
+// GET /api/v1/balances/asset/{symbol} - Um ativo agregado entre todas as exchanges (JWT)
+pub async fn get_asset_balance(
+    user: web::ReqData<Claims>,
+    db: web::Data<MongoDB>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let symbol = path.into_inner();
+    log::info!("🔎 GET /balances/asset/{} - user {}", symbol, user.sub);
+
+    match balance_service::get_asset_balance(&db, &user.sub, &symbol).await {
+        Ok(response) => HttpResponse::Ok().json(response),
+        Err(e) => {
+            log::error!("❌ Error fetching asset balance: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "error": e
+            }))
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DailyPnLQuery {
+    /// YYYY-MM-DD (padrão: hoje)
+    pub date: Option<String>,
+    /// Moeda dos campos formatados (padrão: USD)
+    pub currency: Option<String>,
+}
+
+// GET /api/v1/balances/daily-pnl - Variação do saldo contra o snapshot anterior (JWT)
+pub async fn get_daily_pnl(
+    user: web::ReqData<Claims>,
+    db: web::Data<MongoDB>,
+    query: web::Query<DailyPnLQuery>,
+) -> HttpResponse {
+    let date = query.date.clone().unwrap_or_else(|| chrono::Local::now().format("%Y-%m-%d").to_string());
+    let currency = query.currency.as_deref().unwrap_or("USD");
+    log::info!("📊 GET /balances/daily-pnl - user {} ({}, {})", user.sub, date, currency);
+
+    match balance_service::get_daily_pnl(&db, &user.sub, &date, currency).await {
+        Ok(response) => HttpResponse::Ok().json(response),
+        Err(e) => {
+            log::error!("❌ Error calculating daily PNL: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "error": e
+            }))
+        }
+    }
+}
+
 // GET /api/v1/balances/summary - Fast summary from CCXT
 pub async fn get_balance_summary(
     query: web::Query<BalanceQuery>,
@@ -1,6 +1,7 @@
 use actix_web::{web, HttpResponse};
 use crate::database::MongoDB;
-use crate::services::exchange_service::{self, AvailableExchangesResponse};
+use crate::services::exchange_service::{self, AvailableExchangesResponse, ExchangeTimeframesResponse};
+use crate::services::token_service;
 use serde::Deserialize;
 
 #[derive(Deserialize)]
@@ -64,4 +65,43 @@ pub async fn get_token_details(
             }))
         }
     }
-}
\ No newline at end of file
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/exchanges/{ccxt_id}/timeframes",
+    tag = "Exchanges",
+    params(
+        ("ccxt_id" = String, Path, description = "CCXT exchange id (ex: binance)")
+    ),
+    responses(
+        (status = 200, description = "Supported OHLCV timeframes", body = ExchangeTimeframesResponse),
+        (status = 400, description = "Exchange does not provide OHLCV"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn get_exchange_timeframes(path: web::Path<String>) -> HttpResponse {
+    let ccxt_id = path.into_inner();
+    log::info!("🕯️ GET /exchanges/{}/timeframes", ccxt_id);
+
+    match exchange_service::get_exchange_timeframes(&ccxt_id).await {
+        Ok(response) => HttpResponse::Ok().json(response),
+        Err(e) if token_service::is_not_supported(&e) => {
+            log::warn!("⚠️ {}", e);
+            HttpResponse::BadRequest().json(serde_json::json!({
+                "success": false,
+                "error": e
+            }))
+        }
+        Err(e) => {
+            log::error!("❌ Error fetching timeframes for {}: {}", ccxt_id, e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "error": e
+            }))
+        }
+    }
+}
@@ -1,10 +1,17 @@
 use actix_web::{HttpResponse};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
 
 static REQUEST_COUNT: AtomicU64 = AtomicU64::new(0);
 static ERROR_COUNT: AtomicU64 = AtomicU64::new(0);
 
+lazy_static::lazy_static! {
+    /// Ordens rejeitadas pela exchange, por tipo de erro mapeado (ex: insufficient_funds)
+    static ref ORDER_REJECTIONS: Mutex<BTreeMap<String, u64>> = Mutex::new(BTreeMap::new());
+}
+
 pub fn increment_request_count() {
     REQUEST_COUNT.fetch_add(1, Ordering::Relaxed);
 }
@@ -14,6 +21,14 @@ pub fn increment_error_count() {
     ERROR_COUNT.fetch_add(1, Ordering::Relaxed);
 }
 
+pub fn increment_order_rejection(error_type: &str) {
+    *ORDER_REJECTIONS.lock().unwrap().entry(error_type.to_string()).or_insert(0) += 1;
+}
+
+pub fn order_rejection_counts() -> BTreeMap<String, u64> {
+    ORDER_REJECTIONS.lock().unwrap().clone()
+}
+
 #[derive(Serialize, Deserialize, utoipa::ToSchema)]
 pub struct MetricsResponse {
     pub http_requests_total: u64,
@@ -32,7 +47,7 @@ pub async fn get_metrics() -> HttpResponse {
     let requests = REQUEST_COUNT.load(Ordering::Relaxed);
     let errors = ERROR_COUNT.load(Ordering::Relaxed);
     
-    let metrics = format!(
+    let mut metrics = format!(
         "# HELP http_requests_total Total number of HTTP requests\n\
          # TYPE http_requests_total counter\n\
          http_requests_total {}\n\
@@ -42,6 +57,17 @@ pub async fn get_metrics() -> HttpResponse {
          http_errors_total {}\n",
         requests, errors
     );
+
+    let rejections = order_rejection_counts();
+    if !rejections.is_empty() {
+        metrics.push_str(
+            "\n# HELP order_rejections_total Orders rejected by exchanges, by mapped error type\n\
+             # TYPE order_rejections_total counter\n",
+        );
+        for (error_type, count) in rejections {
+            metrics.push_str(&format!("order_rejections_total{{error_type=\"{}\"}} {}\n", error_type, count));
+        }
+    }
     
     HttpResponse::Ok()
         .content_type("text/plain; version=0.0.4")
@@ -9,8 +9,10 @@ pub mod auth;
 pub mod external;
 pub mod swagger;
 pub mod user_exchanges;
+pub mod user_settings;
 pub mod snapshots;
 pub mod strategies;
 pub mod strategy_templates;
-
+pub mod admin;
+pub mod portfolio;
 
@@ -6,6 +6,7 @@ use crate::{
         DecryptedExchange,
         CreateOrderWithCredsRequest, 
         CancelOrderWithCredsRequest,
+        OrderDateRange,
     },
     middleware::auth::Claims,
     database::MongoDB,
@@ -20,16 +21,56 @@ use crate::{
 // 📊 FETCH ORDERS - Buscar ordens abertas
 // ============================================================================
 
-/// 🔒 POST /api/v1/orders/fetch/secure
+/// Query de `fetch/secure`: `status` (open/closed/all) e janela `since`/`until`
+/// (timestamps em ms). Com datas e sem `status`, busca as fechadas — histórico de
+/// execuções para IR/contabilidade.
+#[derive(Debug, Deserialize)]
+pub struct FetchOrdersQuery {
+    pub status: Option<String>,
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+}
+
+impl FetchOrdersQuery {
+    fn range(&self) -> OrderDateRange {
+        OrderDateRange { since: self.since, until: self.until }
+    }
+
+    fn status(&self) -> Result<String, String> {
+        let status = match self.status.as_deref().map(|s| s.trim().to_lowercase()) {
+            Some(status) => status,
+            None if !self.range().is_empty() => "closed".to_string(),
+            None => "open".to_string(),
+        };
+        match status.as_str() {
+            "open" | "closed" | "all" => Ok(status),
+            other => Err(format!("Invalid status '{}'. Use open, closed or all", other)),
+        }
+    }
+}
+
+/// 🔒 POST /api/v1/orders/fetch/secure?status=closed&since=...&until=...
 /// Busca orders usando JWT - credenciais vêm do MongoDB
 /// Body: vazio (user_id vem do JWT)
 pub async fn fetch_orders_secure(
     user: web::ReqData<Claims>,
     db: web::Data<MongoDB>,
+    query: web::Query<FetchOrdersQuery>,
 ) -> impl Responder {
     let user_id = &user.sub;
     
-    log::info!("🔐 Fetching orders for user {}", user_id);
+    let range = query.range();
+    let status = match query.status().and_then(|status| range.validate().map(|_| status)) {
+        Ok(status) => status,
+        Err(e) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "success": false,
+                "error": e
+            }));
+        }
+    };
+    
+    log::info!("🔐 Fetching {} orders for user {} (since {:?}, until {:?})", status, user_id, range.since, range.until);
     
     // 1. Buscar exchanges do MongoDB (descriptografadas)
     let exchanges = match crate::services::user_exchanges_service::get_user_exchanges_decrypted(&db, user_id).await {
@@ -55,7 +96,7 @@ pub async fn fetch_orders_secure(
     log::info!("📊 Fetching orders from {} exchanges", exchanges.len());
     
     // 2. Buscar orders via CCXT
-    match order_service::fetch_orders_from_exchanges(exchanges).await {
+    match order_service::fetch_orders_from_exchanges(exchanges, &status, range).await {
         Ok(response) => {
             log::info!("✅ Fetched {} orders", response.count);
             HttpResponse::Ok().json(response)
@@ -70,6 +111,31 @@ pub async fn fetch_orders_secure(
     }
 }
 
+/// 🔒 GET /api/v1/orders/open/all
+/// Ordens abertas de todas as exchanges ativas do usuário em uma lista só.
+/// Exchanges que falham entram em `errors` sem derrubar as demais.
+pub async fn fetch_all_open_orders(
+    user: web::ReqData<Claims>,
+    db: web::Data<MongoDB>,
+) -> impl Responder {
+    let user_id = &user.sub;
+
+    match order_service::fetch_all_open_orders(&db, user_id).await {
+        Ok(response) => {
+            log::info!("✅ {} open orders from {} exchanges ({} failed)",
+                response.count, response.exchanges_ok, response.errors.len());
+            HttpResponse::Ok().json(response)
+        }
+        Err(e) => {
+            log::error!("❌ Error fetching exchanges: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "error": format!("Error fetching exchanges: {}", e)
+            }))
+        }
+    }
+}
+
 // ============================================================================
 // ➕ CREATE ORDER - Criar nova ordem
 // ============================================================================
@@ -84,6 +150,10 @@ pub struct CreateOrderRequest {
     pub side: String,             // "buy" ou "sell"
     pub amount: f64,              // Quantidade
     pub price: Option<f64>,       // Preço (obrigatório para limit orders)
+    #[serde(default)]
+    pub time_in_force: Option<String>,   // "GTC", "IOC", "FOK" ou "GTD"
+    #[serde(default)]
+    pub expire_after_secs: Option<i64>,  // TTL em segundos (obrigatório para GTD)
 }
 
 pub async fn create_order_secure(
@@ -96,6 +166,28 @@ pub async fn create_order_secure(
     log::info!("🔒 Creating {} {} order for {} on exchange {}", 
         request.side, request.order_type, request.symbol, request.exchange_id);
     
+    // 0. Kill switch global (incidentes): recusa antes de qualquer chamada à exchange
+    if let Err(e) = crate::utils::trading_switch::TRADING_SWITCH.ensure_enabled() {
+        log::warn!("🛑 Order from user {} refused: trading disabled", user_id);
+        return HttpResponse::ServiceUnavailable().json(serde_json::json!({
+            "success": false,
+            "error": e
+        }));
+    }
+    
+    // 0.1 Validar time in force (GTD vira GTC + expiração server-side)
+    let (time_in_force, ttl_secs) = match order_service::resolve_time_in_force(
+        request.time_in_force.as_deref(), &request.order_type, request.expire_after_secs,
+    ) {
+        Ok(resolved) => resolved,
+        Err(e) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "success": false,
+                "error": e
+            }));
+        }
+    };
+    
     // 1. Buscar exchanges do MongoDB
     let exchanges = match crate::services::user_exchanges_service::get_user_exchanges_decrypted(&db, user_id).await {
         Ok(exs) => exs,
@@ -122,7 +214,31 @@ pub async fn create_order_secure(
     
     log::info!("✅ Found exchange {} ({})", exchange.name, exchange.ccxt_id);
     
-    // 3. Criar ordem via CCXT
+    // 3. Limite de segurança: notional máximo por ordem
+    if let Err(e) = order_service::enforce_max_order_notional(exchange, &request.symbol, &request.side, request.amount, request.price).await {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "success": false,
+            "error": e
+        }));
+    }
+    
+    // 4. Pré-validação contra regras do mercado (mínimos, precisão, saldo, status)
+    match order_service::validate_order(exchange, &request.symbol, &request.side, request.amount, request.price).await {
+        Ok(issues) if !issues.is_empty() => {
+            let summary = issues.iter().map(|i| i.to_string()).collect::<Vec<_>>().join("; ");
+            log::warn!("⚠️ Order for {} failed validation: {}", request.symbol, summary);
+            return HttpResponse::UnprocessableEntity().json(serde_json::json!({
+                "success": false,
+                "error": format!("Order failed validation: {}", summary),
+                "issues": issues
+            }));
+        }
+        Ok(_) => {}
+        // Sem como validar, a exchange continua sendo a última palavra
+        Err(e) => log::warn!("⚠️ Could not pre-validate order for {}: {}", request.symbol, e),
+    }
+    
+    // 5. Criar ordem via CCXT
     let create_request = CreateOrderWithCredsRequest {
         ccxt_id: exchange.ccxt_id.clone(),
         exchange_name: exchange.name.clone(),
@@ -134,12 +250,18 @@ pub async fn create_order_secure(
         side: request.side.clone(),
         amount: request.amount,
         price: request.price,
+        time_in_force,
     };
     
     match order_service::create_order_with_creds(&create_request).await {
         Ok(response) => {
             if response.success {
                 log::info!("✅ Order created successfully");
+                if let (Some(ttl), Some(order)) = (ttl_secs, response.order.as_ref()) {
+                    if let Err(e) = order_service::track_expiring_order(&db, user_id, &request.exchange_id, order, ttl).await {
+                        log::error!("❌ Order {} created but expiry tracking failed: {}", order.id, e);
+                    }
+                }
                 HttpResponse::Ok().json(response)
             } else {
                 log::warn!("⚠️ Order creation failed: {:?}", response.error);
@@ -235,3 +357,60 @@ pub async fn cancel_order_secure(
         }
     }
 }
+
+// ============================================================================
+// 🧹 CANCEL ALL - Cancelar todas as ordens abertas
+// ============================================================================
+
+#[derive(Debug, Deserialize)]
+pub struct CancelAllRequest {
+    pub exchange_id: String,       // MongoDB ID da exchange
+    pub symbol: Option<String>,    // Sem símbolo: todas as ordens abertas
+}
+
+/// 🔒 POST /api/v1/orders/cancel-all
+pub async fn cancel_all_orders_secure(
+    user: web::ReqData<Claims>,
+    db: web::Data<MongoDB>,
+    request: web::Json<CancelAllRequest>,
+) -> impl Responder {
+    let user_id = &user.sub;
+    
+    log::info!("🔒 Canceling all orders ({:?}) on exchange {}", request.symbol, request.exchange_id);
+    
+    let exchanges = match crate::services::user_exchanges_service::get_user_exchanges_decrypted(&db, user_id).await {
+        Ok(exs) => exs,
+        Err(e) => {
+            log::error!("❌ Error fetching exchanges: {}", e);
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "error": format!("Error fetching exchanges: {}", e)
+            }));
+        }
+    };
+    
+    let exchange = match exchanges.iter().find(|ex| ex.exchange_id == request.exchange_id) {
+        Some(ex) => ex,
+        None => {
+            log::error!("❌ Exchange not found: {}", request.exchange_id);
+            return HttpResponse::NotFound().json(serde_json::json!({
+                "success": false,
+                "error": format!("Exchange not found: {}", request.exchange_id)
+            }));
+        }
+    };
+    
+    match order_service::cancel_all_orders_for_exchange(&db, user_id, exchange, request.symbol.clone()).await {
+        Ok(response) => {
+            log::info!("✅ Canceled {} orders on {} ({} errors)", response.canceled_count, exchange.name, response.errors.len());
+            HttpResponse::Ok().json(response)
+        }
+        Err(e) => {
+            log::error!("❌ Error canceling all orders: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "error": e
+            }))
+        }
+    }
+}
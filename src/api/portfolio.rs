@@ -0,0 +1,28 @@
+use actix_web::{web, HttpResponse};
+use crate::database::MongoDB;
+use crate::middleware::auth::Claims;
+use crate::services::strategy_service;
+
+// GET /api/v1/portfolio/asset/{symbol}/position - Posição consolidada do ativo (todas as exchanges)
+pub async fn get_asset_position(
+    user: web::ReqData<Claims>,
+    db: web::Data<MongoDB>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let symbol = path.into_inner();
+    log::info!("📦 GET /portfolio/asset/{}/position - user {}", symbol, user.sub);
+
+    match strategy_service::asset_position(&db, &user.sub, &symbol).await {
+        Ok(position) => HttpResponse::Ok().json(serde_json::json!({
+            "success": true,
+            "position": position
+        })),
+        Err(e) => {
+            log::error!("❌ Error building asset position: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "error": e
+            }))
+        }
+    }
+}
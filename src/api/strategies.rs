@@ -2,11 +2,11 @@ use actix_web::{delete, get, post, put, web, HttpResponse, Responder};
 use mongodb::bson::doc;
 use crate::database::MongoDB;
 use crate::models::{
-    UserStrategies, StrategyItem, CreateStrategyRequest, UpdateStrategyRequest,
-    StrategyResponse, StrategyListItem, StrategyStatus, GradualLot, StrategySignal,
+    UserStrategies, StrategyItem, CreateStrategyRequest, ImportStrategyRequest, UpdateStrategyRequest,
+    StrategyResponse, StrategyStatus, StrategyMode, StrategyConfig, GradualLot, StrategySignal,
 };
 use crate::middleware::auth::Claims;
-use crate::services::strategy_service;
+use crate::services::{strategy_history_service, strategy_service};
 
 const COLLECTION: &str = "user_strategy";
 
@@ -33,12 +33,26 @@ async fn get_or_create_user_doc(db: &MongoDB, user_id: &str) -> Result<UserStrat
     }
 }
 
+#[derive(Debug, serde::Deserialize)]
+pub struct ListQuery {
+    /// Inclui as estratégias movidas para `strategies_archive`
+    #[serde(default)]
+    pub include_archived: bool,
+}
+
 #[get("")]
-pub async fn get_strategies(user: web::ReqData<Claims>, db: web::Data<MongoDB>) -> impl Responder {
+pub async fn get_strategies(user: web::ReqData<Claims>, query: web::Query<ListQuery>, db: web::Data<MongoDB>) -> impl Responder {
     match get_or_create_user_doc(&db, &user.sub).await {
         Ok(user_doc) => {
-            let mut list: Vec<StrategyListItem> = user_doc.strategies.into_iter().map(StrategyListItem::from).collect();
-            list.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+            let archived = if query.include_archived {
+                match strategy_service::archived_strategies(&db, &user.sub).await {
+                    Ok(archived) => archived,
+                    Err(e) => return HttpResponse::InternalServerError().json(serde_json::json!({ "success": false, "error": e })),
+                }
+            } else {
+                vec![]
+            };
+            let list = strategy_service::strategy_list(user_doc.strategies, archived);
             let total = list.len();
             HttpResponse::Ok().json(serde_json::json!({ "success": true, "strategies": list, "total": total }))
         }
@@ -46,6 +60,50 @@ pub async fn get_strategies(user: web::ReqData<Claims>, db: web::Data<MongoDB>)
     }
 }
 
+#[derive(Debug, serde::Deserialize)]
+pub struct EventsQuery {
+    pub ticket: Option<String>,
+    pub last_event_id: Option<u64>,
+}
+
+/// POST /api/v1/strategies/events/ticket - Ticket de uso único (60s) para abrir o stream SSE
+pub async fn create_events_ticket(user: web::ReqData<Claims>) -> HttpResponse {
+    let ticket = crate::services::strategy_events::STREAM_TICKETS.issue(&user.sub);
+    HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "ticket": ticket,
+        "expires_in": crate::services::strategy_events::STREAM_TICKET_TTL.as_secs(),
+    }))
+}
+
+/// SSE: stream de execuções do usuário. Auth via `?ticket=` obtido em
+/// `POST /strategies/events/ticket` (EventSource não envia headers; o JWT não vai na URL).
+/// Reconexão: header `Last-Event-ID` (ou `?last_event_id=`) com um ticket novo.
+pub async fn strategy_events(req: actix_web::HttpRequest, query: web::Query<EventsQuery>) -> HttpResponse {
+    let user_id = match query.ticket.as_deref().and_then(|t| crate::services::strategy_events::STREAM_TICKETS.redeem(t)) {
+        Some(user_id) => user_id,
+        None => {
+            return HttpResponse::Unauthorized().json(serde_json::json!({
+                "success": false, "error": "Invalid, expired or missing ticket"
+            }));
+        }
+    };
+
+    let last_event_id = req.headers().get("Last-Event-ID")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .or(query.last_event_id);
+
+    log::info!("📡 SSE connected: user {} (last_event_id: {:?})", user_id, last_event_id);
+
+    let stream = crate::services::strategy_events::event_stream(
+        &crate::services::strategy_events::STRATEGY_EVENTS, user_id, last_event_id,
+    );
+
+    crate::middleware::compression::event_stream_response()
+        .streaming(stream)
+}
+
 #[get("/{id}")]
 pub async fn get_strategy(user: web::ReqData<Claims>, path: web::Path<String>, db: web::Data<MongoDB>) -> impl Responder {
     let sid = path.into_inner();
@@ -70,6 +128,66 @@ pub async fn get_strategy_stats(user: web::ReqData<Claims>, path: web::Path<Stri
     }
 }
 
+/// PNL realizado + não realizado de todas as estratégias do usuário, arquivadas inclusive
+#[get("/pnl-summary")]
+pub async fn get_pnl_summary(user: web::ReqData<Claims>, db: web::Data<MongoDB>) -> impl Responder {
+    let loaded = async {
+        let ud = get_or_create_user_doc(&db, &user.sub).await?;
+        let archived = strategy_service::archived_strategies(&db, &user.sub).await?;
+        Ok::<_, String>(strategy_service::with_archived(ud.strategies, archived))
+    };
+    match loaded.await {
+        Ok(strategies) => {
+            let summary = strategy_service::pnl_summary(&db, &user.sub, &strategies).await;
+            HttpResponse::Ok().json(serde_json::json!({ "success": true, "pnl": summary }))
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({ "success": false, "error": e })),
+    }
+}
+
+/// Contagem por status e totais ativos (via agregação, sem carregar as estratégias)
+#[get("/summary")]
+pub async fn get_strategies_summary(user: web::ReqData<Claims>, db: web::Data<MongoDB>) -> impl Responder {
+    match strategy_service::strategy_count_summary(&db, &user.sub).await {
+        Ok(summary) => HttpResponse::Ok().json(serde_json::json!({ "success": true, "summary": summary })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({ "success": false, "error": e })),
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct WhatIfQuery {
+    pub price: f64,
+}
+
+/// Simula a posição aberta num preço hipotético (sem efeitos colaterais)
+#[get("/{id}/whatif")]
+pub async fn get_strategy_what_if(user: web::ReqData<Claims>, path: web::Path<String>, query: web::Query<WhatIfQuery>, db: web::Data<MongoDB>) -> impl Responder {
+    let sid = path.into_inner();
+    match get_or_create_user_doc(&db, &user.sub).await {
+        Ok(ud) => match ud.strategies.into_iter().find(|s| s.strategy_id == sid) {
+            Some(s) => match strategy_service::what_if(&s, query.price) {
+                Ok(result) => HttpResponse::Ok().json(serde_json::json!({ "success": true, "whatif": result })),
+                Err(e) => HttpResponse::BadRequest().json(serde_json::json!({ "success": false, "error": e })),
+            },
+            None => HttpResponse::NotFound().json(serde_json::json!({ "success": false, "error": "Strategy not found" })),
+        },
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({ "success": false, "error": e })),
+    }
+}
+
+/// Reavalia os sinais registrados com a configuração atual (sem efeitos colaterais)
+#[get("/{id}/replay")]
+pub async fn get_strategy_replay(user: web::ReqData<Claims>, path: web::Path<String>, db: web::Data<MongoDB>) -> impl Responder {
+    let sid = path.into_inner();
+    match get_or_create_user_doc(&db, &user.sub).await {
+        Ok(ud) => match ud.strategies.into_iter().find(|s| s.strategy_id == sid) {
+            Some(s) => HttpResponse::Ok().json(serde_json::json!({ "success": true, "replay": strategy_service::replay_signals(&s) })),
+            None => HttpResponse::NotFound().json(serde_json::json!({ "success": false, "error": "Strategy not found" })),
+        },
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({ "success": false, "error": e })),
+    }
+}
+
 #[derive(Debug, serde::Deserialize)]
 pub struct PaginationQuery {
     pub limit: Option<i64>,
@@ -112,82 +230,80 @@ pub async fn get_strategy_signals(user: web::ReqData<Claims>, path: web::Path<St
     }
 }
 
+#[get("/{id}/history")]
+pub async fn get_strategy_history(user: web::ReqData<Claims>, path: web::Path<String>, query: web::Query<PaginationQuery>, db: web::Data<MongoDB>) -> impl Responder {
+    let sid = path.into_inner();
+    let limit = query.limit.unwrap_or(50).clamp(1, 100);
+    match strategy_history_service::get_config_history(&db, &user.sub, &sid, limit).await {
+        Ok(history) => HttpResponse::Ok().json(serde_json::json!({ "success": true, "history": history, "count": history.len() })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({ "success": false, "error": e })),
+    }
+}
+
 #[post("")]
 pub async fn create_strategy(user: web::ReqData<Claims>, body: web::Json<CreateStrategyRequest>, db: web::Data<MongoDB>) -> impl Responder {
-    let user_id = &user.sub;
-    log::info!("📝 POST /strategies - user: {}, name: '{}', symbol: '{}'", user_id, body.name, body.symbol);
+    log::info!("📝 POST /strategies - user: {}, name: '{}', symbol: '{}'", user.sub, body.name, body.symbol);
+    insert_strategy(&db, &user.sub, body.into_inner()).await
+}
 
-    // ── Input Validation ────────────────────────────────────────────
-    if body.name.trim().is_empty() {
-        return HttpResponse::BadRequest().json(serde_json::json!({
-            "success": false, "error": "Strategy name is required",
-            "field": "name"
-        }));
-    }
-    if body.name.len() > 100 {
-        return HttpResponse::BadRequest().json(serde_json::json!({
-            "success": false, "error": "Strategy name must be at most 100 characters",
-            "field": "name"
-        }));
+/// Validação comum de create/import/update: campos, invariantes da config, futuros e
+/// tipos de ordem no modo da estratégia (`config.mode`) contra o `has` da exchange
+async fn validate_strategy(
+    db: &MongoDB, name: &str, symbol: &str, exchange_id: &str, exchange_name: &str, config: &StrategyConfig,
+) -> Result<(), HttpResponse> {
+    fn invalid(field: &str, error: impl Into<String>) -> HttpResponse {
+        HttpResponse::BadRequest().json(serde_json::json!({
+            "success": false, "error": error.into(),
+            "field": field
+        }))
     }
-    if body.symbol.trim().is_empty() || !body.symbol.contains('/') {
-        return HttpResponse::BadRequest().json(serde_json::json!({
-            "success": false, "error": "Symbol must be a valid trading pair (e.g. BTC/USDT)",
-            "field": "symbol"
-        }));
+
+    // ── Input Validation ────────────────────────────────────────────
+    if name.trim().is_empty() {
+        return Err(invalid("name", "Strategy name is required"));
     }
-    if body.exchange_id.trim().is_empty() {
-        return HttpResponse::BadRequest().json(serde_json::json!({
-            "success": false, "error": "Exchange ID is required",
-            "field": "exchange_id"
-        }));
+    if name.len() > 100 {
+        return Err(invalid("name", "Strategy name must be at most 100 characters"));
     }
-    if body.config.base_price <= 0.0 {
-        return HttpResponse::BadRequest().json(serde_json::json!({
-            "success": false, "error": "Base price must be greater than 0",
-            "field": "config.base_price"
-        }));
+    if symbol.trim().is_empty() || !symbol.contains('/') {
+        return Err(invalid("symbol", "Symbol must be a valid trading pair (e.g. BTC/USDT)"));
     }
-    if body.config.take_profit_percent <= 0.0 || body.config.take_profit_percent > 1000.0 {
-        return HttpResponse::BadRequest().json(serde_json::json!({
-            "success": false, "error": "Take profit must be between 0.01% and 1000%",
-            "field": "config.take_profit_percent"
-        }));
+    if exchange_id.trim().is_empty() {
+        return Err(invalid("exchange_id", "Exchange ID is required"));
     }
-    if body.config.stop_loss_percent <= 0.0 || body.config.stop_loss_percent > 100.0 {
-        return HttpResponse::BadRequest().json(serde_json::json!({
-            "success": false, "error": "Stop loss must be between 0.01% and 100%",
-            "field": "config.stop_loss_percent"
-        }));
+    if let Err((field, e)) = config.validate() {
+        return Err(invalid(field, e));
     }
-    if body.config.fee_percent < 0.0 || body.config.fee_percent > 50.0 {
-        return HttpResponse::BadRequest().json(serde_json::json!({
-            "success": false, "error": "Fee must be between 0% and 50%",
-            "field": "config.fee_percent"
-        }));
+    if let Err(e) = config.validate_futures(symbol) {
+        return Err(invalid("config.mode", e));
     }
-    if body.config.gradual_sell && (body.config.gradual_take_percent <= 0.0 || body.config.gradual_take_percent > 100.0) {
-        return HttpResponse::BadRequest().json(serde_json::json!({
-            "success": false, "error": "Gradual take percent must be between 0.01% and 100% when gradual sell is enabled",
-            "field": "config.gradual_take_percent"
-        }));
+
+    let capabilities = match crate::services::exchange_service::get_exchange_capabilities(db, exchange_id).await {
+        Ok(Some(capabilities)) => capabilities,
+        Ok(None) => return Err(invalid("exchange_id", "Exchange not found")),
+        Err(e) => {
+            log::error!("❌ Failed to load capabilities for exchange {}: {}", exchange_id, e);
+            return Err(invalid("exchange_id", "Could not verify the capabilities of this exchange"));
+        }
+    };
+    if config.mode == StrategyMode::Futures && !capabilities.supports_futures {
+        return Err(invalid("config.mode", format!("{} does not support futures trading", exchange_name)));
     }
-    if body.config.time_execution_min < 1 || body.config.time_execution_min > 43200 {
-        return HttpResponse::BadRequest().json(serde_json::json!({
-            "success": false, "error": "Execution time must be between 1 minute and 30 days (43200 min)",
-            "field": "config.time_execution_min"
-        }));
+    if let Err(e) = strategy_service::validate_config_order_types(config, &capabilities) {
+        return Err(invalid("config.mode", e));
     }
-    if body.config.timer_gradual_min < 1 || body.config.timer_gradual_min > 1440 {
-        return HttpResponse::BadRequest().json(serde_json::json!({
-            "success": false, "error": "Gradual timer must be between 1 minute and 24 hours (1440 min)",
-            "field": "config.timer_gradual_min"
-        }));
+    Ok(())
+}
+
+/// Valida e cria a estratégia para o usuário (usado por create e import)
+async fn insert_strategy(db: &MongoDB, user_id: &str, body: CreateStrategyRequest) -> HttpResponse {
+    if let Err(response) = validate_strategy(db, &body.name, &body.symbol, &body.exchange_id, &body.exchange_name, &body.config).await {
+        return response;
     }
 
     // ── Limit check: max 20 strategies per user ─────────────────────
     let collection = db.collection::<UserStrategies>(COLLECTION);
-    match get_or_create_user_doc(&db, user_id).await {
+    match get_or_create_user_doc(db, user_id).await {
         Ok(ud) => {
             let active_count = ud.strategies.iter().filter(|s| s.is_active).count();
             if active_count >= 20 {
@@ -196,6 +312,14 @@ pub async fn create_strategy(user: web::ReqData<Claims>, body: web::Json<CreateS
                     "limit": 20, "current": active_count
                 }));
             }
+            if let Some(parent_id) = body.parent_strategy_id.as_deref() {
+                if let Err(e) = strategy_service::validate_parent(&ud.strategies, parent_id, &body.exchange_id, &body.symbol) {
+                    return HttpResponse::BadRequest().json(serde_json::json!({
+                        "success": false, "error": e,
+                        "field": "parent_strategy_id"
+                    }));
+                }
+            }
         }
         Err(e) => {
             log::error!("❌ Failed to check strategy limit for user {}: {}", user_id, e);
@@ -216,20 +340,29 @@ pub async fn create_strategy(user: web::ReqData<Claims>, body: web::Json<CreateS
             GradualLot { lot_number: 4, sell_percent: 25.0, executed: false, executed_at: None, executed_price: None, realized_pnl: None },
         ];
     }
+    let settings = crate::services::user_settings_service::get_user_settings(db, user_id).await
+        .map_err(|e| log::warn!("⚠️ Failed to load settings for user {}, creating strategy in paper mode: {}", user_id, e))
+        .ok();
+    let awaiting_live_confirmation = strategy_service::new_strategy_paper_mode(
+        body.require_confirmation, settings.as_ref(), strategy_service::require_confirmation_default(),
+    );
+    if awaiting_live_confirmation {
+        log::info!("🛡️ Strategy {} created awaiting live confirmation", strategy_id);
+    }
     let new_strategy = StrategyItem {
         strategy_id: strategy_id.clone(), name: body.name.clone(), symbol: body.symbol.clone(),
         exchange_id: body.exchange_id.clone(), exchange_name: body.exchange_name.clone(),
         is_active: true, status: StrategyStatus::Monitoring, config,
-        position: None, executions: vec![], signals: vec![],
-        last_checked_at: None, last_price: None, last_gradual_sell_at: None,
-        error_message: None, total_pnl_usd: 0.0, total_executions: 0,
+        position: None, open_orders: vec![], grid_state: None, executions: vec![], signals: vec![],
+        last_checked_at: None, last_price: None, last_gradual_sell_at: None, last_notified_at: Default::default(),
+        error_message: None, alert_only: false, awaiting_live_confirmation, parent_strategy_id: body.parent_strategy_id.clone(), total_pnl_usd: 0.0, total_executions: 0, pending_buy_usd: 0.0, pending_buy_window: false, consecutive_losses: 0, exchange_pause: None,
         started_at: now, created_at: now, updated_at: now,
     };
     let bson = match mongodb::bson::to_bson(&new_strategy) {
         Ok(b) => b,
         Err(e) => return HttpResponse::InternalServerError().json(serde_json::json!({ "success": false, "error": format!("Serialize: {}", e) })),
     };
-    let _ = get_or_create_user_doc(&db, user_id).await;
+    let _ = get_or_create_user_doc(db, user_id).await;
     match collection.update_one(doc! { "user_id": user_id }, doc! { "$push": { "strategies": bson }, "$set": { "updated_at": now } }).await {
         Ok(r) if r.modified_count > 0 => HttpResponse::Created().json(serde_json::json!({ "success": true, "strategy": StrategyResponse::from(new_strategy) })),
         Ok(_) => HttpResponse::InternalServerError().json(serde_json::json!({ "success": false, "error": "Failed to add strategy" })),
@@ -237,19 +370,78 @@ pub async fn create_strategy(user: web::ReqData<Claims>, body: web::Json<CreateS
     }
 }
 
+/// Exporta a configuração portável da estratégia (sem ids nem estado)
+#[get("/{id}/export")]
+pub async fn export_strategy(user: web::ReqData<Claims>, path: web::Path<String>, db: web::Data<MongoDB>) -> impl Responder {
+    let sid = path.into_inner();
+    match get_or_create_user_doc(&db, &user.sub).await {
+        Ok(ud) => match ud.strategies.iter().find(|s| s.strategy_id == sid) {
+            Some(s) => HttpResponse::Ok().json(serde_json::json!({ "success": true, "strategy": strategy_service::export_strategy(s) })),
+            None => HttpResponse::NotFound().json(serde_json::json!({ "success": false, "error": "Strategy not found" })),
+        },
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({ "success": false, "error": e })),
+    }
+}
+
+/// Cria uma estratégia nova a partir de um export, no símbolo/exchange informados
+#[post("/import")]
+pub async fn import_strategy(user: web::ReqData<Claims>, body: web::Json<ImportStrategyRequest>, db: web::Data<MongoDB>) -> impl Responder {
+    log::info!("📥 POST /strategies/import - user: {}, symbol: '{}'", user.sub, body.symbol);
+    match strategy_service::import_request(body.into_inner()) {
+        Ok(request) => insert_strategy(&db, &user.sub, request).await,
+        Err(e) => HttpResponse::BadRequest().json(serde_json::json!({ "success": false, "error": e, "field": "strategy.version" })),
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct ArchiveQuery {
+    /// Padrão: `STRATEGY_ARCHIVE_AFTER_DAYS`
+    #[serde(default)]
+    pub older_than_days: Option<i64>,
+}
+
+/// Move as estratégias concluídas há mais de N dias para o arquivo
+#[post("/archive-completed")]
+pub async fn archive_completed_strategies(user: web::ReqData<Claims>, query: web::Query<ArchiveQuery>, db: web::Data<MongoDB>) -> impl Responder {
+    let days = query.older_than_days.unwrap_or_else(crate::jobs::strategy_archive::archive_after_days);
+    if days < 0 {
+        return HttpResponse::BadRequest().json(serde_json::json!({ "success": false, "error": "older_than_days must be >= 0", "field": "older_than_days" }));
+    }
+    match strategy_service::archive_completed_strategies(&db, Some(&user.sub), days).await {
+        Ok(r) => HttpResponse::Ok().json(serde_json::json!({ "success": true, "archived": r.archived, "older_than_days": days })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({ "success": false, "error": e })),
+    }
+}
+
 #[put("/{id}")]
 pub async fn update_strategy(user: web::ReqData<Claims>, path: web::Path<String>, body: web::Json<UpdateStrategyRequest>, db: web::Data<MongoDB>) -> impl Responder {
     let user_id = &user.sub;
     let sid = path.into_inner();
     let collection = db.collection::<UserStrategies>(COLLECTION);
-    match get_or_create_user_doc(&db, user_id).await {
-        Ok(ud) => {
-            if !ud.strategies.iter().any(|s| s.strategy_id == sid) {
-                return HttpResponse::NotFound().json(serde_json::json!({ "success": false, "error": "Strategy not found" }));
-            }
-        }
+    let current = match get_or_create_user_doc(&db, user_id).await {
+        Ok(ud) => match ud.strategies.into_iter().find(|s| s.strategy_id == sid) {
+            Some(s) => s,
+            None => return HttpResponse::NotFound().json(serde_json::json!({ "success": false, "error": "Strategy not found" })),
+        },
         Err(e) => return HttpResponse::InternalServerError().json(serde_json::json!({ "success": false, "error": e })),
+    };
+    // Mesmas regras do create sobre o resultado da edição (campos omitidos mantêm o valor atual)
+    let edits_strategy = body.name.is_some() || body.symbol.is_some() || body.exchange_id.is_some()
+        || body.exchange_name.is_some() || body.config.is_some();
+    if edits_strategy {
+        let validation = validate_strategy(
+            &db,
+            body.name.as_deref().unwrap_or(&current.name),
+            body.symbol.as_deref().unwrap_or(&current.symbol),
+            body.exchange_id.as_deref().unwrap_or(&current.exchange_id),
+            body.exchange_name.as_deref().unwrap_or(&current.exchange_name),
+            body.config.as_ref().unwrap_or(&current.config),
+        ).await;
+        if let Err(response) = validation {
+            return response;
+        }
     }
+    let previous_config = current.config;
     let now = chrono::Utc::now().timestamp();
     let p = "strategies.$[elem]";
     let mut udoc = doc! { format!("{}.updated_at", p): now, "updated_at": now };
@@ -264,13 +456,22 @@ pub async fn update_strategy(user: web::ReqData<Claims>, path: web::Path<String>
     if let Some(cfg) = &body.config { udoc.insert(format!("{}.config", p), mongodb::bson::to_bson(cfg).unwrap()); }
     let af = doc! { "elem.strategy_id": &sid };
     match collection.update_one(doc! { "user_id": user_id }, doc! { "$set": udoc }).array_filters(vec![af]).await {
-        Ok(_) => match get_or_create_user_doc(&db, user_id).await {
-            Ok(ud) => match ud.strategies.into_iter().find(|s| s.strategy_id == sid) {
-                Some(s) => HttpResponse::Ok().json(serde_json::json!({ "success": true, "strategy": StrategyResponse::from(s) })),
+        Ok(_) => {
+            if let Some(entry) = body.config.as_ref()
+                .and_then(|cfg| strategy_history_service::build_history_entry(user_id, &sid, &previous_config, cfg, now))
+            {
+                if let Err(e) = strategy_history_service::record_config_change(&db, &entry).await {
+                    log::warn!("⚠️ Strategy {} config history not recorded: {}", sid, e);
+                }
+            }
+            match get_or_create_user_doc(&db, user_id).await {
+                Ok(ud) => match ud.strategies.into_iter().find(|s| s.strategy_id == sid) {
+                    Some(s) => HttpResponse::Ok().json(serde_json::json!({ "success": true, "strategy": StrategyResponse::from(s) })),
+                    _ => HttpResponse::Ok().json(serde_json::json!({ "success": true, "message": "Updated" })),
+                },
                 _ => HttpResponse::Ok().json(serde_json::json!({ "success": true, "message": "Updated" })),
-            },
-            _ => HttpResponse::Ok().json(serde_json::json!({ "success": true, "message": "Updated" })),
-        },
+            }
+        }
         Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({ "success": false, "error": format!("Update failed: {}", e) })),
     }
 }
@@ -279,6 +480,22 @@ pub async fn update_strategy(user: web::ReqData<Claims>, path: web::Path<String>
 pub async fn delete_strategy(user: web::ReqData<Claims>, path: web::Path<String>, db: web::Data<MongoDB>) -> impl Responder {
     let sid = path.into_inner();
     let collection = db.collection::<UserStrategies>(COLLECTION);
+
+    // ── Cancel live exchange orders before deleting ─────────────────
+    if let Ok(ud) = get_or_create_user_doc(&db, &user.sub).await {
+        if let Some(strategy) = ud.strategies.iter().find(|s| s.strategy_id == sid) {
+            let cancelled = strategy_service::cancel_strategy_open_orders(&db, &user.sub, strategy, "cancel_on_delete").await;
+            if !cancelled.errors.is_empty() {
+                log::warn!("⚠️ Delete blocked for strategy {}: {:?}", sid, cancelled.errors);
+                return HttpResponse::Conflict().json(serde_json::json!({
+                    "success": false,
+                    "error": "Strategy has open orders that could not be cancelled. Cancel them on the exchange or try again.",
+                    "details": cancelled.errors,
+                }));
+            }
+        }
+    }
+
     let now = chrono::Utc::now().timestamp();
     match collection.update_one(doc! { "user_id": &user.sub }, doc! { "$pull": { "strategies": { "strategy_id": &sid } }, "$set": { "updated_at": now } }).await {
         Ok(r) if r.modified_count > 0 => HttpResponse::Ok().json(serde_json::json!({ "success": true, "message": "Deleted" })),
@@ -287,9 +504,25 @@ pub async fn delete_strategy(user: web::ReqData<Claims>, path: web::Path<String>
     }
 }
 
+#[derive(Debug, serde::Deserialize)]
+pub struct ActivateQuery {
+    /// Reativa uma estratégia em erro, limpando a mensagem de erro
+    #[serde(default)]
+    pub force: bool,
+}
+
 #[post("/{id}/activate")]
-pub async fn activate_strategy(user: web::ReqData<Claims>, path: web::Path<String>, db: web::Data<MongoDB>) -> impl Responder {
-    match strategy_service::activate_strategy(&db, &path.into_inner(), &user.sub).await {
+pub async fn activate_strategy(user: web::ReqData<Claims>, path: web::Path<String>, query: web::Query<ActivateQuery>, db: web::Data<MongoDB>) -> impl Responder {
+    match strategy_service::activate_strategy(&db, &path.into_inner(), &user.sub, query.force).await {
+        Ok(s) => HttpResponse::Ok().json(serde_json::json!({ "success": true, "strategy": StrategyResponse::from(s) })),
+        Err(e) => HttpResponse::BadRequest().json(serde_json::json!({ "success": false, "error": e })),
+    }
+}
+
+/// Libera as ordens reais de uma estratégia criada com `require_confirmation`
+#[post("/{id}/confirm-live")]
+pub async fn confirm_live_strategy(user: web::ReqData<Claims>, path: web::Path<String>, db: web::Data<MongoDB>) -> impl Responder {
+    match strategy_service::confirm_live_strategy(&db, &path.into_inner(), &user.sub).await {
         Ok(s) => HttpResponse::Ok().json(serde_json::json!({ "success": true, "strategy": StrategyResponse::from(s) })),
         Err(e) => HttpResponse::BadRequest().json(serde_json::json!({ "success": false, "error": e })),
     }
@@ -387,7 +620,7 @@ pub async fn tick_strategy(user: web::ReqData<Claims>, path: web::Path<String>,
             "error": tr.error,
             "summary": summary,
             "signals": tr.signals,
-            "executions": tr.executions,
+            "executions": tr.persisted_executions(),
             "acted_count": acted_signals.len(),
             "info_count": info_signals.len(),
         }
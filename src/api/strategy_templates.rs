@@ -123,6 +123,8 @@ pub async fn create_template(
         summary: body.summary.clone(),
         configs: body.configs.clone(),
         how_it_works: body.how_it_works.clone(),
+        mode: body.mode,
+        leverage: body.leverage,
         is_default: false,
         created_at: now,
         updated_at: now,
@@ -18,6 +18,7 @@ use utoipa::openapi::security::{SecurityScheme, HttpAuthScheme, HttpBuilder};
         crate::api::auth::register,
         crate::api::auth::verify_token,
         crate::api::auth::get_me,
+        crate::api::auth::export_data,
         
         // Health & Metrics
         crate::api::health::health_check,
@@ -25,6 +26,7 @@ use utoipa::openapi::security::{SecurityScheme, HttpAuthScheme, HttpBuilder};
         
         // Exchanges
         crate::api::exchanges::get_available_exchanges,
+        crate::api::exchanges::get_exchange_timeframes,
         
         // Tokens
         crate::api::tokens::get_tokens,
@@ -51,6 +53,7 @@ use utoipa::openapi::security::{SecurityScheme, HttpAuthScheme, HttpBuilder};
             // Exchanges
             crate::services::exchange_service::AvailableExchangesResponse,
             crate::services::exchange_service::ExchangeCatalogInfo,
+            crate::services::exchange_service::ExchangeTimeframesResponse,
         )
     ),
     tags(
@@ -1,8 +1,8 @@
-use actix_web::{web, HttpResponse, Responder};
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
 use serde::Deserialize;
 use crate::{
     database::MongoDB,
-    services::ticker_service,
+    services::{auth_service, ticker_service, user_settings_service},
 };
 
 #[derive(Debug, Deserialize)]
@@ -37,3 +37,74 @@ pub async fn get_tickers(
         }
     }
 }
+
+#[derive(Debug, Deserialize)]
+pub struct WatchQuery {
+    /// true → resposta SSE com atualizações a cada `interval_secs`
+    #[serde(default)]
+    pub stream: bool,
+    pub interval_secs: Option<u64>,
+}
+
+// POST /api/v1/tickers/watch - Preços de uma watchlist numa única chamada (credenciais no body)
+// POST /api/v1/tickers/watch?stream=true&interval_secs=5 - mesma watchlist via SSE
+// Sem `symbols` no body usa a watchlist salva do usuário (JWT no header)
+pub async fn watch_tickers(
+    req: HttpRequest,
+    query: web::Query<WatchQuery>,
+    body: web::Json<ticker_service::WatchlistRequest>,
+    db: web::Data<MongoDB>,
+) -> HttpResponse {
+    let mut request = body.into_inner();
+    if request.symbols.is_empty() {
+        request.symbols = saved_watchlist(&req, &db, &request.exchange.ccxt_id).await;
+    }
+    let symbols = match ticker_service::normalize_watchlist(&request.symbols) {
+        Ok(symbols) => symbols,
+        Err(e) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "success": false,
+                "error": e
+            }));
+        }
+    };
+
+    log::info!("👀 POST /tickers/watch - {} symbols on {} (stream: {})",
+        symbols.len(), request.exchange.name, query.stream);
+
+    if query.stream {
+        let interval = ticker_service::watch_interval(query.interval_secs);
+        return crate::middleware::compression::event_stream_response()
+            .streaming(ticker_service::watchlist_stream(request.exchange, symbols, interval));
+    }
+
+    match ticker_service::get_watchlist(request.exchange, symbols).await {
+        Ok(response) => HttpResponse::Ok().json(response),
+        Err(e) => {
+            log::error!("❌ Error fetching watchlist: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "error": e
+            }))
+        }
+    }
+}
+
+/// Símbolos da watchlist salva do usuário autenticado para a exchange (vazio sem token válido)
+async fn saved_watchlist(req: &HttpRequest, db: &MongoDB, ccxt_id: &str) -> Vec<String> {
+    let Some(token) = req.headers().get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+    else {
+        return vec![];
+    };
+    let Ok(claims) = auth_service::verify_token(token) else { return vec![] };
+
+    match user_settings_service::get_user_settings(db, &claims.sub).await {
+        Ok(settings) => user_settings_service::watchlist_symbols_for(&settings.watchlist, ccxt_id),
+        Err(e) => {
+            log::warn!("⚠️ Failed to load saved watchlist: {}", e);
+            vec![]
+        }
+    }
+}
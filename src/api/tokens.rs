@@ -258,7 +258,104 @@ pub async fn get_token_details_with_creds(
             HttpResponse::Ok().json(response)
         }
         Err(e) => {
-            log::error!("❌ Failed to get token details: {}", e);
+            let message = e.to_string();
+            match e {
+                token_service::TokenDetailsError::SymbolNotAvailable { suggestions, .. } => {
+                    log::warn!("⚠️ {}", message);
+                    HttpResponse::NotFound().json(serde_json::json!({
+                        "success": false,
+                        "error": message,
+                        "suggestions": suggestions
+                    }))
+                }
+                token_service::TokenDetailsError::Other(_) => {
+                    log::error!("❌ Failed to get token details: {}", message);
+                    HttpResponse::InternalServerError().json(serde_json::json!({
+                        "success": false,
+                        "error": message
+                    }))
+                }
+            }
+        }
+    }
+}
+
+// POST /tokens/details/batch - Detalhes de vários símbolos numa exchange (um fetch de tickers)
+pub async fn get_token_details_batch(
+    body: web::Json<token_service::BatchTokenDetailsRequest>,
+) -> HttpResponse {
+    log::info!("🪙 POST /tokens/details/batch - {} symbols, exchange: {}",
+        body.symbols.len(), body.exchange.name);
+
+    match token_service::get_token_details_batch(&body).await {
+        Ok(response) => {
+            log::info!("✅ Token details batch: {} symbols ({} failed)", response.count, response.failed);
+            HttpResponse::Ok().json(response)
+        }
+        Err(e) if e.starts_with("At least") || e.starts_with("Too many") => {
+            HttpResponse::BadRequest().json(serde_json::json!({
+                "success": false,
+                "error": e
+            }))
+        }
+        Err(e) => {
+            log::error!("❌ Token details batch failed: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "error": e
+            }))
+        }
+    }
+}
+
+// ============================================================================
+// FUNDING RATES - ZERO DATABASE PATTERN
+// ============================================================================
+// POST /tokens/funding - Funding rate atual + histórico de um perpétuo
+pub async fn get_funding_rates(
+    body: web::Json<token_service::FundingRateRequest>,
+) -> HttpResponse {
+    log::info!("💸 POST /tokens/funding - symbol: {}, exchange: {}",
+        body.symbol, body.exchange.name);
+
+    match token_service::get_funding_rates(&body).await {
+        Ok(response) => HttpResponse::Ok().json(response),
+        Err(e) if token_service::is_not_supported(&e) => {
+            HttpResponse::BadRequest().json(serde_json::json!({
+                "success": false,
+                "error": e
+            }))
+        }
+        Err(e) => {
+            log::error!("❌ Failed to get funding rates: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "error": e
+            }))
+        }
+    }
+}
+
+// ============================================================================
+// OHLCV - ZERO DATABASE PATTERN
+// ============================================================================
+// POST /tokens/ohlcv - Candles para gráficos (formato compacto)
+pub async fn get_ohlcv(
+    body: web::Json<token_service::OhlcvRequest>,
+) -> HttpResponse {
+    log::info!("🕯️ POST /tokens/ohlcv - symbol: {}, timeframe: {:?}, exchange: {}",
+        body.symbol, body.timeframe, body.exchange.name);
+
+    match token_service::get_ohlcv(&body).await {
+        Ok(response) => HttpResponse::Ok().json(response),
+        Err(e) if token_service::is_not_supported(&e) => {
+            HttpResponse::BadRequest().json(serde_json::json!({
+                "success": false,
+                "error": e
+            }))
+        }
+        Err(e) => {
+            log::error!("❌ Failed to get OHLCV: {}", e);
             HttpResponse::InternalServerError().json(serde_json::json!({
                 "success": false,
                 "error": e
@@ -333,3 +430,42 @@ pub async fn get_token_details_multi(
         }
     }
 }
+
+// ============================================================================
+// ADMIN - REFRESH DO CACHE DE TOKENS
+// ============================================================================
+// POST /admin/tokens/refresh/{ccxt_id} - Força a atualização do cache de uma exchange
+pub async fn refresh_tokens_cache(
+    db: web::Data<MongoDB>,
+    user: web::ReqData<crate::middleware::auth::Claims>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let ccxt_id = path.into_inner();
+
+    if let Some(forbidden) = crate::api::admin::forbidden_unless_admin(&user, "refresh token cache") {
+        return forbidden;
+    }
+
+    log::info!("🔄 POST /admin/tokens/refresh/{} - by {}", ccxt_id, user.sub);
+
+    match token_service::refresh_exchange_tokens(&db, &ccxt_id).await {
+        Ok(response) if response.success => {
+            log::info!("✅ Token cache refreshed for {}: {} tokens", ccxt_id, response.total_tokens);
+            HttpResponse::Ok().json(response)
+        }
+        Ok(response) => HttpResponse::BadGateway().json(response),
+        Err(e) if e.starts_with("Exchange not found") => {
+            HttpResponse::NotFound().json(serde_json::json!({
+                "success": false,
+                "error": e
+            }))
+        }
+        Err(e) => {
+            log::error!("❌ Failed to refresh token cache: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "error": e
+            }))
+        }
+    }
+}
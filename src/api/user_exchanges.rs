@@ -1,7 +1,7 @@
 use actix_web::{web, HttpResponse, Responder};
 use crate::{
     database::MongoDB,
-    services::user_exchanges_service,
+    services::{fee_service, user_exchanges_service},
     middleware::auth::Claims,
 };
 
@@ -61,6 +61,30 @@ pub async fn list_exchanges(
     }
 }
 
+/// GET /api/v1/user/exchanges/overview - Exchanges do usuário com saldo ao vivo
+pub async fn exchanges_overview(
+    user: web::ReqData<Claims>,
+    db: web::Data<MongoDB>,
+) -> impl Responder {
+    let user_id = &user.sub;
+    
+    log::info!("📋 GET /user/exchanges/overview - user {}", user_id);
+    
+    match user_exchanges_service::get_exchanges_overview(&db, user_id).await {
+        Ok(response) => {
+            log::info!("✅ Overview for {} exchanges (${:.2})", response.count, response.total_usd);
+            HttpResponse::Ok().json(response)
+        }
+        Err(e) => {
+            log::error!("❌ Error building exchanges overview: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "error": e
+            }))
+        }
+    }
+}
+
 /// PATCH /api/v1/user/exchanges/{exchange_id} - Atualiza exchange
 pub async fn update_exchange(
     user: web::ReqData<Claims>,
@@ -92,6 +116,35 @@ pub async fn update_exchange(
     }
 }
 
+/// POST /api/v1/user/exchanges/{exchange_id}/reactivate - Testa as credenciais e reativa
+pub async fn reactivate_exchange(
+    user: web::ReqData<Claims>,
+    db: web::Data<MongoDB>,
+    exchange_id: web::Path<String>,
+) -> impl Responder {
+    let user_id = &user.sub;
+
+    log::info!("🔄 POST /user/exchanges/{}/reactivate - for user {}", exchange_id, user_id);
+
+    match user_exchanges_service::reactivate_user_exchange(&db, user_id, &exchange_id).await {
+        Ok(response) => {
+            if response.success {
+                HttpResponse::Ok().json(response)
+            } else {
+                log::warn!("⚠️ Failed to reactivate: {:?}", response.error);
+                HttpResponse::BadRequest().json(response)
+            }
+        }
+        Err(e) => {
+            log::error!("❌ Error reactivating exchange: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "error": e
+            }))
+        }
+    }
+}
+
 /// DELETE /api/v1/user/exchanges/{exchange_id} - Remove exchange
 pub async fn delete_exchange(
     user: web::ReqData<Claims>,
@@ -121,3 +174,32 @@ pub async fn delete_exchange(
         }
     }
 }
+
+/// GET /api/v1/user/exchanges/{exchange_id}/fees - Taxas de trade da conta (cache diário)
+pub async fn get_exchange_fees(
+    user: web::ReqData<Claims>,
+    db: web::Data<MongoDB>,
+    exchange_id: web::Path<String>,
+) -> impl Responder {
+    let user_id = &user.sub;
+
+    log::info!("💸 GET /user/exchanges/{}/fees - user {}", exchange_id, user_id);
+
+    match fee_service::get_trading_fees(&db, user_id, &exchange_id).await {
+        Ok(fees) => HttpResponse::Ok().json(serde_json::json!({
+            "success": true,
+            "fees": fees
+        })),
+        Err(e) if e.contains("not found") => HttpResponse::NotFound().json(serde_json::json!({
+            "success": false,
+            "error": e
+        })),
+        Err(e) => {
+            log::error!("❌ Error fetching trading fees: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "error": e
+            }))
+        }
+    }
+}
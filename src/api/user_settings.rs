@@ -0,0 +1,139 @@
+use actix_web::{web, HttpResponse, Responder};
+use crate::{
+    database::MongoDB,
+    services::user_settings_service::{self, UserSettings, WatchlistEntry},
+    middleware::auth::Claims,
+};
+
+/// GET /api/v1/user/settings - Preferências do usuário (defaults se nunca salvou)
+pub async fn get_settings(
+    user: web::ReqData<Claims>,
+    db: web::Data<MongoDB>,
+) -> impl Responder {
+    log::info!("⚙️ GET /user/settings - user {}", user.sub);
+
+    match user_settings_service::get_user_settings(&db, &user.sub).await {
+        Ok(settings) => HttpResponse::Ok().json(serde_json::json!({
+            "success": true,
+            "settings": settings
+        })),
+        Err(e) => {
+            log::error!("❌ Error loading user settings: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "error": e
+            }))
+        }
+    }
+}
+
+/// PUT /api/v1/user/settings - Substitui as preferências (campos ausentes voltam ao default)
+pub async fn update_settings(
+    user: web::ReqData<Claims>,
+    db: web::Data<MongoDB>,
+    body: web::Json<UserSettings>,
+) -> impl Responder {
+    log::info!("⚙️ PUT /user/settings - user {}", user.sub);
+
+    if let Err(e) = user_settings_service::validate_settings(body.clone()) {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "success": false,
+            "error": e
+        }));
+    }
+
+    match user_settings_service::update_user_settings(&db, &user.sub, body.into_inner()).await {
+        Ok(settings) => HttpResponse::Ok().json(serde_json::json!({
+            "success": true,
+            "settings": settings
+        })),
+        Err(e) => {
+            log::error!("❌ Error saving user settings: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "error": e
+            }))
+        }
+    }
+}
+
+/// GET /api/v1/user/watchlist - Símbolos favoritos do usuário
+pub async fn get_watchlist(
+    user: web::ReqData<Claims>,
+    db: web::Data<MongoDB>,
+) -> impl Responder {
+    match user_settings_service::get_user_settings(&db, &user.sub).await {
+        Ok(settings) => HttpResponse::Ok().json(serde_json::json!({
+            "success": true,
+            "watchlist": settings.watchlist
+        })),
+        Err(e) => {
+            log::error!("❌ Error loading watchlist: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "error": e
+            }))
+        }
+    }
+}
+
+/// POST /api/v1/user/watchlist - Adiciona um símbolo (validado no cache de mercados)
+pub async fn add_watchlist_symbol(
+    user: web::ReqData<Claims>,
+    db: web::Data<MongoDB>,
+    body: web::Json<WatchlistEntry>,
+) -> impl Responder {
+    log::info!("⭐ POST /user/watchlist - {} for user {}", body.symbol, user.sub);
+
+    match user_settings_service::add_watchlist_symbol(&db, &user.sub, body.into_inner()).await {
+        Ok((watchlist, markets)) => HttpResponse::Ok().json(serde_json::json!({
+            "success": true,
+            "watchlist": watchlist,
+            "markets_cache_age_secs": markets.markets_cache_age_secs,
+            "markets_stale": markets.markets_stale
+        })),
+        Err(e) if e.starts_with("Failed") || e.starts_with("Database") => {
+            log::error!("❌ Error saving watchlist: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "error": e
+            }))
+        }
+        Err(e) => HttpResponse::BadRequest().json(serde_json::json!({
+            "success": false,
+            "error": e
+        })),
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct WatchlistRemoveQuery {
+    pub symbol: String,
+}
+
+/// DELETE /api/v1/user/watchlist?symbol=BTC/USDT - Remove um símbolo
+pub async fn remove_watchlist_symbol(
+    user: web::ReqData<Claims>,
+    db: web::Data<MongoDB>,
+    query: web::Query<WatchlistRemoveQuery>,
+) -> impl Responder {
+    log::info!("⭐ DELETE /user/watchlist - {} for user {}", query.symbol, user.sub);
+
+    match user_settings_service::remove_watchlist_symbol(&db, &user.sub, &query.symbol).await {
+        Ok(Some(watchlist)) => HttpResponse::Ok().json(serde_json::json!({
+            "success": true,
+            "watchlist": watchlist
+        })),
+        Ok(None) => HttpResponse::NotFound().json(serde_json::json!({
+            "success": false,
+            "error": format!("'{}' is not in the watchlist", query.symbol)
+        })),
+        Err(e) => {
+            log::error!("❌ Error saving watchlist: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "error": e
+            }))
+        }
+    }
+}
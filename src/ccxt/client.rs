@@ -1,7 +1,97 @@
 use pyo3::prelude::*;
 use pyo3::types::{PyDict, PyList};
 use std::collections::HashMap;
-use crate::models::Balance;
+use crate::models::{asset_amounts, Balance, DecryptedExchange, OrderDateRange};
+
+/// Mensagem padrão quando a exchange não suporta um método do CCXT
+pub fn unsupported_capability_error(exchange_name: &str, capability: &str) -> String {
+    format!("NotSupported: {} does not support {}", exchange_name, capability)
+}
+
+/// `fetch_open_orders`/`fetch_closed_orders`/`fetch_orders(symbol, since, limit, params)`
+/// no objeto de exchange do CCXT
+fn call_fetch_orders(exchange: &PyAny, exchange_name: &str, status: &str, range: OrderDateRange) -> Result<Vec<PyObject>, String> {
+    let py = exchange.py();
+    let method = match status {
+        "open" => "fetch_open_orders",
+        "closed" => "fetch_closed_orders",
+        _ => "fetch_orders",
+    };
+    
+    // ⚠️ Exchanges restritivas (Binance, MEXC, OKX, Bybit, Kraken) não aceitam parâmetros extras
+    let exchange_lower = exchange_name.to_lowercase();
+    let is_restrictive = exchange_lower == "binance" 
+        || exchange_lower == "mexc" 
+        || exchange_lower == "okx"
+        || exchange_lower == "bybit"
+        || exchange_lower == "kraken";
+    
+    // `until` é param unificado do CCXT (tratado pela própria lib), vale para todas
+    let params = PyDict::new(py);
+    if let Some(until) = range.until {
+        params.set_item("until", until)
+            .map_err(|e| format!("Failed to set until: {}", e))?;
+    }
+    if is_restrictive {
+        // Sem parâmetros extras para exchanges restritivas
+        log::debug!("🔧 [{}] Calling {} WITHOUT extra params (restrictive exchange)", exchange_name, method);
+    } else {
+        // 🔥 Adiciona timestamp para bypass de cache
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis();
+        params.set_item("_t", timestamp)
+            .map_err(|e| format!("Failed to set timestamp: {}", e))?;
+        log::debug!("🔧 [{}] Calling {} WITH timestamp: {}", exchange_name, method, timestamp);
+    }
+    
+    let symbol: Option<&str> = None;
+    let limit: Option<u32> = None;
+    let orders = if params.is_empty() && range.since.is_none() {
+        exchange.call_method(method, (), None)
+    } else {
+        exchange.call_method1(method, (symbol, range.since, limit, params))
+    }
+    .map_err(|e| format!("Failed to fetch orders: {}", e))?;
+    
+    let mut result = Vec::new();
+    
+    if let Ok(orders_list) = orders.downcast::<PyList>() {
+        for order in orders_list.iter() {
+            result.push(order.into());
+        }
+    }
+    
+    Ok(result)
+}
+
+/// Exchanges com subcontas. Nelas a chave da conta principal sempre opera a conta
+/// principal: saldo e ordens de uma subconta exigem uma API key criada dentro da
+/// própria subconta, conferida por `verify_sub_account_key_sync` ao conectar.
+pub const SUB_ACCOUNT_EXCHANGES: &[&str] = &["okx", "bybit"];
+
+/// Lê a resposta do endpoint de conta da exchange: true quando a chave é de subconta.
+/// OKX `account/config` traz `uid` ≠ `mainUid`; Bybit `user/query-api` traz `isMaster`.
+fn is_sub_account_key(exchange_name: &str, response: &PyAny) -> Result<bool, String> {
+    let field = |value: &PyAny, key: &str| -> Result<String, String> {
+        let item = value.get_item(key).map_err(|_| format!("Missing '{}' in account response", key))?;
+        Ok(item.str().map_err(|e| e.to_string())?.to_string())
+    };
+    match exchange_name.to_lowercase().as_str() {
+        "okx" => {
+            let config = response.get_item("data").and_then(|d| d.get_item(0))
+                .map_err(|_| "Missing account config in OKX response".to_string())?;
+            Ok(field(config, "uid")? != field(config, "mainUid")?)
+        }
+        "bybit" => {
+            let result = response.get_item("result")
+                .map_err(|_| "Missing result in Bybit response".to_string())?;
+            Ok(field(result, "isMaster")?.to_lowercase() == "false")
+        }
+        _ => Ok(false),
+    }
+}
 
 pub struct CCXTClient {
     exchange: Py<PyAny>,
@@ -15,7 +105,7 @@ impl CCXTClient {
         secret: &str,
         passphrase: Option<&str>,
     ) -> Result<Self, String> {
-        Python::with_gil(|py| {
+        let client = Python::with_gil(|py| {
             // Import ccxt
             let ccxt = py
                 .import("ccxt")
@@ -67,12 +157,114 @@ impl CCXTClient {
                 .call1((config,))
                 .map_err(|e| format!("Failed to create exchange: {}", e))?;
             
-            Ok(Self {
+            // 🚦 Compartilha o rateLimit da exchange com o bucket global (ver utils::rate_limiter)
+            if let Ok(rate_limit_ms) = exchange.getattr("rateLimit").and_then(|v| v.extract::<f64>()) {
+                if rate_limit_ms > 0.0 {
+                    crate::utils::rate_limiter::CCXT_RATE_LIMITER
+                        .set_interval(exchange_name, std::time::Duration::from_secs_f64(rate_limit_ms / 1000.0));
+                }
+            }
+            
+            Ok::<_, String>(Self {
                 exchange: exchange.into(),
                 exchange_name: exchange_name.to_string(),
             })
+        })?;
+        client.sync_clock_skew();
+        Ok(client)
+    }
+
+    /// Cliente da exchange do usuário. Com subconta, as credenciais salvas já são
+    /// da subconta (ver `SUB_ACCOUNT_EXCHANGES`)
+    pub fn for_exchange(exchange: &DecryptedExchange) -> Result<Self, String> {
+        Self::new(&exchange.ccxt_id, &exchange.api_key, &exchange.api_secret, exchange.passphrase.as_deref())
+    }
+
+    /// Confere que a chave foi criada dentro de uma subconta, para que saldo e ordens
+    /// caiam nela e não na conta principal. Exchanges sem subcontas ignoram.
+    pub fn verify_sub_account_key_sync(&self, sub_account: &str) -> Result<(), String> {
+        let exchange = self.exchange_name.to_lowercase();
+        if !SUB_ACCOUNT_EXCHANGES.contains(&exchange.as_str()) {
+            log::debug!("🔧 [{}] Sub-account '{}' ignored: exchange has no sub-accounts", self.exchange_name, sub_account);
+            return Ok(());
+        }
+        let method = if exchange == "okx" { "private_get_account_config" } else { "private_get_v5_user_query_api" };
+        Python::with_gil(|py| {
+            let response = self.exchange.as_ref(py).call_method0(method)
+                .map_err(|e| format!("Failed to read account info: {}", e))?;
+            if !is_sub_account_key(&self.exchange_name, response)? {
+                return Err(format!(
+                    "This API key belongs to the main {} account. Create the API key inside sub-account '{}' to use it.",
+                    self.exchange_name, sub_account
+                ));
+            }
+            log::info!("✅ [{}] API key confirmed for sub-account '{}'", self.exchange_name, sub_account);
+            Ok(())
         })
     }
+
+    /// Hora atual da exchange em ms (`fetch_time`, requer `has["fetchTime"]`)
+    pub fn fetch_time_sync(&self) -> Result<i64, String> {
+        self.require_capability("fetchTime")?;
+        Python::with_gil(|py| {
+            self.exchange
+                .as_ref(py)
+                .call_method0("fetch_time")
+                .and_then(|v| v.extract::<i64>())
+                .map_err(|e| format!("Failed to fetch time: {}", e))
+        })
+    }
+
+    /// Aplica o `timeDifference` já medido para a exchange. Se a medição venceu,
+    /// o `fetch_time` roda em uma thread à parte (a construção do client não espera
+    /// a rede) e o resultado vale para este client e para os próximos.
+    fn sync_clock_skew(&self) {
+        use crate::utils::clock_skew::CLOCK_SKEW;
+
+        let time_difference = CLOCK_SKEW.time_difference(&self.exchange_name);
+        if time_difference != 0 {
+            self.apply_time_difference(time_difference);
+        }
+
+        if CLOCK_SKEW.claim_check(&self.exchange_name, chrono::Utc::now().timestamp_millis()) {
+            let client = Self {
+                exchange: Python::with_gil(|py| self.exchange.clone_ref(py)),
+                exchange_name: self.exchange_name.clone(),
+            };
+            std::thread::spawn(move || client.measure_clock_skew());
+        }
+    }
+
+    fn measure_clock_skew(&self) {
+        use crate::utils::clock_skew::CLOCK_SKEW;
+
+        let now_ms = || chrono::Utc::now().timestamp_millis();
+        let sent_at = now_ms();
+        match self.fetch_time_sync() {
+            Ok(server_time) => {
+                let skew = CLOCK_SKEW.record(&self.exchange_name, server_time, sent_at, now_ms());
+                if skew.time_difference_ms != 0 {
+                    self.apply_time_difference(skew.time_difference_ms);
+                }
+            }
+            Err(e) => {
+                log::debug!("🕰️ [{}] Clock skew check failed: {}", self.exchange_name, e);
+                CLOCK_SKEW.record_error(&self.exchange_name, e, now_ms());
+            }
+        }
+    }
+
+    fn apply_time_difference(&self, time_difference: i64) {
+        let applied = Python::with_gil(|py| {
+            self.exchange
+                .as_ref(py)
+                .getattr("options")
+                .and_then(|options| options.set_item("timeDifference", time_difference))
+        });
+        if let Err(e) = applied {
+            log::warn!("⚠️ [{}] Failed to apply timeDifference: {}", self.exchange_name, e);
+        }
+    }
     
     /// Fetch all ticker prices from exchange in a single optimized call
     /// 🔥 REAL-TIME: Usa timestamp para garantir bypass de cache (exceto exchanges restritivas)
@@ -139,7 +331,9 @@ impl CCXTClient {
         // This method is kept for compatibility but wraps the sync version
         let exchange = self.exchange.clone();
         let exchange_name = self.exchange_name.clone();
-        tokio::task::spawn_blocking(move || {
+        // Dois requests (fetch_balance + fetch_tickers): um token extra antes do pool
+        crate::utils::rate_limiter::CCXT_RATE_LIMITER.acquire(&exchange_name).await;
+        crate::utils::thread_pool::spawn_ccxt_paced(&exchange_name.clone(), move || {
             Self::fetch_balance_internal(&exchange, &exchange_name)
         })
         .await
@@ -297,37 +491,16 @@ impl CCXTClient {
                 .get_item("total")
                 .map_err(|e| format!("Failed to get total: {}", e))?;
             
-            let free = balance_dict
-                .get_item("free")
-                .map_err(|e| format!("Failed to get free: {}", e))?;
-            
-            let used = balance_dict
-                .get_item("used")
-                .map_err(|e| format!("Failed to get used: {}", e))?;
-            
             let mut balances = HashMap::new();
             
             // Convert Python dict to Rust HashMap
             if let Ok(total_dict) = total.downcast::<PyDict>() {
-                for (key, value) in total_dict.iter() {
+                for (key, _) in total_dict.iter() {
                     let symbol: String = key.extract().unwrap_or_default();
-                    let total_amount: f64 = value.extract().unwrap_or(0.0);
+                    let amounts = asset_amounts(balance_dict, &symbol, exchange_name);
+                    let (total_amount, free_amount, used_amount) = (amounts.total, amounts.free, amounts.used);
                     
-                    if total_amount > 0.0 {
-                        let free_amount: f64 = free
-                            .downcast::<PyDict>()
-                            .ok()
-                            .and_then(|d| d.get_item(&symbol).ok())
-                            .and_then(|opt| opt.and_then(|v| v.extract().ok()))
-                            .unwrap_or(0.0);
-                        
-                        let used_amount: f64 = used
-                            .downcast::<PyDict>()
-                            .ok()
-                            .and_then(|d| d.get_item(&symbol).ok())
-                            .and_then(|opt| opt.and_then(|v| v.extract().ok()))
-                            .unwrap_or(0.0);
-                        
+                    if total_amount > 0.0 || amounts.parse_error.is_some() {
                         // 3. Calculate USD value
                         let price_usd = if symbol == "USDT" 
                             || symbol == "USDC" 
@@ -381,6 +554,7 @@ impl CCXTClient {
                                 total: total_amount,
                                 usd_value,
                                 change_24h,  // ✅ NOW HAS CHANGE VALUE!
+                                parse_error: amounts.parse_error,
                             },
                         );
                     }
@@ -480,55 +654,14 @@ impl CCXTClient {
     }
     
     pub fn fetch_orders_sync(&self, status: &str) -> Result<Vec<PyObject>, String> {
-        Python::with_gil(|py| {
-            let method = match status {
-                "open" => "fetch_open_orders",
-                "closed" => "fetch_closed_orders",
-                _ => "fetch_orders",
-            };
-            
-            // ⚠️ Exchanges restritivas (Binance, MEXC, OKX, Bybit, Kraken) não aceitam parâmetros extras
-            let exchange_lower = self.exchange_name.to_lowercase();
-            let is_restrictive = exchange_lower == "binance" 
-                || exchange_lower == "mexc" 
-                || exchange_lower == "okx"
-                || exchange_lower == "bybit"
-                || exchange_lower == "kraken";
-            
-            let orders = if is_restrictive {
-                // Sem parâmetros para exchanges restritivas
-                log::debug!("🔧 [{}] Calling {} WITHOUT params (restrictive exchange)", self.exchange_name, method);
-                self.exchange
-                    .as_ref(py)
-                    .call_method(method, (), None)
-                    .map_err(|e| format!("Failed to fetch orders: {}", e))?
-            } else {
-                // 🔥 Adiciona timestamp para bypass de cache
-                let params = pyo3::types::PyDict::new(py);
-                let timestamp = std::time::SystemTime::now()
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .unwrap()
-                    .as_millis();
-                params.set_item("_t", timestamp)
-                    .map_err(|e| format!("Failed to set timestamp: {}", e))?;
-                
-                log::debug!("🔧 [{}] Calling {} WITH timestamp: {}", self.exchange_name, method, timestamp);
-                self.exchange
-                    .as_ref(py)
-                    .call_method(method, (), Some(params))
-                    .map_err(|e| format!("Failed to fetch orders: {}", e))?
-            };
-            
-            let mut result = Vec::new();
-            
-            if let Ok(orders_list) = orders.downcast::<PyList>() {
-                for order in orders_list.iter() {
-                    result.push(order.into());
-                }
-            }
-            
-            Ok(result)
-        })
+        self.fetch_orders_in_range_sync(status, OrderDateRange::default())
+    }
+    
+    /// Igual a `fetch_orders_sync`, com `since`/`until` repassados ao CCXT.
+    /// Exchanges que ignoram `until` podem devolver ordens fora da janela:
+    /// quem chama filtra o resultado com `OrderDateRange::retain`.
+    pub fn fetch_orders_in_range_sync(&self, status: &str, range: OrderDateRange) -> Result<Vec<PyObject>, String> {
+        Python::with_gil(|py| call_fetch_orders(self.exchange.as_ref(py), &self.exchange_name, status, range))
     }
     
     pub fn create_order_sync(
@@ -538,8 +671,32 @@ impl CCXTClient {
         side: &str,
         amount: f64,
         price: Option<f64>,
+    ) -> Result<PyObject, String> {
+        self.create_order_tif_sync(symbol, order_type, side, amount, price, None)
+    }
+
+    /// Cria ordem com `timeInForce` (GTC/IOC/FOK) via params do CCXT
+    pub fn create_order_tif_sync(
+        &self,
+        symbol: &str,
+        order_type: &str,
+        side: &str,
+        amount: f64,
+        price: Option<f64>,
+        time_in_force: Option<&str>,
     ) -> Result<PyObject, String> {
         Python::with_gil(|py| {
+            if let Some(tif) = time_in_force {
+                let params = PyDict::new(py);
+                params.set_item("timeInForce", tif)
+                    .map_err(|e| format!("Failed to set timeInForce: {}", e))?;
+                let order = self.exchange
+                    .as_ref(py)
+                    .call_method1("create_order", (symbol, order_type, side, amount, price, params))
+                    .map_err(|e| format!("Failed to create order: {}", e))?;
+                return Ok(order.into());
+            }
+
             let order = if let Some(p) = price {
                 self.exchange
                     .as_ref(py)
@@ -556,6 +713,64 @@ impl CCXTClient {
         })
     }
     
+    /// Cria ordem com params arbitrários do CCXT (ex: `trailingPercent`, `trailingDelta`)
+    pub fn create_order_params_sync(
+        &self,
+        symbol: &str,
+        order_type: &str,
+        side: &str,
+        amount: f64,
+        price: Option<f64>,
+        params: &serde_json::Map<String, serde_json::Value>,
+    ) -> Result<PyObject, String> {
+        Python::with_gil(|py| {
+            let params_json = serde_json::to_string(params)
+                .map_err(|e| format!("Failed to serialize order params: {}", e))?;
+            let py_params = py.import("json")
+                .and_then(|json| json.call_method1("loads", (params_json,)))
+                .map_err(|e| format!("Failed to build order params: {}", e))?;
+            let order = self.exchange
+                .as_ref(py)
+                .call_method1("create_order", (symbol, order_type, side, amount, price, py_params))
+                .map_err(|e| format!("Failed to create order: {}", e))?;
+            Ok(order.into())
+        })
+    }
+
+    /// Exchange aceita compra a mercado pelo valor em quote (`cost`) em vez de quantidade base.
+    /// Detectado por `has["createMarketBuyOrderWithCost"]` ou pela opção
+    /// `createMarketBuyOrderRequiresPrice` (que pode ser desligada por ordem).
+    pub fn supports_market_buy_cost_sync(&self) -> bool {
+        if self.has_capability_sync("createMarketBuyOrderWithCost") {
+            return true;
+        }
+        Python::with_gil(|py| {
+            self.exchange
+                .as_ref(py)
+                .getattr("options")
+                .ok()
+                .and_then(|options| options.downcast::<PyDict>().ok())
+                .is_some_and(market_buy_requires_price_option)
+        })
+    }
+
+    /// Compra a mercado gastando `cost` na moeda de quote (params `cost` +
+    /// `createMarketBuyOrderRequiresPrice=false`, então `amount` também é o custo)
+    pub fn create_market_buy_cost_sync(&self, symbol: &str, cost: f64) -> Result<PyObject, String> {
+        Python::with_gil(|py| {
+            let params = PyDict::new(py);
+            params.set_item("cost", cost)
+                .map_err(|e| format!("Failed to set cost: {}", e))?;
+            params.set_item("createMarketBuyOrderRequiresPrice", false)
+                .map_err(|e| format!("Failed to set createMarketBuyOrderRequiresPrice: {}", e))?;
+            let order = self.exchange
+                .as_ref(py)
+                .call_method1("create_order", (symbol, "market", "buy", cost, py.None(), params))
+                .map_err(|e| format!("Failed to create order: {}", e))?;
+            Ok(order.into())
+        })
+    }
+
     pub async fn fetch_ticker(&self, symbol: &str) -> Result<HashMap<String, f64>, String> {
         Python::with_gil(|py| {
             // ⚠️ Exchanges restritivas (Binance, MEXC) não aceitam parâmetros extras
@@ -626,6 +841,391 @@ impl CCXTClient {
         })
     }
     
+    /// Busca o livro de ofertas (`limit` níveis por lado). Níveis malformados são descartados.
+    pub fn fetch_order_book_sync(&self, symbol: &str, limit: usize) -> Result<crate::models::OrderBook, String> {
+        Python::with_gil(|py| {
+            let book = self.exchange
+                .as_ref(py)
+                .call_method1("fetch_order_book", (symbol, limit))
+                .map_err(|e| format!("Failed to fetch order book: {}", e))?;
+
+            // [preço, quantidade(, ...)] — algumas exchanges mandam campos extras
+            let side = |key: &str| -> Vec<[f64; 2]> {
+                book.get_item(key)
+                    .and_then(|levels| levels.extract::<Vec<Vec<Option<f64>>>>())
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter_map(|level| match level.as_slice() {
+                        [Some(price), Some(amount), ..] => Some([*price, *amount]),
+                        _ => None,
+                    })
+                    .collect()
+            };
+
+            Ok(crate::models::OrderBook { bids: side("bids"), asks: side("asks") })
+        })
+    }
+
+    /// Busca candles OHLCV (mais antigo → mais recente)
+    pub fn fetch_ohlcv_sync(&self, symbol: &str, timeframe: &str, limit: usize) -> Result<Vec<crate::models::Candle>, String> {
+        self.fetch_ohlcv_since_sync(symbol, timeframe, None, limit)
+    }
+
+    /// Busca candles OHLCV a partir de `since` (ms), mais antigo → mais recente
+    pub fn fetch_ohlcv_since_sync(&self, symbol: &str, timeframe: &str, since: Option<i64>, limit: usize) -> Result<Vec<crate::models::Candle>, String> {
+        Python::with_gil(|py| {
+            let since = since.into_py(py);
+            let ohlcv = self.exchange
+                .as_ref(py)
+                .call_method1("fetch_ohlcv", (symbol, timeframe, since, limit))
+                .map_err(|e| format!("Failed to fetch OHLCV: {}", e))?;
+
+            let rows = ohlcv.downcast::<PyList>()
+                .map_err(|e| format!("Invalid OHLCV response: {}", e))?;
+
+            let mut candles = Vec::with_capacity(rows.len());
+            for row in rows.iter() {
+                // [timestamp, open, high, low, close, volume] — volume pode vir None
+                let values: Vec<Option<f64>> = match row.extract() {
+                    Ok(v) => v,
+                    Err(_) => continue,
+                };
+                if values.len() < 5 { continue; }
+                let (Some(ts), Some(open), Some(high), Some(low), Some(close)) =
+                    (values[0], values[1], values[2], values[3], values[4]) else { continue };
+                candles.push(crate::models::Candle {
+                    timestamp: ts as i64,
+                    open, high, low, close,
+                    volume: values.get(5).copied().flatten().unwrap_or(0.0),
+                });
+            }
+
+            Ok(candles)
+        })
+    }
+
+    /// Timeframes aceitos por `fetch_ohlcv` (`exchange.timeframes`); None se a exchange não informa
+    pub fn supported_timeframes_sync(&self) -> Option<Vec<String>> {
+        Python::with_gil(|py| {
+            let timeframes = self.exchange.as_ref(py).getattr("timeframes").ok()?;
+            let dict = timeframes.downcast::<PyDict>().ok()?;
+            let keys: Vec<String> = dict.keys().iter().filter_map(|k| k.extract().ok()).collect();
+            (!keys.is_empty()).then_some(keys)
+        })
+    }
+
+    /// `exchange.timeframes` cru (timeframe unificado → código nativo da exchange); Null se ausente
+    /// Dict `exchange.has` (valores `true`/`false`/`"emulated"`/`null`)
+    pub fn has_json_sync(&self) -> Result<serde_json::Value, String> {
+        Python::with_gil(|py| {
+            let has = self.exchange
+                .as_ref(py)
+                .getattr("has")
+                .map_err(|e| format!("Failed to read capabilities: {}", e))?;
+            let json_str: String = py.import("json")
+                .and_then(|json| json.call_method1("dumps", (has,)))
+                .and_then(|s| s.extract())
+                .map_err(|e| format!("Failed to serialize capabilities: {}", e))?;
+            serde_json::from_str(&json_str).map_err(|e| format!("Failed to parse JSON: {}", e))
+        })
+    }
+
+    pub fn timeframes_json_sync(&self) -> Result<serde_json::Value, String> {
+        Python::with_gil(|py| {
+            let timeframes = self.exchange
+                .as_ref(py)
+                .getattr("timeframes")
+                .map_err(|e| format!("Failed to read timeframes: {}", e))?;
+            let json_str: String = py.import("json")
+                .and_then(|json| json.call_method1("dumps", (timeframes,)))
+                .and_then(|s| s.extract())
+                .map_err(|e| format!("Failed to serialize timeframes: {}", e))?;
+            serde_json::from_str(&json_str).map_err(|e| format!("Failed to parse JSON: {}", e))
+        })
+    }
+
+    /// Verifica `exchange.has[capability]` (ex: "setLeverage", "fetchFundingRate")
+    pub fn has_capability_sync(&self, capability: &str) -> bool {
+        Python::with_gil(|py| {
+            self.exchange
+                .as_ref(py)
+                .getattr("has")
+                .ok()
+                .and_then(|has_dict| has_dict.downcast::<PyDict>().ok())
+                .and_then(|dict| dict.get_item(capability).ok().flatten())
+                .and_then(|v| v.extract::<bool>().ok())
+                .unwrap_or(false)
+        })
+    }
+
+    fn require_capability(&self, capability: &str) -> Result<(), String> {
+        if self.has_capability_sync(capability) {
+            Ok(())
+        } else {
+            Err(unsupported_capability_error(&self.exchange_name, capability))
+        }
+    }
+
+    fn call_json(&self, method: &str, args: impl IntoPy<Py<pyo3::types::PyTuple>>) -> Result<serde_json::Value, String> {
+        Python::with_gil(|py| {
+            let result = self.exchange
+                .as_ref(py)
+                .call_method1(method, args)
+                .map_err(|e| format!("Failed to call {}: {}", method, e))?;
+            let json_str: String = py.import("json")
+                .and_then(|json| json.call_method1("dumps", (result,)))
+                .and_then(|s| s.extract())
+                .map_err(|e| format!("Failed to serialize {} response: {}", method, e))?;
+            serde_json::from_str(&json_str).map_err(|e| format!("Failed to parse JSON: {}", e))
+        })
+    }
+
+    /// Funding rate atual de um perpétuo (requer `has["fetchFundingRate"]`)
+    pub fn fetch_funding_rate_sync(&self, symbol: &str) -> Result<crate::models::FundingRate, String> {
+        self.require_capability("fetchFundingRate")?;
+        let raw = self.call_json("fetch_funding_rate", (symbol,))?;
+        crate::models::FundingRate::from_ccxt(&raw)
+    }
+
+    /// Histórico de funding (requer `has["fetchFundingRateHistory"]`), mais antigo → mais recente
+    pub fn fetch_funding_rate_history_sync(&self, symbol: &str, limit: usize) -> Result<Vec<crate::models::FundingRateEntry>, String> {
+        self.require_capability("fetchFundingRateHistory")?;
+        let raw = self.call_json("fetch_funding_rate_history", (symbol, Option::<i64>::None, limit))?;
+        Ok(crate::models::FundingRateEntry::list_from_ccxt(&raw))
+    }
+
+    /// Tickers só dos símbolos pedidos, numa única chamada (`fetch_tickers(symbols)`)
+    pub fn fetch_tickers_for_sync(&self, symbols: &[String]) -> Result<HashMap<String, serde_json::Value>, String> {
+        let raw = self.call_json("fetch_tickers", (symbols.to_vec(),))?;
+        serde_json::from_value(raw).map_err(|e| format!("Failed to parse tickers: {}", e))
+    }
+
+    /// Taxas de trade da conta por símbolo (requer `has["fetchTradingFees"]`)
+    pub fn fetch_trading_fees_sync(&self) -> Result<std::collections::BTreeMap<String, crate::models::TradingFee>, String> {
+        self.require_capability("fetchTradingFees")?;
+        let raw = self.call_json("fetch_trading_fees", ())?;
+        Ok(crate::models::TradingFee::map_from_ccxt(&raw))
+    }
+
+    /// Taxas padrão documentadas no CCXT (`exchange.fees["trading"]`), sem chamada à exchange
+    pub fn default_trading_fees_sync(&self) -> Option<(f64, f64)> {
+        Python::with_gil(|py| {
+            let trading = self.exchange.as_ref(py)
+                .getattr("fees").ok()?
+                .get_item("trading").ok()?;
+            let rate = |key: &str| -> Option<f64> {
+                let v = trading.get_item(key).ok()?;
+                if v.is_none() { None } else { v.extract().ok() }
+            };
+            Some((rate("maker")?, rate("taker")?))
+        })
+    }
+
+    /// Define a alavancagem do símbolo (contratos perpétuos/futuros)
+    pub fn set_leverage_sync(&self, leverage: u32, symbol: &str) -> Result<(), String> {
+        self.require_capability("setLeverage")?;
+        Python::with_gil(|py| {
+            self.exchange
+                .as_ref(py)
+                .call_method1("set_leverage", (leverage, symbol))
+                .map_err(|e| format!("Failed to set leverage: {}", e))?;
+            Ok(())
+        })
+    }
+
+    /// Carrega os mercados e retorna os limites mínimos (amount/cost) por símbolo
+    pub fn fetch_market_limits_sync(&self) -> Result<HashMap<String, crate::models::MarketLimits>, String> {
+        Python::with_gil(|py| {
+            let markets = self.exchange
+                .as_ref(py)
+                .call_method0("load_markets")
+                .map_err(|e| format!("Failed to load markets: {}", e))?;
+
+            let markets = markets.downcast::<PyDict>()
+                .map_err(|_| "Markets is not a dict".to_string())?;
+
+            let min_of = |limits: &PyAny, key: &str| -> Option<f64> {
+                let entry = limits.get_item(key).ok()?;
+                if entry.is_none() { return None; }
+                let min = entry.get_item("min").ok()?;
+                if min.is_none() { None } else { min.extract().ok() }
+            };
+
+            let mut result = HashMap::new();
+            for (symbol, market) in markets.iter() {
+                let Ok(symbol) = symbol.extract::<String>() else { continue };
+                // Ignora mercados desativados
+                let active = market.get_item("active").ok()
+                    .and_then(|v| if v.is_none() { None } else { v.extract::<bool>().ok() })
+                    .unwrap_or(true);
+                if !active { continue; }
+
+                let limits = match market.get_item("limits") {
+                    Ok(l) if !l.is_none() => crate::models::MarketLimits {
+                        min_amount: min_of(l, "amount"),
+                        min_cost: min_of(l, "cost"),
+                        ..Default::default()
+                    },
+                    _ => crate::models::MarketLimits::default(),
+                };
+                result.insert(symbol, limits);
+            }
+
+            Ok(result)
+        })
+    }
+
+    /// Carrega os mercados spot ativos no formato do cache `tokens_exchanges`
+    pub fn fetch_market_tokens_sync(&self) -> Result<Vec<crate::models::TokenInfo>, String> {
+        Python::with_gil(|py| {
+            let markets = self.exchange
+                .as_ref(py)
+                .call_method0("load_markets")
+                .map_err(|e| format!("Failed to load markets: {}", e))?;
+
+            let markets = markets.downcast::<PyDict>()
+                .map_err(|_| "Markets is not a dict".to_string())?;
+
+            let opt_f64 = |v: &PyAny| -> Option<f64> {
+                if v.is_none() { None } else { v.extract().ok() }
+            };
+            let limit_of = |limits: &PyAny, key: &str, bound: &str| -> Option<f64> {
+                let entry = limits.get_item(key).ok()?;
+                if entry.is_none() { return None; }
+                opt_f64(entry.get_item(bound).ok()?)
+            };
+            let flag_of = |market: &PyAny, key: &str| -> Option<bool> {
+                market.get_item(key).ok()
+                    .and_then(|v| if v.is_none() { None } else { v.extract::<bool>().ok() })
+            };
+
+            let mut tokens = Vec::new();
+            for (pair, market) in markets.iter() {
+                let Ok(pair) = pair.extract::<String>() else { continue };
+                // Ignora mercados desativados e derivativos
+                if !flag_of(market, "active").unwrap_or(true) || !flag_of(market, "spot").unwrap_or(true) {
+                    continue;
+                }
+                let (Ok(base), Ok(quote)) = (
+                    market.get_item("base").and_then(|v| v.extract::<String>()),
+                    market.get_item("quote").and_then(|v| v.extract::<String>()),
+                ) else { continue };
+
+                let limits = market.get_item("limits").ok().filter(|l| !l.is_none());
+                tokens.push(crate::models::TokenInfo {
+                    symbol: base,
+                    pair,
+                    quote,
+                    min_amount: limits.and_then(|l| limit_of(l, "amount", "min")),
+                    max_amount: limits.and_then(|l| limit_of(l, "amount", "max")),
+                    min_cost: limits.and_then(|l| limit_of(l, "cost", "min")),
+                    name: None,
+                    logo: None,
+                });
+            }
+
+            Ok(tokens)
+        })
+    }
+
+    /// Precisão (casas decimais) de quantidade e preço de um mercado
+    pub fn fetch_market_precision_sync(&self, symbol: &str) -> Result<crate::models::MarketPrecision, String> {
+        use crate::utils::precision::ccxt_precision_to_decimals;
+        Python::with_gil(|py| {
+            let exchange = self.exchange.as_ref(py);
+            exchange
+                .call_method0("load_markets")
+                .map_err(|e| format!("Failed to load markets: {}", e))?;
+            let market = exchange
+                .call_method1("market", (symbol,))
+                .map_err(|e| format!("Market {} not found: {}", symbol, e))?;
+
+            let mode: i64 = exchange.getattr("precisionMode").ok()
+                .and_then(|v| v.extract().ok())
+                .unwrap_or(crate::utils::precision::CCXT_TICK_SIZE);
+            let precision = market.get_item("precision").ok().filter(|p| !p.is_none());
+            let decimals = |key: &str| -> Option<u32> {
+                let raw: f64 = precision?.get_item(key).ok()
+                    .and_then(|v| if v.is_none() { None } else { v.extract().ok() })?;
+                ccxt_precision_to_decimals(raw, mode)
+            };
+
+            Ok(crate::models::MarketPrecision {
+                amount_decimals: decimals("amount"),
+                price_decimals: decimals("price"),
+            })
+        })
+    }
+
+    /// Status, limites e precisão de um mercado (None se o símbolo não existe na exchange)
+    pub fn fetch_market_rules_sync(&self, symbol: &str) -> Result<Option<crate::models::MarketRules>, String> {
+        use crate::utils::precision::ccxt_precision_to_decimals;
+        Python::with_gil(|py| {
+            let exchange = self.exchange.as_ref(py);
+            let markets = exchange
+                .call_method0("load_markets")
+                .map_err(|e| format!("Failed to load markets: {}", e))?;
+            let markets = markets.downcast::<PyDict>()
+                .map_err(|_| "Markets is not a dict".to_string())?;
+            let Ok(Some(market)) = markets.get_item(symbol) else { return Ok(None) };
+
+            let opt_f64 = |v: &PyAny| -> Option<f64> {
+                if v.is_none() { None } else { v.extract().ok() }
+            };
+            let limit_of = |key: &str, bound: &str| -> Option<f64> {
+                let limits = market.get_item("limits").ok().filter(|l| !l.is_none())?;
+                let entry = limits.get_item(key).ok().filter(|e| !e.is_none())?;
+                opt_f64(entry.get_item(bound).ok()?)
+            };
+            let mode: i64 = exchange.getattr("precisionMode").ok()
+                .and_then(|v| v.extract().ok())
+                .unwrap_or(crate::utils::precision::CCXT_TICK_SIZE);
+            let decimals = |key: &str| -> Option<u32> {
+                let precision = market.get_item("precision").ok().filter(|p| !p.is_none())?;
+                ccxt_precision_to_decimals(opt_f64(precision.get_item(key).ok()?)?, mode)
+            };
+
+            Ok(Some(crate::models::MarketRules {
+                active: market.get_item("active").ok()
+                    .and_then(|v| if v.is_none() { None } else { v.extract::<bool>().ok() })
+                    .unwrap_or(true),
+                limits: crate::models::MarketLimits {
+                    min_amount: limit_of("amount", "min"),
+                    min_cost: limit_of("cost", "min"),
+                    max_amount: limit_of("amount", "max"),
+                    max_cost: limit_of("cost", "max"),
+                    min_price: limit_of("price", "min"),
+                    max_price: limit_of("price", "max"),
+                },
+                precision: crate::models::MarketPrecision {
+                    amount_decimals: decimals("amount"),
+                    price_decimals: decimals("price"),
+                },
+            }))
+        })
+    }
+
+    /// Saldo livre de um ativo (`balance.free[asset]`; 0 se ausente)
+    pub fn fetch_free_balance_sync(&self, asset: &str) -> Result<f64, String> {
+        self.fetch_asset_balance_sync(asset, "free")
+    }
+
+    /// Saldo total (free + used) de um ativo (`balance.total[asset]`; 0 se ausente)
+    pub fn fetch_total_balance_sync(&self, asset: &str) -> Result<f64, String> {
+        self.fetch_asset_balance_sync(asset, "total")
+    }
+
+    fn fetch_asset_balance_sync(&self, asset: &str, field: &str) -> Result<f64, String> {
+        let balance = self.fetch_balance_raw()?;
+        Python::with_gil(|py| {
+            let amounts = balance.as_ref(py).get_item(field).ok().filter(|f| !f.is_none());
+            Ok(amounts
+                .and_then(|f| f.get_item(asset).ok())
+                .and_then(|v| if v.is_none() { None } else { v.extract::<f64>().ok() })
+                .unwrap_or(0.0))
+        })
+    }
+
     pub fn fetch_positions_sync(&self) -> Result<Vec<PyObject>, String> {
         Python::with_gil(|py| {
             // ⚠️ Exchanges restritivas (Binance, MEXC) não aceitam parâmetros extras
@@ -887,11 +1487,11 @@ impl CCXTClient {
     }
 
     /// Verifica as permissões da API key testando operações específicas
-    pub fn check_api_permissions(&self) -> Result<crate::services::user_exchanges_service::ApiPermissions, String> {
+    pub fn check_api_permissions(&self) -> Result<crate::models::ApiPermissions, String> {
         Python::with_gil(|py| {
             log::info!("🔐 Checking API key permissions for {}...", self.exchange_name);
             
-            let mut permissions = crate::services::user_exchanges_service::ApiPermissions {
+            let mut permissions = crate::models::ApiPermissions {
                 can_read: false,
                 can_trade: false,
                 can_withdraw: false,
@@ -1078,3 +1678,182 @@ impl CCXTClient {
     }
 }
 
+
+/// `options["createMarketBuyOrderRequiresPrice"]` ligada (bool `True`): a exchange
+/// calcula o custo por `amount × price` e aceita desligar isso por ordem com `cost`.
+/// Chave ausente, `None` ou `False` não indicam suporte.
+fn market_buy_requires_price_option(options: &PyDict) -> bool {
+    options.get_item("createMarketBuyOrderRequiresPrice").ok().flatten()
+        .and_then(|value| value.extract::<bool>().ok())
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{parse_ccxt_order, Order};
+
+    /// (symbol, since, limit, params) recebidos pela exchange fake
+    type FetchCall = (Option<String>, Option<i64>, Option<u32>, HashMap<String, i64>);
+
+    #[test]
+    fn test_closed_orders_range_forwarded_and_trimmed_client_side() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            // Exchange que respeita `since` mas ignora `until`
+            let locals = PyDict::new(py);
+            py.run(r#"
+class FakeExchange:
+    def __init__(self):
+        self.calls = []
+    def fetch_closed_orders(self, symbol=None, since=None, limit=None, params={}):
+        self.calls.append((symbol, since, limit, dict(params)))
+        orders = [{"id": str(ts), "symbol": "BTC/USDT", "status": "closed", "side": "buy",
+                   "type": "market", "amount": 1.0, "timestamp": ts} for ts in (500, 1000, 1500, 2000, 2500)]
+        return [o for o in orders if since is None or o["timestamp"] >= since]
+fake = FakeExchange()
+"#, None, Some(locals)).unwrap();
+            let fake = locals.get_item("fake").unwrap().unwrap();
+
+            let range = OrderDateRange { since: Some(1000), until: Some(2000) };
+            let raw = call_fetch_orders(fake, "binance", "closed", range).unwrap();
+            let (symbol, since, limit, params): FetchCall =
+                fake.getattr("calls").unwrap().get_item(0).unwrap().extract().unwrap();
+            assert_eq!((symbol, since, limit), (None, Some(1000), None));
+            assert_eq!(params.get("until"), Some(&2000));
+            assert_eq!(raw.len(), 4, "exchange ignored until");
+
+            let mut orders: Vec<Order> = raw.iter()
+                .map(|o| parse_ccxt_order(o.as_ref(py)).unwrap().into_order("u1", "ex1", "Binance"))
+                .collect();
+            range.retain(&mut orders);
+            let kept: Vec<i64> = orders.iter().map(|o| o.timestamp).collect();
+            assert_eq!(kept, vec![1000, 1500, 2000]);
+
+            // Sem janela: chamada sem argumentos, como antes
+            call_fetch_orders(fake, "binance", "closed", OrderDateRange::default()).unwrap();
+            let calls: Vec<FetchCall> =
+                fake.getattr("calls").unwrap().extract().unwrap();
+            assert_eq!(calls[1], (None, None, None, HashMap::new()));
+        });
+        assert!(OrderDateRange { since: Some(2), until: Some(1) }.validate().is_err());
+    }
+
+    #[test]
+    fn test_clock_skew_measured_in_background_once_per_exchange() {
+        use crate::utils::clock_skew::CLOCK_SKEW;
+
+        pyo3::prepare_freethreaded_python();
+        let fake = Python::with_gil(|py| {
+            let locals = PyDict::new(py);
+            // Exchange 5s adiantada cujo fetch_time demora
+            py.run(r#"
+class FakeExchange:
+    def __init__(self):
+        self.has = {"fetchTime": True}
+        self.options = {}
+        self.time_calls = 0
+    def fetch_time(self):
+        import time
+        self.time_calls += 1
+        time.sleep(0.3)
+        return int(time.time() * 1000) + 5000
+fake = FakeExchange()
+"#, None, Some(locals)).unwrap();
+            let fake: Py<PyAny> = locals.get_item("fake").unwrap().unwrap().into();
+            fake
+        });
+        let client = |fake: &Py<PyAny>| CCXTClient {
+            exchange: Python::with_gil(|py| fake.clone_ref(py)),
+            exchange_name: "fakeskewex".to_string(),
+        };
+        let read = |attr: &str| -> i64 {
+            Python::with_gil(|py| {
+                let fake = fake.as_ref(py);
+                match attr {
+                    "time_calls" => fake.getattr("time_calls").unwrap().extract().unwrap(),
+                    _ => fake.getattr("options").unwrap().get_item(attr).ok()
+                        .and_then(|v| v.extract().ok()).unwrap_or(0),
+                }
+            })
+        };
+
+        // A construção não espera o fetch_time
+        let started = std::time::Instant::now();
+        client(&fake).sync_clock_skew();
+        assert!(started.elapsed() < std::time::Duration::from_millis(250));
+
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while CLOCK_SKEW.time_difference("fakeskewex") == 0 && std::time::Instant::now() < deadline {
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+        let time_difference = CLOCK_SKEW.time_difference("fakeskewex");
+        assert!(time_difference < -4000, "skew not recorded: {:?}", CLOCK_SKEW.snapshot());
+        assert_eq!(read("timeDifference"), time_difference);
+
+        // Próximos clients usam o valor guardado, sem nova medição
+        Python::with_gil(|py| fake.as_ref(py).setattr("options", PyDict::new(py)).unwrap());
+        client(&fake).sync_clock_skew();
+        assert_eq!(read("timeDifference"), time_difference);
+        assert_eq!(read("time_calls"), 1);
+    }
+
+    #[test]
+    fn test_sub_account_key_checked_against_exchange_account_endpoint() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let locals = PyDict::new(py);
+            py.run(r#"
+class FakeExchange:
+    def __init__(self, uid, main_uid, is_master):
+        self.calls = []
+        self.uid, self.main_uid, self.is_master = uid, main_uid, is_master
+    def private_get_account_config(self, params={}):
+        self.calls.append("private_get_account_config")
+        return {"code": "0", "data": [{"uid": self.uid, "mainUid": self.main_uid, "label": "bot"}]}
+    def private_get_v5_user_query_api(self, params={}):
+        self.calls.append("private_get_v5_user_query_api")
+        return {"retCode": 0, "result": {"userID": 7, "isMaster": self.is_master}}
+"#, None, Some(locals)).unwrap();
+            let class = locals.get_item("FakeExchange").unwrap().unwrap();
+            let client = |exchange: &str, uid: &str, main_uid: &str, is_master: bool| CCXTClient {
+                exchange: class.call1((uid, main_uid, is_master)).unwrap().into(),
+                exchange_name: exchange.to_string(),
+            };
+            let calls = |client: &CCXTClient| -> Vec<String> {
+                client.exchange.as_ref(py).getattr("calls").unwrap().extract().unwrap()
+            };
+
+            let okx_sub = client("okx", "222", "111", false);
+            assert!(okx_sub.verify_sub_account_key_sync("trading-sub").is_ok());
+            assert_eq!(calls(&okx_sub), vec!["private_get_account_config"]);
+            let okx_main = client("OKX", "111", "111", false);
+            let err = okx_main.verify_sub_account_key_sync("trading-sub").unwrap_err();
+            assert!(err.contains("main OKX account") && err.contains("trading-sub"), "{}", err);
+
+            let bybit_sub = client("bybit", "", "", false);
+            assert!(bybit_sub.verify_sub_account_key_sync("trading-sub").is_ok());
+            assert_eq!(calls(&bybit_sub), vec!["private_get_v5_user_query_api"]);
+            assert!(client("bybit", "", "", true).verify_sub_account_key_sync("trading-sub").is_err());
+
+            // Exchange sem subcontas: nenhuma chamada
+            let binance = client("binance", "", "", true);
+            assert!(binance.verify_sub_account_key_sync("trading-sub").is_ok());
+            assert!(calls(&binance).is_empty());
+        });
+    }
+
+    #[test]
+    fn test_market_buy_cost_option_reads_the_flag_value() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let options = PyDict::new(py);
+            assert!(!market_buy_requires_price_option(options));
+            for (value, expected) in [("True", true), ("False", false), ("None", false)] {
+                let options: &PyDict = py.eval(&format!("{{'createMarketBuyOrderRequiresPrice': {}}}", value), None, None)
+                    .unwrap().downcast().unwrap();
+                assert_eq!(market_buy_requires_price_option(options), expected, "{}", value);
+            }
+        });
+    }
+}
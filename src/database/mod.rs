@@ -117,6 +117,104 @@ impl MongoDB {
             Err(e) => log::debug!("   ℹ️  Index already exists: {}", e),
         }
         
+        // 🔒 TTL index: locks(expires_at) - remove locks órfãos automaticamente
+        let locks = self.database().collection::<mongodb::bson::Document>(crate::utils::lock::LOCKS_COLLECTION);
+
+        let locks_ttl_index = IndexModel::builder()
+            .keys(doc! { "expires_at": 1 })
+            .options(
+                mongodb::options::IndexOptions::builder()
+                    .expire_after(std::time::Duration::from_secs(0))
+                    .build()
+            )
+            .build();
+
+        match locks.create_index(locks_ttl_index).await {
+            Ok(_) => log::info!("   ✅ Index created: locks(expires_at) TTL"),
+            Err(e) => log::debug!("   ℹ️  Index already exists: {}", e),
+        }
+
+        // 🕯️ Unique index: ohlcv_cache(ccxt_id, symbol, timeframe, timestamp)
+        let ohlcv_cache = self.database().collection::<mongodb::bson::Document>(crate::services::ohlcv_cache_service::OHLCV_CACHE_COLLECTION);
+
+        let ohlcv_index = IndexModel::builder()
+            .keys(doc! { "ccxt_id": 1, "symbol": 1, "timeframe": 1, "timestamp": 1 })
+            .options(
+                mongodb::options::IndexOptions::builder()
+                    .unique(true)
+                    .build()
+            )
+            .build();
+
+        match ohlcv_cache.create_index(ohlcv_index).await {
+            Ok(_) => log::info!("   ✅ Index created: ohlcv_cache(ccxt_id, symbol, timeframe, timestamp)"),
+            Err(e) => log::debug!("   ℹ️  Index already exists: {}", e),
+        }
+
+        // ⏰ Index: tracked_orders(expires_at) — consultado pelo job de expiração
+        let tracked_orders = self.database().collection::<mongodb::bson::Document>(crate::services::order_service::TRACKED_ORDERS_COLLECTION);
+
+        let tracked_orders_index = IndexModel::builder()
+            .keys(doc! { "expires_at": 1 })
+            .build();
+
+        match tracked_orders.create_index(tracked_orders_index).await {
+            Ok(_) => log::info!("   ✅ Index created: tracked_orders(expires_at)"),
+            Err(e) => log::debug!("   ℹ️  Index already exists: {}", e),
+        }
+
+        // 🔑 Index: refresh_tokens(family_id) único — um refresh token ativo por sessão/dispositivo
+        let refresh_tokens = self.database().collection::<mongodb::bson::Document>(crate::services::auth_service::REFRESH_TOKENS_COLLECTION);
+
+        // O índice antigo (único por usuário) impediria mais de uma sessão
+        if refresh_tokens.drop_index("user_id_1").await.is_ok() {
+            log::info!("   🧹 Index dropped: refresh_tokens(user_id) unique");
+        }
+
+        let refresh_tokens_index = IndexModel::builder()
+            .keys(doc! { "family_id": 1 })
+            .options(
+                mongodb::options::IndexOptions::builder()
+                    .unique(true)
+                    .sparse(true)
+                    .build()
+            )
+            .build();
+
+        match refresh_tokens.create_index(refresh_tokens_index).await {
+            Ok(_) => log::info!("   ✅ Index created: refresh_tokens(family_id) unique"),
+            Err(e) => log::debug!("   ℹ️  Index already exists: {}", e),
+        }
+
+        // 📜 Index: strategy_config_history(user_id, strategy_id, changed_at)
+        let config_history = self.database().collection::<mongodb::bson::Document>(crate::services::strategy_history_service::STRATEGY_CONFIG_HISTORY_COLLECTION);
+
+        let config_history_index = IndexModel::builder()
+            .keys(doc! { "user_id": 1, "strategy_id": 1, "changed_at": -1 })
+            .build();
+
+        match config_history.create_index(config_history_index).await {
+            Ok(_) => log::info!("   ✅ Index created: strategy_config_history(user_id, strategy_id, changed_at)"),
+            Err(e) => log::debug!("   ℹ️  Index already exists: {}", e),
+        }
+
+        // ⚙️ Index: user_settings(user_id) único — um documento de preferências por usuário
+        let user_settings = self.database().collection::<mongodb::bson::Document>(crate::services::user_settings_service::USER_SETTINGS_COLLECTION);
+
+        let user_settings_index = IndexModel::builder()
+            .keys(doc! { "user_id": 1 })
+            .options(
+                mongodb::options::IndexOptions::builder()
+                    .unique(true)
+                    .build()
+            )
+            .build();
+
+        match user_settings.create_index(user_settings_index).await {
+            Ok(_) => log::info!("   ✅ Index created: user_settings(user_id) unique"),
+            Err(e) => log::debug!("   ℹ️  Index already exists: {}", e),
+        }
+
         log::info!("✅ Database indexes ready");
         
         Ok(())
@@ -3,3 +3,6 @@
 
 pub mod snapshot_scheduler;
 pub mod strategy_monitor;
+pub mod order_expiry;
+pub mod registry;
+pub mod strategy_archive;
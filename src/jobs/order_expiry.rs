@@ -0,0 +1,55 @@
+use crate::{database::MongoDB, jobs::registry::JOBS, services::order_service};
+use futures::FutureExt;
+use std::sync::Arc;
+use tokio::time::{interval, Duration};
+use std::env;
+
+pub const JOB_NAME: &str = "order_expiry";
+const DEFAULT_INTERVAL_SECS: u64 = 60;
+
+async fn run_order_expiry(db: &MongoDB) -> Result<(), String> {
+    let r = order_service::process_expired_orders(db).await?;
+    if !r.resolved.is_empty() || !r.errors.is_empty() {
+        log::info!("Order expiry: {} expired, {} errors", r.resolved.len(), r.errors.len());
+    }
+    for e in &r.errors {
+        log::warn!("⚠️ {}", e);
+    }
+    Ok(())
+}
+
+pub async fn start_order_expiry_job(db: MongoDB) {
+    let enabled = env::var("ORDER_EXPIRY_ENABLED").unwrap_or_else(|_| "true".to_string());
+    let enabled = enabled.to_lowercase() == "true" || enabled == "1";
+
+    let interval_secs: u64 = env::var("ORDER_EXPIRY_INTERVAL_SECS")
+        .ok().and_then(|s| s.parse().ok())
+        .unwrap_or(DEFAULT_INTERVAL_SECS).max(5);
+
+    let job_db = db.clone();
+    JOBS.register(JOB_NAME, enabled, interval_secs, Arc::new(move || {
+        let db = job_db.clone();
+        async move {
+            run_order_expiry(&db).await.map_err(|e| {
+                log::error!("Order expiry failed: {}", e);
+                e
+            })
+        }.boxed()
+    }));
+
+    if !enabled {
+        log::info!("Order expiry job DISABLED");
+        return;
+    }
+
+    log::info!("Starting order expiry job (interval: {}s)", interval_secs);
+
+    tokio::spawn(async move {
+        let mut tick_interval = interval(Duration::from_secs(interval_secs));
+
+        loop {
+            tick_interval.tick().await;
+            JOBS.run_scheduled(JOB_NAME).await;
+        }
+    });
+}
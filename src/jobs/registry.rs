@@ -0,0 +1,238 @@
+//! 🗂️ Registro dos jobs em background
+//!
+//! Cada job registra sua função de execução no início (`start_*`) e roda cada
+//! ciclo via `JOBS.run_scheduled`, que atualiza o estado compartilhado
+//! (última execução, próxima, contadores de sucesso/falha). A API de admin
+//! lê esse estado e pode disparar uma execução imediata com `JOBS.trigger`.
+//! Execuções do mesmo job nunca se sobrepõem: se já estiver rodando, o ciclo
+//! é pulado (agendado) ou recusado (manual).
+
+use futures::future::BoxFuture;
+use lazy_static::lazy_static;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+lazy_static! {
+    /// Registro global usado pelos jobs e pela API de admin
+    pub static ref JOBS: JobRegistry = JobRegistry::new();
+}
+
+pub type JobRunFn = Arc<dyn Fn() -> BoxFuture<'static, Result<(), String>> + Send + Sync>;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JobStatus {
+    pub name: String,
+    pub enabled: bool,
+    pub interval_secs: u64,
+    pub running: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_run_at: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_run_at: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_duration_ms: Option<u64>,
+    pub success_count: u64,
+    pub failure_count: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+struct JobEntry {
+    status: JobStatus,
+    run: JobRunFn,
+}
+
+pub struct JobRegistry {
+    jobs: Mutex<HashMap<String, JobEntry>>,
+}
+
+impl Default for JobRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl JobRegistry {
+    pub fn new() -> Self {
+        Self { jobs: Mutex::new(HashMap::new()) }
+    }
+
+    /// Registra (ou substitui) um job. Jobs desativados continuam disparáveis manualmente.
+    pub fn register(&self, name: &str, enabled: bool, interval_secs: u64, run: JobRunFn) {
+        let status = JobStatus {
+            name: name.to_string(),
+            enabled,
+            interval_secs,
+            running: false,
+            last_run_at: None,
+            next_run_at: None,
+            last_duration_ms: None,
+            success_count: 0,
+            failure_count: 0,
+            last_error: None,
+        };
+        let mut jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        jobs.insert(name.to_string(), JobEntry { status, run });
+    }
+
+    pub fn statuses(&self) -> Vec<JobStatus> {
+        let jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        let mut statuses: Vec<JobStatus> = jobs.values().map(|j| j.status.clone()).collect();
+        statuses.sort_by(|a, b| a.name.cmp(&b.name));
+        statuses
+    }
+
+    pub fn status(&self, name: &str) -> Option<JobStatus> {
+        let jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        jobs.get(name).map(|j| j.status.clone())
+    }
+
+    /// Marca o job como rodando e retorna a função de execução.
+    /// Erro se o job não existe ou já está rodando.
+    fn begin(&self, name: &str) -> Result<JobRunFn, String> {
+        let mut jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = jobs.get_mut(name).ok_or_else(|| format!("Job not found: {}", name))?;
+        if entry.status.running {
+            return Err(format!("Job {} is already running", name));
+        }
+        entry.status.running = true;
+        Ok(entry.run.clone())
+    }
+
+    fn finish(&self, name: &str, started_at: i64, duration_ms: u64, result: &Result<(), String>, scheduled: bool) {
+        let mut jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(entry) = jobs.get_mut(name) else { return };
+        let status = &mut entry.status;
+        status.running = false;
+        status.last_run_at = Some(started_at);
+        status.last_duration_ms = Some(duration_ms);
+        if scheduled {
+            status.next_run_at = Some(started_at + status.interval_secs as i64);
+        }
+        match result {
+            Ok(()) => {
+                status.success_count += 1;
+                status.last_error = None;
+            }
+            Err(e) => {
+                status.failure_count += 1;
+                status.last_error = Some(e.clone());
+            }
+        }
+    }
+
+    async fn execute(&self, name: &str, scheduled: bool) -> Result<JobStatus, String> {
+        let run = self.begin(name)?;
+        let mut guard = RunGuard {
+            registry: self, name, scheduled, finished: false,
+            started_at: chrono::Utc::now().timestamp(),
+            start: std::time::Instant::now(),
+        };
+        let result = run().await;
+        guard.finish(&result);
+        self.status(name).ok_or_else(|| format!("Job not found: {}", name))
+    }
+
+    /// Execução de um ciclo do próprio job (atualiza também `next_run_at`)
+    pub async fn run_scheduled(&self, name: &str) {
+        if let Err(e) = self.execute(name, true).await {
+            log::warn!("⚠️ Skipping scheduled run: {}", e);
+        }
+    }
+
+    /// Disparo manual (API de admin)
+    pub async fn trigger(&self, name: &str) -> Result<JobStatus, String> {
+        log::info!("▶️ Manual trigger for job {}", name);
+        self.execute(name, false).await
+    }
+}
+
+/// Libera o `running` do job mesmo se a execução entrar em pânico ou o future for
+/// cancelado no meio — senão o job ficaria "rodando" para sempre e nunca mais executaria
+struct RunGuard<'a> {
+    registry: &'a JobRegistry,
+    name: &'a str,
+    scheduled: bool,
+    started_at: i64,
+    start: std::time::Instant,
+    finished: bool,
+}
+
+impl RunGuard<'_> {
+    fn finish(&mut self, result: &Result<(), String>) {
+        self.finished = true;
+        self.registry.finish(self.name, self.started_at, self.start.elapsed().as_millis() as u64, result, self.scheduled);
+    }
+}
+
+impl Drop for RunGuard<'_> {
+    fn drop(&mut self) {
+        if !self.finished {
+            log::error!("❌ Job {} did not finish (panicked or cancelled)", self.name);
+            self.finish(&Err("Job panicked or was cancelled".to_string()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::FutureExt;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn test_trigger_invokes_run_and_updates_last_run() {
+        let registry = JobRegistry::new();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        registry.register("snapshot_scheduler", true, 3600, Arc::new(move || {
+            let counter = counter.clone();
+            async move {
+                counter.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            }.boxed()
+        }));
+        registry.register("failing", false, 60, Arc::new(|| async { Err("boom".to_string()) }.boxed()));
+
+        assert!(registry.status("snapshot_scheduler").unwrap().last_run_at.is_none());
+
+        let status = registry.trigger("snapshot_scheduler").await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(status.last_run_at.is_some());
+        assert_eq!(status.success_count, 1);
+        assert!(!status.running);
+        // Disparo manual não mexe no agendamento
+        assert!(status.next_run_at.is_none());
+
+        registry.run_scheduled("snapshot_scheduler").await;
+        let status = registry.status("snapshot_scheduler").unwrap();
+        assert_eq!(status.next_run_at, Some(status.last_run_at.unwrap() + 3600));
+
+        let failed = registry.trigger("failing").await.unwrap();
+        assert_eq!((failed.failure_count, failed.last_error.as_deref()), (1, Some("boom")));
+        assert!(registry.trigger("unknown").await.is_err());
+        assert_eq!(registry.statuses().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_panicking_or_cancelled_run_releases_running_flag() {
+        let registry: &'static JobRegistry = Box::leak(Box::new(JobRegistry::new()));
+        registry.register("panics", true, 60, Arc::new(|| async { panic!("boom") }.boxed()));
+        registry.register("slow", true, 60, Arc::new(|| async {
+            tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+            Ok(())
+        }.boxed()));
+
+        assert!(tokio::spawn(registry.trigger("panics")).await.is_err());
+        let status = registry.status("panics").unwrap();
+        assert!(!status.running);
+        assert_eq!(status.failure_count, 1);
+
+        let cancelled = tokio::time::timeout(std::time::Duration::from_millis(10), registry.trigger("slow")).await;
+        assert!(cancelled.is_err());
+        assert!(!registry.status("slow").unwrap().running);
+        // E o job volta a poder rodar
+        assert!(registry.begin("slow").is_ok());
+    }
+}
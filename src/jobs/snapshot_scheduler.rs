@@ -6,13 +6,28 @@ use crate::{
     database::MongoDB,
     services::balance_service,
     services::exchange_rate_service,
+    services::user_settings_service,
     utils::crypto,
+    utils::lock,
+    jobs::registry::JOBS,
 };
+use futures::FutureExt;
 use mongodb::bson::doc;
+use std::sync::Arc;
 use tokio::time::{interval, Duration};
 use chrono::{Utc, Timelike};
 use std::env;
 
+pub const JOB_NAME: &str = "snapshot_scheduler";
+const SNAPSHOT_INTERVAL_SECS: u64 = 3600;
+
+/// TTL do lock de snapshot — cobre o fetch de balances com folga;
+/// se a instância cair, outra assume depois que o lock expira
+const SNAPSHOT_LOCK_TTL_SECS: i64 = 300;
+
+/// Tentativas de salvar o snapshot (o save é idempotente, então retry é seguro)
+const SNAPSHOT_MAX_ATTEMPTS: u32 = 3;
+
 /// Inicia o scheduler de snapshots diários
 /// Roda a cada hora e garante que existe snapshot do dia para todos os usuários.
 /// Se o servidor reiniciar ou perder algum dia, o snapshot é criado no próximo tick.
@@ -20,40 +35,38 @@ use std::env;
 pub async fn start_daily_snapshot_scheduler(db: MongoDB) {
     log::info!("📅 Starting daily snapshot scheduler (runs every hour, saves once per day)");
     
+    JOBS.register(JOB_NAME, true, SNAPSHOT_INTERVAL_SECS, Arc::new(move || {
+        let db = db.clone();
+        async move {
+            match save_all_user_snapshots(&db).await {
+                Ok(count) => {
+                    log::info!("✅ Snapshot check completed: {} users processed", count);
+                    Ok(())
+                }
+                Err(e) => {
+                    log::error!("❌ Snapshot check failed: {}", e);
+                    Err(e)
+                }
+            }
+        }.boxed()
+    }));
+    
     // Spawn task em background
     tokio::spawn(async move {
         // 🔥 EXECUTA IMEDIATAMENTE na inicialização para garantir snapshot de hoje
         log::info!("🚀 Running initial snapshot check on startup...");
-        match save_all_user_snapshots(&db).await {
-            Ok(count) => {
-                log::info!("✅ Startup snapshot check completed: {} users processed", count);
-            }
-            Err(e) => {
-                log::error!("❌ Startup snapshot check failed: {}", e);
-            }
-        }
+        JOBS.run_scheduled(JOB_NAME).await;
         
         // Depois roda a cada hora
-        let mut interval = interval(Duration::from_secs(3600)); // 1 hora
+        let mut interval = interval(Duration::from_secs(SNAPSHOT_INTERVAL_SECS));
         
         loop {
             interval.tick().await;
             
-            let now = Utc::now();
-            let hour = now.hour();
-            
             // Executa a cada hora — save_user_snapshot já faz skip se já existe snapshot de hoje
             // Preferimos rodar nas primeiras horas do dia UTC mas não falhamos se perder
-            log::debug!("⏰ Hourly snapshot check ({}:00 UTC)...", hour);
-            
-            match save_all_user_snapshots(&db).await {
-                Ok(count) => {
-                    log::debug!("✅ Hourly snapshot check: {} users processed", count);
-                }
-                Err(e) => {
-                    log::error!("❌ Hourly snapshot check failed: {}", e);
-                }
-            }
+            log::debug!("⏰ Hourly snapshot check ({}:00 UTC)...", Utc::now().hour());
+            JOBS.run_scheduled(JOB_NAME).await;
         }
     });
     
@@ -121,9 +134,48 @@ async fn save_all_user_snapshots(db: &MongoDB) -> Result<usize, String> {
 }
 
 /// Salva snapshot diário para um usuário específico
+///
+/// A data do snapshot segue o fuso configurado em `user_settings` (UTC por padrão).
+/// Protegido por lock `(user_id, date)`: com várias instâncias rodando o scheduler,
+/// só uma faz o fetch de balances e grava o snapshot do dia. As demais fazem skip.
 async fn save_user_snapshot(db: &MongoDB, user_id: &str) -> Result<(), String> {
-    let today = Utc::now().format("%Y-%m-%d").to_string();
+    let settings = user_settings_service::get_user_settings(db, user_id).await?;
+    let today = settings.local_date(Utc::now());
+    let lock_key = format!("snapshot:{}:{}", user_id, today);
     
+    // ── Guard: mesma instância já está salvando este snapshot ──────
+    let Some(_local_guard) = lock::try_local_lock(&lock_key) else {
+        log::debug!("    🔒 Snapshot for {} already in progress on this instance, skipping", user_id);
+        return Ok(());
+    };
+    
+    // ── Guard: outra instância detém o lock ──────
+    let outcome = lock::with_lock(db, &lock_key, &lock::INSTANCE_ID, SNAPSHOT_LOCK_TTL_SECS, || async {
+        let mut result = Err("No attempts made".to_string());
+        for attempt in 1..=SNAPSHOT_MAX_ATTEMPTS {
+            result = write_user_snapshot(db, user_id, &today).await;
+            match &result {
+                Ok(_) => break,
+                Err(e) if attempt < SNAPSHOT_MAX_ATTEMPTS => {
+                    let backoff = Duration::from_millis(500 * 2u64.pow(attempt - 1));
+                    log::warn!("    ⚠️  Snapshot attempt {}/{} failed for {}: {} (retrying in {:?})",
+                        attempt, SNAPSHOT_MAX_ATTEMPTS, user_id, e, backoff);
+                    tokio::time::sleep(backoff).await;
+                }
+                Err(_) => {}
+            }
+        }
+        result
+    }).await?;
+    
+    outcome.unwrap_or_else(|| {
+        log::debug!("    🔒 Snapshot for {} already in progress on another instance, skipping", user_id);
+        Ok(())
+    })
+}
+
+/// Calcula e grava o snapshot do dia (idempotente: nunca duplica a data)
+async fn write_user_snapshot(db: &MongoDB, user_id: &str, today: &str) -> Result<(), String> {
     // 0. Obter chave de criptografia
     let encryption_key = env::var("ENCRYPTION_KEY")
         .map_err(|_| "ENCRYPTION_KEY not found in environment".to_string())?;
@@ -132,30 +184,49 @@ async fn save_user_snapshot(db: &MongoDB, user_id: &str) -> Result<(), String> {
     
     // 1. Buscar documento do usuário
     let filter = doc! { "user_id": user_id };
-    let user_doc = snapshots_collection.find_one(filter.clone()).await
+    let user_doc = snapshots_collection.find_one(filter).await
         .map_err(|e| format!("Failed to query snapshots: {}", e))?;
     
-    // 2. Verificar se já existe snapshot para hoje
-    if let Some(doc) = &user_doc {
-        if let Ok(snapshots) = doc.get_array("snapshots") {
-            for snapshot in snapshots {
-                if let Some(snap_doc) = snapshot.as_document() {
-                    if let Ok(date) = snap_doc.get_str("date") {
-                        if date == today {
-                            log::debug!("    ℹ️  Snapshot already exists for user {} today ({}), skipping", user_id, today);
-                            return Ok(());
-                        }
-                    }
-                }
-            }
+    // 2. Verificar se já existe snapshot para hoje (parcial é refeito até ficar completo)
+    let today_snapshot = user_doc.as_ref()
+        .and_then(|doc| doc.get_array("snapshots").ok())
+        .and_then(|snapshots| snapshots.iter()
+            .filter_map(|s| s.as_document())
+            .find(|s| s.get_str("date").ok() == Some(today)));
+    let prior = match today_snapshot {
+        Some(snap) if !snap.get_bool("partial").unwrap_or(false) => {
+            log::debug!("    ℹ️  Snapshot already exists for user {} today ({}), skipping", user_id, today);
+            return Ok(());
         }
-    }
+        Some(snap) => snap.get_str("total_usd").ok()
+            .and_then(|enc| crypto::decrypt_fernet_via_python(enc, &encryption_key).ok())
+            .and_then(|v| v.parse::<f64>().ok())
+            .map(|total_usd| balance_service::PriorSnapshot { total_usd, partial: true }),
+        None => None,
+    };
     
     // 3. Buscar balance atual do usuário
     let balance_response = balance_service::get_user_balances(db, user_id).await
         .map_err(|e| format!("Failed to get balance: {}", e))?;
+    let partial = balance_response.partial;
+    if partial && today_snapshot.is_some() {
+        log::debug!("    ℹ️  Balance still partial for user {}, keeping today's partial snapshot", user_id);
+        return Ok(());
+    }
     
-    let total_usd = balance_response.total_usd;
+    let total_usd = match balance_service::guard_snapshot_total(
+        balance_response.total_usd, partial, prior,
+        balance_service::snapshot_max_partial_drop_percent(), balance_service::snapshot_total_decimals(),
+    ) {
+        balance_service::SnapshotWrite::Save { total_usd, .. } => total_usd,
+        balance_service::SnapshotWrite::KeepPrevious { previous_usd, drop_percent } => {
+            log::warn!("    🛡️  Keeping snapshot ${:.2} for {}: partial total is {:.1}% lower", previous_usd, user_id, drop_percent);
+            return Ok(());
+        }
+    };
+    if partial {
+        log::warn!("    ⚠️  Saving partial snapshot for {}: {} exchange(s) failed", user_id, balance_response.errors.len());
+    }
     
     // 4. Converter USD para BRL
     let total_brl = match exchange_rate_service::get_exchange_rate("USD", "BRL").await {
@@ -198,16 +269,31 @@ async fn save_user_snapshot(db: &MongoDB, user_id: &str) -> Result<(), String> {
     
     // 7. Criar novo snapshot
     let new_snapshot = doc! {
-        "date": &today,
+        "date": today,
         "total_usd": encrypted_total_usd,
         "total_brl": encrypted_total_brl,
         "timestamp": Utc::now().timestamp_millis(),
         "exchanges": exchanges_details,
+        "partial": partial,
     };
     
     // 8. Atualizar ou criar documento do usuário
-    if user_doc.is_some() {
+    if today_snapshot.is_some() {
+        // Snapshot parcial de hoje substituído pelo completo
+        snapshots_collection
+            .update_one(
+                doc! { "user_id": user_id },
+                doc! { "$set": { "snapshots.$[snap]": new_snapshot, "updated_at": mongodb::bson::DateTime::now() } },
+            )
+            .array_filters(vec![doc! { "snap.date": today }])
+            .await
+            .map_err(|e| format!("Failed to replace partial snapshot: {}", e))?;
+        
+        log::debug!("    💾 Partial snapshot replaced: ${:.2} USD (R$ {:.2} BRL)", total_usd, total_brl);
+    } else if user_doc.is_some() {
         // Usuário já tem documento: adiciona snapshot ao array
+        // O filtro por data torna o $push idempotente em caso de retry
+        let push_filter = doc! { "user_id": user_id, "snapshots.date": { "$ne": today } };
         let update = doc! {
             "$push": {
                 "snapshots": new_snapshot
@@ -218,7 +304,7 @@ async fn save_user_snapshot(db: &MongoDB, user_id: &str) -> Result<(), String> {
         };
         
         snapshots_collection
-            .update_one(filter, update)
+            .update_one(push_filter, update)
             .await
             .map_err(|e| format!("Failed to update snapshots: {}", e))?;
         
@@ -0,0 +1,60 @@
+use crate::{database::MongoDB, jobs::registry::JOBS, services::strategy_service};
+use futures::FutureExt;
+use std::sync::Arc;
+use tokio::time::{interval, Duration};
+use std::env;
+
+pub const JOB_NAME: &str = "strategy_archive";
+const DEFAULT_INTERVAL_SECS: u64 = 86_400;
+const DEFAULT_ARCHIVE_AFTER_DAYS: i64 = 30;
+
+/// Dias desde o encerramento até a estratégia ir para o arquivo (`STRATEGY_ARCHIVE_AFTER_DAYS`)
+pub fn archive_after_days() -> i64 {
+    env::var("STRATEGY_ARCHIVE_AFTER_DAYS")
+        .ok().and_then(|s| s.parse().ok())
+        .unwrap_or(DEFAULT_ARCHIVE_AFTER_DAYS).max(0)
+}
+
+async fn run_strategy_archive(db: &MongoDB) -> Result<(), String> {
+    let r = strategy_service::archive_completed_strategies(db, None, archive_after_days()).await?;
+    if r.archived > 0 {
+        log::info!("Strategy archive: {} strategies archived for {} users", r.archived, r.users);
+    }
+    Ok(())
+}
+
+pub async fn start_strategy_archive_job(db: MongoDB) {
+    let enabled = env::var("STRATEGY_ARCHIVE_ENABLED").unwrap_or_else(|_| "true".to_string());
+    let enabled = enabled.to_lowercase() == "true" || enabled == "1";
+
+    let interval_secs: u64 = env::var("STRATEGY_ARCHIVE_INTERVAL_SECS")
+        .ok().and_then(|s| s.parse().ok())
+        .unwrap_or(DEFAULT_INTERVAL_SECS).max(60);
+
+    let job_db = db.clone();
+    JOBS.register(JOB_NAME, enabled, interval_secs, Arc::new(move || {
+        let db = job_db.clone();
+        async move {
+            run_strategy_archive(&db).await.map_err(|e| {
+                log::error!("Strategy archive failed: {}", e);
+                e
+            })
+        }.boxed()
+    }));
+
+    if !enabled {
+        log::info!("Strategy archive job DISABLED");
+        return;
+    }
+
+    log::info!("Starting strategy archive job (interval: {}s, after {} days)", interval_secs, archive_after_days());
+
+    tokio::spawn(async move {
+        let mut tick_interval = interval(Duration::from_secs(interval_secs));
+
+        loop {
+            tick_interval.tick().await;
+            JOBS.run_scheduled(JOB_NAME).await;
+        }
+    });
+}
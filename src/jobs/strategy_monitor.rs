@@ -1,45 +1,77 @@
-use crate::{database::MongoDB, services::strategy_service};
+use crate::{database::MongoDB, jobs::registry::JOBS, services::strategy_service};
+use futures::FutureExt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use tokio::time::{interval, Duration};
 use std::env;
 
+pub const JOB_NAME: &str = "strategy_monitor";
 const DEFAULT_INTERVAL_SECS: u64 = 30;
 
+async fn run_monitor_cycle(db: &MongoDB, cycle: u64) -> Result<(), String> {
+    let start = std::time::Instant::now();
+
+    // 🧹 Ordens pendentes abandonadas (também de estratégias que não fazem mais tick)
+    match strategy_service::reap_abandoned_orders(db).await {
+        Ok(r) => {
+            if r.resolved > 0 || !r.errors.is_empty() {
+                log::info!("Monitor #{}: {} stale orders checked, {} resolved", cycle, r.checked, r.resolved);
+            }
+            for e in &r.errors {
+                log::warn!("⚠️ Order reaper: {}", e);
+            }
+        }
+        Err(e) => log::error!("Monitor #{} order reaper failed: {}", cycle, e),
+    }
+
+    match strategy_service::process_active_strategies(db).await {
+        Ok(r) => {
+            if r.processed > 0 || r.errors > 0 || r.skipped_unhealthy > 0 || cycle % 10 == 0 {
+                log::info!(
+                    "Monitor #{}: {} total, {} processed, {} errors, {} skipped (unhealthy exchange) ({:.0}ms)",
+                    cycle, r.total, r.processed, r.errors, r.skipped_unhealthy, start.elapsed().as_millis()
+                );
+            }
+            Ok(())
+        }
+        Err(e) => {
+            log::error!("Monitor #{} failed: {}", cycle, e);
+            Err(e)
+        }
+    }
+}
+
 pub async fn start_strategy_monitor(db: MongoDB) {
     let enabled = env::var("STRATEGY_MONITOR_ENABLED").unwrap_or_else(|_| "true".to_string());
-    if enabled.to_lowercase() != "true" && enabled != "1" {
-        log::info!("Strategy monitor DISABLED");
-        return;
-    }
+    let enabled = enabled.to_lowercase() == "true" || enabled == "1";
 
     let interval_secs: u64 = env::var("STRATEGY_MONITOR_INTERVAL_SECS")
         .ok().and_then(|s| s.parse().ok())
         .unwrap_or(DEFAULT_INTERVAL_SECS).max(5);
 
+    let cycles = Arc::new(AtomicU64::new(0));
+    JOBS.register(JOB_NAME, enabled, interval_secs, Arc::new(move || {
+        let db = db.clone();
+        let cycle = cycles.fetch_add(1, Ordering::SeqCst) + 1;
+        async move { run_monitor_cycle(&db, cycle).await }.boxed()
+    }));
+
+    if !enabled {
+        log::info!("Strategy monitor DISABLED");
+        return;
+    }
+
     log::info!("Starting strategy monitor (interval: {}s)", interval_secs);
 
     tokio::spawn(async move {
         tokio::time::sleep(Duration::from_secs(10)).await;
         let mut tick_interval = interval(Duration::from_secs(interval_secs));
-        let mut cycle: u64 = 0;
 
         loop {
             tick_interval.tick().await;
-            cycle += 1;
-            let start = std::time::Instant::now();
-
-            match strategy_service::process_active_strategies(&db).await {
-                Ok(r) => {
-                    if r.processed > 0 || r.errors > 0 || cycle % 10 == 0 {
-                        log::info!(
-                            "Monitor #{}: {} total, {} processed, {} errors ({:.0}ms)",
-                            cycle, r.total, r.processed, r.errors, start.elapsed().as_millis()
-                        );
-                    }
-                }
-                Err(e) => {
-                    log::error!("Monitor #{} failed: {}", cycle, e);
-                    tokio::time::sleep(Duration::from_secs(5)).await;
-                }
+            JOBS.run_scheduled(JOB_NAME).await;
+            if JOBS.status(JOB_NAME).and_then(|s| s.last_error).is_some() {
+                tokio::time::sleep(Duration::from_secs(5)).await;
             }
         }
     });
@@ -21,16 +21,16 @@ async fn main() -> std::io::Result<()> {
     dotenv().ok();
     
     // Initialize logger
-    env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
+    utils::log_level::init("info");
     
     // Get configuration from environment
-    let host = env::var("HOST").unwrap_or_else(|_| "0.0.0.0".to_string());
-    let port = env::var("PORT").unwrap_or_else(|_| "3002".to_string());
+    let host = env::var("HOST").unwrap_or_else(|_| utils::runtime_config::DEFAULT_HOST.to_string());
+    let port = env::var("PORT").unwrap_or_else(|_| utils::runtime_config::DEFAULT_PORT.to_string());
     let database_url = env::var("DATABASE_URL")
         .expect("DATABASE_URL must be set");
     
     log::info!("🚀 Starting Trading Service...");
-    log::info!("📊 Database: {}", database_url);
+    log::info!("📊 Database: {}", utils::runtime_config::mask_url_credentials(&database_url));
     
     // 🚀 FASE 3: Pré-aquecer Python GIL e CCXT
     log::info!("🐍 Pre-warming Python GIL and CCXT...");
@@ -72,15 +72,26 @@ async fn main() -> std::io::Result<()> {
     
     // 🎯 Start strategy monitor (Fase 4)
     jobs::strategy_monitor::start_strategy_monitor(db.clone()).await;
+
+    // ⏰ Expiração server-side de ordens limit (GTD)
+    jobs::order_expiry::start_order_expiry_job(db.clone()).await;
+
+    // 🗄️ Arquivamento de estratégias concluídas
+    jobs::strategy_archive::start_strategy_archive_job(db.clone()).await;
     
     log::info!("✅ Background jobs started");
     
     log::info!("🌐 Server starting on {}:{}", host, port);
     log::info!("📚 Swagger UI available at: http://{}:{}/swagger-ui/", host, port);
     log::info!("📄 OpenAPI spec at: http://{}:{}/api-docs/openapi.json", host, port);
+
+    let server_config = utils::server_config::ServerConfig::from_env();
+    log::info!("⚙️ HTTP server: workers={}, keep-alive={:?}, client timeout={:?}",
+        server_config.workers.map(|w| w.to_string()).unwrap_or_else(|| "default".to_string()),
+        server_config.keep_alive, server_config.client_timeout);
     
     // Start HTTP server
-    HttpServer::new(move || {
+    let server = HttpServer::new(move || {
         let cors = Cors::default()
             .allowed_origin("http://localhost:3000") // Frontend Web (Expo)
             .allowed_origin("http://localhost:8081")
@@ -95,6 +106,7 @@ async fn main() -> std::io::Result<()> {
                 actix_web::http::header::ACCEPT,
                 actix_web::http::header::CACHE_CONTROL,
                 actix_web::http::header::PRAGMA,
+                actix_web::http::header::HeaderName::from_static("x-api-key"),
             ])
             .expose_headers(vec![
                 actix_web::http::header::CONTENT_TYPE,
@@ -107,9 +119,10 @@ async fn main() -> std::io::Result<()> {
         
         App::new()
             .app_data(db_data.clone())
+            .wrap(actix_web::middleware::Compress::default())
             .wrap(cors)
             .wrap(middleware::SecurityHeaders)
-            .wrap(Logger::default())
+            .wrap(middleware::request_log::request_logger())
             .wrap(Logger::new("%a %{User-Agent}i"))
             // Swagger UI with authentication
             .service(
@@ -131,6 +144,18 @@ async fn main() -> std::io::Result<()> {
                     .route("/verify", web::get().to(api::auth::verify_token))
                     .route("/me", web::get().to(api::auth::get_me))
                     .route("/delete-account", web::delete().to(api::auth::delete_account))
+                    .route("/export", web::get().to(api::auth::export_data))
+                    .service(
+                        web::resource("/api-keys")
+                            .wrap(middleware::auth::AuthMiddleware)
+                            .route(web::get().to(api::auth::list_api_keys))
+                            .route(web::post().to(api::auth::create_api_key))
+                    )
+                    .service(
+                        web::resource("/api-keys/{key_id}")
+                            .wrap(middleware::auth::AuthMiddleware)
+                            .route(web::delete().to(api::auth::revoke_api_key))
+                    )
             )
             
             // ==================== CATALOG DATA (MongoDB) ====================
@@ -139,6 +164,7 @@ async fn main() -> std::io::Result<()> {
             .service(
                 web::scope("/api/v1/exchanges")
                     .route("/available", web::get().to(api::exchanges::get_available_exchanges))
+                    .route("/{ccxt_id}/timeframes", web::get().to(api::exchanges::get_exchange_timeframes))
                     .route("/{exchange_id}/token/{symbol}", web::get().to(api::exchanges::get_token_details))
             )
             
@@ -152,9 +178,29 @@ async fn main() -> std::io::Result<()> {
                     .route("/search", web::post().to(api::tokens::post_token_search))  // Local-first: receives credentials
                     .route("/details", web::post().to(api::tokens::get_token_details_with_creds))  // Zero Database: receives credentials
                     .route("/details/multi", web::post().to(api::tokens::get_token_details_multi))  // Multi-exchange comparison
+                    .route("/details/batch", web::post().to(api::tokens::get_token_details_batch))  // Vários símbolos, uma exchange
+                    .route("/funding", web::post().to(api::tokens::get_funding_rates))  // Funding rates (perpétuos)
+                    .route("/ohlcv", web::post().to(api::tokens::get_ohlcv))  // Candles para gráficos
                     .route("/{symbol}", web::get().to(api::tokens::get_token))  // DEVE FICAR POR ÚLTIMO (catch-all)
             )
             
+            // Admin: operações manuais (requer JWT com role "admin")
+            .service(
+                web::scope("/api/v1/admin")
+                    .wrap(middleware::auth::AuthMiddleware)
+                    .route("/tokens/refresh/{ccxt_id}", web::post().to(api::tokens::refresh_tokens_cache))
+                    .route("/health", web::get().to(api::admin::admin_health))
+                    .route("/config", web::get().to(api::admin::get_runtime_config))
+                    .route("/jobs", web::get().to(api::admin::list_jobs))
+                    .route("/jobs/{name}/trigger", web::post().to(api::admin::trigger_job))
+                    .route("/log-level", web::get().to(api::admin::get_log_levels))
+                    .route("/log-level", web::put().to(api::admin::set_log_level))
+                    .route("/trading-enabled", web::get().to(api::admin::get_trading_enabled))
+                    .route("/trading-enabled", web::put().to(api::admin::set_trading_enabled))
+                    .route("/exchanges/{ccxt_id}/pause-strategies", web::post().to(api::admin::pause_exchange_strategies))
+                    .route("/exchanges/{ccxt_id}/resume-strategies", web::post().to(api::admin::resume_exchange_strategies))
+            )
+            
             // ==================== CCXT REAL-TIME DATA ====================
             
             // User Exchanges: Manage connected exchanges (CRUD) - Requires JWT
@@ -163,8 +209,35 @@ async fn main() -> std::io::Result<()> {
                     .wrap(middleware::auth::AuthMiddleware)
                     .route("", web::post().to(api::user_exchanges::add_exchange))
                     .route("", web::get().to(api::user_exchanges::list_exchanges))
+                    .route("/overview", web::get().to(api::user_exchanges::exchanges_overview))
                     .route("/{exchange_id}", web::patch().to(api::user_exchanges::update_exchange))
                     .route("/{exchange_id}", web::delete().to(api::user_exchanges::delete_exchange))
+                    .route("/{exchange_id}/reactivate", web::post().to(api::user_exchanges::reactivate_exchange))
+                    .route("/{exchange_id}/fees", web::get().to(api::user_exchanges::get_exchange_fees))
+            )
+            
+            // User Settings: Preferências (fuso, quote padrão, webhook, poeira, exposição) - Requires JWT
+            .service(
+                web::scope("/api/v1/user/settings")
+                    .wrap(middleware::auth::AuthMiddleware)
+                    .route("", web::get().to(api::user_settings::get_settings))
+                    .route("", web::put().to(api::user_settings::update_settings))
+            )
+            
+            // User Watchlist: Símbolos favoritos sincronizados entre dispositivos - Requires JWT
+            .service(
+                web::scope("/api/v1/user/watchlist")
+                    .wrap(middleware::auth::AuthMiddleware)
+                    .route("", web::get().to(api::user_settings::get_watchlist))
+                    .route("", web::post().to(api::user_settings::add_watchlist_symbol))
+                    .route("", web::delete().to(api::user_settings::remove_watchlist_symbol))
+            )
+            
+            // Portfolio: Visão consolidada das posições das estratégias - Requires JWT
+            .service(
+                web::scope("/api/v1/portfolio")
+                    .wrap(middleware::auth::AuthMiddleware)
+                    .route("/asset/{symbol}/position", web::get().to(api::portfolio::get_asset_position))
             )
             
             // Snapshots: Daily balance snapshots for PNL calculation
@@ -175,18 +248,35 @@ async fn main() -> std::io::Result<()> {
                     .route("", web::get().to(api::snapshots::get_snapshots))
             )
             
+            // Strategies: SSE events (ticket via query — EventSource não envia headers)
+            .service(
+                web::resource("/api/v1/strategies/events/ticket")
+                    .wrap(middleware::auth::AuthMiddleware)
+                    .route(web::post().to(api::strategies::create_events_ticket))
+            )
+            .route("/api/v1/strategies/events", web::get().to(api::strategies::strategy_events))
+
             // Strategies: Trading strategies management
             .service(
                 web::scope("/api/v1/strategies")
                     .wrap(middleware::auth::AuthMiddleware)
                     .service(api::strategies::get_strategies)
+                    .service(api::strategies::get_pnl_summary)
+                    .service(api::strategies::get_strategies_summary)
                     .service(api::strategies::get_strategy_stats)
                     .service(api::strategies::get_strategy_executions)
                     .service(api::strategies::get_strategy_signals)
+                    .service(api::strategies::get_strategy_history)
+                    .service(api::strategies::get_strategy_what_if)
+                    .service(api::strategies::get_strategy_replay)
+                    .service(api::strategies::export_strategy)
+                    .service(api::strategies::import_strategy)
                     .service(api::strategies::activate_strategy)
+                    .service(api::strategies::confirm_live_strategy)
                     .service(api::strategies::pause_strategy)
                     .service(api::strategies::tick_strategy)
                     .service(api::strategies::process_all_strategies)
+                    .service(api::strategies::archive_completed_strategies)
                     .service(api::strategies::get_strategy)
                     .service(api::strategies::create_strategy)
                     .service(api::strategies::update_strategy)
@@ -219,6 +309,21 @@ async fn main() -> std::io::Result<()> {
                             .wrap(middleware::auth::AuthMiddleware)
                             .route(web::post().to(api::balances::post_balances_secure))
                     )
+                    .service(
+                        web::resource("/asset/{symbol}")
+                            .wrap(middleware::auth::AuthMiddleware)
+                            .route(web::get().to(api::balances::get_asset_balance))
+                    )
+                    .service(
+                        web::resource("/sweep")
+                            .wrap(middleware::auth::AuthMiddleware)
+                            .route(web::post().to(api::balances::sweep_dust))
+                    )
+                    .service(
+                        web::resource("/daily-pnl")
+                            .wrap(middleware::auth::AuthMiddleware)
+                            .route(web::get().to(api::balances::get_daily_pnl))
+                    )
             )
             
             // ==================== ORDERS API ====================
@@ -229,16 +334,21 @@ async fn main() -> std::io::Result<()> {
                     .wrap(middleware::auth::AuthMiddleware)
                     // 📊 Fetch orders from user's exchanges
                     .route("/fetch/secure", web::post().to(api::orders::fetch_orders_secure))
+                    // 🌐 Open orders from all exchanges in one list
+                    .route("/open/all", web::get().to(api::orders::fetch_all_open_orders))
                     // ➕ Create new order
                     .route("/create", web::post().to(api::orders::create_order_secure))
                     // ❌ Cancel existing order
                     .route("/cancel", web::post().to(api::orders::cancel_order_secure))
+                    // 🧹 Cancel all open orders (optionally for one symbol)
+                    .route("/cancel-all", web::post().to(api::orders::cancel_all_orders_secure))
             )
             
             // Tickers: Real-time prices via CCXT
             .service(
                 web::scope("/api/v1/tickers")
                     .route("", web::get().to(api::tickers::get_tickers))
+                    .route("/watch", web::post().to(api::tickers::watch_tickers))  // Watchlist: receives credentials
             )
             
             // ==================== EXTERNAL APIs ====================
@@ -259,8 +369,17 @@ async fn main() -> std::io::Result<()> {
                     .route("/rates", web::get().to(api::external::get_all_rates))
             )
     })
-    .bind(format!("{}:{}", host, port))?
-    .run()
-    .await
+    .keep_alive(server_config.keep_alive)
+    .client_request_timeout(server_config.client_timeout);
+
+    let server = match server_config.workers {
+        Some(workers) => server.workers(workers),
+        None => server,
+    };
+
+    server
+        .bind(format!("{}:{}", host, port))?
+        .run()
+        .await
 }
 
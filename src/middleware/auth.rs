@@ -1,19 +1,39 @@
 use actix_web::{
     dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
-    Error, HttpMessage,
+    web, Error, HttpMessage,
 };
 use futures::future::LocalBoxFuture;
 use std::future::{ready, Ready};
+use std::rc::Rc;
 use serde::{Deserialize, Serialize};
 
 // Re-export Claims from auth_service to keep consistency
 pub use crate::services::auth_service::Claims;
+use crate::{database::MongoDB, services::api_key_service::{self, ApiKeyRejection}};
 
 pub struct AuthMiddleware;
 
+/// Resposta HTTP para a recusa de um `X-API-Key` (401, 403 ou 500)
+fn api_key_error(rejection: ApiKeyRejection) -> Error {
+    match rejection {
+        ApiKeyRejection::Unauthorized(e) => {
+            log::warn!("🔒 API key rejected: {}", e);
+            actix_web::error::ErrorUnauthorized(e)
+        }
+        ApiKeyRejection::Forbidden(e) => {
+            log::warn!("🔒 API key out of scope: {}", e);
+            actix_web::error::ErrorForbidden(e)
+        }
+        ApiKeyRejection::Internal(e) => {
+            log::error!("❌ API key lookup failed: {}", e);
+            actix_web::error::ErrorInternalServerError("Failed to verify API key")
+        }
+    }
+}
+
 impl<S, B> Transform<S, ServiceRequest> for AuthMiddleware
 where
-    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
     S::Future: 'static,
     B: 'static,
 {
@@ -24,17 +44,17 @@ where
     type Future = Ready<Result<Self::Transform, Self::InitError>>;
 
     fn new_transform(&self, service: S) -> Self::Future {
-        ready(Ok(AuthMiddlewareService { service }))
+        ready(Ok(AuthMiddlewareService { service: Rc::new(service) }))
     }
 }
 
 pub struct AuthMiddlewareService<S> {
-    service: S,
+    service: Rc<S>,
 }
 
 impl<S, B> Service<ServiceRequest> for AuthMiddlewareService<S>
 where
-    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
     S::Future: 'static,
     B: 'static,
 {
@@ -56,6 +76,23 @@ where
             });
         }
         
+        // API key (acesso programático): resolve o dono no banco e aplica o escopo
+        if let Some(api_key) = req.headers().get(api_key_service::API_KEY_HEADER) {
+            let api_key = api_key.to_str().unwrap_or_default().to_string();
+            let service = Rc::clone(&self.service);
+            return Box::pin(async move {
+                let db = req.app_data::<web::Data<MongoDB>>().cloned()
+                    .ok_or_else(|| actix_web::error::ErrorInternalServerError("Database not configured"))?;
+                match api_key_service::authenticate(&db, &api_key, req.method()).await {
+                    Ok(claims) => {
+                        req.extensions_mut().insert(claims);
+                        service.call(req).await
+                    }
+                    Err(rejection) => Err(api_key_error(rejection)),
+                }
+            });
+        }
+        
         // Get Authorization header
         let auth_header = req.headers().get("Authorization");
         
@@ -99,3 +136,35 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{http::StatusCode, test as actix_test, App, HttpResponse};
+
+    #[test]
+    fn test_api_key_rejections_map_to_status_codes() {
+        let status = |r| api_key_error(r).as_response_error().status_code();
+        assert_eq!(status(ApiKeyRejection::Unauthorized("API key revoked".into())), StatusCode::UNAUTHORIZED);
+        assert_eq!(status(ApiKeyRejection::Forbidden("read-only".into())), StatusCode::FORBIDDEN);
+        assert_eq!(status(ApiKeyRejection::Internal("Database error: timeout".into())), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[actix_web::test]
+    async fn test_api_key_header_takes_the_api_key_path() {
+        let app = actix_test::init_service(
+            App::new().wrap(AuthMiddleware).route("/me", web::get().to(|| async { HttpResponse::Ok().finish() })),
+        ).await;
+
+        let missing = actix_test::try_call_service(&app, actix_test::TestRequest::get().uri("/me").to_request()).await;
+        assert_eq!(missing.err().unwrap().as_response_error().status_code(), StatusCode::UNAUTHORIZED);
+
+        // Com X-API-Key o JWT não é consultado: sem banco configurado a verificação falha com 500
+        let req = actix_test::TestRequest::get().uri("/me")
+            .insert_header((api_key_service::API_KEY_HEADER, "tsk_abc"))
+            .insert_header(("Authorization", "Bearer invalid"))
+            .to_request();
+        let res = actix_test::try_call_service(&app, req).await;
+        assert_eq!(res.err().unwrap().as_response_error().status_code(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
@@ -0,0 +1,61 @@
+//! 🗜️ Compressão de respostas
+//!
+//! O app usa o `Compress` do actix (gzip quando o cliente manda `Accept-Encoding`),
+//! o que reduz bastante listas de tokens e OHLCV no mobile. Streams SSE não podem
+//! passar pelo encoder, que segura os eventos em buffer: essas respostas saem com
+//! `Content-Encoding: identity`, que o `Compress` respeita e não recomprime.
+
+use actix_web::{http::header::ContentEncoding, HttpResponse, HttpResponseBuilder};
+
+/// Resposta 200 para streams SSE (sem cache, sem buffer de proxy e sem compressão)
+pub fn event_stream_response() -> HttpResponseBuilder {
+    let mut response = HttpResponse::Ok();
+    response
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .insert_header(("X-Accel-Buffering", "no"))
+        .insert_header(ContentEncoding::Identity);
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{http::header, middleware::Compress, test, web, App};
+
+    #[actix_web::test]
+    async fn test_large_json_is_gzipped_and_sse_is_not() {
+        let app = test::init_service(
+            App::new()
+                .wrap(Compress::default())
+                .route("/tokens", web::get().to(|| async {
+                    let tokens: Vec<_> = (0..2000)
+                        .map(|i| serde_json::json!({ "symbol": format!("TKN{}", i), "name": "Token", "price": i }))
+                        .collect();
+                    HttpResponse::Ok().json(serde_json::json!({ "success": true, "tokens": tokens }))
+                }))
+                .route("/events", web::get().to(|| async {
+                    let events = futures::stream::iter(vec![
+                        Ok::<_, actix_web::Error>(web::Bytes::from_static(b"data: {\"id\":1}\n\n")),
+                    ]);
+                    event_stream_response().streaming(events)
+                })),
+        ).await;
+
+        let gzip_request = |uri: &str| test::TestRequest::get()
+            .uri(uri)
+            .insert_header((header::ACCEPT_ENCODING, "gzip"))
+            .to_request();
+
+        let res = test::call_service(&app, gzip_request("/tokens")).await;
+        assert_eq!(res.headers().get(header::CONTENT_ENCODING).unwrap(), "gzip");
+        let compressed = test::read_body(res).await;
+        let plain = test::call_and_read_body(&app, test::TestRequest::get().uri("/tokens").to_request()).await;
+        assert!(serde_json::from_slice::<serde_json::Value>(&plain).is_ok());
+        assert!(compressed.len() * 5 < plain.len(), "{} vs {}", compressed.len(), plain.len());
+
+        let res = test::call_service(&app, gzip_request("/events")).await;
+        assert_eq!(res.headers().get(header::CONTENT_ENCODING).unwrap(), "identity");
+        assert_eq!(test::read_body(res).await, web::Bytes::from_static(b"data: {\"id\":1}\n\n"));
+    }
+}
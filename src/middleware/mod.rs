@@ -1,4 +1,6 @@
 pub mod auth;
+pub mod compression;
+pub mod request_log;
 pub mod security_headers;
 
 pub use security_headers::*;
@@ -0,0 +1,54 @@
+//! 📝 Log de acesso sem segredos na URL
+//!
+//! O `Logger::default()` grava a request line inteira (`%r`), e o stream SSE
+//! recebe credencial na query (EventSource não envia headers). Aqui o formato
+//! é o mesmo do default, mas a query passa por `redact_query_secrets`.
+
+use actix_web::{dev::ServiceRequest, middleware::Logger};
+
+/// Parâmetros de query cujo valor nunca vai para o log
+const SECRET_QUERY_PARAMS: &[&str] = &["token", "ticket", "access_token"];
+
+/// Mesmo formato do `Logger::default()`, com a request line redigida
+pub fn request_logger() -> Logger {
+    Logger::new(r#"%a "%{request_line}xi" %s %b "%{Referer}i" "%{User-Agent}i" %T"#)
+        .custom_request_replace("request_line", |req: &ServiceRequest| {
+            let path = req.uri().path_and_query().map(|pq| pq.as_str()).unwrap_or("/");
+            format!("{} {} {:?}", req.method(), redact_query_secrets(path), req.version())
+        })
+}
+
+/// Troca o valor de `token`/`ticket`/`access_token` na query por `[REDACTED]`
+pub fn redact_query_secrets(path_and_query: &str) -> String {
+    let Some((path, query)) = path_and_query.split_once('?') else {
+        return path_and_query.to_string();
+    };
+    let query = query
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((name, _)) if SECRET_QUERY_PARAMS.contains(&name) => format!("{}=[REDACTED]", name),
+            _ => pair.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("&");
+    format!("{}?{}", path, query)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secrets_are_redacted_from_logged_urls() {
+        assert_eq!(
+            redact_query_secrets("/api/v1/strategies/events?token=eyJhbGci.abc&last_event_id=7"),
+            "/api/v1/strategies/events?token=[REDACTED]&last_event_id=7"
+        );
+        assert_eq!(
+            redact_query_secrets("/api/v1/strategies/events?ticket=3f2a"),
+            "/api/v1/strategies/events?ticket=[REDACTED]"
+        );
+        assert_eq!(redact_query_secrets("/api/v1/tokens?limit=10"), "/api/v1/tokens?limit=10");
+        assert_eq!(redact_query_secrets("/health"), "/health");
+    }
+}
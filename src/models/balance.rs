@@ -1,3 +1,4 @@
+use pyo3::types::PyAny;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -11,9 +12,93 @@ pub struct Balance {
     pub usd_value: Option<f64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub change_24h: Option<f64>,
+    /// Valor que a exchange mandou e não deu para ler (a quantidade ficou 0)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parse_error: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Quantidades de um ativo lidas do `fetch_balance` do CCXT
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssetAmounts {
+    pub total: f64,
+    pub free: f64,
+    pub used: f64,
+    pub parse_error: Option<String>,
+}
+
+/// Número de saldo do CCXT: float, int ou string numérica ("0.5"). None conta como 0.
+/// Qualquer outra coisa volta como erro com o valor bruto.
+pub fn parse_balance_amount(value: &PyAny) -> Result<f64, String> {
+    if value.is_none() {
+        return Ok(0.0);
+    }
+    if let Ok(number) = value.extract::<f64>() {
+        if number.is_finite() {
+            return Ok(number);
+        }
+    } else if let Ok(text) = value.extract::<String>() {
+        if let Some(number) = text.trim().parse::<f64>().ok().filter(|n| n.is_finite()) {
+            return Ok(number);
+        }
+    }
+    Err(value.repr().map(|r| r.to_string()).unwrap_or_else(|_| "<unprintable>".to_string()))
+}
+
+/// `total`/`free`/`used` de `symbol` no dict de saldo. Campos ilegíveis ficam 0 e
+/// geram um aviso no log e em `parse_error`, em vez de sumirem em silêncio.
+pub fn asset_amounts(balance: &PyAny, symbol: &str, exchange_name: &str) -> AssetAmounts {
+    let mut errors = Vec::new();
+    let mut amount = |field: &str| {
+        let value = balance.get_item(field).ok()
+            .filter(|section| !section.is_none())
+            .and_then(|section| section.get_item(symbol).ok());
+        match value.map(parse_balance_amount).transpose() {
+            Ok(amount) => amount.unwrap_or(0.0),
+            Err(raw) => {
+                log::warn!("⚠️  [{}] Unparseable {} balance for {}: {}", exchange_name, field, symbol, raw);
+                errors.push(format!("{}={}", field, raw));
+                0.0
+            }
+        }
+    };
+    let (total, free, used) = (amount("total"), amount("free"), amount("used"));
+    AssetAmounts {
+        total, free, used,
+        parse_error: (!errors.is_empty()).then(|| format!("Unparseable balance value ({})", errors.join(", "))),
+    }
+}
+
+/// Limites de um mercado (CCXT `market.limits`)
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct MarketLimits {
+    pub min_amount: Option<f64>,
+    pub min_cost: Option<f64>,
+    #[serde(default)]
+    pub max_amount: Option<f64>,
+    #[serde(default)]
+    pub max_cost: Option<f64>,
+    #[serde(default)]
+    pub min_price: Option<f64>,
+    #[serde(default)]
+    pub max_price: Option<f64>,
+}
+
+/// Casas decimais de quantidade/preço de um mercado (derivadas de `market.precision`)
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub struct MarketPrecision {
+    pub amount_decimals: Option<u32>,
+    pub price_decimals: Option<u32>,
+}
+
+/// Regras de um mercado usadas na pré-validação de ordens (status, limites e precisão)
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct MarketRules {
+    pub active: bool,
+    pub limits: MarketLimits,
+    pub precision: MarketPrecision,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ExchangeBalance {
     pub exchange: String,
     pub exchange_id: String,  // MongoDB ObjectId as string
@@ -22,6 +107,20 @@ pub struct ExchangeBalance {
     pub error: Option<String>,
     pub balances: HashMap<String, Balance>,
     pub total_usd: f64,
+    /// Momento (unix, s) em que o balance foi buscado na exchange
+    #[serde(default)]
+    pub fetched_at: i64,
+    /// true quando veio do cache de balances em vez de uma busca nova
+    #[serde(default)]
+    pub from_cache: bool,
+}
+
+/// Exchange que ficou fora do `total_usd` (timeout, credencial inválida, ...)
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ExchangeBalanceError {
+    pub exchange_id: String,
+    pub exchange: String,
+    pub error: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -30,6 +129,24 @@ pub struct BalanceResponse {
     pub exchanges: Vec<ExchangeBalance>,
     pub total_usd: f64,
     pub timestamp: i64,
+    /// true quando alguma exchange falhou e o total está incompleto
+    #[serde(default)]
+    pub partial: bool,
+    #[serde(default)]
+    pub errors: Vec<ExchangeBalanceError>,
+}
+
+impl BalanceResponse {
+    pub fn empty() -> Self {
+        Self {
+            success: true,
+            exchanges: vec![],
+            total_usd: 0.0,
+            timestamp: chrono::Utc::now().timestamp(),
+            partial: false,
+            errors: vec![],
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -39,3 +156,31 @@ pub struct BalanceSummary {
     pub tokens_count: usize,
     pub timestamp: i64,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pyo3::Python;
+
+    #[test]
+    fn test_string_balance_parsed_and_invalid_value_flagged() {
+        Python::with_gil(|py| {
+            let balance = py.eval(r#"{
+                "total": {"BTC": "0.5", "ETH": "n/a", "SOL": 2, "USDT": None},
+                "free": {"BTC": " 0.4 ", "ETH": 1.0, "SOL": 2.0},
+                "used": {"BTC": 0.1, "ETH": {"weird": True}},
+            }"#, None, None).unwrap();
+
+            assert_eq!(asset_amounts(balance, "BTC", "mexc"), AssetAmounts { total: 0.5, free: 0.4, used: 0.1, parse_error: None });
+            assert_eq!(asset_amounts(balance, "SOL", "mexc"), AssetAmounts { total: 2.0, free: 2.0, used: 0.0, parse_error: None });
+            assert_eq!(asset_amounts(balance, "USDT", "mexc").parse_error, None);
+
+            let eth = asset_amounts(balance, "ETH", "mexc");
+            assert_eq!((eth.total, eth.free, eth.used), (0.0, 1.0, 0.0));
+            let note = eth.parse_error.unwrap();
+            assert!(note.contains("total='n/a'") && note.contains("used={'weird': True}"), "{}", note);
+
+            assert!(parse_balance_amount(py.eval("float('nan')", None, None).unwrap()).is_err());
+        });
+    }
+}
@@ -0,0 +1,13 @@
+use serde::{Deserialize, Serialize};
+
+/// Candle OHLCV (formato CCXT: [timestamp, open, high, low, close, volume])
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Candle {
+    pub timestamp: i64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    #[serde(default)]
+    pub volume: f64,
+}
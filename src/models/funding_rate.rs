@@ -0,0 +1,104 @@
+use serde::{Deserialize, Serialize};
+
+/// Funding rate de um contrato perpétuo (dict `fetch_funding_rate` do CCXT)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FundingRate {
+    pub symbol: String,
+    pub funding_rate: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub funding_timestamp: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_funding_rate: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_funding_timestamp: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mark_price: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index_price: Option<f64>,
+    /// Intervalo entre pagamentos (ex: "8h")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interval: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<i64>,
+}
+
+/// Entrada do histórico (`fetch_funding_rate_history`)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FundingRateEntry {
+    pub symbol: String,
+    pub funding_rate: f64,
+    pub timestamp: i64,
+}
+
+fn opt_f64(value: &serde_json::Value, key: &str) -> Option<f64> {
+    value.get(key).and_then(|v| v.as_f64())
+}
+
+fn opt_i64(value: &serde_json::Value, key: &str) -> Option<i64> {
+    value.get(key).and_then(|v| v.as_f64()).map(|v| v as i64)
+}
+
+impl FundingRate {
+    pub fn from_ccxt(value: &serde_json::Value) -> Result<Self, String> {
+        let funding_rate = opt_f64(value, "fundingRate")
+            .ok_or_else(|| "Funding rate missing in exchange response".to_string())?;
+        Ok(FundingRate {
+            symbol: value["symbol"].as_str().unwrap_or_default().to_string(),
+            funding_rate,
+            funding_timestamp: opt_i64(value, "fundingTimestamp"),
+            next_funding_rate: opt_f64(value, "nextFundingRate"),
+            next_funding_timestamp: opt_i64(value, "nextFundingTimestamp"),
+            mark_price: opt_f64(value, "markPrice"),
+            index_price: opt_f64(value, "indexPrice"),
+            interval: value["interval"].as_str().map(str::to_string),
+            timestamp: opt_i64(value, "timestamp"),
+        })
+    }
+}
+
+impl FundingRateEntry {
+    /// Entradas sem taxa ou timestamp são descartadas
+    pub fn list_from_ccxt(value: &serde_json::Value) -> Vec<Self> {
+        value.as_array()
+            .map(|rows| rows.iter().filter_map(|row| {
+                Some(FundingRateEntry {
+                    symbol: row["symbol"].as_str().unwrap_or_default().to_string(),
+                    funding_rate: opt_f64(row, "fundingRate")?,
+                    timestamp: opt_i64(row, "timestamp")?,
+                })
+            }).collect())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_ccxt_funding_rate_dict() {
+        let raw = serde_json::json!({
+            "info": {}, "symbol": "BTC/USDT:USDT",
+            "markPrice": 65010.5, "indexPrice": 65000.1, "interestRate": 0.0001,
+            "fundingRate": 0.0001, "fundingTimestamp": 1717056000000u64, "fundingDatetime": "2024-05-30T08:00:00.000Z",
+            "nextFundingRate": null, "nextFundingTimestamp": null,
+            "timestamp": 1717050000000u64, "datetime": "2024-05-30T06:20:00.000Z", "interval": "8h"
+        });
+        let rate = FundingRate::from_ccxt(&raw).unwrap();
+        assert_eq!(rate.symbol, "BTC/USDT:USDT");
+        assert_eq!(rate.funding_rate, 0.0001);
+        assert_eq!(rate.funding_timestamp, Some(1_717_056_000_000));
+        assert_eq!(rate.next_funding_rate, None);
+        assert_eq!(rate.mark_price, Some(65010.5));
+        assert_eq!(rate.interval.as_deref(), Some("8h"));
+
+        assert!(FundingRate::from_ccxt(&serde_json::json!({ "symbol": "BTC/USDT:USDT" })).is_err());
+
+        let history = FundingRateEntry::list_from_ccxt(&serde_json::json!([
+            { "symbol": "BTC/USDT:USDT", "fundingRate": 0.0001, "timestamp": 1717027200000u64 },
+            { "symbol": "BTC/USDT:USDT", "fundingRate": null, "timestamp": 1717056000000u64 },
+        ]));
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].timestamp, 1_717_027_200_000);
+    }
+}
@@ -6,6 +6,10 @@ pub mod user_exchange;
 pub mod tokens_cache;
 pub mod strategy;
 pub mod strategy_template;
+pub mod candle;
+pub mod order_book;
+pub mod funding_rate;
+pub mod trading_fee;
 
 pub use balance::*;
 pub use order::*;
@@ -15,3 +19,7 @@ pub use strategy::*;
 // Re-export key types for backward compat
 // Strategy (old) is now StrategyItem + UserStrategies
 pub use strategy_template::*;
+pub use candle::*;
+pub use order_book::*;
+pub use funding_rate::*;
+pub use trading_fee::*;
@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 use mongodb::bson::oid::ObjectId;
+use pyo3::PyAny;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Order {
@@ -30,12 +31,168 @@ pub struct Order {
     pub updated_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct OrderFee {
     pub currency: String,
     pub cost: f64,
 }
 
+impl OrderFee {
+    /// Taxa na moeda de quote do par. Paga na quote: como veio; no ativo base: × preço
+    /// de execução; num terceiro ativo (ex: BNB na Binance): × preço `FEE/QUOTE` de
+    /// `third_asset_price`. None quando a conversão não é possível.
+    pub fn cost_in_quote(
+        &self, symbol: &str, fill_price: Option<f64>, third_asset_price: impl FnOnce(&str) -> Option<f64>,
+    ) -> Option<f64> {
+        let (base, quote) = symbol.split_once('/')?;
+        let quote = quote.split(':').next().unwrap_or(quote);
+        let currency = self.currency.trim();
+        if currency.is_empty() || currency.eq_ignore_ascii_case(quote) {
+            return Some(self.cost);
+        }
+        if self.cost == 0.0 {
+            return Some(0.0);
+        }
+        let rate = if currency.eq_ignore_ascii_case(base) {
+            fill_price?
+        } else {
+            third_asset_price(&format!("{}/{}", currency.to_uppercase(), quote.to_uppercase()))?
+        };
+        (rate > 0.0 && rate.is_finite()).then_some(self.cost * rate)
+    }
+}
+
+/// Janela de datas (timestamps em ms, ambos inclusivos) para buscar ordens fechadas.
+/// Repassada ao CCXT (`since` + param `until`) e reaplicada no resultado, já que
+/// várias exchanges ignoram `until` e devolvem tudo a partir de `since`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct OrderDateRange {
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+}
+
+impl OrderDateRange {
+    pub fn is_empty(&self) -> bool {
+        self.since.is_none() && self.until.is_none()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.since.is_some_and(|t| t < 0) || self.until.is_some_and(|t| t < 0) {
+            return Err("since/until must be Unix timestamps in milliseconds".to_string());
+        }
+        if let (Some(since), Some(until)) = (self.since, self.until) {
+            if since > until {
+                return Err("since must be before until".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn contains(&self, timestamp: i64) -> bool {
+        self.since.is_none_or(|since| timestamp >= since) && self.until.is_none_or(|until| timestamp <= until)
+    }
+
+    /// Remove as ordens fora da janela (sem filtro, mantém tudo)
+    pub fn retain(&self, orders: &mut Vec<Order>) {
+        if !self.is_empty() {
+            orders.retain(|o| self.contains(o.timestamp));
+        }
+    }
+}
+
+/// Ordem como retornada pelo CCXT (dict unificado), já tipada.
+/// Campos que a exchange não informa (`None` no Python) ficam `None` aqui.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CcxtOrder {
+    pub id: String,
+    pub symbol: String,
+    pub status: String,
+    pub side: String,
+    pub order_type: String,
+    pub price: Option<f64>,
+    pub average: Option<f64>,
+    pub amount: Option<f64>,
+    pub filled: Option<f64>,
+    pub remaining: Option<f64>,
+    pub cost: Option<f64>,
+    pub fee: Option<OrderFee>,
+    pub timestamp: Option<i64>,
+    pub datetime: Option<String>,
+}
+
+impl CcxtOrder {
+    /// Preço efetivo: média de execução, ou o preço da ordem
+    pub fn fill_price(&self) -> Option<f64> {
+        self.average.or(self.price)
+    }
+
+    pub fn into_order(self, user_id: &str, exchange_id: &str, exchange_name: &str) -> Order {
+        Order {
+            _id: None,
+            id: self.id,
+            user_id: user_id.to_string(),
+            exchange: exchange_name.to_string(),
+            exchange_id: exchange_id.to_string(),
+            symbol: self.symbol,
+            order_type: self.order_type,
+            side: self.side,
+            price: self.price,
+            amount: self.amount.unwrap_or(0.0),
+            filled: self.filled.unwrap_or(0.0),
+            remaining: self.remaining.unwrap_or(0.0),
+            cost: self.cost.unwrap_or(0.0),
+            status: self.status,
+            fee: self.fee,
+            timestamp: self.timestamp.unwrap_or(0),
+            datetime: self.datetime.unwrap_or_default(),
+            created_at: Some(Utc::now()),
+            updated_at: Some(Utc::now()),
+        }
+    }
+}
+
+/// Converte o dict de ordem do CCXT (`create_order`, `fetch_orders`, ...) em `CcxtOrder`
+pub fn parse_ccxt_order(order: &PyAny) -> Result<CcxtOrder, String> {
+    let field = |key: &str| -> Option<&PyAny> {
+        order.get_item(key).ok().filter(|v| !v.is_none())
+    };
+    let string = |key: &str| -> Option<String> { field(key).and_then(|v| v.extract().ok()) };
+    let number = |key: &str| -> Option<f64> { field(key).and_then(|v| v.extract().ok()) };
+
+    if !order.is_instance_of::<pyo3::types::PyDict>() {
+        return Err("Order is not a dict".to_string());
+    }
+
+    // fee: {"cost": 0.1, "currency": "USDT"} — cost ausente = sem fee
+    let fee = field("fee").and_then(|fee| {
+        let cost = fee.get_item("cost").ok().filter(|v| !v.is_none())?.extract::<f64>().ok()?;
+        let currency = fee.get_item("currency").ok()
+            .filter(|v| !v.is_none())
+            .and_then(|v| v.extract::<String>().ok())
+            .unwrap_or_default();
+        Some(OrderFee { currency, cost })
+    });
+
+    Ok(CcxtOrder {
+        id: string("id").unwrap_or_default(),
+        symbol: string("symbol").unwrap_or_default(),
+        status: string("status").unwrap_or_default(),
+        side: string("side").unwrap_or_default(),
+        order_type: string("type").unwrap_or_default(),
+        price: number("price"),
+        average: number("average"),
+        amount: number("amount"),
+        filled: number("filled"),
+        remaining: number("remaining"),
+        cost: number("cost"),
+        fee,
+        timestamp: field("timestamp").and_then(|v| {
+            v.extract::<i64>().ok().or_else(|| v.extract::<f64>().ok().map(|t| t as i64))
+        }),
+        datetime: string("datetime"),
+    })
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct OrdersResponse {
     pub success: bool,
@@ -70,6 +227,22 @@ pub struct CreateOrderWithCredsRequest {
     pub side: String, // buy, sell
     pub amount: f64,
     pub price: Option<f64>,
+    /// GTC, IOC, FOK (nativos) — GTD é tratado no servidor (ver `ManagedOrder`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_in_force: Option<String>,
+}
+
+/// Ordem limit com expiração server-side (collection "tracked_orders")
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ManagedOrder {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user_id: String,
+    pub exchange_id: String,
+    pub symbol: String,
+    #[serde(flatten)]
+    pub order: crate::models::TrackedOrder,
+    pub expires_at: i64,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -120,6 +293,9 @@ pub struct CancelAllOrdersResponse {
     pub success: bool,
     pub canceled_count: usize,
     pub errors: Vec<String>,
+    /// Ids cancelados um a um (vazio quando a exchange cancelou tudo de uma vez)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub canceled_order_ids: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -128,3 +304,43 @@ pub struct OrderStatusResponse {
     pub order: Option<Order>,
     pub error: Option<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pyo3::Python;
+
+    #[test]
+    fn test_parse_ccxt_order_with_nested_fee_and_none_fields() {
+        Python::with_gil(|py| {
+            let order = py.eval(r#"{
+                "id": "28457", "clientOrderId": None, "timestamp": 1710000000123,
+                "datetime": "2024-03-09T16:00:00.123Z", "symbol": "BTC/USDT",
+                "type": "market", "timeInForce": "GTC", "side": "buy",
+                "price": None, "average": 65000.5, "amount": 0.01, "filled": 0.01,
+                "remaining": 0, "cost": 650.005, "status": "closed",
+                "fee": {"cost": 0.65, "currency": "USDT", "rate": None},
+                "trades": [], "info": {"orderId": 28457},
+            }"#, None, None).unwrap();
+
+            let parsed = parse_ccxt_order(order).unwrap();
+            assert_eq!(parsed.id, "28457");
+            assert_eq!((parsed.side.as_str(), parsed.order_type.as_str()), ("buy", "market"));
+            assert_eq!(parsed.price, None);
+            assert_eq!(parsed.fill_price(), Some(65000.5));
+            assert_eq!(parsed.remaining, Some(0.0));
+            assert_eq!(parsed.timestamp, Some(1_710_000_000_123));
+            assert_eq!(parsed.fee.as_ref().map(|f| (f.currency.as_str(), f.cost)), Some(("USDT", 0.65)));
+
+            // Exchange sem fee e sem média: campos ficam None
+            let open = py.eval(r#"{"id": "1", "status": "open", "price": 100, "average": None,
+                "fee": {"cost": None, "currency": None}, "filled": None}"#, None, None).unwrap();
+            let parsed = parse_ccxt_order(open).unwrap();
+            assert_eq!(parsed.fill_price(), Some(100.0));
+            assert!(parsed.fee.is_none() && parsed.filled.is_none());
+            assert_eq!(parsed.into_order("u", "e", "Binance").filled, 0.0);
+
+            assert!(parse_ccxt_order(py.eval("None", None, None).unwrap()).is_err());
+        });
+    }
+}
@@ -0,0 +1,8 @@
+use serde::{Deserialize, Serialize};
+
+/// Livro de ofertas (formato CCXT: níveis `[preço, quantidade]`, melhor preço primeiro)
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct OrderBook {
+    pub bids: Vec<[f64; 2]>,
+    pub asks: Vec<[f64; 2]>,
+}
@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 use mongodb::bson::oid::ObjectId;
+use std::collections::HashMap;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "snake_case")]
@@ -35,6 +36,342 @@ impl std::fmt::Display for StrategyStatus {
     }
 }
 
+/// Mercado em que a estratégia opera
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum StrategyMode {
+    #[default]
+    Spot,
+    /// Perpétuos/futuros com alavancagem (símbolo no formato `BTC/USDT:USDT`)
+    Futures,
+}
+
+/// Tipos de ordem aceitos em spot (`STRATEGY_ORDER_TYPES_SPOT`, separados por vírgula)
+const DEFAULT_SPOT_ORDER_TYPES: &[&str] = &["market", "limit", "stop_loss", "take_profit"];
+/// Tipos que só existem em derivativos; entram no padrão de futuros (`STRATEGY_ORDER_TYPES_FUTURES`)
+pub const FUTURES_ONLY_ORDER_TYPES: &[&str] = &["reduce_only", "stop_market", "take_profit_market", "trailing_stop_market"];
+/// Saída que só reduz a posição (`reduceOnly` nos params); checada na allowlist como tipo próprio
+pub const REDUCE_ONLY_ORDER_TYPE: &str = "reduce_only";
+
+impl StrategyMode {
+    /// Tipos de ordem permitidos no modo (em minúsculas)
+    pub fn allowed_order_types(&self) -> Vec<String> {
+        let (key, defaults): (&str, Vec<&str>) = match self {
+            StrategyMode::Spot => ("STRATEGY_ORDER_TYPES_SPOT", DEFAULT_SPOT_ORDER_TYPES.to_vec()),
+            StrategyMode::Futures => ("STRATEGY_ORDER_TYPES_FUTURES",
+                DEFAULT_SPOT_ORDER_TYPES.iter().chain(FUTURES_ONLY_ORDER_TYPES).copied().collect()),
+        };
+        let configured: Vec<String> = std::env::var(key).unwrap_or_default()
+            .split(',')
+            .map(|t| t.trim().to_lowercase())
+            .filter(|t| !t.is_empty())
+            .collect();
+        if configured.is_empty() {
+            defaults.iter().map(|t| t.to_string()).collect()
+        } else {
+            configured
+        }
+    }
+
+    /// Valida o tipo de ordem no modo e nas capacidades da exchange (`has`).
+    /// Futuros (e tipos exclusivos de derivativos) exigem `swap`/`future` na exchange.
+    pub fn validate_order_type(&self, order_type: &str, has: impl Fn(&str) -> bool) -> Result<(), String> {
+        let normalized = order_type.trim().to_lowercase();
+        let mode = match self {
+            StrategyMode::Spot => "spot",
+            StrategyMode::Futures => "futures",
+        };
+        if !self.allowed_order_types().contains(&normalized) {
+            return Err(format!("Order type '{}' is not allowed for {} strategies", order_type, mode));
+        }
+        let needs_derivatives = *self == StrategyMode::Futures || FUTURES_ONLY_ORDER_TYPES.contains(&normalized.as_str());
+        if needs_derivatives && !(has("swap") || has("future")) {
+            return Err(format!("Order type '{}' requires an exchange with futures support", order_type));
+        }
+        Ok(())
+    }
+}
+
+/// Reconciliação da posição com o saldo real do ativo base (a cada tick, apenas spot)
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReconcilePolicy {
+    #[default]
+    Off,
+    /// Marca a estratégia (`error_message`) e continua operando
+    Warn,
+    /// Corrige `position.quantity` para o saldo real
+    Adjust,
+}
+
+/// Compra automática abaixo do mínimo (`limits.cost.min`) do mercado
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MinNotionalPolicy {
+    /// Não compra e emite um sinal explicando o motivo
+    #[default]
+    Skip,
+    /// Guarda o valor em `pending_buy_usd` e soma na próxima compra até passar do mínimo
+    Accumulate,
+}
+
+/// Margem de manutenção assumida no cálculo da distância de liquidação (%)
+pub const MAINTENANCE_MARGIN_PERCENT: f64 = 0.5;
+/// Folga mínima entre o stop loss e o preço de liquidação (%)
+pub const LIQUIDATION_BUFFER_PERCENT: f64 = 1.0;
+pub const MAX_LEVERAGE: u32 = 20;
+
+/// Sinal de scalping pelo desequilíbrio do livro de ofertas (ver `indicators::order_book_imbalance`)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OrderBookImbalanceConfig {
+    /// Níveis considerados em cada lado do book
+    #[serde(default = "default_imbalance_levels")]
+    pub levels: usize,
+    /// Entrada quando volume de compra / volume de venda >= este valor (ex: 2.0)
+    pub entry_ratio: f64,
+    /// Saída (com posição no lucro) quando venda / compra >= este valor. None = só entrada
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_ratio: Option<f64>,
+}
+
+fn default_imbalance_levels() -> usize { 10 }
+
+pub const MAX_ORDER_BOOK_LEVELS: usize = 100;
+
+/// Compras automáticas como limit um pouco abaixo do preço (menos slippage).
+/// Enquanto a ordem não executa, ela acompanha o preço se ele subir; passado
+/// `timeout_secs` sem execução, o restante é comprado a mercado.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LimitEntryConfig {
+    /// Distância (%) abaixo do preço atual (ex: 0.1 = 0,1%)
+    pub offset_percent: f64,
+    #[serde(default = "default_limit_entry_timeout_secs")]
+    pub timeout_secs: i64,
+}
+
+fn default_limit_entry_timeout_secs() -> i64 { 300 }
+
+pub const MAX_LIMIT_ENTRY_OFFSET_PERCENT: f64 = 5.0;
+
+/// Compra de um nível do grid ainda não vendida
+/// Configuração do grid. O estado de execução (compras abertas por nível) fica em
+/// `StrategyItem::grid_state`, fora da config editável/exportável
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GridConfig {
+    /// Lucro mínimo (%) exigido além das taxas de compra e venda
+    #[serde(default)]
+    pub min_profit_percent: f64,
+    /// Níveis de compra abaixo do `base_price` (centro). 0 = sem layout: só pareia compras/vendas
+    #[serde(default)]
+    pub levels: u32,
+    /// Distância (%) entre níveis consecutivos
+    #[serde(default)]
+    pub spacing_percent: f64,
+}
+
+impl GridConfig {
+    /// Buffer sobre o preço de compra: taxa dos dois lados + lucro mínimo
+    pub fn fee_buffer(&self, fee_percent: f64) -> f64 {
+        (2.0 * fee_percent + self.min_profit_percent) / 100.0
+    }
+
+    /// Grid com níveis definidos: o motor opera por nível via `GridState`
+    pub fn has_layout(&self) -> bool {
+        self.levels > 0 && self.spacing_percent > 0.0
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum GridSide {
+    Buy,
+    Sell,
+}
+
+/// Compra executada em um nível, casada com a venda no nível de cima
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GridFill {
+    pub price: f64,
+    pub amount: f64,
+    pub filled_at: i64,
+}
+
+/// Um nível do grid e a ordem que ele aguarda
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GridLevelState {
+    /// 1 = primeiro nível abaixo do centro
+    pub level: u32,
+    /// Preço de compra do nível (fixo)
+    pub price: f64,
+    pub side: GridSide,
+    /// Preço que dispara a ordem do lado atual
+    pub target_price: f64,
+    /// Compra preenchida aguardando a venda casada
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fill: Option<GridFill>,
+    /// Compras do nível que falharam em sequência (zera no próximo fill)
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub failed_attempts: u32,
+    /// Após uma compra falhar, o nível só tenta de novo a partir daqui (unix, s)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_after: Option<i64>,
+}
+
+fn is_zero(v: &u32) -> bool { *v == 0 }
+
+/// Estado do grid persistido entre ticks. Com layout os níveis são calculados uma vez
+/// a partir do centro, e cada um alterna compra → venda casada → compra. Sem layout
+/// (`spacing_percent == 0`) cada compra da estratégia abre um nível novo, vendido
+/// acima do breakeven da própria compra (a mais recente primeiro).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GridState {
+    pub center_price: f64,
+    pub spacing_percent: f64,
+    pub levels: Vec<GridLevelState>,
+}
+
+/// Quantidade abaixo da qual a compra de um nível é considerada toda vendida
+const GRID_DUST_AMOUNT: f64 = 1e-9;
+
+impl GridState {
+    pub fn build(center_price: f64, levels: u32, spacing_percent: f64) -> Self {
+        let mut state = GridState { center_price, spacing_percent, levels: vec![] };
+        state.levels = (1..=levels)
+            .map(|level| {
+                let price = state.line(level);
+                GridLevelState { level, price, side: GridSide::Buy, target_price: price, fill: None, failed_attempts: 0, retry_after: None }
+            })
+            .collect();
+        state
+    }
+
+    /// Grid sem layout: só pareia as compras da estratégia com as vendas
+    pub fn pairing() -> Self {
+        GridState { center_price: 0.0, spacing_percent: 0.0, levels: vec![] }
+    }
+
+    pub fn has_layout(&self) -> bool {
+        self.spacing_percent > 0.0
+    }
+
+    /// Preço da linha `level` do grid (0 = centro)
+    fn line(&self, level: u32) -> f64 {
+        self.center_price * (1.0 - level as f64 * self.spacing_percent / 100.0)
+    }
+
+    /// Mesmo centro/espaçamento/quantidade de níveis — senão o estado precisa ser recalculado
+    pub fn matches(&self, center_price: f64, config: &GridConfig) -> bool {
+        self.center_price == center_price
+            && self.spacing_percent == config.spacing_percent
+            && self.levels.len() == config.levels as usize
+    }
+
+    pub fn has_fills(&self) -> bool {
+        self.levels.iter().any(|l| l.fill.is_some())
+    }
+
+    /// Níveis cuja ordem o preço atual dispara: compras com preço <= alvo (fora do
+    /// backoff de uma compra que falhou), vendas com preço >= alvo
+    pub fn triggered(&self, price: f64, now: i64) -> Vec<(u32, GridSide)> {
+        self.levels.iter()
+            .filter(|l| match l.side {
+                GridSide::Buy => price <= l.target_price && l.retry_after.is_none_or(|at| now >= at),
+                GridSide::Sell => price >= l.target_price,
+            })
+            .map(|l| (l.level, l.side))
+            .collect()
+    }
+
+    pub fn level(&self, level: u32) -> Option<&GridLevelState> {
+        self.levels.iter().find(|l| l.level == level)
+    }
+
+    /// Stop loss do grid com layout: `stop_loss_percent` abaixo da linha mais baixa,
+    /// para não zerar a posição enquanto o grid ainda tem níveis a comprar
+    pub fn stop_loss_price(&self, stop_loss_percent: f64) -> Option<f64> {
+        let lowest = self.levels.iter().map(|l| l.price).reduce(f64::min)?;
+        Some(lowest * (1.0 - stop_loss_percent / 100.0))
+    }
+
+    /// Sem layout: preço mínimo (exclusivo) de venda — breakeven com taxas da compra mais recente
+    pub fn pair_sell_floor(&self) -> Option<f64> {
+        self.levels.iter().filter(|l| l.fill.is_some()).max_by_key(|l| l.level).map(|l| l.target_price)
+    }
+
+    /// Compra do nível preenchida: passa a aguardar a venda no nível de cima,
+    /// nunca abaixo do breakeven com taxas (`fee_buffer`, ver `GridConfig::fee_buffer`).
+    /// Sem layout a compra abre um nível novo acima dos existentes.
+    pub fn fill_buy(&mut self, level: u32, fill_price: f64, amount: f64, fee_buffer: f64, now: i64) {
+        let breakeven = fill_price * (1.0 + fee_buffer);
+        if !self.has_layout() {
+            let level = self.levels.iter().map(|l| l.level).max().map_or(level, |top| top + 1);
+            self.levels.push(GridLevelState {
+                level, price: fill_price, side: GridSide::Sell, target_price: breakeven,
+                fill: Some(GridFill { price: fill_price, amount, filled_at: now }),
+                failed_attempts: 0, retry_after: None,
+            });
+            return;
+        }
+        let upper = self.line(level.saturating_sub(1));
+        let Some(slot) = self.levels.iter_mut().find(|l| l.level == level) else { return };
+        slot.side = GridSide::Sell;
+        slot.target_price = upper.max(breakeven);
+        slot.fill = Some(GridFill { price: fill_price, amount, filled_at: now });
+        slot.failed_attempts = 0;
+        slot.retry_after = None;
+    }
+
+    /// Compra do nível falhou: adia a próxima tentativa com backoff exponencial
+    /// (`base_secs`, dobrando a cada falha até `max_secs`)
+    pub fn defer_buy(&mut self, level: u32, now: i64, base_secs: i64, max_secs: i64) {
+        let Some(slot) = self.levels.iter_mut().find(|l| l.level == level && l.side == GridSide::Buy) else { return };
+        slot.failed_attempts = slot.failed_attempts.saturating_add(1);
+        let shift = (slot.failed_attempts - 1).min(20);
+        slot.retry_after = Some(now + base_secs.saturating_mul(1 << shift).min(max_secs));
+    }
+
+    /// Venda de `amount` do nível: devolve a parte vendida da compra casada. O que
+    /// não foi vendido continua aguardando a venda; vendido tudo, o nível recoloca
+    /// a compra no preço dele (sem layout o nível é removido)
+    pub fn fill_sell(&mut self, level: u32, amount: f64) -> Option<GridFill> {
+        let index = self.levels.iter().position(|l| l.level == level)?;
+        let has_layout = self.has_layout();
+        let slot = &mut self.levels[index];
+        let fill = slot.fill.as_mut()?;
+        let sold = GridFill { price: fill.price, amount: amount.min(fill.amount), filled_at: fill.filled_at };
+        fill.amount -= sold.amount;
+        if fill.amount > GRID_DUST_AMOUNT {
+            return Some(sold);
+        }
+        if has_layout {
+            slot.fill = None;
+            slot.side = GridSide::Buy;
+            slot.target_price = slot.price;
+        } else {
+            self.levels.remove(index);
+        }
+        Some(sold)
+    }
+
+    /// Venda fora dos níveis (TP, lote gradual, stop): consome as compras abertas
+    /// da mais recente para a mais antiga até cobrir `amount`
+    pub fn take_sells(&mut self, mut amount: f64) {
+        while amount > GRID_DUST_AMOUNT {
+            let Some(level) = self.levels.iter().filter(|l| l.fill.is_some()).map(|l| l.level).max() else { return };
+            let Some(sold) = self.fill_sell(level, amount) else { return };
+            amount -= sold.amount;
+        }
+    }
+
+    /// Posição zerada: nenhuma compra aberta continua casada
+    pub fn clear_fills(&mut self) {
+        let levels: Vec<u32> = self.levels.iter().filter(|l| l.fill.is_some()).map(|l| l.level).collect();
+        for level in levels {
+            self.fill_sell(level, f64::INFINITY);
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GradualLot {
     pub lot_number: i32,
@@ -64,6 +401,71 @@ pub struct StrategyConfig {
     pub timer_gradual_min: i64,
     #[serde(default = "default_time_execution")]
     pub time_execution_min: i64,
+    /// Filtro de volatilidade: bloqueia entrada se ATR% (candles 1h) exceder este valor
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_atr_percent: Option<f64>,
+    /// Valor (USD) de cada compra automática (entrada com `auto_entry` e níveis de grid)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entry_amount_usd: Option<f64>,
+    /// Opt-in da entrada automática: compra `entry_amount_usd` a mercado quando o
+    /// preço <= base_price (ou a `entry_condition`/book dispara). Desligado = entrada manual.
+    #[serde(default)]
+    pub auto_entry: bool,
+    /// Tamanho máximo da posição (USD ao preço atual). Compras que passariam disso
+    /// são recusadas no tick antes de chegar à exchange.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_position_usd: Option<f64>,
+    /// Pausa a estratégia após N vendas seguidas com PnL negativo (circuit breaker)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_consecutive_losses: Option<u32>,
+    /// Condição de entrada customizada (ex: "price < 0.95 * high_24h").
+    /// Quando definida substitui a regra `price <= base_price`. Ver `utils::expression`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entry_condition: Option<String>,
+    /// Drawdown máximo (%) a partir da máxima da posição. Ao exceder, zera a posição e pausa.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_drawdown_percent: Option<f64>,
+    /// Intervalo mínimo (s) entre notificações do mesmo tipo. Padrão 300; 0 desativa.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notification_throttle_secs: Option<i64>,
+    /// Intervalo (s) entre ticks sem posição (idle/monitoring). Padrão 30
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub monitoring_interval_secs: Option<i64>,
+    /// Intervalo (s) entre ticks com posição aberta (in_position/gradual_selling). Padrão 30
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub in_position_interval_secs: Option<i64>,
+    #[serde(default)]
+    pub mode: StrategyMode,
+    /// Alavancagem (apenas `mode: futures`), aplicada na exchange antes da entrada
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub leverage: Option<u32>,
+    /// Grid (níveis e lucro mínimo). O estado dos níveis fica em `StrategyItem::grid_state`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grid: Option<GridConfig>,
+    /// Entrada/saída pelo desequilíbrio do book (scalping). None = desligado
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order_book_imbalance: Option<OrderBookImbalanceConfig>,
+    /// Compras automáticas como limit com fallback a mercado. None = mercado
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit_entry: Option<LimitEntryConfig>,
+    /// O que fazer quando a posição registrada diverge do saldo na exchange
+    #[serde(default)]
+    pub reconcile: ReconcilePolicy,
+    /// Reinveste o lucro realizado: cada compra soma `total_pnl_usd` (se positivo)
+    /// ao `entry_amount_usd`. O teto de exposição continua valendo.
+    #[serde(default)]
+    pub compound: bool,
+    /// Coloca o trailing stop (`max_drawdown_percent`) como ordem nativa na exchange
+    /// quando suportado, para proteger a posição mesmo com o servidor fora do ar
+    #[serde(default)]
+    pub use_exchange_trailing: bool,
+    /// Após a entrada, coloca uma venda limit por nível de take profit (lotes
+    /// graduais ou o TP único) na exchange, em vez de vender a mercado no tick
+    #[serde(default)]
+    pub exchange_take_profits: bool,
+    /// Compra automática abaixo do mínimo do mercado: pular ou acumular
+    #[serde(default)]
+    pub min_notional_policy: MinNotionalPolicy,
 }
 
 fn default_timer_gradual() -> i64 { 15 }
@@ -81,6 +483,26 @@ impl Default for StrategyConfig {
             gradual_lots: vec![],
             timer_gradual_min: 15,
             time_execution_min: 120,
+            max_atr_percent: None,
+            entry_amount_usd: None,
+            auto_entry: false,
+            max_position_usd: None,
+            max_consecutive_losses: None,
+            entry_condition: None,
+            max_drawdown_percent: None,
+            notification_throttle_secs: None,
+            monitoring_interval_secs: None,
+            in_position_interval_secs: None,
+            mode: StrategyMode::Spot,
+            leverage: None,
+            grid: None,
+            order_book_imbalance: None,
+            limit_entry: None,
+            reconcile: ReconcilePolicy::Off,
+            compound: false,
+            use_exchange_trailing: false,
+            exchange_take_profits: false,
+            min_notional_policy: MinNotionalPolicy::Skip,
         }
     }
 }
@@ -96,6 +518,154 @@ impl StrategyConfig {
         self.base_price * (1.0 - self.stop_loss_percent / 100.0)
     }
 
+    /// Stop loss máximo (%) que ainda dispara antes da liquidação na alavancagem dada
+    pub fn max_stop_loss_for_leverage(leverage: u32) -> f64 {
+        100.0 / leverage.max(1) as f64 - MAINTENANCE_MARGIN_PERCENT - LIQUIDATION_BUFFER_PERCENT
+    }
+
+    /// Valida modo/alavancagem/stop de estratégias de futuros
+    pub fn validate_futures(&self, symbol: &str) -> Result<(), String> {
+        match self.mode {
+            StrategyMode::Spot => {
+                if self.leverage.is_some_and(|l| l > 1) {
+                    return Err("Leverage requires mode 'futures'".to_string());
+                }
+                Ok(())
+            }
+            StrategyMode::Futures => {
+                let leverage = self.leverage.ok_or("Leverage is required for futures strategies")?;
+                if !(1..=MAX_LEVERAGE).contains(&leverage) {
+                    return Err(format!("Leverage must be between 1 and {}", MAX_LEVERAGE));
+                }
+                if !symbol.contains(':') {
+                    return Err("Futures strategies require a perpetual symbol (e.g. BTC/USDT:USDT)".to_string());
+                }
+                let max_stop = Self::max_stop_loss_for_leverage(leverage);
+                if self.stop_loss_percent >= max_stop {
+                    return Err(format!(
+                        "Stop loss {:.2}% would trigger after liquidation at {}x (max {:.2}%)",
+                        self.stop_loss_percent, leverage, max_stop
+                    ));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Tipos de ordem que a configuração envia à exchange. `native_trailing` é o tipo
+    /// do trailing nativo da exchange (`NativeTrailing::order_type`); sem ele o trailing
+    /// fica no servidor. Em futuros as saídas na exchange (TP e trailing) vão `reduce_only`
+    pub fn order_types(&self, native_trailing: Option<&'static str>) -> Vec<&'static str> {
+        let mut types = vec!["market"];
+        if self.limit_entry.is_some() || self.exchange_take_profits || self.grid.is_some() {
+            types.push("limit");
+        }
+        let trailing = native_trailing.filter(|_| self.use_exchange_trailing);
+        if let Some(order_type) = trailing.filter(|t| !types.contains(t)) {
+            types.push(order_type);
+        }
+        if self.mode == StrategyMode::Futures && (self.exchange_take_profits || trailing.is_some()) {
+            types.push(REDUCE_ONLY_ORDER_TYPE);
+        }
+        types
+    }
+
+    /// Invariantes da configuração. Em caso de erro devolve (campo, mensagem)
+    pub fn validate(&self) -> Result<(), (&'static str, String)> {
+        fn fail(field: &'static str, msg: &str) -> Result<(), (&'static str, String)> {
+            Err((field, msg.to_string()))
+        }
+        if !(self.base_price > 0.0 && self.base_price.is_finite()) {
+            return fail("config.base_price", "Base price must be greater than 0");
+        }
+        if !(self.take_profit_percent > 0.0 && self.take_profit_percent <= 1000.0) {
+            return fail("config.take_profit_percent", "Take profit must be between 0.01% and 1000%");
+        }
+        if !(self.stop_loss_percent > 0.0 && self.stop_loss_percent <= 100.0) {
+            return fail("config.stop_loss_percent", "Stop loss must be between 0.01% and 100%");
+        }
+        if !(0.0..=50.0).contains(&self.fee_percent) {
+            return fail("config.fee_percent", "Fee must be between 0% and 50%");
+        }
+        if self.gradual_sell && !(self.gradual_take_percent > 0.0 && self.gradual_take_percent <= 100.0) {
+            return fail("config.gradual_take_percent", "Gradual take percent must be between 0.01% and 100% when gradual sell is enabled");
+        }
+        if !(1..=43200).contains(&self.time_execution_min) {
+            return fail("config.time_execution_min", "Execution time must be between 1 minute and 30 days (43200 min)");
+        }
+        if !(1..=1440).contains(&self.timer_gradual_min) {
+            return fail("config.timer_gradual_min", "Gradual timer must be between 1 minute and 24 hours (1440 min)");
+        }
+        if self.max_atr_percent.is_some_and(|v| !(v > 0.0 && v <= 100.0)) {
+            return fail("config.max_atr_percent", "Max ATR percent must be between 0.01% and 100%");
+        }
+        if self.max_drawdown_percent.is_some_and(|v| !(v > 0.0 && v <= 100.0)) {
+            return fail("config.max_drawdown_percent", "Max drawdown percent must be between 0.01% and 100%");
+        }
+        if self.notification_throttle_secs.is_some_and(|v| v < 0) {
+            return fail("config.notification_throttle_secs", "Notification throttle must be >= 0 seconds");
+        }
+        if let Some(book) = &self.order_book_imbalance {
+            if book.levels == 0 || book.levels > MAX_ORDER_BOOK_LEVELS {
+                return fail("config.order_book_imbalance.levels", "Order book levels must be between 1 and 100");
+            }
+            if !(book.entry_ratio > 1.0 && book.entry_ratio.is_finite()) {
+                return fail("config.order_book_imbalance.entry_ratio", "Entry ratio must be greater than 1");
+            }
+            if book.exit_ratio.is_some_and(|v| !(v > 1.0 && v.is_finite())) {
+                return fail("config.order_book_imbalance.exit_ratio", "Exit ratio must be greater than 1");
+            }
+        }
+        if let Some(limit) = &self.limit_entry {
+            if !(limit.offset_percent > 0.0 && limit.offset_percent <= MAX_LIMIT_ENTRY_OFFSET_PERCENT) {
+                return fail("config.limit_entry.offset_percent", "Limit entry offset must be between 0 and 5%");
+            }
+            if limit.timeout_secs <= 0 {
+                return fail("config.limit_entry.timeout_secs", "Limit entry timeout must be greater than 0 seconds");
+            }
+        }
+        if self.monitoring_interval_secs.is_some_and(|v| v <= 0) {
+            return fail("config.monitoring_interval_secs", "Monitoring interval must be greater than 0 seconds");
+        }
+        if self.in_position_interval_secs.is_some_and(|v| v <= 0) {
+            return fail("config.in_position_interval_secs", "In-position interval must be greater than 0 seconds");
+        }
+        if self.entry_amount_usd.is_some_and(|v| !(v > 0.0 && v.is_finite())) {
+            return fail("config.entry_amount_usd", "Entry amount must be greater than 0");
+        }
+        if self.auto_entry && self.entry_amount_usd.is_none() {
+            return fail("config.auto_entry", "Automatic entry requires entry_amount_usd");
+        }
+        if self.max_position_usd.is_some_and(|v| !(v > 0.0 && v.is_finite())) {
+            return fail("config.max_position_usd", "Max position size must be greater than 0");
+        }
+        if self.max_consecutive_losses == Some(0) {
+            return fail("config.max_consecutive_losses", "Max consecutive losses must be at least 1");
+        }
+        if let Some(condition) = self.entry_condition.as_deref() {
+            if let Err(e) = crate::utils::expression::parse(condition) {
+                return Err(("config.entry_condition", format!("Invalid entry condition: {}", e)));
+            }
+        }
+        if self.exchange_take_profits && self.use_exchange_trailing {
+            return fail("config.exchange_take_profits", "Exchange take profit orders can't be combined with the native trailing stop (both lock the position balance)");
+        }
+        Ok(())
+    }
+
+    /// Cópia sem estado de execução (lotes vendidos), usada para exportar/importar
+    /// a configuração entre estratégias
+    pub fn portable(&self) -> Self {
+        let mut config = self.clone();
+        for lot in config.gradual_lots.iter_mut() {
+            lot.executed = false;
+            lot.executed_at = None;
+            lot.executed_price = None;
+            lot.realized_pnl = None;
+        }
+        config
+    }
+
     pub fn gradual_trigger_price(&self, lot_index: usize) -> f64 {
         let base_tp = self.take_profit_percent / 100.0;
         let fee = self.fee_percent / 100.0;
@@ -111,6 +681,7 @@ pub enum ExecutionAction {
     Sell,
     BuyFailed,
     SellFailed,
+    Cancel,
 }
 
 impl std::fmt::Display for ExecutionAction {
@@ -120,6 +691,7 @@ impl std::fmt::Display for ExecutionAction {
             ExecutionAction::Sell => write!(f, "sell"),
             ExecutionAction::BuyFailed => write!(f, "buy_failed"),
             ExecutionAction::SellFailed => write!(f, "sell_failed"),
+            ExecutionAction::Cancel => write!(f, "cancel"),
         }
     }
 }
@@ -143,23 +715,53 @@ pub struct StrategyExecution {
     pub error_message: Option<String>,
 }
 
+impl StrategyExecution {
+    /// Cópia gravada/exibida na precisão do mercado (a posição usa os valores brutos).
+    /// Quantidade truncada nas casas de `amount`; preço e total nas de `price`;
+    /// taxa e PnL em `QUOTE_VALUE_DECIMALS`.
+    pub fn rounded(mut self, precision: &crate::utils::precision::ExecutionPrecision) -> Self {
+        use crate::utils::precision::{round_to, truncate_to, QUOTE_VALUE_DECIMALS};
+        self.amount = truncate_to(self.amount, precision.amount);
+        self.price = round_to(self.price, precision.price);
+        self.total = round_to(self.total, precision.price);
+        self.fee = round_to(self.fee, QUOTE_VALUE_DECIMALS);
+        self.pnl_usd = round_to(self.pnl_usd, QUOTE_VALUE_DECIMALS);
+        self
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub enum SignalType {
+    Buy,
     TakeProfit,
     StopLoss,
+    MaxDrawdown,
     GradualSell,
     Expired,
+    /// Circuit breaker de perdas seguidas pausou a estratégia
+    LossStreak,
     Info,
 }
 
+impl SignalType {
+    /// Sinais que resultam em ordem na exchange
+    pub fn places_order(&self) -> bool {
+        matches!(self, SignalType::Buy | SignalType::TakeProfit | SignalType::GradualSell
+            | SignalType::StopLoss | SignalType::MaxDrawdown)
+    }
+}
+
 impl std::fmt::Display for SignalType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
+            SignalType::Buy => write!(f, "buy"),
             SignalType::TakeProfit => write!(f, "take_profit"),
             SignalType::StopLoss => write!(f, "stop_loss"),
+            SignalType::MaxDrawdown => write!(f, "max_drawdown"),
             SignalType::GradualSell => write!(f, "gradual_sell"),
             SignalType::Expired => write!(f, "expired"),
+            SignalType::LossStreak => write!(f, "loss_streak"),
             SignalType::Info => write!(f, "info"),
         }
     }
@@ -193,6 +795,38 @@ pub struct PositionInfo {
     pub opened_at: i64,
 }
 
+/// Ordem aberta na exchange criada pela estratégia (ainda não executada)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackedOrder {
+    pub order_id: String,
+    pub side: String,
+    pub order_type: String,
+    pub amount: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub price: Option<f64>,
+    pub created_at: i64,
+    /// Expiração server-side (GTD): cancelada pelo job após `created_at + ttl_secs`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl_secs: Option<i64>,
+    /// Lote gradual coberto por uma venda de take profit na exchange
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lot_number: Option<i32>,
+}
+
+impl TrackedOrder {
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.ttl_secs.is_some_and(|ttl| now >= self.created_at + ttl)
+    }
+}
+
+impl PositionInfo {
+    /// Valor atual da posição em USD (usa o último preço conhecido ou o de entrada)
+    pub fn notional_usd(&self) -> f64 {
+        let price = if self.current_price > 0.0 { self.current_price } else { self.entry_price };
+        self.quantity * price
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserStrategies {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
@@ -204,6 +838,16 @@ pub struct UserStrategies {
     pub updated_at: i64,
 }
 
+/// Estratégia encerrada movida para `strategies_archive`, com execuções e PnL intactos
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedStrategy {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user_id: String,
+    pub archived_at: i64,
+    pub strategy: StrategyItem,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StrategyItem {
     pub strategy_id: String,
@@ -220,6 +864,11 @@ pub struct StrategyItem {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub position: Option<PositionInfo>,
     #[serde(default)]
+    pub open_orders: Vec<TrackedOrder>,
+    /// Níveis/compras abertas do grid (estratégias com `config.grid`), persistidos entre ticks
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grid_state: Option<GridState>,
+    #[serde(default)]
     pub executions: Vec<StrategyExecution>,
     #[serde(default)]
     pub signals: Vec<StrategySignal>,
@@ -229,12 +878,40 @@ pub struct StrategyItem {
     pub last_price: Option<f64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub last_gradual_sell_at: Option<i64>,
+    /// Último envio de notificação por tipo de sinal (throttling)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub last_notified_at: HashMap<String, i64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error_message: Option<String>,
+    /// Chave da exchange sem permissão de trade: a estratégia só monitora
+    /// preços e emite sinais, sem enviar ordens
+    #[serde(default)]
+    pub alert_only: bool,
+    /// Criada com `require_confirmation`: gera sinais mas não envia ordens
+    /// até o usuário confirmar via `POST /strategies/{id}/confirm-live`
+    #[serde(default)]
+    pub awaiting_live_confirmation: bool,
+    /// Estratégia pai (mesma exchange/símbolo): esta não abre posição própria,
+    /// herda a posição do pai quando ele termina (ver `strategy_service::parent_handoff`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_strategy_id: Option<String>,
     #[serde(default)]
     pub total_pnl_usd: f64,
     #[serde(default)]
     pub total_executions: i32,
+    /// Valor (USD) de compras puladas por ficarem abaixo do mínimo (`MinNotionalPolicy::Accumulate`)
+    #[serde(default)]
+    pub pending_buy_usd: f64,
+    /// O sinal de compra em curso já somou em `pending_buy_usd`; volta a false quando
+    /// o sinal some, para que cada oportunidade de compra acumule uma vez só
+    #[serde(default)]
+    pub pending_buy_window: bool,
+    /// Vendas seguidas com PnL negativo (zera numa venda lucrativa). Ver `config.max_consecutive_losses`
+    #[serde(default)]
+    pub consecutive_losses: u32,
+    /// Pausada junto com as demais da exchange (`POST /admin/exchanges/{ccxt_id}/pause-strategies`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exchange_pause: Option<ExchangePause>,
     pub started_at: i64,
     pub created_at: i64,
     pub updated_at: i64,
@@ -242,6 +919,15 @@ pub struct StrategyItem {
 
 fn default_true() -> bool { true }
 
+/// Pausa por exchange: guarda o status anterior para o resume restaurar
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ExchangePause {
+    pub ccxt_id: String,
+    pub previous_status: StrategyStatus,
+    pub reason: String,
+    pub paused_at: i64,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct CreateStrategyRequest {
     pub name: String,
@@ -249,6 +935,38 @@ pub struct CreateStrategyRequest {
     pub exchange_id: String,
     pub exchange_name: String,
     pub config: StrategyConfig,
+    #[serde(default)]
+    pub parent_strategy_id: Option<String>,
+    /// Segura as ordens reais até `confirm-live` (paper). Padrão: `paper_trading_default`
+    /// do usuário, senão `STRATEGY_REQUIRE_CONFIRMATION`. Ignorado (sempre paper) para
+    /// usuário com `paper_trading_default` ligado
+    #[serde(default)]
+    pub require_confirmation: Option<bool>,
+}
+
+/// Versão atual do formato de export/import de estratégias
+pub const STRATEGY_EXPORT_VERSION: u32 = 1;
+
+/// Configuração portável de uma estratégia (backup/compartilhamento).
+/// Não carrega ids, usuário, exchange, posição nem histórico.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StrategyExport {
+    pub version: u32,
+    pub name: String,
+    pub config: StrategyConfig,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ImportStrategyRequest {
+    pub strategy: StrategyExport,
+    pub symbol: String,
+    pub exchange_id: String,
+    pub exchange_name: String,
+    /// Sobrescreve o nome exportado
+    #[serde(default)]
+    pub name: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -299,6 +1017,10 @@ pub struct StrategyResponse {
     pub last_price: Option<f64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error_message: Option<String>,
+    pub alert_only: bool,
+    pub awaiting_live_confirmation: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_strategy_id: Option<String>,
     pub total_pnl_usd: f64,
     pub total_executions: i32,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -331,6 +1053,27 @@ impl StrategyItem {
         }
     }
 
+    /// Valor (USD) de cada compra automática: `entry_amount_usd`, acrescido do lucro
+    /// realizado acumulado quando `compound` está ligado (prejuízo não reduz o valor).
+    /// O lucro entra uma vez por ciclo, só na compra que abre a posição; compras
+    /// seguintes (níveis de grid) usam o valor base.
+    pub fn buy_amount_usd(&self) -> f64 {
+        let base = self.config.entry_amount_usd.unwrap_or(0.0);
+        let opens_cycle = self.position.as_ref().is_none_or(|p| p.quantity <= 0.0);
+        if self.config.compound && base > 0.0 && opens_cycle {
+            base + self.total_pnl_usd.max(0.0)
+        } else {
+            base
+        }
+    }
+
+    /// Estratégia filha ainda sem a posição do pai
+    pub fn waiting_for_parent(&self) -> bool {
+        self.parent_strategy_id.is_some()
+            && self.position.is_none()
+            && matches!(self.status, StrategyStatus::Idle | StrategyStatus::Monitoring)
+    }
+
     pub fn is_expired(&self) -> bool {
         let now = chrono::Utc::now().timestamp();
         let max_secs = self.config.time_execution_min * 60;
@@ -358,6 +1101,9 @@ impl From<StrategyItem> for StrategyResponse {
             last_checked_at: item.last_checked_at,
             last_price: item.last_price,
             error_message: item.error_message,
+            alert_only: item.alert_only,
+            awaiting_live_confirmation: item.awaiting_live_confirmation,
+            parent_strategy_id: item.parent_strategy_id,
             total_pnl_usd: item.total_pnl_usd,
             total_executions: item.total_executions,
             stats: Some(stats),
@@ -387,6 +1133,8 @@ pub struct StrategyListItem {
     pub started_at: i64,
     pub created_at: i64,
     pub updated_at: i64,
+    /// true para estratégias vindas de `strategies_archive`
+    pub archived: bool,
 }
 
 impl From<StrategyItem> for StrategyListItem {
@@ -407,6 +1155,7 @@ impl From<StrategyItem> for StrategyListItem {
             started_at: item.started_at,
             created_at: item.created_at,
             updated_at: item.updated_at,
+            archived: false,
         }
     }
 }
@@ -1,3 +1,4 @@
+use crate::models::StrategyMode;
 use mongodb::bson::oid::ObjectId;
 use serde::{Deserialize, Serialize};
 
@@ -49,6 +50,14 @@ pub struct StrategyTemplate {
     /// Passos de "como funciona"
     pub how_it_works: Vec<String>,
 
+    /// Mercado (spot/futures). Templates de futuros só ativam em exchanges com suporte a futuros
+    #[serde(default)]
+    pub mode: StrategyMode,
+
+    /// Alavancagem sugerida (apenas futures)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub leverage: Option<u32>,
+
     /// Se é template padrão do sistema (não pode ser deletado pelo usuário)
     pub is_default: bool,
 
@@ -69,6 +78,10 @@ pub struct CreateTemplateRequest {
     pub summary: String,
     pub configs: Vec<TemplateConfig>,
     pub how_it_works: Vec<String>,
+    #[serde(default)]
+    pub mode: StrategyMode,
+    #[serde(default)]
+    pub leverage: Option<u32>,
 }
 
 /// Request para atualizar template
@@ -95,6 +108,9 @@ pub struct StrategyTemplateResponse {
     pub summary: String,
     pub configs: Vec<TemplateConfig>,
     pub how_it_works: Vec<String>,
+    pub mode: StrategyMode,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub leverage: Option<u32>,
     pub is_default: bool,
     pub created_at: i64,
     pub updated_at: i64,
@@ -112,6 +128,8 @@ impl From<StrategyTemplate> for StrategyTemplateResponse {
             summary: t.summary,
             configs: t.configs,
             how_it_works: t.how_it_works,
+            mode: t.mode,
+            leverage: t.leverage,
             is_default: t.is_default,
             created_at: t.created_at,
             updated_at: t.updated_at,
@@ -15,6 +15,11 @@ pub struct TokenInfo {
     pub max_amount: Option<f64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub min_cost: Option<f64>,
+    /// Nome e logo do catálogo `tokens` (preenchidos na resposta, não no cache)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logo: Option<String>,
 }
 
 /// Cached tokens for an exchange (collection: tokens_exchanges)
@@ -23,6 +28,8 @@ pub struct TokensExchangeCache {
     #[serde(rename = "_id")]
     pub id: ObjectId,
     pub exchange_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exchange_ccxt_id: Option<String>,
     pub tokens_by_quote: HashMap<String, Vec<TokenInfo>>,
     pub update_status: String,
     #[serde(skip_serializing_if = "Option::is_none")]
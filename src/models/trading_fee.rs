@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Taxas de um símbolo como frações (0.001 = 0.1%), formato `fetch_trading_fees` do CCXT
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TradingFee {
+    pub symbol: String,
+    pub maker: f64,
+    pub taker: f64,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FeeSource {
+    /// Tier real da conta, retornado pela exchange
+    Exchange,
+    /// Exchange não expõe taxas (ou falhou): taxa padrão documentada
+    Default,
+}
+
+/// Taxas de trade de um usuário numa exchange (cache diário)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TradingFees {
+    pub exchange_id: String,
+    pub ccxt_id: String,
+    pub source: FeeSource,
+    /// Taxa geral da conta (mediana dos símbolos quando vem da exchange)
+    pub maker: f64,
+    pub taker: f64,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub symbols: BTreeMap<String, TradingFee>,
+    pub fetched_at: i64,
+}
+
+impl TradingFee {
+    /// Dict `{symbol: {maker, taker, ...}}`; entradas sem maker/taker são descartadas
+    pub fn map_from_ccxt(value: &serde_json::Value) -> BTreeMap<String, TradingFee> {
+        value.as_object()
+            .map(|fees| fees.iter().filter_map(|(symbol, fee)| {
+                Some((symbol.clone(), TradingFee {
+                    symbol: symbol.clone(),
+                    maker: fee.get("maker")?.as_f64()?,
+                    taker: fee.get("taker")?.as_f64()?,
+                }))
+            }).collect())
+            .unwrap_or_default()
+    }
+}
+
+impl TradingFees {
+    /// Taxa taker (%) do símbolo, ou a geral da conta
+    pub fn taker_percent(&self, symbol: &str) -> f64 {
+        self.symbols.get(symbol).map(|f| f.taker).unwrap_or(self.taker) * 100.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_ccxt_trading_fees_dict() {
+        let raw = serde_json::json!({
+            "BTC/USDT": { "info": {}, "symbol": "BTC/USDT", "maker": 0.00075, "taker": 0.00075, "percentage": true, "tierBased": true },
+            "ETH/USDT": { "symbol": "ETH/USDT", "maker": 0.001, "taker": null },
+        });
+        let fees = TradingFee::map_from_ccxt(&raw);
+        assert_eq!(fees.len(), 1);
+        assert_eq!(fees["BTC/USDT"].taker, 0.00075);
+    }
+}
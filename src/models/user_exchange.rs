@@ -32,6 +32,24 @@ pub struct UserExchangeItem {
     pub updated_at: Option<Bson>,
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub reconnected_at: Option<Bson>,
+    /// Permissões detectadas na validação da chave (None = desconhecidas)
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub permissions: Option<ApiPermissions>,
+    /// Quando o usuário aceitou o aviso de risco (exchanges em `HIGH_RISK_EXCHANGES`)
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub risk_acknowledged_at: Option<Bson>,
+    /// Subconta dona da API key salva (ver `ccxt::client::SUB_ACCOUNT_EXCHANGES`)
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub sub_account: Option<String>,
+}
+
+/// Permissões da API key na exchange
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ApiPermissions {
+    pub can_read: bool,
+    pub can_trade: bool,
+    pub can_withdraw: bool,
+    pub is_restricted: bool,  // IP whitelist ativo
 }
 
 fn default_true() -> bool {
@@ -90,4 +108,10 @@ pub struct DecryptedExchange {
     pub api_secret: String,
     pub passphrase: Option<String>,
     pub is_active: bool,
+    /// false quando a chave salva é somente leitura (estratégias rodam em modo alerta)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub can_trade: Option<bool>,
+    /// Subconta (exchanges que suportam); None = conta principal
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sub_account: Option<String>,
 }
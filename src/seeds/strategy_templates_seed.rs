@@ -1,8 +1,11 @@
 use crate::database::MongoDB;
-use crate::models::{StrategyTemplate, RiskLevel, TemplateConfig};
+use crate::models::{StrategyTemplate, StrategyMode, RiskLevel, TemplateConfig};
 use mongodb::bson::doc;
 
-/// Seed dos 7 templates padrão no MongoDB.
+/// Quantidade de templates padrão (spot + futuros)
+const DEFAULT_TEMPLATE_COUNT: u64 = 9;
+
+/// Seed dos templates padrão no MongoDB.
 /// Só insere se a collection estiver vazia de defaults.
 pub async fn seed_default_templates(db: &MongoDB) {
     let collection = db.collection::<StrategyTemplate>("strategy_templates");
@@ -13,18 +16,18 @@ pub async fn seed_default_templates(db: &MongoDB) {
         .await
         .unwrap_or(0);
 
-    if count >= 7 {
+    if count >= DEFAULT_TEMPLATE_COUNT {
         log::info!("📋 Strategy templates: {} defaults already in DB — skipping seed", count);
         return;
     }
 
-    // Se existem menos de 7 (versão antiga ou parcial), remove e recria
+    // Se existem menos (versão antiga ou parcial), remove e recria
     if count > 0 {
-        log::info!("📋 Strategy templates: found {} defaults (expected 7) — recreating...", count);
+        log::info!("📋 Strategy templates: found {} defaults (expected {}) — recreating...", count, DEFAULT_TEMPLATE_COUNT);
         let _ = collection.delete_many(doc! { "is_default": true }).await;
     }
 
-    log::info!("📋 Strategy templates: seeding 7 default templates into MongoDB...");
+    log::info!("📋 Strategy templates: seeding {} default templates into MongoDB...", DEFAULT_TEMPLATE_COUNT);
 
     let now = chrono::Utc::now().timestamp();
     let templates = build_default_templates(now);
@@ -40,7 +43,7 @@ pub async fn seed_default_templates(db: &MongoDB) {
     }
 }
 
-/// Constrói os 7 templates padrão
+/// Constrói os templates padrão (7 spot + 2 futuros)
 fn build_default_templates(now: i64) -> Vec<StrategyTemplate> {
     vec![
         // ─────────────────────────────────────────────
@@ -73,6 +76,8 @@ fn build_default_templates(now: i64) -> Vec<StrategyTemplate> {
                 "💡 Ideal para: quem acredita no potencial de longo prazo do ativo".into(),
                 "⏰ Paciência é a chave — ignore o ruído do dia a dia".into(),
             ],
+            mode: StrategyMode::Spot,
+            leverage: None,
             is_default: true,
             created_at: now,
             updated_at: now,
@@ -110,6 +115,8 @@ fn build_default_templates(now: i64) -> Vec<StrategyTemplate> {
                 "💡 Ideal para: quem quer investir regularmente sem se preocupar com timing".into(),
                 "📊 Estatisticamente supera quem tenta acertar o melhor momento de compra".into(),
             ],
+            mode: StrategyMode::Spot,
+            leverage: None,
             is_default: true,
             created_at: now,
             updated_at: now,
@@ -147,6 +154,8 @@ fn build_default_templates(now: i64) -> Vec<StrategyTemplate> {
                 "💡 Ideal para: quem acompanha gráficos e quer lucrar com tendências de dias/semanas".into(),
                 "📊 Requer atenção moderada — não precisa olhar a cada minuto".into(),
             ],
+            mode: StrategyMode::Spot,
+            leverage: None,
             is_default: true,
             created_at: now,
             updated_at: now,
@@ -186,6 +195,8 @@ fn build_default_templates(now: i64) -> Vec<StrategyTemplate> {
                 "💡 Ideal para: traders ativos que podem acompanhar o mercado durante o dia".into(),
                 "📊 Proporção ideal: ganhe 2% quando acerta, perca 1% quando erra (2:1)".into(),
             ],
+            mode: StrategyMode::Spot,
+            leverage: None,
             is_default: true,
             created_at: now,
             updated_at: now,
@@ -225,6 +236,8 @@ fn build_default_templates(now: i64) -> Vec<StrategyTemplate> {
                 "💡 Ideal para: traders experientes com exchange de taxas baixas (ex: Binance VIP)".into(),
                 "🚫 Não recomendado para iniciantes — exige reflexo e disciplina extrema".into(),
             ],
+            mode: StrategyMode::Spot,
+            leverage: None,
             is_default: true,
             created_at: now,
             updated_at: now,
@@ -264,6 +277,8 @@ fn build_default_templates(now: i64) -> Vec<StrategyTemplate> {
                 "💡 Ideal para: quem tem contas em várias exchanges e busca lucro de baixo risco".into(),
                 "📊 Lucro pequeno por operação, mas praticamente sem risco quando executado rápido".into(),
             ],
+            mode: StrategyMode::Spot,
+            leverage: None,
             is_default: true,
             created_at: now,
             updated_at: now,
@@ -306,9 +321,109 @@ fn build_default_templates(now: i64) -> Vec<StrategyTemplate> {
                 "🤖 100% automático — configure e deixe o bot trabalhar por você".into(),
                 "📊 Quanto mais o preço oscila dentro do grid, mais lucro é gerado".into(),
             ],
+            mode: StrategyMode::Spot,
+            leverage: None,
+            is_default: true,
+            created_at: now,
+            updated_at: now,
+        },
+        // ─────────────────────────────────────────────
+        // 8. SWING ALAVANCADO (Futuros)
+        // ─────────────────────────────────────────────
+        StrategyTemplate {
+            id: None,
+            user_id: "system".into(),
+            name: "Swing Alavancado".into(),
+            icon: "⚡".into(),
+            strategy_type: "leveraged_swing".into(),
+            risk: RiskLevel { label: "Alto".into(), color: "#ef4444".into() },
+            summary: "Swing trade em contratos perpétuos com alavancagem moderada (3x). Captura movimentos de dias a semanas com stop posicionado bem antes do preço de liquidação.".into(),
+            configs: vec![
+                TemplateConfig { label: "Tipo".into(), value: "Swing Trade (Futuros)".into(), detail: None },
+                TemplateConfig { label: "Alavancagem".into(), value: "3x".into(), detail: Some("Definida na exchange antes da entrada".into()) },
+                TemplateConfig { label: "Take Profit".into(), value: "12%".into(), detail: Some("Movimento de preço — equivale a ~36% sobre a margem".into()) },
+                TemplateConfig { label: "Stop Loss".into(), value: "8%".into(), detail: Some("Liquidação a ~33% — o stop dispara muito antes".into()) },
+                TemplateConfig { label: "Investimento mín.".into(), value: "100 USDT".into(), detail: Some("Margem isolada alocada na posição".into()) },
+                TemplateConfig { label: "Modo".into(), value: "Futuros".into(), detail: Some("Somente exchanges com suporte a futuros/perpétuos".into()) },
+            ],
+            how_it_works: vec![
+                "1. Você escolhe um contrato perpétuo (ex: BTC/USDT:USDT) numa exchange com futuros".into(),
+                "2. O sistema define a alavancagem (3x) na exchange antes de abrir a posição".into(),
+                "3. Monitora o preço continuamente em segundo plano".into(),
+                "4. Se o preço subir +12%, realiza o lucro (Take Profit)".into(),
+                "5. Se o preço cair -8%, fecha a posição (Stop Loss) — longe da liquidação".into(),
+                "⚠️ Alavancagem amplia ganhos e perdas — use apenas capital que aceita arriscar".into(),
+            ],
+            mode: StrategyMode::Futures,
+            leverage: Some(3),
+            is_default: true,
+            created_at: now,
+            updated_at: now,
+        },
+
+        // ─────────────────────────────────────────────
+        // 9. FUNDING RATE FARMING (Futuros)
+        // ─────────────────────────────────────────────
+        StrategyTemplate {
+            id: None,
+            user_id: "system".into(),
+            name: "Funding Rate Farming".into(),
+            icon: "🌾".into(),
+            strategy_type: "funding_farming".into(),
+            risk: RiskLevel { label: "Médio".into(), color: "#f59e0b".into() },
+            summary: "Recebe a taxa de funding dos perpétuos mantendo posição de baixa alavancagem (2x) no lado que recebe o pagamento. O lucro vem principalmente do funding, não da direção do preço.".into(),
+            configs: vec![
+                TemplateConfig { label: "Tipo".into(), value: "Funding Farming (Futuros)".into(), detail: None },
+                TemplateConfig { label: "Alavancagem".into(), value: "2x".into(), detail: Some("Baixa alavancagem para suportar oscilações".into()) },
+                TemplateConfig { label: "Take Profit".into(), value: "6%".into(), detail: Some("Encerra se o preço andar a favor".into()) },
+                TemplateConfig { label: "Stop Loss".into(), value: "15%".into(), detail: Some("Liquidação a ~50% — stop com folga ampla".into()) },
+                TemplateConfig { label: "Investimento mín.".into(), value: "200 USDT".into(), detail: Some("Funding rende pouco por período — capital maior compensa".into()) },
+                TemplateConfig { label: "Modo".into(), value: "Futuros".into(), detail: Some("Somente exchanges com suporte a futuros/perpétuos".into()) },
+            ],
+            how_it_works: vec![
+                "1. Você escolhe um perpétuo com funding consistentemente positivo".into(),
+                "2. O sistema define a alavancagem (2x) e abre a posição".into(),
+                "3. A cada período de funding (geralmente 8h) a posição recebe o pagamento".into(),
+                "4. Stop Loss em -15% protege contra movimentos fortes, bem antes da liquidação".into(),
+                "💡 Ideal para: mercados com funding elevado e tendência estável".into(),
+            ],
+            mode: StrategyMode::Futures,
+            leverage: Some(2),
             is_default: true,
             created_at: now,
             updated_at: now,
         },
     ]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::StrategyConfig;
+
+    fn config_percent(template: &StrategyTemplate, label: &str) -> f64 {
+        let config = template.configs.iter().find(|c| c.label == label).unwrap();
+        config.value.trim_end_matches('%').parse().unwrap()
+    }
+
+    #[test]
+    fn test_futures_templates_shape() {
+        let templates = build_default_templates(0);
+        assert_eq!(templates.len() as u64, DEFAULT_TEMPLATE_COUNT);
+
+        let futures: Vec<_> = templates.iter().filter(|t| t.mode == StrategyMode::Futures).collect();
+        assert_eq!(futures.len(), 2);
+        for template in futures {
+            let leverage = template.leverage.expect("futures template without leverage");
+            let config = StrategyConfig {
+                mode: StrategyMode::Futures,
+                leverage: Some(leverage),
+                stop_loss_percent: config_percent(template, "Stop Loss"),
+                take_profit_percent: config_percent(template, "Take Profit"),
+                ..Default::default()
+            };
+            assert!(config.validate_futures("BTC/USDT:USDT").is_ok(), "{}", template.name);
+        }
+        assert!(templates.iter().filter(|t| t.mode == StrategyMode::Spot).all(|t| t.leverage.is_none()));
+    }
+}
@@ -0,0 +1,401 @@
+//! 🔑 API keys de usuário (coleção `api_keys`)
+//!
+//! Acesso programático sem o JWT interativo: `POST /api/v1/auth/api-keys` gera a
+//! chave (mostrada uma única vez), e o `AuthMiddleware` aceita o header `X-API-Key`
+//! resolvendo para o dono da chave. Só o SHA-256 da chave é guardado. Cada chave
+//! tem um escopo (`read_only` libera apenas GET/HEAD), pode expirar e pode ser
+//! revogada. Chaves nunca carregam a role admin e não podem gerar outras chaves.
+
+use crate::{
+    database::MongoDB,
+    services::auth_service::{Claims, User, ADMIN_ROLE, DEFAULT_JWT_AUDIENCE, DEFAULT_JWT_ISSUER},
+};
+use actix_web::http::Method;
+use mongodb::bson::doc;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+pub const API_KEYS_COLLECTION: &str = "api_keys";
+pub const API_KEY_HEADER: &str = "X-API-Key";
+/// Prefixo das chaves geradas (facilita identificar vazamentos em logs/repos)
+pub const API_KEY_PREFIX: &str = "tsk_";
+/// `jti` das Claims de requisições autenticadas por API key
+pub const API_KEY_JTI_PREFIX: &str = "apikey:";
+
+const MAX_KEYS_PER_USER: usize = 10;
+const MAX_KEY_NAME_LEN: usize = 64;
+const MAX_EXPIRY_DAYS: i64 = 365;
+/// Caracteres da chave exibidos na listagem para o usuário reconhecê-la
+const DISPLAY_PREFIX_LEN: usize = 12;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiKeyScope {
+    /// Apenas leitura (GET/HEAD)
+    ReadOnly,
+    /// Tudo que o usuário pode fazer com o JWT
+    #[default]
+    Full,
+}
+
+impl ApiKeyScope {
+    pub fn allows(&self, method: &Method) -> bool {
+        match self {
+            ApiKeyScope::ReadOnly => matches!(*method, Method::GET | Method::HEAD),
+            ApiKeyScope::Full => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ApiKey {
+    pub key_id: String,
+    pub user_id: String,
+    pub name: String,
+    /// Início da chave (ex: `tsk_1a2b3c4d`), para exibição
+    pub prefix: String,
+    pub key_hash: String,
+    #[serde(default)]
+    pub scope: ApiKeyScope,
+    pub created_at: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revoked_at: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_used_at: Option<i64>,
+}
+
+/// Visão da chave devolvida pela API (sem o hash)
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ApiKeySummary {
+    pub key_id: String,
+    pub name: String,
+    pub prefix: String,
+    pub scope: ApiKeyScope,
+    pub created_at: i64,
+    pub expires_at: Option<i64>,
+    pub revoked_at: Option<i64>,
+    pub last_used_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    #[serde(default)]
+    pub scope: ApiKeyScope,
+    /// Validade em dias (sem o campo a chave não expira)
+    #[serde(default)]
+    pub expires_in_days: Option<i64>,
+}
+
+/// Motivo da recusa de uma requisição com `X-API-Key`
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApiKeyRejection {
+    /// Chave inexistente, revogada, expirada ou dono inativo (401)
+    Unauthorized(String),
+    /// Chave válida sem escopo para a operação (403)
+    Forbidden(String),
+    /// Falha de banco (500)
+    Internal(String),
+}
+
+impl ApiKey {
+    /// Chave ainda utilizável e com escopo para o método da requisição
+    pub fn authorize(&self, method: &Method, now: i64) -> Result<(), ApiKeyRejection> {
+        if self.revoked_at.is_some() {
+            return Err(ApiKeyRejection::Unauthorized("API key revoked".to_string()));
+        }
+        if self.expires_at.is_some_and(|exp| exp <= now) {
+            return Err(ApiKeyRejection::Unauthorized("API key expired".to_string()));
+        }
+        if !self.scope.allows(method) {
+            return Err(ApiKeyRejection::Forbidden(format!(
+                "API key '{}' is read-only and can't perform {} requests", self.name, method
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn summary(&self) -> ApiKeySummary {
+        ApiKeySummary {
+            key_id: self.key_id.clone(),
+            name: self.name.clone(),
+            prefix: self.prefix.clone(),
+            scope: self.scope,
+            created_at: self.created_at,
+            expires_at: self.expires_at,
+            revoked_at: self.revoked_at,
+            last_used_at: self.last_used_at,
+        }
+    }
+
+    /// Claims equivalentes às do JWT do dono (sem a role admin)
+    pub fn claims(&self, user: &User) -> Claims {
+        Claims {
+            sub: self.user_id.clone(),
+            email: user.email.clone(),
+            name: user.name.clone(),
+            roles: user.roles.iter().filter(|r| *r != ADMIN_ROLE).cloned().collect(),
+            is_active: user.is_active,
+            iat: self.created_at.max(0) as usize,
+            exp: self.expires_at.unwrap_or(0).max(0) as usize,
+            jti: format!("{}{}", API_KEY_JTI_PREFIX, self.key_id),
+            aud: DEFAULT_JWT_AUDIENCE.to_string(),
+            iss: DEFAULT_JWT_ISSUER.to_string(),
+        }
+    }
+}
+
+/// Requisição autenticada por API key (e não pelo JWT interativo)
+pub fn is_api_key_session(claims: &Claims) -> bool {
+    claims.jti.starts_with(API_KEY_JTI_PREFIX)
+}
+
+/// Nova chave em texto puro: prefixo + 256 bits aleatórios (dois UUID v4)
+pub fn generate_api_key() -> String {
+    format!("{}{}{}", API_KEY_PREFIX, uuid::Uuid::new_v4().simple(), uuid::Uuid::new_v4().simple())
+}
+
+/// SHA-256 (hex) da chave — é o que fica no banco e o que é buscado no login
+pub fn hash_api_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.trim().as_bytes()))
+}
+
+/// Monta o registro de uma nova chave. Devolve (registro, chave em texto puro).
+pub fn new_api_key(user_id: &str, request: &CreateApiKeyRequest, now: i64) -> Result<(ApiKey, String), String> {
+    let name = request.name.trim();
+    if name.is_empty() || name.chars().count() > MAX_KEY_NAME_LEN {
+        return Err(format!("Name must have between 1 and {} characters", MAX_KEY_NAME_LEN));
+    }
+    if request.expires_in_days.is_some_and(|d| !(1..=MAX_EXPIRY_DAYS).contains(&d)) {
+        return Err(format!("expires_in_days must be between 1 and {}", MAX_EXPIRY_DAYS));
+    }
+
+    let key = generate_api_key();
+    let record = ApiKey {
+        key_id: uuid::Uuid::new_v4().to_string(),
+        user_id: user_id.to_string(),
+        name: name.to_string(),
+        prefix: key.chars().take(DISPLAY_PREFIX_LEN).collect(),
+        key_hash: hash_api_key(&key),
+        scope: request.scope,
+        created_at: now,
+        expires_at: request.expires_in_days.map(|d| now + d * 86_400),
+        revoked_at: None,
+        last_used_at: None,
+    };
+    Ok((record, key))
+}
+
+pub async fn create_api_key(db: &MongoDB, user_id: &str, request: &CreateApiKeyRequest) -> Result<(ApiKeySummary, String), String> {
+    let now = chrono::Utc::now().timestamp();
+    let (record, key) = new_api_key(user_id, request, now)?;
+
+    let collection = db.collection::<ApiKey>(API_KEYS_COLLECTION);
+    let active = collection
+        .count_documents(doc! { "user_id": user_id, "revoked_at": { "$exists": false } })
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+    if active as usize >= MAX_KEYS_PER_USER {
+        return Err(format!("Limit of {} active API keys reached. Revoke an unused key first.", MAX_KEYS_PER_USER));
+    }
+
+    collection.insert_one(&record).await
+        .map_err(|e| format!("Failed to store API key: {}", e))?;
+    Ok((record.summary(), key))
+}
+
+/// Chaves do usuário (inclusive revogadas/expiradas), mais recentes primeiro
+pub async fn list_api_keys(db: &MongoDB, user_id: &str) -> Result<Vec<ApiKeySummary>, String> {
+    use futures::stream::TryStreamExt;
+
+    let keys: Vec<ApiKey> = db.collection::<ApiKey>(API_KEYS_COLLECTION)
+        .find(doc! { "user_id": user_id })
+        .sort(doc! { "created_at": -1 })
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .try_collect()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+    Ok(keys.iter().map(ApiKey::summary).collect())
+}
+
+/// Revoga a chave; Ok(false) se não existe (ou não é do usuário) ou já estava revogada
+pub async fn revoke_api_key(db: &MongoDB, user_id: &str, key_id: &str) -> Result<bool, String> {
+    let result = db.collection::<ApiKey>(API_KEYS_COLLECTION)
+        .update_one(
+            doc! { "user_id": user_id, "key_id": key_id, "revoked_at": { "$exists": false } },
+            doc! { "$set": { "revoked_at": chrono::Utc::now().timestamp() } },
+        )
+        .await
+        .map_err(|e| format!("Failed to revoke API key: {}", e))?;
+    Ok(result.modified_count > 0)
+}
+
+/// Resolve `X-API-Key` para as Claims do dono, validando estado, escopo e usuário
+pub async fn authenticate(db: &MongoDB, presented: &str, method: &Method) -> Result<Claims, ApiKeyRejection> {
+    let now = chrono::Utc::now().timestamp();
+    let collection = db.collection::<ApiKey>(API_KEYS_COLLECTION);
+    let users = db.collection::<User>("users");
+    let claims = authenticate_with(
+        |key_hash| {
+            let collection = collection.clone();
+            async move {
+                collection.find_one(doc! { "key_hash": key_hash }).await
+                    .map_err(|e| format!("Database error: {}", e))
+            }
+        },
+        |user_id| {
+            let users = users.clone();
+            async move {
+                users.find_one(doc! { "user_id": user_id }).await
+                    .map_err(|e| format!("Database error: {}", e))
+            }
+        },
+        presented,
+        method,
+        now,
+    ).await?;
+
+    let key_id = claims.jti.trim_start_matches(API_KEY_JTI_PREFIX);
+    if let Err(e) = collection
+        .update_one(doc! { "key_id": key_id }, doc! { "$set": { "last_used_at": now } })
+        .await
+    {
+        log::warn!("⚠️ Failed to update last_used_at of API key {}: {}", key_id, e);
+    }
+    Ok(claims)
+}
+
+/// Regras de `authenticate` com as buscas injetadas: chave pelo hash do valor
+/// apresentado e dono pelo `user_id` da chave
+pub async fn authenticate_with<FK, KFut, FU, UFut>(
+    find_key: FK,
+    find_user: FU,
+    presented: &str,
+    method: &Method,
+    now: i64,
+) -> Result<Claims, ApiKeyRejection>
+where
+    FK: FnOnce(String) -> KFut,
+    KFut: std::future::Future<Output = Result<Option<ApiKey>, String>>,
+    FU: FnOnce(String) -> UFut,
+    UFut: std::future::Future<Output = Result<Option<User>, String>>,
+{
+    let key = find_key(hash_api_key(presented)).await
+        .map_err(ApiKeyRejection::Internal)?
+        .ok_or_else(|| ApiKeyRejection::Unauthorized("Invalid API key".to_string()))?;
+    key.authorize(method, now)?;
+
+    let user = find_user(key.user_id.clone()).await
+        .map_err(ApiKeyRejection::Internal)?
+        .filter(|u| u.is_active)
+        .ok_or_else(|| ApiKeyRejection::Unauthorized("API key owner not found or inactive".to_string()))?;
+    Ok(key.claims(&user))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn owner() -> User {
+        serde_json::from_value(serde_json::json!({
+            "user_id": "u1", "email": "dev@example.com", "name": "Dev", "password": null,
+            "picture": null, "google_id": null, "apple_id": null, "provider": "local",
+            "roles": ["user", "admin"], "is_active": true,
+            "created_at": null, "updated_at": null, "last_login": null,
+        })).unwrap()
+    }
+
+    fn request(scope: ApiKeyScope, expires_in_days: Option<i64>) -> CreateApiKeyRequest {
+        CreateApiKeyRequest { name: "script".into(), scope, expires_in_days }
+    }
+
+    /// `authenticate_with` sobre chaves e usuários em memória
+    async fn authenticate_in(keys: &[ApiKey], users: &[User], presented: &str, method: Method, now: i64) -> Result<Claims, ApiKeyRejection> {
+        authenticate_with(
+            |hash| async move { Ok(keys.iter().find(|k| k.key_hash == hash).cloned()) },
+            |user_id| async move { Ok(users.iter().find(|u| u.user_id == user_id).cloned()) },
+            presented,
+            &method,
+            now,
+        ).await
+    }
+
+    #[tokio::test]
+    async fn test_api_key_resolves_to_owner_and_only_its_hash_is_stored() {
+        let (record, key) = new_api_key("u1", &request(ApiKeyScope::Full, Some(30)), 1_000).unwrap();
+        assert!(key.starts_with(API_KEY_PREFIX) && key.len() > 60);
+        let stored = serde_json::to_string(&record).unwrap();
+        assert!(!stored.contains(&key[API_KEY_PREFIX.len()..]), "plaintext key persisted");
+        assert_eq!(record.expires_at, Some(1_000 + 30 * 86_400));
+
+        let (keys, users) = (vec![record], vec![owner()]);
+        assert_eq!(
+            authenticate_in(&keys, &users, "tsk_wrong", Method::GET, 2_000).await.err(),
+            Some(ApiKeyRejection::Unauthorized("Invalid API key".into()))
+        );
+        // Espaços em volta do header não mudam o hash
+        let claims = authenticate_in(&keys, &users, &format!(" {} ", key), Method::POST, 2_000).await.expect("key resolves");
+        assert_eq!((claims.sub.as_str(), claims.email.as_str()), ("u1", "dev@example.com"));
+        assert_eq!(claims.jti, format!("{}{}", API_KEY_JTI_PREFIX, keys[0].key_id));
+        assert!(is_api_key_session(&claims));
+        assert!(!claims.is_admin(), "API keys never carry the admin role");
+
+        let mut inactive = owner();
+        inactive.is_active = false;
+        for users in [vec![], vec![inactive]] {
+            assert_eq!(
+                authenticate_in(&keys, &users, &key, Method::GET, 2_000).await.err(),
+                Some(ApiKeyRejection::Unauthorized("API key owner not found or inactive".into()))
+            );
+        }
+        let failed = authenticate_with(
+            |_| async { Err("Database error: timeout".to_string()) },
+            |_| async { Ok(Some(owner())) },
+            &key, &Method::GET, 2_000,
+        ).await;
+        assert_eq!(failed.err(), Some(ApiKeyRejection::Internal("Database error: timeout".into())));
+
+        assert!(new_api_key("u1", &request(ApiKeyScope::Full, Some(0)), 0).is_err());
+        assert!(new_api_key("u1", &CreateApiKeyRequest { name: " ".into(), ..request(ApiKeyScope::Full, None) }, 0).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_read_only_key_is_limited_to_reads() {
+        let (record, key) = new_api_key("u1", &request(ApiKeyScope::ReadOnly, None), 0).unwrap();
+        let (keys, users) = (vec![record], vec![owner()]);
+        for method in [Method::GET, Method::HEAD] {
+            assert!(authenticate_in(&keys, &users, &key, method.clone(), 10).await.is_ok(), "{}", method);
+        }
+        for method in [Method::POST, Method::PUT, Method::PATCH, Method::DELETE] {
+            assert!(
+                matches!(authenticate_in(&keys, &users, &key, method.clone(), 10).await, Err(ApiKeyRejection::Forbidden(_))),
+                "{}", method
+            );
+        }
+        let scope: CreateApiKeyRequest = serde_json::from_value(serde_json::json!({ "name": "ro", "scope": "read_only" })).unwrap();
+        assert_eq!(scope.scope, ApiKeyScope::ReadOnly);
+    }
+
+    #[tokio::test]
+    async fn test_revoked_or_expired_key_is_rejected() {
+        let (record, key) = new_api_key("u1", &request(ApiKeyScope::Full, Some(1)), 0).unwrap();
+        let users = vec![owner()];
+        let mut keys = vec![record];
+        assert!(authenticate_in(&keys, &users, &key, Method::GET, 86_399).await.is_ok());
+        assert_eq!(
+            authenticate_in(&keys, &users, &key, Method::GET, 86_400).await.err(),
+            Some(ApiKeyRejection::Unauthorized("API key expired".into()))
+        );
+
+        keys[0].expires_at = None;
+        keys[0].revoked_at = Some(50);
+        assert_eq!(
+            authenticate_in(&keys, &users, &key, Method::GET, 60).await.err(),
+            Some(ApiKeyRejection::Unauthorized("API key revoked".into()))
+        );
+        assert!(keys[0].summary().revoked_at.is_some());
+    }
+}
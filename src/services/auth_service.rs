@@ -24,6 +24,14 @@ pub struct Claims {
     pub iss: String,           // issuer
 }
 
+pub const ADMIN_ROLE: &str = "admin";
+
+impl Claims {
+    pub fn is_admin(&self) -> bool {
+        self.roles.iter().any(|r| r == ADMIN_ROLE)
+    }
+}
+
 // User model
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct User {
@@ -45,6 +53,9 @@ pub struct User {
     pub created_at: Option<BsonDateTime>,
     pub updated_at: Option<BsonDateTime>,
     pub last_login: Option<BsonDateTime>,
+    /// Limite de exposição total (USD) somando as posições abertas de todas as estratégias
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_total_exposure_usd: Option<f64>,
 }
 
 // Default functions for serde
@@ -117,12 +128,15 @@ fn get_jwt_secret() -> String {
     std::env::var("JWT_SECRET").unwrap_or_else(|_| "default-secret-change-me".to_string())
 }
 
+pub const DEFAULT_JWT_ISSUER: &str = "trading-service";
+pub const DEFAULT_JWT_AUDIENCE: &str = "trading-api";
+
 fn get_jwt_issuer() -> String {
-    std::env::var("JWT_ISSUER").unwrap_or_else(|_| "trading-service".to_string())
+    std::env::var("JWT_ISSUER").unwrap_or_else(|_| DEFAULT_JWT_ISSUER.to_string())
 }
 
 fn get_jwt_audience() -> String {
-    std::env::var("JWT_AUDIENCE").unwrap_or_else(|_| "trading-api".to_string())
+    std::env::var("JWT_AUDIENCE").unwrap_or_else(|_| DEFAULT_JWT_AUDIENCE.to_string())
 }
 
 // Generate JWT token (1 hour expiry - refreshed automatically by client)
@@ -152,10 +166,10 @@ pub fn generate_jwt(user: &User) -> Result<String, String> {
 }
 
 // Generate refresh token (longer expiry)
-pub fn generate_refresh_token(user_id: &str) -> Result<String, String> {
+pub fn generate_refresh_token(user_id: &str, jti: &str) -> Result<String, String> {
     let iat = Utc::now().timestamp() as usize;
     let exp = (Utc::now() + Duration::days(30)).timestamp() as usize;
-    let jti = Uuid::new_v4().to_string();
+    let jti = jti.to_string();
     
     let claims = Claims {
         sub: user_id.to_string(),
@@ -177,6 +191,138 @@ pub fn generate_refresh_token(user_id: &str) -> Result<String, String> {
     ).map_err(|e| format!("Failed to generate refresh token: {}", e))
 }
 
+// ==================== REFRESH TOKEN ROTATION ====================
+// Cada login abre uma família de refresh tokens (uma por sessão/dispositivo),
+// guardada em "refresh_tokens" com o jti ativo. O jti carrega a família
+// ("<family_id>.<uuid>"); todo refresh troca o jti dentro dela e apresentar um
+// jti já substituído indica roubo/reuso e revoga a família — os outros
+// dispositivos do usuário continuam logados.
+
+pub const REFRESH_TOKENS_COLLECTION: &str = "refresh_tokens";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct RefreshTokenState {
+    pub user_id: String,
+    /// Ausente nos registros antigos (um por usuário, antes das famílias)
+    #[serde(default)]
+    pub family_id: Option<String>,
+    #[serde(default)]
+    pub active_jti: Option<String>,
+    #[serde(default)]
+    pub revoked_at: Option<i64>,
+}
+
+impl RefreshTokenState {
+    /// Troca o jti ativo pelo novo se `presented` for o ativo.
+    /// Reuso de um jti antigo revoga a família e retorna erro.
+    pub fn rotate(&mut self, presented: &str, new_jti: &str, now: i64) -> Result<(), String> {
+        if self.revoked_at.is_some() {
+            return Err("Refresh token revoked. Please log in again.".to_string());
+        }
+        if self.active_jti.as_deref() != Some(presented) {
+            self.active_jti = None;
+            self.revoked_at = Some(now);
+            return Err("Refresh token reuse detected. Session revoked, please log in again.".to_string());
+        }
+        self.active_jti = Some(new_jti.to_string());
+        Ok(())
+    }
+}
+
+/// Família de um jti; tokens emitidos antes das famílias (sem ".") viram a própria família
+pub fn refresh_token_family(jti: &str) -> &str {
+    jti.split_once('.').map(|(family, _)| family).unwrap_or(jti)
+}
+
+fn new_refresh_jti(family_id: &str) -> String {
+    format!("{}.{}", family_id, Uuid::new_v4())
+}
+
+/// Gera um refresh token numa nova família (novo login/dispositivo)
+pub async fn issue_refresh_token(db: &MongoDB, user_id: &str) -> Result<String, String> {
+    let family_id = Uuid::new_v4().to_string();
+    let jti = new_refresh_jti(&family_id);
+    let token = generate_refresh_token(user_id, &jti)?;
+    
+    db.collection::<RefreshTokenState>(REFRESH_TOKENS_COLLECTION)
+        .insert_one(RefreshTokenState {
+            user_id: user_id.to_string(),
+            family_id: Some(family_id),
+            active_jti: Some(jti),
+            revoked_at: None,
+        })
+        .await
+        .map_err(|e| format!("Failed to store refresh token: {}", e))?;
+    
+    Ok(token)
+}
+
+/// Valida o jti apresentado e rotaciona para um novo refresh token da mesma família
+async fn rotate_refresh_token(db: &MongoDB, user_id: &str, presented_jti: &str) -> Result<String, String> {
+    let collection = db.collection::<RefreshTokenState>(REFRESH_TOKENS_COLLECTION);
+    let family_id = refresh_token_family(presented_jti);
+    let family_filter = doc! { "user_id": user_id, "family_id": family_id };
+    // Registro de antes das famílias: um por usuário, sem family_id
+    let pre_family_filter = doc! { "user_id": user_id, "family_id": { "$exists": false } };
+    
+    let mut record_filter = family_filter.clone();
+    let mut stored = collection
+        .find_one(family_filter.clone())
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+    if stored.is_none() && family_id == presented_jti {
+        record_filter = pre_family_filter.clone();
+        stored = collection
+            .find_one(pre_family_filter)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+    }
+    // Tokens emitidos antes da rotação (sem registro): aceita uma vez e passa a rotacionar
+    let legacy = stored.is_none();
+    let mut state = stored.unwrap_or_else(|| RefreshTokenState {
+        user_id: user_id.to_string(),
+        family_id: Some(family_id.to_string()),
+        active_jti: Some(presented_jti.to_string()),
+        revoked_at: None,
+    });
+    
+    let new_jti = new_refresh_jti(family_id);
+    if let Err(e) = state.rotate(presented_jti, &new_jti, Utc::now().timestamp()) {
+        log::warn!("🚨 Refresh token rejected for user {}: {}", user_id, e);
+        if state.revoked_at.is_some() {
+            collection
+                .update_one(
+                    record_filter,
+                    doc! { "$set": { "family_id": family_id, "active_jti": mongodb::bson::Bson::Null, "revoked_at": state.revoked_at } },
+                )
+                .upsert(true)
+                .await
+                .map_err(|e| format!("Failed to revoke refresh tokens: {}", e))?;
+        }
+        return Err(e);
+    }
+    
+    // Troca condicional: dois refresh simultâneos com o mesmo token não geram duas cadeias
+    // (índice único em family_id faz o upsert concorrente do caso legado falhar)
+    let mut filter = record_filter;
+    if legacy {
+        filter.insert("active_jti", doc! { "$exists": false });
+    } else {
+        filter.insert("active_jti", presented_jti);
+    }
+    let result = collection
+        .update_one(filter, doc! { "$set": { "family_id": family_id, "active_jti": &new_jti, "revoked_at": mongodb::bson::Bson::Null } })
+        .upsert(legacy)
+        .await
+        .map_err(|e| format!("Failed to rotate refresh token: {}", e))?;
+    
+    if result.matched_count == 0 && result.upserted_id.is_none() {
+        return Err("Refresh token already used".to_string());
+    }
+    
+    generate_refresh_token(user_id, &new_jti)
+}
+
 // Verify JWT token
 pub fn verify_token(token: &str) -> Result<Claims, String> {
     let mut validation = Validation::new(Algorithm::HS256);
@@ -230,7 +376,7 @@ pub async fn login(
     }
     
     let token = generate_jwt(&user)?;
-    let refresh_token = generate_refresh_token(&user.user_id)?;
+    let refresh_token = issue_refresh_token(db, &user.user_id).await?;
     
     Ok(AuthResponse {
         success: true,
@@ -333,6 +479,7 @@ pub async fn register(
         created_at: Some(BsonDateTime::now()),
         updated_at: Some(BsonDateTime::now()),
         last_login: Some(BsonDateTime::now()),
+        max_total_exposure_usd: None,
     };
     
     collection
@@ -341,7 +488,7 @@ pub async fn register(
         .map_err(|e| format!("Failed to create user: {}", e))?;
     
     let token = generate_jwt(&new_user)?;
-    let refresh_token = generate_refresh_token(&new_user_id)?;
+    let refresh_token = issue_refresh_token(db, &new_user_id).await?;
     
     log::info!("✅ User registered successfully: {} (provider: {})", email, provider);
     
@@ -384,7 +531,7 @@ pub async fn refresh_token(
     }
     
     let token = generate_jwt(&user)?;
-    let new_refresh_token = generate_refresh_token(&user.user_id)?;
+    let new_refresh_token = rotate_refresh_token(db, &user.user_id, &claims.jti).await?;
     
     Ok(AuthResponse {
         success: true,
@@ -465,6 +612,151 @@ pub fn generate_google_oauth_url() -> Result<GoogleAuthUrlResponse, String> {
 }
 
 // Handle Google OAuth callback
+// ============================================================================
+// GOOGLE OAUTH - TROCA DO CODE COM TIMEOUT, RETRY E DEDUP
+// ============================================================================
+
+pub const GOOGLE_TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+pub const GOOGLE_USERINFO_URL: &str = "https://www.googleapis.com/oauth2/v2/userinfo";
+pub const CODE_ALREADY_USED_ERROR: &str = "Authorization code already used or expired. Please sign in again.";
+
+const DEFAULT_GOOGLE_OAUTH_TIMEOUT_MS: u64 = 10_000;
+const DEFAULT_GOOGLE_OAUTH_MAX_RETRIES: u32 = 1;
+const DEFAULT_GOOGLE_OAUTH_RETRY_BACKOFF_MS: u64 = 300;
+/// Por quanto tempo um code já submetido é recusado localmente (Google expira em ~10 min)
+const USED_CODE_TTL: std::time::Duration = std::time::Duration::from_secs(600);
+
+lazy_static::lazy_static! {
+    /// Codes em uso/já trocados: o callback repetido (duplo clique, reload) falha na hora
+    static ref USED_OAUTH_CODES: std::sync::Mutex<std::collections::HashMap<String, std::time::Instant>> =
+        std::sync::Mutex::new(std::collections::HashMap::new());
+}
+
+#[derive(Debug, Clone)]
+pub struct GoogleOAuthConfig {
+    pub client_id: String,
+    pub client_secret: String,
+    pub redirect_uri: String,
+    pub token_url: String,
+    pub userinfo_url: String,
+    /// Limite de cada requisição ao Google
+    pub timeout: std::time::Duration,
+    /// Novas tentativas em falhas transitórias (rede, 5xx, 429) — nunca em 4xx
+    pub max_retries: u32,
+    pub retry_backoff: std::time::Duration,
+}
+
+impl GoogleOAuthConfig {
+    /// Configurado via env (`GOOGLE_OAUTH_TIMEOUT_MS`, `GOOGLE_OAUTH_MAX_RETRIES`, `GOOGLE_OAUTH_RETRY_BACKOFF_MS`)
+    pub fn from_env(client_id: String, client_secret: String, redirect_uri: String) -> Self {
+        let env_u64 = |key: &str, default: u64| {
+            std::env::var(key).ok().and_then(|v| v.parse::<u64>().ok()).unwrap_or(default)
+        };
+        GoogleOAuthConfig {
+            client_id,
+            client_secret,
+            redirect_uri,
+            token_url: GOOGLE_TOKEN_URL.to_string(),
+            userinfo_url: GOOGLE_USERINFO_URL.to_string(),
+            timeout: std::time::Duration::from_millis(env_u64("GOOGLE_OAUTH_TIMEOUT_MS", DEFAULT_GOOGLE_OAUTH_TIMEOUT_MS)),
+            max_retries: env_u64("GOOGLE_OAUTH_MAX_RETRIES", DEFAULT_GOOGLE_OAUTH_MAX_RETRIES as u64) as u32,
+            retry_backoff: std::time::Duration::from_millis(env_u64("GOOGLE_OAUTH_RETRY_BACKOFF_MS", DEFAULT_GOOGLE_OAUTH_RETRY_BACKOFF_MS)),
+        }
+    }
+}
+
+/// Reserva o code; false = já submetido há menos de `USED_CODE_TTL`
+fn claim_oauth_code(code: &str) -> bool {
+    let mut used = USED_OAUTH_CODES.lock().unwrap();
+    let now = std::time::Instant::now();
+    used.retain(|_, at| now.duration_since(*at) < USED_CODE_TTL);
+    if used.contains_key(code) {
+        return false;
+    }
+    used.insert(code.to_string(), now);
+    true
+}
+
+fn release_oauth_code(code: &str) {
+    USED_OAUTH_CODES.lock().unwrap().remove(code);
+}
+
+/// Envia com retry em falhas transitórias (erro de rede/timeout, 5xx, 429)
+async fn send_with_retry(
+    config: &GoogleOAuthConfig, step: &str, build: impl Fn() -> reqwest::RequestBuilder,
+) -> Result<reqwest::Response, String> {
+    let mut attempt = 0;
+    loop {
+        let transient = match build().timeout(config.timeout).send().await {
+            Ok(response) if response.status().is_server_error()
+                || response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS => format!("HTTP {}", response.status()),
+            Ok(response) => return Ok(response),
+            Err(e) => format!("{}", e),
+        };
+        if attempt >= config.max_retries {
+            return Err(format!("Failed to {}: {}", step, transient));
+        }
+        attempt += 1;
+        log::warn!("🔄 Google OAuth {} failed ({}), retry {}/{}", step, transient, attempt, config.max_retries);
+        tokio::time::sleep(config.retry_backoff).await;
+    }
+}
+
+/// Troca o code pelo access token e busca o userinfo do Google
+pub async fn fetch_google_user_info(config: &GoogleOAuthConfig, code: &str) -> Result<serde_json::Value, String> {
+    if !claim_oauth_code(code) {
+        log::warn!("⚠️ Google OAuth code submitted twice");
+        return Err(CODE_ALREADY_USED_ERROR.to_string());
+    }
+    let result = exchange_google_code(config, code).await;
+    // Code recusado pelo Google continua marcado; outras falhas liberam nova tentativa
+    if matches!(&result, Err(e) if e != CODE_ALREADY_USED_ERROR) {
+        release_oauth_code(code);
+    }
+    result
+}
+
+async fn exchange_google_code(config: &GoogleOAuthConfig, code: &str) -> Result<serde_json::Value, String> {
+    let client = reqwest::Client::new();
+    let form = [
+        ("code", code),
+        ("client_id", config.client_id.as_str()),
+        ("client_secret", config.client_secret.as_str()),
+        ("redirect_uri", config.redirect_uri.as_str()),
+        ("grant_type", "authorization_code"),
+    ];
+
+    // Exchange code for tokens
+    let token_response = send_with_retry(config, "exchange code", || client.post(&config.token_url).form(&form)).await?;
+    let status = token_response.status();
+    let tokens: serde_json::Value = token_response.json().await.unwrap_or_default();
+    if !status.is_success() {
+        // `invalid_grant`: code reutilizado ou expirado
+        if tokens["error"].as_str() == Some("invalid_grant") {
+            return Err(CODE_ALREADY_USED_ERROR.to_string());
+        }
+        log::error!("❌ Google token exchange rejected: HTTP {} {}", status, tokens);
+        return Err("Failed to exchange authorization code".to_string());
+    }
+
+    let access_token = tokens["access_token"]
+        .as_str()
+        .ok_or_else(|| "No access token in response".to_string())?;
+
+    // Get user info
+    let user_info_response = send_with_retry(config, "get user info", || {
+        client.get(&config.userinfo_url).header("Authorization", format!("Bearer {}", access_token))
+    }).await?;
+    if !user_info_response.status().is_success() {
+        return Err(format!("Failed to get user info: HTTP {}", user_info_response.status()));
+    }
+
+    user_info_response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse user info: {}", e))
+}
+
 pub async fn handle_google_callback(
     db: &MongoDB,
     code: &str,
@@ -476,46 +768,8 @@ pub async fn handle_google_callback(
     let redirect_uri = std::env::var("GOOGLE_REDIRECT_URI")
         .unwrap_or_else(|_| "http://localhost:3000/auth/callback".to_string());
     
-    // Exchange code for tokens
-    let client = reqwest::Client::new();
-    let token_response = client
-        .post("https://oauth2.googleapis.com/token")
-        .form(&[
-            ("code", code),
-            ("client_id", &client_id),
-            ("client_secret", &client_secret),
-            ("redirect_uri", &redirect_uri),
-            ("grant_type", "authorization_code"),
-        ])
-        .send()
-        .await
-        .map_err(|e| format!("Failed to exchange code: {}", e))?;
-    
-    if !token_response.status().is_success() {
-        return Err("Failed to exchange authorization code".to_string());
-    }
-    
-    let tokens: serde_json::Value = token_response
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse token response: {}", e))?;
-    
-    let access_token = tokens["access_token"]
-        .as_str()
-        .ok_or_else(|| "No access token in response".to_string())?;
-    
-    // Get user info
-    let user_info_response = client
-        .get("https://www.googleapis.com/oauth2/v2/userinfo")
-        .header("Authorization", format!("Bearer {}", access_token))
-        .send()
-        .await
-        .map_err(|e| format!("Failed to get user info: {}", e))?;
-    
-    let user_info: serde_json::Value = user_info_response
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse user info: {}", e))?;
+    let oauth = GoogleOAuthConfig::from_env(client_id, client_secret, redirect_uri);
+    let user_info = fetch_google_user_info(&oauth, code).await?;
     
     let email = user_info["email"]
         .as_str()
@@ -621,6 +875,7 @@ pub async fn handle_google_callback(
                 created_at: Some(BsonDateTime::now()),
                 updated_at: Some(BsonDateTime::now()),
                 last_login: Some(BsonDateTime::now()),
+                max_total_exposure_usd: None,
             };
             
             collection
@@ -633,7 +888,7 @@ pub async fn handle_google_callback(
     };
     
     let token = generate_jwt(&user)?;
-    let refresh_token = generate_refresh_token(&user.user_id)?;
+    let refresh_token = issue_refresh_token(db, &user.user_id).await?;
     
     Ok(AuthResponse {
         success: true,
@@ -705,6 +960,44 @@ pub async fn delete_user_account(
         .map_err(|e| format!("Failed to delete strategies: {}", e))?;
     
     log::info!("✅ Deleted {} strategies for user {}", delete_strategies_result.deleted_count, user_id);
+
+    // 5b. Estratégias atuais e arquivadas
+    for collection in ["user_strategy", crate::services::strategy_service::ARCHIVE_COLLECTION] {
+        db.database().collection::<mongodb::bson::Document>(collection)
+            .delete_many(doc! { "user_id": user_id })
+            .await
+            .map_err(|e| format!("Failed to delete {}: {}", collection, e))?;
+    }
+    
+    // 6. Revoke refresh tokens
+    db.collection::<RefreshTokenState>(REFRESH_TOKENS_COLLECTION)
+        .delete_many(doc! { "user_id": user_id })
+        .await
+        .map_err(|e| format!("Failed to delete refresh tokens: {}", e))?;
+    
+    // 7. Delete strategy config history
+    db.database().collection::<mongodb::bson::Document>(crate::services::strategy_history_service::STRATEGY_CONFIG_HISTORY_COLLECTION)
+        .delete_many(doc! { "user_id": user_id })
+        .await
+        .map_err(|e| format!("Failed to delete strategy config history: {}", e))?;
+    
+    // 8. Delete user settings
+    db.database().collection::<mongodb::bson::Document>(crate::services::user_settings_service::USER_SETTINGS_COLLECTION)
+        .delete_many(doc! { "user_id": user_id })
+        .await
+        .map_err(|e| format!("Failed to delete user settings: {}", e))?;
+    
+    // 9. Delete API keys
+    db.database().collection::<mongodb::bson::Document>(crate::services::api_key_service::API_KEYS_COLLECTION)
+        .delete_many(doc! { "user_id": user_id })
+        .await
+        .map_err(|e| format!("Failed to delete API keys: {}", e))?;
+    
+    // 10. Delete orders tracked for server-side expiry
+    db.database().collection::<mongodb::bson::Document>(crate::services::order_service::TRACKED_ORDERS_COLLECTION)
+        .delete_many(doc! { "user_id": user_id })
+        .await
+        .map_err(|e| format!("Failed to delete tracked orders: {}", e))?;
     
     // NOTE: Notifications are stored locally in WatermelonDB (Zero Database architecture)
     // No backend cleanup needed - they're automatically removed when app is uninstalled
@@ -712,3 +1005,258 @@ pub async fn delete_user_account(
     log::info!("🎉 Account and all data successfully deleted for user {}", user_id);
     Ok(())
 }
+
+/// Campos que nunca podem sair num export (senha e credenciais, cifradas ou não)
+const EXPORT_SENSITIVE_FIELDS: &[&str] = &[
+    "password", "api_key", "api_secret", "secret", "passphrase",
+    "api_key_encrypted", "api_secret_encrypted", "passphrase_encrypted",
+];
+
+/// Remove recursivamente campos sensíveis de um documento
+fn redact_document(document: &mut mongodb::bson::Document) {
+    use mongodb::bson::Bson;
+
+    fn redact_value(value: &mut Bson) {
+        match value {
+            Bson::Document(d) => redact_document(d),
+            Bson::Array(items) => items.iter_mut().for_each(redact_value),
+            _ => {}
+        }
+    }
+
+    let sensitive: Vec<String> = document.keys()
+        .filter(|k| EXPORT_SENSITIVE_FIELDS.contains(&k.as_str()))
+        .cloned()
+        .collect();
+    for key in sensitive {
+        document.remove(&key);
+    }
+    for (_, value) in document.iter_mut() {
+        redact_value(value);
+    }
+}
+
+/// Monta o documento de export (GDPR) a partir dos dados brutos do usuário.
+/// `sections`: nome da seção → documentos da coleção correspondente
+pub fn build_user_export(
+    user: mongodb::bson::Document,
+    sections: Vec<(&str, Vec<mongodb::bson::Document>)>,
+) -> serde_json::Value {
+    use mongodb::bson::Bson;
+
+    let to_json = |mut d: mongodb::bson::Document| {
+        redact_document(&mut d);
+        Bson::Document(d).into_relaxed_extjson()
+    };
+
+    let mut export = serde_json::Map::new();
+    export.insert("exported_at".into(), serde_json::json!(Utc::now().to_rfc3339()));
+    export.insert("user".into(), to_json(user));
+    for (name, docs) in sections {
+        export.insert(name.to_string(), serde_json::Value::Array(docs.into_iter().map(to_json).collect()));
+    }
+    serde_json::Value::Object(export)
+}
+
+/// 📦 Export all user data (GDPR) — credentials and password are never included
+pub async fn export_user_data(
+    db: &MongoDB,
+    user_id: &str,
+) -> Result<serde_json::Value, String> {
+    use futures::stream::TryStreamExt;
+    use mongodb::bson::Document;
+
+    log::info!("📦 Exporting data for user_id: {}", user_id);
+
+    let user = db.collection::<Document>("users")
+        .find_one(doc! { "user_id": user_id })
+        .await
+        .map_err(|e| format!("Failed to load user: {}", e))?
+        .ok_or_else(|| format!("User {} not found", user_id))?;
+
+    let find_all = |collection: &'static str| async move {
+        db.collection::<Document>(collection)
+            .find(doc! { "user_id": user_id })
+            .await
+            .map_err(|e| format!("Failed to load {}: {}", collection, e))?
+            .try_collect::<Vec<Document>>()
+            .await
+            .map_err(|e| format!("Failed to read {}: {}", collection, e))
+    };
+
+    let sections = vec![
+        ("exchanges", find_all("user_exchanges").await?),
+        ("strategies", find_all("user_strategy").await?),
+        ("archived_strategies", find_all(crate::services::strategy_service::ARCHIVE_COLLECTION).await?),
+        ("strategy_config_history", find_all(crate::services::strategy_history_service::STRATEGY_CONFIG_HISTORY_COLLECTION).await?),
+        ("orders", find_all("orders").await?),
+        ("tracked_orders", find_all(crate::services::order_service::TRACKED_ORDERS_COLLECTION).await?),
+        ("snapshots", find_all("balance_snapshots").await?),
+        ("settings", find_all(crate::services::user_settings_service::USER_SETTINGS_COLLECTION).await?),
+    ];
+
+    log::info!("✅ Export ready for user {}", user_id);
+    Ok(build_user_export(user, sections))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Servidor HTTP mínimo: responde as respostas na ordem, uma por conexão
+    fn mock_google(responses: Vec<(u16, &'static str)>) -> (String, std::thread::JoinHandle<Vec<String>>) {
+        use std::io::{Read, Write};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let mut requests = Vec::new();
+            for (status, body) in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut buf = [0u8; 8192];
+                let n = stream.read(&mut buf).unwrap();
+                requests.push(String::from_utf8_lossy(&buf[..n]).lines().next().unwrap_or_default().to_string());
+                let reply = format!(
+                    "HTTP/1.1 {} X\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status, body.len(), body,
+                );
+                stream.write_all(reply.as_bytes()).unwrap();
+            }
+            requests
+        });
+        (base, handle)
+    }
+
+    fn oauth_config(base: &str) -> GoogleOAuthConfig {
+        GoogleOAuthConfig {
+            client_id: "id".into(),
+            client_secret: "secret".into(),
+            redirect_uri: "http://localhost/cb".into(),
+            token_url: format!("{}/token", base),
+            userinfo_url: format!("{}/userinfo", base),
+            timeout: std::time::Duration::from_secs(5),
+            max_retries: 1,
+            retry_backoff: std::time::Duration::from_millis(1),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_google_oauth_retries_transient_and_rejects_reused_code() {
+        // 503 na troca do code é repetido uma vez
+        let (base, server) = mock_google(vec![
+            (503, r#"{"error":"backendError"}"#),
+            (200, r#"{"access_token":"at-1"}"#),
+            (200, r#"{"id":"g-1","email":"u@example.com","name":"U"}"#),
+        ]);
+        let config = oauth_config(&base);
+        let user_info = fetch_google_user_info(&config, "code-transient").await.unwrap();
+        assert_eq!(user_info["email"], "u@example.com");
+        assert_eq!(server.join().unwrap(), vec![
+            "POST /token HTTP/1.1", "POST /token HTTP/1.1", "GET /userinfo HTTP/1.1",
+        ]);
+
+        // Mesmo code de novo: recusado sem chamar o Google
+        assert_eq!(fetch_google_user_info(&config, "code-transient").await.unwrap_err(), CODE_ALREADY_USED_ERROR);
+
+        // Google recusa o code (4xx) — sem retry, erro claro
+        let (base, server) = mock_google(vec![
+            (400, r#"{"error":"invalid_grant","error_description":"Bad Request"}"#),
+        ]);
+        let err = fetch_google_user_info(&oauth_config(&base), "code-reused").await.unwrap_err();
+        assert_eq!(err, CODE_ALREADY_USED_ERROR);
+        assert_eq!(server.join().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_export_contains_sections_and_no_secrets() {
+        let user = doc! {
+            "user_id": "u1", "email": "u1@example.com",
+            "password": "$2b$12$hash",
+        };
+        let exchanges = vec![doc! {
+            "user_id": "u1",
+            "exchanges": [{
+                "exchange_id": "ex1",
+                "api_key_encrypted": "enc-key",
+                "api_secret_encrypted": "enc-secret",
+                "passphrase_encrypted": "enc-pass",
+                "is_active": true,
+            }],
+        }];
+        let strategies = vec![doc! { "user_id": "u1", "strategies": [{ "strategy_id": "s1" }] }];
+        let archived = vec![doc! { "user_id": "u1", "archived_at": 1, "strategy": { "strategy_id": "s0" } }];
+        let orders = vec![doc! { "user_id": "u1", "order_id": "o1", "api_key": "plain-key" }];
+        let snapshots = vec![doc! { "user_id": "u1", "snapshots": [{ "date": "2026-01-01" }] }];
+        let tracked = vec![doc! { "user_id": "u1", "exchange_id": "ex1", "order": { "order_id": "o2" }, "expires_at": 10 }];
+        let config_history = vec![doc! { "user_id": "u1", "strategy_id": "s1", "changed_by": "u1", "changed_at": 5 }];
+        let settings = vec![doc! { "user_id": "u1", "timezone": "America/Sao_Paulo", "default_quote_currency": "BRL" }];
+
+        let export = build_user_export(user, vec![
+            ("exchanges", exchanges), ("strategies", strategies), ("archived_strategies", archived),
+            ("strategy_config_history", config_history),
+            ("orders", orders), ("tracked_orders", tracked), ("snapshots", snapshots),
+            ("settings", settings),
+        ]);
+
+        for section in ["user", "exchanges", "strategies", "archived_strategies", "strategy_config_history", "orders", "tracked_orders", "snapshots", "settings"] {
+            assert!(export.get(section).is_some(), "missing section {}", section);
+        }
+        assert_eq!(export["user"]["email"], "u1@example.com");
+        assert_eq!(export["exchanges"][0]["exchanges"][0]["exchange_id"], "ex1");
+        assert_eq!(export["strategies"][0]["strategies"][0]["strategy_id"], "s1");
+        assert_eq!(export["archived_strategies"][0]["strategy"]["strategy_id"], "s0");
+        assert_eq!(export["strategy_config_history"][0]["changed_at"], 5);
+        assert_eq!(export["tracked_orders"][0]["order"]["order_id"], "o2");
+        assert_eq!(export["settings"][0]["timezone"], "America/Sao_Paulo");
+
+        let raw = export.to_string();
+        for secret in ["password", "$2b$12$hash", "enc-key", "enc-secret", "enc-pass", "plain-key", "api_key"] {
+            assert!(!raw.contains(secret), "export leaked {}", secret);
+        }
+    }
+
+    #[test]
+    fn test_refresh_token_rotation() {
+        let mut state = RefreshTokenState { user_id: "u1".into(), family_id: Some("f1".into()), active_jti: Some("t1".into()), revoked_at: None };
+        assert!(state.rotate("t1", "t2", 10).is_ok());
+        assert!(state.rotate("t2", "t3", 20).is_ok());
+        assert_eq!(state.active_jti.as_deref(), Some("t3"));
+    }
+
+    #[test]
+    fn test_superseded_refresh_token_reuse_revokes_chain() {
+        let mut state = RefreshTokenState { user_id: "u1".into(), family_id: Some("f1".into()), active_jti: Some("t1".into()), revoked_at: None };
+        state.rotate("t1", "t2", 10).unwrap();
+
+        // Token antigo apresentado de novo: reuso detectado
+        let err = state.rotate("t1", "t3", 20).unwrap_err();
+        assert!(err.contains("reuse detected"));
+        assert_eq!(state.revoked_at, Some(20));
+        assert!(state.active_jti.is_none());
+
+        // Nem o token legítimo mais recente funciona depois da revogação
+        assert!(state.rotate("t2", "t4", 30).unwrap_err().contains("revoked"));
+    }
+
+    #[test]
+    fn test_each_login_rotates_its_own_family() {
+        let jti_a = new_refresh_jti("fam-a");
+        let jti_b = new_refresh_jti("fam-b");
+        assert_eq!(refresh_token_family(&jti_a), "fam-a");
+        assert_eq!(refresh_token_family(&jti_b), "fam-b");
+        // Rotação mantém a família; jti antigo (sem ".") é a própria família
+        assert_eq!(refresh_token_family(&new_refresh_jti(refresh_token_family(&jti_a))), "fam-a");
+        assert_eq!(refresh_token_family("legacy-uuid"), "legacy-uuid");
+
+        // Segundo dispositivo loga e rotaciona: o primeiro segue válido
+        let mut device_a = RefreshTokenState { user_id: "u1".into(), family_id: Some("fam-a".into()), active_jti: Some(jti_a.clone()), revoked_at: None };
+        let mut device_b = RefreshTokenState { user_id: "u1".into(), family_id: Some("fam-b".into()), active_jti: Some(jti_b.clone()), revoked_at: None };
+        let jti_b2 = new_refresh_jti("fam-b");
+        device_b.rotate(&jti_b, &jti_b2, 10).unwrap();
+        let jti_a2 = new_refresh_jti("fam-a");
+        assert!(device_a.rotate(&jti_a, &jti_a2, 20).is_ok());
+
+        // Reuso no dispositivo B revoga só a família B
+        assert!(device_b.rotate(&jti_b, "x", 30).unwrap_err().contains("reuse detected"));
+        assert!(device_a.rotate(&jti_a2, &new_refresh_jti("fam-a"), 40).is_ok());
+    }
+}
@@ -1,10 +1,12 @@
 use crate::{
     ccxt::CCXTClient,
     database::MongoDB,
-    models::{Balance, BalanceResponse, BalanceSummary, ExchangeBalance, UserExchanges, ExchangeCatalog, DecryptedExchange},
-    utils::crypto::decrypt_fernet_via_python,
-    utils::thread_pool::spawn_ccxt_blocking,  // 🚀 FASE 3: Thread pool dedicado
+    models::{Balance, BalanceResponse, BalanceSummary, ExchangeBalance, ExchangeBalanceError, UserExchanges, ExchangeCatalog, DecryptedExchange},
+    utils::thread_pool::spawn_ccxt_paced,  // 🚀 FASE 3: Thread pool dedicado
+    utils::currency_format::{format_currency, format_number},
+    utils::ticker_cache::TickerCache,
 };
+use lazy_static::lazy_static;
 use futures::future::join_all;
 use futures::TryStreamExt; // Para cursor.try_next()
 use mongodb::bson::{doc, oid::ObjectId};
@@ -12,6 +14,22 @@ use std::collections::HashMap;
 use std::env;
 use serde::{Serialize, Deserialize};
 
+const DEFAULT_BALANCE_CACHE_TTL_MS: u64 = 10_000;
+
+lazy_static! {
+    /// Balances recentes por conta; evita refazer o `fetch_balance` a cada refresh da UI
+    static ref BALANCE_CACHE: TickerCache<ExchangeBalance> = TickerCache::new(balance_cache_ttl());
+}
+
+/// TTL configurado via env (`BALANCE_CACHE_TTL_MS`). 0 desativa o cache.
+fn balance_cache_ttl() -> std::time::Duration {
+    let ms = env::var("BALANCE_CACHE_TTL_MS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_BALANCE_CACHE_TTL_MS);
+    std::time::Duration::from_millis(ms)
+}
+
 // Estrutura para armazenar snapshot detalhado de cada exchange
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ExchangeSnapshotDetail {
@@ -31,50 +49,66 @@ pub async fn get_user_balances(
     
     if exchanges.is_empty() {
         log::info!("No exchanges found for user {}", user_id);
-        return Ok(BalanceResponse {
-            success: true,
-            exchanges: vec![],
-            total_usd: 0.0,
-            timestamp: chrono::Utc::now().timestamp(),
-        });
+        return Ok(BalanceResponse::empty());
     }
     
     log::info!("Found {} exchanges for user {}", exchanges.len(), user_id);
     
-    // Fetch balances from all exchanges in parallel
+    Ok(fetch_all_exchange_balances(exchanges).await)
+}
+
+/// Busca os balances de todas as exchanges em paralelo
+async fn fetch_all_exchange_balances(exchanges: Vec<DecryptedExchange>) -> BalanceResponse {
     let tasks: Vec<_> = exchanges
         .into_iter()
-        .map(|exchange| tokio::spawn(async move { fetch_exchange_balance(exchange).await }))
+        .map(|exchange| {
+            let key = (exchange.exchange_id.clone(), exchange.name.clone());
+            (key, tokio::spawn(async move { fetch_exchange_balance(exchange).await }))
+        })
         .collect();
     
-    let results = join_all(tasks).await;
+    let (keys, handles): (Vec<_>, Vec<_>) = tasks.into_iter().unzip();
+    let results = join_all(handles).await;
     
-    let mut exchange_balances = Vec::new();
-    let mut total_usd = 0.0;
+    let outcomes = keys.into_iter().zip(results)
+        .map(|((exchange_id, exchange), result)| {
+            let result = result
+                .map_err(|e| format!("Task join error: {}", e))
+                .and_then(|r| r);
+            (exchange_id, exchange, result)
+        })
+        .collect();
+    
+    build_balance_response(outcomes)
+}
+
+/// Consolida os resultados por exchange. Exchanges que falharam não entram no
+/// `total_usd` e são listadas em `errors`, com `partial = true`.
+pub fn build_balance_response(outcomes: Vec<(String, String, Result<ExchangeBalance, String>)>) -> BalanceResponse {
+    let mut response = BalanceResponse::empty();
     
-    for result in results {
+    for (exchange_id, exchange, result) in outcomes {
         match result {
-            Ok(Ok(balance)) => {
+            Ok(balance) if balance.success => {
                 // ✅ OPTIMIZATION: Retorna TODOS os balances, deixa frontend filtrar
-                total_usd += balance.total_usd;
-                exchange_balances.push(balance);
+                response.total_usd += balance.total_usd;
+                response.exchanges.push(balance);
             }
-            Ok(Err(e)) => {
-                log::error!("Error fetching exchange balance: {}", e);
-                // Continue with other exchanges
+            Ok(balance) => {
+                let error = balance.error.clone().unwrap_or_else(|| "Unknown error".to_string());
+                log::error!("Error fetching exchange balance from {}: {}", exchange, error);
+                response.errors.push(ExchangeBalanceError { exchange_id, exchange, error });
+                response.exchanges.push(balance);
             }
-            Err(e) => {
-                log::error!("Task join error: {}", e);
+            Err(error) => {
+                log::error!("Error fetching exchange balance from {}: {}", exchange, error);
+                response.errors.push(ExchangeBalanceError { exchange_id, exchange, error });
             }
         }
     }
     
-    Ok(BalanceResponse {
-        success: true,
-        exchanges: exchange_balances,
-        total_usd,
-        timestamp: chrono::Utc::now().timestamp(),
-    })
+    response.partial = !response.errors.is_empty();
+    response
 }
 
 pub async fn get_balance_summary(
@@ -162,58 +196,21 @@ async fn get_user_exchanges_from_db(
     
     log::debug!("Fetched {} exchange catalogs from database", catalog_map.len());
     
-    // 🚀 FASE 2: Paraleliza descriptografia - 5-10x mais rápido!
-    let decrypt_tasks: Vec<_> = active_exchanges
+    // 🚀 FASE 2: Descriptografa todas as credenciais num único lote (uma aquisição do GIL)
+    let items: Vec<_> = active_exchanges
         .into_iter()
         .filter_map(|user_exchange| {
             let exchange_oid = ObjectId::parse_str(&user_exchange.exchange_id).ok()?;
             let catalog = catalog_map.get(&exchange_oid)?.clone();
-            let key = encryption_key.clone();
-            
-            Some(tokio::task::spawn_blocking(move || {
-                // Descriptografa API key
-                let api_key = decrypt_fernet_via_python(&user_exchange.api_key_encrypted, &key)
-                    .unwrap_or_else(|e| {
-                        log::error!("Failed to decrypt API key: {}", e);
-                        user_exchange.api_key_encrypted.clone()
-                    });
-                
-                // Descriptografa API secret
-                let api_secret = decrypt_fernet_via_python(&user_exchange.api_secret_encrypted, &key)
-                    .unwrap_or_else(|e| {
-                        log::error!("Failed to decrypt API secret: {}", e);
-                        user_exchange.api_secret_encrypted.clone()
-                    });
-                
-                // Descriptografa passphrase se existir
-                let passphrase = user_exchange.passphrase_encrypted.as_ref()
-                    .and_then(|p| decrypt_fernet_via_python(p, &key).ok());
-                
-                DecryptedExchange {
-                    exchange_id: user_exchange.exchange_id,
-                    ccxt_id: catalog.ccxt_id.clone(),
-                    name: catalog.nome.clone().unwrap_or_else(|| "Unknown".to_string()),
-                    api_key,
-                    api_secret,
-                    passphrase,
-                    is_active: user_exchange.is_active,
-                }
-            }))
+            Some((user_exchange, catalog))
         })
         .collect();
     
-    // Aguarda todas as descriptografias completarem em paralelo
-    let decrypt_results = join_all(decrypt_tasks).await;
-    
-    let mut decrypted_exchanges = Vec::new();
-    for result in decrypt_results {
-        match result {
-            Ok(exchange) => decrypted_exchanges.push(exchange),
-            Err(e) => log::error!("Decryption task failed: {}", e),
-        }
-    }
-    
-    Ok(decrypted_exchanges)
+    tokio::task::spawn_blocking(move || {
+        crate::services::user_exchanges_service::decrypt_exchange_credentials(items, &encryption_key)
+    })
+    .await
+    .map_err(|e| format!("Decryption task failed: {}", e))?
 }
 
 // 🆕 Nova função para processar balances de exchanges enviadas pelo frontend
@@ -221,48 +218,92 @@ pub async fn fetch_balances_from_exchanges(
     exchanges: Vec<DecryptedExchange>,
 ) -> Result<BalanceResponse, String> {
     if exchanges.is_empty() {
-        return Ok(BalanceResponse {
-            success: true,
-            exchanges: vec![],
-            total_usd: 0.0,
-            timestamp: chrono::Utc::now().timestamp(),
-        });
+        return Ok(BalanceResponse::empty());
     }
     
     log::info!("📊 Processing {} exchanges from frontend", exchanges.len());
     
-    // Fetch balances from all exchanges in parallel
-    let tasks: Vec<_> = exchanges
-        .into_iter()
-        .map(|exchange| tokio::spawn(async move { fetch_exchange_balance(exchange).await }))
+    Ok(fetch_all_exchange_balances(exchanges).await)
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct AssetExchangeBalance {
+    pub exchange_id: String,
+    pub exchange: String,
+    pub free: f64,
+    pub used: f64,
+    pub total: f64,
+    pub usd_value: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AssetBalanceResponse {
+    pub success: bool,
+    pub symbol: String,
+    pub free: f64,
+    pub used: f64,
+    pub total: f64,
+    pub usd_value: f64,
+    pub exchanges: Vec<AssetExchangeBalance>,
+    pub timestamp: i64,
+}
+
+/// Agrega um único ativo entre todas as exchanges (zeros se ausente em todas)
+pub fn aggregate_asset_balance(symbol: &str, exchanges: &[ExchangeBalance]) -> AssetBalanceResponse {
+    let symbol = symbol.to_uppercase();
+    let breakdown: Vec<AssetExchangeBalance> = exchanges.iter()
+        .filter_map(|ex| {
+            let balance = ex.balances.iter()
+                .find(|(asset, _)| asset.eq_ignore_ascii_case(&symbol))
+                .map(|(_, b)| b)?;
+            Some(AssetExchangeBalance {
+                exchange_id: ex.exchange_id.clone(),
+                exchange: ex.exchange.clone(),
+                free: balance.free,
+                used: balance.used,
+                total: balance.total,
+                usd_value: balance.usd_value.unwrap_or(0.0),
+            })
+        })
         .collect();
-    
-    let results = join_all(tasks).await;
-    
-    let mut exchange_balances = Vec::new();
-    let mut total_usd = 0.0;
-    
-    for result in results {
-        match result {
-            Ok(Ok(balance)) => {
-                total_usd += balance.total_usd;
-                exchange_balances.push(balance);
-            }
-            Ok(Err(e)) => {
-                log::error!("Error fetching exchange balance: {}", e);
-            }
-            Err(e) => {
-                log::error!("Task join error: {}", e);
-            }
-        }
-    }
-    
-    Ok(BalanceResponse {
+
+    AssetBalanceResponse {
         success: true,
-        exchanges: exchange_balances,
-        total_usd,
+        free: breakdown.iter().map(|b| b.free).sum(),
+        used: breakdown.iter().map(|b| b.used).sum(),
+        total: breakdown.iter().map(|b| b.total).sum(),
+        usd_value: breakdown.iter().map(|b| b.usd_value).sum(),
+        symbol,
+        exchanges: breakdown,
         timestamp: chrono::Utc::now().timestamp(),
-    })
+    }
+}
+
+/// Saldo de um ativo agregado entre as exchanges do usuário
+pub async fn get_asset_balance(db: &MongoDB, user_id: &str, symbol: &str) -> Result<AssetBalanceResponse, String> {
+    let exchanges = crate::services::user_exchanges_service::get_user_exchanges_decrypted(db, user_id).await?;
+    let response = fetch_balances_from_exchanges(exchanges).await?;
+    crate::services::credential_health_service::report_balance_results(db, user_id, &response.exchanges).await;
+    Ok(aggregate_asset_balance(symbol, &response.exchanges))
+}
+
+/// Converte BRL→USD com a última taxa cacheada quando a busca falha.
+/// Sem taxa conhecida devolve erro: o total em BRL não pode sair como `total_usd`.
+fn convert_with_cached_brl_rate(total_brl: f64, reason: &str) -> Result<f64, String> {
+    brl_total_to_usd(total_brl, crate::services::exchange_rate_service::last_cached_rate("BRL", "USD"), reason)
+}
+
+fn brl_total_to_usd(total_brl: f64, cached_rate: Option<f64>, reason: &str) -> Result<f64, String> {
+    match cached_rate {
+        Some(rate) => {
+            log::warn!("⚠️  [NovaDAX] {}. Using last cached rate {:.6}", reason, rate);
+            Ok(total_brl * rate)
+        }
+        None => {
+            log::error!("❌ [NovaDAX] {} and no cached BRL/USD rate. Total left out of total_usd", reason);
+            Err(format!("BRL/USD rate unavailable ({}); total could not be converted to USD", reason))
+        }
+    }
 }
 
 /// 🚀 OTIMIZAÇÃO: Retorna timeout ideal baseado na performance histórica de cada exchange
@@ -290,8 +331,70 @@ fn get_optimal_timeout(exchange_id: &str) -> std::time::Duration {
     }
 }
 
+/// Chave do cache de balances: a conta é a exchange + credencial (a api key
+/// entra apenas como hash, para não ficar em texto puro no mapa)
+fn api_key_hash(api_key: &str) -> String {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    api_key.hash(&mut hasher);
+    format!("{:x}", hasher.finish())
+}
+
+fn balance_cache_key(exchange: &DecryptedExchange) -> (String, String) {
+    (exchange.exchange_id.clone(), api_key_hash(&exchange.api_key))
+}
+
+/// Remove do cache os balances da conta (mesma API key); chamado após enviar
+/// ou cancelar ordens, que mudam `free`/`used` antes do TTL expirar.
+pub fn invalidate_account_balance(api_key: &str) {
+    invalidate_account_balance_in(&BALANCE_CACHE, api_key);
+}
+
+fn invalidate_account_balance_in(cache: &TickerCache<ExchangeBalance>, api_key: &str) {
+    let hash = api_key_hash(api_key);
+    cache.invalidate_where(|_, key_hash| key_hash == hash);
+}
+
+/// Serve o balance do cache enquanto estiver no TTL, marcando `from_cache`
+/// e mantendo o `fetched_at` da busca original. Balances com falha não são cacheados.
+pub async fn cached_exchange_balance<F, Fut>(
+    cache: &TickerCache<ExchangeBalance>,
+    key: &(String, String),
+    fetch: F,
+) -> Result<ExchangeBalance, String>
+where
+    F: FnOnce() -> Fut,
+    Fut: std::future::Future<Output = Result<ExchangeBalance, String>>,
+{
+    let mut fetched = false;
+    let mut failed = None;
+    let result = cache.get_or_fetch(&key.0, &key.1, || {
+        fetched = true;
+        let failed = &mut failed;
+        async move {
+            let balance = fetch().await?;
+            if balance.success {
+                return Ok(balance);
+            }
+            let error = balance.error.clone().unwrap_or_default();
+            *failed = Some(balance);
+            Err(error)
+        }
+    }).await;
+
+    match (result, failed) {
+        (_, Some(balance)) => Ok(balance),
+        (Ok(mut balance), None) => {
+            balance.from_cache = !fetched;
+            Ok(balance)
+        }
+        (Err(e), None) => Err(e),
+    }
+}
+
 async fn fetch_exchange_balance(exchange: DecryptedExchange) -> Result<ExchangeBalance, String> {
-    fetch_exchange_balance_with_retry(exchange, 3).await
+    let key = balance_cache_key(&exchange);
+    cached_exchange_balance(&BALANCE_CACHE, &key, || fetch_exchange_balance_with_retry(exchange, 3)).await
 }
 
 async fn fetch_exchange_balance_with_retry(exchange: DecryptedExchange, max_retries: u32) -> Result<ExchangeBalance, String> {
@@ -323,16 +426,13 @@ async fn fetch_exchange_balance_with_retry(exchange: DecryptedExchange, max_retr
             api_secret: exchange.api_secret.clone(),
             passphrase: exchange.passphrase.clone(),
             is_active: exchange.is_active,
+            can_trade: exchange.can_trade,
+            sub_account: exchange.sub_account.clone(),
         };
     
         // 🚀 FASE 3: Usa thread pool dedicado ao invés de tokio::spawn_blocking
-        let balance_task = spawn_ccxt_blocking(move || {
-            let client = CCXTClient::new(
-                &exchange_clone.ccxt_id,
-                &exchange_clone.api_key,
-                &exchange_clone.api_secret,
-                exchange_clone.passphrase.as_deref(),
-            )?;
+        let balance_task = spawn_ccxt_paced(&exchange.ccxt_id, move || {
+            let client = CCXTClient::for_exchange(&exchange_clone)?;
             
             client.fetch_balance_sync()
         });
@@ -350,6 +450,8 @@ async fn fetch_exchange_balance_with_retry(exchange: DecryptedExchange, max_retr
                     error: Some("Request timeout after 60s".to_string()),
                     balances: HashMap::new(),
                     total_usd: 0.0,
+                    fetched_at: chrono::Utc::now().timestamp(),
+                    from_cache: false,
                 });
             }
         };
@@ -385,6 +487,8 @@ async fn fetch_exchange_balance_with_retry(exchange: DecryptedExchange, max_retr
                     error: Some(error_str),
                     balances: HashMap::new(),
                     total_usd: 0.0,
+                    fetched_at: chrono::Utc::now().timestamp(),
+                    from_cache: false,
                 });
             }
             Ok(_) => {
@@ -448,6 +552,7 @@ async fn fetch_exchange_balance_with_retry(exchange: DecryptedExchange, max_retr
             }
             
             let mut total_usd: f64 = balances.values().map(|b| b.usd_value.unwrap_or(0.0)).sum();
+            let mut conversion: Result<f64, String> = Ok(total_usd);
             
             // 🚀 FASE 2: Lazy conversion - spawna task apenas se for NovaDAX
             if exchange_name.to_lowercase() == "novadax" {
@@ -460,19 +565,34 @@ async fn fetch_exchange_balance_with_retry(exchange: DecryptedExchange, max_retr
                 ).await {
                     Ok(Ok(rate)) => {
                         let original_total = total_usd;
-                        total_usd = original_total * rate;
+                        conversion = Ok(original_total * rate);
                         log::debug!("🇧🇷 [NovaDAX] Converted: R$ {:.2} × {:.6} = ${:.2}", 
-                            original_total, rate, total_usd);
+                            original_total, rate, original_total * rate);
                     }
                     Ok(Err(e)) => {
-                        log::warn!("⚠️  [NovaDAX] Failed to fetch rate: {}. Using fallback 0.20", e);
-                        total_usd *= 0.20;
+                        conversion = convert_with_cached_brl_rate(total_usd, &format!("rate fetch failed: {}", e));
                     }
                     Err(_) => {
-                        log::warn!("⚠️  [NovaDAX] Rate fetch timeout. Using fallback 0.20");
-                        total_usd *= 0.20;
+                        conversion = convert_with_cached_brl_rate(total_usd, "rate fetch timeout");
                     }
                 }
+                if let Ok(converted) = conversion {
+                    total_usd = converted;
+                }
+            }
+
+            // Sem taxa BRL/USD o total não vai como USD: a exchange sai do total (parcial)
+            if let Err(e) = conversion {
+                return Ok(ExchangeBalance {
+                    exchange: exchange_name.clone(),
+                    exchange_id: exchange_id.clone(),
+                    success: false,
+                    error: Some(e),
+                    balances,
+                    total_usd: 0.0,
+                    fetched_at: chrono::Utc::now().timestamp(),
+                    from_cache: false,
+                });
             }
             
             log::info!("Successfully fetched {} balances from {}", balances.len(), exchange_name);
@@ -484,6 +604,8 @@ async fn fetch_exchange_balance_with_retry(exchange: DecryptedExchange, max_retr
                 error: None,
                 balances,
                 total_usd,
+                fetched_at: chrono::Utc::now().timestamp(),
+                from_cache: false,
             })
         }
         Err(e) => {
@@ -495,6 +617,8 @@ async fn fetch_exchange_balance_with_retry(exchange: DecryptedExchange, max_retr
                 error: Some(e.to_string()),
                 balances: HashMap::new(),
                 total_usd: 0.0,
+                fetched_at: chrono::Utc::now().timestamp(),
+                from_cache: false,
             })
         }
     }
@@ -541,6 +665,8 @@ pub async fn get_exchange_balance(
         api_secret: encrypted_secret,
         passphrase: None,
         is_active: true,
+        can_trade: None,
+        sub_account: None,
     };
     
     fetch_exchange_balance(decrypted).await
@@ -578,9 +704,14 @@ pub async fn get_market_movers(
 #[derive(serde::Serialize)]
 pub struct DailyPnLResponse {
     pub user_id: String,
+    /// Moeda de `today`, `yesterday`, `pnl` e `pnl_percent`; os campos `*_usd` ficam sempre em USD
+    pub currency: String,
     pub today_usd: String,
     pub yesterday_usd: String,
     pub pnl_usd: String,
+    pub today: String,
+    pub yesterday: String,
+    pub pnl: String,
     pub pnl_percent: String,
     pub is_profit: Option<bool>,
     pub _raw: DailyPnLRaw,
@@ -594,10 +725,49 @@ pub struct DailyPnLRaw {
     pub pnl_percent: f64,
 }
 
+/// Monta a resposta a partir dos totais em USD; `usd_rate` converte para `currency`
+pub fn build_daily_pnl_response(
+    user_id: &str, today_usd: f64, yesterday_usd: f64, currency: &str, usd_rate: f64,
+) -> DailyPnLResponse {
+    let pnl_usd = today_usd - yesterday_usd;
+    let pnl_percent = if yesterday_usd != 0.0 && yesterday_usd.abs() > 0.01 {
+        (pnl_usd / yesterday_usd) * 100.0
+    } else {
+        0.0
+    };
+    
+    let is_profit = if pnl_usd == 0.0 {
+        None // No change
+    } else {
+        Some(pnl_usd > 0.0)
+    };
+    
+    let local = |usd: f64| format_currency(usd * usd_rate, currency);
+    DailyPnLResponse {
+        user_id: user_id.to_string(),
+        currency: currency.to_string(),
+        today_usd: format_currency(today_usd, "USD"),
+        yesterday_usd: format_currency(yesterday_usd, "USD"),
+        pnl_usd: format_currency(pnl_usd, "USD"),
+        today: local(today_usd),
+        yesterday: local(yesterday_usd),
+        pnl: local(pnl_usd),
+        pnl_percent: format_number(pnl_percent, 2, currency),
+        is_profit,
+        _raw: DailyPnLRaw {
+            today_usd,
+            yesterday_usd,
+            pnl_usd,
+            pnl_percent,
+        },
+    }
+}
+
 pub async fn get_daily_pnl(
     db: &MongoDB,
     user_id: &str,
     date: &str,
+    currency: &str,
 ) -> Result<DailyPnLResponse, String> {
     log::info!("📊 Getting daily PNL for user: {}, date: {}", user_id, date);
     
@@ -645,9 +815,10 @@ pub async fn get_daily_pnl(
         let current_balance = get_user_balances(db, user_id).await
             .map_err(|e| format!("Failed to get current balance: {}", e))?;
         
-        // 💾 Auto-save snapshot for today to improve future queries
-        let save_result = save_balance_snapshot_custom(db, user_id, Some(&today_str), Some(current_balance.total_usd)).await;
-        if let Err(e) = save_result {
+        // 💾 Auto-save snapshot for today to improve future queries (nunca com total incompleto)
+        if current_balance.partial {
+            log::warn!("   ⚠️  Skipping auto-save: {} exchange(s) failed, total is incomplete", current_balance.errors.len());
+        } else if let Err(e) = save_balance_snapshot_custom(db, user_id, Some(&today_str), Some(current_balance.total_usd)).await {
             log::warn!("   ⚠️  Failed to auto-save today's snapshot: {}", e);
         }
         
@@ -685,38 +856,23 @@ pub async fn get_daily_pnl(
         })
     };
     
-    // Calculate PNL with precision
     let pnl_usd = today_usd - yesterday_usd;
-    let pnl_percent = if yesterday_usd != 0.0 && yesterday_usd.abs() > 0.01 {
-        (pnl_usd / yesterday_usd) * 100.0
-    } else {
-        0.0
-    };
+    log::info!("   💰 PNL: ${:.2}", pnl_usd);
     
-    let is_profit = if pnl_usd == 0.0 {
-        None // No change
+    // ✅ Texto formatado na moeda pedida (2 casas); sem câmbio disponível fica em USD
+    let currency = currency.trim().to_uppercase();
+    let (currency, rate) = if currency.is_empty() || currency == "USD" {
+        ("USD".to_string(), 1.0)
     } else {
-        Some(pnl_usd > 0.0)
+        match crate::services::exchange_rate_service::get_exchange_rate("USD", &currency).await {
+            Ok(rate) => (currency, rate),
+            Err(e) => {
+                log::warn!("   ⚠️  No USD→{} rate, formatting daily PNL in USD: {}", currency, e);
+                ("USD".to_string(), 1.0)
+            }
+        }
     };
-    
-    log::info!("   💰 PNL: ${:.2} ({:.2}%) - profit: {:?}", 
-        pnl_usd, pnl_percent, is_profit);
-    
-    // ✅ Valores monetários USD sempre com 2 casas decimais
-    Ok(DailyPnLResponse {
-        user_id: user_id.to_string(),
-        today_usd: format!("{:.2}", today_usd),
-        yesterday_usd: format!("{:.2}", yesterday_usd),
-        pnl_usd: format!("{:.2}", pnl_usd),
-        pnl_percent: format!("{:.2}", pnl_percent),
-        is_profit,
-        _raw: DailyPnLRaw {
-            today_usd,
-            yesterday_usd,
-            pnl_usd,
-            pnl_percent,
-        },
-    })
+    Ok(build_daily_pnl_response(user_id, today_usd, yesterday_usd, &currency, rate))
 }
 
 // Auto-save daily snapshot (only once per day)
@@ -751,6 +907,56 @@ pub async fn save_balance_snapshot(
     save_balance_snapshot_custom(db, user_id, None, None).await
 }
 
+const DEFAULT_SNAPSHOT_TOTAL_DECIMALS: u32 = 2;
+const DEFAULT_SNAPSHOT_MAX_PARTIAL_DROP_PERCENT: f64 = 20.0;
+
+/// Casas decimais do `total_usd` gravado (`SNAPSHOT_TOTAL_DECIMALS`)
+pub fn snapshot_total_decimals() -> u32 {
+    env::var("SNAPSHOT_TOTAL_DECIMALS").ok()
+        .and_then(|v| v.parse::<u32>().ok())
+        .unwrap_or(DEFAULT_SNAPSHOT_TOTAL_DECIMALS)
+}
+
+/// Queda máxima (%) aceita ao sobrescrever um snapshot completo com um total
+/// parcial (`SNAPSHOT_MAX_PARTIAL_DROP_PERCENT`)
+pub fn snapshot_max_partial_drop_percent() -> f64 {
+    env::var("SNAPSHOT_MAX_PARTIAL_DROP_PERCENT").ok()
+        .and_then(|v| v.parse::<f64>().ok())
+        .filter(|p| p.is_finite() && *p >= 0.0)
+        .unwrap_or(DEFAULT_SNAPSHOT_MAX_PARTIAL_DROP_PERCENT)
+}
+
+/// Snapshot já gravado para a data
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PriorSnapshot {
+    pub total_usd: f64,
+    pub partial: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SnapshotWrite {
+    /// Grava o total (marcado `partial` quando alguma exchange falhou)
+    Save { total_usd: f64, partial: bool },
+    /// Mantém o snapshot anterior: o total novo está incompleto e caiu demais
+    KeepPrevious { previous_usd: f64, drop_percent: f64 },
+}
+
+/// Decide o que gravar. Um total parcial só perde para um snapshot completo
+/// anterior quando cai mais que `max_drop_percent` — aí o anterior é mantido
+/// para não contaminar o PNL dos dias seguintes.
+pub fn guard_snapshot_total(
+    total_usd: f64, partial: bool, prior: Option<PriorSnapshot>, max_drop_percent: f64, decimals: u32,
+) -> SnapshotWrite {
+    let total_usd = crate::utils::precision::round_to(total_usd, decimals);
+    if let Some(prior) = prior.filter(|p| partial && !p.partial && p.total_usd > 0.0) {
+        let drop_percent = (prior.total_usd - total_usd) / prior.total_usd * 100.0;
+        if drop_percent > max_drop_percent {
+            return SnapshotWrite::KeepPrevious { previous_usd: prior.total_usd, drop_percent };
+        }
+    }
+    SnapshotWrite::Save { total_usd, partial }
+}
+
 // Save daily balance snapshot with custom date and/or balance
 // 🔥 VERSÃO DINÂMICA: Salva detalhes de CADA exchange (ativas E inativas)
 pub async fn save_balance_snapshot_custom(
@@ -774,6 +980,7 @@ pub async fn save_balance_snapshot_custom(
     
     // Se tem balance customizado, usa formato simples (compatibilidade com dados antigos)
     if let Some(balance) = custom_balance {
+        let balance = crate::utils::precision::round_to(balance, snapshot_total_decimals());
         log::info!("   Using custom balance: ${:.2} (simple format)", balance);
         
         let collection = db.collection::<mongodb::bson::Document>("balance_snapshots");
@@ -813,6 +1020,10 @@ pub async fn save_balance_snapshot_custom(
     
     let current_balance_response = get_user_balances(db, user_id).await
         .map_err(|e| format!("Failed to get current balance: {}", e))?;
+    let partial = current_balance_response.partial;
+    let failed_exchanges: Vec<String> = current_balance_response.errors.iter()
+        .map(|e| e.exchange.clone())
+        .collect();
     
     let mut exchanges_details = Vec::new();
     let mut total_active_usd = 0.0;
@@ -859,6 +1070,31 @@ pub async fn save_balance_snapshot_custom(
         "user_id": user_id,
         "date": &date,
     };
+
+    // 🛡️ Total parcial não sobrescreve um snapshot completo muito maior
+    let prior = if partial {
+        collection.find_one(filter.clone()).await
+            .map_err(|e| format!("Failed to read snapshot: {}", e))?
+            .and_then(|d| d.get_f64("total_usd").ok().map(|total_usd| PriorSnapshot {
+                total_usd,
+                partial: d.get_bool("partial").unwrap_or(false),
+            }))
+    } else {
+        None
+    };
+    let total_active_usd = match guard_snapshot_total(
+        total_active_usd, partial, prior, snapshot_max_partial_drop_percent(), snapshot_total_decimals(),
+    ) {
+        SnapshotWrite::Save { total_usd, .. } => total_usd,
+        SnapshotWrite::KeepPrevious { previous_usd, drop_percent } => {
+            log::warn!("🛡️ Keeping snapshot {} at ${:.2}: partial total ${:.2} is {:.1}% lower (failed: {})",
+                date, previous_usd, total_active_usd, drop_percent, failed_exchanges.join(", "));
+            return Ok(());
+        }
+    };
+    if partial {
+        log::warn!("   ⚠️  Saving partial snapshot (failed: {})", failed_exchanges.join(", "));
+    }
     
     let update = doc! {
         "$set": {
@@ -868,6 +1104,8 @@ pub async fn save_balance_snapshot_custom(
             "timestamp": timestamp,
             "updated_at": mongodb::bson::DateTime::now(),
             "exchanges": exchanges_bson, // 🔥 Array com detalhes
+            "partial": partial,
+            "failed_exchanges": &failed_exchanges,
         }
     };
     
@@ -886,3 +1124,405 @@ pub async fn save_balance_snapshot_custom(
     
     Ok(())
 }
+
+// ==================== DUST SWEEP ====================
+
+/// Valor (USD) padrão abaixo do qual um saldo é considerado "poeira"
+pub const DEFAULT_DUST_THRESHOLD_USD: f64 = 10.0;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SweepItem {
+    pub asset: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+    pub amount: f64,
+    pub usd_value: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SweepResult {
+    pub success: bool,
+    pub exchange_id: String,
+    pub target: String,
+    pub swept: Vec<SweepItem>,
+    pub skipped: Vec<SweepItem>,
+    pub total_swept_usd: f64,
+}
+
+/// Preço de `asset` cotado em `target`, a partir dos preços em USD.
+/// Stablecoins de dólar valem 1; sem preço do alvo retorna 0.
+pub fn price_in_target(asset_price_usd: f64, target: &str, prices: &HashMap<String, f64>) -> f64 {
+    let target = target.to_uppercase();
+    let target_usd = if crate::services::order_service::USD_QUOTES.contains(&target.as_str()) {
+        1.0
+    } else {
+        prices.get(&target).copied().unwrap_or(0.0)
+    };
+    if target_usd > 0.0 { asset_price_usd / target_usd } else { 0.0 }
+}
+
+/// Decide se um saldo pode ser varrido para `target`.
+/// O limite de poeira é em USD; o `min_cost` da exchange é comparado com o
+/// valor em `target` (a quote do mercado), via `price_target`.
+/// Retorna o símbolo do mercado direto (`ASSET/TARGET`) ou o motivo do skip.
+pub fn plan_dust_sell(
+    balance: &Balance, price_usd: f64, price_target: f64, target: &str, threshold_usd: f64,
+    markets: &HashMap<String, crate::models::MarketLimits>,
+) -> Result<String, String> {
+    let usd_value = balance.free * price_usd;
+    if balance.free <= 0.0 || price_usd <= 0.0 {
+        return Err("no free balance or price".to_string());
+    }
+    if usd_value >= threshold_usd {
+        return Err(format!("not dust (${:.2} >= ${:.2})", usd_value, threshold_usd));
+    }
+    if price_target <= 0.0 {
+        return Err(format!("no {} price to value the order", target.to_uppercase()));
+    }
+
+    let symbol = format!("{}/{}", balance.symbol.to_uppercase(), target.to_uppercase());
+    let limits = markets.get(&symbol)
+        .ok_or_else(|| format!("no direct market {}", symbol))?;
+
+    if let Some(min_amount) = limits.min_amount {
+        if balance.free < min_amount {
+            return Err(format!("below exchange minimum amount ({} < {})", balance.free, min_amount));
+        }
+    }
+    if let Some(min_cost) = limits.min_cost {
+        let quote_value = balance.free * price_target;
+        if quote_value < min_cost {
+            return Err(format!(
+                "below exchange minimum order value ({:.8} < {:.8} {})",
+                quote_value, min_cost, target.to_uppercase()
+            ));
+        }
+    }
+
+    Ok(symbol)
+}
+
+/// Executa o sweep: vende a mercado cada poeira elegível via `sell(symbol, amount)`.
+pub async fn execute_dust_sweep<F, Fut>(
+    exchange_id: &str, balances: &HashMap<String, Balance>, prices: &HashMap<String, f64>,
+    target: &str, threshold_usd: f64,
+    markets: &HashMap<String, crate::models::MarketLimits>, sell: F,
+) -> SweepResult
+where
+    F: Fn(String, f64) -> Fut,
+    Fut: std::future::Future<Output = Result<crate::services::strategy_service::OrderResult, String>>,
+{
+    let mut swept = Vec::new();
+    let mut skipped = Vec::new();
+
+    let mut assets: Vec<&Balance> = balances.values()
+        .filter(|b| !b.symbol.eq_ignore_ascii_case(target) && b.free > 0.0)
+        .collect();
+    assets.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+
+    for balance in assets {
+        let price = prices.get(&balance.symbol.to_uppercase()).copied().unwrap_or(0.0);
+        let mut item = SweepItem {
+            asset: balance.symbol.clone(), symbol: None,
+            amount: balance.free, usd_value: balance.free * price,
+            order_id: None, reason: None,
+        };
+
+        let price_target = price_in_target(price, target, prices);
+        match plan_dust_sell(balance, price, price_target, target, threshold_usd, markets) {
+            Ok(symbol) => {
+                item.symbol = Some(symbol.clone());
+                match sell(symbol, balance.free).await {
+                    Ok(order) => {
+                        log::info!("🧹 Swept {} {} (${:.2})", item.amount, item.asset, item.usd_value);
+                        item.order_id = Some(order.order_id);
+                        swept.push(item);
+                    }
+                    Err(e) => {
+                        log::warn!("⚠️ Failed to sweep {}: {}", item.asset, e);
+                        item.reason = Some(format!("order failed: {}", e));
+                        skipped.push(item);
+                    }
+                }
+            }
+            Err(reason) => {
+                // Saldos acima do limite não são poeira — não poluem o relatório
+                if item.usd_value < threshold_usd {
+                    item.reason = Some(reason);
+                    skipped.push(item);
+                }
+            }
+        }
+    }
+
+    let total_swept_usd = swept.iter().map(|i| i.usd_value).sum();
+    SweepResult {
+        success: true,
+        exchange_id: exchange_id.to_string(),
+        target: target.to_uppercase(),
+        swept, skipped, total_swept_usd,
+    }
+}
+
+/// 🧹 Converte poeira (saldos < `threshold_usd`) em `target` via ordens a mercado.
+/// Exige que a API key tenha permissão de trade.
+pub async fn sweep_dust(
+    db: &MongoDB, user_id: &str, exchange_id: &str, target: &str, threshold_usd: f64,
+) -> Result<SweepResult, String> {
+    let exchange = crate::services::user_exchanges_service::get_user_exchanges_decrypted(db, user_id).await?
+        .into_iter()
+        .find(|e| e.exchange_id == exchange_id)
+        .ok_or_else(|| format!("Exchange {} not found", exchange_id))?;
+
+    log::info!("🧹 Dust sweep on {} → {} (threshold ${:.2})", exchange.name, target, threshold_usd);
+
+    let ex = exchange.clone();
+
+    let (balances, prices, markets) = spawn_ccxt_paced(&exchange.ccxt_id, move || {
+        let client = CCXTClient::for_exchange(&ex)?;
+
+        let permissions = client.check_api_permissions()
+            .map_err(|e| format!("Could not verify trade permission: {}", e))?;
+        if !permissions.can_trade {
+            return Err("Trade permission required: API key cannot place orders".to_string());
+        }
+
+        let balances = client.fetch_balance_sync()?;
+        let prices = client.fetch_tickers_sync()?;
+        let markets = client.fetch_market_limits_sync()?;
+        Ok((balances, prices, markets))
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))??;
+
+    let result = execute_dust_sweep(
+        exchange_id, &balances, &prices, target, threshold_usd, &markets,
+        |symbol, amount| {
+            let exchange = exchange.clone();
+            async move {
+                crate::services::strategy_service::execute_order(&exchange, crate::models::StrategyMode::Spot, &symbol, "market", "sell", amount, None).await
+            }
+        },
+    ).await;
+
+    log::info!("✅ Dust sweep done: {} swept (${:.2}), {} skipped",
+        result.swept.len(), result.total_swept_usd, result.skipped.len());
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::MarketLimits;
+    use crate::services::strategy_service::OrderResult;
+
+    fn balance(symbol: &str, free: f64) -> Balance {
+        Balance { symbol: symbol.into(), free, used: 0.0, total: free, usd_value: None, change_24h: None, parse_error: None }
+    }
+
+    #[test]
+    fn test_partial_fetch_does_not_overwrite_complete_snapshot_with_lower_total() {
+        let complete = Some(PriorSnapshot { total_usd: 10_000.0, partial: false });
+
+        // Uma exchange falhou: total caiu 60% → mantém o anterior
+        assert_eq!(
+            guard_snapshot_total(4_000.0, true, complete, 20.0, 2),
+            SnapshotWrite::KeepPrevious { previous_usd: 10_000.0, drop_percent: 60.0 },
+        );
+        // Queda pequena ou sem snapshot anterior: grava marcado como parcial
+        assert_eq!(guard_snapshot_total(9_500.004, true, complete, 20.0, 2), SnapshotWrite::Save { total_usd: 9_500.0, partial: true });
+        assert_eq!(guard_snapshot_total(4_000.0, true, None, 20.0, 2), SnapshotWrite::Save { total_usd: 4_000.0, partial: true });
+        // Busca completa sempre grava, mesmo com queda real
+        assert_eq!(guard_snapshot_total(4_000.0, false, complete, 20.0, 2), SnapshotWrite::Save { total_usd: 4_000.0, partial: false });
+        // Anterior também parcial não é protegido
+        let partial_prior = Some(PriorSnapshot { total_usd: 10_000.0, partial: true });
+        assert!(matches!(guard_snapshot_total(4_000.0, true, partial_prior, 20.0, 2), SnapshotWrite::Save { .. }));
+    }
+
+    fn exchange_balance(id: &str, balances: Vec<Balance>) -> ExchangeBalance {
+        ExchangeBalance {
+            exchange: id.to_uppercase(), exchange_id: id.into(), success: true, error: None,
+            total_usd: 0.0, fetched_at: 0, from_cache: false,
+            balances: balances.into_iter().map(|b| (b.symbol.clone(), b)).collect(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cached_balance_keeps_original_fetch_time() {
+        let cache = TickerCache::new(std::time::Duration::from_secs(60));
+        let key = ("ex1".to_string(), "k".to_string());
+        let calls = std::sync::atomic::AtomicU32::new(0);
+        let fetch = || async {
+            calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            let mut balance = exchange_balance("binance", vec![balance("BTC", 1.0)]);
+            balance.fetched_at = chrono::Utc::now().timestamp();
+            Ok(balance)
+        };
+
+        let fresh = cached_exchange_balance(&cache, &key, fetch).await.unwrap();
+        assert!(!fresh.from_cache);
+        assert!((chrono::Utc::now().timestamp() - fresh.fetched_at).abs() <= 1);
+
+        let cached = cached_exchange_balance(&cache, &key, fetch).await.unwrap();
+        assert!(cached.from_cache);
+        assert_eq!(cached.fetched_at, fresh.fetched_at);
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_order_placement_invalidates_cached_account_balance() {
+        let cache = TickerCache::new(std::time::Duration::from_secs(60));
+        let key = ("ex1".to_string(), api_key_hash("key-a"));
+        let other = ("ex2".to_string(), api_key_hash("key-b"));
+        let fetch = |free: f64| move || async move { Ok(exchange_balance("binance", vec![balance("USDT", free)])) };
+
+        cached_exchange_balance(&cache, &key, fetch(100.0)).await.unwrap();
+        cached_exchange_balance(&cache, &other, fetch(50.0)).await.unwrap();
+
+        invalidate_account_balance_in(&cache, "key-a");
+
+        let after = cached_exchange_balance(&cache, &key, fetch(40.0)).await.unwrap();
+        assert!(!after.from_cache);
+        assert_eq!(after.balances["USDT"].free, 40.0);
+        let untouched = cached_exchange_balance(&cache, &other, fetch(0.0)).await.unwrap();
+        assert!(untouched.from_cache);
+        assert_eq!(untouched.balances["USDT"].free, 50.0);
+    }
+
+    #[test]
+    fn test_brl_total_without_rate_is_not_reported_as_usd() {
+        assert!((brl_total_to_usd(1_000.0, Some(0.2), "rate fetch timeout").unwrap() - 200.0).abs() < 1e-9);
+        let err = brl_total_to_usd(1_000.0, None, "rate fetch timeout").unwrap_err();
+        assert!(err.contains("BRL/USD rate unavailable"));
+    }
+
+    #[test]
+    fn test_failed_exchange_marks_balance_response_partial() {
+        let mut binance = exchange_balance("binance", vec![]);
+        binance.success = false;
+        binance.error = Some("Request timeout after 60s".into());
+        let mut bybit = exchange_balance("bybit", vec![balance("BTC", 1.0)]);
+        bybit.total_usd = 65_000.0;
+
+        let response = build_balance_response(vec![
+            ("binance".into(), "Binance".into(), Ok(binance)),
+            ("bybit".into(), "Bybit".into(), Ok(bybit)),
+            ("okx".into(), "OKX".into(), Err("Task join error: panicked".into())),
+        ]);
+
+        assert!(response.partial);
+        assert_eq!(response.total_usd, 65_000.0);
+        let failed: Vec<&str> = response.errors.iter().map(|e| e.exchange.as_str()).collect();
+        assert_eq!(failed, vec!["Binance", "OKX"]);
+        assert_eq!(response.errors[0].error, "Request timeout after 60s");
+
+        // Respostas antigas (sem os campos novos) continuam desserializando
+        let legacy: BalanceResponse = serde_json::from_value(serde_json::json!({
+            "success": true, "exchanges": [], "total_usd": 0.0, "timestamp": 0
+        })).unwrap();
+        assert!(!legacy.partial && legacy.errors.is_empty());
+    }
+
+    #[test]
+    fn test_asset_balance_aggregates_across_exchanges() {
+        let mut btc_a = balance("BTC", 0.5);
+        btc_a.used = 0.1;
+        btc_a.total = 0.6;
+        btc_a.usd_value = Some(36_000.0);
+        let mut btc_b = balance("BTC", 0.25);
+        btc_b.usd_value = Some(15_000.0);
+
+        let exchanges = vec![
+            exchange_balance("binance", vec![btc_a, balance("ETH", 2.0)]),
+            exchange_balance("kraken", vec![btc_b]),
+        ];
+
+        let result = aggregate_asset_balance("btc", &exchanges);
+        assert_eq!(result.symbol, "BTC");
+        assert_eq!(result.exchanges.len(), 2);
+        assert!((result.free - 0.75).abs() < 1e-9);
+        assert!((result.used - 0.1).abs() < 1e-9);
+        assert!((result.total - 0.85).abs() < 1e-9);
+        assert!((result.usd_value - 51_000.0).abs() < 1e-6);
+
+        let absent = aggregate_asset_balance("SOL", &exchanges);
+        assert_eq!((absent.total, absent.usd_value), (0.0, 0.0));
+        assert!(absent.exchanges.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sweep_sells_valid_dust_and_skips_below_minimum() {
+        let balances = HashMap::from([
+            ("DOGE".to_string(), balance("DOGE", 20.0)),   // $2, mercado válido
+            ("SHIB".to_string(), balance("SHIB", 100.0)),  // abaixo do mínimo da exchange
+            ("BTC".to_string(), balance("BTC", 1.0)),      // não é poeira
+            ("USDT".to_string(), balance("USDT", 50.0)),   // moeda alvo
+        ]);
+        let prices = HashMap::from([
+            ("DOGE".to_string(), 0.1), ("SHIB".to_string(), 0.00001), ("BTC".to_string(), 60_000.0),
+        ]);
+        let markets = HashMap::from([
+            ("DOGE/USDT".to_string(), MarketLimits { min_amount: Some(1.0), min_cost: Some(1.0), ..Default::default() }),
+            ("SHIB/USDT".to_string(), MarketLimits { min_amount: Some(1000.0), min_cost: Some(1.0), ..Default::default() }),
+            ("BTC/USDT".to_string(), MarketLimits::default()),
+        ]);
+
+        let sold = std::sync::Mutex::new(Vec::new());
+        let result = execute_dust_sweep("ex1", &balances, &prices, "USDT", 10.0, &markets, |symbol, amount| {
+            sold.lock().unwrap().push((symbol, amount));
+            async {
+                Ok(OrderResult {
+                    order_id: "o1".into(), status: "closed".into(),
+                    filled: None, avg_price: None, cost: None, fee: None,
+                })
+            }
+        }).await;
+
+        assert_eq!(*sold.lock().unwrap(), vec![("DOGE/USDT".to_string(), 20.0)]);
+        assert_eq!(result.swept.len(), 1);
+        assert_eq!(result.swept[0].asset, "DOGE");
+        assert_eq!(result.skipped.len(), 1);
+        assert_eq!(result.skipped[0].asset, "SHIB");
+        assert!(result.skipped[0].reason.as_deref().unwrap().contains("minimum"));
+    }
+
+    #[test]
+    fn test_dust_min_cost_is_checked_in_target_quote() {
+        let prices = HashMap::from([("DOGE".to_string(), 0.1), ("BTC".to_string(), 60_000.0)]);
+        let markets = HashMap::from([
+            ("DOGE/BTC".to_string(), MarketLimits { min_cost: Some(0.0001), ..Default::default() }),
+            ("DOGE/USDT".to_string(), MarketLimits { min_cost: Some(1.0), ..Default::default() }),
+        ]);
+        let doge = balance("DOGE", 20.0); // $2
+
+        // $2 passaria num min_cost de 0.0001 comparado em USD, mas são só ~0.0000333 BTC
+        let in_btc = price_in_target(0.1, "BTC", &prices);
+        assert!((in_btc - 0.1 / 60_000.0).abs() < 1e-15);
+        let err = plan_dust_sell(&doge, 0.1, in_btc, "BTC", 10.0, &markets).unwrap_err();
+        assert!(err.contains("minimum order value") && err.contains("BTC"), "{}", err);
+
+        // Stablecoin alvo: preço em USD já é o preço na quote
+        assert_eq!(price_in_target(0.1, "usdt", &prices), 0.1);
+        assert_eq!(plan_dust_sell(&doge, 0.1, 0.1, "USDT", 10.0, &markets).unwrap(), "DOGE/USDT");
+
+        // Sem preço do alvo não dá para avaliar o mínimo
+        assert_eq!(price_in_target(0.1, "XYZ", &prices), 0.0);
+        assert!(plan_dust_sell(&doge, 0.1, 0.0, "XYZ", 10.0, &markets).is_err());
+    }
+
+    #[test]
+    fn test_daily_pnl_keeps_usd_fields_in_usd_when_formatting_brl() {
+        let response = build_daily_pnl_response("u1", 1_234.56, 1_000.0, "BRL", 5.0);
+        assert_eq!(response.today_usd, "$1,234.56");
+        assert_eq!(response.pnl_usd, "$234.56");
+        assert_eq!(response.today, "R$ 6.172,80");
+        assert_eq!(response.pnl, "R$ 1.172,80");
+        assert_eq!(response.pnl_percent, "23,46");
+        assert_eq!(response._raw.today_usd, 1_234.56);
+        assert_eq!(response.is_profit, Some(true));
+    }
+}
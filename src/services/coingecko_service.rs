@@ -1,8 +1,106 @@
 use serde::{Deserialize, Serialize};
 use reqwest;
+use lazy_static::lazy_static;
 use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use crate::utils::rate_limiter::RateLimiter;
+
+// ==================== API COINGECKO ====================
+// Sem `COINGECKO_API_KEY` usa a API pública (limite agressivo, ~30 req/min).
+// Com a chave usa a API pro (`COINGECKO_PRO_API_BASE` para sobrescrever a URL)
+// enviando o header `x-cg-pro-api-key`. Todas as chamadas passam por um rate
+// limiter compartilhado (`COINGECKO_MIN_INTERVAL_MS`) e as respostas ficam em
+// cache por `COINGECKO_CACHE_TTL_SECS`.
 
 const COINGECKO_API_BASE: &str = "https://api.coingecko.com/api/v3";
+const COINGECKO_PRO_API_BASE: &str = "https://pro-api.coingecko.com/api/v3";
+const PRO_API_KEY_HEADER: &str = "x-cg-pro-api-key";
+const DEFAULT_FREE_INTERVAL_MS: u64 = 2000;
+const DEFAULT_PRO_INTERVAL_MS: u64 = 120;
+const DEFAULT_CACHE_TTL_SECS: u64 = 60;
+const RATE_LIMIT_KEY: &str = "coingecko";
+
+lazy_static! {
+    static ref API: CoinGeckoApi = CoinGeckoApi::from_lookup(|key| std::env::var(key).ok());
+    static ref HTTP_CLIENT: reqwest::Client = reqwest::Client::new();
+    static ref RATE_LIMITER: RateLimiter = RateLimiter::new(API.min_interval, 1);
+    static ref RESPONSE_CACHE: Mutex<HashMap<String, (Instant, serde_json::Value)>> = Mutex::new(HashMap::new());
+}
+
+/// Endpoint e credencial da API em uso
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoinGeckoApi {
+    pub base_url: String,
+    pub api_key: Option<String>,
+    pub min_interval: Duration,
+    pub cache_ttl: Duration,
+}
+
+impl CoinGeckoApi {
+    /// Lê a configuração de `lookup` (env em produção, mapa nos testes)
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let api_key = lookup("COINGECKO_API_KEY").map(|k| k.trim().to_string()).filter(|k| !k.is_empty());
+        let base_url = match api_key {
+            Some(_) => lookup("COINGECKO_PRO_API_BASE").unwrap_or_else(|| COINGECKO_PRO_API_BASE.to_string()),
+            None => COINGECKO_API_BASE.to_string(),
+        };
+        let default_interval = if api_key.is_some() { DEFAULT_PRO_INTERVAL_MS } else { DEFAULT_FREE_INTERVAL_MS };
+        let parse_u64 = |key: &str, default: u64| lookup(key).and_then(|v| v.parse().ok()).unwrap_or(default);
+        CoinGeckoApi {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+            min_interval: Duration::from_millis(parse_u64("COINGECKO_MIN_INTERVAL_MS", default_interval)),
+            cache_ttl: Duration::from_secs(parse_u64("COINGECKO_CACHE_TTL_SECS", DEFAULT_CACHE_TTL_SECS)),
+        }
+    }
+
+    /// GET em `path` (ex: "/search?query=btc") com o header da chave quando configurada
+    pub fn request(&self, client: &reqwest::Client, path: &str) -> reqwest::RequestBuilder {
+        let request = client
+            .get(format!("{}{}", self.base_url, path))
+            .header("Accept", "application/json");
+        match &self.api_key {
+            Some(key) => request.header(PRO_API_KEY_HEADER, key),
+            None => request,
+        }
+    }
+}
+
+/// GET com cache e rate limit compartilhados; `context` entra nas mensagens de erro
+async fn get_json(path: &str, context: &str) -> Result<serde_json::Value, String> {
+    if !API.cache_ttl.is_zero() {
+        let cache = RESPONSE_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((at, value)) = cache.get(path) {
+            if at.elapsed() < API.cache_ttl {
+                log::debug!("🦎 CoinGecko cache hit: {}", path);
+                return Ok(value.clone());
+            }
+        }
+    }
+
+    RATE_LIMITER.acquire(RATE_LIMIT_KEY).await;
+    let response = API.request(&HTTP_CLIENT, path)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to {} from CoinGecko: {}", context, e))?;
+
+    if !response.status().is_success() {
+        return Err(format!("CoinGecko API error: {}", response.status()));
+    }
+
+    let value: serde_json::Value = response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse CoinGecko response: {}", e))?;
+
+    if !API.cache_ttl.is_zero() {
+        let mut cache = RESPONSE_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+        cache.retain(|_, (at, _)| at.elapsed() < API.cache_ttl);
+        cache.insert(path.to_string(), (Instant::now(), value.clone()));
+    }
+    Ok(value)
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CoinGeckoTokenInfo {
@@ -100,24 +198,10 @@ pub async fn get_token_info_from_coingecko(
 ) -> Result<TokenInfoResponse, String> {
     log::info!("🦎 Fetching token info from CoinGecko: {}", coingecko_id);
 
-    let url = format!("{}/coins/{}?localization=false&tickers=false&market_data=true&community_data=false&developer_data=false&sparkline=false", 
-        COINGECKO_API_BASE, coingecko_id);
-
-    let client = reqwest::Client::new();
-    let response = client
-        .get(&url)
-        .header("Accept", "application/json")
-        .send()
-        .await
-        .map_err(|e| format!("Failed to fetch from CoinGecko: {}", e))?;
+    let path = format!("/coins/{}?localization=false&tickers=false&market_data=true&community_data=false&developer_data=false&sparkline=false", 
+        coingecko_id);
 
-    if !response.status().is_success() {
-        return Err(format!("CoinGecko API error: {}", response.status()));
-    }
-
-    let coin_data: CoinGeckoTokenInfo = response
-        .json()
-        .await
+    let coin_data: CoinGeckoTokenInfo = serde_json::from_value(get_json(&path, "fetch").await?)
         .map_err(|e| format!("Failed to parse CoinGecko response: {}", e))?;
 
     // Extract data
@@ -213,24 +297,9 @@ pub async fn get_prices_from_coingecko(
     log::info!("🦎 Fetching prices from CoinGecko for {} tokens", coingecko_ids.len());
 
     let ids_string = coingecko_ids.join(",");
-    let url = format!("{}/simple/price?ids={}&vs_currencies=usd&include_24hr_change=true", 
-        COINGECKO_API_BASE, ids_string);
-
-    let client = reqwest::Client::new();
-    let response = client
-        .get(&url)
-        .header("Accept", "application/json")
-        .send()
-        .await
-        .map_err(|e| format!("Failed to fetch prices from CoinGecko: {}", e))?;
+    let path = format!("/simple/price?ids={}&vs_currencies=usd&include_24hr_change=true", ids_string);
 
-    if !response.status().is_success() {
-        return Err(format!("CoinGecko API error: {}", response.status()));
-    }
-
-    let prices_data: HashMap<String, CoinPrice> = response
-        .json()
-        .await
+    let prices_data: HashMap<String, CoinPrice> = serde_json::from_value(get_json(&path, "fetch prices").await?)
         .map_err(|e| format!("Failed to parse CoinGecko prices: {}", e))?;
 
     let mut result = HashMap::new();
@@ -249,23 +318,9 @@ pub async fn search_token_by_symbol(
 ) -> Result<Vec<CoinGeckoSearchResult>, String> {
     log::info!("🔍 Searching CoinGecko for symbol: {}", symbol);
 
-    let url = format!("{}/search?query={}", COINGECKO_API_BASE, symbol);
-
-    let client = reqwest::Client::new();
-    let response = client
-        .get(&url)
-        .header("Accept", "application/json")
-        .send()
-        .await
-        .map_err(|e| format!("Failed to search CoinGecko: {}", e))?;
-
-    if !response.status().is_success() {
-        return Err(format!("CoinGecko API error: {}", response.status()));
-    }
+    let path = format!("/search?query={}", symbol);
 
-    let search_response: CoinGeckoSearchResponse = response
-        .json()
-        .await
+    let search_response: CoinGeckoSearchResponse = serde_json::from_value(get_json(&path, "search").await?)
         .map_err(|e| format!("Failed to parse CoinGecko search: {}", e))?;
 
     log::info!("✅ Found {} results for '{}'", search_response.coins.len(), symbol);
@@ -290,3 +345,33 @@ pub struct CoinGeckoSearchResult {
     #[serde(default)]
     pub market_cap_rank: Option<u32>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn api(vars: &[(&str, &str)]) -> CoinGeckoApi {
+        let vars: HashMap<String, String> = vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        CoinGeckoApi::from_lookup(|key| vars.get(key).cloned())
+    }
+
+    #[test]
+    fn test_api_key_header_attached_only_when_configured() {
+        let client = reqwest::Client::new();
+
+        let pro = api(&[("COINGECKO_API_KEY", "cg-secret")]);
+        let request = pro.request(&client, "/search?query=btc").build().unwrap();
+        assert_eq!(request.url().as_str(), "https://pro-api.coingecko.com/api/v3/search?query=btc");
+        assert_eq!(request.headers().get(PRO_API_KEY_HEADER).unwrap(), "cg-secret");
+        assert_eq!(pro.min_interval, Duration::from_millis(DEFAULT_PRO_INTERVAL_MS));
+
+        let free = api(&[("COINGECKO_API_KEY", "  ")]);
+        let request = free.request(&client, "/search?query=btc").build().unwrap();
+        assert_eq!(request.url().as_str(), "https://api.coingecko.com/api/v3/search?query=btc");
+        assert!(request.headers().get(PRO_API_KEY_HEADER).is_none());
+        assert_eq!(free.min_interval, Duration::from_millis(DEFAULT_FREE_INTERVAL_MS));
+
+        let custom = api(&[("COINGECKO_API_KEY", "k"), ("COINGECKO_PRO_API_BASE", "https://proxy.local/v3/")]);
+        assert_eq!(custom.base_url, "https://proxy.local/v3");
+    }
+}
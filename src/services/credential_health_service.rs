@@ -0,0 +1,445 @@
+//! 🔑 Auto-desativação de credenciais de exchange quebradas
+//!
+//! Quando a API key é revogada ou expira, todo tick de estratégia e toda busca
+//! de saldo continua falhando contra a exchange. Contamos falhas de autenticação
+//! consecutivas por `user_exchange` e, ao atingir o limite
+//! (`EXCHANGE_AUTH_FAILURE_THRESHOLD`, padrão 3), a exchange é marcada
+//! `is_active=false` e as estratégias dela vão para `Error` com um sinal avisando
+//! o usuário. Erros transitórios (rede, rate limit) não contam nem zeram o contador;
+//! qualquer chamada bem-sucedida zera.
+//!
+//! Período de carência (`EXCHANGE_AUTH_GRACE_PERIOD_SECS`, padrão 600): enquanto
+//! a primeira falha da sequência for mais recente que isso, a exchange não é
+//! desativada — cobre a propagação da allowlist de IP logo após adicionar um
+//! servidor novo. A primeira falha só avisa o usuário (sinal nas estratégias).
+//! `POST /api/v1/user/exchanges/{id}/reactivate` testa as credenciais de novo e
+//! reativa a exchange se a exchange aceitar.
+
+use crate::{
+    database::MongoDB,
+    models::{ExchangeBalance, SignalType, StrategyItem, StrategySignal, StrategyStatus, UserStrategies},
+};
+use lazy_static::lazy_static;
+use mongodb::bson::{doc, Document};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+const DEFAULT_AUTH_FAILURE_THRESHOLD: u32 = 3;
+const DEFAULT_AUTH_GRACE_PERIOD_SECS: i64 = 600;
+const STRATEGY_COLLECTION: &str = "user_strategy";
+
+lazy_static! {
+    /// Contador global usado pelo monitor de estratégias e pelos endpoints de saldo
+    pub static ref AUTH_FAILURES: AuthFailureTracker = AuthFailureTracker::with_grace_period(
+        auth_failure_threshold(), auth_grace_period_secs(),
+    );
+}
+
+fn auth_failure_threshold() -> u32 {
+    std::env::var("EXCHANGE_AUTH_FAILURE_THRESHOLD")
+        .ok()
+        .and_then(|v| v.parse::<u32>().ok())
+        .unwrap_or(DEFAULT_AUTH_FAILURE_THRESHOLD)
+        .max(1)
+}
+
+fn auth_grace_period_secs() -> i64 {
+    std::env::var("EXCHANGE_AUTH_GRACE_PERIOD_SECS")
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_AUTH_GRACE_PERIOD_SECS)
+        .max(0)
+}
+
+/// Categoria do erro segundo a hierarquia de exceções do CCXT
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CcxtErrorKind {
+    /// AuthenticationError, PermissionDenied, AccountSuspended — credencial inválida
+    Auth,
+    /// NetworkError e subclasses (RequestTimeout, ExchangeNotAvailable, DDoSProtection, RateLimitExceeded)
+    Transient,
+    Other,
+}
+
+pub fn classify_ccxt_error(raw: &str) -> CcxtErrorKind {
+    const AUTH: &[&str] = &["AuthenticationError", "PermissionDenied", "AccountSuspended"];
+    const TRANSIENT: &[&str] = &[
+        "NetworkError", "RequestTimeout", "ExchangeNotAvailable", "DDoSProtection",
+        "RateLimitExceeded", "OnMaintenance", "InvalidNonce",
+    ];
+
+    if TRANSIENT.iter().any(|name| raw.contains(name)) {
+        return CcxtErrorKind::Transient;
+    }
+    if AUTH.iter().any(|name| raw.contains(name)) {
+        return CcxtErrorKind::Auth;
+    }
+    let lower = raw.to_lowercase();
+    if lower.contains("invalid api") || lower.contains("api key") || lower.contains("apikey")
+        || lower.contains("signature") {
+        CcxtErrorKind::Auth
+    } else if lower.contains("timeout") || lower.contains("timed out") || lower.contains("connection") {
+        CcxtErrorKind::Transient
+    } else {
+        CcxtErrorKind::Other
+    }
+}
+
+/// O que fazer após registrar o resultado de uma chamada autenticada
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AuthFailureAction {
+    None,
+    /// Primeira falha de autenticação da sequência: avisar o usuário
+    Warn,
+    /// Limite atingido fora do período de carência: desativar a exchange
+    Disable,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct FailureStreak {
+    count: u32,
+    first_failure_at: i64,
+}
+
+/// Falhas de autenticação consecutivas por (user_id, exchange_id)
+pub struct AuthFailureTracker {
+    threshold: u32,
+    grace_period_secs: i64,
+    streaks: Mutex<HashMap<(String, String), FailureStreak>>,
+}
+
+impl AuthFailureTracker {
+    pub fn with_grace_period(threshold: u32, grace_period_secs: i64) -> Self {
+        Self { threshold, grace_period_secs, streaks: Mutex::new(HashMap::new()) }
+    }
+
+    /// Registra o resultado de uma chamada autenticada em `now` (segundos).
+    /// `Disable` só sai com o limite atingido e a carência esgotada.
+    pub fn record_at(&self, user_id: &str, exchange_id: &str, outcome: Result<(), &str>, now: i64) -> AuthFailureAction {
+        let key = (user_id.to_string(), exchange_id.to_string());
+        let mut streaks = self.streaks.lock().unwrap_or_else(|e| e.into_inner());
+        match outcome {
+            Ok(()) => {
+                streaks.remove(&key);
+                AuthFailureAction::None
+            }
+            Err(e) => match classify_ccxt_error(e) {
+                CcxtErrorKind::Auth => {
+                    let streak = streaks.entry(key.clone())
+                        .or_insert(FailureStreak { count: 0, first_failure_at: now });
+                    streak.count += 1;
+                    let in_grace = now - streak.first_failure_at < self.grace_period_secs;
+                    if streak.count >= self.threshold && !in_grace {
+                        streaks.remove(&key);
+                        AuthFailureAction::Disable
+                    } else if streak.count == 1 {
+                        AuthFailureAction::Warn
+                    } else {
+                        AuthFailureAction::None
+                    }
+                }
+                CcxtErrorKind::Transient | CcxtErrorKind::Other => AuthFailureAction::None,
+            },
+        }
+    }
+
+    /// Esquece a sequência de falhas (ex.: exchange reativada manualmente)
+    pub fn reset(&self, user_id: &str, exchange_id: &str) {
+        let mut streaks = self.streaks.lock().unwrap_or_else(|e| e.into_inner());
+        streaks.remove(&(user_id.to_string(), exchange_id.to_string()));
+    }
+}
+
+pub fn credentials_warning_message(exchange_name: &str, grace_period_secs: i64) -> String {
+    format!(
+        "API credentials for {} were rejected. If this keeps happening for {} minutes the exchange \
+         will be disabled — check the key and the IP allowlist.",
+        exchange_name,
+        (grace_period_secs / 60).max(1)
+    )
+}
+
+/// Anexa o sinal de aviso às estratégias ativas na exchange, sem mudar o status.
+/// Retorna os ids afetados.
+pub fn apply_credentials_warning(
+    strategies: &mut [StrategyItem], exchange_id: &str, message: &str, now: i64,
+) -> Vec<String> {
+    let mut affected = Vec::new();
+    for strategy in strategies.iter_mut().filter(|s| s.exchange_id == exchange_id && s.is_active) {
+        strategy.signals.push(info_signal(strategy, message, now));
+        affected.push(strategy.strategy_id.clone());
+    }
+    affected
+}
+
+fn info_signal(strategy: &StrategyItem, message: &str, now: i64) -> StrategySignal {
+    StrategySignal {
+        signal_type: SignalType::Info,
+        price: strategy.last_price.unwrap_or(0.0),
+        message: message.to_string(),
+        acted: false,
+        price_change_percent: 0.0,
+        created_at: now,
+    }
+}
+
+pub fn credentials_disabled_message(exchange_name: &str) -> String {
+    format!(
+        "API credentials for {} were rejected repeatedly and the exchange was disabled. \
+         Update your API keys and reactivate the strategy.",
+        exchange_name
+    )
+}
+
+/// Marca como `Error` as estratégias ainda ativas na exchange desativada,
+/// anexando um sinal de aviso. Retorna os ids afetados.
+pub fn apply_credentials_disabled(
+    strategies: &mut [StrategyItem], exchange_id: &str, message: &str, now: i64,
+) -> Vec<String> {
+    let mut affected = Vec::new();
+    for strategy in strategies.iter_mut().filter(|s| s.exchange_id == exchange_id && s.is_active) {
+        strategy.status = StrategyStatus::Error;
+        strategy.is_active = false;
+        strategy.error_message = Some(message.to_string());
+        let signal = info_signal(strategy, message, now);
+        strategy.signals.push(signal);
+        strategy.updated_at = now;
+        affected.push(strategy.strategy_id.clone());
+    }
+    affected
+}
+
+/// Update que empurra o último sinal de cada estratégia afetada para ela mesma:
+/// um identificador de array filter por estratégia (`$[s0]`, `$[s1]`...), já que
+/// cada sinal carrega o preço da própria estratégia. Devolve (update, array filters).
+fn per_strategy_updates(strategies: &[StrategyItem], affected: &[String], now: i64) -> (Document, Vec<Document>) {
+    let mut push = Document::new();
+    let mut filters = Vec::new();
+    for (i, id) in affected.iter().enumerate() {
+        let signal = strategies.iter()
+            .find(|s| &s.strategy_id == id)
+            .and_then(|s| s.signals.last())
+            .and_then(|s| mongodb::bson::to_bson(s).ok())
+            .unwrap_or_default();
+        push.insert(format!("strategies.$[s{}].signals", i), doc! { "$each": [signal], "$slice": -100 });
+        filters.push(doc! { format!("s{}.strategy_id", i): id });
+    }
+    (doc! { "$set": { "updated_at": now }, "$push": push }, filters)
+}
+
+/// Desativa a exchange do usuário e coloca as estratégias dependentes em `Error`
+pub async fn disable_exchange_credentials(
+    db: &MongoDB, user_id: &str, exchange_id: &str, exchange_name: &str,
+) -> Result<Vec<String>, String> {
+    let message = credentials_disabled_message(exchange_name);
+    log::warn!("🔒 Disabling exchange {} for user {}: repeated authentication failures", exchange_id, user_id);
+
+    crate::services::user_exchanges_service::set_exchange_active(db, user_id, exchange_id, false).await?;
+
+    let collection = db.collection::<UserStrategies>(STRATEGY_COLLECTION);
+    let Some(mut user_doc) = collection
+        .find_one(doc! { "user_id": user_id })
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+    else {
+        return Ok(vec![]);
+    };
+
+    let now = chrono::Utc::now().timestamp();
+    let affected = apply_credentials_disabled(&mut user_doc.strategies, exchange_id, &message, now);
+    if affected.is_empty() {
+        return Ok(affected);
+    }
+
+    let (mut update, filters) = per_strategy_updates(&user_doc.strategies, &affected, now);
+    let set = update.get_document_mut("$set").map_err(|e| format!("Invalid update: {}", e))?;
+    for (i, _) in affected.iter().enumerate() {
+        let p = format!("strategies.$[s{}]", i);
+        set.insert(format!("{}.status", p), mongodb::bson::to_bson(&StrategyStatus::Error).unwrap_or_default());
+        set.insert(format!("{}.is_active", p), false);
+        set.insert(format!("{}.error_message", p), &message);
+        set.insert(format!("{}.updated_at", p), now);
+    }
+    collection
+        .update_one(doc! { "user_id": user_id }, update)
+        .array_filters(filters)
+        .await
+        .map_err(|e| format!("Failed to update strategies: {}", e))?;
+
+    log::warn!("⚠️ {} strategies moved to Error after disabling exchange {}", affected.len(), exchange_id);
+    Ok(affected)
+}
+
+/// Avisa o usuário (sinal nas estratégias ativas) que a exchange rejeitou as credenciais
+pub async fn warn_exchange_credentials(
+    db: &MongoDB, user_id: &str, exchange_id: &str, exchange_name: &str,
+) -> Result<Vec<String>, String> {
+    let message = credentials_warning_message(exchange_name, auth_grace_period_secs());
+    log::warn!("⚠️ Exchange {} rejected credentials for user {} (grace period, not disabling yet)", exchange_id, user_id);
+
+    let collection = db.collection::<UserStrategies>(STRATEGY_COLLECTION);
+    let Some(mut user_doc) = collection
+        .find_one(doc! { "user_id": user_id })
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+    else {
+        return Ok(vec![]);
+    };
+
+    let now = chrono::Utc::now().timestamp();
+    let affected = apply_credentials_warning(&mut user_doc.strategies, exchange_id, &message, now);
+    if affected.is_empty() {
+        return Ok(affected);
+    }
+
+    let (update, filters) = per_strategy_updates(&user_doc.strategies, &affected, now);
+    collection
+        .update_one(doc! { "user_id": user_id }, update)
+        .array_filters(filters)
+        .await
+        .map_err(|e| format!("Failed to update strategies: {}", e))?;
+
+    Ok(affected)
+}
+
+/// Registra o resultado; avisa na primeira falha de autenticação e desativa a
+/// exchange se o limite for atingido fora da carência.
+/// Retorna `true` quando a exchange acabou de ser desativada.
+pub async fn report_exchange_result(
+    db: &MongoDB, user_id: &str, exchange_id: &str, exchange_name: &str, outcome: Result<(), &str>,
+) -> bool {
+    let now = chrono::Utc::now().timestamp();
+    match AUTH_FAILURES.record_at(user_id, exchange_id, outcome, now) {
+        AuthFailureAction::None => false,
+        AuthFailureAction::Warn => {
+            if let Err(e) = warn_exchange_credentials(db, user_id, exchange_id, exchange_name).await {
+                log::error!("❌ Failed to warn about exchange {} for user {}: {}", exchange_id, user_id, e);
+            }
+            false
+        }
+        AuthFailureAction::Disable => {
+            if let Err(e) = disable_exchange_credentials(db, user_id, exchange_id, exchange_name).await {
+                log::error!("❌ Failed to disable exchange {} for user {}: {}", exchange_id, user_id, e);
+            }
+            true
+        }
+    }
+}
+
+/// Registra o resultado de cada exchange numa busca de saldos
+pub async fn report_balance_results(db: &MongoDB, user_id: &str, balances: &[ExchangeBalance]) {
+    for balance in balances {
+        let outcome = match balance.error.as_deref() {
+            Some(e) if !balance.success => Err(e),
+            _ => Ok(()),
+        };
+        report_exchange_result(db, user_id, &balance.exchange_id, &balance.exchange, outcome).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::StrategyConfig;
+
+    fn strategy(id: &str, exchange_id: &str) -> StrategyItem {
+        StrategyItem {
+            strategy_id: id.into(), name: id.into(), symbol: "BTC/USDT".into(),
+            exchange_id: exchange_id.into(), exchange_name: "Binance".into(),
+            is_active: true, status: StrategyStatus::Monitoring, config: StrategyConfig::default(),
+            position: None, open_orders: vec![], grid_state: None, executions: vec![], signals: vec![],
+            last_checked_at: None, last_price: Some(100.0), last_gradual_sell_at: None,
+            last_notified_at: Default::default(), error_message: None, alert_only: false, awaiting_live_confirmation: false, parent_strategy_id: None,
+            total_pnl_usd: 0.0, total_executions: 0, pending_buy_usd: 0.0, pending_buy_window: false, consecutive_losses: 0, exchange_pause: None, started_at: 0, created_at: 0, updated_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_classifies_auth_vs_transient_errors() {
+        assert_eq!(classify_ccxt_error("AuthenticationError: binance {\"code\":-2015}"), CcxtErrorKind::Auth);
+        assert_eq!(classify_ccxt_error("PermissionDenied: key revoked"), CcxtErrorKind::Auth);
+        assert_eq!(classify_ccxt_error("RequestTimeout: binance GET https://api.binance.com"), CcxtErrorKind::Transient);
+        assert_eq!(classify_ccxt_error("BadSymbol: XYZ/USDT"), CcxtErrorKind::Other);
+    }
+
+    #[test]
+    fn test_repeated_auth_failures_disable_exchange_and_strategies() {
+        let tracker = AuthFailureTracker::with_grace_period(3, 0);
+        let auth = Err("AuthenticationError: Invalid API-key");
+        let record = |user_id, exchange_id, outcome| {
+            tracker.record_at(user_id, exchange_id, outcome, 0) == AuthFailureAction::Disable
+        };
+
+        assert!(!record("u1", "ex1", auth));
+        assert!(!record("u1", "ex1", auth));
+        // Erro de rede no meio não zera nem conta
+        assert!(!record("u1", "ex1", Err("NetworkError: connection reset")));
+        // Outra exchange tem contador próprio
+        assert!(!record("u1", "ex2", auth));
+        assert!(record("u1", "ex1", auth));
+
+        // Sucesso zera o contador
+        assert!(!record("u1", "ex2", Ok(())));
+        assert!(!record("u1", "ex2", auth));
+
+        let mut strategies = vec![strategy("s1", "ex1"), strategy("s2", "ex2"), strategy("s3", "ex1")];
+        strategies[2].is_active = false;
+        strategies[2].status = StrategyStatus::Completed;
+
+        let affected = apply_credentials_disabled(&mut strategies, "ex1", &credentials_disabled_message("Binance"), 1_000);
+        assert_eq!(affected, vec!["s1".to_string()]);
+        assert_eq!(strategies[0].status, StrategyStatus::Error);
+        assert!(!strategies[0].is_active);
+        assert!(strategies[0].error_message.as_deref().unwrap().contains("Binance"));
+        assert_eq!(strategies[0].signals.len(), 1);
+        assert_eq!(strategies[1].status, StrategyStatus::Monitoring);
+        assert_eq!(strategies[2].status, StrategyStatus::Completed);
+    }
+
+    #[test]
+    fn test_auth_failures_warn_during_grace_period_then_disable() {
+        let tracker = AuthFailureTracker::with_grace_period(3, 600);
+        let auth = Err("AuthenticationError: IP not in whitelist");
+
+        // Primeira falha só avisa; as seguintes dentro da carência nem desativam
+        assert_eq!(tracker.record_at("u1", "ex1", auth, 1_000), AuthFailureAction::Warn);
+        assert_eq!(tracker.record_at("u1", "ex1", auth, 1_060), AuthFailureAction::None);
+        assert_eq!(tracker.record_at("u1", "ex1", auth, 1_120), AuthFailureAction::None);
+        assert_eq!(tracker.record_at("u1", "ex1", auth, 1_599), AuthFailureAction::None);
+        // Carência esgotada com o limite atingido: desativa
+        assert_eq!(tracker.record_at("u1", "ex1", auth, 1_600), AuthFailureAction::Disable);
+        // Nova sequência começa com aviso de novo
+        assert_eq!(tracker.record_at("u1", "ex1", auth, 1_700), AuthFailureAction::Warn);
+
+        // Sucesso durante a carência (allowlist propagou) zera sem desativar
+        assert_eq!(tracker.record_at("u1", "ex2", auth, 1_000), AuthFailureAction::Warn);
+        assert_eq!(tracker.record_at("u1", "ex2", Ok(()), 1_100), AuthFailureAction::None);
+        assert_eq!(tracker.record_at("u1", "ex2", auth, 2_000), AuthFailureAction::Warn);
+
+        let mut strategies = vec![strategy("s1", "ex1"), strategy("s2", "ex2")];
+        let affected = apply_credentials_warning(&mut strategies, "ex1", &credentials_warning_message("Binance", 600), 1_000);
+        assert_eq!(affected, vec!["s1".to_string()]);
+        // Aviso não muda o status da estratégia
+        assert_eq!(strategies[0].status, StrategyStatus::Monitoring);
+        assert!(strategies[0].is_active);
+        assert!(strategies[0].signals[0].message.contains("10 minutes"));
+        assert!(strategies[1].signals.is_empty());
+    }
+
+    #[test]
+    fn test_each_affected_strategy_gets_its_own_signal() {
+        let mut strategies = vec![strategy("s1", "ex1"), strategy("s2", "ex1")];
+        strategies[1].last_price = Some(2_500.0);
+        let affected = apply_credentials_warning(&mut strategies, "ex1", "warn", 1_000);
+
+        let (update, filters) = per_strategy_updates(&strategies, &affected, 1_000);
+        assert_eq!(filters, vec![doc! { "s0.strategy_id": "s1" }, doc! { "s1.strategy_id": "s2" }]);
+        let push = update.get_document("$push").unwrap();
+        let price_of = |key: &str| {
+            let each = push.get_document(key).unwrap().get_array("$each").unwrap();
+            each[0].as_document().unwrap().get_f64("price").unwrap()
+        };
+        assert_eq!(price_of("strategies.$[s0].signals"), 100.0);
+        assert_eq!(price_of("strategies.$[s1].signals"), 2_500.0);
+    }
+}
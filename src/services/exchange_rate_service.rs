@@ -1,23 +1,255 @@
 use serde::{Deserialize, Serialize};
 use reqwest;
+use lazy_static::lazy_static;
 use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// ==================== PROVIDERS DE CÂMBIO ====================
+// Cadeia ordenada configurável via `EXCHANGE_RATE_PROVIDERS`
+// (ex.: "exchangerate-api,open-er-api,exchangerate-host"). Cada provider tem
+// timeout próprio (`EXCHANGE_RATE_PROVIDER_TIMEOUT_MS`); o primeiro sucesso é
+// cacheado por `EXCHANGE_RATE_CACHE_TTL_SECS`. Se todos falharem, usa-se o
+// último valor cacheado (mesmo expirado).
 
 // ExchangeRate-API (Free tier: 1,500 requests/month)
 const EXCHANGERATE_API_BASE: &str = "https://api.exchangerate-api.com/v4/latest";
+// Open Exchange Rates (Free tier: 1,000 requests/month)
+const OPEN_ER_API_BASE: &str = "https://open.er-api.com/v6/latest";
+// exchangerate.host (formato "quotes": {"USDBRL": 5.0})
+const EXCHANGERATE_HOST_BASE: &str = "https://api.exchangerate.host/live";
+
+const DEFAULT_PROVIDERS: &str = "exchangerate-api,open-er-api,exchangerate-host";
+const DEFAULT_PROVIDER_TIMEOUT_MS: u64 = 3000;
+const DEFAULT_CACHE_TTL_SECS: u64 = 600;
+
+lazy_static! {
+    static ref RATE_CACHE: RateCache = RateCache::new(Duration::from_secs(env_u64(
+        "EXCHANGE_RATE_CACHE_TTL_SECS", DEFAULT_CACHE_TTL_SECS,
+    )));
+}
 
-// Alternativa: Open Exchange Rates (Free tier: 1,000 requests/month)
-// const OPEN_EXCHANGE_RATES_BASE: &str = "https://open.er-api.com/v6/latest";
-
-// Alternativa 2: Fixer.io (requer API key)
-// const FIXER_API_BASE: &str = "https://api.fixer.io/latest";
+fn env_u64(key: &str, default: u64) -> u64 {
+    std::env::var(key).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+}
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExchangeRatesResponse {
     pub base: String,
     pub date: String,
     pub rates: HashMap<String, f64>,
 }
 
+/// Provider de câmbio: monta a URL e normaliza a resposta para `ExchangeRatesResponse`
+pub trait RateProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn url(&self, base: &str) -> String;
+    fn parse(&self, base: &str, body: &serde_json::Value) -> Result<ExchangeRatesResponse, String>;
+}
+
+fn parse_rates_map(value: Option<&serde_json::Value>) -> Result<HashMap<String, f64>, String> {
+    let map = value.and_then(|v| v.as_object()).ok_or("Missing rates object")?;
+    Ok(map.iter()
+        .filter_map(|(k, v)| v.as_f64().map(|rate| (k.to_uppercase(), rate)))
+        .collect())
+}
+
+/// api.exchangerate-api.com: `{"base": "USD", "date": "2024-01-01", "rates": {...}}`
+pub struct ExchangeRateApi;
+
+impl RateProvider for ExchangeRateApi {
+    fn name(&self) -> &'static str { "exchangerate-api" }
+
+    fn url(&self, base: &str) -> String {
+        format!("{}/{}", EXCHANGERATE_API_BASE, base)
+    }
+
+    fn parse(&self, base: &str, body: &serde_json::Value) -> Result<ExchangeRatesResponse, String> {
+        Ok(ExchangeRatesResponse {
+            base: body["base"].as_str().unwrap_or(base).to_uppercase(),
+            date: body["date"].as_str().unwrap_or_default().to_string(),
+            rates: parse_rates_map(body.get("rates"))?,
+        })
+    }
+}
+
+/// open.er-api.com: `{"result": "success", "base_code": "USD", "time_last_update_utc": "...", "rates": {...}}`
+pub struct OpenErApi;
+
+impl RateProvider for OpenErApi {
+    fn name(&self) -> &'static str { "open-er-api" }
+
+    fn url(&self, base: &str) -> String {
+        format!("{}/{}", OPEN_ER_API_BASE, base)
+    }
+
+    fn parse(&self, base: &str, body: &serde_json::Value) -> Result<ExchangeRatesResponse, String> {
+        if body["result"].as_str() != Some("success") {
+            return Err(format!("open-er-api error: {}", body["error-type"].as_str().unwrap_or("unknown")));
+        }
+        Ok(ExchangeRatesResponse {
+            base: body["base_code"].as_str().unwrap_or(base).to_uppercase(),
+            date: body["time_last_update_utc"].as_str().unwrap_or_default().to_string(),
+            rates: parse_rates_map(body.get("rates"))?,
+        })
+    }
+}
+
+/// exchangerate.host: `{"success": true, "source": "USD", "timestamp": 1700000000, "quotes": {"USDBRL": 4.9}}`
+pub struct ExchangeRateHost;
+
+impl RateProvider for ExchangeRateHost {
+    fn name(&self) -> &'static str { "exchangerate-host" }
+
+    fn url(&self, base: &str) -> String {
+        let mut url = format!("{}?source={}", EXCHANGERATE_HOST_BASE, base);
+        if let Ok(key) = std::env::var("EXCHANGERATE_HOST_ACCESS_KEY") {
+            url.push_str(&format!("&access_key={}", key));
+        }
+        url
+    }
+
+    fn parse(&self, base: &str, body: &serde_json::Value) -> Result<ExchangeRatesResponse, String> {
+        if body["success"].as_bool() == Some(false) {
+            return Err(format!("exchangerate.host error: {}", body["error"]["info"].as_str().unwrap_or("unknown")));
+        }
+        let source = body["source"].as_str().unwrap_or(base).to_uppercase();
+        // Quotes vêm prefixadas com a moeda base ("USDBRL")
+        let rates = parse_rates_map(body.get("quotes"))?
+            .into_iter()
+            .map(|(pair, rate)| (pair.strip_prefix(&source).map(str::to_string).unwrap_or(pair), rate))
+            .collect();
+        let date = body["timestamp"].as_i64()
+            .and_then(|ts| chrono::DateTime::from_timestamp(ts, 0))
+            .map(|dt| dt.format("%Y-%m-%d").to_string())
+            .unwrap_or_default();
+        Ok(ExchangeRatesResponse { base: source, date, rates })
+    }
+}
+
+/// Cadeia configurada em `EXCHANGE_RATE_PROVIDERS` (nomes desconhecidos são ignorados)
+pub fn configured_providers() -> Vec<Box<dyn RateProvider>> {
+    let names = std::env::var("EXCHANGE_RATE_PROVIDERS").unwrap_or_else(|_| DEFAULT_PROVIDERS.to_string());
+    let providers: Vec<Box<dyn RateProvider>> = names.split(',')
+        .filter_map(|name| -> Option<Box<dyn RateProvider>> {
+            match name.trim().to_lowercase().as_str() {
+                "exchangerate-api" => Some(Box::new(ExchangeRateApi)),
+                "open-er-api" => Some(Box::new(OpenErApi)),
+                "exchangerate-host" => Some(Box::new(ExchangeRateHost)),
+                "" => None,
+                other => {
+                    log::warn!("⚠️ Unknown exchange rate provider '{}' ignored", other);
+                    None
+                }
+            }
+        })
+        .collect();
+
+    if providers.is_empty() {
+        vec![Box::new(ExchangeRateApi)]
+    } else {
+        providers
+    }
+}
+
+/// Cache de tabelas de câmbio por moeda base
+pub struct RateCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<String, (Instant, ExchangeRatesResponse)>>,
+}
+
+impl RateCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self { ttl, entries: Mutex::new(HashMap::new()) }
+    }
+
+    fn get(&self, base: &str, allow_stale: bool) -> Option<ExchangeRatesResponse> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.get(base)
+            .filter(|(at, _)| allow_stale || at.elapsed() < self.ttl)
+            .map(|(_, table)| table.clone())
+    }
+
+    fn put(&self, base: &str, table: ExchangeRatesResponse) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.insert(base.to_string(), (Instant::now(), table));
+    }
+
+    /// Cache fresco → providers em ordem → último valor cacheado
+    pub async fn fetch_with<F, Fut>(
+        &self, providers: &[Box<dyn RateProvider>], base: &str, fetch_json: F,
+    ) -> Result<ExchangeRatesResponse, String>
+    where
+        F: Fn(String) -> Fut,
+        Fut: Future<Output = Result<serde_json::Value, String>>,
+    {
+        let base = base.to_uppercase();
+        if let Some(table) = self.get(&base, false) {
+            return Ok(table);
+        }
+
+        let mut errors = Vec::new();
+        for provider in providers {
+            let result = fetch_json(provider.url(&base)).await
+                .and_then(|body| provider.parse(&base, &body));
+            match result {
+                Ok(table) if !table.rates.is_empty() => {
+                    log::debug!("💱 Rates for {} from {}", base, provider.name());
+                    self.put(&base, table.clone());
+                    return Ok(table);
+                }
+                Ok(_) => errors.push(format!("{}: empty rates", provider.name())),
+                Err(e) => {
+                    log::warn!("⚠️ Exchange rate provider {} failed: {}", provider.name(), e);
+                    errors.push(format!("{}: {}", provider.name(), e));
+                }
+            }
+        }
+
+        if let Some(table) = self.get(&base, true) {
+            log::warn!("⚠️ All exchange rate providers failed for {}, using last cached rates", base);
+            return Ok(table);
+        }
+
+        Err(format!("Failed to fetch exchange rates for {}: {}", base, errors.join("; ")))
+    }
+}
+
+async fn fetch_provider_json(url: String) -> Result<serde_json::Value, String> {
+    let timeout = Duration::from_millis(env_u64("EXCHANGE_RATE_PROVIDER_TIMEOUT_MS", DEFAULT_PROVIDER_TIMEOUT_MS));
+    let response = reqwest::Client::new()
+        .get(&url)
+        .header("Accept", "application/json")
+        .timeout(timeout)
+        .send()
+        .await
+        .map_err(|e| format!("Request failed: {}", e))?;
+
+    if !response.status().is_success() {
+        return Err(format!("HTTP {}", response.status()));
+    }
+
+    response.json().await.map_err(|e| format!("Invalid JSON: {}", e))
+}
+
+/// Tabela de câmbio para `base` usando a cadeia de providers + cache global
+pub async fn fetch_rates(base: &str) -> Result<ExchangeRatesResponse, String> {
+    RATE_CACHE.fetch_with(&configured_providers(), base, fetch_provider_json).await
+}
+
+/// Última taxa conhecida (mesmo expirada), sem ir à rede
+pub fn last_cached_rate(from: &str, to: &str) -> Option<f64> {
+    RATE_CACHE.get(&from.to_uppercase(), true)
+        .and_then(|table| table.rates.get(&to.to_uppercase()).copied())
+        .or_else(|| {
+            RATE_CACHE.get(&to.to_uppercase(), true)
+                .and_then(|table| table.rates.get(&from.to_uppercase()).copied())
+                .filter(|rate| *rate > 0.0)
+                .map(|rate| 1.0 / rate)
+        })
+}
+
 #[derive(Debug, Serialize)]
 pub struct ConversionResponse {
     pub success: bool,
@@ -49,25 +281,7 @@ pub async fn get_exchange_rate(
         return Ok(1.0);
     }
 
-    let url = format!("{}/{}", EXCHANGERATE_API_BASE, from.to_uppercase());
-
-    let client = reqwest::Client::new();
-    let response = client
-        .get(&url)
-        .header("Accept", "application/json")
-        .timeout(std::time::Duration::from_secs(10))
-        .send()
-        .await
-        .map_err(|e| format!("Failed to fetch exchange rate: {}", e))?;
-
-    if !response.status().is_success() {
-        return Err(format!("Exchange rate API error: {}", response.status()));
-    }
-
-    let rates_data: ExchangeRatesResponse = response
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse exchange rates: {}", e))?;
+    let rates_data = fetch_rates(from).await?;
 
     let to_upper = to.to_uppercase();
     let rate = rates_data.rates
@@ -93,25 +307,7 @@ pub async fn get_batch_exchange_rates(
     }
 
     // Busca todas as taxas a partir da moeda destino
-    let url = format!("{}/{}", EXCHANGERATE_API_BASE, to.to_uppercase());
-
-    let client = reqwest::Client::new();
-    let response = client
-        .get(&url)
-        .header("Accept", "application/json")
-        .timeout(std::time::Duration::from_secs(10))
-        .send()
-        .await
-        .map_err(|e| format!("Failed to fetch batch exchange rates: {}", e))?;
-
-    if !response.status().is_success() {
-        return Err(format!("Exchange rate API error: {}", response.status()));
-    }
-
-    let rates_data: ExchangeRatesResponse = response
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse exchange rates: {}", e))?;
+    let rates_data = fetch_rates(to).await?;
 
     // Extrai apenas as moedas solicitadas e inverte a taxa (FROM/TO ao invés de TO/FROM)
     let mut result = HashMap::new();
@@ -168,25 +364,7 @@ pub async fn get_all_rates(
 ) -> Result<AllRatesResponse, String> {
     log::info!("💱 Fetching all exchange rates for base: {}", base);
 
-    let url = format!("{}/{}", EXCHANGERATE_API_BASE, base.to_uppercase());
-
-    let client = reqwest::Client::new();
-    let response = client
-        .get(&url)
-        .header("Accept", "application/json")
-        .timeout(std::time::Duration::from_secs(10))
-        .send()
-        .await
-        .map_err(|e| format!("Failed to fetch exchange rates: {}", e))?;
-
-    if !response.status().is_success() {
-        return Err(format!("Exchange rate API error: {}", response.status()));
-    }
-
-    let rates_data: ExchangeRatesResponse = response
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse exchange rates: {}", e))?;
+    let rates_data = fetch_rates(base).await?;
 
     log::info!("✅ Retrieved {} exchange rates for {}", rates_data.rates.len(), base);
 
@@ -238,4 +416,64 @@ mod tests {
         assert!(rate.is_ok());
         assert_eq!(rate.unwrap(), 1.0);
     }
+
+    fn provider_chain() -> Vec<Box<dyn RateProvider>> {
+        vec![Box::new(ExchangeRateApi), Box::new(OpenErApi)]
+    }
+
+    #[test]
+    fn test_normalizes_provider_response_shapes() {
+        let open_er = serde_json::json!({
+            "result": "success", "base_code": "USD",
+            "time_last_update_utc": "Mon, 01 Jan 2024 00:00:01 +0000",
+            "rates": { "BRL": 4.9, "EUR": 0.91 }
+        });
+        let table = OpenErApi.parse("USD", &open_er).unwrap();
+        assert_eq!(table.base, "USD");
+        assert_eq!(table.rates.get("BRL"), Some(&4.9));
+
+        let host = serde_json::json!({
+            "success": true, "source": "USD", "timestamp": 1704067200,
+            "quotes": { "USDBRL": 4.9, "USDEUR": 0.91 }
+        });
+        let table = ExchangeRateHost.parse("USD", &host).unwrap();
+        assert_eq!(table.base, "USD");
+        assert_eq!(table.date, "2024-01-01");
+        assert_eq!(table.rates.get("BRL"), Some(&4.9));
+        assert_eq!(table.rates.get("EUR"), Some(&0.91));
+
+        assert!(OpenErApi.parse("USD", &serde_json::json!({"result": "error", "error-type": "unsupported-code"})).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_provider_failover_and_cached_fallback() {
+        let cache = RateCache::new(Duration::from_millis(0));
+        let calls = Mutex::new(Vec::new());
+
+        // Primeiro provider fora do ar, segundo responde
+        let table = cache.fetch_with(&provider_chain(), "usd", |url| {
+            calls.lock().unwrap().push(url.clone());
+            async move {
+                if url.starts_with(EXCHANGERATE_API_BASE) {
+                    Err("HTTP 503 Service Unavailable".to_string())
+                } else {
+                    Ok(serde_json::json!({ "result": "success", "base_code": "USD", "rates": { "BRL": 5.1 } }))
+                }
+            }
+        }).await.unwrap();
+        assert_eq!(table.rates.get("BRL"), Some(&5.1));
+        assert_eq!(calls.lock().unwrap().len(), 2);
+
+        // Todos falham: usa o último valor cacheado (mesmo expirado)
+        let table = cache.fetch_with(&provider_chain(), "USD", |_| async {
+            Err::<serde_json::Value, _>("timeout".to_string())
+        }).await.unwrap();
+        assert_eq!(table.rates.get("BRL"), Some(&5.1));
+
+        // Sem cache nenhum, o erro lista os providers
+        let err = cache.fetch_with(&provider_chain(), "EUR", |_| async {
+            Err::<serde_json::Value, _>("timeout".to_string())
+        }).await.unwrap_err();
+        assert!(err.contains("exchangerate-api") && err.contains("open-er-api"));
+    }
 }
@@ -6,8 +6,11 @@ use crate::{
     database::MongoDB,
     models::ExchangeCatalog,
 };
+use lazy_static::lazy_static;
 use mongodb::bson::{doc, oid::ObjectId};
 use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
 
 #[derive(Debug, Serialize, utoipa::ToSchema)]
 pub struct AvailableExchangesResponse {
@@ -95,7 +98,7 @@ pub async fn get_token_details(
     use crate::models::user_exchange::UserExchanges;
     use crate::models::ExchangeCatalog;
     use crate::utils::crypto::decrypt_fernet_via_python;
-    use crate::utils::thread_pool::spawn_ccxt_blocking;
+    use crate::utils::thread_pool::spawn_ccxt_paced;
     use std::env;
     
     log::info!("🔍 Fetching token details for {} on exchange {}", symbol, exchange_id);
@@ -169,7 +172,7 @@ pub async fn get_token_details(
     // 6. Executa fetch em thread bloqueante (CCXT usa Python/GIL)
     log::info!("📊 Fetching market data for {}", market_symbol);
     
-    let ticker_task = spawn_ccxt_blocking(move || {
+    let ticker_task = spawn_ccxt_paced(&ccxt_id, move || {
         let client = crate::ccxt::client::CCXTClient::new(
             &ccxt_id_clone,
             &api_key_clone,
@@ -202,3 +205,201 @@ pub async fn get_token_details(
         "datetime": chrono::Utc::now().to_rfc3339(),
     }))
 }
+
+// ==================== TIMEFRAMES PARA GRÁFICOS ====================
+
+lazy_static! {
+    /// `timeframes`/`has` vêm da definição da exchange no CCXT: estáticos por processo
+    static ref TIMEFRAMES_CACHE: Mutex<HashMap<String, ExchangeTimeframesResponse>> = Mutex::new(HashMap::new());
+    static ref CAPABILITIES_CACHE: Mutex<HashMap<String, HashMap<String, bool>>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Debug, Clone, Serialize, utoipa::ToSchema)]
+pub struct ExchangeTimeframesResponse {
+    pub success: bool,
+    pub ccxt_id: String,
+    pub supports_ohlcv: bool,
+    /// Timeframe unificado (`1m`, `1h`, `1d`...) → código nativo da exchange
+    pub timeframes: BTreeMap<String, String>,
+}
+
+/// Converte o dict `exchange.timeframes` (valores string ou número, ex: Kraken usa minutos)
+pub fn parse_timeframes(raw: &serde_json::Value) -> BTreeMap<String, String> {
+    let Some(map) = raw.as_object() else {
+        return BTreeMap::new();
+    };
+    map.iter()
+        .filter_map(|(timeframe, native)| {
+            let native = match native {
+                serde_json::Value::String(s) => s.clone(),
+                serde_json::Value::Number(n) => n.to_string(),
+                _ => return None,
+            };
+            Some((timeframe.clone(), native))
+        })
+        .collect()
+}
+
+/// Monta a resposta; exchange sem `fetchOHLCV` vira erro NotSupported (400 na API)
+pub fn build_timeframes_response(ccxt_id: &str, supports_ohlcv: bool, raw: &serde_json::Value) -> Result<ExchangeTimeframesResponse, String> {
+    if !supports_ohlcv {
+        return Err(format!(
+            "{} - candle charts are not available for this exchange",
+            crate::ccxt::client::unsupported_capability_error(ccxt_id, "fetchOHLCV"),
+        ));
+    }
+    Ok(ExchangeTimeframesResponse {
+        success: true,
+        ccxt_id: ccxt_id.to_string(),
+        supports_ohlcv,
+        timeframes: parse_timeframes(raw),
+    })
+}
+
+/// Timeframes suportados por uma exchange (client sem credenciais; cache em memória)
+pub async fn get_exchange_timeframes(ccxt_id: &str) -> Result<ExchangeTimeframesResponse, String> {
+    use crate::ccxt::client::CCXTClient;
+    use crate::utils::thread_pool::spawn_ccxt_paced;
+    use std::time::Duration;
+    use tokio::time::timeout;
+
+    let ccxt_id = ccxt_id.trim().to_lowercase();
+    let cached = TIMEFRAMES_CACHE.lock().unwrap().get(&ccxt_id).cloned();
+    let response = match cached {
+        Some(response) => response,
+        None => {
+            let ccxt_id_clone = ccxt_id.clone();
+            let fetch_task = spawn_ccxt_paced(&ccxt_id, move || {
+                // Metadados públicos: não precisa de credenciais
+                let client = CCXTClient::new(&ccxt_id_clone, "", "", None)?;
+                let raw = client.timeframes_json_sync()?;
+                Ok::<_, String>(ExchangeTimeframesResponse {
+                    success: true,
+                    ccxt_id: ccxt_id_clone,
+                    supports_ohlcv: client.has_capability_sync("fetchOHLCV"),
+                    timeframes: parse_timeframes(&raw),
+                })
+            });
+            let response = match timeout(Duration::from_secs(15), fetch_task).await {
+                Ok(Ok(result)) => result?,
+                Ok(Err(e)) => return Err(format!("Task join error: {}", e)),
+                Err(_) => return Err("Timeout loading exchange timeframes".to_string()),
+            };
+            TIMEFRAMES_CACHE.lock().unwrap().insert(ccxt_id.clone(), response.clone());
+            response
+        }
+    };
+
+    if !response.supports_ohlcv {
+        return build_timeframes_response(&ccxt_id, false, &serde_json::Value::Null);
+    }
+    Ok(response)
+}
+
+// ==================== CAPACIDADES (VALIDAÇÃO DE ESTRATÉGIAS) ====================
+
+/// Exchange do catálogo com o `has` do CCXT, para validar a estratégia antes de salvar
+#[derive(Debug, Clone, Default)]
+pub struct ExchangeCapabilities {
+    pub ccxt_id: String,
+    /// `supports_futures` do catálogo
+    pub supports_futures: bool,
+    pub has: HashMap<String, bool>,
+}
+
+impl ExchangeCapabilities {
+    pub fn has(&self, capability: &str) -> bool {
+        self.has.get(capability).copied().unwrap_or(false)
+    }
+}
+
+/// Converte o dict `exchange.has`; `"emulated"` conta como suportado
+pub fn parse_capabilities(raw: &serde_json::Value) -> HashMap<String, bool> {
+    let Some(map) = raw.as_object() else {
+        return HashMap::new();
+    };
+    map.iter()
+        .map(|(capability, value)| {
+            let supported = value.as_bool().unwrap_or(false) || value.as_str() == Some("emulated");
+            (capability.clone(), supported)
+        })
+        .collect()
+}
+
+/// Capacidades da exchange do catálogo (`has` via client sem credenciais, em cache);
+/// Ok(None) quando o exchange_id não está no catálogo
+pub async fn get_exchange_capabilities(db: &MongoDB, exchange_id: &str) -> Result<Option<ExchangeCapabilities>, String> {
+    use crate::ccxt::client::CCXTClient;
+    use crate::utils::thread_pool::spawn_ccxt_paced;
+    use std::time::Duration;
+    use tokio::time::timeout;
+
+    let exchange_oid = ObjectId::parse_str(exchange_id)
+        .map_err(|e| format!("Invalid exchange_id: {}", e))?;
+    let Some(catalog) = db.collection::<ExchangeCatalog>("exchanges")
+        .find_one(doc! { "_id": exchange_oid })
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+    else {
+        return Ok(None);
+    };
+
+    let ccxt_id = catalog.ccxt_id.trim().to_lowercase();
+    let cached = CAPABILITIES_CACHE.lock().unwrap().get(&ccxt_id).cloned();
+    let has = match cached {
+        Some(has) => has,
+        None => {
+            let ccxt_id_clone = ccxt_id.clone();
+            let fetch_task = spawn_ccxt_paced(&ccxt_id, move || {
+                let client = CCXTClient::new(&ccxt_id_clone, "", "", None)?;
+                Ok::<_, String>(parse_capabilities(&client.has_json_sync()?))
+            });
+            let has = match timeout(Duration::from_secs(15), fetch_task).await {
+                Ok(Ok(result)) => result?,
+                Ok(Err(e)) => return Err(format!("Task join error: {}", e)),
+                Err(_) => return Err("Timeout loading exchange capabilities".to_string()),
+            };
+            CAPABILITIES_CACHE.lock().unwrap().insert(ccxt_id.clone(), has.clone());
+            has
+        }
+    };
+
+    Ok(Some(ExchangeCapabilities {
+        ccxt_id,
+        supports_futures: catalog.supports_futures.unwrap_or(false),
+        has,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timeframes_response_from_ccxt_dict() {
+        let raw = serde_json::json!({ "1m": "1min", "1h": "60", "1d": 1440, "bad": null });
+        let response = build_timeframes_response("kraken", true, &raw).unwrap();
+        assert_eq!(response.timeframes.len(), 3);
+        assert_eq!(response.timeframes["1m"], "1min");
+        assert_eq!(response.timeframes["1d"], "1440");
+        assert!(response.supports_ohlcv);
+        assert_eq!(serde_json::to_value(&response).unwrap()["timeframes"]["1h"], "60");
+
+        // Sem OHLCV: erro amigável reconhecido como NotSupported
+        let err = build_timeframes_response("noohlcv", false, &raw).unwrap_err();
+        assert!(crate::services::token_service::is_not_supported(&err), "{}", err);
+        assert!(err.contains("fetchOHLCV"));
+        assert!(parse_timeframes(&serde_json::Value::Null).is_empty());
+    }
+
+    #[test]
+    fn test_capabilities_from_ccxt_has() {
+        let raw = serde_json::json!({ "swap": true, "future": false, "createStopLossOrder": "emulated", "margin": null });
+        let caps = ExchangeCapabilities { ccxt_id: "bybit".into(), supports_futures: true, has: parse_capabilities(&raw) };
+        assert!(caps.has("swap"));
+        assert!(caps.has("createStopLossOrder"));
+        assert!(!caps.has("future"));
+        assert!(!caps.has("margin"));
+        assert!(!caps.has("createTrailingPercentOrder"));
+    }
+}
@@ -0,0 +1,226 @@
+//! 💸 Taxas de trade por usuário/exchange (cache em memória, renovado diariamente)
+//!
+//! O tier de taxa depende do nível VIP da conta, então é buscado na exchange
+//! (`fetch_trading_fees`) e guardado por `(user_id, exchange_id)` por 24h.
+//! Exchanges que não expõem taxas (ou falham) caem na taxa padrão documentada
+//! no CCXT (`exchange.fees["trading"]`) e, na falta dela, em `FALLBACK_FEE_RATE`;
+//! esse fallback só fica `FEE_FALLBACK_TTL_SECS` no cache para que uma falha
+//! momentânea não prenda a conta na taxa padrão o dia inteiro.
+
+use crate::{
+    ccxt::CCXTClient,
+    database::MongoDB,
+    models::{DecryptedExchange, FeeSource, TradingFee, TradingFees},
+    services::user_exchanges_service,
+    utils::thread_pool::spawn_ccxt_paced,
+};
+use lazy_static::lazy_static;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::sync::Mutex;
+
+const FEE_CACHE_TTL_SECS: i64 = 86_400;
+/// Taxas padrão (fetch falhou ou não suportado) são rebuscadas bem antes
+const FEE_FALLBACK_TTL_SECS: i64 = 600;
+/// Último recurso quando nem a exchange nem o CCXT informam a taxa (0.1%)
+const FALLBACK_FEE_RATE: f64 = 0.001;
+
+lazy_static! {
+    /// Cache global usado pela API e pelas estratégias
+    pub static ref FEE_CACHE: FeeCache = FeeCache::new(FEE_CACHE_TTL_SECS, FEE_FALLBACK_TTL_SECS);
+}
+
+pub struct FeeCache {
+    ttl_secs: i64,
+    fallback_ttl_secs: i64,
+    entries: Mutex<HashMap<(String, String), TradingFees>>,
+}
+
+impl FeeCache {
+    pub fn new(ttl_secs: i64, fallback_ttl_secs: i64) -> Self {
+        Self { ttl_secs, fallback_ttl_secs, entries: Mutex::new(HashMap::new()) }
+    }
+
+    fn ttl_for(&self, fees: &TradingFees) -> i64 {
+        match fees.source {
+            FeeSource::Exchange => self.ttl_secs,
+            FeeSource::Default => self.fallback_ttl_secs,
+        }
+    }
+
+    /// Taxas em cache ainda dentro do TTL (curto para o fallback)
+    pub fn get(&self, user_id: &str, exchange_id: &str, now: i64) -> Option<TradingFees> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.get(&(user_id.to_string(), exchange_id.to_string()))
+            .filter(|fees| now - fees.fetched_at < self.ttl_for(fees))
+            .cloned()
+    }
+
+    /// Retorna as taxas em cache ou executa `fetch` e guarda o resultado
+    pub async fn get_or_fetch<F, Fut>(&self, user_id: &str, exchange_id: &str, now: i64, fetch: F) -> TradingFees
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = TradingFees>,
+    {
+        if let Some(fees) = self.get(user_id, exchange_id, now) {
+            return fees;
+        }
+        let fees = fetch().await;
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.insert((user_id.to_string(), exchange_id.to_string()), fees.clone());
+        fees
+    }
+}
+
+fn median(mut values: Vec<f64>) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    Some(values[values.len() / 2])
+}
+
+/// Monta as taxas a partir da resposta da exchange, caindo no padrão documentado
+/// (ou em `FALLBACK_FEE_RATE`) quando a exchange não retorna nada utilizável
+pub fn build_trading_fees(
+    exchange_id: &str,
+    ccxt_id: &str,
+    fetched: Result<BTreeMap<String, TradingFee>, String>,
+    documented: Option<(f64, f64)>,
+    now: i64,
+) -> TradingFees {
+    let symbols = match fetched {
+        Ok(symbols) if !symbols.is_empty() => symbols,
+        Ok(_) => {
+            log::warn!("⚠️ {} returned no trading fees, using defaults", ccxt_id);
+            BTreeMap::new()
+        }
+        Err(e) => {
+            log::warn!("⚠️ Trading fees unavailable for {} ({}), using defaults", ccxt_id, e);
+            BTreeMap::new()
+        }
+    };
+
+    let account_rates = median(symbols.values().map(|f| f.maker).collect())
+        .zip(median(symbols.values().map(|f| f.taker).collect()));
+    let (source, (maker, taker)) = match account_rates {
+        Some(rates) => (FeeSource::Exchange, rates),
+        None => (FeeSource::Default, documented.unwrap_or((FALLBACK_FEE_RATE, FALLBACK_FEE_RATE))),
+    };
+
+    TradingFees {
+        exchange_id: exchange_id.to_string(),
+        ccxt_id: ccxt_id.to_string(),
+        source,
+        maker,
+        taker,
+        symbols,
+        fetched_at: now,
+    }
+}
+
+/// Taxas da exchange do usuário (cache diário; nunca falha graças ao fallback)
+pub async fn fees_for_exchange(user_id: &str, exchange: &DecryptedExchange) -> TradingFees {
+    let now = chrono::Utc::now().timestamp();
+    FEE_CACHE.get_or_fetch(user_id, &exchange.exchange_id, now, || async {
+        let ex = exchange.clone();
+        let (fetched, documented) = spawn_ccxt_paced(&exchange.ccxt_id, move || {
+            match CCXTClient::for_exchange(&ex) {
+                Ok(client) => (client.fetch_trading_fees_sync(), client.default_trading_fees_sync()),
+                Err(e) => (Err(e), None),
+            }
+        })
+        .await
+        .unwrap_or_else(|e| (Err(format!("Task join error: {}", e)), None));
+
+        log::info!("💸 Refreshed trading fees for {} (user {})", exchange.ccxt_id, user_id);
+        build_trading_fees(&exchange.exchange_id, &exchange.ccxt_id, fetched, documented, now)
+    }).await
+}
+
+/// Taxas de uma exchange conectada do usuário
+pub async fn get_trading_fees(db: &MongoDB, user_id: &str, exchange_id: &str) -> Result<TradingFees, String> {
+    let exchanges = user_exchanges_service::get_user_exchanges_decrypted(db, user_id).await?;
+    let exchange = exchanges.iter()
+        .find(|ex| ex.exchange_id == exchange_id)
+        .ok_or_else(|| format!("Exchange {} not found", exchange_id))?;
+    Ok(fees_for_exchange(user_id, exchange).await)
+}
+
+/// Taxa taker (%) real da conta; None quando só há o padrão documentado
+pub fn account_taker_percent(fees: &TradingFees, symbol: &str) -> Option<f64> {
+    (fees.source == FeeSource::Exchange).then(|| fees.taker_percent(symbol))
+}
+
+/// Como `account_taker_percent`, mas só a partir do cache (não busca na exchange)
+pub fn cached_taker_percent(user_id: &str, exchange_id: &str, symbol: &str) -> Option<f64> {
+    FEE_CACHE.get(user_id, exchange_id, chrono::Utc::now().timestamp())
+        .and_then(|fees| account_taker_percent(&fees, symbol))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn fee(symbol: &str, maker: f64, taker: f64) -> (String, TradingFee) {
+        (symbol.to_string(), TradingFee { symbol: symbol.to_string(), maker, taker })
+    }
+
+    #[tokio::test]
+    async fn test_fees_cached_per_user_exchange_until_daily_refresh() {
+        let cache = FeeCache::new(FEE_CACHE_TTL_SECS, FEE_FALLBACK_TTL_SECS);
+        let calls = AtomicUsize::new(0);
+        let fetch = |now: i64| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            let symbols = BTreeMap::from([fee("BTC/USDT", 0.0008, 0.001), fee("ETH/USDT", 0.0009, 0.001)]);
+            async move { build_trading_fees("ex1", "binance", Ok(symbols), None, now) }
+        };
+
+        let first = cache.get_or_fetch("u1", "ex1", 1_000, || fetch(1_000)).await;
+        assert_eq!(first.source, FeeSource::Exchange);
+        assert_eq!(account_taker_percent(&first, "BTC/USDT"), Some(0.1));
+
+        cache.get_or_fetch("u1", "ex1", 1_000 + 3_600, || fetch(4_600)).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // Outro usuário e expiração diária buscam de novo
+        cache.get_or_fetch("u2", "ex1", 1_000, || fetch(1_000)).await;
+        let refreshed = cache.get_or_fetch("u1", "ex1", 1_000 + FEE_CACHE_TTL_SECS, || fetch(87_400)).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert_eq!(refreshed.fetched_at, 87_400);
+    }
+
+    #[test]
+    fn test_unsupported_exchange_falls_back_to_documented_defaults() {
+        let unsupported = Err("mercado does not support fetchTradingFees".to_string());
+        let fees = build_trading_fees("ex1", "mercado", unsupported, Some((0.003, 0.007)), 0);
+        assert_eq!(fees.source, FeeSource::Default);
+        assert_eq!((fees.maker, fees.taker), (0.003, 0.007));
+        assert!((fees.taker_percent("BTC/BRL") - 0.7).abs() < 1e-9);
+        // Estratégias mantêm a taxa configurada quando a conta não informa o tier
+        assert_eq!(account_taker_percent(&fees, "BTC/BRL"), None);
+
+        let unknown = build_trading_fees("ex1", "novadax", Ok(BTreeMap::new()), None, 0);
+        assert_eq!((unknown.source, unknown.taker), (FeeSource::Default, FALLBACK_FEE_RATE));
+    }
+
+    #[tokio::test]
+    async fn test_fallback_fees_expire_quickly_after_failed_fetch() {
+        let cache = FeeCache::new(FEE_CACHE_TTL_SECS, FEE_FALLBACK_TTL_SECS);
+        let failed = cache.get_or_fetch("u1", "ex1", 1_000, || async {
+            build_trading_fees("ex1", "binance", Err("timeout".to_string()), None, 1_000)
+        }).await;
+        assert_eq!(failed.source, FeeSource::Default);
+        assert!(cache.get("u1", "ex1", 1_000 + FEE_FALLBACK_TTL_SECS - 1).is_some());
+
+        // Passado o TTL curto a exchange é consultada de novo
+        assert!(cache.get("u1", "ex1", 1_000 + FEE_FALLBACK_TTL_SECS).is_none());
+        let now = 1_000 + FEE_FALLBACK_TTL_SECS;
+        let recovered = cache.get_or_fetch("u1", "ex1", now, || async move {
+            build_trading_fees("ex1", "binance", Ok(BTreeMap::from([fee("BTC/USDT", 0.0008, 0.001)])), None, now)
+        }).await;
+        assert_eq!(recovered.source, FeeSource::Exchange);
+        assert!(cache.get("u1", "ex1", now + FEE_FALLBACK_TTL_SECS).is_some());
+    }
+}
@@ -1,4 +1,5 @@
 pub mod auth_service;
+pub mod api_key_service;
 pub mod balance_service;
 pub mod order_service;
 pub mod exchange_service;
@@ -8,3 +9,9 @@ pub mod coingecko_service;
 pub mod exchange_rate_service;
 pub mod user_exchanges_service;
 pub mod strategy_service;
+pub mod strategy_events;
+pub mod strategy_history_service;
+pub mod ohlcv_cache_service;
+pub mod credential_health_service;
+pub mod fee_service;
+pub mod user_settings_service;
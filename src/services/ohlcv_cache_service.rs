@@ -0,0 +1,357 @@
+//! 🕯️ Cache de candles OHLCV no MongoDB (collection `ohlcv_cache`)
+//!
+//! Cada candle é um documento chaveado por `(ccxt_id, symbol, timeframe, timestamp)`.
+//! Ao pedir um intervalo, os candles já em cache são servidos direto e só os
+//! trechos faltantes são buscados no exchange (em blocos), mesclados e salvos.
+//!
+//! O candle mais recente ainda está aberto quando é buscado; ele é marcado com
+//! `fetched_at` e rebuscado se ficar mais velho que `OPEN_CANDLE_REFRESH_MS`.
+
+use crate::{
+    ccxt::CCXTClient,
+    database::MongoDB,
+    models::{Candle, DecryptedExchange},
+    utils::thread_pool::spawn_ccxt_paced,
+};
+use futures::TryStreamExt;
+use mongodb::bson::doc;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::future::Future;
+
+pub const OHLCV_CACHE_COLLECTION: &str = "ohlcv_cache";
+
+/// Máximo de candles por chamada ao exchange (limite comum entre exchanges)
+const MAX_FETCH_LIMIT: usize = 1000;
+/// Candle aberto em cache é rebuscado após este intervalo
+const OPEN_CANDLE_REFRESH_MS: i64 = 60_000;
+/// Código do Mongo para violação do índice único
+const DUPLICATE_KEY_CODE: i32 = 11000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CandleKey {
+    pub ccxt_id: String,
+    pub symbol: String,
+    pub timeframe: String,
+}
+
+/// Documento da collection `ohlcv_cache`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedCandle {
+    pub ccxt_id: String,
+    pub symbol: String,
+    pub timeframe: String,
+    pub timestamp: i64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    #[serde(default)]
+    pub volume: f64,
+    /// Quando o candle foi buscado (ms) — define se ainda estava aberto
+    pub fetched_at: i64,
+}
+
+impl CachedCandle {
+    fn from_candle(key: &CandleKey, c: &Candle, fetched_at: i64) -> Self {
+        Self {
+            ccxt_id: key.ccxt_id.clone(), symbol: key.symbol.clone(), timeframe: key.timeframe.clone(),
+            timestamp: c.timestamp, open: c.open, high: c.high, low: c.low, close: c.close,
+            volume: c.volume, fetched_at,
+        }
+    }
+
+    fn candle(&self) -> Candle {
+        Candle {
+            timestamp: self.timestamp,
+            open: self.open, high: self.high, low: self.low, close: self.close,
+            volume: self.volume,
+        }
+    }
+
+    /// Candle completo (fechado antes do fetch) ou aberto mas buscado há pouco
+    fn is_fresh(&self, tf_ms: i64, now_ms: i64) -> bool {
+        self.timestamp + tf_ms <= self.fetched_at || now_ms - self.fetched_at < OPEN_CANDLE_REFRESH_MS
+    }
+}
+
+/// Armazenamento dos candles (MongoDB em produção, memória nos testes)
+pub trait CandleStore {
+    fn load(&self, key: &CandleKey, from_ms: i64, to_ms: i64) -> impl Future<Output = Result<Vec<CachedCandle>, String>>;
+    fn save(&self, key: &CandleKey, candles: &[Candle], fetched_at: i64) -> impl Future<Output = Result<(), String>>;
+}
+
+pub struct MongoCandleStore<'a> {
+    pub db: &'a MongoDB,
+}
+
+impl CandleStore for MongoCandleStore<'_> {
+    async fn load(&self, key: &CandleKey, from_ms: i64, to_ms: i64) -> Result<Vec<CachedCandle>, String> {
+        self.db.collection::<CachedCandle>(OHLCV_CACHE_COLLECTION)
+            .find(doc! {
+                "ccxt_id": &key.ccxt_id,
+                "symbol": &key.symbol,
+                "timeframe": &key.timeframe,
+                "timestamp": { "$gte": from_ms, "$lte": to_ms },
+            })
+            .await
+            .map_err(|e| format!("Failed to query OHLCV cache: {}", e))?
+            .try_collect()
+            .await
+            .map_err(|e| format!("Failed to read OHLCV cache: {}", e))
+    }
+
+    /// Um `insert_many` não ordenado grava os candles novos de uma vez; os que já
+    /// existiam (duplicate key no índice único — em geral o candle que estava
+    /// aberto) são atualizados individualmente.
+    async fn save(&self, key: &CandleKey, candles: &[Candle], fetched_at: i64) -> Result<(), String> {
+        if candles.is_empty() {
+            return Ok(());
+        }
+        let collection = self.db.collection::<CachedCandle>(OHLCV_CACHE_COLLECTION);
+        let docs: Vec<CachedCandle> = candles.iter().map(|c| CachedCandle::from_candle(key, c, fetched_at)).collect();
+
+        let existing: Vec<usize> = match collection.insert_many(&docs).ordered(false).await {
+            Ok(_) => return Ok(()),
+            Err(e) => match *e.kind {
+                mongodb::error::ErrorKind::InsertMany(ref failure) if failure.write_concern_error.is_none() => {
+                    let errors = failure.write_errors.as_deref().unwrap_or_default();
+                    if errors.iter().any(|w| w.code != DUPLICATE_KEY_CODE) {
+                        return Err(format!("Failed to save OHLCV cache: {}", e));
+                    }
+                    errors.iter().map(|w| w.index).collect()
+                }
+                _ => return Err(format!("Failed to save OHLCV cache: {}", e)),
+            },
+        };
+
+        for c in existing.into_iter().filter_map(|i| candles.get(i)) {
+            collection.update_one(
+                doc! {
+                    "ccxt_id": &key.ccxt_id, "symbol": &key.symbol,
+                    "timeframe": &key.timeframe, "timestamp": c.timestamp,
+                },
+                doc! { "$set": {
+                    "open": c.open, "high": c.high, "low": c.low, "close": c.close,
+                    "volume": c.volume, "fetched_at": fetched_at,
+                } },
+            )
+            .upsert(true)
+            .await
+            .map_err(|e| format!("Failed to save OHLCV cache: {}", e))?;
+        }
+        Ok(())
+    }
+}
+
+/// Duração do timeframe CCXT em ms ("1m", "15m", "1h", "4h", "1d", "1w")
+pub fn timeframe_to_millis(timeframe: &str) -> Option<i64> {
+    let (num, unit) = timeframe.split_at(timeframe.len().checked_sub(1)?);
+    let n: i64 = num.parse().ok().filter(|n| *n > 0)?;
+    let unit_ms = match unit {
+        "m" => 60_000,
+        "h" => 3_600_000,
+        "d" => 86_400_000,
+        "w" => 604_800_000,
+        _ => return None,
+    };
+    Some(n * unit_ms)
+}
+
+/// Intervalos contíguos `[start, end]` (timestamps de candle) que faltam no cache
+pub fn missing_ranges(fresh: &BTreeMap<i64, Candle>, from_ms: i64, to_ms: i64, tf_ms: i64) -> Vec<(i64, i64)> {
+    let mut ranges = Vec::new();
+    let mut current: Option<(i64, i64)> = None;
+    let mut ts = from_ms;
+    while ts <= to_ms {
+        if fresh.contains_key(&ts) {
+            if let Some(r) = current.take() { ranges.push(r); }
+        } else {
+            current = Some(match current {
+                Some((start, _)) => (start, ts),
+                None => (ts, ts),
+            });
+        }
+        ts += tf_ms;
+    }
+    if let Some(r) = current { ranges.push(r); }
+    ranges
+}
+
+/// Retorna os candles de `[from_ms, to_ms]`, servindo do cache e buscando no
+/// exchange (`fetch(since_ms, limit)`) apenas os trechos faltantes.
+pub async fn get_candles_range<S, F, Fut>(
+    store: &S, key: &CandleKey, from_ms: i64, to_ms: i64, now_ms: i64, fetch: F,
+) -> Result<Vec<Candle>, String>
+where
+    S: CandleStore,
+    F: Fn(i64, usize) -> Fut,
+    Fut: Future<Output = Result<Vec<Candle>, String>>,
+{
+    let tf_ms = timeframe_to_millis(&key.timeframe)
+        .ok_or_else(|| format!("Unsupported timeframe: {}", key.timeframe))?;
+
+    // Alinha ao início dos candles e não pede o futuro
+    let from_ms = from_ms - from_ms.rem_euclid(tf_ms);
+    let to_ms = to_ms.min(now_ms);
+    let to_ms = to_ms - to_ms.rem_euclid(tf_ms);
+    if to_ms < from_ms {
+        return Ok(vec![]);
+    }
+
+    let mut candles: BTreeMap<i64, Candle> = store.load(key, from_ms, to_ms).await?
+        .into_iter()
+        .filter(|c| c.is_fresh(tf_ms, now_ms))
+        .map(|c| (c.timestamp, c.candle()))
+        .collect();
+
+    for (start, end) in missing_ranges(&candles, from_ms, to_ms, tf_ms) {
+        let mut since = start;
+        while since <= end {
+            let remaining = ((end - since) / tf_ms + 1) as usize;
+            let limit = remaining.min(MAX_FETCH_LIMIT);
+            let fetched: Vec<Candle> = fetch(since, limit).await?
+                .into_iter()
+                .filter(|c| c.timestamp >= since && c.timestamp <= end)
+                .collect();
+
+            log::debug!("🕯️ OHLCV cache miss {} {} {}: fetched {} candles from {}",
+                key.ccxt_id, key.symbol, key.timeframe, fetched.len(), since);
+
+            if !fetched.is_empty() {
+                store.save(key, &fetched, now_ms).await?;
+            }
+            for c in fetched {
+                candles.insert(c.timestamp, c);
+            }
+            since += limit as i64 * tf_ms;
+        }
+    }
+
+    Ok(candles.into_values().collect())
+}
+
+/// Candles de um exchange com cache no MongoDB (para backtests)
+pub async fn fetch_candles_cached(
+    db: &MongoDB, exchange: &DecryptedExchange, symbol: &str, timeframe: &str,
+    from_ms: i64, to_ms: i64,
+) -> Result<Vec<Candle>, String> {
+    let key = CandleKey {
+        ccxt_id: exchange.ccxt_id.clone(),
+        symbol: symbol.to_string(),
+        timeframe: timeframe.to_string(),
+    };
+    let now_ms = chrono::Utc::now().timestamp_millis();
+    let store = MongoCandleStore { db };
+
+    get_candles_range(&store, &key, from_ms, to_ms, now_ms, |since, limit| {
+        let ccxt_id = exchange.ccxt_id.clone();
+        let api_key = exchange.api_key.clone();
+        let api_secret = exchange.api_secret.clone();
+        let passphrase = exchange.passphrase.clone();
+        let symbol = symbol.to_string();
+        let timeframe = timeframe.to_string();
+        async move {
+            spawn_ccxt_paced(&ccxt_id.clone(), move || {
+                let client = CCXTClient::new(&ccxt_id, &api_key, &api_secret, passphrase.as_deref())?;
+                client.fetch_ohlcv_since_sync(&symbol, &timeframe, Some(since), limit)
+            })
+            .await
+            .map_err(|e| format!("Task join error: {}", e))?
+        }
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    const HOUR: i64 = 3_600_000;
+
+    #[derive(Default)]
+    struct MemoryStore {
+        rows: Mutex<Vec<CachedCandle>>,
+    }
+
+    impl CandleStore for MemoryStore {
+        async fn load(&self, key: &CandleKey, from_ms: i64, to_ms: i64) -> Result<Vec<CachedCandle>, String> {
+            Ok(self.rows.lock().unwrap().iter()
+                .filter(|c| c.symbol == key.symbol && c.timestamp >= from_ms && c.timestamp <= to_ms)
+                .cloned()
+                .collect())
+        }
+
+        async fn save(&self, key: &CandleKey, candles: &[Candle], fetched_at: i64) -> Result<(), String> {
+            let mut rows = self.rows.lock().unwrap();
+            for c in candles {
+                rows.retain(|r| r.timestamp != c.timestamp);
+                rows.push(CachedCandle::from_candle(key, c, fetched_at));
+            }
+            Ok(())
+        }
+    }
+
+    fn exchange_candles(since: i64, limit: usize) -> Vec<Candle> {
+        (0..limit as i64).map(|i| {
+            let ts = since + i * HOUR;
+            Candle { timestamp: ts, open: 1.0, high: 2.0, low: 0.5, close: 1.5, volume: 10.0 }
+        }).collect()
+    }
+
+    #[tokio::test]
+    async fn test_second_backtest_over_same_range_hits_cache() {
+        let store = MemoryStore::default();
+        let key = CandleKey { ccxt_id: "binance".into(), symbol: "BTC/USDT".into(), timeframe: "1h".into() };
+        let calls = AtomicUsize::new(0);
+        let fetch = |since: i64, limit: usize| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async move { Ok(exchange_candles(since, limit)) }
+        };
+
+        let (from, to, now) = (100 * HOUR, 147 * HOUR, 1_000 * HOUR);
+
+        let first = get_candles_range(&store, &key, from, to, now, fetch).await.unwrap();
+        assert_eq!(first.len(), 48);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        let second = get_candles_range(&store, &key, from, to, now, fetch).await.unwrap();
+        assert_eq!(second, first);
+        assert_eq!(calls.load(Ordering::SeqCst), 1, "second run must be served from cache");
+
+        // Estender o intervalo busca só o trecho novo
+        let extended = get_candles_range(&store, &key, from, to + 2 * HOUR, now, fetch).await.unwrap();
+        assert_eq!(extended.len(), 50);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_open_candle_is_refreshed_after_ttl() {
+        let store = MemoryStore::default();
+        let key = CandleKey { ccxt_id: "binance".into(), symbol: "BTC/USDT".into(), timeframe: "1h".into() };
+        let calls = AtomicUsize::new(0);
+        let fetch = |since: i64, limit: usize| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async move { Ok(exchange_candles(since, limit)) }
+        };
+
+        // "agora" está no meio do candle das 10h → ele está aberto
+        let now = 10 * HOUR + HOUR / 2;
+        get_candles_range(&store, &key, 8 * HOUR, now, now, fetch).await.unwrap();
+        get_candles_range(&store, &key, 8 * HOUR, now, now + 1_000, fetch).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        get_candles_range(&store, &key, 8 * HOUR, now, now + OPEN_CANDLE_REFRESH_MS, fetch).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_timeframe_to_millis() {
+        assert_eq!(timeframe_to_millis("1h"), Some(HOUR));
+        assert_eq!(timeframe_to_millis("15m"), Some(900_000));
+        assert_eq!(timeframe_to_millis("1d"), Some(86_400_000));
+        assert_eq!(timeframe_to_millis("x"), None);
+    }
+}
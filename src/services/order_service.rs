@@ -5,19 +5,30 @@
 use crate::{
     ccxt::CCXTClient,
     models::{
-        Order, OrdersResponse, CreateOrderResponse, CancelOrderResponse,
-        DecryptedExchange, OrderFee,
+        Order, OrdersResponse, CreateOrderResponse, CancelOrderResponse, CancelAllOrdersResponse,
+        DecryptedExchange, parse_ccxt_order,
         CreateOrderWithCredsRequest, CancelOrderWithCredsRequest,
+        ManagedOrder, TrackedOrder, MarketRules, OrderDateRange,
     },
+    database::MongoDB,
+    services::{exchange_rate_service, strategy_service, user_exchanges_service},
+    utils::{precision::fits_decimals, thread_pool::spawn_ccxt_paced},
 };
+use mongodb::bson::{doc, oid::ObjectId, Document};
+use std::collections::HashMap;
 use futures::future::join_all;
 use pyo3::{Python, types::PyDict};
+use serde::Serialize;
 
-/// Fetch orders from exchanges sent by frontend (with decrypted credentials)
+/// Fetch orders from exchanges sent by frontend (with decrypted credentials).
+/// `status` open/closed/all; `range` limita por data (ver `OrderDateRange`).
 pub async fn fetch_orders_from_exchanges(
     exchanges: Vec<DecryptedExchange>,
+    status: &str,
+    range: OrderDateRange,
 ) -> Result<OrdersResponse, String> {
     log::info!("📊 Processing {} exchanges from frontend", exchanges.len());
+    range.validate()?;
     
     if exchanges.is_empty() {
         return Ok(OrdersResponse {
@@ -31,8 +42,9 @@ pub async fn fetch_orders_from_exchanges(
     let tasks: Vec<_> = exchanges
         .into_iter()
         .map(|exchange| {
+            let status = status.to_string();
             tokio::spawn(async move {
-                fetch_exchange_orders(exchange, "dummy_user", "open").await
+                fetch_exchange_orders(exchange, "dummy_user", &status, range).await
             })
         })
         .collect();
@@ -75,11 +87,84 @@ pub async fn fetch_orders_from_exchanges(
     })
 }
 
+/// Exchanges consultadas ao mesmo tempo em `fetch_all_open_orders`
+const OPEN_ORDERS_CONCURRENCY: usize = 4;
+
+/// Falha de uma exchange na visão unificada (as demais seguem na resposta)
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ExchangeOrdersError {
+    pub exchange_id: String,
+    pub exchange: String,
+    pub error: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AllOpenOrdersResponse {
+    pub success: bool,
+    /// Ordens abertas de todas as exchanges (cada uma com `exchange`/`exchange_id`)
+    pub orders: Vec<Order>,
+    pub count: usize,
+    pub exchanges_ok: usize,
+    pub errors: Vec<ExchangeOrdersError>,
+}
+
+/// Ordens abertas de todas as exchanges ativas do usuário em uma chamada
+pub async fn fetch_all_open_orders(db: &MongoDB, user_id: &str) -> Result<AllOpenOrdersResponse, String> {
+    let exchanges = user_exchanges_service::get_user_exchanges_decrypted(db, user_id).await?;
+    log::info!("📊 [Orders] Fetching open orders from {} exchanges for {}", exchanges.len(), user_id);
+    Ok(merge_open_orders(exchanges, OPEN_ORDERS_CONCURRENCY, |exchange| {
+        fetch_exchange_orders(exchange, user_id, "open", OrderDateRange::default())
+    }).await)
+}
+
+/// Busca com `fetch` em até `concurrency` exchanges por vez e junta tudo em uma
+/// lista (mais recentes primeiro). Falhas viram `errors` por exchange.
+pub async fn merge_open_orders<F, Fut>(
+    exchanges: Vec<DecryptedExchange>, concurrency: usize, fetch: F,
+) -> AllOpenOrdersResponse
+where
+    F: Fn(DecryptedExchange) -> Fut,
+    Fut: std::future::Future<Output = Result<Vec<Order>, String>>,
+{
+    use futures::stream::{self, StreamExt};
+
+    let fetch = &fetch;
+    let results: Vec<(String, String, Result<Vec<Order>, String>)> = stream::iter(exchanges)
+        .map(|exchange| async move {
+            let (exchange_id, name) = (exchange.exchange_id.clone(), exchange.name.clone());
+            (exchange_id, name, fetch(exchange).await)
+        })
+        .buffer_unordered(concurrency.max(1))
+        .collect()
+        .await;
+
+    let mut orders = Vec::new();
+    let mut errors = Vec::new();
+    let mut exchanges_ok = 0;
+    for (exchange_id, exchange, result) in results {
+        match result {
+            Ok(mut list) => {
+                exchanges_ok += 1;
+                orders.append(&mut list);
+            }
+            Err(error) => {
+                log::warn!("⚠️ [Orders] {} open orders unavailable: {}", exchange, error);
+                errors.push(ExchangeOrdersError { exchange_id, exchange, error });
+            }
+        }
+    }
+    orders.sort_by(|a, b| b.timestamp.cmp(&a.timestamp).then_with(|| a.exchange.cmp(&b.exchange)));
+    errors.sort_by(|a, b| a.exchange.cmp(&b.exchange));
+
+    AllOpenOrdersResponse { success: true, count: orders.len(), orders, exchanges_ok, errors }
+}
+
 /// Helper: Fetch orders from a single exchange
 async fn fetch_exchange_orders(
     exchange: DecryptedExchange,
     user_id: &str,
     status_filter: &str,
+    range: OrderDateRange,
 ) -> Result<Vec<Order>, String> {
     let exchange_name = exchange.name.clone();
     let ccxt_id = exchange.ccxt_id.clone();
@@ -97,14 +182,11 @@ async fn fetch_exchange_orders(
     let timeout_duration = std::time::Duration::from_secs(10);
     let exchange_name_for_timeout = exchange_name.clone();
     
-    let task = tokio::task::spawn_blocking(move || {
+    // O token do `spawn_ccxt_paced` cobre o primeiro request; os demais pegam o seu
+    let task = spawn_ccxt_paced(&ccxt_id, move || {
+        let pace = || crate::utils::rate_limiter::CCXT_RATE_LIMITER.acquire_blocking(&ccxt_id_clone);
         log::debug!("🔧 [Orders] Creating CCXT client for {}", ccxt_id_clone);
-        let client = CCXTClient::new(
-            &exchange.ccxt_id,
-            &exchange.api_key,
-            &exchange.api_secret,
-            exchange.passphrase.as_deref(),
-        )?;
+        let client = CCXTClient::for_exchange(&exchange)?;
         
         // Special handling for MEXC: requires symbol for fetch_open_orders
         if ccxt_id_clone.to_lowercase() == "mexc" && status == "open" {
@@ -159,6 +241,7 @@ async fn fetch_exchange_orders(
                                                             symbols_checked += 1;
                                                             
                                                             // Fetch orders for this symbol
+                                                            py.allow_threads(pace);
                                                             match client.fetch_open_orders_with_symbol(&symbol) {
                                                                 Ok(orders) => {
                                                                     if !orders.is_empty() {
@@ -195,20 +278,23 @@ async fn fetch_exchange_orders(
                 Err(e) => {
                     log::warn!("⚠️  [MEXC] Failed to fetch balance: {}", e);
                     log::warn!("⚠️  [MEXC] Falling back to standard fetch (will likely fail)");
+                    pace();
                 }
             }
         }
         
         // Standard fetch for other exchanges or MEXC fallback
         log::debug!("📞 [Orders] Calling fetch_orders_sync with status: {}", status);
-        let orders = client.fetch_orders_sync(&status)?;
+        let orders = client.fetch_orders_in_range_sync(&status, range)?;
         
         log::info!("✅ [Orders] Received {} orders from {}", orders.len(), exchange_name_clone);
         
-        // Convert to Vec<Order>
-        orders.into_iter()
+        // Convert to Vec<Order> (exchanges que ignoram `until` são aparadas aqui)
+        let mut orders = orders.into_iter()
             .map(|order| convert_ccxt_order_to_model(order, &user_id_clone, &exchange_id_clone, &exchange_name_clone))
-            .collect()
+            .collect::<Result<Vec<Order>, String>>()?;
+        range.retain(&mut orders);
+        Ok(orders)
     });
     
     let result: Result<Vec<Order>, String> = match tokio::time::timeout(timeout_duration, task).await {
@@ -238,80 +324,22 @@ fn convert_ccxt_order_to_model(
     exchange_id: &str,
     exchange_name: &str,
 ) -> Result<Order, String> {
-    use pyo3::prelude::*;
+    let order = Python::with_gil(|py| parse_ccxt_order(order.as_ref(py)))
+        .map_err(|e| format!("Failed to convert order: {}", e))?;
     
-    Python::with_gil(|py| {
-        let order_ref = order.as_ref(py);
-        
-        // Helper para extrair String com fallback
-        let extract_string = |key: &str| -> String {
-            order_ref.get_item(key)
-                .ok()
-                .and_then(|v| {
-                    if v.is_none() {
-                        None
-                    } else {
-                        v.extract().ok()
-                    }
-                })
-                .unwrap_or_else(|| String::new())
-        };
-        
-        // Extrai ID da API - NÃO gera fallback
-        let order_id = extract_string("id");
-        
-        if order_id.is_empty() {
-            log::warn!("⚠️  Exchange returned order without ID");
-        }
-        
-        Ok(Order {
-            _id: None,
-            id: order_id,
-            user_id: user_id.to_string(),
-            exchange: exchange_name.to_string(),
-            exchange_id: exchange_id.to_string(),
-            symbol: extract_string("symbol"),
-            order_type: extract_string("type"),
-            side: extract_string("side"),
-            price: order_ref.get_item("price").ok().and_then(|v| {
-                if v.is_none() { None } else { v.extract().ok() }
-            }),
-            amount: order_ref.get_item("amount").ok().and_then(|v| {
-                if v.is_none() { None } else { v.extract().ok() }
-            }).unwrap_or(0.0),
-            filled: order_ref.get_item("filled").ok().and_then(|v| {
-                if v.is_none() { None } else { v.extract().ok() }
-            }).unwrap_or(0.0),
-            remaining: order_ref.get_item("remaining").ok().and_then(|v| {
-                if v.is_none() { None } else { v.extract().ok() }
-            }).unwrap_or(0.0),
-            cost: order_ref.get_item("cost").ok().and_then(|v| {
-                if v.is_none() { None } else { v.extract().ok() }
-            }).unwrap_or(0.0),
-            status: extract_string("status"),
-            fee: order_ref.get_item("fee").ok().and_then(|fee| {
-                if fee.is_none() {
-                    return None;
-                }
-                Some(OrderFee {
-                    currency: fee.get_item("currency").ok()?.extract().ok()?,
-                    cost: fee.get_item("cost").ok()?.extract().ok()?,
-                })
-            }),
-            timestamp: order_ref.get_item("timestamp").ok().and_then(|v| {
-                if v.is_none() { None } else { v.extract().ok() }
-            }).unwrap_or(0),
-            datetime: extract_string("datetime"),
-            created_at: Some(chrono::Utc::now()),
-            updated_at: Some(chrono::Utc::now()),
-        })
-    }).map_err(|e: pyo3::PyErr| format!("Failed to convert order: {}", e))
+    // ID vem da API - NÃO gera fallback
+    if order.id.is_empty() {
+        log::warn!("⚠️  Exchange returned order without ID");
+    }
+    
+    Ok(order.into_order(user_id, exchange_id, exchange_name))
 }
 
 /// Create order com credenciais do frontend (sem MongoDB)
 pub async fn create_order_with_creds(
     request: &CreateOrderWithCredsRequest,
 ) -> Result<CreateOrderResponse, String> {
+    crate::utils::trading_switch::TRADING_SWITCH.ensure_enabled()?;
     log::info!("Creating {} {} order for {} on {} (with frontend creds)", 
         request.side, request.order_type, request.symbol, request.exchange_name);
     
@@ -325,8 +353,9 @@ pub async fn create_order_with_creds(
     let api_key_clone = request.api_key.clone();
     let api_secret_clone = request.api_secret.clone();
     let passphrase_clone = request.passphrase.clone();
+    let tif_clone = request.time_in_force.clone();
     
-    let result = tokio::task::spawn_blocking(move || {
+    let result = spawn_ccxt_paced(&request.ccxt_id, move || {
         let client = CCXTClient::new(
             &ccxt_id_clone,
             &api_key_clone,
@@ -334,16 +363,18 @@ pub async fn create_order_with_creds(
             passphrase_clone.as_deref(),
         )?;
         
-        let order = client.create_order_sync(
+        let order = client.create_order_tif_sync(
             &symbol_clone,
             &order_type_clone,
             &side_clone,
             amount_clone,
             price_clone,
+            tif_clone.as_deref(),
         )?;
         
         convert_ccxt_order_to_model(order, "no_user", "no_exchange_id", &exchange_name_clone)
     }).await.map_err(|e| format!("Task error: {}", e))??;
+    crate::services::balance_service::invalidate_account_balance(&request.api_key);
     
     if result.id.is_empty() {
         log::error!("❌ Order created but exchange returned empty ID");
@@ -372,7 +403,7 @@ pub async fn cancel_order_with_creds(
     let api_secret_clone = request.api_secret.clone();
     let passphrase_clone = request.passphrase.clone();
     
-    tokio::task::spawn_blocking(move || {
+    spawn_ccxt_paced(&request.ccxt_id, move || {
         let client = CCXTClient::new(
             &ccxt_id_clone,
             &api_key_clone,
@@ -382,6 +413,7 @@ pub async fn cancel_order_with_creds(
         
         client.cancel_order_sync(&order_id_clone, symbol_clone.as_deref())
     }).await.map_err(|e| format!("Task error: {}", e))??;
+    crate::services::balance_service::invalidate_account_balance(&request.api_key);
     
     log::info!("Order {} canceled successfully", request.order_id);
     
@@ -392,3 +424,707 @@ pub async fn cancel_order_with_creds(
         error: None,
     })
 }
+
+// ==================== CANCELAR TODAS AS ORDENS ====================
+// Usa `cancel_all_orders` quando a exchange suporta. Exchanges sem suporte
+// (NotSupported) ou que exigem símbolo (ArgumentsRequired) caem no fallback:
+// busca as ordens abertas e cancela uma a uma. Ordens canceladas saem também
+// de "tracked_orders" para o job de expiração não tentar cancelá-las de novo.
+
+/// Operações de cancelamento (CCXTClient em produção)
+pub trait OrderCanceller {
+    fn cancel_all(&self, symbol: Option<&str>) -> Result<usize, String>;
+    /// Ordens abertas como (order_id, symbol)
+    fn open_orders(&self, symbol: Option<&str>) -> Result<Vec<(String, String)>, String>;
+    fn cancel_one(&self, order_id: &str, symbol: &str) -> Result<bool, String>;
+}
+
+impl OrderCanceller for CCXTClient {
+    fn cancel_all(&self, symbol: Option<&str>) -> Result<usize, String> {
+        self.cancel_all_orders_sync(symbol)
+    }
+
+    fn open_orders(&self, symbol: Option<&str>) -> Result<Vec<(String, String)>, String> {
+        let orders = match symbol {
+            Some(sym) => self.fetch_open_orders_with_symbol(sym)?,
+            None => self.fetch_orders_sync("open")?,
+        };
+        Python::with_gil(|py| {
+            orders.iter()
+                .map(|o| parse_ccxt_order(o.as_ref(py)).map(|order| (order.id, order.symbol)))
+                .collect()
+        })
+    }
+
+    fn cancel_one(&self, order_id: &str, symbol: &str) -> Result<bool, String> {
+        self.cancel_order_sync(order_id, Some(symbol))
+    }
+}
+
+/// `cancel_all_orders` indisponível ou exige símbolo → cancelar individualmente
+pub fn needs_individual_cancel(error: &str) -> bool {
+    error.contains("NotSupported") || error.contains("ArgumentsRequired")
+        || error.to_lowercase().contains("requires a `symbol`")
+}
+
+pub fn cancel_all_orders_with(source: &impl OrderCanceller, symbol: Option<&str>) -> Result<CancelAllOrdersResponse, String> {
+    match source.cancel_all(symbol) {
+        Ok(canceled_count) => {
+            return Ok(CancelAllOrdersResponse { success: true, canceled_count, errors: vec![], canceled_order_ids: vec![] });
+        }
+        Err(e) if needs_individual_cancel(&e) => {
+            log::info!("↩️ cancel_all_orders unavailable ({}), canceling individually", e);
+        }
+        Err(e) => return Err(e),
+    }
+
+    let mut canceled_order_ids = Vec::new();
+    let mut errors = Vec::new();
+    for (order_id, order_symbol) in source.open_orders(symbol)? {
+        match source.cancel_one(&order_id, &order_symbol) {
+            Ok(_) => canceled_order_ids.push(order_id),
+            Err(e) => errors.push(format!("{} ({}): {}", order_id, order_symbol, e)),
+        }
+    }
+    Ok(CancelAllOrdersResponse {
+        success: errors.is_empty(),
+        canceled_count: canceled_order_ids.len(),
+        errors,
+        canceled_order_ids,
+    })
+}
+
+/// Filtro das ordens rastreadas que o cancelamento encerrou: sem erros nada do
+/// escopo continua aberto; com erros só os ids efetivamente cancelados
+pub fn canceled_tracked_orders_filter(
+    user_id: &str, exchange_id: &str, symbol: Option<&str>, response: &CancelAllOrdersResponse,
+) -> Option<Document> {
+    let mut filter = doc! { "user_id": user_id, "exchange_id": exchange_id };
+    if response.success {
+        if let Some(sym) = symbol {
+            filter.insert("symbol", sym);
+        }
+    } else if response.canceled_order_ids.is_empty() {
+        return None;
+    } else {
+        filter.insert("order_id", doc! { "$in": &response.canceled_order_ids });
+    }
+    Some(filter)
+}
+
+/// Cancela todas as ordens abertas da exchange (opcionalmente só de um símbolo)
+pub async fn cancel_all_orders_for_exchange(
+    db: &MongoDB, user_id: &str, exchange: &DecryptedExchange, symbol: Option<String>,
+) -> Result<CancelAllOrdersResponse, String> {
+    log::info!("🧹 Canceling all orders on {} (symbol: {:?})", exchange.name, symbol);
+
+    let ex = exchange.clone();
+    let sym = symbol.clone();
+
+    let response = spawn_ccxt_paced(&exchange.ccxt_id, move || {
+        let client = CCXTClient::for_exchange(&ex)?;
+        cancel_all_orders_with(&client, sym.as_deref())
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))??;
+    crate::services::balance_service::invalidate_account_balance(&exchange.api_key);
+
+    if let Some(filter) = canceled_tracked_orders_filter(user_id, &exchange.exchange_id, symbol.as_deref(), &response) {
+        if let Err(e) = db.collection::<ManagedOrder>(TRACKED_ORDERS_COLLECTION).delete_many(filter).await {
+            log::warn!("⚠️ Failed to untrack canceled orders on {}: {}", exchange.name, e);
+        }
+    }
+    Ok(response)
+}
+
+// ==================== TIME IN FORCE / EXPIRAÇÃO ====================
+// GTC, IOC e FOK são enviados nativamente para a exchange.
+// GTD não é suportado pela maioria das exchanges via CCXT: a ordem é criada
+// como GTC e registrada em "tracked_orders"; o job `order_expiry` cancela
+// as ordens limit cujo TTL já passou.
+
+pub const TRACKED_ORDERS_COLLECTION: &str = "tracked_orders";
+
+const NATIVE_TIME_IN_FORCE: &[&str] = &["GTC", "IOC", "FOK"];
+
+/// Valida `time_in_force` + `expire_after_secs`.
+/// Retorna o TIF a enviar para a exchange e o TTL server-side (se houver).
+pub fn resolve_time_in_force(
+    time_in_force: Option<&str>, order_type: &str, expire_after_secs: Option<i64>,
+) -> Result<(Option<String>, Option<i64>), String> {
+    let tif = time_in_force.map(|t| t.trim().to_uppercase());
+    let is_limit = order_type.eq_ignore_ascii_case("limit");
+
+    if let Some(ttl) = expire_after_secs {
+        if ttl <= 0 {
+            return Err("expire_after_secs must be greater than 0".to_string());
+        }
+    }
+
+    match tif.as_deref() {
+        None => Ok((None, None)),
+        Some("GTD") => {
+            if !is_limit {
+                return Err("time_in_force GTD is only supported for limit orders".to_string());
+            }
+            let ttl = expire_after_secs
+                .ok_or_else(|| "expire_after_secs is required for time_in_force GTD".to_string())?;
+            Ok((Some("GTC".to_string()), Some(ttl)))
+        }
+        Some(t) if NATIVE_TIME_IN_FORCE.contains(&t) => {
+            if expire_after_secs.is_some() {
+                return Err(format!("expire_after_secs is only valid with GTD (got {})", t));
+            }
+            Ok((Some(t.to_string()), None))
+        }
+        Some(t) => Err(format!("Invalid time_in_force '{}'. Allowed: GTC, IOC, FOK, GTD", t)),
+    }
+}
+
+/// Registra uma ordem limit para expiração server-side
+pub async fn track_expiring_order(
+    db: &MongoDB, user_id: &str, exchange_id: &str, order: &Order, ttl_secs: i64,
+) -> Result<(), String> {
+    let now = chrono::Utc::now().timestamp();
+    let managed = ManagedOrder {
+        id: None,
+        user_id: user_id.to_string(),
+        exchange_id: exchange_id.to_string(),
+        symbol: order.symbol.clone(),
+        order: TrackedOrder {
+            order_id: order.id.clone(),
+            side: order.side.clone(),
+            order_type: order.order_type.clone(),
+            amount: order.amount,
+            price: order.price,
+            created_at: now,
+            ttl_secs: Some(ttl_secs),
+            lot_number: None,
+        },
+        expires_at: now + ttl_secs,
+    };
+
+    db.collection::<ManagedOrder>(TRACKED_ORDERS_COLLECTION)
+        .insert_one(&managed)
+        .await
+        .map_err(|e| format!("Failed to track order {}: {}", order.id, e))?;
+    Ok(())
+}
+
+/// Resultado de uma rodada de expiração
+#[derive(Debug, Default)]
+pub struct ExpiryResult {
+    /// Ordens canceladas (ou já fechadas na exchange) — podem sair do rastreio
+    pub resolved: Vec<ObjectId>,
+    pub errors: Vec<String>,
+}
+
+/// Cancela as ordens cujo TTL passou; ordens ainda válidas não são tocadas.
+/// Falhas de cancelamento ficam rastreadas para a próxima rodada.
+pub async fn expire_tracked_orders<F, Fut>(
+    orders: &[ManagedOrder], now: i64, cancel: F,
+) -> ExpiryResult
+where
+    F: Fn(ManagedOrder) -> Fut,
+    Fut: std::future::Future<Output = Result<bool, String>>,
+{
+    let mut result = ExpiryResult::default();
+    for managed in orders.iter().filter(|m| m.order.is_expired(now)) {
+        match cancel(managed.clone()).await {
+            Ok(_) => {
+                log::info!("⏰ Order {} expired after {}s, canceled",
+                    managed.order.order_id, managed.order.ttl_secs.unwrap_or(0));
+            }
+            Err(e) if strategy_service::is_order_already_closed(&e) => {
+                log::info!("ℹ️ Expired order {} already closed on exchange", managed.order.order_id);
+            }
+            Err(e) => {
+                result.errors.push(format!("Failed to cancel expired order {}: {}", managed.order.order_id, e));
+                continue;
+            }
+        }
+        if let Some(id) = managed.id {
+            result.resolved.push(id);
+        }
+    }
+    result
+}
+
+/// Busca as ordens expiradas de todos os usuários e cancela na exchange
+pub async fn process_expired_orders(db: &MongoDB) -> Result<ExpiryResult, String> {
+    use futures::TryStreamExt;
+
+    let now = chrono::Utc::now().timestamp();
+    let collection = db.collection::<ManagedOrder>(TRACKED_ORDERS_COLLECTION);
+    let orders: Vec<ManagedOrder> = collection
+        .find(doc! { "expires_at": { "$lte": now } })
+        .await
+        .map_err(|e| format!("Failed to query tracked orders: {}", e))?
+        .try_collect()
+        .await
+        .map_err(|e| format!("Failed to read tracked orders: {}", e))?;
+
+    if orders.is_empty() {
+        return Ok(ExpiryResult::default());
+    }
+
+    // Credenciais descriptografadas por usuário (uma busca por usuário)
+    let mut exchanges_by_user: HashMap<String, Vec<DecryptedExchange>> = HashMap::new();
+    for managed in &orders {
+        if !exchanges_by_user.contains_key(&managed.user_id) {
+            let list = user_exchanges_service::get_user_exchanges_decrypted(db, &managed.user_id)
+                .await
+                .unwrap_or_default();
+            exchanges_by_user.insert(managed.user_id.clone(), list);
+        }
+    }
+
+    let result = expire_tracked_orders(&orders, now, |managed| {
+        let exchange = exchanges_by_user.get(&managed.user_id)
+            .and_then(|list| list.iter().find(|ex| ex.exchange_id == managed.exchange_id))
+            .cloned();
+        async move {
+            let ex = exchange.ok_or_else(|| format!("Exchange {} not found", managed.exchange_id))?;
+            let api_key = ex.api_key.clone();
+            let result = crate::utils::thread_pool::spawn_ccxt_paced(&ex.ccxt_id.clone(), move || {
+                let client = CCXTClient::for_exchange(&ex)?;
+                client.cancel_order_sync(&managed.order.order_id, Some(&managed.symbol))
+            }).await.map_err(|e| format!("Task error: {}", e))?;
+            crate::services::balance_service::invalidate_account_balance(&api_key);
+            result
+        }
+    }).await;
+
+    if !result.resolved.is_empty() {
+        collection
+            .delete_many(doc! { "_id": { "$in": &result.resolved } })
+            .await
+            .map_err(|e| format!("Failed to remove expired orders: {}", e))?;
+    }
+
+    Ok(result)
+}
+
+// ==================== LIMITE DE SEGURANÇA: NOTIONAL MÁXIMO ====================
+// Última barreira contra ordens gigantes (erro de digitação ou bug de estratégia),
+// independente do sizing de cada estratégia. Configurado via `MAX_ORDER_NOTIONAL_USD`
+// (padrão $100k; 0 desativa). Aplicado em `strategy_service::execute_order` e
+// em `create_order_secure`, antes de enviar a ordem — só em compras, que abrem
+// ou aumentam posição; vendas (stop, drawdown, take profit, liquidação) nunca
+// são barradas, nem quando o preço não pode ser consultado.
+
+const DEFAULT_MAX_ORDER_NOTIONAL_USD: f64 = 100_000.0;
+
+/// Quotes tratadas como 1:1 com USD
+pub(crate) const USD_QUOTES: &[&str] = &["USD", "USDT", "USDC", "BUSD", "FDUSD", "TUSD", "DAI"];
+/// Quotes fiat convertidas pela tabela de câmbio
+const FIAT_QUOTES: &[&str] = &["BRL", "EUR", "GBP", "TRY", "ARS", "MXN"];
+
+pub fn max_order_notional_usd() -> Option<f64> {
+    let limit = std::env::var("MAX_ORDER_NOTIONAL_USD")
+        .ok()
+        .and_then(|v| v.parse::<f64>().ok())
+        .unwrap_or(DEFAULT_MAX_ORDER_NOTIONAL_USD);
+    (limit > 0.0).then_some(limit)
+}
+
+/// Rejeita a ordem se `amount × price` (convertido para USD) exceder o limite
+pub fn check_order_notional(symbol: &str, amount: f64, price: f64, quote_usd_rate: f64, limit_usd: f64) -> Result<(), String> {
+    let notional_usd = amount * price * quote_usd_rate;
+    if notional_usd > limit_usd {
+        return Err(format!(
+            "Order rejected: notional ${:.2} for {} {} exceeds the safety limit of ${:.2}",
+            notional_usd, amount, symbol, limit_usd
+        ));
+    }
+    Ok(())
+}
+
+/// Quote do par ("BTC/USDT:USDT" → "USDT")
+pub fn quote_asset(symbol: &str) -> String {
+    symbol.split('/').nth(1)
+        .and_then(|quote| quote.split(':').next())
+        .unwrap_or("USDT")
+        .to_uppercase()
+}
+
+/// Quanto vale 1 unidade da quote em USD (stablecoins 1:1, fiat pelo câmbio,
+/// cripto pelo par `<quote>/USDT` na exchange — sem exchange não há cotação)
+pub async fn quote_usd_rate(exchange: Option<&DecryptedExchange>, quote: &str) -> Result<f64, String> {
+    if USD_QUOTES.contains(&quote) {
+        return Ok(1.0);
+    }
+    if FIAT_QUOTES.contains(&quote) {
+        return exchange_rate_service::get_exchange_rate(quote, "USD").await;
+    }
+    let exchange = exchange.ok_or_else(|| format!("no exchange to price {}", quote))?;
+    strategy_service::fetch_current_price(
+        &exchange.ccxt_id, &exchange.api_key, &exchange.api_secret,
+        exchange.passphrase.as_deref(), &format!("{}/USDT", quote),
+    ).await.map(|q| q.price)
+}
+
+/// Só compras abrem ou aumentam posição; saídas passam sempre
+pub fn notional_limit_applies(side: &str) -> bool {
+    side.eq_ignore_ascii_case("buy")
+}
+
+/// Valida o notional de compras antes do envio. Ordens a mercado usam o último preço
+/// (cache de ticker). Se não for possível avaliar o valor em USD a compra é rejeitada.
+pub async fn enforce_max_order_notional(
+    exchange: &DecryptedExchange, symbol: &str, side: &str, amount: f64, price: Option<f64>,
+) -> Result<(), String> {
+    let Some(limit) = max_order_notional_usd() else { return Ok(()) };
+    if !notional_limit_applies(side) {
+        return Ok(());
+    }
+
+    let price = match price.filter(|p| *p > 0.0) {
+        Some(p) => p,
+        None => strategy_service::fetch_current_price(
+            &exchange.ccxt_id, &exchange.api_key, &exchange.api_secret,
+            exchange.passphrase.as_deref(), symbol,
+        ).await.map(|q| q.price).map_err(|e| format!("Order rejected: could not price {} for the notional check: {}", symbol, e))?,
+    };
+    let quote = quote_asset(symbol);
+    let rate = quote_usd_rate(Some(exchange), &quote).await
+        .map_err(|e| format!("Order rejected: could not convert {} to USD for the notional check: {}", quote, e))?;
+
+    check_order_notional(symbol, amount, price, rate, limit).inspect_err(|e| log::warn!("🛑 {}", e))
+}
+
+// ==================== PRÉ-VALIDAÇÃO CONTRA REGRAS DO MERCADO ====================
+// Reúne as checagens que a exchange faria (mercado ativo, mínimos, precisão e saldo)
+// para devolver todos os problemas de uma vez, em vez do erro cru da exchange.
+
+/// Problema encontrado na pré-validação de uma ordem
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "code", rename_all = "snake_case")]
+pub enum OrderIssue {
+    /// Mercado inexistente ou desativado na exchange
+    MarketInactive { symbol: String },
+    BelowMinAmount { amount: f64, min_amount: f64 },
+    /// `amount × price` (na moeda de cotação) abaixo de `limits.cost.min`
+    BelowMinNotional { notional: f64, min_notional: f64 },
+    AmountPrecision { amount: f64, decimals: u32 },
+    PricePrecision { price: f64, decimals: u32 },
+    InsufficientBalance { asset: String, required: f64, available: f64 },
+}
+
+impl std::fmt::Display for OrderIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MarketInactive { symbol } => write!(f, "market {} is not active on the exchange", symbol),
+            Self::BelowMinAmount { amount, min_amount } => write!(f, "amount {} is below the minimum of {}", amount, min_amount),
+            Self::BelowMinNotional { notional, min_notional } => write!(f, "order value {:.8} is below the minimum of {}", notional, min_notional),
+            Self::AmountPrecision { amount, decimals } => write!(f, "amount {} exceeds the market precision of {} decimals", amount, decimals),
+            Self::PricePrecision { price, decimals } => write!(f, "price {} exceeds the market precision of {} decimals", price, decimals),
+            Self::InsufficientBalance { asset, required, available } => write!(f, "insufficient {} balance ({} required, {} available)", asset, required, available),
+        }
+    }
+}
+
+/// Base do par ("BTC/USDT:USDT" → "BTC")
+fn base_asset(symbol: &str) -> String {
+    symbol.split('/').next().unwrap_or(symbol).to_uppercase()
+}
+
+/// Ativo debitado pela ordem: quote na compra, base na venda
+fn spent_asset(symbol: &str, side: &str) -> String {
+    if side.eq_ignore_ascii_case("buy") { quote_asset(symbol) } else { base_asset(symbol) }
+}
+
+/// Checagens puras da ordem. `price` é o preço de referência (limit ou último preço);
+/// `limit_price` só é informado em ordens limit e é o único validado contra a precisão.
+/// Sem preço de referência ou saldo conhecido as checagens dependentes são puladas.
+pub fn check_order(
+    symbol: &str, rules: Option<&MarketRules>, side: &str, amount: f64,
+    price: Option<f64>, limit_price: Option<f64>, free_balance: Option<f64>,
+) -> Vec<OrderIssue> {
+    let Some(rules) = rules.filter(|r| r.active) else {
+        return vec![OrderIssue::MarketInactive { symbol: symbol.to_string() }];
+    };
+    let mut issues = Vec::new();
+
+    if let Some(min_amount) = rules.limits.min_amount {
+        if amount < min_amount {
+            issues.push(OrderIssue::BelowMinAmount { amount, min_amount });
+        }
+    }
+    if let Some(decimals) = rules.precision.amount_decimals {
+        if !fits_decimals(amount, decimals) {
+            issues.push(OrderIssue::AmountPrecision { amount, decimals });
+        }
+    }
+    if let (Some(price), Some(decimals)) = (limit_price, rules.precision.price_decimals) {
+        if !fits_decimals(price, decimals) {
+            issues.push(OrderIssue::PricePrecision { price, decimals });
+        }
+    }
+
+    let notional = price.filter(|p| *p > 0.0).map(|p| amount * p);
+    if let (Some(notional), Some(min_notional)) = (notional, rules.limits.min_cost) {
+        if notional < min_notional {
+            issues.push(OrderIssue::BelowMinNotional { notional, min_notional });
+        }
+    }
+
+    let required = if side.eq_ignore_ascii_case("buy") { notional } else { Some(amount) };
+    if let (Some(required), Some(available)) = (required, free_balance) {
+        if required > available {
+            issues.push(OrderIssue::InsufficientBalance { asset: spent_asset(symbol, side), required, available });
+        }
+    }
+
+    issues
+}
+
+/// Pré-valida a ordem contra as regras do mercado e o saldo livre.
+/// Ordens a mercado usam o último preço; derivativos (`BASE/QUOTE:SETTLE`) não têm
+/// o saldo checado, já que a margem depende da alavancagem.
+pub async fn validate_order(
+    exchange: &DecryptedExchange, symbol: &str, side: &str, amount: f64, price: Option<f64>,
+) -> Result<Vec<OrderIssue>, String> {
+    let ex = exchange.clone();
+    let sym = symbol.to_string();
+    let asset = (!symbol.contains(':')).then(|| spent_asset(symbol, side));
+    let (rules, free_balance) = spawn_ccxt_paced(&exchange.ccxt_id, move || {
+        let client = CCXTClient::for_exchange(&ex)?;
+        let rules = client.fetch_market_rules_sync(&sym)?;
+        let free = match (&rules, asset) {
+            (Some(_), Some(asset)) => Some(client.fetch_free_balance_sync(&asset)?),
+            _ => None,
+        };
+        Ok::<_, String>((rules, free))
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))??;
+
+    let limit_price = price.filter(|p| *p > 0.0);
+    let reference_price = match limit_price {
+        Some(p) => Some(p),
+        None if rules.is_some() => strategy_service::fetch_current_price(
+            &exchange.ccxt_id, &exchange.api_key, &exchange.api_secret,
+            exchange.passphrase.as_deref(), symbol,
+        ).await
+            .map(|q| q.price)
+            .inspect_err(|e| log::warn!("⚠️ Could not price {} for order validation: {}", symbol, e))
+            .ok(),
+        None => None,
+    };
+
+    Ok(check_order(symbol, rules.as_ref(), side, amount, reference_price, limit_price, free_balance))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    fn managed(order_id: &str, created_at: i64, ttl: i64) -> ManagedOrder {
+        ManagedOrder {
+            id: Some(ObjectId::new()),
+            user_id: "u1".into(), exchange_id: "ex1".into(), symbol: "BTC/USDT".into(),
+            order: TrackedOrder {
+                order_id: order_id.into(), side: "buy".into(), order_type: "limit".into(),
+                amount: 0.1, price: Some(60_000.0), created_at, ttl_secs: Some(ttl), lot_number: None,
+            },
+            expires_at: created_at + ttl,
+        }
+    }
+
+    fn exchange(id: &str) -> DecryptedExchange {
+        DecryptedExchange {
+            exchange_id: id.into(), ccxt_id: id.into(), name: id.to_uppercase(),
+            api_key: String::new(), api_secret: String::new(), passphrase: None,
+            is_active: true, can_trade: None, sub_account: None,
+        }
+    }
+
+    fn open_order(id: &str, exchange: &DecryptedExchange, timestamp: i64) -> Order {
+        Order {
+            _id: None, id: id.into(), user_id: "u1".into(),
+            exchange: exchange.name.clone(), exchange_id: exchange.exchange_id.clone(),
+            symbol: "BTC/USDT".into(), order_type: "limit".into(), side: "buy".into(),
+            price: Some(60_000.0), amount: 0.1, filled: 0.0, remaining: 0.1, cost: 0.0,
+            status: "open".into(), fee: None, timestamp, datetime: String::new(),
+            created_at: None, updated_at: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_open_orders_merged_across_exchanges_with_failure_reported() {
+        let exchanges = vec![exchange("binance"), exchange("okx"), exchange("kucoin")];
+        let response = merge_open_orders(exchanges, 2, |ex| async move {
+            match ex.exchange_id.as_str() {
+                "binance" => Ok(vec![open_order("b1", &ex, 100), open_order("b2", &ex, 300)]),
+                "okx" => Ok(vec![open_order("o1", &ex, 200)]),
+                _ => Err("AuthenticationError: invalid api key".to_string()),
+            }
+        }).await;
+
+        assert!(response.success);
+        assert_eq!(response.exchanges_ok, 2);
+        assert_eq!(response.count, 3);
+        let merged: Vec<(&str, &str)> = response.orders.iter().map(|o| (o.id.as_str(), o.exchange.as_str())).collect();
+        assert_eq!(merged, vec![("b2", "BINANCE"), ("o1", "OKX"), ("b1", "BINANCE")]);
+        assert_eq!(response.errors, vec![ExchangeOrdersError {
+            exchange_id: "kucoin".into(), exchange: "KUCOIN".into(),
+            error: "AuthenticationError: invalid api key".into(),
+        }]);
+    }
+
+    #[tokio::test]
+    async fn test_expiry_cancels_only_orders_past_ttl() {
+        let now = 10_000;
+        let stale = managed("stale", now - 600, 300);
+        let fresh = managed("fresh", now - 60, 300);
+        let canceled = Mutex::new(Vec::new());
+
+        let result = expire_tracked_orders(&[stale.clone(), fresh], now, |m| {
+            canceled.lock().unwrap().push(m.order.order_id.clone());
+            async { Ok(true) }
+        }).await;
+
+        assert_eq!(*canceled.lock().unwrap(), vec!["stale".to_string()]);
+        assert_eq!(result.resolved, vec![stale.id.unwrap()]);
+        assert!(result.errors.is_empty());
+    }
+
+    #[test]
+    fn test_resolve_time_in_force() {
+        assert_eq!(resolve_time_in_force(Some("gtd"), "limit", Some(60)), Ok((Some("GTC".into()), Some(60))));
+        assert_eq!(resolve_time_in_force(Some("IOC"), "limit", None), Ok((Some("IOC".into()), None)));
+        assert!(resolve_time_in_force(Some("GTD"), "market", Some(60)).is_err());
+        assert!(resolve_time_in_force(Some("GTD"), "limit", None).is_err());
+        assert!(resolve_time_in_force(Some("DAY"), "limit", None).is_err());
+    }
+
+    #[test]
+    fn test_oversized_order_blocked_by_notional_limit() {
+        // 2 BTC × $65k = $130k > $100k
+        let err = check_order_notional("BTC/USDT", 2.0, 65_000.0, 1.0, 100_000.0).unwrap_err();
+        assert!(err.contains("exceeds the safety limit"), "{}", err);
+        assert!(check_order_notional("BTC/USDT", 0.5, 65_000.0, 1.0, 100_000.0).is_ok());
+
+        // Quote em BRL convertida para USD: R$400k ≈ $80k
+        assert!(check_order_notional("BTC/BRL", 1.0, 400_000.0, 0.2, 100_000.0).is_ok());
+        assert_eq!(quote_asset("BTC/USDT:USDT"), "USDT");
+        assert_eq!(quote_asset("eth/brl"), "BRL");
+
+        // Saídas nunca são barradas (nem consultam preço)
+        assert!(notional_limit_applies("buy"));
+        assert!(notional_limit_applies("BUY"));
+        assert!(!notional_limit_applies("sell"));
+    }
+
+    fn btc_rules() -> MarketRules {
+        MarketRules {
+            active: true,
+            limits: crate::models::MarketLimits { min_amount: Some(0.001), min_cost: Some(5.0), ..Default::default() },
+            precision: crate::models::MarketPrecision { amount_decimals: Some(4), price_decimals: Some(2) },
+        }
+    }
+
+    #[test]
+    fn test_check_order_reports_each_issue() {
+        let rules = btc_rules();
+        let check = |side, amount, price: Option<f64>, limit_price, free| {
+            check_order("BTC/USDT", Some(&rules), side, amount, price, limit_price, free)
+        };
+
+        // Ordem válida: 0.01 BTC × $60k = $600 com $1000 livres
+        assert!(check("buy", 0.01, Some(60_000.0), Some(60_000.0), Some(1_000.0)).is_empty());
+        assert!(check("sell", 0.01, Some(60_000.0), None, Some(0.5)).is_empty());
+
+        assert_eq!(
+            check_order("BTC/USDT", None, "buy", 0.01, Some(60_000.0), None, None),
+            vec![OrderIssue::MarketInactive { symbol: "BTC/USDT".into() }]
+        );
+        let inactive = MarketRules { active: false, ..btc_rules() };
+        assert!(matches!(
+            check_order("BTC/USDT", Some(&inactive), "buy", 0.01, None, None, None)[..],
+            [OrderIssue::MarketInactive { .. }]
+        ));
+
+        assert_eq!(check("sell", 0.0005, None, None, None), vec![OrderIssue::BelowMinAmount { amount: 0.0005, min_amount: 0.001 }]);
+        assert!(matches!(check("buy", 0.001, Some(3_000.0), None, None)[..], [OrderIssue::BelowMinNotional { min_notional, .. }] if min_notional == 5.0));
+        assert_eq!(check("sell", 0.012345, None, None, None), vec![OrderIssue::AmountPrecision { amount: 0.012345, decimals: 4 }]);
+        assert_eq!(check("buy", 0.01, Some(60_000.123), Some(60_000.123), None), vec![OrderIssue::PricePrecision { price: 60_000.123, decimals: 2 }]);
+        assert_eq!(
+            check("buy", 0.01, Some(60_000.0), Some(60_000.0), Some(100.0)),
+            vec![OrderIssue::InsufficientBalance { asset: "USDT".into(), required: 600.0, available: 100.0 }]
+        );
+        assert_eq!(
+            check("sell", 0.01, None, None, Some(0.001)),
+            vec![OrderIssue::InsufficientBalance { asset: "BTC".into(), required: 0.01, available: 0.001 }]
+        );
+    }
+
+    struct FakeCanceller {
+        cancel_all_error: Option<&'static str>,
+        open: Vec<(&'static str, &'static str)>,
+        canceled: Mutex<Vec<String>>,
+    }
+
+    impl OrderCanceller for FakeCanceller {
+        fn cancel_all(&self, symbol: Option<&str>) -> Result<usize, String> {
+            match self.cancel_all_error {
+                Some(e) => Err(e.to_string()),
+                None => Ok(self.open.iter().filter(|(_, s)| symbol.is_none_or(|sym| sym == *s)).count()),
+            }
+        }
+
+        fn open_orders(&self, symbol: Option<&str>) -> Result<Vec<(String, String)>, String> {
+            Ok(self.open.iter()
+                .filter(|(_, s)| symbol.is_none_or(|sym| sym == *s))
+                .map(|(id, s)| (id.to_string(), s.to_string()))
+                .collect())
+        }
+
+        fn cancel_one(&self, order_id: &str, _symbol: &str) -> Result<bool, String> {
+            if order_id == "stuck" {
+                return Err("OrderNotFound".into());
+            }
+            self.canceled.lock().unwrap().push(order_id.to_string());
+            Ok(true)
+        }
+    }
+
+    #[test]
+    fn test_cancel_all_with_symbol_without_symbol_and_fallback() {
+        let open = vec![("o1", "BTC/USDT"), ("o2", "ETH/USDT"), ("o3", "BTC/USDT")];
+        let native = FakeCanceller { cancel_all_error: None, open: open.clone(), canceled: Mutex::new(vec![]) };
+        assert_eq!(cancel_all_orders_with(&native, Some("BTC/USDT")).unwrap().canceled_count, 2);
+        assert_eq!(cancel_all_orders_with(&native, None).unwrap().canceled_count, 3);
+
+        // Exchange exige símbolo: busca as abertas e cancela uma a uma
+        let fallback = FakeCanceller {
+            cancel_all_error: Some("ArgumentsRequired: binance cancelAllOrders() requires a symbol argument"),
+            open: vec![("o1", "BTC/USDT"), ("stuck", "ETH/USDT"), ("o3", "SOL/USDT")],
+            canceled: Mutex::new(vec![]),
+        };
+        let result = cancel_all_orders_with(&fallback, None).unwrap();
+        assert_eq!(*fallback.canceled.lock().unwrap(), vec!["o1", "o3"]);
+        assert_eq!(result.canceled_count, 2);
+        assert!(!result.success);
+        assert_eq!(result.errors.len(), 1);
+        // Só as ordens canceladas saem do rastreio; "stuck" continua rastreada
+        assert_eq!(
+            canceled_tracked_orders_filter("u1", "ex1", None, &result),
+            Some(doc! { "user_id": "u1", "exchange_id": "ex1", "order_id": { "$in": ["o1", "o3"] } })
+        );
+
+        // Cancelamento completo encerra todo o escopo rastreado
+        let all = cancel_all_orders_with(&native, Some("BTC/USDT")).unwrap();
+        assert_eq!(
+            canceled_tracked_orders_filter("u1", "ex1", Some("BTC/USDT"), &all),
+            Some(doc! { "user_id": "u1", "exchange_id": "ex1", "symbol": "BTC/USDT" })
+        );
+        let nothing = CancelAllOrdersResponse { success: false, canceled_count: 0, errors: vec!["x".into()], canceled_order_ids: vec![] };
+        assert_eq!(canceled_tracked_orders_filter("u1", "ex1", None, &nothing), None);
+
+        let broken = FakeCanceller { cancel_all_error: Some("AuthenticationError: bad key"), open: vec![], canceled: Mutex::new(vec![]) };
+        assert!(cancel_all_orders_with(&broken, None).is_err());
+    }
+}
@@ -0,0 +1,239 @@
+//! 📡 Eventos de execução de estratégias (Server-Sent Events)
+//!
+//! `persist_tick_result` publica cada execução persistida num canal broadcast.
+//! Cada conexão SSE assina o canal e filtra pelos eventos do próprio usuário.
+//! O broadcast nunca bloqueia quem publica: assinantes lentos perdem eventos
+//! antigos (`Lagged`) em vez de segurar o tick. Um buffer curto em memória
+//! permite reenviar eventos perdidos quando o cliente reconecta com `Last-Event-ID`.
+//!
+//! Como o EventSource não envia headers, o stream autentica por `?ticket=`: um
+//! ticket de uso único e vida curta emitido por `POST /strategies/events/ticket`,
+//! para que o JWT não trafegue na URL (logs, proxies, histórico).
+
+use crate::models::StrategyExecution;
+use actix_web::web::Bytes;
+use futures::stream::Stream;
+use lazy_static::lazy_static;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+const CHANNEL_CAPACITY: usize = 1024;
+const REPLAY_BUFFER_SIZE: usize = 500;
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+pub const STREAM_TICKET_TTL: Duration = Duration::from_secs(60);
+
+lazy_static! {
+    /// Barramento global usado pela API e pelo monitor
+    pub static ref STRATEGY_EVENTS: EventBus = EventBus::new();
+
+    /// Tickets de conexão SSE emitidos por esta instância
+    pub static ref STREAM_TICKETS: StreamTickets = StreamTickets::new(STREAM_TICKET_TTL);
+}
+
+/// Tickets de uso único para abrir o stream SSE (ticket → user_id)
+pub struct StreamTickets {
+    ttl: Duration,
+    tickets: Mutex<HashMap<String, (String, Instant)>>,
+}
+
+impl StreamTickets {
+    pub fn new(ttl: Duration) -> Self {
+        Self { ttl, tickets: Mutex::new(HashMap::new()) }
+    }
+
+    /// Emite um ticket para `user_id`, válido por `ttl`
+    pub fn issue(&self, user_id: &str) -> String {
+        self.issue_at(user_id, Instant::now())
+    }
+
+    /// Consome o ticket: devolve o dono uma única vez, se ainda não expirou
+    pub fn redeem(&self, ticket: &str) -> Option<String> {
+        self.redeem_at(ticket, Instant::now())
+    }
+
+    fn issue_at(&self, user_id: &str, now: Instant) -> String {
+        let ticket = uuid::Uuid::new_v4().simple().to_string();
+        let mut tickets = self.tickets.lock().unwrap_or_else(|e| e.into_inner());
+        tickets.retain(|_, (_, issued_at)| now.saturating_duration_since(*issued_at) < self.ttl);
+        tickets.insert(ticket.clone(), (user_id.to_string(), now));
+        ticket
+    }
+
+    fn redeem_at(&self, ticket: &str, now: Instant) -> Option<String> {
+        let mut tickets = self.tickets.lock().unwrap_or_else(|e| e.into_inner());
+        let (user_id, issued_at) = tickets.remove(ticket)?;
+        (now.saturating_duration_since(issued_at) < self.ttl).then_some(user_id)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StrategyEvent {
+    pub id: u64,
+    #[serde(skip)]
+    pub user_id: String,
+    pub strategy_id: String,
+    pub symbol: String,
+    pub execution: StrategyExecution,
+}
+
+struct EventLog {
+    next_id: u64,
+    recent: VecDeque<StrategyEvent>,
+}
+
+pub struct EventBus {
+    sender: broadcast::Sender<StrategyEvent>,
+    log: Mutex<EventLog>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        Self {
+            sender,
+            log: Mutex::new(EventLog { next_id: 1, recent: VecDeque::new() }),
+        }
+    }
+
+    /// Publica as execuções de um tick já persistido
+    pub fn publish_executions(&self, user_id: &str, strategy_id: &str, symbol: &str, executions: &[StrategyExecution]) {
+        let mut log = self.log.lock().unwrap_or_else(|e| e.into_inner());
+        for execution in executions {
+            let event = StrategyEvent {
+                id: log.next_id,
+                user_id: user_id.to_string(),
+                strategy_id: strategy_id.to_string(),
+                symbol: symbol.to_string(),
+                execution: execution.clone(),
+            };
+            log.next_id += 1;
+            log.recent.push_back(event.clone());
+            if log.recent.len() > REPLAY_BUFFER_SIZE {
+                log.recent.pop_front();
+            }
+            // Sem assinantes o send falha — ok, o evento fica no buffer de replay
+            let _ = self.sender.send(event);
+        }
+    }
+
+    /// Assina o canal. Retorna os eventos do usuário com id > `last_event_id`
+    /// ainda no buffer, mais o receiver para os próximos.
+    pub fn subscribe(&self, user_id: &str, last_event_id: Option<u64>) -> (Vec<StrategyEvent>, broadcast::Receiver<StrategyEvent>) {
+        // Segura o log enquanto assina para não perder nem duplicar eventos entre replay e live
+        let log = self.log.lock().unwrap_or_else(|e| e.into_inner());
+        let receiver = self.sender.subscribe();
+        let replay = match last_event_id {
+            Some(last) => log.recent.iter()
+                .filter(|e| e.id > last && e.user_id == user_id)
+                .cloned()
+                .collect(),
+            None => vec![],
+        };
+        (replay, receiver)
+    }
+}
+
+/// Formata um evento no protocolo SSE
+pub fn format_sse(event: &StrategyEvent) -> String {
+    let data = serde_json::to_string(event).unwrap_or_else(|_| "{}".to_string());
+    format!("id: {}\nevent: execution\ndata: {}\n\n", event.id, data)
+}
+
+/// Stream SSE do usuário: replay + eventos ao vivo + keepalive
+pub fn event_stream(
+    bus: &EventBus, user_id: String, last_event_id: Option<u64>,
+) -> impl Stream<Item = Result<Bytes, actix_web::Error>> {
+    let (replay, receiver) = bus.subscribe(&user_id, last_event_id);
+    let state = (VecDeque::from(replay), receiver, user_id);
+
+    futures::stream::unfold(state, |(mut replay, mut receiver, user_id)| async move {
+        if let Some(event) = replay.pop_front() {
+            let chunk = Bytes::from(format_sse(&event));
+            return Some((Ok(chunk), (replay, receiver, user_id)));
+        }
+
+        loop {
+            match tokio::time::timeout(KEEPALIVE_INTERVAL, receiver.recv()).await {
+                Ok(Ok(event)) if event.user_id == user_id => {
+                    let chunk = Bytes::from(format_sse(&event));
+                    return Some((Ok(chunk), (replay, receiver, user_id)));
+                }
+                Ok(Ok(_)) => continue,
+                Ok(Err(broadcast::error::RecvError::Lagged(skipped))) => {
+                    log::warn!("⚠️ SSE subscriber for {} lagged, skipped {} events", user_id, skipped);
+                    continue;
+                }
+                Ok(Err(broadcast::error::RecvError::Closed)) => return None,
+                Err(_) => {
+                    let chunk = Bytes::from_static(b": keepalive\n\n");
+                    return Some((Ok(chunk), (replay, receiver, user_id)));
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ExecutionAction;
+    use futures::StreamExt;
+
+    #[test]
+    fn test_stream_ticket_is_single_use_and_expires() {
+        let tickets = StreamTickets::new(Duration::from_secs(60));
+        let now = Instant::now();
+
+        let ticket = tickets.issue_at("u1", now);
+        assert_eq!(tickets.redeem_at(&ticket, now + Duration::from_secs(5)), Some("u1".to_string()));
+        assert_eq!(tickets.redeem_at(&ticket, now + Duration::from_secs(6)), None, "ticket reused");
+
+        let stale = tickets.issue_at("u1", now);
+        assert_eq!(tickets.redeem_at(&stale, now + Duration::from_secs(61)), None, "expired ticket accepted");
+        assert_eq!(tickets.redeem_at("unknown", now), None);
+    }
+
+    fn execution(id: &str) -> StrategyExecution {
+        StrategyExecution {
+            execution_id: id.into(), action: ExecutionAction::Sell, reason: "take_profit".into(),
+            price: 100.0, amount: 1.0, total: 100.0, fee: 0.1, pnl_usd: 5.0,
+            exchange_order_id: Some("ord-1".into()), executed_at: 0, error_message: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_persisted_execution_is_delivered_to_subscriber() {
+        let bus = EventBus::new();
+        let mut stream = Box::pin(event_stream(&bus, "u1".into(), None));
+
+        bus.publish_executions("u2", "other", "ETH/USDT", &[execution("exec-other")]);
+        bus.publish_executions("u1", "s1", "BTC/USDT", &[execution("exec-1")]);
+
+        let chunk = stream.next().await.unwrap().unwrap();
+        let text = String::from_utf8(chunk.to_vec()).unwrap();
+        assert!(text.starts_with("id: 2\nevent: execution\n"), "{}", text);
+        assert!(text.contains("exec-1"));
+        assert!(!text.contains("exec-other"));
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_replays_after_last_event_id() {
+        let bus = EventBus::new();
+        bus.publish_executions("u1", "s1", "BTC/USDT", &[execution("a"), execution("b"), execution("c")]);
+
+        let mut stream = Box::pin(event_stream(&bus, "u1".into(), Some(1)));
+        let first = String::from_utf8(stream.next().await.unwrap().unwrap().to_vec()).unwrap();
+        let second = String::from_utf8(stream.next().await.unwrap().unwrap().to_vec()).unwrap();
+        assert!(first.contains("\"execution_id\":\"b\""));
+        assert!(second.contains("\"execution_id\":\"c\""));
+    }
+}
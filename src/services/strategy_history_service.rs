@@ -0,0 +1,139 @@
+//! 📜 Histórico (append-only) das alterações de config das estratégias
+//!
+//! Cada `PUT /strategies/{id}` que muda a config grava uma entrada em
+//! `strategy_config_history` com a config anterior, a nova, o diff por campo,
+//! quem alterou e quando. Mantém no máximo `MAX_HISTORY_PER_STRATEGY` entradas
+//! por estratégia (as mais antigas são removidas).
+
+use crate::{database::MongoDB, models::StrategyConfig};
+use futures::TryStreamExt;
+use mongodb::bson::{doc, oid::ObjectId};
+use serde::{Deserialize, Serialize};
+
+pub const STRATEGY_CONFIG_HISTORY_COLLECTION: &str = "strategy_config_history";
+
+const MAX_HISTORY_PER_STRATEGY: u64 = 100;
+
+/// Campo alterado (chave de primeiro nível da config)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ConfigChange {
+    pub field: String,
+    pub before: serde_json::Value,
+    pub after: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StrategyConfigHistoryEntry {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user_id: String,
+    pub strategy_id: String,
+    pub changed_by: String,
+    pub changed_at: i64,
+    pub changes: Vec<ConfigChange>,
+    pub previous_config: StrategyConfig,
+    pub new_config: StrategyConfig,
+}
+
+/// Diff campo a campo entre duas configs (ordem alfabética dos campos)
+pub fn config_diff(before: &StrategyConfig, after: &StrategyConfig) -> Vec<ConfigChange> {
+    let as_map = |config: &StrategyConfig| match serde_json::to_value(config) {
+        Ok(serde_json::Value::Object(map)) => map,
+        _ => serde_json::Map::new(),
+    };
+    let (before, after) = (as_map(before), as_map(after));
+
+    let mut fields: Vec<&String> = before.keys().chain(after.keys()).collect();
+    fields.sort();
+    fields.dedup();
+
+    fields.into_iter()
+        .filter_map(|field| {
+            let old = before.get(field).cloned().unwrap_or(serde_json::Value::Null);
+            let new = after.get(field).cloned().unwrap_or(serde_json::Value::Null);
+            (old != new).then(|| ConfigChange { field: field.clone(), before: old, after: new })
+        })
+        .collect()
+}
+
+/// Entrada de histórico, ou None se a config não mudou
+pub fn build_history_entry(
+    user_id: &str, strategy_id: &str, previous: &StrategyConfig, new: &StrategyConfig, now: i64,
+) -> Option<StrategyConfigHistoryEntry> {
+    let changes = config_diff(previous, new);
+    if changes.is_empty() {
+        return None;
+    }
+    Some(StrategyConfigHistoryEntry {
+        id: None,
+        user_id: user_id.to_string(),
+        strategy_id: strategy_id.to_string(),
+        changed_by: user_id.to_string(),
+        changed_at: now,
+        changes,
+        previous_config: previous.clone(),
+        new_config: new.clone(),
+    })
+}
+
+/// Grava a entrada e remove as excedentes mais antigas da estratégia
+pub async fn record_config_change(db: &MongoDB, entry: &StrategyConfigHistoryEntry) -> Result<(), String> {
+    let collection = db.collection::<StrategyConfigHistoryEntry>(STRATEGY_CONFIG_HISTORY_COLLECTION);
+    collection.insert_one(entry).await
+        .map_err(|e| format!("Failed to record config history: {}", e))?;
+
+    let filter = doc! { "user_id": &entry.user_id, "strategy_id": &entry.strategy_id };
+    let stale: Vec<StrategyConfigHistoryEntry> = collection.find(filter)
+        .sort(doc! { "changed_at": -1, "_id": -1 })
+        .skip(MAX_HISTORY_PER_STRATEGY)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .try_collect()
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    let stale_ids: Vec<ObjectId> = stale.into_iter().filter_map(|e| e.id).collect();
+    if !stale_ids.is_empty() {
+        collection.delete_many(doc! { "_id": { "$in": stale_ids } }).await
+            .map_err(|e| format!("Failed to prune config history: {}", e))?;
+    }
+    Ok(())
+}
+
+/// Histórico da estratégia, mais recente primeiro
+pub async fn get_config_history(
+    db: &MongoDB, user_id: &str, strategy_id: &str, limit: i64,
+) -> Result<Vec<StrategyConfigHistoryEntry>, String> {
+    db.collection::<StrategyConfigHistoryEntry>(STRATEGY_CONFIG_HISTORY_COLLECTION)
+        .find(doc! { "user_id": user_id, "strategy_id": strategy_id })
+        .sort(doc! { "changed_at": -1, "_id": -1 })
+        .limit(limit)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .try_collect()
+        .await
+        .map_err(|e| format!("Database error: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_profit_update_records_before_after_diff() {
+        let previous = StrategyConfig { base_price: 100.0, take_profit_percent: 10.0, ..Default::default() };
+        let new = StrategyConfig { take_profit_percent: 15.0, ..previous.clone() };
+
+        let entry = build_history_entry("u1", "s1", &previous, &new, 1_700_000_000).expect("entry");
+        assert_eq!(entry.changes, vec![ConfigChange {
+            field: "take_profit_percent".into(),
+            before: serde_json::json!(10.0),
+            after: serde_json::json!(15.0),
+        }]);
+        assert_eq!(entry.changed_by, "u1");
+        assert_eq!(entry.previous_config.take_profit_percent, 10.0);
+        assert_eq!(entry.new_config.take_profit_percent, 15.0);
+
+        assert!(build_history_entry("u1", "s1", &previous, &previous, 0).is_none());
+    }
+}
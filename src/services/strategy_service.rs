@@ -2,16 +2,42 @@ use crate::{
     ccxt::CCXTClient,
     database::MongoDB,
     models::{
-        DecryptedExchange, ExecutionAction, PositionInfo, StrategyItem,
-        StrategyExecution, StrategySignal, StrategyStatus, SignalType,
-        UserStrategies,
+        ArchivedStrategy, CcxtOrder, CreateStrategyRequest, DecryptedExchange, ExecutionAction, GridSide, GridState, LimitEntryConfig, MarketPrecision, MinNotionalPolicy, parse_ccxt_order, PositionInfo, ReconcilePolicy, StrategyConfig, StrategyItem, StrategyMode,
+        ImportStrategyRequest, StrategyExecution, StrategyExport, StrategyListItem, StrategySignal, StrategyStatus, SignalType,
+        TrackedOrder, UserStrategies, STRATEGY_EXPORT_VERSION, ExchangeCatalog, ExchangePause,
     },
-    services::user_exchanges_service,
-    utils::thread_pool::spawn_ccxt_blocking,
+    services::{credential_health_service, fee_service, ohlcv_cache_service, user_exchanges_service, user_settings_service},
+    utils::expression,
+    utils::precision::ExecutionPrecision,
+    utils::indicators,
+    utils::ticker_cache::{PriceQuote, TICKER_CACHE},
+    utils::thread_pool::spawn_ccxt_paced,
+    utils::trading_switch::TRADING_SWITCH,
+    utils::circuit_breaker::{ExchangeBreaker, EXCHANGE_BREAKER},
 };
 use mongodb::bson::doc;
+use std::collections::HashMap;
 
 const COLLECTION: &str = "user_strategy";
+pub const ARCHIVE_COLLECTION: &str = "strategies_archive";
+/// Status finais: a estratégia não opera mais e pode ir para o arquivo
+const ARCHIVABLE_STATUSES: &[&str] = &["completed", "stopped_out", "expired"];
+
+/// Parâmetros do filtro de volatilidade (ATR)
+const ATR_TIMEFRAME: &str = "1h";
+const ATR_PERIOD: usize = 14;
+const SMA_FAST_PERIOD: usize = 9;
+const RSI_PERIOD: usize = 14;
+const DEFAULT_NOTIFICATION_THROTTLE_SECS: i64 = 300;
+const DEFAULT_CHECK_INTERVAL_SECS: i64 = 30;
+const DEFAULT_ORDER_MAX_RETRIES: u32 = 2;
+const DEFAULT_ORDER_RETRY_BACKOFF_MS: u64 = 500;
+const DEFAULT_MAX_PRICE_AGE_SECS: i64 = 60;
+const DEFAULT_RECONCILE_TOLERANCE_PERCENT: f64 = 2.0;
+const DEFAULT_GRID_BUY_RETRY_BACKOFF_SECS: i64 = 60;
+const DEFAULT_GRID_BUY_RETRY_MAX_SECS: i64 = 3600;
+/// Abaixo disso a posição é considerada zerada (resto de arredondamento)
+const MIN_POSITION_QTY: f64 = 1e-9;
 
 #[derive(Debug)]
 pub struct TickResult {
@@ -22,28 +48,241 @@ pub struct TickResult {
     pub executions: Vec<StrategyExecution>,
     pub new_status: Option<StrategyStatus>,
     pub error: Option<String>,
+    /// Precisão do mercado para arredondar as execuções gravadas/exibidas; os
+    /// cálculos (posição, PnL) usam `executions` sem arredondar. None = padrão
+    pub precision: Option<ExecutionPrecision>,
+}
+
+impl TickResult {
+    /// Cópia das execuções na precisão do mercado, para gravar e exibir
+    pub fn persisted_executions(&self) -> Vec<StrategyExecution> {
+        let precision = self.precision.unwrap_or_default();
+        self.executions.iter().cloned().map(|e| e.rounded(&precision)).collect()
+    }
 }
 
+/// Último preço do símbolo com o `timestamp` do ticker (para a checagem de preço defasado)
 pub async fn fetch_current_price(
     ccxt_id: &str, api_key: &str, api_secret: &str,
     passphrase: Option<&str>, symbol: &str,
-) -> Result<f64, String> {
-    let ccxt_id = ccxt_id.to_string();
-    let api_key = api_key.to_string();
-    let api_secret = api_secret.to_string();
-    let passphrase = passphrase.map(|s| s.to_string());
-    let symbol = symbol.to_string();
+) -> Result<PriceQuote, String> {
+    // ⚡ Ticks simultâneos no mesmo (exchange, símbolo) compartilham um único fetch
+    TICKER_CACHE.get_or_fetch(ccxt_id, symbol, || {
+        let ccxt_id = ccxt_id.to_string();
+        let api_key = api_key.to_string();
+        let api_secret = api_secret.to_string();
+        let passphrase = passphrase.map(|s| s.to_string());
+        let symbol = symbol.to_string();
+
+        async move {
+            spawn_ccxt_paced(&ccxt_id.clone(), move || {
+                let client = CCXTClient::new(&ccxt_id, &api_key, &api_secret, passphrase.as_deref())?;
+                let ticker = client.fetch_ticker_sync(&symbol)?;
+                let price = ticker.get("last").and_then(|v| v.as_f64())
+                    .ok_or_else(|| format!("No 'last' price for {}", symbol))?;
+                Ok(PriceQuote { price, timestamp: ticker.get("timestamp").and_then(|v| v.as_i64()) })
+            })
+            .await
+            .map_err(|e| format!("Task join error: {}", e))?
+        }
+    })
+    .await
+}
+
+/// Últimos `limit` candles, servidos pelo cache `ohlcv_cache` (só o trecho faltante vai ao exchange)
+pub async fn fetch_candles(
+    db: &MongoDB, exchange: &DecryptedExchange, symbol: &str, timeframe: &str, limit: usize,
+) -> Result<Vec<crate::models::Candle>, String> {
+    let tf_ms = ohlcv_cache_service::timeframe_to_millis(timeframe)
+        .ok_or_else(|| format!("Unsupported timeframe: {}", timeframe))?;
+    let now_ms = chrono::Utc::now().timestamp_millis();
+    let from_ms = now_ms - (limit as i64 - 1) * tf_ms;
+
+    ohlcv_cache_service::fetch_candles_cached(db, exchange, symbol, timeframe, from_ms, now_ms).await
+}
+
+/// Carrega as variáveis usadas por `entry_condition` (ver `utils::expression::ALLOWED_VARIABLES`).
+/// Ticker e candles só são buscados se a expressão os referencia.
+async fn load_entry_variables(
+    db: &MongoDB, exchange: &DecryptedExchange, symbol: &str, price: f64, expr: &expression::Expr,
+) -> Result<HashMap<&'static str, f64>, String> {
+    let used = expr.variables();
+    let mut vars: HashMap<&'static str, f64> = HashMap::from([("price", price)]);
+
+    if used.iter().any(|v| *v == "high_24h" || *v == "low_24h") {
+        let ccxt_id = exchange.ccxt_id.clone();
+        let api_key = exchange.api_key.clone();
+        let api_secret = exchange.api_secret.clone();
+        let passphrase = exchange.passphrase.clone();
+        let sym = symbol.to_string();
+        let ticker = spawn_ccxt_paced(&exchange.ccxt_id, move || {
+            let client = CCXTClient::new(&ccxt_id, &api_key, &api_secret, passphrase.as_deref())?;
+            client.fetch_ticker_sync(&sym)
+        })
+        .await
+        .map_err(|e| format!("Task join error: {}", e))??;
+
+        if let Some(high) = ticker.get("high").and_then(|v| v.as_f64()) {
+            vars.insert("high_24h", high);
+        }
+        if let Some(low) = ticker.get("low").and_then(|v| v.as_f64()) {
+            vars.insert("low_24h", low);
+        }
+    }
+
+    if used.iter().any(|v| *v == "sma_fast" || *v == "rsi") {
+        let candles = fetch_candles(db, exchange, symbol, ATR_TIMEFRAME, RSI_PERIOD * 2 + 1).await?;
+        if let Some(sma) = indicators::sma(&candles, SMA_FAST_PERIOD) {
+            vars.insert("sma_fast", sma);
+        }
+        if let Some(rsi) = indicators::rsi(&candles, RSI_PERIOD) {
+            vars.insert("rsi", rsi);
+        }
+    }
+
+    Ok(vars)
+}
+
+/// Idade máxima do ticker (`MAX_PRICE_AGE_SECS`, padrão 60s). 0 desativa a checagem.
+fn max_price_age_secs() -> Option<i64> {
+    let secs = std::env::var("MAX_PRICE_AGE_SECS")
+        .ok().and_then(|v| v.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_MAX_PRICE_AGE_SECS);
+    (secs > 0).then_some(secs)
+}
+
+/// Sinal Info quando o `timestamp` do ticker é mais antigo que `max_age_secs`;
+/// nesse caso a tick não avalia nem executa nada. Ticker sem timestamp passa.
+fn check_price_staleness(quote: &PriceQuote, now: i64, max_age_secs: Option<i64>) -> Option<StrategySignal> {
+    let max_age = max_age_secs?;
+    let age = now - quote.timestamp? / 1000;
+    if age <= max_age {
+        return None;
+    }
+    Some(StrategySignal {
+        signal_type: SignalType::Info, price: quote.price,
+        message: format!("⏳ Preço defasado ({}s, máximo {}s), execução pulada.", age, max_age),
+        acted: false, price_change_percent: 0.0, created_at: now,
+    })
+}
+
+/// Resultado da tick quando o preço está defasado: só o aviso, sem avaliação nem ordens
+fn stale_price_result(strategy: &StrategyItem, quote: &PriceQuote, now: i64, max_age_secs: Option<i64>) -> Option<TickResult> {
+    let signal = check_price_staleness(quote, now, max_age_secs)?;
+    log::warn!("⏳ [{}] {}", strategy.strategy_id, signal.message);
+    Some(TickResult {
+        strategy_id: strategy.strategy_id.clone(), symbol: strategy.symbol.clone(), price: quote.price,
+        signals: vec![signal], executions: vec![], new_status: None, error: None,
+        precision: None,
+    })
+}
+
+/// Divergência (para menos) acima da qual a posição é reconciliada (`RECONCILE_TOLERANCE_PERCENT`, padrão 2%)
+fn reconcile_tolerance_percent() -> f64 {
+    std::env::var("RECONCILE_TOLERANCE_PERCENT")
+        .ok().and_then(|v| v.parse::<f64>().ok())
+        .filter(|v| *v >= 0.0)
+        .unwrap_or(DEFAULT_RECONCILE_TOLERANCE_PERCENT)
+}
+
+/// Posição registrada divergente do saldo real, conforme `config.reconcile`
+#[derive(Debug, Clone)]
+pub enum PositionDrift {
+    /// Só marca a estratégia (error_message)
+    Flag(String),
+    /// Posição com a quantidade corrigida para o saldo real
+    Adjust(PositionInfo, String),
+}
+
+/// Compara `position.quantity` com o saldo total (free + used) do ativo base.
+/// Só faltas contam: saldo acima do esperado pode ser de outras estratégias ou do próprio usuário.
+/// Futuros não são reconciliados (a posição não aparece como saldo do ativo).
+pub fn check_position_drift(strategy: &StrategyItem, actual: f64, price: f64, tolerance_percent: f64) -> Option<PositionDrift> {
+    if strategy.config.mode == StrategyMode::Futures {
+        return None;
+    }
+    let position = open_position(strategy)?;
+    let shortfall_percent = (position.quantity - actual) / position.quantity * 100.0;
+    if shortfall_percent <= tolerance_percent {
+        return None;
+    }
+
+    let asset = strategy.symbol.split('/').next().unwrap_or(&strategy.symbol);
+    let message = format!(
+        "Posição divergente: a estratégia registra {:.8} {} mas o saldo na exchange é {:.8} ({:.2}% a menos).",
+        position.quantity, asset, actual, shortfall_percent
+    );
+    match strategy.config.reconcile {
+        ReconcilePolicy::Off => None,
+        ReconcilePolicy::Adjust if actual > 0.0001 => {
+            let mut adjusted = position.clone();
+            adjusted.quantity = actual;
+            adjusted.total_cost = position.entry_price * actual;
+            if price > 0.0 && position.entry_price > 0.0 {
+                adjusted.current_price = price;
+                adjusted.unrealized_pnl = (price - position.entry_price) * actual;
+                adjusted.unrealized_pnl_percent = ((price - position.entry_price) / position.entry_price) * 100.0;
+            }
+            Some(PositionDrift::Adjust(adjusted, message))
+        }
+        ReconcilePolicy::Adjust => Some(PositionDrift::Flag(format!("{} Saldo zerado, nada a ajustar.", message))),
+        ReconcilePolicy::Warn => Some(PositionDrift::Flag(message)),
+    }
+}
 
-    spawn_ccxt_blocking(move || {
-        let client = CCXTClient::new(&ccxt_id, &api_key, &api_secret, passphrase.as_deref())?;
-        let ticker = client.fetch_ticker_sync(&symbol)?;
-        ticker.get("last").and_then(|v| v.as_f64())
-            .ok_or_else(|| format!("No 'last' price for {}", symbol))
+/// Saldo total do ativo base do par ("BTC/USDT" → BTC)
+async fn fetch_base_balance(exchange: &DecryptedExchange, symbol: &str) -> Result<f64, String> {
+    let ex = exchange.clone();
+    let asset = symbol.split('/').next().unwrap_or(symbol).to_uppercase();
+    spawn_ccxt_paced(&exchange.ccxt_id, move || {
+        let client = CCXTClient::for_exchange(&ex)?;
+        client.fetch_total_balance_sync(&asset)
     })
     .await
     .map_err(|e| format!("Task join error: {}", e))?
 }
 
+/// Filtro de volatilidade para entradas: retorna um sinal Info explicando o bloqueio
+/// quando o ATR% atual excede `max_atr_percent`. Falhas ao buscar candles não bloqueiam.
+async fn check_volatility_filter(
+    db: &MongoDB, exchange: &DecryptedExchange, strategy: &StrategyItem, price: f64, now: i64,
+) -> Option<StrategySignal> {
+    strategy.config.max_atr_percent.filter(|v| *v > 0.0)?;
+
+    let candles = match fetch_candles(db, exchange, &strategy.symbol, ATR_TIMEFRAME, ATR_PERIOD * 2 + 1).await {
+        Ok(c) => c,
+        Err(e) => {
+            log::warn!("⚠️ [{}] OHLCV fetch failed, skipping volatility filter: {}", strategy.strategy_id, e);
+            return None;
+        }
+    };
+
+    volatility_filter_signal(strategy, &candles, price, now)
+}
+
+/// Parte pura do filtro: sinal Info se o ATR% dos `candles` passa do limite
+fn volatility_filter_signal(
+    strategy: &StrategyItem, candles: &[crate::models::Candle], price: f64, now: i64,
+) -> Option<StrategySignal> {
+    let max_atr = strategy.config.max_atr_percent.filter(|v| *v > 0.0)?;
+    let atr_pct = indicators::atr_percent(candles, ATR_PERIOD)?;
+    if atr_pct <= max_atr {
+        return None;
+    }
+
+    let pct = if strategy.config.base_price > 0.0 {
+        ((price - strategy.config.base_price) / strategy.config.base_price) * 100.0
+    } else { 0.0 };
+    Some(StrategySignal {
+        signal_type: SignalType::Info, price,
+        message: format!(
+            "🌪️ Entrada suspensa: volatilidade alta. ATR({}, {}) = {:.2}% > limite {:.2}%. Aguardando o mercado acalmar.",
+            ATR_PERIOD, ATR_TIMEFRAME, atr_pct, max_atr
+        ),
+        acted: false, price_change_percent: pct, created_at: now,
+    })
+}
+
 pub async fn tick(db: &MongoDB, user_id: &str, strategy: &StrategyItem) -> TickResult {
     let strategy_id = strategy.strategy_id.clone();
     let now = chrono::Utc::now().timestamp();
@@ -54,6 +293,7 @@ pub async fn tick(db: &MongoDB, user_id: &str, strategy: &StrategyItem) -> TickR
             strategy_id, symbol: strategy.symbol.clone(), price: 0.0,
             signals: vec![], executions: vec![], new_status: None,
             error: Some(format!("Strategy '{}' is not active. Activate it to resume monitoring.", strategy.name)),
+            precision: None,
         };
     }
 
@@ -64,6 +304,7 @@ pub async fn tick(db: &MongoDB, user_id: &str, strategy: &StrategyItem) -> TickR
                 strategy_id, symbol: strategy.symbol.clone(), price: 0.0,
                 signals: vec![], executions: vec![], new_status: None,
                 error: Some(format!("Strategy '{}' is paused. Activate it to resume.", strategy.name)),
+                precision: None,
             };
         }
         StrategyStatus::Completed => {
@@ -71,6 +312,7 @@ pub async fn tick(db: &MongoDB, user_id: &str, strategy: &StrategyItem) -> TickR
                 strategy_id, symbol: strategy.symbol.clone(), price: 0.0,
                 signals: vec![], executions: vec![], new_status: None,
                 error: Some(format!("Strategy '{}' already completed with PnL ${:.2}.", strategy.name, strategy.total_pnl_usd)),
+                precision: None,
             };
         }
         StrategyStatus::StoppedOut => {
@@ -78,6 +320,7 @@ pub async fn tick(db: &MongoDB, user_id: &str, strategy: &StrategyItem) -> TickR
                 strategy_id, symbol: strategy.symbol.clone(), price: 0.0,
                 signals: vec![], executions: vec![], new_status: None,
                 error: Some(format!("Strategy '{}' was stopped out (stop loss triggered).", strategy.name)),
+                precision: None,
             };
         }
         StrategyStatus::Expired => {
@@ -85,6 +328,7 @@ pub async fn tick(db: &MongoDB, user_id: &str, strategy: &StrategyItem) -> TickR
                 strategy_id, symbol: strategy.symbol.clone(), price: 0.0,
                 signals: vec![], executions: vec![], new_status: None,
                 error: Some(format!("Strategy '{}' expired after {} minutes.", strategy.name, strategy.config.time_execution_min)),
+                precision: None,
             };
         }
         StrategyStatus::Error => {
@@ -93,6 +337,7 @@ pub async fn tick(db: &MongoDB, user_id: &str, strategy: &StrategyItem) -> TickR
                 signals: vec![], executions: vec![], new_status: None,
                 error: Some(format!("Strategy '{}' is in error state: {}. Fix the issue and reactivate.",
                     strategy.name, strategy.error_message.as_deref().unwrap_or("unknown error"))),
+                precision: None,
             };
         }
         _ => {}
@@ -105,6 +350,24 @@ pub async fn tick(db: &MongoDB, user_id: &str, strategy: &StrategyItem) -> TickR
             signals: vec![], executions: vec![],
             new_status: Some(StrategyStatus::Error),
             error: Some("Invalid configuration: base_price must be greater than 0. Update the strategy config.".into()),
+            precision: None,
+        };
+    }
+
+    // ── Guard: filha aguardando a posição do pai (não abre posição própria nem expira) ──
+    if strategy.waiting_for_parent() {
+        return TickResult {
+            strategy_id, symbol: strategy.symbol.clone(), price: 0.0,
+            signals: vec![StrategySignal {
+                signal_type: SignalType::Info, price: 0.0,
+                message: format!(
+                    "⛓️ Aguardando a estratégia pai {} terminar para herdar a posição.",
+                    strategy.parent_strategy_id.as_deref().unwrap_or_default()
+                ),
+                acted: false, price_change_percent: 0.0, created_at: now,
+            }],
+            executions: vec![], new_status: None, error: None,
+            precision: None,
         };
     }
 
@@ -122,6 +385,7 @@ pub async fn tick(db: &MongoDB, user_id: &str, strategy: &StrategyItem) -> TickR
                 acted: false, price_change_percent: 0.0, created_at: now,
             }],
             executions: vec![], new_status: Some(StrategyStatus::Expired), error: None,
+            precision: None,
         };
     }
 
@@ -135,6 +399,7 @@ pub async fn tick(db: &MongoDB, user_id: &str, strategy: &StrategyItem) -> TickR
                 signals: vec![], executions: vec![],
                 new_status: Some(StrategyStatus::Error),
                 error: Some("Failed to access exchange credentials. Please reconnect your exchange.".into()),
+                precision: None,
             };
         }
     };
@@ -151,16 +416,31 @@ pub async fn tick(db: &MongoDB, user_id: &str, strategy: &StrategyItem) -> TickR
                     "Exchange '{}' not found or disconnected. Reconnect your exchange and reactivate the strategy.",
                     strategy.exchange_name
                 )),
+                precision: None,
             };
         }
     };
 
+    // ── Modo somente alerta (chave sem permissão de trade) ──────────
+    let alert_only = !can_execute_orders(exchange);
+    if alert_only != strategy.alert_only {
+        if alert_only {
+            log::warn!("🔕 [{}] Exchange key lacks trade permission, running in alert-only mode", strategy_id);
+        }
+        if let Err(e) = set_alert_only(db, user_id, &strategy_id, alert_only).await {
+            log::warn!("⚠️ [{}] Failed to persist alert-only mode: {}", strategy_id, e);
+        }
+    }
+
+    // ── Aguardando confirmação ou trading desligado: sinais sim, ordens não ──
+    let suppressed = suppression_reason(strategy, exchange, TRADING_SWITCH.is_enabled());
+
     // ── Fetch current price ─────────────────────────────────────────
-    let price = match fetch_current_price(
+    let quote = match fetch_current_price(
         &exchange.ccxt_id, &exchange.api_key, &exchange.api_secret,
         exchange.passphrase.as_deref(), &strategy.symbol,
     ).await {
-        Ok(p) if p <= 0.0 => {
+        Ok(PriceQuote { price: p, .. }) if p <= 0.0 => {
             return TickResult {
                 strategy_id, symbol: strategy.symbol.clone(), price: 0.0,
                 signals: vec![], executions: vec![], new_status: None,
@@ -168,10 +448,23 @@ pub async fn tick(db: &MongoDB, user_id: &str, strategy: &StrategyItem) -> TickR
                     "Received invalid price ({}) for {}. The market may be closed or the pair delisted.",
                     p, strategy.symbol
                 )),
+                precision: None,
             };
         }
-        Ok(p) => p,
+        Ok(quote) => quote,
         Err(e) => {
+            // ── Guard: credenciais revogadas/expiradas ──────────────────
+            if credential_health_service::report_exchange_result(
+                db, user_id, &exchange.exchange_id, &exchange.name, Err(&e),
+            ).await {
+                return TickResult {
+                    strategy_id, symbol: strategy.symbol.clone(), price: 0.0,
+                    signals: vec![], executions: vec![],
+                    new_status: Some(StrategyStatus::Error),
+                    error: Some(credential_health_service::credentials_disabled_message(&strategy.exchange_name)),
+                    precision: None,
+                };
+            }
             let friendly = if e.contains("NetworkError") || e.contains("timeout") {
                 format!("Network error fetching {} price. Will retry on next tick.", strategy.symbol)
             } else if e.contains("BadSymbol") || e.contains("not found") {
@@ -190,56 +483,382 @@ pub async fn tick(db: &MongoDB, user_id: &str, strategy: &StrategyItem) -> TickR
                 strategy_id, symbol: strategy.symbol.clone(), price: 0.0,
                 signals: vec![], executions: vec![], new_status: None,
                 error: Some(friendly),
+                precision: None,
             };
         }
     };
 
+    let price = quote.price;
+
+    // ── Guard: preço defasado (atraso nos dados da exchange) ────────
+    if let Some(result) = stale_price_result(strategy, &quote, now, max_price_age_secs()) {
+        return result;
+    }
+
+    // ── Take profit na exchange: vendas limit executadas desde o último tick ──
+    if !take_profit_orders(strategy).is_empty() {
+        let fills = reconcile_take_profit_orders(db, user_id, exchange, strategy, now).await;
+        if !fills.is_empty() {
+            // Tick só de fills: a próxima avalia saídas com a posição atualizada
+            return TickResult {
+                strategy_id, symbol: strategy.symbol.clone(), price, signals: vec![],
+                new_status: take_profit_fill_status(strategy, &fills), executions: fills, error: None,
+                precision: None,
+            };
+        }
+    }
+
+    // ── Trailing nativo: venda executada na exchange desde o último tick ──
+    if let Some(trailing) = native_trailing_order(strategy) {
+        let fills = reconcile_native_trailing(db, user_id, exchange, strategy, trailing, now).await;
+        if !fills.is_empty() {
+            let new_status = native_trailing_fill_status(strategy, &fills);
+            let error = new_status.as_ref().map(|_| format!(
+                "Pausada automaticamente: trailing stop nativo de {:.2}% executado na exchange.",
+                strategy.config.max_drawdown_percent.unwrap_or(0.0)
+            ));
+            return TickResult {
+                strategy_id, symbol: strategy.symbol.clone(), price, signals: vec![],
+                new_status, executions: fills, error,
+                precision: None,
+            };
+        }
+    }
+
+    // ── Entrada limit pendente: executou, persegue o preço ou cai para mercado ──
+    if let Some(order) = limit_entry_order(strategy) {
+        return tick_limit_entry(db, user_id, exchange, strategy, order, price, now).await;
+    }
+
+    // ── Reconciliação: posição registrada × saldo real do ativo base ──
+    let mut reconcile_warning: Option<String> = None;
+    if strategy.config.reconcile != ReconcilePolicy::Off && open_position(strategy).is_some() {
+        let drift = match fetch_base_balance(exchange, &strategy.symbol).await {
+            Ok(actual) => check_position_drift(strategy, actual, price, reconcile_tolerance_percent()),
+            Err(e) => {
+                log::warn!("⚠️ [{}] Position reconciliation skipped: {}", strategy_id, e);
+                None
+            }
+        };
+        match drift {
+            Some(PositionDrift::Flag(message)) => {
+                log::warn!("⚖️ [{}] {}", strategy_id, message);
+                reconcile_warning = Some(message);
+            }
+            Some(PositionDrift::Adjust(position, message)) => {
+                log::warn!("⚖️ [{}] {} Adjusting quantity to {:.8}", strategy_id, message, position.quantity);
+                if let Err(e) = set_position(db, user_id, &strategy_id, &position).await {
+                    log::error!("❌ [{}] Failed to persist reconciled position: {}", strategy_id, e);
+                    return TickResult {
+                        strategy_id, symbol: strategy.symbol.clone(), price: 0.0,
+                        signals: vec![], executions: vec![], new_status: None,
+                        error: Some(message),
+                        precision: None,
+                    };
+                }
+                // Tick só de ajuste: a próxima avalia saídas com a quantidade corrigida
+                return TickResult {
+                    strategy_id, symbol: strategy.symbol.clone(), price: 0.0,
+                    signals: vec![StrategySignal {
+                        signal_type: SignalType::Info, price,
+                        message: format!("⚖️ {} Quantidade ajustada para {:.8}.", message, position.quantity),
+                        acted: false, price_change_percent: 0.0, created_at: now,
+                    }],
+                    executions: vec![], new_status: None, error: None,
+                    precision: None,
+                };
+            }
+            None => {}
+        }
+    }
+
+    // ── Grid: com layout cada nível compra e vende por conta própria ──
+    let grid_state = active_grid_state(strategy);
+    let grid_levels = grid_state.as_ref().filter(|s| s.has_layout());
+
     let mut signals: Vec<StrategySignal> = Vec::new();
     let mut executions: Vec<StrategyExecution> = Vec::new();
     let mut new_status: Option<StrategyStatus> = None;
 
     match strategy.status {
-        StrategyStatus::Idle | StrategyStatus::Monitoring => {
+        // Guardas comuns (drawdown, stop loss) antes dos níveis; entradas e TP são dos níveis
+        StrategyStatus::Idle | StrategyStatus::Monitoring | StrategyStatus::InPosition if grid_levels.is_some() => {
             if strategy.status == StrategyStatus::Idle {
                 new_status = Some(StrategyStatus::Monitoring);
             }
-            evaluate_trigger(strategy, price, now, &mut signals);
+            if let Some(signal) = grid_levels.and_then(|state| evaluate_grid_guards(strategy, state, price, now)) {
+                signals.push(signal);
+            }
         }
-        StrategyStatus::InPosition => {
-            evaluate_exit(strategy, price, now, &mut signals);
+        StrategyStatus::Idle | StrategyStatus::Monitoring => {
+            if strategy.status == StrategyStatus::Idle {
+                new_status = Some(StrategyStatus::Monitoring);
+            }
+            // ── Guard: volatility filter (only while waiting for entry) ──
+            let blocked = if strategy.position.is_none() {
+                check_volatility_filter(db, exchange, strategy, price, now).await
+            } else {
+                None
+            };
+            match blocked {
+                Some(signal) => signals.push(signal),
+                None => {
+                    // ── Custom entry condition (expression) ─────────────
+                    let mut entry_vars = None;
+                    if strategy.position.is_none() && strategy.config.auto_entry {
+                        if let Some(src) = strategy.config.entry_condition.as_deref() {
+                            match expression::parse(src) {
+                                Ok(expr) => match load_entry_variables(db, exchange, &strategy.symbol, price, &expr).await {
+                                    Ok(vars) => entry_vars = Some(vars),
+                                    Err(e) => log::warn!("⚠️ [{}] Failed to load entry variables: {}", strategy_id, e),
+                                },
+                                Err(e) => log::warn!("⚠️ [{}] Invalid entry_condition '{}': {}", strategy_id, src, e),
+                            }
+                        }
+                    }
+                    // ── Scalping: pressão de compra no book (só com o entry_condition satisfeito) ──
+                    let book_imbalance = match &strategy.config.order_book_imbalance {
+                        Some(book) if strategy.position.is_none() && strategy.config.auto_entry
+                            && entry_condition_met(strategy, entry_vars.as_ref()) != Some(false) => {
+                            fetch_order_book_imbalance(exchange, strategy, book.levels).await
+                        }
+                        _ => None,
+                    };
+                    if !evaluate_order_book_entry(strategy, book_imbalance, entry_vars.as_ref(), price, now, &mut signals)
+                        && !evaluate_entry(strategy, price, now, entry_vars.as_ref(), &mut signals) {
+                        evaluate_trigger(strategy, price, now, &mut signals);
+                    }
+                }
+            }
         }
-        StrategyStatus::GradualSelling => {
-            evaluate_gradual(strategy, price, now, &mut signals);
+        StrategyStatus::InPosition | StrategyStatus::GradualSelling => {
+            // ── Guard: max drawdown from peak → flatten and pause ──────
+            // (desligado enquanto o trailing nativo protege a posição na exchange)
+            let drawdown = match native_trailing_order(strategy) {
+                Some(_) => None,
+                None => evaluate_drawdown_guard(strategy, price, now),
+            };
+            if let Some(signal) = drawdown {
+                signals.push(signal);
+            } else if strategy.status == StrategyStatus::InPosition {
+                evaluate_exit(strategy, price, now, &mut signals);
+                // ── Scalping: pressão de venda no book antes do TP ──────
+                let book_exit = strategy.config.order_book_imbalance.as_ref().filter(|b| b.exit_ratio.is_some());
+                if let Some(book) = book_exit {
+                    if !signals.iter().any(|s| s.signal_type.places_order()) {
+                        let imbalance = fetch_order_book_imbalance(exchange, strategy, book.levels).await;
+                        evaluate_order_book_exit(strategy, imbalance, price, now, &mut signals);
+                    }
+                }
+            } else {
+                evaluate_gradual(strategy, price, now, &mut signals);
+            }
+            drop_exchange_take_profit_signals(strategy, &mut signals);
         }
         _ => {}
     }
 
+    // ── Grid: breakeven com a taxa real da conta (cache diário, lido em persist_tick_result) ──
+    if strategy.config.grid.is_some() {
+        fee_service::fees_for_exchange(user_id, exchange).await;
+    }
+
+    // ── Acumulado abaixo do mínimo: nova janela quando o sinal de compra some ──
+    let buy_signal = signals.iter().any(|s| s.signal_type == SignalType::Buy);
+    if let Some((pending, window)) = pending_buy_update(strategy, buy_signal) {
+        if let Err(e) = set_pending_buy_usd(db, user_id, &strategy.strategy_id, pending, window).await {
+            log::error!("❌ [{}] {}", strategy.strategy_id, e);
+        }
+    }
+
+    let mut guard_signals: Vec<StrategySignal> = Vec::new();
+    let mut tick_error: Option<String> = reconcile_warning;
+    let mut trailing_released = false;
+
     for signal in &mut signals {
+        if !admit_signal(strategy, suppressed.as_ref(), signal, price) {
+            continue;
+        }
+        // ── Invariante de lado: venda sem posição nunca chega à exchange ──
+        if signal.signal_type != SignalType::Buy {
+            if let Some(refused) = check_order_side(strategy, &signal.signal_type, 0.0, price, now) {
+                signal.acted = false;
+                record_refusal(strategy, &mut executions, refused);
+                continue;
+            }
+        }
         match signal.signal_type {
+            SignalType::Buy => {
+                // ── Mínimo do mercado: pula ou acumula compras pequenas demais ──
+                let invest = match plan_min_notional_buy(strategy, market_min_cost_usd(exchange, &strategy.symbol).await) {
+                    MinNotionalBuy::Buy(invest) => invest,
+                    MinNotionalBuy::Skip { amount, min_cost } => {
+                        log::info!("🪙 [{}] Buy of ${:.2} below market minimum ${:.2}, skipped", strategy.strategy_id, amount, min_cost);
+                        mark_suppressed(signal, &SuppressedReason::BelowMinimum { amount, min_cost });
+                        continue;
+                    }
+                    MinNotionalBuy::Accumulate { pending, min_cost } => {
+                        signal.acted = false;
+                        if !strategy.pending_buy_window {
+                            log::info!("🪙 [{}] Buy below market minimum, accumulated ${:.2} of ${:.2}", strategy.strategy_id, pending, min_cost);
+                            if let Err(e) = set_pending_buy_usd(db, user_id, &strategy.strategy_id, pending, true).await {
+                                log::error!("❌ [{}] {}", strategy.strategy_id, e);
+                            }
+                        }
+                        guard_signals.push(StrategySignal {
+                            signal_type: SignalType::Info, price,
+                            message: format!("🪙 Compra abaixo do mínimo de ${:.2} da exchange: acumulado ${:.2} para a próxima.", min_cost, pending),
+                            acted: false, price_change_percent: signal.price_change_percent, created_at: now,
+                        });
+                        continue;
+                    }
+                };
+                if invest <= 0.0 { continue; }
+
+                // ── Invariante de lado: compra além do tamanho máximo da posição ──
+                if let Some(refused) = check_order_side(strategy, &signal.signal_type, invest, price, now) {
+                    signal.acted = false;
+                    record_refusal(strategy, &mut executions, refused);
+                    continue;
+                }
+
+                // ── Guard: user exposure cap ────────────────────────
+                if let Err(msg) = check_exposure_cap(db, user_id, strategy, invest).await {
+                    log::warn!("🚧 [{}] Buy blocked: {}", strategy.strategy_id, msg);
+                    guard_signals.push(StrategySignal {
+                        signal_type: SignalType::Info, price,
+                        message: format!("🚧 Compra bloqueada: {}", msg),
+                        acted: false, price_change_percent: signal.price_change_percent, created_at: now,
+                    });
+                    continue;
+                }
+
+                // ── Futures: alavancagem definida antes da entrada ──
+                if let Err(e) = apply_futures_leverage(&strategy.config, &strategy.symbol, |leverage, symbol| {
+                    set_exchange_leverage(exchange, leverage, symbol)
+                }).await {
+                    let friendly = report_order_rejection(strategy, "set_leverage", &e);
+                    executions.push(StrategyExecution {
+                        execution_id: uuid::Uuid::new_v4().to_string(),
+                        action: ExecutionAction::BuyFailed,
+                        reason: format!("buy_failed: {}", friendly),
+                        price, amount: invest / price, total: invest,
+                        fee: 0.0, pnl_usd: 0.0, exchange_order_id: None,
+                        executed_at: now, error_message: Some(friendly),
+                    });
+                    continue;
+                }
+
+                // ── Entrada limit abaixo do preço (fallback a mercado nos próximos ticks) ──
+                if let Some(limit) = &strategy.config.limit_entry {
+                    let target = limit_entry_price(price, limit.offset_percent);
+                    match create_limit_entry_order(exchange, strategy.config.mode, &strategy.symbol, invest / target, target, now).await {
+                        Ok(order) => {
+                            signal.acted = true;
+                            log::info!("🧾 [{}] Limit entry {:.6} @ {:.4} placed: {}", strategy.strategy_id, order.amount, target, order.order_id);
+                            if let Err(e) = push_open_order(db, user_id, &strategy.strategy_id, &order).await {
+                                log::error!("❌ [{}] {}", strategy.strategy_id, e);
+                            }
+                            guard_signals.push(StrategySignal {
+                                signal_type: SignalType::Info, price,
+                                message: format!("🧾 Compra limit de ${:.2} colocada a {:.4} ({:.2}% abaixo do preço).", invest, target, limit.offset_percent),
+                                acted: false, price_change_percent: signal.price_change_percent, created_at: now,
+                            });
+                        }
+                        Err(e) => {
+                            signal.acted = false;
+                            let friendly = report_order_rejection(strategy, "limit_entry", &e);
+                            executions.push(StrategyExecution {
+                                execution_id: uuid::Uuid::new_v4().to_string(),
+                                action: ExecutionAction::BuyFailed,
+                                reason: format!("buy_failed: {}", friendly),
+                                price: target, amount: invest / target, total: invest,
+                                fee: 0.0, pnl_usd: 0.0, exchange_order_id: None,
+                                executed_at: now, error_message: Some(friendly),
+                            });
+                        }
+                    }
+                    continue;
+                }
+
+                let amount = invest / price;
+                match execute_reported_market_buy(db, user_id, exchange, strategy.config.mode, &strategy.symbol, invest, price).await {
+                    Ok(order) => {
+                        signal.acted = true;
+                        let filled = order.filled.unwrap_or(amount);
+                        let buy_price = order.avg_price.unwrap_or(price);
+                        let fee = order.fee.unwrap_or(0.0);
+                        log::info!("✅ [{}] entry executed: {:.6} {} @ {:.4} (${:.2})",
+                            strategy.strategy_id, filled, strategy.symbol, buy_price, buy_price * filled);
+                        executions.push(StrategyExecution {
+                            execution_id: uuid::Uuid::new_v4().to_string(),
+                            action: ExecutionAction::Buy, reason: "entry".into(),
+                            price: buy_price, amount: filled,
+                            total: order.cost.unwrap_or(buy_price * filled),
+                            fee, pnl_usd: 0.0,
+                            exchange_order_id: Some(order.order_id),
+                            executed_at: now, error_message: None,
+                        });
+                        new_status = Some(StrategyStatus::InPosition);
+                        if let Some(message) = after_entry_fill(db, user_id, exchange, strategy, filled, now).await {
+                            guard_signals.push(StrategySignal {
+                                signal_type: SignalType::Info, price, message,
+                                acted: false, price_change_percent: signal.price_change_percent, created_at: now,
+                            });
+                        }
+                    }
+                    Err(e) => {
+                        signal.acted = false;
+                        let friendly = report_order_rejection(strategy, "buy", &e);
+                        executions.push(StrategyExecution {
+                            execution_id: uuid::Uuid::new_v4().to_string(),
+                            action: ExecutionAction::BuyFailed,
+                            reason: format!("buy_failed: {}", friendly),
+                            price, amount, total: invest,
+                            fee: 0.0, pnl_usd: 0.0, exchange_order_id: None,
+                            executed_at: now, error_message: Some(friendly),
+                        });
+                    }
+                }
+            }
             SignalType::TakeProfit | SignalType::GradualSell => {
                 let sell_amount = calc_sell_amount(strategy, &signal.signal_type);
                 if sell_amount <= 0.0 { continue; }
 
-                match execute_order(exchange, &strategy.symbol, "market", "sell", sell_amount, None).await {
+                // ── Grid: só vende acima do breakeven (com taxas) da compra casada ──
+                if let Some(min_price) = grid_state.as_ref().and_then(|s| s.pair_sell_floor()) {
+                    if price <= min_price {
+                        signal.acted = false;
+                        log::info!("⏸️ [{}] grid sell suppressed: {:.4} <= breakeven {:.4}",
+                            strategy.strategy_id, price, min_price);
+                        continue;
+                    }
+                }
+
+                if !trailing_released {
+                    executions.extend(release_exchange_exits(db, user_id, exchange, strategy, now).await);
+                    trailing_released = true;
+                }
+                match execute_reported_order(db, user_id, exchange, strategy.config.mode, &strategy.symbol, "sell", sell_amount).await {
                     Ok(order) => {
                         signal.acted = true;
                         let entry = strategy.position.as_ref().map(|p| p.entry_price).unwrap_or(0.0);
                         let filled = order.filled.unwrap_or(sell_amount);
                         let sell_price = order.avg_price.unwrap_or(price);
-                        let pnl = (sell_price - entry) * filled;
                         let fee = order.fee.unwrap_or(0.0);
+                        let pnl_usd = sell_pnl_usd(entry, sell_price, filled, fee);
                         let reason = match signal.signal_type {
                             SignalType::GradualSell => "gradual_sell".to_string(),
                             _ => "take_profit".to_string(),
                         };
                         log::info!("✅ [{}] {} executed: {:.6} {} @ {:.4} | PnL: ${:.2}",
-                            strategy.strategy_id, reason, filled, strategy.symbol, sell_price, pnl - fee);
+                            strategy.strategy_id, reason, filled, strategy.symbol, sell_price, pnl_usd);
                         executions.push(StrategyExecution {
                             execution_id: uuid::Uuid::new_v4().to_string(),
                             action: ExecutionAction::Sell, reason: reason.clone(),
                             price: sell_price, amount: filled,
                             total: order.cost.unwrap_or(sell_price * filled),
-                            fee, pnl_usd: pnl - fee,
+                            fee, pnl_usd,
                             exchange_order_id: Some(order.order_id),
                             executed_at: now, error_message: None,
                         });
@@ -257,8 +876,7 @@ pub async fn tick(db: &MongoDB, user_id: &str, strategy: &StrategyItem) -> TickR
                     }
                     Err(e) => {
                         signal.acted = false;
-                        let friendly = classify_order_error(&e, &strategy.symbol, &strategy.exchange_name);
-                        log::error!("❌ [{}] Sell failed: {} | raw: {}", strategy.strategy_id, friendly, e);
+                        let friendly = report_order_rejection(strategy, "sell", &e);
                         executions.push(StrategyExecution {
                             execution_id: uuid::Uuid::new_v4().to_string(),
                             action: ExecutionAction::SellFailed,
@@ -270,38 +888,48 @@ pub async fn tick(db: &MongoDB, user_id: &str, strategy: &StrategyItem) -> TickR
                     }
                 }
             }
-            SignalType::StopLoss => {
-                let qty = strategy.position.as_ref().map(|p| p.quantity).unwrap_or(0.0);
+            SignalType::StopLoss | SignalType::MaxDrawdown => {
+                let qty = calc_sell_amount(strategy, &signal.signal_type);
                 if qty <= 0.0 { continue; }
-                match execute_order(exchange, &strategy.symbol, "market", "sell", qty, None).await {
+                let reason = signal.signal_type.to_string();
+                if !trailing_released {
+                    executions.extend(release_exchange_exits(db, user_id, exchange, strategy, now).await);
+                    trailing_released = true;
+                }
+                match execute_reported_order(db, user_id, exchange, strategy.config.mode, &strategy.symbol, "sell", qty).await {
                     Ok(order) => {
                         signal.acted = true;
                         let entry = strategy.position.as_ref().map(|p| p.entry_price).unwrap_or(0.0);
                         let filled = order.filled.unwrap_or(qty);
                         let sell_price = order.avg_price.unwrap_or(price);
-                        let pnl = (sell_price - entry) * filled;
                         let fee = order.fee.unwrap_or(0.0);
-                        log::warn!("🛑 [{}] STOP LOSS executed: {:.6} {} @ {:.4} | Loss: ${:.2}",
-                            strategy.strategy_id, filled, strategy.symbol, sell_price, pnl - fee);
+                        let pnl_usd = sell_pnl_usd(entry, sell_price, filled, fee);
+                        log::warn!("🛑 [{}] {} executed: {:.6} {} @ {:.4} | Loss: ${:.2}",
+                            strategy.strategy_id, reason, filled, strategy.symbol, sell_price, pnl_usd);
                         executions.push(StrategyExecution {
                             execution_id: uuid::Uuid::new_v4().to_string(),
-                            action: ExecutionAction::Sell, reason: "stop_loss".into(),
+                            action: ExecutionAction::Sell, reason: reason.clone(),
                             price: sell_price, amount: filled,
                             total: order.cost.unwrap_or(sell_price * filled),
-                            fee, pnl_usd: pnl - fee,
+                            fee, pnl_usd,
                             exchange_order_id: Some(order.order_id),
                             executed_at: now, error_message: None,
                         });
-                        new_status = Some(StrategyStatus::StoppedOut);
+                        new_status = Some(protective_exit_status(&signal.signal_type));
+                        if signal.signal_type == SignalType::MaxDrawdown {
+                            tick_error = Some(format!(
+                                "Pausada automaticamente: drawdown máximo de {:.2}% atingido. Posição zerada @ {:.4}.",
+                                strategy.config.max_drawdown_percent.unwrap_or(0.0), sell_price
+                            ));
+                        }
                     }
                     Err(e) => {
                         signal.acted = false;
-                        let friendly = classify_order_error(&e, &strategy.symbol, &strategy.exchange_name);
-                        log::error!("❌ [{}] Stop loss SELL FAILED: {} | raw: {}", strategy.strategy_id, friendly, e);
+                        let friendly = report_order_rejection(strategy, &format!("{}_sell", reason), &e);
                         executions.push(StrategyExecution {
                             execution_id: uuid::Uuid::new_v4().to_string(),
                             action: ExecutionAction::SellFailed,
-                            reason: format!("stop_loss_failed: {}", friendly),
+                            reason: format!("{}_failed: {}", reason, friendly),
                             price, amount: qty, total: qty * price,
                             fee: 0.0, pnl_usd: 0.0, exchange_order_id: None,
                             executed_at: now, error_message: Some(friendly),
@@ -313,49 +941,405 @@ pub async fn tick(db: &MongoDB, user_id: &str, strategy: &StrategyItem) -> TickR
         }
     }
 
-    TickResult { strategy_id, symbol: strategy.symbol.clone(), price, signals, executions, new_status, error: None }
+    signals.extend(guard_signals);
+
+    // ── Grid com layout: níveis só operam se nenhuma guarda zerou a posição ──
+    if let Some(state) = grid_levels {
+        if !signals.iter().any(|s| matches!(s.signal_type, SignalType::StopLoss | SignalType::MaxDrawdown)) {
+            let (level_signals, level_executions) = execute_grid_levels(
+                db, user_id, exchange, strategy, state, price, now, suppressed.as_ref(),
+            ).await;
+            signals.extend(level_signals);
+            executions.extend(level_executions);
+        }
+    }
+
+    // ── Precisão do mercado para a cópia persistida (a posição usa os valores brutos) ──
+    let precision = if executions.is_empty() {
+        None
+    } else {
+        Some(market_precision(exchange, &strategy.symbol).await)
+    };
+
+    TickResult { strategy_id, symbol: strategy.symbol.clone(), price, signals, executions, new_status, error: tick_error, precision }
 }
 
-fn evaluate_trigger(strategy: &StrategyItem, price: f64, now: i64, signals: &mut Vec<StrategySignal>) {
-    let config = &strategy.config;
-    if config.base_price <= 0.0 { return; }
+/// Guardas comuns do grid com layout: drawdown máximo (sem trailing nativo) e stop
+/// loss abaixo da linha mais baixa (`GridState::stop_loss_price`). Sem posição, nada.
+fn evaluate_grid_guards(strategy: &StrategyItem, state: &GridState, price: f64, now: i64) -> Option<StrategySignal> {
+    let position = strategy.position.as_ref().filter(|p| p.quantity > 0.0)?;
+    if native_trailing_order(strategy).is_none() {
+        if let Some(signal) = evaluate_drawdown_guard(strategy, price, now) {
+            return Some(signal);
+        }
+    }
+    let stop = state.stop_loss_price(strategy.config.stop_loss_percent)?;
+    if price > stop {
+        return None;
+    }
+    let pct = if position.entry_price > 0.0 { ((price - position.entry_price) / position.entry_price) * 100.0 } else { 0.0 };
+    Some(StrategySignal {
+        signal_type: SignalType::StopLoss, price,
+        message: format!(
+            "🛑 STOP LOSS (grid)! Preço {:.4} <= stop {:.4} abaixo do último nível. Vendendo tudo para limitar perda.",
+            price, stop
+        ),
+        acted: false, price_change_percent: pct, created_at: now,
+    })
+}
 
-    let trigger = config.trigger_price();
-    let sl_price = config.stop_loss_price();
-    let pct = ((price - config.base_price) / config.base_price) * 100.0;
+/// Níveis do grid: dispara a compra de cada nível com preço <= alvo e a venda
+/// casada dos níveis comprados com preço >= alvo. O PnL de cada venda é contra a compra
+/// do próprio nível (não contra o preço médio da posição). O estado dos níveis é
+/// atualizado em `persist_tick_result` a partir das execuções (`apply_grid_state_executions`).
+#[allow(clippy::too_many_arguments)]
+async fn execute_grid_levels(
+    db: &MongoDB, user_id: &str, exchange: &DecryptedExchange, strategy: &StrategyItem,
+    state: &GridState, price: f64, now: i64, suppressed: Option<&SuppressedReason>,
+) -> (Vec<StrategySignal>, Vec<StrategyExecution>) {
+    let mut signals: Vec<StrategySignal> = Vec::new();
+    let mut executions: Vec<StrategyExecution> = Vec::new();
+    let pct = ((price - state.center_price) / state.center_price) * 100.0;
 
-    if strategy.position.is_some() {
-        if price >= trigger {
-            if config.gradual_sell && !config.gradual_lots.is_empty() {
-                let lot = config.gradual_lots.iter().find(|l| !l.executed);
-                if let Some(lot) = lot {
-                    signals.push(StrategySignal {
-                        signal_type: SignalType::TakeProfit, price,
-                        message: format!(
-                            "🎯 TRIGGER ATINGIDO! Preço {:.2} >= trigger {:.2} ({:+.2}%). Iniciando venda gradual — lote {} de {:.0}%.",
-                            price, trigger, pct, lot.lot_number, lot.sell_percent
-                        ),
-                        acted: false, price_change_percent: pct, created_at: now,
-                    });
+    for (level, side) in state.triggered(price, now) {
+        let Some(slot) = state.level(level) else { continue };
+        let reason = grid_level_reason(level);
+        match side {
+            GridSide::Buy => {
+                let invest = strategy.buy_amount_usd();
+                let mut signal = StrategySignal {
+                    signal_type: SignalType::Buy, price,
+                    message: format!("🟢 GRID nível {}: preço {:.4} <= {:.4}. Comprando ${:.2} a mercado.", level, price, slot.target_price, invest),
+                    acted: false, price_change_percent: pct, created_at: now,
+                };
+                if let Some(reason) = suppressed {
+                    mark_suppressed(&mut signal, reason);
                 }
-            } else {
-                signals.push(StrategySignal {
+                if suppressed.is_some() || invest <= 0.0 {
+                    signals.push(signal);
+                    continue;
+                }
+                if let Some(refused) = grid_side_refusal(strategy, level, &SignalType::Buy, invest, price, now) {
+                    record_refusal(strategy, &mut executions, refused);
+                    signals.push(signal);
+                    continue;
+                }
+                if let Err(msg) = check_exposure_cap(db, user_id, strategy, invest).await {
+                    log::warn!("🚧 [{}] Grid level {} buy blocked: {}", strategy.strategy_id, level, msg);
+                    signal.message = format!("🚧 Compra do nível {} bloqueada: {}", level, msg);
+                    signal.signal_type = SignalType::Info;
+                    signals.push(signal);
+                    continue;
+                }
+                match execute_reported_market_buy(db, user_id, exchange, strategy.config.mode, &strategy.symbol, invest, price).await {
+                    Ok(order) => {
+                        signal.acted = true;
+                        let filled = order.filled.unwrap_or(invest / price);
+                        let buy_price = order.avg_price.unwrap_or(price);
+                        log::info!("✅ [{}] grid level {} buy: {:.6} {} @ {:.4}", strategy.strategy_id, level, filled, strategy.symbol, buy_price);
+                        executions.push(StrategyExecution {
+                            execution_id: uuid::Uuid::new_v4().to_string(),
+                            action: ExecutionAction::Buy, reason,
+                            price: buy_price, amount: filled,
+                            total: order.cost.unwrap_or(buy_price * filled),
+                            fee: order.fee.unwrap_or(0.0), pnl_usd: 0.0,
+                            exchange_order_id: Some(order.order_id),
+                            executed_at: now, error_message: None,
+                        });
+                    }
+                    Err(e) => {
+                        let friendly = report_order_rejection(strategy, &format!("grid_level_{}_buy", level), &e);
+                        executions.push(StrategyExecution {
+                            execution_id: uuid::Uuid::new_v4().to_string(),
+                            action: ExecutionAction::BuyFailed,
+                            reason: format!("{}_buy_failed: {}", grid_level_reason(level), friendly),
+                            price, amount: invest / price, total: invest,
+                            fee: 0.0, pnl_usd: 0.0, exchange_order_id: None,
+                            executed_at: now, error_message: Some(friendly),
+                        });
+                    }
+                }
+                signals.push(signal);
+            }
+            GridSide::Sell => {
+                let Some(fill) = slot.fill.as_ref() else { continue };
+                let mut signal = StrategySignal {
                     signal_type: SignalType::TakeProfit, price,
-                    message: format!(
-                        "🎯 TRIGGER ATINGIDO! Preço {:.2} >= trigger {:.2} ({:+.2}%). Executando venda total.",
-                        price, trigger, pct
-                    ),
+                    message: format!("🎯 GRID nível {}: preço {:.4} >= {:.4}. Vendendo compra de {:.4}.", level, price, slot.target_price, fill.price),
                     acted: false, price_change_percent: pct, created_at: now,
-                });
-            }
-        } else if price <= sl_price {
-            signals.push(StrategySignal {
-                signal_type: SignalType::StopLoss, price,
-                message: format!(
-                    "🛑 STOP LOSS ATINGIDO! Preço {:.2} <= stop {:.2} ({:+.2}%). Vendendo tudo para limitar perda.",
-                    price, sl_price, pct
-                ),
-                acted: false, price_change_percent: pct, created_at: now,
+                };
+                if let Some(reason) = suppressed {
+                    mark_suppressed(&mut signal, reason);
+                    signals.push(signal);
+                    continue;
+                }
+                if let Some(refused) = grid_side_refusal(strategy, level, &SignalType::TakeProfit, 0.0, price, now) {
+                    record_refusal(strategy, &mut executions, refused);
+                    signals.push(signal);
+                    continue;
+                }
+                match execute_reported_order(db, user_id, exchange, strategy.config.mode, &strategy.symbol, "sell", fill.amount).await {
+                    Ok(order) => {
+                        signal.acted = true;
+                        let filled = order.filled.unwrap_or(fill.amount);
+                        let sell_price = order.avg_price.unwrap_or(price);
+                        let fee = order.fee.unwrap_or(0.0);
+                        let pnl = (sell_price - fill.price) * filled;
+                        log::info!("✅ [{}] grid level {} sell: {:.6} {} @ {:.4} | PnL: ${:.2}",
+                            strategy.strategy_id, level, filled, strategy.symbol, sell_price, pnl - fee);
+                        executions.push(StrategyExecution {
+                            execution_id: uuid::Uuid::new_v4().to_string(),
+                            action: ExecutionAction::Sell, reason,
+                            price: sell_price, amount: filled,
+                            total: order.cost.unwrap_or(sell_price * filled),
+                            fee, pnl_usd: pnl - fee,
+                            exchange_order_id: Some(order.order_id),
+                            executed_at: now, error_message: None,
+                        });
+                    }
+                    Err(e) => {
+                        let friendly = report_order_rejection(strategy, &format!("grid_level_{}_sell", level), &e);
+                        executions.push(StrategyExecution {
+                            execution_id: uuid::Uuid::new_v4().to_string(),
+                            action: ExecutionAction::SellFailed,
+                            reason: format!("{}_sell_failed: {}", grid_level_reason(level), friendly),
+                            price, amount: fill.amount, total: fill.amount * price,
+                            fee: 0.0, pnl_usd: 0.0, exchange_order_id: None,
+                            executed_at: now, error_message: Some(friendly),
+                        });
+                    }
+                }
+                signals.push(signal);
+            }
+        }
+    }
+
+    (signals, executions)
+}
+
+/// Entrada automática (opt-in `auto_entry`): gera sinal de compra de `entry_amount_usd` quando
+/// a regra de entrada é satisfeita — `entry_condition` se definida (avaliada com `vars`),
+/// senão preço no (ou abaixo do) base_price. Retorna true se gerou sinal.
+fn evaluate_entry(
+    strategy: &StrategyItem, price: f64, now: i64,
+    vars: Option<&HashMap<&str, f64>>, signals: &mut Vec<StrategySignal>,
+) -> bool {
+    let config = &strategy.config;
+    let invest = strategy.buy_amount_usd();
+    if !config.auto_entry || invest <= 0.0 {
+        return false;
+    }
+    if strategy.position.is_some() {
+        return false;
+    }
+
+    let pct = if config.base_price > 0.0 {
+        ((price - config.base_price) / config.base_price) * 100.0
+    } else { 0.0 };
+
+    let reason = match config.entry_condition.as_deref() {
+        Some(src) => {
+            if entry_condition_met(strategy, vars) != Some(true) {
+                return false;
+            }
+            format!("condição '{}' satisfeita (preço {:.2})", src, price)
+        }
+        None => {
+            if config.base_price <= 0.0 || price > config.base_price {
+                return false;
+            }
+            format!("Preço {:.2} <= base {:.2} ({:+.2}%)", price, config.base_price, pct)
+        }
+    };
+
+    signals.push(StrategySignal {
+        signal_type: SignalType::Buy, price,
+        message: format!("🟢 ENTRADA! {}. Comprando ${:.2} a mercado.", reason, invest),
+        acted: false, price_change_percent: pct, created_at: now,
+    });
+    true
+}
+
+/// Avalia o `entry_condition` da estratégia; None quando não há condição. Sem as
+/// variáveis carregadas, ou com erro na avaliação, a condição não está satisfeita.
+fn entry_condition_met(strategy: &StrategyItem, vars: Option<&HashMap<&str, f64>>) -> Option<bool> {
+    let src = strategy.config.entry_condition.as_deref()?;
+    let Some(vars) = vars else { return Some(false) };
+    match expression::parse(src).and_then(|expr| expr.is_satisfied(vars)) {
+        Ok(satisfied) => Some(satisfied),
+        Err(e) => {
+            log::warn!("⚠️ [{}] entry_condition evaluation failed: {}", strategy.strategy_id, e);
+            Some(false)
+        }
+    }
+}
+
+/// Desequilíbrio (compra/venda) dos `levels` melhores níveis do book.
+/// Falha ao buscar ou book raso → None (nenhum sinal de book no tick).
+async fn fetch_order_book_imbalance(exchange: &DecryptedExchange, strategy: &StrategyItem, levels: usize) -> Option<f64> {
+    let ex = exchange.clone();
+    let symbol = strategy.symbol.clone();
+    let book = spawn_ccxt_paced(&exchange.ccxt_id, move || {
+        CCXTClient::for_exchange(&ex)?.fetch_order_book_sync(&symbol, levels)
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))
+    .and_then(|r| r);
+
+    match book {
+        Ok(book) => indicators::order_book_imbalance(&book, levels),
+        Err(e) => {
+            log::warn!("⚠️ [{}] Order book fetch failed, skipping imbalance signal: {}", strategy.strategy_id, e);
+            None
+        }
+    }
+}
+
+/// Entrada por desequilíbrio do book: compra quando compra/venda >= `entry_ratio`
+/// e o `entry_condition` (se houver) está satisfeito. Retorna true se gerou sinal.
+fn evaluate_order_book_entry(
+    strategy: &StrategyItem, imbalance: Option<f64>, vars: Option<&HashMap<&str, f64>>,
+    price: f64, now: i64, signals: &mut Vec<StrategySignal>,
+) -> bool {
+    let (Some(book), Some(imbalance)) = (strategy.config.order_book_imbalance.as_ref(), imbalance) else {
+        return false;
+    };
+    if entry_condition_met(strategy, vars) == Some(false) {
+        return false;
+    }
+    let invest = strategy.buy_amount_usd();
+    if !strategy.config.auto_entry || invest <= 0.0 || strategy.position.is_some() || imbalance < book.entry_ratio {
+        return false;
+    }
+    signals.push(StrategySignal {
+        signal_type: SignalType::Buy, price,
+        message: format!(
+            "🟢 ENTRADA! Book comprador: compra/venda {:.2}x >= {:.2}x (top {} níveis). Comprando ${:.2} a mercado.",
+            imbalance, book.entry_ratio, book.levels, invest
+        ),
+        acted: false, price_change_percent: 0.0, created_at: now,
+    });
+    true
+}
+
+/// Saída por desequilíbrio do book: com a posição no lucro (ou no zero a zero), vende tudo
+/// quando venda/compra >= `exit_ratio`. Retorna true se gerou sinal.
+fn evaluate_order_book_exit(
+    strategy: &StrategyItem, imbalance: Option<f64>, price: f64, now: i64, signals: &mut Vec<StrategySignal>,
+) -> bool {
+    let Some(exit_ratio) = strategy.config.order_book_imbalance.as_ref().and_then(|b| b.exit_ratio) else {
+        return false;
+    };
+    let Some(position) = strategy.position.as_ref().filter(|p| p.quantity > 0.0 && p.entry_price > 0.0) else {
+        return false;
+    };
+    let Some(ask_pressure) = imbalance.filter(|v| *v > 0.0).map(|v| 1.0 / v) else {
+        return false;
+    };
+    if price < position.entry_price || ask_pressure < exit_ratio {
+        return false;
+    }
+    let pct = ((price - position.entry_price) / position.entry_price) * 100.0;
+    signals.push(StrategySignal {
+        signal_type: SignalType::TakeProfit, price,
+        message: format!(
+            "🎯 SAÍDA! Book vendedor: venda/compra {:.2}x >= {:.2}x com a posição em {:+.2}%. Vendendo tudo.",
+            ask_pressure, exit_ratio, pct
+        ),
+        acted: false, price_change_percent: pct, created_at: now,
+    });
+    true
+}
+
+/// Soma o valor (USD) das posições abertas de todas as estratégias, exceto `exclude_id`
+pub fn open_exposure_usd(strategies: &[StrategyItem], exclude_id: &str) -> f64 {
+    strategies.iter()
+        .filter(|s| s.strategy_id != exclude_id)
+        .filter_map(|s| s.position.as_ref())
+        .filter(|p| p.quantity > 0.0)
+        .map(|p| p.notional_usd())
+        .sum()
+}
+
+/// Valida se uma compra de `pending_usd` mantém a exposição total dentro de `cap`
+pub fn validate_exposure(open_usd: f64, pending_usd: f64, cap: f64) -> Result<(), String> {
+    if open_usd + pending_usd > cap {
+        return Err(format!(
+            "exposição total ${:.2} (aberta ${:.2} + compra ${:.2}) excederia o limite de ${:.2}.",
+            open_usd + pending_usd, open_usd, pending_usd, cap
+        ));
+    }
+    Ok(())
+}
+
+/// Lê o limite de exposição do usuário (`user_settings`, com fallback para o
+/// campo legado `users.max_total_exposure_usd`)
+async fn get_max_total_exposure(db: &MongoDB, user_id: &str) -> Result<Option<f64>, String> {
+    let settings = crate::services::user_settings_service::get_user_settings(db, user_id).await?;
+    if let Some(cap) = settings.max_total_exposure_usd {
+        return Ok(Some(cap));
+    }
+
+    let users = db.collection::<crate::services::auth_service::User>("users");
+    let user = users.find_one(doc! { "user_id": user_id }).await
+        .map_err(|e| format!("Failed to load user settings: {}", e))?;
+    Ok(user.and_then(|u| u.max_total_exposure_usd).filter(|v| *v > 0.0))
+}
+
+/// Garante que a compra pendente não ultrapassa o limite de exposição do usuário
+async fn check_exposure_cap(db: &MongoDB, user_id: &str, strategy: &StrategyItem, pending_usd: f64) -> Result<(), String> {
+    let Some(cap) = get_max_total_exposure(db, user_id).await? else {
+        return Ok(());
+    };
+
+    let user_doc = db.collection::<UserStrategies>(COLLECTION)
+        .find_one(doc! { "user_id": user_id }).await
+        .map_err(|e| format!("Failed to load strategies: {}", e))?;
+    let strategies = user_doc.map(|d| d.strategies).unwrap_or_default();
+
+    validate_exposure(open_exposure_usd(&strategies, &strategy.strategy_id), pending_usd, cap)
+}
+
+fn evaluate_trigger(strategy: &StrategyItem, price: f64, now: i64, signals: &mut Vec<StrategySignal>) {
+    let config = &strategy.config;
+    if config.base_price <= 0.0 { return; }
+
+    let trigger = config.trigger_price();
+    let sl_price = config.stop_loss_price();
+    let pct = ((price - config.base_price) / config.base_price) * 100.0;
+
+    if strategy.position.is_some() {
+        if price >= trigger {
+            if config.gradual_sell && !config.gradual_lots.is_empty() {
+                let lot = config.gradual_lots.iter().find(|l| !l.executed);
+                if let Some(lot) = lot {
+                    signals.push(StrategySignal {
+                        signal_type: SignalType::TakeProfit, price,
+                        message: format!(
+                            "🎯 TRIGGER ATINGIDO! Preço {:.2} >= trigger {:.2} ({:+.2}%). Iniciando venda gradual — lote {} de {:.0}%.",
+                            price, trigger, pct, lot.lot_number, lot.sell_percent
+                        ),
+                        acted: false, price_change_percent: pct, created_at: now,
+                    });
+                }
+            } else {
+                signals.push(StrategySignal {
+                    signal_type: SignalType::TakeProfit, price,
+                    message: format!(
+                        "🎯 TRIGGER ATINGIDO! Preço {:.2} >= trigger {:.2} ({:+.2}%). Executando venda total.",
+                        price, trigger, pct
+                    ),
+                    acted: false, price_change_percent: pct, created_at: now,
+                });
+            }
+        } else if price <= sl_price {
+            signals.push(StrategySignal {
+                signal_type: SignalType::StopLoss, price,
+                message: format!(
+                    "🛑 STOP LOSS ATINGIDO! Preço {:.2} <= stop {:.2} ({:+.2}%). Vendendo tudo para limitar perda.",
+                    price, sl_price, pct
+                ),
+                acted: false, price_change_percent: pct, created_at: now,
             });
         } else {
             let diff_trigger = trigger - price;
@@ -475,577 +1459,5820 @@ fn evaluate_exit(strategy: &StrategyItem, price: f64, now: i64, signals: &mut Ve
     }
 }
 
-fn evaluate_gradual(strategy: &StrategyItem, price: f64, now: i64, signals: &mut Vec<StrategySignal>) {
-    let config = &strategy.config;
-    let position = match &strategy.position {
-        Some(pos) if pos.quantity > 0.0 => pos,
-        _ => {
-            signals.push(StrategySignal {
-                signal_type: SignalType::Info, price,
-                message: "⚠️ Status 'gradual_selling' mas sem posição aberta. Todos os lotes podem já ter sido vendidos.".into(),
-                acted: false, price_change_percent: 0.0, created_at: now,
-            });
-            return;
-        }
-    };
-
-    let entry = position.entry_price;
-    if entry <= 0.0 {
-        signals.push(StrategySignal {
-            signal_type: SignalType::Info, price,
-            message: "⚠️ Preço de entrada é 0 durante venda gradual. Verifique a posição.".into(),
-            acted: false, price_change_percent: 0.0, created_at: now,
-        });
-        return;
+/// Status após zerar a posição por stop loss (encerra) ou drawdown máximo (pausa)
+fn protective_exit_status(signal_type: &SignalType) -> StrategyStatus {
+    match signal_type {
+        SignalType::MaxDrawdown => StrategyStatus::Paused,
+        _ => StrategyStatus::StoppedOut,
     }
-    let pct = ((price - entry) / entry) * 100.0;
-    let sl_price = config.stop_loss_price();
-    let unrealized_pnl = (price - entry) * position.quantity;
-    let executed_lots = config.gradual_lots.iter().filter(|l| l.executed).count();
-    let total_lots = config.gradual_lots.len();
+}
 
-    if price <= sl_price {
-        signals.push(StrategySignal {
-            signal_type: SignalType::StopLoss, price,
-            message: format!(
-                "🛑 STOP LOSS durante venda gradual! Preço {:.2} <= stop {:.2} ({:+.2}%). {}/{} lotes vendidos. Vendendo posição restante ({:.6}) para limitar perda.",
-                price, sl_price, pct, executed_lots, total_lots, position.quantity
-            ),
-            acted: false, price_change_percent: pct, created_at: now,
-        });
-        return;
+/// Drawdown (%) do preço atual em relação à máxima da posição
+pub fn position_drawdown_percent(position: &PositionInfo, price: f64) -> f64 {
+    let peak = position.highest_price.max(position.entry_price).max(price);
+    if peak <= 0.0 { return 0.0; }
+    ((peak - price) / peak) * 100.0
+}
+
+/// Guard de drawdown máximo: gera sinal MaxDrawdown quando a queda desde a máxima
+/// da posição excede `max_drawdown_percent`
+fn evaluate_drawdown_guard(strategy: &StrategyItem, price: f64, now: i64) -> Option<StrategySignal> {
+    let max_dd = strategy.config.max_drawdown_percent.filter(|v| *v > 0.0)?;
+    let position = strategy.position.as_ref().filter(|p| p.quantity > 0.0)?;
+
+    let drawdown = position_drawdown_percent(position, price);
+    if drawdown < max_dd {
+        return None;
     }
 
-    let timer_secs = config.timer_gradual_min * 60;
-    let last_sell = strategy.last_gradual_sell_at.unwrap_or(0);
-    if now - last_sell < timer_secs {
-        let remaining_secs = timer_secs - (now - last_sell);
-        let remaining_min = remaining_secs / 60;
-        let remaining_sec = remaining_secs % 60;
-        signals.push(StrategySignal {
-            signal_type: SignalType::Info, price,
-            message: format!(
-                "⏱️ Timer gradual ativo: próximo lote em {}min {}s. Preço {:.2} ({:+.2}%). PnL: ${:.2}. Progresso: {}/{} lotes vendidos.",
-                remaining_min, remaining_sec, price, pct, unrealized_pnl, executed_lots, total_lots
-            ),
-            acted: false, price_change_percent: pct, created_at: now,
-        });
-        return;
+    let pct = if position.entry_price > 0.0 {
+        ((price - position.entry_price) / position.entry_price) * 100.0
+    } else { 0.0 };
+    Some(StrategySignal {
+        signal_type: SignalType::MaxDrawdown, price,
+        message: format!(
+            "📉 DRAWDOWN MÁXIMO! Preço {:.2} está {:.2}% abaixo da máxima {:.2} (limite {:.2}%). Zerando posição e pausando estratégia.",
+            price, drawdown, position.highest_price.max(position.entry_price), max_dd
+        ),
+        acted: false, price_change_percent: pct, created_at: now,
+    })
+}
+
+// ==================== WHAT-IF (RE-PRICE) ====================
+// Reavalia a posição aberta num preço hipotético com a mesma matemática do
+// tick (trigger_price, stop_loss_price, gradual_trigger_price, drawdown guard).
+// Puro: não busca preço, não grava nada e não gera sinais.
+
+/// Nível de saída (TP, lote gradual ou SL) relativo ao preço hipotético
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct WhatIfLevel {
+    pub kind: String,
+    pub price: f64,
+    /// Distância do preço hipotético até o nível (positiva = nível acima)
+    pub distance: f64,
+    pub distance_percent: f64,
+    pub triggered: bool,
+}
+
+/// Trailing stop (drawdown máximo a partir da máxima da posição)
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct WhatIfTrailingStop {
+    pub max_drawdown_percent: f64,
+    pub peak_price: f64,
+    pub drawdown_percent: f64,
+    pub triggered: bool,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct WhatIfResult {
+    pub strategy_id: String,
+    pub symbol: String,
+    pub price: f64,
+    pub entry_price: f64,
+    pub quantity: f64,
+    pub unrealized_pnl: f64,
+    pub unrealized_pnl_percent: f64,
+    pub levels: Vec<WhatIfLevel>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trailing_stop: Option<WhatIfTrailingStop>,
+}
+
+/// Simula a posição aberta da estratégia em `price`
+pub fn what_if(strategy: &StrategyItem, price: f64) -> Result<WhatIfResult, String> {
+    if !price.is_finite() || price <= 0.0 {
+        return Err("price must be greater than 0".to_string());
     }
+    let position = strategy.position.as_ref()
+        .filter(|p| p.quantity > 0.0 && p.entry_price > 0.0)
+        .ok_or_else(|| "Strategy has no open position".to_string())?;
+    let config = &strategy.config;
 
-    let next_lot_idx = config.gradual_lots.iter().position(|l| !l.executed);
-    match next_lot_idx {
-        Some(idx) => {
-            let lot = &config.gradual_lots[idx];
+    let level = |kind: String, level_price: f64, triggered: bool| WhatIfLevel {
+        kind,
+        price: level_price,
+        distance: level_price - price,
+        distance_percent: ((level_price - price) / price) * 100.0,
+        triggered,
+    };
+
+    let mut levels = Vec::new();
+    let trigger = config.trigger_price();
+    levels.push(level("take_profit".into(), trigger, price >= trigger));
+    if config.gradual_sell {
+        for (idx, lot) in config.gradual_lots.iter().enumerate().filter(|(_, l)| !l.executed) {
             let gradual_trigger = config.gradual_trigger_price(idx);
-            if price >= gradual_trigger {
-                let sell_qty = (position.total_cost / position.entry_price * lot.sell_percent / 100.0).min(position.quantity);
-                signals.push(StrategySignal {
-                    signal_type: SignalType::GradualSell, price,
-                    message: format!(
-                        "📈 VENDA GRADUAL! Lote {} de {}: preço {:.2} >= trigger gradual {:.2}. Vendendo {:.0}% ({:.6} unidades). Progresso: {}/{} lotes.",
-                        lot.lot_number, total_lots, price, gradual_trigger, lot.sell_percent, sell_qty, executed_lots, total_lots
-                    ),
-                    acted: false, price_change_percent: pct, created_at: now,
-                });
-            } else {
-                let diff = gradual_trigger - price;
-                let diff_pct = (diff / price) * 100.0;
-                signals.push(StrategySignal {
-                    signal_type: SignalType::Info, price,
-                    message: format!(
-                        "⏳ Aguardando lote {} de {}: preço {:.2} < trigger gradual {:.2}. Faltam {:.2} ({:.2}%) para acionar. PnL: ${:.2}. Timer: pronto. Progresso: {}/{} lotes.",
-                        lot.lot_number, total_lots, price, gradual_trigger, diff, diff_pct, unrealized_pnl, executed_lots, total_lots
-                    ),
-                    acted: false, price_change_percent: pct, created_at: now,
-                });
-            }
-        }
-        None => {
-            signals.push(StrategySignal {
-                signal_type: SignalType::TakeProfit, price,
-                message: format!(
-                    "✅ Todos os {} lotes graduais executados! Vendendo posição restante ({:.6} unidades) a {:.2}.",
-                    total_lots, position.quantity, price
-                ),
-                acted: false, price_change_percent: pct, created_at: now,
-            });
+            levels.push(level(format!("gradual_lot_{}", lot.lot_number), gradual_trigger, price >= gradual_trigger));
         }
     }
+    let sl_price = config.stop_loss_price();
+    levels.push(level("stop_loss".into(), sl_price, price <= sl_price));
+
+    let trailing_stop = config.max_drawdown_percent.filter(|v| *v > 0.0).map(|max_dd| {
+        let drawdown = position_drawdown_percent(position, price);
+        WhatIfTrailingStop {
+            max_drawdown_percent: max_dd,
+            peak_price: position.highest_price.max(position.entry_price).max(price),
+            drawdown_percent: drawdown,
+            triggered: drawdown >= max_dd,
+        }
+    });
+
+    Ok(WhatIfResult {
+        strategy_id: strategy.strategy_id.clone(),
+        symbol: strategy.symbol.clone(),
+        price,
+        entry_price: position.entry_price,
+        quantity: position.quantity,
+        unrealized_pnl: (price - position.entry_price) * position.quantity,
+        unrealized_pnl_percent: ((price - position.entry_price) / position.entry_price) * 100.0,
+        levels,
+        trailing_stop,
+    })
 }
 
-fn calc_sell_amount(strategy: &StrategyItem, signal_type: &SignalType) -> f64 {
-    let position = match &strategy.position {
-        Some(pos) if pos.quantity > 0.0 => pos,
-        _ => return 0.0,
-    };
+// ==================== REPLAY DE SINAIS ====================
+// Reexecuta as regras com a configuração ATUAL em cada preço registrado nos
+// sinais e compara com o que o motor decidiu na época. Divergências em geral
+// vêm de mudanças de configuração (TP/SL/base/entrada) depois do sinal.
+// Puro: não busca preço, não grava nada. O que depende de dados que não ficam nos
+// sinais não conta como divergência e sai como `replayable: false`: entradas por
+// condição com indicadores ou pelo book, e saídas do drawdown guard que a máxima
+// reconstruída (maior preço entre os sinais desde a entrada) não alcança.
+
+/// Decisão comparável entre o sinal original e o replay
+fn replay_decision(signal_type: &SignalType) -> &'static str {
     match signal_type {
-        SignalType::TakeProfit => {
-            if strategy.config.gradual_sell && !strategy.config.gradual_lots.is_empty() {
-                let lot = strategy.config.gradual_lots.iter().find(|l| !l.executed);
-                match lot {
-                    Some(lot) => {
-                        let original_qty = if position.entry_price > 0.0 {
-                            position.total_cost / position.entry_price
-                        } else {
-                            position.quantity
-                        };
-                        (original_qty * lot.sell_percent / 100.0).min(position.quantity)
-                    }
-                    None => position.quantity,
+        SignalType::Buy => "buy",
+        SignalType::TakeProfit | SignalType::GradualSell => "take_profit",
+        SignalType::StopLoss => "stop_loss",
+        SignalType::MaxDrawdown => "max_drawdown",
+        _ => "hold",
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ReplayStep {
+    pub created_at: i64,
+    pub price: f64,
+    /// Havia posição aberta no momento do sinal (inferido do sinal/execuções)
+    pub in_position: bool,
+    pub original: String,
+    pub replayed: String,
+    /// false quando a decisão depende de dados da época que não foram gravados
+    pub replayable: bool,
+    /// Motivo quando `replayable` é false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub not_replayable_reason: Option<String>,
+    pub diverged: bool,
+    /// A decisão original virou ordem
+    pub acted: bool,
+    pub original_message: String,
+    pub replayed_message: String,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct StrategyReplay {
+    pub strategy_id: String,
+    pub symbol: String,
+    pub steps: Vec<ReplayStep>,
+    pub divergences: usize,
+    pub not_replayable: usize,
+}
+
+/// Posição aberta em `at`: sinais de saída implicam posição, compra implica que
+/// não havia; nos demais vale a última execução anterior (compra = aberta).
+/// A máxima é o maior preço entre os sinais gravados desde a entrada (limite inferior
+/// da máxima real, já que só há preço nos ticks que geraram sinal)
+fn position_at_signal(strategy: &StrategyItem, signal: &StrategySignal) -> Option<PositionInfo> {
+    let last_execution = strategy.executions.iter()
+        .filter(|e| e.executed_at <= signal.created_at && e.error_message.is_none())
+        .max_by_key(|e| e.executed_at);
+    let in_position = match replay_decision(&signal.signal_type) {
+        "buy" => false,
+        "hold" => last_execution.is_some_and(|e| e.action == ExecutionAction::Buy),
+        _ => true,
+    };
+    if !in_position {
+        return None;
+    }
+    let entry_price = last_execution.filter(|e| e.action == ExecutionAction::Buy).map(|e| e.price)
+        .or_else(|| strategy.position.as_ref().map(|p| p.entry_price))
+        .unwrap_or(strategy.config.base_price);
+    let quantity = last_execution.map(|e| e.amount)
+        .or_else(|| strategy.position.as_ref().map(|p| p.quantity))
+        .filter(|q| *q > 0.0)
+        .unwrap_or(1.0);
+    let entered_at = last_execution.filter(|e| e.action == ExecutionAction::Buy).map(|e| e.executed_at);
+    let highest_price = strategy.signals.iter()
+        .filter(|s| s.price > 0.0 && s.created_at <= signal.created_at)
+        .filter(|s| entered_at.is_some_and(|at| s.created_at >= at))
+        .map(|s| s.price)
+        .fold(entry_price.max(signal.price), f64::max);
+    Some(PositionInfo {
+        entry_price, quantity, total_cost: entry_price * quantity,
+        current_price: signal.price, unrealized_pnl: 0.0, unrealized_pnl_percent: 0.0,
+        highest_price, opened_at: 0,
+    })
+}
+
+/// Por que o passo não pode ser comparado só com os sinais gravados (None = comparável)
+fn replay_blocker(strategy: &StrategyItem, in_position: bool, original: &str, replayed: &str) -> Option<&'static str> {
+    let config = &strategy.config;
+    if !in_position {
+        let uses_indicators = config.entry_condition.as_deref()
+            .and_then(|src| expression::parse(src).ok())
+            .is_some_and(|expr| expr.variables().iter().any(|v| *v != "price"));
+        if uses_indicators {
+            return Some("entry condition uses indicators that are not stored with the signals");
+        }
+        if original == "buy" && replayed != "buy" && config.order_book_imbalance.is_some() {
+            return Some("order book entries depend on the book at the time, which is not stored");
+        }
+        return None;
+    }
+    // A máxima reconstruída não passa da real: drawdown menor só pode deixar de disparar
+    if original == "max_drawdown" && replayed != "max_drawdown" {
+        return Some("the trailing peak since entry is not stored; the drawdown guard cannot be reproduced");
+    }
+    None
+}
+
+/// Reavalia cada sinal registrado com a configuração atual da estratégia
+pub fn replay_signals(strategy: &StrategyItem) -> StrategyReplay {
+    let replayable = |s: &&StrategySignal| s.price > 0.0
+        && !matches!(s.signal_type, SignalType::Expired | SignalType::LossStreak);
+    let mut signals: Vec<&StrategySignal> = strategy.signals.iter().filter(replayable).collect();
+    signals.sort_by_key(|s| s.created_at);
+
+    let steps: Vec<ReplayStep> = signals.into_iter()
+        .map(|signal| {
+            let mut state = strategy.clone();
+            state.position = position_at_signal(strategy, signal);
+            let in_position = state.position.is_some();
+            let price = signal.price;
+            let now = signal.created_at;
+
+            let mut replayed = Vec::new();
+            if in_position {
+                replayed.extend(evaluate_drawdown_guard(&state, price, now));
+                if replayed.is_empty() {
+                    evaluate_trigger(&state, price, now, &mut replayed);
                 }
             } else {
-                position.quantity
+                let vars = HashMap::from([("price", price)]);
+                evaluate_entry(&state, price, now, Some(&vars), &mut replayed);
             }
-        }
-        SignalType::GradualSell => {
-            let lot = strategy.config.gradual_lots.iter().find(|l| !l.executed);
-            match lot {
-                Some(lot) => {
-                    let original_qty = if position.entry_price > 0.0 {
-                        position.total_cost / position.entry_price
-                    } else {
-                        position.quantity
-                    };
-                    (original_qty * lot.sell_percent / 100.0).min(position.quantity)
-                }
-                None => position.quantity,
+            let decided = replayed.iter().find(|s| s.signal_type != SignalType::Info).or(replayed.first());
+            let replayed_decision = decided.map(|s| replay_decision(&s.signal_type)).unwrap_or("hold");
+            let original = replay_decision(&signal.signal_type);
+            let blocker = replay_blocker(strategy, in_position, original, replayed_decision);
+
+            ReplayStep {
+                created_at: signal.created_at,
+                price,
+                in_position,
+                original: original.to_string(),
+                replayed: replayed_decision.to_string(),
+                replayable: blocker.is_none(),
+                not_replayable_reason: blocker.map(str::to_string),
+                diverged: blocker.is_none() && original != replayed_decision,
+                acted: signal.acted,
+                original_message: signal.message.clone(),
+                replayed_message: decided.map(|s| s.message.clone()).unwrap_or_default(),
             }
-        }
-        SignalType::StopLoss => position.quantity,
-        _ => 0.0,
+        })
+        .collect();
+
+    StrategyReplay {
+        strategy_id: strategy.strategy_id.clone(),
+        symbol: strategy.symbol.clone(),
+        divergences: steps.iter().filter(|s| s.diverged).count(),
+        not_replayable: steps.iter().filter(|s| !s.replayable).count(),
+        steps,
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct OrderResult {
-    pub order_id: String,
-    pub status: String,
-    pub filled: Option<f64>,
-    pub avg_price: Option<f64>,
-    pub cost: Option<f64>,
-    pub fee: Option<f64>,
+// ==================== PNL CONSOLIDADO ====================
+// Realizado (`total_pnl_usd` de todas as estratégias) + não realizado das posições
+// abertas a preço de mercado. Um preço por (exchange, símbolo), buscado com
+// concorrência limitada e via cache de ticker; falhas só marcam o resumo como parcial.
+// Os valores por símbolo ficam na moeda de cotação do par; os totais são convertidos
+// para USD (uma taxa por quote) antes de somar.
+
+const PNL_PRICE_CONCURRENCY: usize = 4;
+
+/// Totais de um símbolo (somando todas as exchanges)
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize)]
+pub struct SymbolPnl {
+    pub symbol: String,
+    /// Moeda dos valores abaixo
+    pub quote: String,
+    pub strategies: usize,
+    pub realized_pnl: f64,
+    pub unrealized_pnl: f64,
+    pub total_pnl: f64,
+    /// Alguma posição aberta do símbolo ficou sem preço (não entra no não realizado)
+    pub unpriced: bool,
 }
 
-/// Classify raw CCXT/exchange errors into user-friendly messages
-fn classify_order_error(raw: &str, symbol: &str, exchange_name: &str) -> String {
-    let lower = raw.to_lowercase();
-    if lower.contains("insufficient") || lower.contains("balance") || lower.contains("not enough") {
-        format!("Insufficient balance on {} to sell {}. Check your exchange balance.", exchange_name, symbol)
-    } else if lower.contains("minimum") || lower.contains("min order") || lower.contains("too small") {
-        format!("Order amount too small for {} on {}. Minimum order size not met.", symbol, exchange_name)
-    } else if lower.contains("authentication") || lower.contains("invalid api") || lower.contains("apikey") {
-        format!("API authentication failed on {}. Your API keys may be expired or invalid.", exchange_name)
-    } else if lower.contains("permission") || lower.contains("not allowed") || lower.contains("restricted") {
-        format!("API key lacks trade permission on {}. Enable spot trading in your API settings.", exchange_name)
-    } else if lower.contains("rate limit") || lower.contains("too many") {
-        format!("Rate limited by {}. Will retry on next tick.", exchange_name)
-    } else if lower.contains("network") || lower.contains("timeout") || lower.contains("connection") {
-        format!("Network error connecting to {}. Will retry on next tick.", exchange_name)
-    } else if lower.contains("not found") || lower.contains("bad symbol") || lower.contains("invalid symbol") {
-        format!("Trading pair '{}' not found on {}. It may have been delisted.", symbol, exchange_name)
-    } else if lower.contains("market closed") || lower.contains("maintenance") {
-        format!("{} market is closed or under maintenance. Will retry when available.", exchange_name)
-    } else if lower.contains("ip") || lower.contains("whitelist") {
-        format!("IP not whitelisted on {} API. Add the server IP to your API key whitelist.", exchange_name)
-    } else {
-        format!("Order failed on {}: {}", exchange_name, raw)
-    }
+/// Posição aberta que não pôde ser precificada
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct PnlPriceError {
+    pub exchange_id: String,
+    pub symbol: String,
+    pub error: String,
 }
 
-async fn execute_order(
-    exchange: &DecryptedExchange, symbol: &str,
-    order_type: &str, side: &str, amount: f64, price: Option<f64>,
-) -> Result<OrderResult, String> {
-    let ccxt_id = exchange.ccxt_id.clone();
-    let api_key = exchange.api_key.clone();
-    let api_secret = exchange.api_secret.clone();
-    let passphrase = exchange.passphrase.clone();
-    let symbol = symbol.to_string();
-    let order_type = order_type.to_string();
-    let side = side.to_string();
+/// Quote que não pôde ser convertida para USD
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct PnlRateError {
+    pub quote: String,
+    pub error: String,
+}
 
-    spawn_ccxt_blocking(move || {
-        let client = CCXTClient::new(&ccxt_id, &api_key, &api_secret, passphrase.as_deref())?;
-        let order_obj = client.create_order_sync(&symbol, &order_type, &side, amount, price)?;
-        use pyo3::prelude::*;
-        Python::with_gil(|py| {
-            let order_ref = order_obj.as_ref(py);
-            let s = |key: &str| -> String {
-                order_ref.get_item(key).ok()
-                    .and_then(|v| if v.is_none() { None } else { v.extract().ok() })
-                    .unwrap_or_default()
-            };
-            let f = |key: &str| -> Option<f64> {
-                order_ref.get_item(key).ok()
-                    .and_then(|v| if v.is_none() { None } else { v.extract().ok() })
-            };
-            let fee_cost: Option<f64> = order_ref.get_item("fee").ok()
-                .and_then(|fee| {
-                    if fee.is_none() { return None; }
-                    fee.get_item("cost").ok()?.extract().ok()
-                });
-            Ok(OrderResult {
-                order_id: s("id"), status: s("status"),
-                filled: f("filled"), avg_price: f("average").or_else(|| f("price")),
-                cost: f("cost"), fee: fee_cost,
-            })
-        })
-    })
-    .await
-    .map_err(|e| format!("Task join error: {}", e))?
+/// Totais em USD
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize)]
+pub struct PnlSummary {
+    pub realized_pnl: f64,
+    pub unrealized_pnl: f64,
+    pub total_pnl: f64,
+    pub symbols: Vec<SymbolPnl>,
+    /// true quando algum preço ou conversão falhou e os totais estão incompletos
+    pub partial: bool,
+    pub price_errors: Vec<PnlPriceError>,
+    /// Símbolos dessas quotes ficam fora dos totais
+    pub rate_errors: Vec<PnlRateError>,
 }
 
-pub async fn persist_tick_result(
-    db: &MongoDB, user_id: &str, strategy: &StrategyItem, result: &TickResult, manual: bool,
-) -> Result<(), String> {
-    let collection = db.collection::<UserStrategies>(COLLECTION);
-    let now = chrono::Utc::now().timestamp();
-    let p = "strategies.$[elem]";
+type PriceKey = (String, String);
 
-    let mut update_set = doc! {
-        format!("{}.last_checked_at", p): now,
-        format!("{}.updated_at", p): now,
-        "updated_at": now,
-    };
+fn open_position(strategy: &StrategyItem) -> Option<&PositionInfo> {
+    strategy.position.as_ref().filter(|p| p.quantity > 0.0)
+}
 
-    if result.price > 0.0 {
-        update_set.insert(format!("{}.last_price", p), result.price);
+/// Soma realizado + não realizado; `prices` vem de `(exchange_id, symbol)` e
+/// `quote_rates` (quote → USD) converte cada símbolo antes de entrar nos totais
+pub fn summarize_pnl(
+    strategies: &[StrategyItem], prices: &HashMap<PriceKey, Result<f64, String>>,
+    quote_rates: &HashMap<String, Result<f64, String>>,
+) -> PnlSummary {
+    let mut by_symbol: std::collections::BTreeMap<String, SymbolPnl> = Default::default();
+    let mut price_errors: Vec<PnlPriceError> = Vec::new();
+
+    for strategy in strategies {
+        let entry = by_symbol.entry(strategy.symbol.clone()).or_insert_with(|| SymbolPnl {
+            symbol: strategy.symbol.clone(),
+            quote: crate::services::order_service::quote_asset(&strategy.symbol),
+            ..Default::default()
+        });
+        entry.strategies += 1;
+        entry.realized_pnl += strategy.total_pnl_usd;
+
+        let Some(position) = open_position(strategy) else { continue };
+        let key = (strategy.exchange_id.clone(), strategy.symbol.clone());
+        match prices.get(&key) {
+            Some(Ok(price)) => entry.unrealized_pnl += (price - position.entry_price) * position.quantity,
+            other => {
+                entry.unpriced = true;
+                let error = match other {
+                    Some(Err(e)) => e.clone(),
+                    _ => "price not fetched".to_string(),
+                };
+                if !price_errors.iter().any(|e| e.exchange_id == key.0 && e.symbol == key.1) {
+                    price_errors.push(PnlPriceError { exchange_id: key.0, symbol: key.1, error });
+                }
+            }
+        }
     }
 
-    if let Some(ref new_status) = result.new_status {
-        update_set.insert(format!("{}.status", p), mongodb::bson::to_bson(new_status).unwrap_or_default());
-        match new_status {
-            StrategyStatus::Completed | StrategyStatus::StoppedOut
-            | StrategyStatus::Expired | StrategyStatus::Error | StrategyStatus::Paused => {
-                update_set.insert(format!("{}.is_active", p), false);
+    let mut summary = PnlSummary { price_errors, ..Default::default() };
+    for (_, mut symbol) in by_symbol {
+        symbol.total_pnl = symbol.realized_pnl + symbol.unrealized_pnl;
+        match quote_rates.get(&symbol.quote) {
+            Some(Ok(rate)) => {
+                summary.realized_pnl += symbol.realized_pnl * rate;
+                summary.unrealized_pnl += symbol.unrealized_pnl * rate;
+            }
+            other => {
+                let error = match other {
+                    Some(Err(e)) => e.clone(),
+                    _ => "rate not fetched".to_string(),
+                };
+                if !summary.rate_errors.iter().any(|e| e.quote == symbol.quote) {
+                    summary.rate_errors.push(PnlRateError { quote: symbol.quote.clone(), error });
+                }
             }
-            _ => {}
         }
+        summary.symbols.push(symbol);
     }
+    summary.total_pnl = summary.realized_pnl + summary.unrealized_pnl;
+    summary.partial = !summary.price_errors.is_empty() || !summary.rate_errors.is_empty();
+    summary
+}
 
-    if let Some(ref error) = result.error {
-        update_set.insert(format!("{}.error_message", p), error.as_str());
-    } else {
-        update_set.insert(format!("{}.error_message", p), mongodb::bson::Bson::Null);
+/// PNL consolidado do usuário, buscando os preços das posições abertas
+pub async fn pnl_summary(db: &MongoDB, user_id: &str, strategies: &[StrategyItem]) -> PnlSummary {
+    let prices = fetch_position_prices(db, user_id, strategies).await;
+    let rates = fetch_quote_usd_rates(db, user_id, strategies).await;
+    summarize_pnl(strategies, &prices, &rates)
+}
+
+/// Taxa quote → USD de cada moeda de cotação usada pelas estratégias.
+/// Quotes cripto são cotadas na exchange da primeira estratégia que as usa.
+async fn fetch_quote_usd_rates(db: &MongoDB, user_id: &str, strategies: &[StrategyItem]) -> HashMap<String, Result<f64, String>> {
+    use crate::services::order_service::{quote_asset, quote_usd_rate};
+
+    let mut quotes: Vec<(String, String)> = Vec::new();
+    for strategy in strategies {
+        let quote = quote_asset(&strategy.symbol);
+        if !quotes.iter().any(|(q, _)| *q == quote) {
+            quotes.push((quote, strategy.exchange_id.clone()));
+        }
     }
 
-    let mut current_position = strategy.position.clone();
-    let mut accumulated_pnl: f64 = 0.0;
-    let mut gradual_lot_indices_executed: Vec<usize> = Vec::new();
-    let mut had_gradual_sell = false;
+    let mut rates: HashMap<String, Result<f64, String>> = HashMap::new();
+    if quotes.is_empty() {
+        return rates;
+    }
+    let exchanges = user_exchanges_service::get_user_exchanges_decrypted(db, user_id).await.unwrap_or_default();
+    for (quote, exchange_id) in quotes {
+        let exchange = exchanges.iter().find(|ex| ex.exchange_id == exchange_id);
+        let rate = quote_usd_rate(exchange, &quote).await;
+        rates.insert(quote, rate);
+    }
+    rates
+}
 
-    for exec in &result.executions {
-        match exec.action {
-            ExecutionAction::Buy => {
-                if let Some(ref mut pos) = current_position {
-                    let old_cost = pos.entry_price * pos.quantity;
-                    let new_cost = exec.price * exec.amount;
-                    let new_qty = pos.quantity + exec.amount;
-                    if new_qty > 0.0 {
-                        pos.entry_price = (old_cost + new_cost) / new_qty;
-                        pos.quantity = new_qty;
-                        pos.total_cost = old_cost + new_cost;
-                    }
-                    pos.current_price = result.price;
-                    if result.price > pos.highest_price { pos.highest_price = result.price; }
-                } else {
-                    current_position = Some(PositionInfo {
-                        entry_price: exec.price, quantity: exec.amount, total_cost: exec.total,
-                        current_price: result.price, unrealized_pnl: 0.0, unrealized_pnl_percent: 0.0,
-                        highest_price: result.price, opened_at: now,
-                    });
-                }
+/// Preço atual de cada (exchange, símbolo) com posição aberta
+async fn fetch_position_prices(db: &MongoDB, user_id: &str, strategies: &[StrategyItem]) -> HashMap<PriceKey, Result<f64, String>> {
+    use futures::stream::{self, StreamExt};
+
+    let mut keys: Vec<PriceKey> = strategies.iter()
+        .filter(|s| open_position(s).is_some())
+        .map(|s| (s.exchange_id.clone(), s.symbol.clone()))
+        .collect();
+    keys.sort();
+    keys.dedup();
+
+    let mut prices: HashMap<PriceKey, Result<f64, String>> = HashMap::new();
+    if !keys.is_empty() {
+        match user_exchanges_service::get_user_exchanges_decrypted(db, user_id).await {
+            Ok(exchanges) => {
+                let exchanges = &exchanges;
+                prices = stream::iter(keys)
+                    .map(|(exchange_id, symbol)| async move {
+                        let price = match exchanges.iter().find(|ex| ex.exchange_id == exchange_id) {
+                            Some(ex) => fetch_current_price(
+                                &ex.ccxt_id, &ex.api_key, &ex.api_secret, ex.passphrase.as_deref(), &symbol,
+                            ).await.map(|q| q.price),
+                            None => Err("exchange not connected".to_string()),
+                        };
+                        ((exchange_id, symbol), price)
+                    })
+                    .buffer_unordered(PNL_PRICE_CONCURRENCY)
+                    .collect()
+                    .await;
             }
-            ExecutionAction::Sell => {
-                accumulated_pnl += exec.pnl_usd;
-                if let Some(ref mut pos) = current_position {
-                    pos.quantity -= exec.amount;
-                    if pos.quantity > 0.0001 {
-                        pos.total_cost = pos.entry_price * pos.quantity;
-                        pos.current_price = result.price;
-                    }
+            Err(e) => {
+                log::warn!("⚠️ Position prices for {}: failed to decrypt exchanges: {}", user_id, e);
+                prices = keys.into_iter().map(|k| (k, Err(format!("credentials unavailable: {}", e)))).collect();
+            }
+        }
+    }
+    prices
+}
+
+// ── Posição consolidada de um ativo ─────────────────────────────────
+// Mesmo token em várias exchanges/estratégias: preço médio ponderado pela
+// quantidade, quantidade total e PNL não realizado a preço de mercado. Pernas
+// em quotes diferentes (BTC/USDT, BTC/BRL) são convertidas para USD antes de
+// entrar nos totais, como no resumo de PNL.
+
+/// Posição aberta de uma estratégia no ativo, com preços na quote do par
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct AssetPositionLeg {
+    pub strategy_id: String,
+    pub strategy_name: String,
+    pub exchange_id: String,
+    pub exchange_name: String,
+    pub symbol: String,
+    /// Moeda de `entry_price`, `current_price` e `unrealized_pnl`
+    pub quote: String,
+    pub quantity: f64,
+    pub entry_price: f64,
+    /// None = preço indisponível (perna fica fora do não realizado)
+    pub current_price: Option<f64>,
+    pub unrealized_pnl: Option<f64>,
+    /// None = quote sem conversão para USD (perna fica fora dos totais em USD)
+    pub unrealized_pnl_usd: Option<f64>,
+}
+
+/// Totais em USD
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize)]
+pub struct AssetPosition {
+    pub asset: String,
+    pub total_quantity: f64,
+    /// Σ(preço em USD × quantidade) / Σ quantidade, das pernas com conversão
+    pub average_entry_price: f64,
+    pub total_cost: f64,
+    pub unrealized_pnl: f64,
+    pub unrealized_pnl_percent: f64,
+    pub positions: Vec<AssetPositionLeg>,
+    /// true quando alguma perna ficou sem preço ou sem conversão
+    pub partial: bool,
+    pub price_errors: Vec<PnlPriceError>,
+    pub rate_errors: Vec<PnlRateError>,
+}
+
+/// Ativo base do par (`btc/usdt` → `BTC`)
+fn base_asset(symbol: &str) -> String {
+    symbol.split('/').next().unwrap_or(symbol).trim().to_uppercase()
+}
+
+/// Junta as posições abertas do ativo (qualquer quote/exchange); `prices` vem de
+/// `(exchange_id, symbol)` e `quote_rates` (quote → USD) converte cada perna
+pub fn summarize_asset_position(
+    asset: &str, strategies: &[StrategyItem], prices: &HashMap<PriceKey, Result<f64, String>>,
+    quote_rates: &HashMap<String, Result<f64, String>>,
+) -> AssetPosition {
+    let asset = base_asset(asset);
+    let mut summary = AssetPosition { asset: asset.clone(), ..Default::default() };
+    let mut converted_quantity = 0.0;
+    let mut priced_cost = 0.0;
+
+    for strategy in strategies.iter().filter(|s| base_asset(&s.symbol) == asset) {
+        let Some(position) = open_position(strategy) else { continue };
+        let key = (strategy.exchange_id.clone(), strategy.symbol.clone());
+        let current_price = match prices.get(&key) {
+            Some(Ok(price)) => Some(*price),
+            other => {
+                let error = match other {
+                    Some(Err(e)) => e.clone(),
+                    _ => "price not fetched".to_string(),
+                };
+                if !summary.price_errors.iter().any(|e| e.exchange_id == key.0 && e.symbol == key.1) {
+                    summary.price_errors.push(PnlPriceError { exchange_id: key.0.clone(), symbol: key.1.clone(), error });
                 }
-                if exec.reason.contains("gradual") || exec.reason == "take_profit" {
-                    had_gradual_sell = true;
-                    for (i, lot) in strategy.config.gradual_lots.iter().enumerate() {
-                        if !lot.executed && !gradual_lot_indices_executed.contains(&i) {
-                            gradual_lot_indices_executed.push(i);
-                            break;
-                        }
-                    }
+                None
+            }
+        };
+        let quote = crate::services::order_service::quote_asset(&strategy.symbol);
+        let rate = match quote_rates.get(&quote) {
+            Some(Ok(rate)) => Some(*rate),
+            other => {
+                let error = match other {
+                    Some(Err(e)) => e.clone(),
+                    _ => "rate not fetched".to_string(),
+                };
+                if !summary.rate_errors.iter().any(|e| e.quote == quote) {
+                    summary.rate_errors.push(PnlRateError { quote: quote.clone(), error });
                 }
+                None
             }
-            _ => {}
+        };
+
+        let unrealized_pnl = current_price.map(|price| (price - position.entry_price) * position.quantity);
+        let unrealized_pnl_usd = unrealized_pnl.zip(rate).map(|(pnl, rate)| pnl * rate);
+        summary.total_quantity += position.quantity;
+        if let Some(rate) = rate {
+            let cost_usd = position.entry_price * position.quantity * rate;
+            converted_quantity += position.quantity;
+            summary.total_cost += cost_usd;
+            if let Some(pnl) = unrealized_pnl_usd {
+                summary.unrealized_pnl += pnl;
+                priced_cost += cost_usd;
+            }
+        }
+        summary.positions.push(AssetPositionLeg {
+            strategy_id: strategy.strategy_id.clone(),
+            strategy_name: strategy.name.clone(),
+            exchange_id: strategy.exchange_id.clone(),
+            exchange_name: strategy.exchange_name.clone(),
+            symbol: strategy.symbol.clone(),
+            quote,
+            quantity: position.quantity,
+            entry_price: position.entry_price,
+            current_price,
+            unrealized_pnl,
+            unrealized_pnl_usd,
+        });
+    }
+
+    if converted_quantity > 0.0 {
+        summary.average_entry_price = summary.total_cost / converted_quantity;
+    }
+    if priced_cost > 0.0 {
+        summary.unrealized_pnl_percent = summary.unrealized_pnl / priced_cost * 100.0;
+    }
+    summary.partial = !summary.price_errors.is_empty() || !summary.rate_errors.is_empty();
+    summary
+}
+
+/// Posição consolidada do ativo em todas as estratégias do usuário
+pub async fn asset_position(db: &MongoDB, user_id: &str, asset: &str) -> Result<AssetPosition, String> {
+    let strategies = db.collection::<UserStrategies>(COLLECTION)
+        .find_one(doc! { "user_id": user_id }).await
+        .map_err(|e| format!("Failed to access database: {}", e))?
+        .map(|d| d.strategies)
+        .unwrap_or_default();
+    let asset = base_asset(asset);
+    let holding: Vec<StrategyItem> = strategies.into_iter()
+        .filter(|s| base_asset(&s.symbol) == asset)
+        .collect();
+
+    let prices = fetch_position_prices(db, user_id, &holding).await;
+    let rates = fetch_quote_usd_rates(db, user_id, &holding).await;
+    Ok(summarize_asset_position(&asset, &holding, &prices, &rates))
+}
+
+/// Contagem das estratégias do usuário por status (cabeçalho do dashboard)
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize)]
+pub struct StrategyCountSummary {
+    pub total: u64,
+    /// `is_active = true`
+    pub active: u64,
+    /// `in_position` + `gradual_selling`
+    pub in_position: u64,
+    /// Todos os status, inclusive os sem estratégias (0)
+    pub by_status: std::collections::BTreeMap<String, u64>,
+}
+
+/// Agrupa as estratégias do usuário por (status, is_active) sem carregar os documentos
+fn strategy_count_pipeline(user_id: &str) -> Vec<mongodb::bson::Document> {
+    vec![
+        doc! { "$match": { "user_id": user_id } },
+        doc! { "$unwind": "$strategies" },
+        doc! { "$group": {
+            "_id": { "status": "$strategies.status", "is_active": "$strategies.is_active" },
+            "count": { "$sum": 1 },
+        } },
+    ]
+}
+
+/// Soma as linhas do `$group`. Campos ausentes seguem os defaults do modelo
+/// (status `idle`, `is_active` true).
+fn fold_strategy_counts(rows: &[mongodb::bson::Document]) -> StrategyCountSummary {
+    let all = [
+        StrategyStatus::Idle, StrategyStatus::Monitoring, StrategyStatus::InPosition,
+        StrategyStatus::GradualSelling, StrategyStatus::Completed, StrategyStatus::StoppedOut,
+        StrategyStatus::Expired, StrategyStatus::Paused, StrategyStatus::Error,
+    ];
+    let mut summary = StrategyCountSummary {
+        by_status: all.iter().map(|s| (s.to_string(), 0)).collect(),
+        ..Default::default()
+    };
+
+    for row in rows {
+        let key = row.get_document("_id").ok();
+        let status = key.and_then(|k| k.get_str("status").ok()).unwrap_or("idle");
+        let is_active = key.and_then(|k| k.get_bool("is_active").ok()).unwrap_or(true);
+        let count = match row.get("count") {
+            Some(mongodb::bson::Bson::Int32(n)) => *n as u64,
+            Some(mongodb::bson::Bson::Int64(n)) => *n as u64,
+            _ => 0,
+        };
+
+        summary.total += count;
+        if is_active { summary.active += count; }
+        if status == "in_position" || status == "gradual_selling" { summary.in_position += count; }
+        *summary.by_status.entry(status.to_string()).or_insert(0) += count;
+    }
+    summary
+}
+
+pub async fn strategy_count_summary(db: &MongoDB, user_id: &str) -> Result<StrategyCountSummary, String> {
+    use futures::TryStreamExt;
+
+    let rows: Vec<mongodb::bson::Document> = db.collection::<UserStrategies>(COLLECTION)
+        .aggregate(strategy_count_pipeline(user_id)).await
+        .map_err(|e| format!("Failed to aggregate strategies: {}", e))?
+        .try_collect().await
+        .map_err(|e| format!("Failed to read strategy counts: {}", e))?;
+    Ok(fold_strategy_counts(&rows))
+}
+
+/// Encerrada (concluída, stop ou expirada) antes do corte (`updated_at` marca o encerramento)
+pub fn is_archivable(strategy: &StrategyItem, cutoff: i64) -> bool {
+    matches!(strategy.status, StrategyStatus::Completed | StrategyStatus::StoppedOut | StrategyStatus::Expired)
+        && strategy.updated_at < cutoff
+}
+
+/// Lista da API: ativas e, opcionalmente, as arquivadas, das mais recentes para as mais antigas
+pub fn strategy_list(strategies: Vec<StrategyItem>, archived: Vec<ArchivedStrategy>) -> Vec<StrategyListItem> {
+    let mut list: Vec<StrategyListItem> = strategies.into_iter().map(StrategyListItem::from).collect();
+    list.extend(archived.into_iter().map(|a| StrategyListItem { archived: true, ..StrategyListItem::from(a.strategy) }));
+    list.sort_by_key(|s| std::cmp::Reverse(s.updated_at));
+    list
+}
+
+/// Estratégias ativas seguidas das arquivadas, para relatórios que cobrem todo o histórico (PnL)
+pub fn with_archived(strategies: Vec<StrategyItem>, archived: Vec<ArchivedStrategy>) -> Vec<StrategyItem> {
+    let mut all = strategies;
+    all.extend(archived.into_iter().map(|a| a.strategy));
+    all
+}
+
+#[derive(Debug, Default, serde::Serialize)]
+pub struct ArchiveResult {
+    pub archived: usize,
+    pub users: usize,
+}
+
+/// Move as estratégias encerradas há mais de `older_than_days` dias para
+/// `strategies_archive`. Sem `user_id` processa todos os usuários (job).
+/// Insere no arquivo antes de remover, então uma falha no meio nunca perde histórico.
+pub async fn archive_completed_strategies(db: &MongoDB, user_id: Option<&str>, older_than_days: i64) -> Result<ArchiveResult, String> {
+    use futures::TryStreamExt;
+
+    let now = chrono::Utc::now().timestamp();
+    let cutoff = now - older_than_days.max(0) * 86_400;
+    let collection = db.collection::<UserStrategies>(COLLECTION);
+    let archive = db.collection::<ArchivedStrategy>(ARCHIVE_COLLECTION);
+
+    let mut filter = doc! { "strategies": { "$elemMatch": { "status": { "$in": ARCHIVABLE_STATUSES }, "updated_at": { "$lt": cutoff } } } };
+    if let Some(uid) = user_id {
+        filter.insert("user_id", uid);
+    }
+    let user_docs: Vec<UserStrategies> = collection.find(filter).await
+        .map_err(|e| format!("Failed to load strategies: {}", e))?
+        .try_collect().await
+        .map_err(|e| format!("Failed to read strategies: {}", e))?;
+
+    let mut result = ArchiveResult::default();
+    for user_doc in user_docs {
+        let entries: Vec<ArchivedStrategy> = user_doc.strategies.into_iter()
+            .filter(|s| is_archivable(s, cutoff))
+            .map(|strategy| ArchivedStrategy { id: None, user_id: user_doc.user_id.clone(), archived_at: now, strategy })
+            .collect();
+        if entries.is_empty() {
+            continue;
         }
+        let ids: Vec<&str> = entries.iter().map(|a| a.strategy.strategy_id.as_str()).collect();
+
+        archive.insert_many(&entries).await
+            .map_err(|e| format!("Failed to archive strategies of {}: {}", user_doc.user_id, e))?;
+        collection.update_one(
+            doc! { "user_id": &user_doc.user_id },
+            doc! {
+                "$pull": { "strategies": { "strategy_id": { "$in": &ids }, "status": { "$in": ARCHIVABLE_STATUSES } } },
+                "$set": { "updated_at": now },
+            },
+        ).await
+            .map_err(|e| format!("Failed to remove archived strategies of {}: {}", user_doc.user_id, e))?;
+
+        log::info!("🗄️ Archived {} finished strategies for user {}", entries.len(), user_doc.user_id);
+        result.archived += entries.len();
+        result.users += 1;
+    }
+    Ok(result)
+}
+
+pub async fn archived_strategies(db: &MongoDB, user_id: &str) -> Result<Vec<ArchivedStrategy>, String> {
+    use futures::TryStreamExt;
+
+    db.collection::<ArchivedStrategy>(ARCHIVE_COLLECTION)
+        .find(doc! { "user_id": user_id }).await
+        .map_err(|e| format!("Failed to load archived strategies: {}", e))?
+        .try_collect().await
+        .map_err(|e| format!("Failed to read archived strategies: {}", e))
+}
+
+fn evaluate_gradual(strategy: &StrategyItem, price: f64, now: i64, signals: &mut Vec<StrategySignal>) {
+    let config = &strategy.config;
+    let position = match &strategy.position {
+        Some(pos) if pos.quantity > 0.0 => pos,
+        _ => {
+            signals.push(StrategySignal {
+                signal_type: SignalType::Info, price,
+                message: "⚠️ Status 'gradual_selling' mas sem posição aberta. Todos os lotes podem já ter sido vendidos.".into(),
+                acted: false, price_change_percent: 0.0, created_at: now,
+            });
+            return;
+        }
+    };
+
+    let entry = position.entry_price;
+    if entry <= 0.0 {
+        signals.push(StrategySignal {
+            signal_type: SignalType::Info, price,
+            message: "⚠️ Preço de entrada é 0 durante venda gradual. Verifique a posição.".into(),
+            acted: false, price_change_percent: 0.0, created_at: now,
+        });
+        return;
+    }
+    let pct = ((price - entry) / entry) * 100.0;
+    let sl_price = config.stop_loss_price();
+    let unrealized_pnl = (price - entry) * position.quantity;
+    let executed_lots = config.gradual_lots.iter().filter(|l| l.executed).count();
+    let total_lots = config.gradual_lots.len();
+
+    if price <= sl_price {
+        signals.push(StrategySignal {
+            signal_type: SignalType::StopLoss, price,
+            message: format!(
+                "🛑 STOP LOSS durante venda gradual! Preço {:.2} <= stop {:.2} ({:+.2}%). {}/{} lotes vendidos. Vendendo posição restante ({:.6}) para limitar perda.",
+                price, sl_price, pct, executed_lots, total_lots, position.quantity
+            ),
+            acted: false, price_change_percent: pct, created_at: now,
+        });
+        return;
+    }
+
+    let timer_secs = config.timer_gradual_min * 60;
+    let last_sell = strategy.last_gradual_sell_at.unwrap_or(0);
+    if now - last_sell < timer_secs {
+        let remaining_secs = timer_secs - (now - last_sell);
+        let remaining_min = remaining_secs / 60;
+        let remaining_sec = remaining_secs % 60;
+        signals.push(StrategySignal {
+            signal_type: SignalType::Info, price,
+            message: format!(
+                "⏱️ Timer gradual ativo: próximo lote em {}min {}s. Preço {:.2} ({:+.2}%). PnL: ${:.2}. Progresso: {}/{} lotes vendidos.",
+                remaining_min, remaining_sec, price, pct, unrealized_pnl, executed_lots, total_lots
+            ),
+            acted: false, price_change_percent: pct, created_at: now,
+        });
+        return;
+    }
+
+    let next_lot_idx = config.gradual_lots.iter().position(|l| !l.executed);
+    match next_lot_idx {
+        Some(idx) => {
+            let lot = &config.gradual_lots[idx];
+            let gradual_trigger = config.gradual_trigger_price(idx);
+            if price >= gradual_trigger {
+                let sell_qty = (position.total_cost / position.entry_price * lot.sell_percent / 100.0).min(position.quantity);
+                signals.push(StrategySignal {
+                    signal_type: SignalType::GradualSell, price,
+                    message: format!(
+                        "📈 VENDA GRADUAL! Lote {} de {}: preço {:.2} >= trigger gradual {:.2}. Vendendo {:.0}% ({:.6} unidades). Progresso: {}/{} lotes.",
+                        lot.lot_number, total_lots, price, gradual_trigger, lot.sell_percent, sell_qty, executed_lots, total_lots
+                    ),
+                    acted: false, price_change_percent: pct, created_at: now,
+                });
+            } else {
+                let diff = gradual_trigger - price;
+                let diff_pct = (diff / price) * 100.0;
+                signals.push(StrategySignal {
+                    signal_type: SignalType::Info, price,
+                    message: format!(
+                        "⏳ Aguardando lote {} de {}: preço {:.2} < trigger gradual {:.2}. Faltam {:.2} ({:.2}%) para acionar. PnL: ${:.2}. Timer: pronto. Progresso: {}/{} lotes.",
+                        lot.lot_number, total_lots, price, gradual_trigger, diff, diff_pct, unrealized_pnl, executed_lots, total_lots
+                    ),
+                    acted: false, price_change_percent: pct, created_at: now,
+                });
+            }
+        }
+        None => {
+            signals.push(StrategySignal {
+                signal_type: SignalType::TakeProfit, price,
+                message: format!(
+                    "✅ Todos os {} lotes graduais executados! Vendendo posição restante ({:.6} unidades) a {:.2}.",
+                    total_lots, position.quantity, price
+                ),
+                acted: false, price_change_percent: pct, created_at: now,
+            });
+        }
+    }
+}
+
+fn calc_sell_amount(strategy: &StrategyItem, signal_type: &SignalType) -> f64 {
+    let position = match &strategy.position {
+        Some(pos) if pos.quantity > 0.0 => pos,
+        _ => return 0.0,
+    };
+    match signal_type {
+        SignalType::TakeProfit => {
+            if strategy.config.gradual_sell && !strategy.config.gradual_lots.is_empty() {
+                let lot = strategy.config.gradual_lots.iter().find(|l| !l.executed);
+                match lot {
+                    Some(lot) => {
+                        let original_qty = if position.entry_price > 0.0 {
+                            position.total_cost / position.entry_price
+                        } else {
+                            position.quantity
+                        };
+                        (original_qty * lot.sell_percent / 100.0).min(position.quantity)
+                    }
+                    None => position.quantity,
+                }
+            } else {
+                position.quantity
+            }
+        }
+        SignalType::GradualSell => {
+            let lot = strategy.config.gradual_lots.iter().find(|l| !l.executed);
+            match lot {
+                Some(lot) => {
+                    let original_qty = if position.entry_price > 0.0 {
+                        position.total_cost / position.entry_price
+                    } else {
+                        position.quantity
+                    };
+                    (original_qty * lot.sell_percent / 100.0).min(position.quantity)
+                }
+                None => position.quantity,
+            }
+        }
+        SignalType::StopLoss | SignalType::MaxDrawdown => position.quantity,
+        _ => 0.0,
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OrderResult {
+    pub order_id: String,
+    pub status: String,
+    pub filled: Option<f64>,
+    pub avg_price: Option<f64>,
+    pub cost: Option<f64>,
+    pub fee: Option<f64>,
+}
+
+/// Map raw CCXT/exchange errors to a stable error type (CCXT exception class first, then message text)
+fn order_error_type(raw: &str) -> &'static str {
+    const EXCEPTIONS: &[(&str, &str)] = &[
+        ("InsufficientFunds", "insufficient_funds"),
+        ("RateLimitExceeded", "rate_limit"),
+        ("DDoSProtection", "rate_limit"),
+        ("AuthenticationError", "auth"),
+        ("PermissionDenied", "permission"),
+        ("AccountSuspended", "permission"),
+        ("BadSymbol", "bad_symbol"),
+        ("OnMaintenance", "exchange_unavailable"),
+        ("ExchangeNotAvailable", "exchange_unavailable"),
+        ("RequestTimeout", "network"),
+        ("NetworkError", "network"),
+    ];
+    if let Some((_, error_type)) = EXCEPTIONS.iter().find(|(name, _)| raw.contains(name)) {
+        return error_type;
+    }
+
+    let lower = raw.to_lowercase();
+    if lower.contains("insufficient") || lower.contains("balance") || lower.contains("not enough") {
+        "insufficient_funds"
+    } else if lower.contains("minimum") || lower.contains("min order") || lower.contains("too small")
+        || lower.contains("notional") {
+        "min_notional"
+    } else if lower.contains("authentication") || lower.contains("invalid api") || lower.contains("apikey") {
+        "auth"
+    } else if lower.contains("permission") || lower.contains("not allowed") || lower.contains("restricted") {
+        "permission"
+    } else if lower.contains("rate limit") || lower.contains("too many") {
+        "rate_limit"
+    } else if lower.contains("network") || lower.contains("timeout") || lower.contains("connection") {
+        "network"
+    } else if lower.contains("not found") || lower.contains("bad symbol") || lower.contains("invalid symbol") {
+        "bad_symbol"
+    } else if lower.contains("market closed") || lower.contains("maintenance") {
+        "exchange_unavailable"
+    } else if lower.contains("ip") || lower.contains("whitelist") {
+        "ip_whitelist"
+    } else if raw.contains("InvalidOrder") {
+        "invalid_order"
+    } else {
+        "unknown"
+    }
+}
+
+/// Classify raw CCXT/exchange errors into user-friendly messages
+fn classify_order_error(raw: &str, symbol: &str, exchange_name: &str) -> String {
+    match order_error_type(raw) {
+        "insufficient_funds" => format!("Insufficient balance on {} to sell {}. Check your exchange balance.", exchange_name, symbol),
+        "min_notional" => format!("Order amount too small for {} on {}. Minimum order size not met.", symbol, exchange_name),
+        "auth" => format!("API authentication failed on {}. Your API keys may be expired or invalid.", exchange_name),
+        "permission" => format!("API key lacks trade permission on {}. Enable spot trading in your API settings.", exchange_name),
+        "rate_limit" => format!("Rate limited by {}. Will retry on next tick.", exchange_name),
+        "network" => format!("Network error connecting to {}. Will retry on next tick.", exchange_name),
+        "bad_symbol" => format!("Trading pair '{}' not found on {}. It may have been delisted.", symbol, exchange_name),
+        "exchange_unavailable" => format!("{} market is closed or under maintenance. Will retry when available.", exchange_name),
+        "ip_whitelist" => format!("IP not whitelisted on {} API. Add the server IP to your API key whitelist.", exchange_name),
+        _ => format!("Order failed on {}: {}", exchange_name, raw),
+    }
+}
+
+/// Loga a rejeição em formato estruturado, conta na métrica por tipo e devolve o
+/// `error_message` da execução: `[tipo] mensagem amigável`
+fn report_order_rejection(strategy: &StrategyItem, stage: &str, raw: &str) -> String {
+    let error_type = order_error_type(raw);
+    crate::api::metrics::increment_order_rejection(error_type);
+    log::error!(
+        "❌ order_rejected strategy={} exchange={} symbol={} stage={} error_type={} raw={:?}",
+        strategy.strategy_id, strategy.exchange_name, strategy.symbol, stage, error_type, raw,
+    );
+    format!("[{}] {}", error_type, classify_order_error(raw, &strategy.symbol, &strategy.exchange_name))
+}
+
+lazy_static::lazy_static! {
+    /// Precisão por (ccxt_id, símbolo) — muda raramente, então fica em memória
+    static ref MARKET_PRECISION: std::sync::Mutex<HashMap<(String, String), MarketPrecision>> = Default::default();
+}
+
+/// Casas decimais do mercado (cacheadas); sem resposta da exchange usa o padrão configurado
+async fn market_precision(exchange: &DecryptedExchange, symbol: &str) -> ExecutionPrecision {
+    let key = (exchange.ccxt_id.clone(), symbol.to_string());
+    if let Some(cached) = MARKET_PRECISION.lock().unwrap_or_else(|e| e.into_inner()).get(&key) {
+        return ExecutionPrecision::from_market(Some(cached));
+    }
+
+    let ex = exchange.clone();
+    let sym = symbol.to_string();
+    let fetched = spawn_ccxt_paced(&exchange.ccxt_id, move || {
+        let client = CCXTClient::for_exchange(&ex)?;
+        client.fetch_market_precision_sync(&sym)
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))
+    .and_then(|r| r);
+
+    match fetched {
+        Ok(market) => {
+            MARKET_PRECISION.lock().unwrap_or_else(|e| e.into_inner()).insert(key, market);
+            ExecutionPrecision::from_market(Some(&market))
+        }
+        Err(e) => {
+            log::warn!("⚠️ Failed to load precision for {} on {}: {}", symbol, exchange.ccxt_id, e);
+            ExecutionPrecision::default()
+        }
+    }
+}
+
+lazy_static::lazy_static! {
+    /// `limits.cost.min` por (ccxt_id, símbolo); None = mercado sem mínimo informado
+    static ref MARKET_MIN_COST: std::sync::Mutex<HashMap<(String, String), Option<f64>>> = Default::default();
+}
+
+/// `market_min_cost` em USD: o mínimo vem na moeda de cotação do par. Sem cotação
+/// da quote, o mínimo fica desconhecido (não bloqueia a compra).
+async fn market_min_cost_usd(exchange: &DecryptedExchange, symbol: &str) -> Option<f64> {
+    let min_cost = market_min_cost(exchange, symbol).await?;
+    let quote = crate::services::order_service::quote_asset(symbol);
+    match crate::services::order_service::quote_usd_rate(Some(exchange), &quote).await {
+        Ok(rate) => Some(min_cost * rate),
+        Err(e) => {
+            log::warn!("⚠️ Failed to price {} min cost of {} in USD: {}", quote, symbol, e);
+            None
+        }
+    }
+}
+
+/// Valor mínimo de ordem do mercado (cacheado). Falha ao buscar não bloqueia a compra.
+async fn market_min_cost(exchange: &DecryptedExchange, symbol: &str) -> Option<f64> {
+    let key = (exchange.ccxt_id.clone(), symbol.to_string());
+    if let Some(cached) = MARKET_MIN_COST.lock().unwrap_or_else(|e| e.into_inner()).get(&key) {
+        return *cached;
+    }
+
+    let ex = exchange.clone();
+    let sym = symbol.to_string();
+    let fetched = spawn_ccxt_paced(&exchange.ccxt_id, move || {
+        let client = CCXTClient::for_exchange(&ex)?;
+        client.fetch_market_rules_sync(&sym)
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))
+    .and_then(|r| r);
+
+    match fetched {
+        Ok(rules) => {
+            let min_cost = rules.and_then(|r| r.limits.min_cost);
+            MARKET_MIN_COST.lock().unwrap_or_else(|e| e.into_inner()).insert(key, min_cost);
+            min_cost
+        }
+        Err(e) => {
+            log::warn!("⚠️ Failed to load min cost for {} on {}: {}", symbol, exchange.ccxt_id, e);
+            None
+        }
+    }
+}
+
+/// Decisão da compra automática frente ao mínimo do mercado
+#[derive(Debug, Clone, PartialEq)]
+pub enum MinNotionalBuy {
+    Buy(f64),
+    Skip { amount: f64, min_cost: f64 },
+    /// Novo `pending_buy_usd` (ainda abaixo do mínimo)
+    Accumulate { pending: f64, min_cost: f64 },
+}
+
+/// Valor da compra automática (`buy_amount_usd` + o acumulado, se a política for
+/// `accumulate`) comparado ao `min_cost` do mercado em USD. Enquanto o mesmo sinal
+/// de compra persiste (`pending_buy_window`), o valor dele já está no acumulado.
+pub fn plan_min_notional_buy(strategy: &StrategyItem, min_cost: Option<f64>) -> MinNotionalBuy {
+    let policy = strategy.config.min_notional_policy;
+    let pending = strategy.pending_buy_usd.max(0.0);
+    let amount = match policy {
+        MinNotionalPolicy::Accumulate if strategy.pending_buy_window && pending > 0.0 => pending,
+        MinNotionalPolicy::Accumulate => strategy.buy_amount_usd() + pending,
+        MinNotionalPolicy::Skip => strategy.buy_amount_usd(),
+    };
+    match min_cost.filter(|m| *m > 0.0) {
+        Some(min_cost) if amount > 0.0 && amount < min_cost => match policy {
+            MinNotionalPolicy::Skip => MinNotionalBuy::Skip { amount, min_cost },
+            MinNotionalPolicy::Accumulate => MinNotionalBuy::Accumulate { pending: amount, min_cost },
+        },
+        _ => MinNotionalBuy::Buy(amount),
+    }
+}
+
+/// Invariante de lado antes de enviar a ordem: venda exige posição aberta e compra
+/// não pode levar a posição além de `max_position_usd`. Se violada, devolve a
+/// execução de erro registrada no lugar da ordem (nada vai para a exchange).
+pub fn check_order_side(strategy: &StrategyItem, signal_type: &SignalType, buy_usd: f64, price: f64, now: i64) -> Option<StrategyExecution> {
+    let held = strategy.position.as_ref().map(|p| p.quantity).unwrap_or(0.0);
+    let (action, amount, total, message) = match signal_type {
+        SignalType::Buy => {
+            let max = strategy.config.max_position_usd.filter(|m| *m > 0.0)?;
+            let current = held.max(0.0) * price;
+            if current + buy_usd <= max * (1.0 + 1e-9) {
+                return None;
+            }
+            let amount = if price > 0.0 { buy_usd / price } else { 0.0 };
+            (ExecutionAction::BuyFailed, amount, buy_usd, format!(
+                "Buy refused: position would reach ${:.2} (current ${:.2} + ${:.2}), above the max position size of ${:.2}",
+                current + buy_usd, current, buy_usd, max
+            ))
+        }
+        SignalType::TakeProfit | SignalType::GradualSell | SignalType::StopLoss | SignalType::MaxDrawdown => {
+            if held > MIN_POSITION_QTY {
+                return None;
+            }
+            let message = match strategy.position {
+                Some(_) => format!("Sell refused: position quantity is {} {}, nothing to sell", held, strategy.symbol),
+                None => format!("Sell refused: no open position in {}", strategy.symbol),
+            };
+            (ExecutionAction::SellFailed, 0.0, 0.0, message)
+        }
+        _ => return None,
+    };
+    Some(StrategyExecution {
+        execution_id: uuid::Uuid::new_v4().to_string(),
+        action,
+        reason: format!("side_check_failed: {}", signal_type),
+        price, amount, total,
+        fee: 0.0, pnl_usd: 0.0, exchange_order_id: None,
+        executed_at: now, error_message: Some(message),
+    })
+}
+
+/// `check_order_side` para um nível do grid: a recusa leva o motivo do nível
+/// (`grid_level_N_sell_failed: side_check_failed: ...`), como as falhas da exchange
+fn grid_side_refusal(
+    strategy: &StrategyItem, level: u32, signal_type: &SignalType, buy_usd: f64, price: f64, now: i64,
+) -> Option<StrategyExecution> {
+    let mut refused = check_order_side(strategy, signal_type, buy_usd, price, now)?;
+    let side = if refused.action == ExecutionAction::BuyFailed { "buy" } else { "sell" };
+    refused.reason = format!("{}_{}_failed: {}", grid_level_reason(level), side, refused.reason);
+    Some(refused)
+}
+
+/// A mesma recusa já é a última execução do alvo (o nível do grid, ou a estratégia
+/// fora do grid): o sinal que se repete a cada tick não gera uma execução por tick
+pub fn is_repeated_refusal(strategy: &StrategyItem, refused: &StrategyExecution) -> bool {
+    let level = grid_level_of(&refused.reason);
+    strategy.executions.iter().rev()
+        .find(|e| e.action != ExecutionAction::Cancel && grid_level_of(&e.reason) == level)
+        .is_some_and(|last| last.action == refused.action && last.reason == refused.reason)
+}
+
+fn record_refusal(strategy: &StrategyItem, executions: &mut Vec<StrategyExecution>, refused: StrategyExecution) {
+    let message = refused.error_message.as_deref().unwrap_or_default();
+    if is_repeated_refusal(strategy, &refused) || executions.iter().any(|e| e.reason == refused.reason) {
+        log::debug!("🚫 [{}] {} (already recorded)", strategy.strategy_id, message);
+        return;
+    }
+    log::error!("🚫 [{}] {}", strategy.strategy_id, message);
+    executions.push(refused);
+}
+
+/// Novo (`pending_buy_usd`, `pending_buy_window`) quando muda: o sinal de compra sumiu
+/// (a próxima oportunidade acumula de novo) ou a estratégia deixou de acumular
+pub fn pending_buy_update(strategy: &StrategyItem, buy_signal: bool) -> Option<(f64, bool)> {
+    let accumulating = strategy.config.auto_entry
+        && strategy.config.min_notional_policy == MinNotionalPolicy::Accumulate;
+    if !accumulating && (strategy.pending_buy_usd > 0.0 || strategy.pending_buy_window) {
+        return Some((0.0, false));
+    }
+    (strategy.pending_buy_window && !buy_signal).then_some((strategy.pending_buy_usd, false))
+}
+
+async fn set_pending_buy_usd(db: &MongoDB, user_id: &str, strategy_id: &str, pending: f64, window: bool) -> Result<(), String> {
+    db.collection::<UserStrategies>(COLLECTION).update_one(
+        doc! { "user_id": user_id },
+        doc! { "$set": {
+            "strategies.$[elem].pending_buy_usd": pending,
+            "strategies.$[elem].pending_buy_window": window,
+        } },
+    )
+        .array_filters(vec![doc! { "elem.strategy_id": strategy_id }]).await
+        .map_err(|e| format!("Update pending buy failed: {}", e))?;
+    Ok(())
+}
+
+/// Em estratégias de futuros, define a alavancagem configurada antes da entrada.
+/// Spot não faz nenhuma chamada.
+pub async fn apply_futures_leverage<F, Fut>(config: &StrategyConfig, symbol: &str, set_leverage: F) -> Result<(), String>
+where
+    F: FnOnce(u32, String) -> Fut,
+    Fut: std::future::Future<Output = Result<(), String>>,
+{
+    if config.mode != StrategyMode::Futures {
+        return Ok(());
+    }
+    let leverage = config.leverage.ok_or("Futures strategy without leverage")?;
+    set_leverage(leverage, symbol.to_string()).await
+}
+
+async fn set_exchange_leverage(exchange: &DecryptedExchange, leverage: u32, symbol: String) -> Result<(), String> {
+    let ex = exchange.clone();
+    spawn_ccxt_paced(&exchange.ccxt_id, move || {
+        let client = CCXTClient::for_exchange(&ex)?;
+        client.set_leverage_sync(leverage, &symbol)
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?
+}
+
+/// Retentativas (`ORDER_MAX_RETRIES`, padrão 2) e backoff inicial (`ORDER_RETRY_BACKOFF_MS`)
+fn order_retry_policy() -> (u32, u64) {
+    let retries = std::env::var("ORDER_MAX_RETRIES")
+        .ok().and_then(|v| v.parse::<u32>().ok())
+        .unwrap_or(DEFAULT_ORDER_MAX_RETRIES);
+    let backoff_ms = std::env::var("ORDER_RETRY_BACKOFF_MS")
+        .ok().and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_ORDER_RETRY_BACKOFF_MS);
+    (retries, backoff_ms)
+}
+
+/// Recusas em que a exchange não criou a ordem (rate limit, manutenção, nonce)
+const ORDER_REJECTED_BEFORE_PLACEMENT: &[&str] = &["RateLimitExceeded", "DDoSProtection", "OnMaintenance", "InvalidNonce"];
+
+/// Falha que vale repetir na mesma tick: só recusas em que a ordem com certeza não
+/// foi criada. Timeout/erro de rede/5xx são ambíguos — a ordem a mercado pode ter
+/// executado e reenviá-la duplicaria a posição — então viram falha e a próxima tick
+/// (com a posição reconciliada) decide. Saldo insuficiente, símbolo inválido, auth etc.
+/// são definitivos.
+pub fn is_retryable_order_error(raw: &str) -> bool {
+    !raw.contains("InsufficientFunds") && ORDER_REJECTED_BEFORE_PLACEMENT.iter().any(|class| raw.contains(class))
+}
+
+/// Executa `attempt` até `max_retries` vezes a mais em falhas transitórias, com backoff exponencial
+pub async fn retry_transient_order<F, Fut, T>(max_retries: u32, backoff_ms: u64, mut attempt: F) -> Result<T, String>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<T, String>>,
+{
+    let mut tries = 0;
+    loop {
+        match attempt().await {
+            Err(e) if tries < max_retries && is_retryable_order_error(&e) => {
+                tries += 1;
+                let delay = backoff_ms * 2u64.pow(tries - 1);
+                log::warn!("🔄 Transient order error (retry {}/{} in {}ms): {}", tries, max_retries, delay, e);
+                tokio::time::sleep(std::time::Duration::from_millis(delay)).await;
+            }
+            Err(e) if credential_health_service::classify_ccxt_error(&e) == credential_health_service::CcxtErrorKind::Transient => {
+                log::warn!("⚠️ Order outcome unknown, not resending (may have been placed): {}", e);
+                return Err(e);
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Ordem a mercado registrando o resultado no contador de falhas de autenticação.
+/// Recusas transitórias (rate limit, manutenção) são repetidas na própria tick antes de
+/// virar BuyFailed/SellFailed; timeouts não (ver `is_retryable_order_error`).
+async fn execute_reported_order(
+    db: &MongoDB, user_id: &str, exchange: &DecryptedExchange, mode: StrategyMode, symbol: &str, side: &str, amount: f64,
+) -> Result<OrderResult, String> {
+    let (max_retries, backoff_ms) = order_retry_policy();
+    let result = retry_transient_order(max_retries, backoff_ms, || {
+        execute_order(exchange, mode, symbol, "market", side, amount, None)
+    }).await;
+    let outcome = result.as_ref().map(|_| ()).map_err(|e| e.as_str());
+    credential_health_service::report_exchange_result(db, user_id, &exchange.exchange_id, &exchange.name, outcome).await;
+    result
+}
+
+/// Compra de entrada a mercado pelo valor investido (quote).
+/// Passa `cost` direto quando a exchange suporta (sem arredondar quantidade nem gerar poeira);
+/// senão converte para quantidade base com o preço atual.
+async fn execute_reported_market_buy(
+    db: &MongoDB, user_id: &str, exchange: &DecryptedExchange, mode: StrategyMode, symbol: &str, quote_amount: f64, price: f64,
+) -> Result<OrderResult, String> {
+    let (max_retries, backoff_ms) = order_retry_policy();
+    let result = retry_transient_order(max_retries, backoff_ms, || {
+        execute_market_buy(exchange, mode, symbol, quote_amount, price)
+    }).await;
+    let outcome = result.as_ref().map(|_| ()).map_err(|e| e.as_str());
+    credential_health_service::report_exchange_result(db, user_id, &exchange.exchange_id, &exchange.name, outcome).await;
+    result
+}
+
+/// Como enviar uma compra a mercado de `quote_amount`
+#[derive(Debug, Clone, PartialEq)]
+pub enum MarketBuyPlan {
+    /// `cost` em quote nos params da ordem
+    Cost(f64),
+    /// Quantidade base calculada (`quote_amount / price`)
+    Amount(f64),
+}
+
+pub fn plan_market_buy(quote_amount: f64, price: f64, supports_cost: bool) -> Result<MarketBuyPlan, String> {
+    if supports_cost {
+        return Ok(MarketBuyPlan::Cost(quote_amount));
+    }
+    if price <= 0.0 {
+        return Err("Cannot compute buy amount without a price".to_string());
+    }
+    Ok(MarketBuyPlan::Amount(quote_amount / price))
+}
+
+pub async fn execute_market_buy(
+    exchange: &DecryptedExchange, mode: StrategyMode, symbol: &str, quote_amount: f64, price: f64,
+) -> Result<OrderResult, String> {
+    TRADING_SWITCH.ensure_enabled()?;
+    crate::services::order_service::enforce_max_order_notional(exchange, symbol, "buy", quote_amount / price, Some(price)).await?;
+    await_order_slot(exchange).await;
+
+    let ex = exchange.clone();
+    let symbol = symbol.to_string();
+
+    let result = spawn_ccxt_paced(&exchange.ccxt_id, move || {
+        let client = CCXTClient::for_exchange(&ex)?;
+        ensure_order_type_allowed(&client, mode, "market")?;
+        let order_obj = match plan_market_buy(quote_amount, price, client.supports_market_buy_cost_sync())? {
+            MarketBuyPlan::Cost(cost) => {
+                log::debug!("💵 Market buy by cost: {:.2} on {}", cost, symbol);
+                client.create_market_buy_cost_sync(&symbol, cost)?
+            }
+            MarketBuyPlan::Amount(amount) => client.create_order_sync(&symbol, "market", "buy", amount, None)?,
+        };
+        let mut order = pyo3::Python::with_gil(|py| parse_ccxt_order(order_obj.as_ref(py)))?;
+        convert_fee_to_quote(&client, &symbol, &mut order);
+        Ok(OrderResult {
+            avg_price: order.fill_price(),
+            fee: order.fee.map(|f| f.cost),
+            order_id: order.id, status: order.status,
+            filled: order.filled, cost: order.cost,
+        })
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?;
+    crate::services::balance_service::invalidate_account_balance(&exchange.api_key);
+    result
+}
+
+/// Converte a taxa da ordem para a quote do par (custo/PnL assumem a mesma moeda).
+/// Sem cotação para converter, a taxa é descartada com aviso em vez de somar
+/// quantidades de moedas diferentes.
+fn convert_fee_to_quote(client: &CCXTClient, symbol: &str, order: &mut CcxtOrder) {
+    let Some(fee) = order.fee.as_ref() else { return };
+    let converted = fee.cost_in_quote(symbol, order.fill_price(), |pair| {
+        client.fetch_ticker_sync(pair).ok()?.get("last")?.as_f64()
+    });
+    match converted {
+        Some(cost) => {
+            if !fee.currency.is_empty() && cost != fee.cost {
+                log::debug!("💱 Fee {} {} on {} = {:.6} in quote", fee.cost, fee.currency, symbol, cost);
+            }
+            let quote = symbol.split('/').nth(1).and_then(|q| q.split(':').next()).unwrap_or_default();
+            order.fee = Some(crate::models::OrderFee { currency: quote.to_string(), cost });
+        }
+        None => {
+            log::warn!("⚠️ Could not convert fee {} {} on {} to quote; ignoring it in cost/PnL", fee.cost, fee.currency, symbol);
+            order.fee = None;
+        }
+    }
+}
+
+// ── Espaçamento de ordens por conta ─────────────────────────────────
+// Várias estratégias (grid/scalping) na mesma conta disparando no mesmo ciclo
+// competem entre si no book. Cada conta (exchange + credencial) recebe vagas
+// sucessivas separadas por `ORDER_MIN_INTERVAL_MS`; quem chega cedo espera.
+
+const DEFAULT_ORDER_MIN_INTERVAL_MS: u64 = 250;
+
+lazy_static::lazy_static! {
+    static ref ORDER_SPACER: OrderSpacer = OrderSpacer::default();
+}
+
+/// Intervalo mínimo entre ordens da mesma conta (`ORDER_MIN_INTERVAL_MS`, 0 = desligado)
+fn order_min_interval() -> std::time::Duration {
+    let ms = std::env::var("ORDER_MIN_INTERVAL_MS").ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_ORDER_MIN_INTERVAL_MS);
+    std::time::Duration::from_millis(ms)
+}
+
+/// Conta = exchange + credencial (a api key entra só como hash)
+fn order_account_key(exchange: &DecryptedExchange) -> (String, String) {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    exchange.api_key.hash(&mut hasher);
+    exchange.sub_account.hash(&mut hasher);
+    (exchange.exchange_id.clone(), format!("{:x}", hasher.finish()))
+}
+
+#[derive(Default)]
+pub struct OrderSpacer {
+    /// Próxima vaga livre de cada conta
+    next_slot: std::sync::Mutex<HashMap<(String, String), std::time::Instant>>,
+}
+
+impl OrderSpacer {
+    /// Reserva a próxima vaga e devolve quanto esperar por ela. A reserva é
+    /// feita sob o lock, então ordens simultâneas recebem vagas sucessivas.
+    fn reserve(&self, key: &(String, String), interval: std::time::Duration, now: std::time::Instant) -> std::time::Duration {
+        let mut slots = self.next_slot.lock().unwrap_or_else(|e| e.into_inner());
+        slots.retain(|_, next| *next > now);
+        let slot = slots.get(key).copied().unwrap_or(now).max(now);
+        slots.insert(key.clone(), slot + interval);
+        slot - now
+    }
+
+    pub async fn wait_turn(&self, key: &(String, String), interval: std::time::Duration) {
+        if interval.is_zero() {
+            return;
+        }
+        let wait = self.reserve(key, interval, std::time::Instant::now());
+        if !wait.is_zero() {
+            log::debug!("⏳ Spacing order on {} by {}ms", key.0, wait.as_millis());
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+async fn await_order_slot(exchange: &DecryptedExchange) {
+    ORDER_SPACER.wait_turn(&order_account_key(exchange), order_min_interval()).await;
+}
+
+/// Recusa tipos de ordem fora da allowlist do modo da estratégia (`config.mode`) ou
+/// que a exchange não suporta, antes de enviar
+fn ensure_order_type_allowed(client: &CCXTClient, mode: StrategyMode, order_type: &str) -> Result<(), String> {
+    mode.validate_order_type(order_type, |capability| client.has_capability_sync(capability))
+}
+
+/// Params das saídas que ficam na exchange (TP e trailing nativo), checando o tipo.
+/// Em futuros só podem reduzir a posição: sem `reduceOnly` um TP atrasado abriria um short
+pub fn exit_order_params(
+    mode: StrategyMode, order_type: &str, has: impl Fn(&str) -> bool,
+) -> Result<serde_json::Map<String, serde_json::Value>, String> {
+    mode.validate_order_type(order_type, &has)?;
+    let mut params = serde_json::Map::new();
+    if mode == StrategyMode::Futures {
+        mode.validate_order_type(crate::models::REDUCE_ONLY_ORDER_TYPE, &has)?;
+        params.insert("reduceOnly".into(), serde_json::json!(true));
+    }
+    Ok(params)
+}
+
+/// Tipos de ordem da configuração (ver `StrategyConfig::order_types`) na allowlist do
+/// `config.mode` e no `has` da exchange; usado ao criar e ao editar a estratégia
+pub fn validate_config_order_types(
+    config: &StrategyConfig, capabilities: &crate::services::exchange_service::ExchangeCapabilities,
+) -> Result<(), String> {
+    let has = |capability: &str| capabilities.has(capability);
+    let native_trailing = native_trailing_support(&capabilities.ccxt_id, has).map(|kind| kind.order_type());
+    config.order_types(native_trailing).into_iter()
+        .try_for_each(|order_type| config.mode.validate_order_type(order_type, has))
+}
+
+pub async fn execute_order(
+    exchange: &DecryptedExchange, mode: StrategyMode, symbol: &str,
+    order_type: &str, side: &str, amount: f64, price: Option<f64>,
+) -> Result<OrderResult, String> {
+    TRADING_SWITCH.ensure_enabled()?;
+    crate::services::order_service::enforce_max_order_notional(exchange, symbol, side, amount, price).await?;
+    await_order_slot(exchange).await;
+
+    let ex = exchange.clone();
+    let symbol = symbol.to_string();
+    let order_type = order_type.to_string();
+    let side = side.to_string();
+
+    let result = spawn_ccxt_paced(&exchange.ccxt_id, move || {
+        let client = CCXTClient::for_exchange(&ex)?;
+        ensure_order_type_allowed(&client, mode, &order_type)?;
+        let order_obj = client.create_order_sync(&symbol, &order_type, &side, amount, price)?;
+        let mut order = pyo3::Python::with_gil(|py| parse_ccxt_order(order_obj.as_ref(py)))?;
+        convert_fee_to_quote(&client, &symbol, &mut order);
+        Ok(OrderResult {
+            avg_price: order.fill_price(),
+            fee: order.fee.map(|f| f.cost),
+            order_id: order.id, status: order.status,
+            filled: order.filled, cost: order.cost,
+        })
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?;
+    crate::services::balance_service::invalidate_account_balance(&exchange.api_key);
+    result
+}
+
+/// Exceções CCXT que indicam que a ordem já não está aberta (executada/cancelada)
+const CLOSED_ORDER_EXCEPTIONS: &[&str] = &["OrderNotFound"];
+
+/// true quando `raw` contém a exceção CCXT `class` levantada (`OrderNotFound: ...`
+/// ou `ccxt.base.errors.OrderNotFound: ...`), e não só a palavra na mensagem
+fn raised_ccxt_exception(raw: &str, class: &str) -> bool {
+    raw.match_indices(class).any(|(i, _)| {
+        let inside_word = matches!(raw[..i].chars().last(), Some(c) if c.is_alphanumeric() || c == '_');
+        !inside_word && raw[i + class.len()..].starts_with(':')
+    })
+}
+
+/// Erros de cancelamento que indicam que a ordem já não está aberta (executada/cancelada).
+/// Casa pela classe da exceção CCXT: mensagens livres como "insufficient balance,
+/// order not filled" não podem fazer uma ordem aberta sair do rastreamento.
+pub fn is_order_already_closed(raw: &str) -> bool {
+    CLOSED_ORDER_EXCEPTIONS.iter().any(|class| raised_ccxt_exception(raw, class))
+}
+
+/// Resultado do cancelamento das ordens rastreadas de uma estratégia
+#[derive(Debug, Default)]
+pub struct CancelOrdersResult {
+    pub executions: Vec<StrategyExecution>,
+    /// Ordens que não puderam ser canceladas (continuam rastreadas)
+    pub remaining: Vec<TrackedOrder>,
+    pub errors: Vec<String>,
+}
+
+/// Cancela cada ordem rastreada usando `cancel`. Ordens já executadas/canceladas
+/// na exchange são tratadas como resolvidas (não é erro).
+pub async fn cancel_tracked_orders<F, Fut>(
+    orders: &[TrackedOrder], reason: &str, now: i64, cancel: F,
+) -> CancelOrdersResult
+where
+    F: Fn(String) -> Fut,
+    Fut: std::future::Future<Output = Result<bool, String>>,
+{
+    let mut result = CancelOrdersResult::default();
+    for order in orders {
+        let note = match cancel(order.order_id.clone()).await {
+            Ok(_) => None,
+            Err(e) if is_order_already_closed(&e) => Some(format!("Order already closed on exchange: {}", e)),
+            Err(e) => {
+                result.errors.push(format!("Failed to cancel order {}: {}", order.order_id, e));
+                result.remaining.push(order.clone());
+                continue;
+            }
+        };
+        let price = order.price.unwrap_or(0.0);
+        result.executions.push(StrategyExecution {
+            execution_id: uuid::Uuid::new_v4().to_string(),
+            action: ExecutionAction::Cancel, reason: reason.to_string(),
+            price, amount: order.amount, total: price * order.amount,
+            fee: 0.0, pnl_usd: 0.0, exchange_order_id: Some(order.order_id.clone()),
+            executed_at: now, error_message: note,
+        });
+    }
+    result
+}
+
+/// Cancela na exchange as ordens abertas rastreadas pela estratégia
+pub async fn cancel_strategy_open_orders(
+    db: &MongoDB, user_id: &str, strategy: &StrategyItem, reason: &str,
+) -> CancelOrdersResult {
+    if strategy.open_orders.is_empty() {
+        return CancelOrdersResult::default();
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    let exchange = match user_exchanges_service::get_user_exchanges_decrypted(db, user_id).await {
+        Ok(list) => list.into_iter().find(|ex| ex.exchange_id == strategy.exchange_id),
+        Err(e) => {
+            log::error!("❌ [{}] Failed to decrypt exchanges for cancel: {}", strategy.strategy_id, e);
+            None
+        }
+    };
+    let Some(exchange) = exchange else {
+        return CancelOrdersResult {
+            executions: vec![],
+            remaining: strategy.open_orders.clone(),
+            errors: vec![format!(
+                "Exchange '{}' not available to cancel {} open order(s). Reconnect your exchange and try again.",
+                strategy.exchange_name, strategy.open_orders.len()
+            )],
+        };
+    };
+
+    log::info!("🧹 [{}] Cancelling {} open order(s) ({})", strategy.strategy_id, strategy.open_orders.len(), reason);
+
+    cancel_tracked_orders(&strategy.open_orders, reason, now, |order_id| {
+        cancel_exchange_order(&exchange, &strategy.symbol, order_id)
+    }).await
+}
+
+async fn cancel_exchange_order(exchange: &DecryptedExchange, symbol: &str, order_id: String) -> Result<bool, String> {
+    let ex = exchange.clone();
+    let symbol = symbol.to_string();
+    let result = spawn_ccxt_paced(&exchange.ccxt_id, move || {
+        let client = CCXTClient::for_exchange(&ex)?;
+        client.cancel_order_sync(&order_id, Some(&symbol))
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?;
+    crate::services::balance_service::invalidate_account_balance(&exchange.api_key);
+    result
+}
+
+// ==================== REAPER DE ORDENS PENDENTES ====================
+// Uma ordem rastreada em `open_orders` que nunca executa fica presa quando o
+// tick deixa de rodar (estratégia parada, monitor desligado por um tempo).
+// O monitor revisita essas ordens: passado o timeout, confere o status na
+// exchange e cancela as que seguem abertas; o que executou entra na posição e
+// no PnL como num tick. O trailing nativo e as vendas de
+// take profit protegem a posição e nunca são tratados como abandonados — o
+// tick confere a cada ciclo se executaram (`reconcile_native_trailing` e
+// `reconcile_take_profit_orders`).
+
+const DEFAULT_PENDING_ORDER_TIMEOUT_SECS: i64 = 3600;
+
+/// Timeout (s) de ordens pendentes (`STRATEGY_PENDING_ORDER_TIMEOUT_SECS`). 0 desativa.
+pub fn pending_order_timeout_secs() -> i64 {
+    std::env::var("STRATEGY_PENDING_ORDER_TIMEOUT_SECS")
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_PENDING_ORDER_TIMEOUT_SECS)
+        .max(0)
+}
+
+/// Ordens abertas há mais que o timeout (ou que o `ttl_secs` da própria ordem)
+pub fn stale_open_orders(strategy: &StrategyItem, now: i64, timeout_secs: i64) -> Vec<TrackedOrder> {
+    strategy.open_orders.iter()
+        .filter(|o| o.order_type != NATIVE_TRAILING_ORDER_TYPE && o.order_type != TAKE_PROFIT_ORDER_TYPE)
+        .filter(|o| {
+            let limit = o.ttl_secs.unwrap_or(timeout_secs);
+            limit > 0 && now - o.created_at >= limit
+        })
+        .cloned()
+        .collect()
+}
+
+#[derive(Debug, Default)]
+pub struct ReapResult {
+    /// Ordens que saem de `open_orders` (canceladas ou já fechadas na exchange)
+    pub resolved: Vec<String>,
+    pub executions: Vec<StrategyExecution>,
+    pub errors: Vec<String>,
+    pub new_status: Option<StrategyStatus>,
+}
+
+/// Confere cada ordem vencida com `fetch` e cancela (via `cancel`) as que seguem
+/// abertas. O que executou (antes ou durante o cancelamento) vira Buy/Sell para
+/// o PnL e a posição. Sem execução e sem nada pendente, a estratégia ativa volta
+/// para `Monitoring`; pausada, encerrada ou em erro mantém o status.
+pub async fn reap_stale_orders<S, SFut, C, CFut>(
+    strategy: &StrategyItem, stale: &[TrackedOrder], now: i64, fetch: S, cancel: C,
+) -> ReapResult
+where
+    S: Fn(String) -> SFut,
+    SFut: std::future::Future<Output = Result<CcxtOrder, String>>,
+    C: Fn(String) -> CFut,
+    CFut: std::future::Future<Output = Result<bool, String>>,
+{
+    let mut result = ReapResult::default();
+    let mut still_open = Vec::new();
+    for order in stale {
+        match fetch(order.order_id.clone()).await {
+            Ok(fetched) if fetched.status == "open" => still_open.push((order.clone(), fetched)),
+            Ok(fetched) => {
+                log::warn!("⚠️ [{}] Pending {} order {} is already '{}' on the exchange; no longer tracked",
+                    strategy.strategy_id, order.side, order.order_id, fetched.status);
+                result.resolved.push(order.order_id.clone());
+                result.executions.extend(closed_order_execution(strategy, order, &fetched, "pending_order_filled", now));
+            }
+            Err(e) => result.errors.push(format!("Failed to check order {}: {}", order.order_id, e)),
+        }
+    }
+
+    let orders: Vec<TrackedOrder> = still_open.iter().map(|(o, _)| o.clone()).collect();
+    let cancelled = cancel_tracked_orders(&orders, "pending_order_timeout", now, cancel).await;
+    for execution in cancelled.executions {
+        let Some(order_id) = execution.exchange_order_id.clone() else { continue };
+        log::warn!("🧹 [{}] Pending order {} cancelled after timeout", strategy.strategy_id, order_id);
+        // Execução parcial até o cancelamento: relê a ordem (ou usa a última leitura)
+        if let Some((order, before)) = still_open.iter().find(|(o, _)| o.order_id == order_id) {
+            let fetched = fetch(order_id.clone()).await.unwrap_or_else(|_| before.clone());
+            result.executions.extend(closed_order_execution(strategy, order, &fetched, "pending_order_filled", now));
+        }
+        result.resolved.push(order_id);
+        result.executions.push(execution);
+    }
+    result.errors.extend(cancelled.errors);
+    result.new_status = reaped_status(strategy, &result);
+    result
+}
+
+/// Status após o reaper: compra executada abre posição, venda que zera conclui;
+/// só cancelamento devolve a estratégia ativa para `Monitoring`
+fn reaped_status(strategy: &StrategyItem, result: &ReapResult) -> Option<StrategyStatus> {
+    let settled = !strategy.is_active || matches!(strategy.status,
+        StrategyStatus::Paused | StrategyStatus::Completed | StrategyStatus::StoppedOut
+        | StrategyStatus::Expired | StrategyStatus::Error);
+    if settled {
+        return None;
+    }
+
+    let filled = |action: ExecutionAction| -> f64 {
+        result.executions.iter().filter(|e| e.action == action).map(|e| e.amount).sum()
+    };
+    let (bought, sold) = (filled(ExecutionAction::Buy), filled(ExecutionAction::Sell));
+    if bought > 0.0 || sold > 0.0 {
+        let quantity = strategy.position.as_ref().map(|p| p.quantity).unwrap_or(0.0) + bought - sold;
+        return if quantity <= 0.0001 {
+            (sold > 0.0).then_some(StrategyStatus::Completed)
+        } else if !matches!(strategy.status, StrategyStatus::InPosition | StrategyStatus::GradualSelling) {
+            Some(StrategyStatus::InPosition)
+        } else {
+            None
+        };
+    }
+
+    let pending = strategy.open_orders.iter().any(|o| !result.resolved.contains(&o.order_id));
+    let cancelled = result.executions.iter().any(|e| e.action == ExecutionAction::Cancel);
+    (cancelled && !pending && strategy.position.is_none() && strategy.status != StrategyStatus::Monitoring)
+        .then_some(StrategyStatus::Monitoring)
+}
+
+async fn fetch_exchange_order(exchange: &DecryptedExchange, symbol: &str, order_id: String) -> Result<CcxtOrder, String> {
+    let ex = exchange.clone();
+    let symbol = symbol.to_string();
+    spawn_ccxt_paced(&exchange.ccxt_id, move || {
+        let client = CCXTClient::for_exchange(&ex)?;
+        let order_obj = client.fetch_order_sync(&order_id, &symbol)?;
+        let mut order = pyo3::Python::with_gil(|py| parse_ccxt_order(order_obj.as_ref(py)))?;
+        convert_fee_to_quote(&client, &symbol, &mut order);
+        Ok(order)
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?
+}
+
+#[derive(Debug, Default)]
+pub struct ReapSummary {
+    pub checked: usize,
+    pub resolved: usize,
+    pub errors: Vec<String>,
+}
+
+/// Ciclo do reaper (chamado pelo monitor) sobre todas as estratégias com ordens abertas
+pub async fn reap_abandoned_orders(db: &MongoDB) -> Result<ReapSummary, String> {
+    use futures::TryStreamExt;
+
+    let timeout_secs = pending_order_timeout_secs();
+    let now = chrono::Utc::now().timestamp();
+    let collection = db.collection::<UserStrategies>(COLLECTION);
+    let user_docs: Vec<UserStrategies> = collection
+        .find(doc! { "strategies.open_orders.0": { "$exists": true } }).await
+        .map_err(|e| format!("Failed to load strategies: {}", e))?
+        .try_collect().await
+        .map_err(|e| format!("Failed to read strategies: {}", e))?;
+
+    let mut summary = ReapSummary::default();
+    for user_doc in user_docs {
+        let pending: Vec<(&StrategyItem, Vec<TrackedOrder>)> = user_doc.strategies.iter()
+            .map(|s| (s, stale_open_orders(s, now, timeout_secs)))
+            .filter(|(_, stale)| !stale.is_empty())
+            .collect();
+        if pending.is_empty() {
+            continue;
+        }
+        let exchanges = user_exchanges_service::get_user_exchanges_decrypted(db, &user_doc.user_id).await
+            .unwrap_or_default();
+
+        for (strategy, stale) in pending {
+            summary.checked += stale.len();
+            let Some(exchange) = exchanges.iter().find(|ex| ex.exchange_id == strategy.exchange_id) else {
+                summary.errors.push(format!("[{}] Exchange {} not available", strategy.strategy_id, strategy.exchange_name));
+                continue;
+            };
+            let result = reap_stale_orders(
+                strategy, &stale, now,
+                |order_id| fetch_exchange_order(exchange, &strategy.symbol, order_id),
+                |order_id| cancel_exchange_order(exchange, &strategy.symbol, order_id),
+            ).await;
+            summary.errors.extend(result.errors.iter().map(|e| format!("[{}] {}", strategy.strategy_id, e)));
+            if result.resolved.is_empty() {
+                continue;
+            }
+
+            pull_open_orders(db, &user_doc.user_id, &strategy.strategy_id, &result.resolved).await
+                .map_err(|e| format!("Failed to untrack reaped orders of {}: {}", strategy.strategy_id, e))?;
+            // Execuções passam pelo mesmo caminho do tick: posição, PnL realizado e status
+            let fill_price = result.executions.iter().rev()
+                .find(|e| matches!(e.action, ExecutionAction::Buy | ExecutionAction::Sell))
+                .map(|e| e.price)
+                .unwrap_or(0.0);
+            let tick = TickResult {
+                strategy_id: strategy.strategy_id.clone(),
+                symbol: strategy.symbol.clone(),
+                price: fill_price,
+                signals: vec![],
+                executions: result.executions.clone(),
+                new_status: result.new_status.clone(),
+                error: strategy.error_message.clone(),
+                precision: None,
+            };
+            persist_tick_result(db, &user_doc.user_id, strategy, &tick, false).await?;
+
+            let bought: f64 = result.executions.iter()
+                .filter(|e| e.action == ExecutionAction::Buy)
+                .map(|e| e.amount)
+                .sum();
+            if bought > 0.0 && result.new_status == Some(StrategyStatus::InPosition) {
+                if let Some(message) = after_entry_fill(db, &user_doc.user_id, exchange, strategy, bought, now).await {
+                    log::warn!("⚠️ [{}] {}", strategy.strategy_id, message);
+                }
+            }
+            summary.resolved += result.resolved.len();
+        }
+    }
+    Ok(summary)
+}
+
+// ==================== TRAILING STOP NATIVO ====================
+// Com `use_exchange_trailing`, o `max_drawdown_percent` vira uma ordem de trailing
+// na exchange logo após a entrada (rastreada em `open_orders`), e o guard de
+// drawdown do servidor fica desligado enquanto ela existir. Sem suporte na
+// exchange (ou se a ordem falhar) o guard server-side continua valendo.
+
+/// `order_type` das ordens de trailing nativo rastreadas em `open_orders`
+pub const NATIVE_TRAILING_ORDER_TYPE: &str = "trailing_stop";
+
+/// Faixa aceita pela Binance para `trailingDelta` (BIPS)
+const TRAILING_DELTA_BIPS_RANGE: std::ops::RangeInclusive<i64> = 10..=2000;
+
+/// Como a exchange recebe um trailing stop nativo
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NativeTrailing {
+    /// `has["createTrailingPercentOrder"]`: ordem a mercado com `trailingPercent` (%)
+    Percent,
+    /// Binance spot: STOP_LOSS com `trailingDelta` em BIPS (1% = 100)
+    DeltaBips,
+}
+
+impl NativeTrailing {
+    pub fn order_type(&self) -> &'static str {
+        match self {
+            NativeTrailing::Percent => "market",
+            NativeTrailing::DeltaBips => "STOP_LOSS",
+        }
+    }
+
+    /// Params do `create_order` para um trailing de `trailing_percent`%
+    pub fn params(&self, trailing_percent: f64) -> Result<serde_json::Map<String, serde_json::Value>, String> {
+        let mut params = serde_json::Map::new();
+        match self {
+            NativeTrailing::Percent => {
+                params.insert("trailingPercent".into(), serde_json::json!(trailing_percent));
+            }
+            NativeTrailing::DeltaBips => {
+                let bips = (trailing_percent * 100.0).round() as i64;
+                if !TRAILING_DELTA_BIPS_RANGE.contains(&bips) {
+                    return Err(format!(
+                        "trailingDelta {} BIPS out of range ({}-{})",
+                        bips, TRAILING_DELTA_BIPS_RANGE.start(), TRAILING_DELTA_BIPS_RANGE.end()
+                    ));
+                }
+                params.insert("trailingDelta".into(), serde_json::json!(bips));
+            }
+        }
+        Ok(params)
+    }
+}
+
+/// Suporte a trailing nativo a partir de `exchange.has`
+pub fn native_trailing_support(ccxt_id: &str, has: impl Fn(&str) -> bool) -> Option<NativeTrailing> {
+    if has("createTrailingPercentOrder") {
+        Some(NativeTrailing::Percent)
+    } else if ccxt_id.eq_ignore_ascii_case("binance") && has("createStopLossOrder") {
+        Some(NativeTrailing::DeltaBips)
+    } else {
+        None
+    }
+}
+
+fn native_trailing_order(strategy: &StrategyItem) -> Option<&TrackedOrder> {
+    strategy.open_orders.iter().find(|o| o.order_type == NATIVE_TRAILING_ORDER_TYPE)
+}
+
+/// Execução de uma ordem rastreada que fechou na exchange (total ou parcial antes do
+/// cancelamento); None quando nada executou. Vendas calculam o PnL sobre a entrada.
+pub fn closed_order_execution(
+    strategy: &StrategyItem, order: &TrackedOrder, fetched: &CcxtOrder, reason: &str, now: i64,
+) -> Option<StrategyExecution> {
+    let filled = fetched.filled.unwrap_or(if fetched.status == "closed" { order.amount } else { 0.0 });
+    if filled <= 0.0 {
+        return None;
+    }
+    let fill_price = fetched.fill_price().or(order.price).unwrap_or(0.0);
+    let fee = fetched.fee.as_ref().map(|f| f.cost).unwrap_or(0.0);
+    let is_sell = order.side.eq_ignore_ascii_case("sell");
+    let pnl_usd = match strategy.position.as_ref().filter(|_| is_sell) {
+        Some(position) => sell_pnl_usd(position.entry_price, fill_price, filled, fee),
+        None => 0.0,
+    };
+    Some(StrategyExecution {
+        execution_id: uuid::Uuid::new_v4().to_string(),
+        action: if is_sell { ExecutionAction::Sell } else { ExecutionAction::Buy },
+        reason: reason.to_string(),
+        price: fill_price, amount: filled,
+        total: fetched.cost.unwrap_or(fill_price * filled),
+        fee, pnl_usd,
+        exchange_order_id: Some(order.order_id.clone()),
+        executed_at: now, error_message: None,
+    })
+}
+
+/// Confere o trailing nativo rastreado. Executado: registra a venda (como o drawdown
+/// server-side) e cancela as vendas de take profit que sobraram; cancelado sem
+/// execução: sai do rastreio e o guard server-side volta a valer.
+async fn reconcile_native_trailing(
+    db: &MongoDB, user_id: &str, exchange: &DecryptedExchange, strategy: &StrategyItem, order: &TrackedOrder, now: i64,
+) -> Vec<StrategyExecution> {
+    let fetched = match fetch_exchange_order(exchange, &strategy.symbol, order.order_id.clone()).await {
+        Ok(o) => o,
+        Err(e) => {
+            log::warn!("⚠️ [{}] Failed to check native trailing order {}: {}", strategy.strategy_id, order.order_id, e);
+            return vec![];
+        }
+    };
+    if fetched.status == "open" {
+        return vec![];
+    }
+    if let Err(e) = pull_open_orders(db, user_id, &strategy.strategy_id, std::slice::from_ref(&order.order_id)).await {
+        log::error!("❌ [{}] Failed to untrack native trailing order: {}", strategy.strategy_id, e);
+    }
+
+    let Some(execution) = closed_order_execution(strategy, order, &fetched, "max_drawdown", now) else {
+        log::warn!("⚠️ [{}] Native trailing order {} is '{}' on the exchange; server-side drawdown guard resumes",
+            strategy.strategy_id, order.order_id, fetched.status);
+        return vec![];
+    };
+    log::warn!("🛑 [{}] Native trailing stop filled: {:.6} {} @ {:.4} | PnL: ${:.2}",
+        strategy.strategy_id, execution.amount, strategy.symbol, execution.price, execution.pnl_usd);
+
+    let mut executions = vec![execution];
+    let take_profits: Vec<TrackedOrder> = take_profit_orders(strategy).into_iter().cloned().collect();
+    if !take_profits.is_empty() {
+        let result = cancel_tracked_orders(&take_profits, "cancel_take_profit", now, |order_id| {
+            cancel_exchange_order(exchange, &strategy.symbol, order_id)
+        }).await;
+        if !result.errors.is_empty() {
+            log::warn!("⚠️ [{}] {}", strategy.strategy_id, result.errors.join("; "));
+        }
+        let released: Vec<String> = result.executions.iter().filter_map(|e| e.exchange_order_id.clone()).collect();
+        if !released.is_empty() {
+            if let Err(e) = pull_open_orders(db, user_id, &strategy.strategy_id, &released).await {
+                log::error!("❌ [{}] Failed to untrack released orders: {}", strategy.strategy_id, e);
+            }
+        }
+        executions.extend(result.executions);
+    }
+    executions
+}
+
+/// Status após o trailing nativo executar: posição zerada pausa, como o drawdown server-side
+fn native_trailing_fill_status(strategy: &StrategyItem, executions: &[StrategyExecution]) -> Option<StrategyStatus> {
+    let sold: f64 = executions.iter().filter(|e| e.action == ExecutionAction::Sell).map(|e| e.amount).sum();
+    let remaining = strategy.position.as_ref().map(|p| p.quantity).unwrap_or(0.0) - sold;
+    (remaining <= 0.0001).then(|| protective_exit_status(&SignalType::MaxDrawdown))
+}
+
+/// Coloca a venda com trailing nativo; Ok(None) quando a exchange não suporta
+async fn place_native_trailing(
+    exchange: &DecryptedExchange, mode: StrategyMode, symbol: &str, amount: f64, trailing_percent: f64,
+) -> Result<Option<TrackedOrder>, String> {
+    TRADING_SWITCH.ensure_enabled()?;
+    await_order_slot(exchange).await;
+    let ex = exchange.clone();
+    let symbol = symbol.to_string();
+    let result = spawn_ccxt_paced(&exchange.ccxt_id, move || {
+        let client = CCXTClient::for_exchange(&ex)?;
+        let Some(kind) = native_trailing_support(&ex.ccxt_id, |c| client.has_capability_sync(c)) else {
+            return Ok(None);
+        };
+        let mut params = exit_order_params(mode, kind.order_type(), |c| client.has_capability_sync(c))?;
+        params.extend(kind.params(trailing_percent)?);
+        let order_obj = client.create_order_params_sync(&symbol, kind.order_type(), "sell", amount, None, &params)?;
+        let order = pyo3::Python::with_gil(|py| parse_ccxt_order(order_obj.as_ref(py)))?;
+        Ok(Some(TrackedOrder {
+            order_id: order.id, side: "sell".into(), order_type: NATIVE_TRAILING_ORDER_TYPE.into(),
+            amount, price: None, created_at: chrono::Utc::now().timestamp(), ttl_secs: None, lot_number: None,
+        }))
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?;
+    crate::services::balance_service::invalidate_account_balance(&exchange.api_key);
+    result
+}
+
+/// Cancela as saídas colocadas na exchange (trailing nativo e vendas de take profit)
+/// antes de uma venda do próprio tick, já que elas travam o saldo
+async fn release_exchange_exits(
+    db: &MongoDB, user_id: &str, exchange: &DecryptedExchange, strategy: &StrategyItem, now: i64,
+) -> Vec<StrategyExecution> {
+    let mut executions = Vec::new();
+    let groups = [
+        (native_trailing_order(strategy).into_iter().cloned().collect::<Vec<_>>(), "cancel_native_trailing"),
+        (take_profit_orders(strategy).into_iter().cloned().collect(), "cancel_take_profit"),
+    ];
+    for (orders, reason) in groups {
+        if orders.is_empty() {
+            continue;
+        }
+        let result = cancel_tracked_orders(&orders, reason, now, |order_id| {
+            cancel_exchange_order(exchange, &strategy.symbol, order_id)
+        }).await;
+        if !result.errors.is_empty() {
+            log::warn!("⚠️ [{}] {}", strategy.strategy_id, result.errors.join("; "));
+        }
+        let released: Vec<String> = result.executions.iter().filter_map(|e| e.exchange_order_id.clone()).collect();
+        if !released.is_empty() {
+            if let Err(e) = pull_open_orders(db, user_id, &strategy.strategy_id, &released).await {
+                log::error!("❌ [{}] Failed to untrack released orders: {}", strategy.strategy_id, e);
+            }
+        }
+        executions.extend(result.executions);
+    }
+    executions
+}
+
+// ==================== TAKE PROFIT NA EXCHANGE ====================
+// Com `exchange_take_profits`, cada nível de take profit (lotes graduais ou o TP
+// único) vira uma venda limit na exchange logo após a entrada, rastreada em
+// `open_orders` com o `lot_number`. Os ticks seguintes conferem quais executaram
+// e registram a venda do lote; enquanto houver vendas na exchange, o TP
+// server-side fica desligado. Se a exchange limitar o número de ordens abertas,
+// os níveis que não couberam seguem monitorados pelo servidor.
+
+/// `order_type` das vendas de take profit rastreadas em `open_orders`
+pub const TAKE_PROFIT_ORDER_TYPE: &str = "take_profit";
+
+/// Venda limit planejada para um nível de take profit
+#[derive(Debug, Clone, PartialEq)]
+pub struct TakeProfitOrderPlan {
+    /// None no TP único (sem venda gradual)
+    pub lot_number: Option<i32>,
+    pub price: f64,
+    pub amount: f64,
+}
+
+#[derive(Debug, Default)]
+pub struct TakeProfitPlacement {
+    pub placed: Vec<TrackedOrder>,
+    pub error: Option<String>,
+    /// A exchange recusou por limite de ordens abertas
+    pub capped: bool,
+}
+
+fn take_profit_orders(strategy: &StrategyItem) -> Vec<&TrackedOrder> {
+    strategy.open_orders.iter().filter(|o| o.order_type == TAKE_PROFIT_ORDER_TYPE).collect()
+}
+
+/// Nível que a próxima venda de TP do servidor executaria: o primeiro lote não
+/// executado, ou None no TP único. `Err` quando não sobra lote.
+fn next_take_profit_level(config: &StrategyConfig) -> Result<Option<i32>, ()> {
+    if !config.gradual_sell || config.gradual_lots.is_empty() {
+        return Ok(None);
+    }
+    config.gradual_lots.iter().find(|l| !l.executed).map(|l| Some(l.lot_number)).ok_or(())
+}
+
+/// Remove os sinais de TP cujo nível já tem venda na exchange; os níveis que
+/// não couberam (limite de ordens abertas) seguem com o TP server-side
+fn drop_exchange_take_profit_signals(strategy: &StrategyItem, signals: &mut Vec<StrategySignal>) {
+    let on_exchange = match next_take_profit_level(&strategy.config) {
+        Ok(level) => take_profit_orders(strategy).iter().any(|o| o.lot_number == level),
+        Err(()) => !take_profit_orders(strategy).is_empty(),
+    };
+    if on_exchange {
+        signals.retain(|s| !matches!(s.signal_type, SignalType::TakeProfit | SignalType::GradualSell));
+    }
+}
+
+/// Uma venda por lote ainda não executado (preço de `gradual_trigger_price`,
+/// tamanho como em `calc_sell_amount`), ou uma única venda no `trigger_price`
+pub fn plan_take_profit_orders(config: &StrategyConfig, quantity: f64, precision: &ExecutionPrecision) -> Vec<TakeProfitOrderPlan> {
+    let scale = 10f64.powi(precision.amount as i32);
+    let floor_amount = |amount: f64| (amount * scale + 1e-9).floor() / scale;
+    let price_of = |p: f64| crate::utils::precision::round_to(p, precision.price);
+
+    if !config.gradual_sell || config.gradual_lots.is_empty() {
+        return vec![TakeProfitOrderPlan { lot_number: None, price: price_of(config.trigger_price()), amount: floor_amount(quantity) }];
+    }
+
+    let mut remaining = quantity;
+    let mut plan = Vec::new();
+    for (idx, lot) in config.gradual_lots.iter().enumerate().filter(|(_, l)| !l.executed) {
+        let amount = floor_amount((quantity * lot.sell_percent / 100.0).min(remaining));
+        if amount <= 0.0 {
+            break;
+        }
+        remaining -= amount;
+        plan.push(TakeProfitOrderPlan { lot_number: Some(lot.lot_number), price: price_of(config.gradual_trigger_price(idx)), amount });
+    }
+    plan
+}
+
+/// Recusa por limite de ordens abertas na conta/par
+pub fn is_open_order_limit_error(raw: &str) -> bool {
+    let lower = raw.to_lowercase();
+    lower.contains("too many open orders") || lower.contains("max open orders")
+        || lower.contains("maximum number of open orders") || lower.contains("max_num_orders")
+        || lower.contains("open order limit") || lower.contains("order count limit")
+}
+
+/// Coloca as vendas planejadas em sequência com `place` (devolve o id da ordem).
+/// Para no primeiro erro: os níveis restantes ficam com o TP server-side.
+pub async fn place_take_profit_orders<F, Fut>(plan: &[TakeProfitOrderPlan], now: i64, place: F) -> TakeProfitPlacement
+where
+    F: Fn(TakeProfitOrderPlan) -> Fut,
+    Fut: std::future::Future<Output = Result<String, String>>,
+{
+    let mut result = TakeProfitPlacement::default();
+    for level in plan {
+        match place(level.clone()).await {
+            Ok(order_id) => result.placed.push(TrackedOrder {
+                order_id, side: "sell".into(), order_type: TAKE_PROFIT_ORDER_TYPE.into(),
+                amount: level.amount, price: Some(level.price), created_at: now, ttl_secs: None,
+                lot_number: level.lot_number,
+            }),
+            Err(e) => {
+                result.capped = is_open_order_limit_error(&e);
+                result.error = Some(e);
+                break;
+            }
+        }
+    }
+    result
+}
+
+async fn create_take_profit_order(
+    exchange: &DecryptedExchange, mode: StrategyMode, symbol: &str, amount: f64, price: f64,
+) -> Result<String, String> {
+    TRADING_SWITCH.ensure_enabled()?;
+    await_order_slot(exchange).await;
+    let ex = exchange.clone();
+    let symbol = symbol.to_string();
+    let result = spawn_ccxt_paced(&exchange.ccxt_id, move || {
+        let client = CCXTClient::for_exchange(&ex)?;
+        let params = exit_order_params(mode, "limit", |c| client.has_capability_sync(c))?;
+        let order_obj = client.create_order_params_sync(&symbol, "limit", "sell", amount, Some(price), &params)?;
+        let order = pyo3::Python::with_gil(|py| parse_ccxt_order(order_obj.as_ref(py)))?;
+        Ok(order.id)
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?;
+    crate::services::balance_service::invalidate_account_balance(&exchange.api_key);
+    result
+}
+
+/// Coloca e rastreia as vendas de take profit após a entrada; devolve um aviso
+/// quando parte dos níveis ficou com o servidor
+async fn open_take_profit_orders(
+    db: &MongoDB, user_id: &str, exchange: &DecryptedExchange, strategy: &StrategyItem, quantity: f64, now: i64,
+) -> Option<String> {
+    let precision = market_precision(exchange, &strategy.symbol).await;
+    let plan = plan_take_profit_orders(&strategy.config, quantity, &precision);
+    let placement = place_take_profit_orders(&plan, now, |level| {
+        create_take_profit_order(exchange, strategy.config.mode, &strategy.symbol, level.amount, level.price)
+    }).await;
+
+    for order in &placement.placed {
+        log::info!("🎯 [{}] Take profit sell {:.6} @ {:.4} placed: {}", strategy.strategy_id, order.amount, order.price.unwrap_or(0.0), order.order_id);
+        if let Err(e) = push_open_order(db, user_id, &strategy.strategy_id, order).await {
+            log::error!("❌ [{}] {}", strategy.strategy_id, e);
+        }
+    }
+
+    let error = placement.error?;
+    log::warn!("⚠️ [{}] {} of {} take profit orders placed{}: {}", strategy.strategy_id, placement.placed.len(), plan.len(),
+        if placement.capped { " (open order limit)" } else { "" }, error);
+    Some(format!(
+        "🎯 {} de {} vendas de take profit colocadas na exchange; os demais níveis seguem monitorados pelo servidor.",
+        placement.placed.len(), plan.len()
+    ))
+}
+
+/// Confere as vendas de take profit rastreadas e registra as executadas (inclusive
+/// o parcial das canceladas na exchange). Ordens resolvidas saem de `open_orders`.
+async fn reconcile_take_profit_orders(
+    db: &MongoDB, user_id: &str, exchange: &DecryptedExchange, strategy: &StrategyItem, now: i64,
+) -> Vec<StrategyExecution> {
+    let entry = strategy.position.as_ref().map(|p| p.entry_price).unwrap_or(0.0);
+    let mut orders = take_profit_orders(strategy);
+    orders.sort_by_key(|o| o.lot_number);
+
+    let mut executions = Vec::new();
+    let mut resolved = Vec::new();
+    for order in orders {
+        let fetched = match fetch_exchange_order(exchange, &strategy.symbol, order.order_id.clone()).await {
+            Ok(o) => o,
+            Err(e) => {
+                log::warn!("⚠️ [{}] Failed to check take profit order {}: {}", strategy.strategy_id, order.order_id, e);
+                continue;
+            }
+        };
+        if fetched.status == "open" {
+            continue;
+        }
+        resolved.push(order.order_id.clone());
+
+        let filled = fetched.filled.unwrap_or(if fetched.status == "closed" { order.amount } else { 0.0 });
+        if filled <= 0.0 {
+            log::warn!("⚠️ [{}] Take profit order {} is '{}' on the exchange; level back to server-side monitoring",
+                strategy.strategy_id, order.order_id, fetched.status);
+            continue;
+        }
+        let sell_price = fetched.average.or(fetched.price).or(order.price).unwrap_or(0.0);
+        let fee = fetched.fee.as_ref().map(|f| f.cost).unwrap_or(0.0);
+        let pnl_usd = sell_pnl_usd(entry, sell_price, filled, fee);
+        // Como no TP server-side: a primeira venda sai de InPosition, as demais são graduais
+        let reason = if strategy.status == StrategyStatus::InPosition && executions.is_empty() {
+            "take_profit"
+        } else {
+            "gradual_sell"
+        };
+        log::info!("✅ [{}] {} filled on exchange: {:.6} {} @ {:.4} | PnL: ${:.2}",
+            strategy.strategy_id, reason, filled, strategy.symbol, sell_price, pnl_usd);
+        executions.push(StrategyExecution {
+            execution_id: uuid::Uuid::new_v4().to_string(),
+            action: ExecutionAction::Sell, reason: reason.into(),
+            price: sell_price, amount: filled,
+            total: fetched.cost.unwrap_or(sell_price * filled),
+            fee, pnl_usd,
+            exchange_order_id: Some(order.order_id.clone()),
+            executed_at: now, error_message: None,
+        });
+    }
+
+    if !resolved.is_empty() {
+        if let Err(e) = pull_open_orders(db, user_id, &strategy.strategy_id, &resolved).await {
+            log::error!("❌ [{}] Failed to untrack take profit orders: {}", strategy.strategy_id, e);
+        }
+    }
+    executions
+}
+
+/// Status após vendas de take profit executadas na exchange
+fn take_profit_fill_status(strategy: &StrategyItem, executions: &[StrategyExecution]) -> Option<StrategyStatus> {
+    let sold: f64 = executions.iter().filter(|e| e.action == ExecutionAction::Sell).map(|e| e.amount).sum();
+    let remaining = strategy.position.as_ref().map(|p| p.quantity).unwrap_or(0.0) - sold;
+    if remaining <= 0.0001 {
+        Some(StrategyStatus::Completed)
+    } else if strategy.config.gradual_sell {
+        Some(StrategyStatus::GradualSelling)
+    } else {
+        None
+    }
+}
+
+/// Pós-compra de entrada: zera o acumulado abaixo do mínimo e coloca trailing
+/// nativo e take profits na exchange; devolve o aviso de TP parcial, se houver
+async fn after_entry_fill(
+    db: &MongoDB, user_id: &str, exchange: &DecryptedExchange, strategy: &StrategyItem, filled: f64, now: i64,
+) -> Option<String> {
+    if strategy.pending_buy_usd > 0.0 || strategy.pending_buy_window {
+        if let Err(e) = set_pending_buy_usd(db, user_id, &strategy.strategy_id, 0.0, false).await {
+            log::error!("❌ [{}] {}", strategy.strategy_id, e);
+        }
+    }
+
+    // ── Trailing stop nativo (fallback: guard server-side) ──
+    if let Some(trailing) = strategy.config.max_drawdown_percent.filter(|p| *p > 0.0 && strategy.config.use_exchange_trailing) {
+        match place_native_trailing(exchange, strategy.config.mode, &strategy.symbol, filled, trailing).await {
+            Ok(Some(order)) => {
+                log::info!("🪝 [{}] Native trailing stop {:.2}% placed: {}", strategy.strategy_id, trailing, order.order_id);
+                if let Err(e) = push_open_order(db, user_id, &strategy.strategy_id, &order).await {
+                    log::error!("❌ [{}] {}", strategy.strategy_id, e);
+                }
+            }
+            Ok(None) => log::info!("ℹ️ [{}] {} has no native trailing stop, using server-side drawdown guard",
+                strategy.strategy_id, strategy.exchange_name),
+            Err(e) => log::warn!("⚠️ [{}] Native trailing stop failed, using server-side drawdown guard: {}",
+                strategy.strategy_id, e),
+        }
+    }
+
+    // ── Take profit na exchange (fallback: TP server-side) ──
+    if strategy.config.exchange_take_profits {
+        return open_take_profit_orders(db, user_id, exchange, strategy, filled, now).await;
+    }
+    None
+}
+
+// ==================== ENTRADA LIMIT ====================
+// Com `limit_entry`, a compra automática vira uma limit `offset_percent` abaixo
+// do preço, rastreada em `open_orders`. Enquanto ela existe, cada tick só cuida
+// dela: executada → registra a compra; preço subiu além do offset → cancela e
+// recoloca no novo alvo (mantendo o `created_at` original); passado
+// `timeout_secs` → cancela e compra o restante a mercado. A execução parcial
+// nunca é perseguida: o restante vai a mercado no mesmo tick, então a estratégia
+// só entra em posição sem compra limit pendente (e o tick normal, com stop e
+// drawdown, volta a rodar). Se os ticks pararem, o reaper de ordens pendentes
+// cancela a ordem como qualquer outra.
+
+/// `order_type` da compra limit de entrada rastreada em `open_orders`
+pub const LIMIT_ENTRY_ORDER_TYPE: &str = "limit_entry";
+
+pub fn limit_entry_price(price: f64, offset_percent: f64) -> f64 {
+    price * (1.0 - offset_percent / 100.0)
+}
+
+fn limit_entry_order(strategy: &StrategyItem) -> Option<&TrackedOrder> {
+    strategy.open_orders.iter().find(|o| o.order_type == LIMIT_ENTRY_ORDER_TYPE)
+}
+
+/// Resultado de um tick com a compra limit pendente
+#[derive(Debug, Default)]
+pub struct LimitEntryProgress {
+    pub executions: Vec<StrategyExecution>,
+    /// Ordens que saem de `open_orders`
+    pub resolved: Vec<String>,
+    /// Limit recolocada no novo alvo (perseguindo o preço)
+    pub replaced: Option<TrackedOrder>,
+    /// Quantidade comprada neste tick (limit e/ou mercado)
+    pub filled: f64,
+    pub error: Option<String>,
+}
+
+fn limit_entry_buy(order_id: &str, reason: &str, price: f64, amount: f64, cost: Option<f64>, fee: f64, now: i64) -> StrategyExecution {
+    StrategyExecution {
+        execution_id: uuid::Uuid::new_v4().to_string(),
+        action: ExecutionAction::Buy, reason: reason.into(),
+        price, amount, total: cost.unwrap_or(price * amount),
+        fee, pnl_usd: 0.0,
+        exchange_order_id: Some(order_id.to_string()),
+        executed_at: now, error_message: None,
+    }
+}
+
+/// Um passo da compra limit. `fetch` traz a ordem da exchange (de novo após o
+/// cancelamento, para pegar execuções que correram com ele), `cancel` cancela,
+/// `place_limit(amount, price)` recoloca e `market_buy(custo)` é o fallback.
+#[allow(clippy::too_many_arguments)]
+pub async fn advance_limit_entry<F, FFut, C, CFut, P, PFut, M, MFut>(
+    config: &LimitEntryConfig, order: &TrackedOrder, price: f64, now: i64,
+    fetch: F, cancel: C, place_limit: P, market_buy: M,
+) -> LimitEntryProgress
+where
+    F: Fn(String) -> FFut,
+    FFut: std::future::Future<Output = Result<CcxtOrder, String>>,
+    C: FnOnce(String) -> CFut,
+    CFut: std::future::Future<Output = Result<bool, String>>,
+    P: FnOnce(f64, f64) -> PFut,
+    PFut: std::future::Future<Output = Result<TrackedOrder, String>>,
+    M: FnOnce(f64) -> MFut,
+    MFut: std::future::Future<Output = Result<OrderResult, String>>,
+{
+    let mut progress = LimitEntryProgress::default();
+    let mut fetched = match fetch(order.order_id.clone()).await {
+        Ok(fetched) => fetched,
+        Err(e) => {
+            progress.error = Some(format!("Failed to check limit entry {}: {}", order.order_id, e));
+            return progress;
+        }
+    };
+    let placed_price = order.price.unwrap_or(price);
+    let target = limit_entry_price(price, config.offset_percent);
+
+    let timed_out = now - order.created_at >= config.timeout_secs;
+    let chase = target > placed_price * (1.0 + config.offset_percent / 100.0);
+    let acting = fetched.status == "open";
+    if acting && !timed_out && !chase {
+        return progress;
+    }
+    if acting {
+        if let Err(e) = cancel(order.order_id.clone()).await {
+            progress.error = Some(format!("Failed to cancel limit entry {}: {}", order.order_id, e));
+            return progress;
+        }
+        // O que executou até o cancelamento só aparece na ordem final; sem ela a ordem
+        // segue rastreada e o próximo tick registra a execução
+        fetched = match fetch(order.order_id.clone()).await {
+            Ok(fetched) => fetched,
+            Err(e) => {
+                progress.error = Some(format!("Limit entry {} cancelled, but its final fill could not be read: {}", order.order_id, e));
+                return progress;
+            }
+        };
+    }
+
+    let filled = fetched.filled.unwrap_or(if fetched.status == "closed" { order.amount } else { 0.0 });
+    let fill_price = fetched.average.or(fetched.price).or(order.price).unwrap_or(price);
+    let fee = fetched.fee.as_ref().map(|f| f.cost).unwrap_or(0.0);
+    progress.resolved.push(order.order_id.clone());
+    if filled > 0.0 {
+        progress.filled += filled;
+        progress.executions.push(limit_entry_buy(&order.order_id, "entry", fill_price, filled, fetched.cost, fee, now));
+    }
+
+    let remaining = order.amount - filled;
+    if !acting || fetched.status == "closed" || remaining * price <= 1e-9 {
+        return progress;
+    }
+    if timed_out || filled > 0.0 {
+        match market_buy(remaining * price).await {
+            Ok(result) => {
+                let amount = result.filled.unwrap_or(remaining);
+                progress.filled += amount;
+                progress.executions.push(limit_entry_buy(
+                    &result.order_id, "entry_market_fallback", result.avg_price.unwrap_or(price), amount,
+                    result.cost, result.fee.unwrap_or(0.0), now,
+                ));
+            }
+            Err(e) => progress.error = Some(e),
+        }
+    } else {
+        match place_limit(remaining, target).await {
+            Ok(replaced) => progress.replaced = Some(TrackedOrder { created_at: order.created_at, ..replaced }),
+            Err(e) => progress.error = Some(e),
+        }
+    }
+    progress
+}
+
+async fn create_limit_entry_order(
+    exchange: &DecryptedExchange, mode: StrategyMode, symbol: &str, amount: f64, price: f64, now: i64,
+) -> Result<TrackedOrder, String> {
+    TRADING_SWITCH.ensure_enabled()?;
+    let precision = market_precision(exchange, symbol).await;
+    let price = crate::utils::precision::round_to(price, precision.price);
+    let scale = 10f64.powi(precision.amount as i32);
+    let amount = (amount * scale + 1e-9).floor() / scale;
+    crate::services::order_service::enforce_max_order_notional(exchange, symbol, "buy", amount, Some(price)).await?;
+    await_order_slot(exchange).await;
+
+    let ex = exchange.clone();
+    let pair = symbol.to_string();
+    let order_id = spawn_ccxt_paced(&exchange.ccxt_id, move || {
+        let client = CCXTClient::for_exchange(&ex)?;
+        ensure_order_type_allowed(&client, mode, "limit")?;
+        let order_obj = client.create_order_sync(&pair, "limit", "buy", amount, Some(price))?;
+        let order = pyo3::Python::with_gil(|py| parse_ccxt_order(order_obj.as_ref(py)))?;
+        Ok::<_, String>(order.id)
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))??;
+    crate::services::balance_service::invalidate_account_balance(&exchange.api_key);
+
+    Ok(TrackedOrder {
+        order_id, side: "buy".into(), order_type: LIMIT_ENTRY_ORDER_TYPE.into(),
+        amount, price: Some(price), created_at: now, ttl_secs: None, lot_number: None,
+    })
+}
+
+/// Tick com a compra limit pendente: nada além de acompanhar a ordem
+async fn tick_limit_entry(
+    db: &MongoDB, user_id: &str, exchange: &DecryptedExchange, strategy: &StrategyItem,
+    order: &TrackedOrder, price: f64, now: i64,
+) -> TickResult {
+    // Sem `limit_entry` na config (removido com a ordem aberta): cai para mercado já
+    let config = strategy.config.limit_entry.clone()
+        .unwrap_or(LimitEntryConfig { offset_percent: 0.0, timeout_secs: 0 });
+    let progress = advance_limit_entry(&config, order, price, now,
+        |order_id| fetch_exchange_order(exchange, &strategy.symbol, order_id),
+        |order_id| cancel_exchange_order(exchange, &strategy.symbol, order_id),
+        |amount, target| create_limit_entry_order(exchange, strategy.config.mode, &strategy.symbol, amount, target, now),
+        |cost| execute_reported_market_buy(db, user_id, exchange, strategy.config.mode, &strategy.symbol, cost, price),
+    ).await;
+
+    if !progress.resolved.is_empty() {
+        if let Err(e) = pull_open_orders(db, user_id, &strategy.strategy_id, &progress.resolved).await {
+            log::error!("❌ [{}] Failed to untrack limit entry: {}", strategy.strategy_id, e);
+        }
+    }
+    let mut signals = Vec::new();
+    if let Some(replaced) = &progress.replaced {
+        log::info!("🧾 [{}] Limit entry moved up to {:.4}: {}", strategy.strategy_id, replaced.price.unwrap_or(0.0), replaced.order_id);
+        if let Err(e) = push_open_order(db, user_id, &strategy.strategy_id, replaced).await {
+            log::error!("❌ [{}] {}", strategy.strategy_id, e);
+        }
+    }
+    if let Some(e) = &progress.error {
+        log::warn!("⚠️ [{}] Limit entry: {}", strategy.strategy_id, e);
+    }
+
+    let mut new_status = None;
+    if progress.filled > 0.0 {
+        log::info!("✅ [{}] Limit entry filled: {:.6} {}", strategy.strategy_id, progress.filled, strategy.symbol);
+        new_status = Some(StrategyStatus::InPosition);
+        if let Some(message) = after_entry_fill(db, user_id, exchange, strategy, progress.filled, now).await {
+            signals.push(StrategySignal {
+                signal_type: SignalType::Info, price, message,
+                acted: false, price_change_percent: 0.0, created_at: now,
+            });
+        }
+    }
+    TickResult {
+        strategy_id: strategy.strategy_id.clone(), symbol: strategy.symbol.clone(), price,
+        signals, executions: progress.executions, new_status, error: progress.error,
+        precision: None,
+    }
+}
+
+async fn pull_open_orders(db: &MongoDB, user_id: &str, strategy_id: &str, order_ids: &[String]) -> Result<(), String> {
+    db.collection::<UserStrategies>(COLLECTION).update_one(
+        doc! { "user_id": user_id },
+        doc! { "$pull": { "strategies.$[elem].open_orders": { "order_id": { "$in": order_ids } } } },
+    )
+        .array_filters(vec![doc! { "elem.strategy_id": strategy_id }]).await
+        .map_err(|e| format!("Untrack orders failed: {}", e))?;
+    Ok(())
+}
+
+async fn push_open_order(db: &MongoDB, user_id: &str, strategy_id: &str, order: &TrackedOrder) -> Result<(), String> {
+    let order = mongodb::bson::to_bson(order).map_err(|e| format!("Serialize order failed: {}", e))?;
+    db.collection::<UserStrategies>(COLLECTION).update_one(
+        doc! { "user_id": user_id },
+        doc! { "$push": { "strategies.$[elem].open_orders": order } },
+    )
+        .array_filters(vec![doc! { "elem.strategy_id": strategy_id }]).await
+        .map_err(|e| format!("Track order failed: {}", e))?;
+    Ok(())
+}
+
+/// Chaves sem permissão de trade (conforme validado ao conectar) só geram sinais.
+/// Permissão desconhecida (exchange antiga) não bloqueia a execução.
+pub fn can_execute_orders(exchange: &DecryptedExchange) -> bool {
+    exchange.can_trade != Some(false)
+}
+
+/// A estratégia envia ordens reais: chave com permissão de trade e, se criada
+/// com `require_confirmation`, já confirmada pelo usuário
+pub fn places_live_orders(strategy: &StrategyItem, exchange: &DecryptedExchange) -> bool {
+    can_execute_orders(exchange) && !strategy.awaiting_live_confirmation
+}
+
+/// Por que o sinal não virou ordem. O sinal continua sendo notificado ("teria
+/// comprado aqui") com o motivo no texto.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SuppressedReason {
+    /// Chave sem permissão de trade (modo somente alerta)
+    NoTradePermission,
+    /// Trading desligado globalmente pelo admin (safe-mode)
+    TradingDisabled,
+    /// Estratégia em paper aguardando `confirm-live`
+    AwaitingConfirmation,
+    /// Compra abaixo do mínimo do mercado (política `skip`)
+    BelowMinimum { amount: f64, min_cost: f64 },
+}
+
+impl SuppressedReason {
+    pub fn describe(&self) -> String {
+        match self {
+            SuppressedReason::NoTradePermission => "a chave de API não tem permissão de trade".to_string(),
+            SuppressedReason::TradingDisabled => "trading desligado globalmente (safe-mode)".to_string(),
+            SuppressedReason::AwaitingConfirmation => "estratégia em paper aguardando confirmação para operar ao vivo".to_string(),
+            SuppressedReason::BelowMinimum { amount, min_cost } => {
+                format!("compra de ${:.2} abaixo do mínimo de ${:.2} da exchange", amount, min_cost)
+            }
+        }
+    }
+}
+
+/// Motivo que impede qualquer ordem neste tick (None = pode executar)
+pub fn suppression_reason(strategy: &StrategyItem, exchange: &DecryptedExchange, trading_enabled: bool) -> Option<SuppressedReason> {
+    if !can_execute_orders(exchange) {
+        Some(SuppressedReason::NoTradePermission)
+    } else if !trading_enabled {
+        Some(SuppressedReason::TradingDisabled)
+    } else if !places_live_orders(strategy, exchange) {
+        Some(SuppressedReason::AwaitingConfirmation)
+    } else {
+        None
+    }
+}
+
+/// Porta do loop de ordens do tick: sinal de ordem suprimido vira notificação e não
+/// chega à exchange. true = segue para a ordem
+pub fn admit_signal(strategy: &StrategyItem, suppressed: Option<&SuppressedReason>, signal: &mut StrategySignal, price: f64) -> bool {
+    match suppressed {
+        Some(reason) if signal.signal_type.places_order() => {
+            log::info!("🔕 [{}] {:?}: {} signal at {:.4} not executed", strategy.strategy_id, reason, signal.signal_type, price);
+            mark_suppressed(signal, reason);
+            false
+        }
+        _ => true,
+    }
+}
+
+/// Caminho único das ações suprimidas: o sinal segue como notificação (não `Info`,
+/// então passa pelo throttle normal) descrevendo a ação e o motivo
+pub fn mark_suppressed(signal: &mut StrategySignal, reason: &SuppressedReason) {
+    signal.acted = false;
+    signal.message = format!("{} 🔕 Ordem não enviada: {}.", signal.message, reason.describe());
+}
+
+/// Padrão de `require_confirmation` na criação (`STRATEGY_REQUIRE_CONFIRMATION`)
+pub fn require_confirmation_default() -> bool {
+    std::env::var("STRATEGY_REQUIRE_CONFIRMATION")
+        .map(|v| v.to_lowercase() == "true" || v == "1")
+        .unwrap_or(false)
+}
+
+/// Estratégia nova começa em paper (`awaiting_live_confirmation`)? Usuário com
+/// `paper_trading_default` ligado sempre começa em paper — ir para live é só pelo
+/// `confirm-live`, nunca pelo request. Fora isso vale o `require_confirmation` do
+/// request, depois a preferência do usuário e por fim o padrão global do env.
+/// `settings` None (falha ao carregar) também cai em paper.
+pub fn new_strategy_paper_mode(requested: Option<bool>, settings: Option<&user_settings_service::UserSettings>, global_default: bool) -> bool {
+    match settings {
+        None => true,
+        Some(settings) if settings.paper_trading_default == Some(true) => true,
+        Some(settings) => requested.or(settings.paper_trading_default).unwrap_or(global_default),
+    }
+}
+
+async fn set_position(db: &MongoDB, user_id: &str, strategy_id: &str, position: &PositionInfo) -> Result<(), String> {
+    let collection = db.collection::<UserStrategies>(COLLECTION);
+    let position = mongodb::bson::to_bson(position).map_err(|e| format!("Serialize position failed: {}", e))?;
+    collection.update_one(
+        doc! { "user_id": user_id },
+        doc! { "$set": { "strategies.$[elem].position": position } },
+    )
+        .array_filters(vec![doc! { "elem.strategy_id": strategy_id }]).await
+        .map_err(|e| format!("Update position failed: {}", e))?;
+    Ok(())
+}
+
+async fn set_alert_only(db: &MongoDB, user_id: &str, strategy_id: &str, alert_only: bool) -> Result<(), String> {
+    let collection = db.collection::<UserStrategies>(COLLECTION);
+    collection.update_one(
+        doc! { "user_id": user_id },
+        doc! { "$set": { "strategies.$[elem].alert_only": alert_only } },
+    )
+        .array_filters(vec![doc! { "elem.strategy_id": strategy_id }]).await
+        .map_err(|e| format!("Update alert_only failed: {}", e))?;
+    Ok(())
+}
+
+/// Soma uma compra à posição com custo base incluindo a taxa: `total_cost` leva
+/// `exec.fee` e `entry_price` vira custo total / quantidade (PnL de saída realista)
+pub fn apply_buy_to_position(position: Option<PositionInfo>, exec: &StrategyExecution, price: f64, now: i64) -> PositionInfo {
+    let buy_cost = exec.price * exec.amount + exec.fee;
+    match position {
+        Some(mut pos) => {
+            let old_cost = pos.entry_price * pos.quantity;
+            let new_qty = pos.quantity + exec.amount;
+            if new_qty > 0.0 {
+                pos.entry_price = (old_cost + buy_cost) / new_qty;
+                pos.quantity = new_qty;
+                pos.total_cost = old_cost + buy_cost;
+            }
+            pos.current_price = price;
+            if price > pos.highest_price { pos.highest_price = price; }
+            pos
+        }
+        None => {
+            let entry_price = if exec.amount > 0.0 { buy_cost / exec.amount } else { exec.price };
+            PositionInfo {
+                entry_price, quantity: exec.amount, total_cost: buy_cost,
+                current_price: price, unrealized_pnl: 0.0, unrealized_pnl_percent: 0.0,
+                highest_price: price, opened_at: now,
+            }
+        }
+    }
+}
+
+/// PnL realizado de uma venda sobre o custo base (já com a taxa de compra), menos a taxa da venda
+pub fn sell_pnl_usd(entry_price: f64, sell_price: f64, filled: f64, sell_fee: f64) -> f64 {
+    (sell_price - entry_price) * filled - sell_fee
+}
+
+/// Sequência de perdas após as vendas do tick: venda com PnL negativo soma,
+/// venda lucrativa zera, PnL zero não mexe
+pub fn update_loss_streak(current: u32, executions: &[StrategyExecution]) -> u32 {
+    executions.iter()
+        .filter(|e| e.action == ExecutionAction::Sell)
+        .fold(current, |streak, e| {
+            if e.pnl_usd < 0.0 {
+                streak + 1
+            } else if e.pnl_usd > 0.0 {
+                0
+            } else {
+                streak
+            }
+        })
+}
+
+/// Sinal de pausa quando a sequência atinge `config.max_consecutive_losses`
+pub fn loss_streak_breaker(strategy: &StrategyItem, streak: u32, price: f64, now: i64) -> Option<StrategySignal> {
+    let limit = strategy.config.max_consecutive_losses.filter(|n| *n > 0)?;
+    if streak < limit {
+        return None;
+    }
+    Some(StrategySignal {
+        signal_type: SignalType::LossStreak,
+        price,
+        message: format!(
+            "⏸️ {} vendas seguidas com prejuízo (limite {}). Estratégia pausada — revise a configuração antes de reativar.",
+            streak, limit
+        ),
+        acted: false,
+        price_change_percent: 0.0,
+        created_at: now,
+    })
+}
+
+pub async fn persist_tick_result(
+    db: &MongoDB, user_id: &str, strategy: &StrategyItem, result: &TickResult, manual: bool,
+) -> Result<(), String> {
+    let collection = db.collection::<UserStrategies>(COLLECTION);
+    let now = chrono::Utc::now().timestamp();
+    let p = "strategies.$[elem]";
+
+    let mut update_set = doc! {
+        format!("{}.last_checked_at", p): now,
+        format!("{}.updated_at", p): now,
+        "updated_at": now,
+    };
+
+    if result.price > 0.0 {
+        update_set.insert(format!("{}.last_price", p), result.price);
+    }
+
+    if let Some(ref new_status) = result.new_status {
+        update_set.insert(format!("{}.status", p), mongodb::bson::to_bson(new_status).unwrap_or_default());
+        match new_status {
+            StrategyStatus::Completed | StrategyStatus::StoppedOut
+            | StrategyStatus::Expired | StrategyStatus::Error | StrategyStatus::Paused => {
+                update_set.insert(format!("{}.is_active", p), false);
+                update_set.insert(format!("{}.pending_buy_usd", p), 0.0);
+                update_set.insert(format!("{}.pending_buy_window", p), false);
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(ref error) = result.error {
+        update_set.insert(format!("{}.error_message", p), error.as_str());
+    } else {
+        update_set.insert(format!("{}.error_message", p), mongodb::bson::Bson::Null);
+    }
+
+    let mut current_position = strategy.position.clone();
+    let mut accumulated_pnl: f64 = 0.0;
+    let mut gradual_lot_indices_executed: Vec<usize> = Vec::new();
+    let mut had_gradual_sell = false;
+
+    for exec in &result.executions {
+        match exec.action {
+            ExecutionAction::Buy => {
+                current_position = Some(apply_buy_to_position(current_position.take(), exec, result.price, now));
+            }
+            ExecutionAction::Sell => {
+                accumulated_pnl += exec.pnl_usd;
+                if let Some(ref mut pos) = current_position {
+                    pos.quantity -= exec.amount;
+                    if pos.quantity > 0.0001 {
+                        pos.total_cost = pos.entry_price * pos.quantity;
+                        pos.current_price = result.price;
+                    }
+                }
+                if exec.reason.contains("gradual") || exec.reason == "take_profit" {
+                    had_gradual_sell = true;
+                    for (i, lot) in strategy.config.gradual_lots.iter().enumerate() {
+                        if !lot.executed && !gradual_lot_indices_executed.contains(&i) {
+                            gradual_lot_indices_executed.push(i);
+                            break;
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if had_gradual_sell {
+        update_set.insert(format!("{}.last_gradual_sell_at", p), now);
+    }
+
+    let position_closed = current_position.as_ref().map(|p| p.quantity <= 0.0001).unwrap_or(false);
+
+    if let (Some(grid), Some(mut state)) = (&strategy.config.grid, active_grid_state(strategy)) {
+        let fee_percent = fee_service::cached_taker_percent(user_id, &strategy.exchange_id, &strategy.symbol)
+            .unwrap_or(strategy.config.fee_percent);
+        apply_grid_state_executions(&mut state, grid.fee_buffer(fee_percent), &result.executions, now);
+        if position_closed || current_position.is_none() {
+            state.clear_fills();
+        }
+        if let Ok(state_bson) = mongodb::bson::to_bson(&state) {
+            update_set.insert(format!("{}.grid_state", p), state_bson);
+        }
+    }
+    if position_closed {
+        update_set.insert(format!("{}.position", p), mongodb::bson::Bson::Null);
+    } else if let Some(ref pos) = current_position {
+        if let Ok(pos_bson) = mongodb::bson::to_bson(pos) {
+            update_set.insert(format!("{}.position", p), pos_bson);
+        }
+    } else if result.price > 0.0 {
+        if let Some(ref position) = strategy.position {
+            if result.price > position.highest_price {
+                update_set.insert(format!("{}.position.highest_price", p), result.price);
+            }
+            update_set.insert(format!("{}.position.current_price", p), result.price);
+            if position.entry_price > 0.0 {
+                let unrealized_pnl = (result.price - position.entry_price) * position.quantity;
+                let unrealized_pnl_pct = ((result.price - position.entry_price) / position.entry_price) * 100.0;
+                update_set.insert(format!("{}.position.unrealized_pnl", p), unrealized_pnl);
+                update_set.insert(format!("{}.position.unrealized_pnl_percent", p), unrealized_pnl_pct);
+            }
+        }
+    }
+
+    let mut update_inc = doc! {};
+    if accumulated_pnl.abs() > 0.0001 {
+        update_inc.insert(format!("{}.total_pnl_usd", p), accumulated_pnl);
+    }
+    let new_exec_count = result.executions.iter()
+        .filter(|e| !matches!(e.action, ExecutionAction::BuyFailed | ExecutionAction::SellFailed))
+        .count() as i32;
+    if new_exec_count > 0 {
+        update_inc.insert(format!("{}.total_executions", p), new_exec_count);
+    }
+
+    for idx in &gradual_lot_indices_executed {
+        update_set.insert(format!("{}.config.gradual_lots.{}.executed", p, idx), true);
+        update_set.insert(format!("{}.config.gradual_lots.{}.executed_at", p, idx), now);
+        update_set.insert(format!("{}.config.gradual_lots.{}.executed_price", p, idx), result.price);
+    }
+
+    // ── Circuit breaker de perdas seguidas ──────────────────────────
+    // Ao disparar, pausa e zera a sequência para que a reativação comece do zero
+    let mut loss_streak = update_loss_streak(strategy.consecutive_losses, &result.executions);
+    let already_stopped = matches!(result.new_status, Some(
+        StrategyStatus::Completed | StrategyStatus::StoppedOut | StrategyStatus::Expired
+        | StrategyStatus::Error | StrategyStatus::Paused
+    ));
+    let breaker_signal = if already_stopped {
+        None
+    } else {
+        loss_streak_breaker(strategy, loss_streak, result.price, now)
+    };
+    if let Some(ref signal) = breaker_signal {
+        log::warn!("⏸️ [{}] Loss streak breaker tripped after {} losing sells", strategy.strategy_id, loss_streak);
+        loss_streak = 0;
+        update_set.insert(format!("{}.status", p), mongodb::bson::to_bson(&StrategyStatus::Paused).unwrap_or_default());
+        update_set.insert(format!("{}.is_active", p), false);
+        update_set.insert(format!("{}.pending_buy_usd", p), 0.0);
+        update_set.insert(format!("{}.pending_buy_window", p), false);
+        update_set.insert(format!("{}.error_message", p), signal.message.as_str());
+    }
+    if loss_streak != strategy.consecutive_losses {
+        update_set.insert(format!("{}.consecutive_losses", p), loss_streak as i64);
+    }
+
+    let mut final_status = result.new_status.clone();
+    if breaker_signal.is_some() {
+        final_status = Some(StrategyStatus::Paused);
+    }
+    if !gradual_lot_indices_executed.is_empty() {
+        let all_executed = strategy.config.gradual_lots.iter().enumerate().all(|(i, lot)| {
+            lot.executed || gradual_lot_indices_executed.contains(&i)
+        });
+        if all_executed && position_closed {
+            final_status = Some(StrategyStatus::Completed);
+            update_set.insert(format!("{}.status", p), mongodb::bson::to_bson(&StrategyStatus::Completed).unwrap_or_default());
+            update_set.insert(format!("{}.is_active", p), false);
+        }
+    }
+
+    // ── Signals to persist (notifications) ──────────────────────────
+    // When automatic (monitor), only save actionable signals (TP, SL, GradualSell, Expired)
+    // to avoid inflating MongoDB with "monitoring..." info logs every 30s, and throttle
+    // repeated alerts of the same type. When manual (user clicked Tick), save ALL signals.
+    let signals_to_save: Vec<&StrategySignal> = if manual {
+        result.signals.iter().chain(breaker_signal.iter()).collect()
+    } else {
+        let actionable: Vec<&StrategySignal> = result.signals.iter()
+            .chain(breaker_signal.iter())
+            .filter(|s| !matches!(s.signal_type, SignalType::Info))
+            .collect();
+        let (kept, notified) = throttle_notifications(strategy, actionable, now);
+        for (signal_type, at) in notified {
+            update_set.insert(format!("{}.last_notified_at.{}", p, signal_type), at);
+        }
+        kept
+    };
+
+    let mut update_doc = doc! { "$set": update_set };
+    if !update_inc.is_empty() {
+        update_doc.insert("$inc", update_inc);
+    }
+
+    let array_filter = doc! { "elem.strategy_id": &strategy.strategy_id };
+
+    collection.update_one(
+        doc! { "user_id": user_id },
+        update_doc,
+    ).array_filters(vec![array_filter.clone()]).await
+        .map_err(|e| format!("Failed to persist tick: {}", e))?;
+
+    // ── Estratégias filhas: herança de posição quando o pai termina ─
+    if let Some(ref status) = final_status {
+        let remaining = if position_closed { None } else { current_position.as_ref() };
+        if let Err(e) = hand_off_to_children(db, user_id, strategy, status, remaining).await {
+            log::error!("❌ [{}] Child handoff failed: {}", strategy.strategy_id, e);
+        }
+    }
+
+    // ── Persist signals ─────────────────────────────────────────────
+    if !signals_to_save.is_empty() {
+        let signals_bson: Vec<mongodb::bson::Bson> = signals_to_save.iter()
+            .filter_map(|s| mongodb::bson::to_bson(s).ok()).collect();
+        if !signals_bson.is_empty() {
+            let _ = collection.update_one(
+                doc! { "user_id": user_id },
+                doc! { "$push": { format!("{}.signals", p): { "$each": signals_bson, "$slice": -100 } } },
+            ).array_filters(vec![array_filter.clone()]).await;
+        }
+
+        // 📨 Webhook do usuário recebe só os sinais acionáveis
+        let actionable: Vec<StrategySignal> = signals_to_save.iter()
+            .filter(|s| !matches!(s.signal_type, SignalType::Info))
+            .map(|s| (*s).clone())
+            .collect();
+        crate::services::user_settings_service::deliver_signals_to_webhook(
+            db, user_id, &strategy.strategy_id, &strategy.symbol, actionable,
+        );
+    }
+
+    if !result.executions.is_empty() {
+        let persisted = result.persisted_executions();
+        let execs_bson: Vec<mongodb::bson::Bson> = persisted.iter()
+            .filter_map(|e| mongodb::bson::to_bson(e).ok()).collect();
+        if !execs_bson.is_empty() {
+            let _ = collection.update_one(
+                doc! { "user_id": user_id },
+                doc! { "$push": { format!("{}.executions", p): { "$each": execs_bson } } },
+            ).array_filters(vec![array_filter]).await;
+        }
+
+        // 📡 Notifica assinantes SSE
+        crate::services::strategy_events::STRATEGY_EVENTS.publish_executions(
+            user_id, &strategy.strategy_id, &strategy.symbol, &persisted,
+        );
+    }
+
+    Ok(())
+}
+
+// ==================== ESTRATÉGIAS ENCADEADAS (PAI → FILHA) ====================
+// Uma filha (`parent_strategy_id`) não abre posição própria: espera o pai terminar.
+// Regras quando o pai muda de status:
+//   - Completed/Expired com posição aberta → a posição passa para a filha mais antiga
+//     que ainda aguarda (fica InPosition, ativa, prazo contando da herança) e o pai
+//     fica sem posição. Só uma filha herda; as demais continuam aguardando.
+//   - Completed/Expired sem posição, ou StoppedOut → filhas pausadas com o motivo.
+//   - Error/Paused → nada muda: a posição continua com o pai e as filhas aguardam
+//     até o pai ser corrigido e reativado.
+
+/// O que fazer com as filhas quando o pai muda para `parent_status`
+#[derive(Debug, Clone)]
+pub enum ParentHandoff {
+    Position(PositionInfo),
+    PauseChildren(String),
+    Wait,
+}
+
+pub fn parent_handoff(parent_status: &StrategyStatus, remaining: Option<&PositionInfo>) -> ParentHandoff {
+    match parent_status {
+        StrategyStatus::Completed | StrategyStatus::Expired => match remaining {
+            Some(position) if position.quantity > 0.0001 => ParentHandoff::Position(position.clone()),
+            _ => ParentHandoff::PauseChildren("Parent strategy finished without an open position".into()),
+        },
+        StrategyStatus::StoppedOut => ParentHandoff::PauseChildren("Parent strategy was stopped out".into()),
+        _ => ParentHandoff::Wait,
+    }
+}
+
+/// Filha assume a posição herdada (preço médio se já tiver alguma)
+pub fn inherit_position(child: &mut StrategyItem, inherited: PositionInfo, now: i64) {
+    let position = match child.position.take() {
+        Some(own) if own.quantity > 0.0 => {
+            let quantity = own.quantity + inherited.quantity;
+            let total_cost = own.entry_price * own.quantity + inherited.entry_price * inherited.quantity;
+            PositionInfo {
+                entry_price: total_cost / quantity, quantity, total_cost,
+                highest_price: own.highest_price.max(inherited.highest_price),
+                opened_at: own.opened_at.min(inherited.opened_at),
+                ..inherited
+            }
+        }
+        _ => inherited,
+    };
+    child.position = Some(position);
+    child.status = StrategyStatus::InPosition;
+    child.is_active = true;
+    child.started_at = now;
+    child.error_message = None;
+}
+
+/// Pai precisa existir, estar na mesma exchange/símbolo e ainda não ter terminado
+pub fn validate_parent(strategies: &[StrategyItem], parent_id: &str, exchange_id: &str, symbol: &str) -> Result<(), String> {
+    let parent = strategies.iter().find(|s| s.strategy_id == parent_id)
+        .ok_or_else(|| format!("Parent strategy {} not found", parent_id))?;
+    if parent.exchange_id != exchange_id || parent.symbol != symbol {
+        return Err("Parent strategy must use the same exchange and symbol".to_string());
+    }
+    if matches!(parent.status, StrategyStatus::Completed | StrategyStatus::StoppedOut | StrategyStatus::Expired) {
+        return Err(format!("Parent strategy already finished ({})", parent.status));
+    }
+    Ok(())
+}
+
+/// Configuração portável da estratégia, sem ids nem estado de execução
+pub fn export_strategy(strategy: &StrategyItem) -> StrategyExport {
+    StrategyExport {
+        version: STRATEGY_EXPORT_VERSION,
+        name: strategy.name.clone(),
+        config: strategy.config.portable(),
+    }
+}
+
+/// Converte um export em pedido de criação para o símbolo/exchange informados.
+/// As invariantes do `StrategyConfig` são checadas na criação, como num POST normal.
+pub fn import_request(import: ImportStrategyRequest) -> Result<CreateStrategyRequest, String> {
+    if import.strategy.version != STRATEGY_EXPORT_VERSION {
+        return Err(format!(
+            "Unsupported export version {} (expected {})",
+            import.strategy.version, STRATEGY_EXPORT_VERSION
+        ));
+    }
+    let name = import.name
+        .filter(|n| !n.trim().is_empty())
+        .unwrap_or(import.strategy.name);
+    Ok(CreateStrategyRequest {
+        name,
+        symbol: import.symbol,
+        exchange_id: import.exchange_id,
+        exchange_name: import.exchange_name,
+        config: import.strategy.config.portable(),
+        parent_strategy_id: None,
+        require_confirmation: None,
+    })
+}
+
+/// Update único que passa a posição para a filha e limpa a do pai, para que
+/// uma falha no meio não deixe a posição nos dois (ou em nenhum). O filtro exige
+/// que o pai ainda tenha a posição, então um repasse repetido não duplica nada.
+pub fn handoff_update(
+    user_id: &str, parent_id: &str, child: &StrategyItem, now: i64,
+) -> Result<(mongodb::bson::Document, mongodb::bson::Document, Vec<mongodb::bson::Document>), String> {
+    let position_bson = mongodb::bson::to_bson(&child.position)
+        .map_err(|e| format!("Failed to serialize position: {}", e))?;
+    let c = "strategies.$[child]";
+    let filter = doc! {
+        "user_id": user_id,
+        "strategies": { "$elemMatch": { "strategy_id": parent_id, "position": { "$ne": mongodb::bson::Bson::Null } } },
+    };
+    let update = doc! { "$set": {
+        format!("{}.position", c): position_bson,
+        format!("{}.status", c): mongodb::bson::to_bson(&child.status).unwrap_or_default(),
+        format!("{}.is_active", c): child.is_active,
+        format!("{}.started_at", c): child.started_at,
+        format!("{}.error_message", c): mongodb::bson::Bson::Null,
+        format!("{}.updated_at", c): now,
+        "strategies.$[parent].position": mongodb::bson::Bson::Null,
+        "strategies.$[parent].updated_at": now,
+    } };
+    let array_filters = vec![
+        doc! { "parent.strategy_id": parent_id },
+        doc! { "child.strategy_id": &child.strategy_id },
+    ];
+    Ok((filter, update, array_filters))
+}
+
+/// Aplica `parent_handoff` às filhas que aguardam o pai
+async fn hand_off_to_children(
+    db: &MongoDB, user_id: &str, parent: &StrategyItem, parent_status: &StrategyStatus, remaining: Option<&PositionInfo>,
+) -> Result<(), String> {
+    let handoff = parent_handoff(parent_status, remaining);
+    if matches!(handoff, ParentHandoff::Wait) {
+        return Ok(());
+    }
+
+    let collection = db.collection::<UserStrategies>(COLLECTION);
+    let user_doc = collection.find_one(doc! { "user_id": user_id }).await
+        .map_err(|e| format!("Failed to load strategies: {}", e))?;
+    let children: Vec<StrategyItem> = user_doc.map(|d| d.strategies).unwrap_or_default()
+        .into_iter()
+        .filter(|s| s.parent_strategy_id.as_deref() == Some(parent.strategy_id.as_str()) && s.waiting_for_parent())
+        .collect();
+    let Some(first_child) = children.iter().min_by_key(|s| s.created_at) else {
+        return Ok(());
+    };
+
+    let now = chrono::Utc::now().timestamp();
+    let p = "strategies.$[elem]";
+    match handoff {
+        ParentHandoff::Position(position) => {
+            let mut child = first_child.clone();
+            inherit_position(&mut child, position, now);
+            let (filter, update, array_filters) = handoff_update(user_id, &parent.strategy_id, &child, now)?;
+            let result = collection.update_one(filter, update).array_filters(array_filters).await
+                .map_err(|e| format!("Failed to hand off position: {}", e))?;
+            if result.modified_count == 0 {
+                log::warn!("⛓️ [{}] Position already handed off, skipping child {}", parent.strategy_id, child.strategy_id);
+                return Ok(());
+            }
+
+            log::info!("⛓️ [{}] Position handed off to child {}", parent.strategy_id, child.strategy_id);
+        }
+        ParentHandoff::PauseChildren(reason) => {
+            for child in &children {
+                collection.update_one(
+                    doc! { "user_id": user_id },
+                    doc! { "$set": {
+                        format!("{}.status", p): mongodb::bson::to_bson(&StrategyStatus::Paused).unwrap_or_default(),
+                        format!("{}.is_active", p): false,
+                        format!("{}.error_message", p): reason.as_str(),
+                        format!("{}.updated_at", p): now,
+                    } },
+                ).array_filters(vec![doc! { "elem.strategy_id": &child.strategy_id }]).await
+                    .map_err(|e| format!("Failed to pause child strategy: {}", e))?;
+            }
+            log::info!("⛓️ [{}] {} child strategies paused: {}", parent.strategy_id, children.len(), reason);
+        }
+        ParentHandoff::Wait => {}
+    }
+    Ok(())
+}
+
+/// Estado do grid em uso: o persistido, ou um novo — calculado a partir do `base_price`
+/// com layout (quando ainda não existe ou o layout mudou sem níveis comprados), ou
+/// vazio só de pareamento sem layout
+pub fn active_grid_state(strategy: &StrategyItem) -> Option<GridState> {
+    let grid = strategy.config.grid.as_ref()?;
+    let center = strategy.config.base_price;
+    match &strategy.grid_state {
+        Some(state) if state.has_fills() => Some(state.clone()),
+        Some(state) if grid.has_layout() && state.matches(center, grid) => Some(state.clone()),
+        Some(state) if !grid.has_layout() && !state.has_layout() => Some(state.clone()),
+        _ if grid.has_layout() => (center > 0.0).then(|| GridState::build(center, grid.levels, grid.spacing_percent)),
+        _ => Some(GridState::pairing()),
+    }
+}
+
+fn grid_level_reason(level: u32) -> String {
+    format!("grid_level_{}", level)
+}
+
+/// Nível de uma execução do grid (`grid_level_3`, `grid_level_3_buy_failed: ...`)
+fn grid_level_of(reason: &str) -> Option<u32> {
+    let rest = reason.strip_prefix("grid_level_")?;
+    let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+    rest[..digits].parse().ok()
+}
+
+/// Backoff (s) da recompra de um nível após falha (`GRID_BUY_RETRY_BACKOFF_SECS`,
+/// padrão 60), dobrando a cada falha seguida até `GRID_BUY_RETRY_MAX_SECS` (padrão 3600)
+fn grid_buy_retry_backoff() -> (i64, i64) {
+    let env = |key: &str, default: i64| std::env::var(key).ok().and_then(|v| v.parse::<i64>().ok()).unwrap_or(default).max(0);
+    let base = env("GRID_BUY_RETRY_BACKOFF_SECS", DEFAULT_GRID_BUY_RETRY_BACKOFF_SECS);
+    (base, env("GRID_BUY_RETRY_MAX_SECS", DEFAULT_GRID_BUY_RETRY_MAX_SECS).max(base))
+}
+
+/// Aplica as execuções do tick ao grid: compra de nível preenche o nível, venda de
+/// nível libera o que foi vendido, compra de nível que falhou entra em backoff.
+/// Compras fora dos níveis (sem layout) abrem um nível; vendas fora dos níveis (TP,
+/// lote gradual, stop) consomem as compras abertas da mais recente para a mais antiga.
+pub fn apply_grid_state_executions(state: &mut GridState, fee_buffer: f64, executions: &[StrategyExecution], now: i64) {
+    for exec in executions {
+        match (grid_level_of(&exec.reason), &exec.action) {
+            (Some(level), ExecutionAction::Buy) => state.fill_buy(level, exec.price, exec.amount, fee_buffer, now),
+            (Some(level), ExecutionAction::Sell) => {
+                state.fill_sell(level, exec.amount);
+            }
+            (Some(level), ExecutionAction::BuyFailed) => {
+                let (base, max) = grid_buy_retry_backoff();
+                state.defer_buy(level, now, base, max);
+            }
+            (None, ExecutionAction::Buy) if !state.has_layout() => state.fill_buy(0, exec.price, exec.amount, fee_buffer, now),
+            (None, ExecutionAction::Sell) => state.take_sells(exec.amount),
+            _ => {}
+        }
+    }
+}
+
+/// Throttle/dedup de notificações: descarta sinais não executados idênticos ao último
+/// do mesmo tipo, ou do mesmo tipo dentro de `notification_throttle_secs`.
+/// Retorna os sinais a notificar e os novos `last_notified_at` por tipo.
+pub fn throttle_notifications<'a>(
+    strategy: &StrategyItem, signals: Vec<&'a StrategySignal>, now: i64,
+) -> (Vec<&'a StrategySignal>, HashMap<String, i64>) {
+    let window = strategy.config.notification_throttle_secs.unwrap_or(DEFAULT_NOTIFICATION_THROTTLE_SECS);
+    let mut last_notified = strategy.last_notified_at.clone();
+    let mut notified: HashMap<String, i64> = HashMap::new();
+    let mut kept = Vec::new();
+
+    for signal in signals {
+        let key = signal.signal_type.to_string();
+
+        // Sinais que executaram ordem sempre são registrados
+        if !signal.acted {
+            let previous = strategy.signals.iter().rev().find(|s| s.signal_type == signal.signal_type);
+            if previous.is_some_and(|p| p.message == signal.message) {
+                continue;
+            }
+            if window > 0 && last_notified.get(&key).is_some_and(|at| now - at < window) {
+                continue;
+            }
+        }
+
+        last_notified.insert(key.clone(), now);
+        notified.insert(key, now);
+        kept.push(signal);
+    }
+
+    (kept, notified)
+}
+
+/// Resultado da checagem de ativação (espelha o filtro de `activation_filter`)
+#[derive(Debug, Clone, PartialEq)]
+pub enum ActivationCheck {
+    Activate,
+    /// Já ativa e rodando: ativar de novo não muda nada
+    AlreadyActive,
+    Reject(String),
+}
+
+/// Se a estratégia pode ser ativada. Estratégias em erro só com `force` (que limpa o erro).
+pub fn check_activation(strategy: &StrategyItem, force: bool) -> ActivationCheck {
+    use StrategyStatus::*;
+    if strategy.is_active && matches!(strategy.status, Idle | Monitoring | InPosition | GradualSelling) {
+        return ActivationCheck::AlreadyActive;
+    }
+    match strategy.status {
+        Completed | StoppedOut | Expired => {
+            return ActivationCheck::Reject(format!(
+                "Cannot activate strategy '{}' — it is in terminal state '{}'. Create a new strategy instead.",
+                strategy.name, strategy.status
+            ));
+        }
+        Error if !force => {
+            return ActivationCheck::Reject(format!(
+                "Strategy '{}' stopped with an error: {}. Review it and activate with force=true to clear the error.",
+                strategy.name, strategy.error_message.as_deref().unwrap_or("unknown error")
+            ));
+        }
+        Idle | Monitoring | Paused | Error => {}
+        InPosition | GradualSelling => {
+            return ActivationCheck::Reject(format!("Cannot activate strategy '{}' from status '{}'.", strategy.name, strategy.status));
+        }
+    }
+    if strategy.config.base_price <= 0.0 {
+        return ActivationCheck::Reject("Cannot activate: base price is 0 or invalid. Update the strategy configuration first.".to_string());
+    }
+    ActivationCheck::Activate
+}
+
+/// Filtro do `find_one_and_update` da ativação: só casa se a estratégia ainda estiver
+/// num estado ativável no momento da escrita (mesmas regras de `check_activation`)
+fn activation_filter(user_id: &str, strategy_id: &str, force: bool) -> mongodb::bson::Document {
+    let mut statuses = vec!["idle", "monitoring", "paused"];
+    if force {
+        statuses.push("error");
+    }
+    doc! {
+        "user_id": user_id,
+        "strategies": { "$elemMatch": {
+            "strategy_id": strategy_id,
+            "is_active": false,
+            "status": { "$in": statuses },
+            "config.base_price": { "$gt": 0.0 },
+        } },
+    }
+}
+
+/// Ativa a estratégia numa única escrita atômica. Ativar uma estratégia já ativa é no-op.
+pub async fn activate_strategy(db: &MongoDB, strategy_id: &str, user_id: &str, force: bool) -> Result<StrategyItem, String> {
+    let collection = db.collection::<UserStrategies>(COLLECTION);
+    let now = chrono::Utc::now().timestamp();
+    let p = "strategies.$[elem]";
+
+    let updated = collection.find_one_and_update(
+        activation_filter(user_id, strategy_id, force),
+        doc! { "$set": {
+            format!("{}.status", p): "monitoring",
+            format!("{}.is_active", p): true,
+            format!("{}.error_message", p): mongodb::bson::Bson::Null,
+            format!("{}.exchange_pause", p): mongodb::bson::Bson::Null,
+            format!("{}.updated_at", p): now,
+            "updated_at": now,
+        }},
+    )
+        .array_filters(vec![doc! { "elem.strategy_id": strategy_id }])
+        .return_document(mongodb::options::ReturnDocument::After)
+        .await
+        .map_err(|e| format!("Failed to activate strategy: {}", e))?;
+
+    if let Some(user_doc) = updated {
+        let strategy = user_doc.strategies.into_iter()
+            .find(|s| s.strategy_id == strategy_id)
+            .ok_or_else(|| "Strategy activated but not found in response.".to_string())?;
+        log::info!("▶️ Activated strategy '{}' ({}) for user {}", strategy.name, strategy_id, user_id);
+        return Ok(strategy);
+    }
+
+    // ── Nada casou: explica o motivo com o estado atual ─────────────
+    let user_doc = collection.find_one(doc! { "user_id": user_id }).await
+        .map_err(|e| format!("Failed to access database: {}", e))?
+        .ok_or_else(|| "No strategies found for your account.".to_string())?;
+    let strategy = user_doc.strategies.into_iter()
+        .find(|s| s.strategy_id == strategy_id)
+        .ok_or_else(|| "Strategy not found. It may have been deleted.".to_string())?;
+
+    match check_activation(&strategy, force) {
+        ActivationCheck::AlreadyActive => Ok(strategy),
+        ActivationCheck::Reject(msg) => Err(msg),
+        ActivationCheck::Activate => Err(format!("Strategy '{}' changed while activating. Try again.", strategy.name)),
+    }
+}
+
+/// Confirma a operação real da estratégia. Confirmar de novo é no-op.
+pub async fn confirm_live_strategy(db: &MongoDB, strategy_id: &str, user_id: &str) -> Result<StrategyItem, String> {
+    let collection = db.collection::<UserStrategies>(COLLECTION);
+    let now = chrono::Utc::now().timestamp();
+    let p = "strategies.$[elem]";
+
+    let updated = collection.find_one_and_update(
+        doc! { "user_id": user_id, "strategies.strategy_id": strategy_id },
+        doc! { "$set": {
+            format!("{}.awaiting_live_confirmation", p): false,
+            format!("{}.updated_at", p): now,
+            "updated_at": now,
+        }},
+    )
+        .array_filters(vec![doc! { "elem.strategy_id": strategy_id }])
+        .return_document(mongodb::options::ReturnDocument::After)
+        .await
+        .map_err(|e| format!("Failed to confirm strategy: {}", e))?
+        .ok_or_else(|| "Strategy not found. It may have been deleted.".to_string())?;
+
+    let strategy = updated.strategies.into_iter()
+        .find(|s| s.strategy_id == strategy_id)
+        .ok_or_else(|| "Strategy confirmed but not found in response.".to_string())?;
+    log::warn!("🛡️ Strategy '{}' ({}) confirmed for live trading by user {}", strategy.name, strategy_id, user_id);
+    Ok(strategy)
+}
+
+/// Filtro do `find_one_and_update` da pausa: só casa com a estratégia ainda ativa e
+/// fora de pausa/estado terminal (par de `activation_filter`, que exige `is_active: false`)
+fn pause_filter(user_id: &str, strategy_id: &str) -> mongodb::bson::Document {
+    doc! {
+        "user_id": user_id,
+        "strategies": { "$elemMatch": {
+            "strategy_id": strategy_id,
+            "is_active": true,
+            "status": { "$nin": ["paused", "completed", "stopped_out", "expired"] },
+        } },
+    }
+}
+
+pub async fn pause_strategy(db: &MongoDB, strategy_id: &str, user_id: &str) -> Result<StrategyItem, String> {
+    let collection = db.collection::<UserStrategies>(COLLECTION);
+
+    // ── Pre-check ───────────────────────────────────────────────────
+    let user_doc = collection.find_one(doc! { "user_id": user_id }).await
+        .map_err(|e| format!("Failed to access database: {}", e))?
+        .ok_or_else(|| "No strategies found for your account.".to_string())?;
+
+    let strategy = user_doc.strategies.iter()
+        .find(|s| s.strategy_id == strategy_id)
+        .ok_or_else(|| "Strategy not found. It may have been deleted.".to_string())?;
+
+    if !strategy.is_active || strategy.status == StrategyStatus::Paused {
+        return Err(format!("Strategy '{}' is already paused.", strategy.name));
+    }
+
+    match strategy.status {
+        StrategyStatus::Completed | StrategyStatus::StoppedOut | StrategyStatus::Expired => {
+            return Err(format!(
+                "Cannot pause strategy '{}' — it is already in terminal state '{}'. Create a new strategy instead.",
+                strategy.name, strategy.status
+            ));
+        }
+        _ => {}
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    let p = "strategies.$[elem]";
+
+    // ── Cancel live exchange orders before changing state ───────────
+    let cancelled = cancel_strategy_open_orders(db, user_id, strategy, "cancel_on_pause").await;
+    if !strategy.open_orders.is_empty() {
+        let remaining_bson = mongodb::bson::to_bson(&cancelled.remaining).unwrap_or_default();
+        let mut update = doc! { "$set": { format!("{}.open_orders", p): remaining_bson } };
+        if !cancelled.executions.is_empty() {
+            let execs_bson: Vec<mongodb::bson::Bson> = cancelled.executions.iter()
+                .filter_map(|e| mongodb::bson::to_bson(e).ok()).collect();
+            update.insert("$push", doc! { format!("{}.executions", p): { "$each": execs_bson } });
+        }
+        collection.update_one(doc! { "user_id": user_id }, update)
+            .array_filters(vec![doc! { "elem.strategy_id": strategy_id }]).await
+            .map_err(|e| format!("Failed to record order cancellations: {}", e))?;
+    }
+    if !cancelled.errors.is_empty() {
+        return Err(format!(
+            "Strategy '{}' was not paused because open orders could not be cancelled: {}",
+            strategy.name, cancelled.errors.join("; ")
+        ));
+    }
+
+    log::info!("⏸️ Pausing strategy '{}' ({}) for user {}", strategy.name, strategy_id, user_id);
+
+    write_pause(db, user_id, strategy_id, now, doc! {}).await?
+        .ok_or_else(|| format!("Strategy '{}' changed while pausing. Refresh and try again.", strategy.name))
+}
+
+/// Escrita condicional da pausa (ver `pause_filter`) com campos extras no mesmo `$set`
+/// (ex.: `exchange_pause`). Ok(None) se uma ativação/tick mudou o status no meio.
+async fn write_pause(
+    db: &MongoDB, user_id: &str, strategy_id: &str, now: i64, extra_set: mongodb::bson::Document,
+) -> Result<Option<StrategyItem>, String> {
+    let p = "strategies.$[elem]";
+    let mut update_set = doc! {
+        format!("{}.status", p): "paused",
+        format!("{}.is_active", p): false,
+        format!("{}.pending_buy_usd", p): 0.0,
+        format!("{}.pending_buy_window", p): false,
+        format!("{}.updated_at", p): now,
+        "updated_at": now,
+    };
+    for (key, value) in extra_set {
+        update_set.insert(format!("{}.{}", p, key), value);
+    }
+
+    let user_doc = db.collection::<UserStrategies>(COLLECTION).find_one_and_update(
+        pause_filter(user_id, strategy_id),
+        doc! { "$set": update_set },
+    )
+        .array_filters(vec![doc! { "elem.strategy_id": strategy_id }])
+        .return_document(mongodb::options::ReturnDocument::After)
+        .await
+        .map_err(|e| format!("Failed to pause strategy: {}", e))?;
+
+    Ok(user_doc.and_then(|doc| doc.strategies.into_iter().find(|s| s.strategy_id == strategy_id)))
+}
+
+// ==================== PAUSA POR EXCHANGE (ADMIN) ====================
+// Queda/degradação de uma exchange: pausa só as estratégias dela marcando
+// `exchange_pause` com o status anterior. Diferente da pausa do usuário, as ordens
+// abertas ficam na exchange (cancelar falharia com ela fora, e stops/TPs colocados
+// lá seguem protegendo a posição). O resume só reativa as que foram pausadas por
+// esse mecanismo e seguem pausadas, e recoloca a proteção na exchange que tenha
+// sumido no meio (ex.: cancelada pela exchange durante a manutenção).
+
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct ExchangePauseSummary {
+    pub ccxt_id: String,
+    pub strategy_ids: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
+}
+
+/// Estratégia ativa (não terminal) na exchange `exchange_id`
+pub fn pausable_on_exchange(strategy: &StrategyItem, exchange_id: &str) -> bool {
+    strategy.exchange_id == exchange_id && strategy.is_active && !matches!(strategy.status,
+        StrategyStatus::Paused | StrategyStatus::Completed | StrategyStatus::StoppedOut
+        | StrategyStatus::Expired | StrategyStatus::Error)
+}
+
+pub fn exchange_paused_message(ccxt_id: &str, reason: &str) -> String {
+    format!("Paused: exchange {} unavailable ({}). It will resume when the exchange recovers.", ccxt_id, reason)
+}
+
+/// Marca a estratégia como pausada pela exchange (campos gravados por `pause_exchange_strategies`)
+pub fn apply_exchange_pause(strategy: &mut StrategyItem, pause: ExchangePause) {
+    strategy.status = StrategyStatus::Paused;
+    strategy.is_active = false;
+    strategy.error_message = Some(exchange_paused_message(&pause.ccxt_id, &pause.reason));
+    strategy.exchange_pause = Some(pause);
+}
+
+/// Restaura o status anterior se a estratégia foi pausada pela exchange `ccxt_id`
+/// e ninguém mexeu nela desde então. Retorna true se restaurou.
+pub fn restore_exchange_pause(strategy: &mut StrategyItem, ccxt_id: &str) -> bool {
+    let Some(pause) = strategy.exchange_pause.as_ref().filter(|p| p.ccxt_id == ccxt_id) else {
+        return false;
+    };
+    if strategy.status != StrategyStatus::Paused || strategy.is_active {
+        return false;
+    }
+    strategy.status = pause.previous_status.clone();
+    strategy.is_active = true;
+    strategy.error_message = None;
+    strategy.exchange_pause = None;
+    true
+}
+
+async fn catalog_exchange_id(db: &MongoDB, ccxt_id: &str) -> Result<String, String> {
+    db.collection::<ExchangeCatalog>("exchanges")
+        .find_one(doc! { "ccxt_id": ccxt_id })
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .and_then(|catalog| catalog._id)
+        .map(|id| id.to_hex())
+        .ok_or_else(|| format!("Exchange '{}' not found in catalog", ccxt_id))
+}
+
+/// Pausa as estratégias ativas de todos os usuários na exchange `ccxt_id`
+pub async fn pause_exchange_strategies(db: &MongoDB, ccxt_id: &str, reason: &str) -> Result<ExchangePauseSummary, String> {
+    let exchange_id = catalog_exchange_id(db, ccxt_id).await?;
+    let collection = db.collection::<UserStrategies>(COLLECTION);
+    let mut cursor = collection
+        .find(doc! { "strategies": { "$elemMatch": { "exchange_id": &exchange_id, "is_active": true } } })
+        .await
+        .map_err(|e| format!("Failed to query: {}", e))?;
+
+    use futures::stream::StreamExt;
+    let now = chrono::Utc::now().timestamp();
+    let mut summary = ExchangePauseSummary { ccxt_id: ccxt_id.to_string(), ..Default::default() };
+    while let Some(user_doc) = cursor.next().await {
+        let user_doc = user_doc.map_err(|e| format!("Error reading user_strategy: {}", e))?;
+        for strategy in user_doc.strategies.iter().filter(|s| pausable_on_exchange(s, &exchange_id)) {
+            let pause = ExchangePause {
+                ccxt_id: ccxt_id.to_string(),
+                previous_status: strategy.status.clone(),
+                reason: reason.to_string(),
+                paused_at: now,
+            };
+            let mut paused = strategy.clone();
+            apply_exchange_pause(&mut paused, pause);
+            let extra = doc! {
+                "error_message": paused.error_message,
+                "exchange_pause": mongodb::bson::to_bson(&paused.exchange_pause).map_err(|e| e.to_string())?,
+            };
+            match write_pause(db, &user_doc.user_id, &strategy.strategy_id, now, extra).await {
+                Ok(Some(_)) => summary.strategy_ids.push(strategy.strategy_id.clone()),
+                Ok(None) => summary.errors.push(format!("{}: changed while pausing", strategy.strategy_id)),
+                Err(e) => summary.errors.push(format!("{}: {}", strategy.strategy_id, e)),
+            }
+        }
+    }
+
+    log::warn!("⏸️ Exchange {} paused: {} strategies ({} failed)", ccxt_id, summary.strategy_ids.len(), summary.errors.len());
+    Ok(summary)
+}
+
+/// Proteções na exchange (trailing nativo, vendas de take profit) configuradas para a
+/// estratégia em posição mas sem ordem rastreada. Devolve (trailing, take profits).
+pub fn missing_exchange_protection(strategy: &StrategyItem) -> (bool, bool) {
+    let in_position = matches!(strategy.status, StrategyStatus::InPosition | StrategyStatus::GradualSelling)
+        && strategy.position.as_ref().is_some_and(|p| p.quantity > 0.0);
+    if !in_position {
+        return (false, false);
+    }
+    let config = &strategy.config;
+    let trailing = config.use_exchange_trailing
+        && config.max_drawdown_percent.is_some_and(|p| p > 0.0)
+        && native_trailing_order(strategy).is_none();
+    let take_profits = config.exchange_take_profits
+        && take_profit_orders(strategy).is_empty()
+        && next_take_profit_level(config).is_ok();
+    (trailing, take_profits)
+}
+
+/// Recoloca na exchange a proteção que falta à estratégia reativada (ver
+/// `missing_exchange_protection`); falhas deixam o guard/TP server-side valendo
+async fn restore_exchange_protection(
+    db: &MongoDB, user_id: &str, exchange: &DecryptedExchange, strategy: &StrategyItem, now: i64,
+) {
+    let (trailing, take_profits) = missing_exchange_protection(strategy);
+    let quantity = strategy.position.as_ref().map(|p| p.quantity).unwrap_or(0.0);
+    if !places_live_orders(strategy, exchange) || quantity <= 0.0 {
+        return;
+    }
+    if let Some(percent) = strategy.config.max_drawdown_percent.filter(|_| trailing) {
+        match place_native_trailing(exchange, strategy.config.mode, &strategy.symbol, quantity, percent).await {
+            Ok(Some(order)) => {
+                log::info!("🪝 [{}] Native trailing stop restored after exchange pause: {}", strategy.strategy_id, order.order_id);
+                if let Err(e) = push_open_order(db, user_id, &strategy.strategy_id, &order).await {
+                    log::error!("❌ [{}] {}", strategy.strategy_id, e);
+                }
+            }
+            Ok(None) => {}
+            Err(e) => log::warn!("⚠️ [{}] Failed to restore native trailing stop, using server-side drawdown guard: {}",
+                strategy.strategy_id, e),
+        }
+    }
+    if take_profits {
+        if let Some(warning) = open_take_profit_orders(db, user_id, exchange, strategy, quantity, now).await {
+            log::warn!("⚠️ [{}] {}", strategy.strategy_id, warning);
+        }
+    }
+}
+
+/// Reativa (no status anterior) as estratégias pausadas por `pause_exchange_strategies`
+pub async fn resume_exchange_strategies(db: &MongoDB, ccxt_id: &str) -> Result<ExchangePauseSummary, String> {
+    let collection = db.collection::<UserStrategies>(COLLECTION);
+    let mut cursor = collection
+        .find(doc! { "strategies.exchange_pause.ccxt_id": ccxt_id })
+        .await
+        .map_err(|e| format!("Failed to query: {}", e))?;
+
+    use futures::stream::StreamExt;
+    let now = chrono::Utc::now().timestamp();
+    let p = "strategies.$[elem]";
+    let mut summary = ExchangePauseSummary { ccxt_id: ccxt_id.to_string(), ..Default::default() };
+    while let Some(user_doc) = cursor.next().await {
+        let user_doc = user_doc.map_err(|e| format!("Error reading user_strategy: {}", e))?;
+        let mut exchanges: Option<Vec<DecryptedExchange>> = None;
+        for mut strategy in user_doc.strategies {
+            if !restore_exchange_pause(&mut strategy, ccxt_id) {
+                continue;
+            }
+            // Condicional: não reativa se o usuário mexeu na estratégia depois da pausa
+            let result = collection.update_one(
+                doc! {
+                    "user_id": &user_doc.user_id,
+                    "strategies": { "$elemMatch": {
+                        "strategy_id": &strategy.strategy_id,
+                        "status": "paused",
+                        "exchange_pause.ccxt_id": ccxt_id,
+                    } },
+                },
+                doc! {
+                    "$set": {
+                        format!("{}.status", p): mongodb::bson::to_bson(&strategy.status).unwrap_or_default(),
+                        format!("{}.is_active", p): true,
+                        format!("{}.error_message", p): mongodb::bson::Bson::Null,
+                        format!("{}.updated_at", p): now,
+                        "updated_at": now,
+                    },
+                    "$unset": { format!("{}.exchange_pause", p): "" },
+                },
+            )
+                .array_filters(vec![doc! { "elem.strategy_id": &strategy.strategy_id }])
+                .await;
+            match result {
+                Ok(r) if r.modified_count > 0 => {}
+                Ok(_) => continue,
+                Err(e) => {
+                    summary.errors.push(format!("{}: {}", strategy.strategy_id, e));
+                    continue;
+                }
+            }
+
+            if missing_exchange_protection(&strategy) != (false, false) {
+                if exchanges.is_none() {
+                    exchanges = Some(user_exchanges_service::get_user_exchanges_decrypted(db, &user_doc.user_id).await
+                        .unwrap_or_default());
+                }
+                match exchanges.iter().flatten().find(|ex| ex.exchange_id == strategy.exchange_id) {
+                    Some(exchange) => restore_exchange_protection(db, &user_doc.user_id, exchange, &strategy, now).await,
+                    None => summary.errors.push(format!(
+                        "{}: resumed, but exchange {} is not available to restore its protective orders",
+                        strategy.strategy_id, strategy.exchange_name
+                    )),
+                }
+            }
+            summary.strategy_ids.push(strategy.strategy_id);
+        }
+    }
+
+    log::info!("▶️ Exchange {} resumed: {} strategies", ccxt_id, summary.strategy_ids.len());
+    Ok(summary)
+}
+
+/// Intervalo entre ticks conforme o estado: com posição usa `in_position_interval_secs`,
+/// sem posição `monitoring_interval_secs` (padrão 30s para ambos)
+pub fn check_interval_secs(strategy: &StrategyItem) -> i64 {
+    let configured = match strategy.status {
+        StrategyStatus::InPosition | StrategyStatus::GradualSelling => strategy.config.in_position_interval_secs,
+        _ => strategy.config.monitoring_interval_secs,
+    };
+    configured.filter(|v| *v > 0).unwrap_or(DEFAULT_CHECK_INTERVAL_SECS)
+}
+
+pub async fn process_active_strategies(db: &MongoDB) -> Result<ProcessResult, String> {
+    let collection = db.collection::<UserStrategies>(COLLECTION);
+    let now = chrono::Utc::now().timestamp();
+
+    let filter = doc! {
+        "strategies": {
+            "$elemMatch": {
+                "is_active": true,
+                "status": { "$in": ["idle", "monitoring", "in_position", "gradual_selling"] }
+            }
+        }
+    };
+
+    let mut cursor = collection.find(filter).await
+        .map_err(|e| format!("Failed to query: {}", e))?;
+
+    use futures::stream::StreamExt;
+    let mut total = 0;
+    let mut processed = 0;
+    let mut errors = 0;
+    let mut signals_generated = 0;
+    let mut orders_executed = 0;
+    let mut skipped_unhealthy = 0;
+
+    while let Some(result) = cursor.next().await {
+        match result {
+            Ok(user_doc) => {
+                let user_id = user_doc.user_id.clone();
+                let due: Vec<&StrategyItem> = user_doc.strategies.iter()
+                    .filter(|strategy| strategy.is_active && matches!(strategy.status,
+                        StrategyStatus::Idle | StrategyStatus::Monitoring
+                        | StrategyStatus::InPosition | StrategyStatus::GradualSelling))
+                    .inspect(|_| total += 1)
+                    .filter(|strategy| now - strategy.last_checked_at.unwrap_or(0) >= check_interval_secs(strategy))
+                    .collect();
+                let (healthy, skipped) = filter_healthy_exchanges(due, &EXCHANGE_BREAKER, now);
+                if skipped > 0 {
+                    log::warn!("🔌 Skipping {} strategies of user {} on exchanges with open circuit breaker", skipped, user_id);
+                    skipped_unhealthy += skipped;
+                }
+
+                for strategy in healthy {
+                    let tick_result = tick(db, &user_id, strategy).await;
+                    record_exchange_health(&EXCHANGE_BREAKER, strategy, &tick_result, now);
+                    signals_generated += tick_result.signals.len();
+                    orders_executed += tick_result.executions.len();
+
+                    match persist_tick_result(db, &user_id, strategy, &tick_result, false).await {
+                        Ok(_) => processed += 1,
+                        Err(e) => {
+                            log::error!("[Strategy {}] Persist failed: {}", tick_result.strategy_id, e);
+                            errors += 1;
+                        }
+                    }
+                    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+                }
+            }
+            Err(e) => {
+                log::error!("Error reading user_strategy: {}", e);
+                errors += 1;
+            }
+        }
+    }
+
+    Ok(ProcessResult { total, processed, errors, signals_generated, orders_executed, skipped_unhealthy })
+}
+
+/// Separa as estratégias cujas exchanges estão com o breaker aberto; retorna as
+/// que seguem para o tick e quantas foram puladas
+pub fn filter_healthy_exchanges<'a>(
+    strategies: Vec<&'a StrategyItem>, breaker: &ExchangeBreaker, now: i64,
+) -> (Vec<&'a StrategyItem>, usize) {
+    let before = strategies.len();
+    let healthy: Vec<&StrategyItem> = strategies.into_iter()
+        .filter(|s| !breaker.is_open(&s.exchange_id, now))
+        .collect();
+    let skipped = before - healthy.len();
+    (healthy, skipped)
+}
+
+/// Falha transitória (rede, timeout, manutenção) conta para o breaker da
+/// exchange; tick sem erro fecha. Outros erros (config, saldo) não mexem.
+fn record_exchange_health(breaker: &ExchangeBreaker, strategy: &StrategyItem, result: &TickResult, now: i64) {
+    match result.error.as_deref() {
+        None => breaker.record_success(&strategy.exchange_id),
+        Some(e) if credential_health_service::classify_ccxt_error(e) == credential_health_service::CcxtErrorKind::Transient => {
+            if breaker.record_failure(&strategy.exchange_id, now) {
+                log::warn!("🔌 Circuit breaker opened for exchange {} after repeated failures: {}", strategy.exchange_name, e);
+            }
+        }
+        Some(_) => {}
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ProcessResult {
+    pub total: usize,
+    pub processed: usize,
+    pub errors: usize,
+    pub signals_generated: usize,
+    pub orders_executed: usize,
+    /// Estratégias puladas por breaker aberto na exchange
+    pub skipped_unhealthy: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::GridConfig;
+    use std::sync::Mutex;
+
+    fn tracked(order_id: &str) -> TrackedOrder {
+        TrackedOrder {
+            order_id: order_id.into(), side: "sell".into(), order_type: "limit".into(),
+            amount: 0.5, price: Some(110.0), created_at: 0, ttl_secs: None, lot_number: None,
+        }
+    }
+
+    fn strategy_with_position(id: &str, quantity: f64, price: f64) -> StrategyItem {
+        StrategyItem {
+            strategy_id: id.into(), name: id.into(), symbol: "BTC/USDT".into(),
+            exchange_id: "ex".into(), exchange_name: "Binance".into(),
+            is_active: true, status: StrategyStatus::InPosition, config: Default::default(),
+            position: Some(PositionInfo {
+                entry_price: price, quantity, total_cost: price * quantity,
+                current_price: price, unrealized_pnl: 0.0, unrealized_pnl_percent: 0.0,
+                highest_price: price, opened_at: 0,
+            }),
+            open_orders: vec![], grid_state: None, executions: vec![], signals: vec![],
+            last_checked_at: None, last_price: None, last_gradual_sell_at: None, last_notified_at: Default::default(),
+            error_message: None, alert_only: false, awaiting_live_confirmation: false, parent_strategy_id: None, total_pnl_usd: 0.0, total_executions: 0, pending_buy_usd: 0.0, pending_buy_window: false, consecutive_losses: 0, exchange_pause: None,
+            started_at: 0, created_at: 0, updated_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_strategy_export_import_round_trip() {
+        let mut original = strategy_with_position("s1", 1.0, 100.0);
+        original.config.base_price = 100.0;
+        original.config.entry_condition = Some("price < 0.95 * high_24h".into());
+        original.config.gradual_sell = true;
+        original.config.gradual_lots = vec![crate::models::GradualLot {
+            lot_number: 1, sell_percent: 50.0, executed: true,
+            executed_at: Some(10), executed_price: Some(112.0), realized_pnl: Some(6.0),
+        }];
+        original.config.grid = Some(GridConfig { min_profit_percent: 0.2, levels: 4, spacing_percent: 1.0 });
+        original.grid_state = active_grid_state(&original);
+        original.grid_state.as_mut().unwrap().fill_buy(1, 99.0, 0.5, 0.01, 0);
+
+        let exported = serde_json::to_value(export_strategy(&original)).unwrap();
+        assert!(exported.get("strategy_id").is_none() && exported.get("exchange_id").is_none());
+
+        let import: ImportStrategyRequest = serde_json::from_value(serde_json::json!({
+            "strategy": exported, "symbol": "ETH/USDT", "exchange_id": "ex2", "exchange_name": "OKX",
+        })).unwrap();
+        let request = import_request(import).unwrap();
+        assert_eq!(request.name, "s1");
+        assert_eq!(request.symbol, "ETH/USDT");
+        assert!(request.config.validate().is_ok());
+        assert_eq!(
+            serde_json::to_value(&request.config).unwrap(),
+            serde_json::to_value(original.config.portable()).unwrap(),
+        );
+        // Estado de execução não viaja no export
+        assert!(!request.config.gradual_lots[0].executed);
+        assert!(exported.get("grid_state").is_none());
+
+        // Campos desconhecidos e invariantes quebradas são rejeitados
+        let mut unknown = serde_json::to_value(export_strategy(&original)).unwrap();
+        unknown["user_id"] = "u1".into();
+        assert!(serde_json::from_value::<StrategyExport>(unknown).is_err());
+        let mut unsafe_config = original.config.portable();
+        unsafe_config.stop_loss_percent = 0.0;
+        assert_eq!(unsafe_config.validate().unwrap_err().0, "config.stop_loss_percent");
+    }
+
+    fn exchange_order(order: &TrackedOrder, status: &str, filled: f64, average: f64) -> CcxtOrder {
+        CcxtOrder {
+            id: order.order_id.clone(), symbol: "BTC/USDT".into(), status: status.into(), side: order.side.clone(),
+            order_type: order.order_type.clone(), price: order.price, average: Some(average), amount: Some(order.amount),
+            filled: Some(filled), remaining: None, cost: None, fee: None, timestamp: None, datetime: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stuck_pending_order_cancelled_and_strategy_reverts() {
+        let now = 10_000;
+        let mut strategy = strategy_with_position("s1", 0.0, 0.0);
+        strategy.position = None;
+        strategy.status = StrategyStatus::Idle;
+        let stuck = TrackedOrder { side: "buy".into(), ..tracked("stuck") };
+        let fresh = TrackedOrder { created_at: now - 60, ..tracked("fresh") };
+        let trailing = TrackedOrder { order_type: NATIVE_TRAILING_ORDER_TYPE.into(), ..tracked("trail") };
+        strategy.open_orders = vec![stuck.clone(), fresh, trailing];
+
+        // Só a ordem antiga é reaped; a recente e o trailing nativo ficam
+        let stale = stale_open_orders(&strategy, now, 3600);
+        assert_eq!(stale.len(), 1);
+        assert_eq!(stale[0].order_id, "stuck");
+
+        strategy.open_orders = vec![stuck.clone()];
+        let cancelled = Mutex::new(Vec::new());
+        let reap = |strategy: StrategyItem| {
+            let cancelled = &cancelled;
+            let stuck = stuck.clone();
+            async move {
+                reap_stale_orders(
+                    &strategy, std::slice::from_ref(&stuck), now,
+                    |_| { let order = exchange_order(&stuck, "open", 0.0, 110.0); async move { Ok(order) } },
+                    |order_id| {
+                        cancelled.lock().unwrap().push(order_id);
+                        async { Ok(true) }
+                    },
+                ).await
+            }
+        };
+        let result = reap(strategy.clone()).await;
+
+        assert_eq!(*cancelled.lock().unwrap(), vec!["stuck".to_string()]);
+        assert_eq!(result.resolved, vec!["stuck".to_string()]);
+        assert_eq!(result.executions.len(), 1);
+        assert_eq!(result.executions[0].action, ExecutionAction::Cancel);
+        assert_eq!(result.executions[0].reason, "pending_order_timeout");
+        assert_eq!(result.new_status, Some(StrategyStatus::Monitoring));
+        assert!(result.errors.is_empty());
+
+        // Em erro (inativa) a ordem é cancelada, mas o status não muda
+        strategy.status = StrategyStatus::Error;
+        strategy.is_active = false;
+        let result = reap(strategy).await;
+        assert_eq!(result.resolved, vec!["stuck".to_string()]);
+        assert_eq!(result.new_status, None);
+    }
+
+    #[tokio::test]
+    async fn test_reaped_orders_that_filled_update_position_and_pnl() {
+        let now = 10_000;
+        let cancel = |_| async { Ok(true) };
+
+        // Compra vencida que executou na exchange: abre a posição
+        let mut waiting = strategy_with_position("s1", 0.0, 0.0);
+        waiting.position = None;
+        waiting.status = StrategyStatus::Monitoring;
+        let buy = TrackedOrder { side: "buy".into(), amount: 0.5, price: Some(100.0), ..tracked("buy") };
+        waiting.open_orders = vec![buy.clone()];
+        let result = reap_stale_orders(&waiting, std::slice::from_ref(&buy), now,
+            |_| { let order = exchange_order(&buy, "closed", 0.5, 100.0); async move { Ok(order) } }, cancel).await;
+        assert_eq!(result.resolved, vec!["buy".to_string()]);
+        assert_eq!(result.executions.len(), 1);
+        assert_eq!((result.executions[0].action.clone(), result.executions[0].amount), (ExecutionAction::Buy, 0.5));
+        assert_eq!(result.new_status, Some(StrategyStatus::InPosition));
+
+        // Venda que zera a posição: PnL realizado e estratégia concluída
+        let mut holding = strategy_with_position("s2", 0.5, 100.0);
+        let sell = TrackedOrder { amount: 0.5, ..tracked("sell") };
+        holding.open_orders = vec![sell.clone()];
+        let result = reap_stale_orders(&holding, std::slice::from_ref(&sell), now,
+            |_| { let order = exchange_order(&sell, "closed", 0.5, 110.0); async move { Ok(order) } }, cancel).await;
+        assert_eq!(result.executions[0].action, ExecutionAction::Sell);
+        assert!((result.executions[0].pnl_usd - 5.0).abs() < 1e-9);
+        assert_eq!(result.new_status, Some(StrategyStatus::Completed));
+
+        // Parcial antes do cancelamento: relida após cancelar, a parte executada entra
+        let fetches = Mutex::new(0);
+        let result = reap_stale_orders(&holding, std::slice::from_ref(&sell), now,
+            |_| {
+                let mut n = fetches.lock().unwrap();
+                *n += 1;
+                let order = if *n == 1 { exchange_order(&sell, "open", 0.1, 110.0) } else { exchange_order(&sell, "canceled", 0.2, 110.0) };
+                async move { Ok(order) }
+            },
+            cancel,
+        ).await;
+        let sold: Vec<f64> = result.executions.iter().filter(|e| e.action == ExecutionAction::Sell).map(|e| e.amount).collect();
+        assert_eq!(sold, vec![0.2]);
+        assert!(result.executions.iter().any(|e| e.action == ExecutionAction::Cancel));
+        assert_eq!(result.new_status, None);
+    }
+
+    #[test]
+    fn test_old_completed_strategy_archived_and_hidden_by_default() {
+        let now = 100 * 86_400;
+        let cutoff = now - 30 * 86_400;
+        let mut old_done = strategy_with_position("old", 0.0, 0.0);
+        old_done.status = StrategyStatus::Completed;
+        old_done.updated_at = cutoff - 1;
+        let mut recent_done = strategy_with_position("recent", 0.0, 0.0);
+        recent_done.status = StrategyStatus::Completed;
+        recent_done.updated_at = now - 86_400;
+        let mut running = strategy_with_position("running", 1.0, 100.0);
+        running.updated_at = cutoff - 1;
+        let mut stopped = strategy_with_position("stopped", 0.0, 0.0);
+        stopped.status = StrategyStatus::StoppedOut;
+        stopped.updated_at = cutoff - 1;
+        let mut expired = strategy_with_position("expired", 0.0, 0.0);
+        expired.status = StrategyStatus::Expired;
+        expired.updated_at = cutoff - 1;
+        let mut paused = strategy_with_position("paused", 0.0, 0.0);
+        paused.status = StrategyStatus::Paused;
+        paused.updated_at = cutoff - 1;
+
+        let (archived, kept): (Vec<_>, Vec<_>) = vec![old_done, recent_done, running, stopped, expired, paused]
+            .into_iter()
+            .partition(|s| is_archivable(s, cutoff));
+        let archived_ids: Vec<&str> = archived.iter().map(|s| s.strategy_id.as_str()).collect();
+        assert_eq!(archived_ids, vec!["old", "stopped", "expired"]);
+
+        let archived: Vec<ArchivedStrategy> = archived.into_iter()
+            .map(|strategy| ArchivedStrategy { id: None, user_id: "u1".into(), archived_at: now, strategy })
+            .collect();
+
+        let default_list = strategy_list(kept.clone(), vec![]);
+        assert!(default_list.iter().all(|s| s.id != "old" && !s.archived));
+        assert_eq!(default_list.len(), 3);
+
+        let with_archived_list = strategy_list(kept, archived.clone());
+        let old = with_archived_list.iter().find(|s| s.id == "old").unwrap();
+        assert!(old.archived);
+        assert_eq!(with_archived_list.len(), 6);
+
+        // O PnL realizado das arquivadas continua no resumo
+        let mut archived = archived;
+        archived[0].strategy.total_pnl_usd = 25.0;
+        let rates = HashMap::from([("USDT".to_string(), Ok(1.0))]);
+        let all = with_archived(vec![], archived);
+        assert_eq!(all.len(), 3);
+        assert_eq!(summarize_pnl(&all, &HashMap::new(), &rates).realized_pnl, 25.0);
+    }
+
+    #[test]
+    fn test_second_strategy_buy_blocked_by_exposure_cap() {
+        let mut second = strategy_with_position("s2", 0.0, 0.0);
+        second.position = None;
+        let strategies = vec![strategy_with_position("s1", 0.01, 60_000.0), second];
+
+        // s1 já tem $600 em posição; cap de $1000
+        let open = open_exposure_usd(&strategies, "s2");
+        assert!((open - 600.0).abs() < 1e-6);
+        assert!(validate_exposure(open, 300.0, 1000.0).is_ok());
+        assert!(validate_exposure(open, 500.0, 1000.0).is_err());
+    }
+
+    #[test]
+    fn test_compound_sizes_next_buy_by_realized_profit() {
+        let mut strategy = strategy_with_position("s1", 0.0, 0.0);
+        strategy.position = None;
+        strategy.status = StrategyStatus::Monitoring;
+        strategy.config.base_price = 100.0;
+        strategy.config.entry_amount_usd = Some(100.0);
+        strategy.config.auto_entry = true;
+        strategy.config.compound = true;
+        assert_eq!(strategy.buy_amount_usd(), 100.0);
+
+        // Ida e volta lucrativa: compra 1 @ 100, vende @ 112.5 → PnL somado em total_pnl_usd
+        strategy.total_pnl_usd += (112.5 - 100.0) * 1.0;
+        assert_eq!(strategy.buy_amount_usd(), 112.5);
+        let mut signals = Vec::new();
+        assert!(evaluate_entry(&strategy, 99.0, 0, None, &mut signals));
+        assert!(signals[0].message.contains("$112.50"));
+
+        // O teto de exposição continua limitando o tamanho composto
+        assert!(validate_exposure(900.0, strategy.buy_amount_usd(), 1000.0).is_err());
+
+        // Sem compound (ou com prejuízo acumulado) o valor configurado não muda
+        strategy.config.compound = false;
+        assert_eq!(strategy.buy_amount_usd(), 100.0);
+        strategy.config.compound = true;
+        strategy.total_pnl_usd = -30.0;
+        assert_eq!(strategy.buy_amount_usd(), 100.0);
+
+        // Lucro reinvestido uma vez por ciclo: com a posição aberta (ex.: próximos
+        // níveis do grid) as compras voltam ao valor base
+        strategy.total_pnl_usd = 12.5;
+        strategy.position = strategy_with_position("s1", 1.0, 100.0).position;
+        assert_eq!(strategy.buy_amount_usd(), 100.0);
+        strategy.position = None;
+        assert_eq!(strategy.buy_amount_usd(), 112.5);
+    }
+
+    #[test]
+    fn test_sub_minimum_buys_accumulate_until_they_clear_the_minimum() {
+        let mut strategy = strategy_with_position("s1", 0.0, 0.0);
+        strategy.position = None;
+        strategy.status = StrategyStatus::Monitoring;
+        strategy.config.entry_amount_usd = Some(4.0);
+
+        // Padrão: compra abaixo do mínimo é pulada, sem acumular
+        assert_eq!(plan_min_notional_buy(&strategy, Some(10.0)), MinNotionalBuy::Skip { amount: 4.0, min_cost: 10.0 });
+        assert_eq!(plan_min_notional_buy(&strategy, None), MinNotionalBuy::Buy(4.0));
+
+        strategy.config.min_notional_policy = MinNotionalPolicy::Accumulate;
+        strategy.config.auto_entry = true;
+        // Cada oportunidade de compra: vários ticks com o sinal, depois o sinal some
+        let mut fired = None;
+        'opportunities: for _ in 0..3 {
+            for _ in 0..5 {
+                match plan_min_notional_buy(&strategy, Some(10.0)) {
+                    MinNotionalBuy::Accumulate { pending, .. } => {
+                        strategy.pending_buy_usd = pending;
+                        strategy.pending_buy_window = true;
+                    }
+                    MinNotionalBuy::Buy(amount) => {
+                        fired = Some(amount);
+                        strategy.pending_buy_usd = 0.0;
+                        strategy.pending_buy_window = false;
+                        break 'opportunities;
+                    }
+                    other => panic!("unexpected {:?}", other),
+                }
+                assert_eq!(pending_buy_update(&strategy, true), None);
+            }
+            let (pending, window) = pending_buy_update(&strategy, false).expect("window closes");
+            (strategy.pending_buy_usd, strategy.pending_buy_window) = (pending, window);
+        }
+        // 4 → 8 acumulados (uma vez por oportunidade, não por tick); a terceira sai com 12
+        assert_eq!(fired, Some(12.0));
+        assert_eq!(strategy.pending_buy_usd, 0.0);
+        assert_eq!(plan_min_notional_buy(&strategy, Some(10.0)), MinNotionalBuy::Accumulate { pending: 4.0, min_cost: 10.0 });
+
+        // Política trocada (ou entrada automática desligada): o acumulado é descartado
+        strategy.pending_buy_usd = 8.0;
+        strategy.config.min_notional_policy = MinNotionalPolicy::Skip;
+        assert_eq!(pending_buy_update(&strategy, true), Some((0.0, false)));
+    }
+
+    #[test]
+    fn test_sell_without_position_is_refused_before_the_exchange() {
+        let mut strategy = strategy_with_position("s1", 0.5, 100.0);
+        assert!(check_order_side(&strategy, &SignalType::StopLoss, 0.0, 90.0, 1).is_none());
+
+        strategy.position = None;
+        let refused = check_order_side(&strategy, &SignalType::TakeProfit, 0.0, 120.0, 1).expect("sell with no position");
+        assert_eq!(refused.action, ExecutionAction::SellFailed);
+        assert_eq!(refused.reason, "side_check_failed: take_profit");
+        assert_eq!((refused.amount, refused.exchange_order_id.clone()), (0.0, None));
+        assert!(refused.error_message.unwrap().contains("no open position in BTC/USDT"));
+
+        // Poeira de arredondamento conta como posição zerada
+        strategy = strategy_with_position("s1", 1e-12, 100.0);
+        let refused = check_order_side(&strategy, &SignalType::MaxDrawdown, 0.0, 90.0, 1).expect("dust position");
+        assert!(refused.error_message.clone().unwrap().contains("nothing to sell"));
+
+        // Ticks seguintes com o mesmo sinal não gravam a recusa de novo
+        let mut executions = Vec::new();
+        record_refusal(&strategy, &mut executions, refused.clone());
+        assert_eq!(executions.len(), 1);
+        strategy.executions = executions;
+        let again = check_order_side(&strategy, &SignalType::MaxDrawdown, 0.0, 90.0, 31).unwrap();
+        let mut executions = Vec::new();
+        record_refusal(&strategy, &mut executions, again);
+        assert!(executions.is_empty());
+        // Outro motivo volta a registrar
+        let other = check_order_side(&strategy, &SignalType::StopLoss, 0.0, 90.0, 61).unwrap();
+        record_refusal(&strategy, &mut executions, other);
+        assert_eq!(executions.len(), 1);
+    }
+
+    #[test]
+    fn test_grid_level_sell_without_position_is_refused_once_per_level() {
+        let mut strategy = strategy_with_position("s1", 0.0, 0.0);
+        strategy.position = None;
+
+        let refused = grid_side_refusal(&strategy, 3, &SignalType::TakeProfit, 0.0, 120.0, 1).expect("grid sell with no position");
+        assert_eq!(refused.action, ExecutionAction::SellFailed);
+        assert_eq!(refused.reason, "grid_level_3_sell_failed: side_check_failed: take_profit");
+        assert_eq!(grid_level_of(&refused.reason), Some(3));
+
+        let mut executions = Vec::new();
+        record_refusal(&strategy, &mut executions, refused);
+        strategy.executions = executions;
+        // Mesmo nível no próximo tick: deduplicado; outro nível registra a sua
+        let mut executions = Vec::new();
+        record_refusal(&strategy, &mut executions, grid_side_refusal(&strategy, 3, &SignalType::TakeProfit, 0.0, 121.0, 31).unwrap());
+        assert!(executions.is_empty());
+        record_refusal(&strategy, &mut executions, grid_side_refusal(&strategy, 4, &SignalType::TakeProfit, 0.0, 121.0, 31).unwrap());
+        assert_eq!(executions.len(), 1);
+
+        // Compra do nível além do tamanho máximo
+        strategy.config.max_position_usd = Some(10.0);
+        let over = grid_side_refusal(&strategy, 1, &SignalType::Buy, 50.0, 100.0, 1).expect("over-size grid buy");
+        assert_eq!(over.reason, "grid_level_1_buy_failed: side_check_failed: buy");
+    }
+
+    #[test]
+    fn test_buy_beyond_max_position_size_is_refused() {
+        let mut strategy = strategy_with_position("s1", 0.5, 100.0);
+        strategy.config.max_position_usd = Some(100.0);
+
+        // 0.5 @ 100 = $50 já investidos: +$50 cabe, +$60 passa do máximo
+        assert!(check_order_side(&strategy, &SignalType::Buy, 50.0, 100.0, 1).is_none());
+        let refused = check_order_side(&strategy, &SignalType::Buy, 60.0, 100.0, 1).expect("over-size buy");
+        assert_eq!(refused.action, ExecutionAction::BuyFailed);
+        assert_eq!((refused.amount, refused.total), (0.6, 60.0));
+        assert!(refused.error_message.unwrap().contains("above the max position size of $100.00"));
+
+        // Sem limite configurado a compra segue (o teto de exposição do usuário continua valendo)
+        strategy.config.max_position_usd = None;
+        assert!(check_order_side(&strategy, &SignalType::Buy, 1_000.0, 100.0, 1).is_none());
+        assert!(StrategyConfig { max_position_usd: Some(0.0), base_price: 1.0, ..Default::default() }.validate().is_err());
+    }
+
+    #[test]
+    fn test_strategy_counts_by_status_from_aggregation() {
+        let pipeline = strategy_count_pipeline("u1");
+        assert_eq!(pipeline[0], doc! { "$match": { "user_id": "u1" } });
+
+        // Linhas como o `$group` devolve: (status, is_active) → count
+        let rows = vec![
+            doc! { "_id": { "status": "monitoring", "is_active": true }, "count": 3 },
+            doc! { "_id": { "status": "in_position", "is_active": true }, "count": 2 },
+            doc! { "_id": { "status": "gradual_selling", "is_active": true }, "count": 1_i64 },
+            doc! { "_id": { "status": "paused", "is_active": false }, "count": 1 },
+            doc! { "_id": { "status": "completed", "is_active": false }, "count": 4 },
+            doc! { "_id": { "status": "error", "is_active": false }, "count": 1 },
+            // Documento antigo sem status/is_active: idle e ativo
+            doc! { "_id": {}, "count": 2 },
+        ];
+        let summary = fold_strategy_counts(&rows);
+
+        assert_eq!(summary.total, 14);
+        assert_eq!(summary.active, 8);
+        assert_eq!(summary.in_position, 3);
+        assert_eq!(summary.by_status["idle"], 2);
+        assert_eq!(summary.by_status["monitoring"], 3);
+        assert_eq!(summary.by_status["paused"], 1);
+        assert_eq!(summary.by_status["completed"], 4);
+        assert_eq!(summary.by_status["error"], 1);
+        assert_eq!(summary.by_status["expired"], 0);
+    }
+
+    /// Avalia o `$elemMatch` dos filtros de ativação/pausa contra a estratégia em memória
+    /// (subconjunto usado por eles: igualdade, `$in`, `$nin` e `$gt`)
+    fn filter_matches(filter: &mongodb::bson::Document, strategy: &StrategyItem) -> bool {
+        use mongodb::bson::Bson;
+        let value = |field: &str| -> Bson {
+            match field {
+                "strategy_id" => Bson::String(strategy.strategy_id.clone()),
+                "is_active" => Bson::Boolean(strategy.is_active),
+                "status" => mongodb::bson::to_bson(&strategy.status).unwrap(),
+                "config.base_price" => Bson::Double(strategy.config.base_price),
+                other => panic!("unexpected filter field {}", other),
+            }
+        };
+        let elem = filter.get_document("strategies").unwrap().get_document("$elemMatch").unwrap();
+        elem.iter().all(|(field, cond)| {
+            let actual = value(field);
+            match cond {
+                Bson::Document(ops) => ops.iter().all(|(op, arg)| match op.as_str() {
+                    "$in" => arg.as_array().unwrap().contains(&actual),
+                    "$nin" => !arg.as_array().unwrap().contains(&actual),
+                    "$gt" => actual.as_f64().unwrap() > arg.as_f64().unwrap(),
+                    other => panic!("unexpected operator {}", other),
+                }),
+                expected => actual == *expected,
+            }
+        })
+    }
+
+    #[test]
+    fn test_concurrent_activate_and_pause_end_consistent() {
+        use StrategyStatus::*;
+        let activate = |s: &mut StrategyItem| if filter_matches(&activation_filter("u1", "s1", false), s) {
+            s.status = Monitoring;
+            s.is_active = true;
+            s.error_message = None;
+        };
+        let pause = |s: &mut StrategyItem| if filter_matches(&pause_filter("u1", "s1"), s) {
+            s.status = Paused;
+            s.is_active = false;
+        };
+
+        // Qualquer intercalação de ativações e pausas termina num estado coerente,
+        // e o filtro de ativação concorda com `check_activation`
+        let starts = [(Paused, false), (Monitoring, true), (InPosition, true), (Idle, false), (Error, false), (Completed, false)];
+        for (status, is_active) in starts {
+            for order in 0u32..16 {
+                let mut s = strategy_with_position("s1", 0.0, 0.0);
+                s.position = None;
+                s.config.base_price = 100.0;
+                s.status = status.clone();
+                s.is_active = is_active;
+                assert_eq!(
+                    filter_matches(&activation_filter("u1", "s1", false), &s),
+                    check_activation(&s, false) == ActivationCheck::Activate,
+                    "{:?} active={}", status, is_active
+                );
+                for step in 0..4 {
+                    if order & (1 << step) == 0 { activate(&mut s) } else { pause(&mut s) }
+                }
+                assert!(
+                    matches!((&s.status, s.is_active), (Monitoring | InPosition, true) | (Paused | Idle | Error | Completed, false)),
+                    "{:?} -> {:?} active={}", status, s.status, s.is_active
+                );
+            }
+        }
+
+        // Os filtros se excluem: a mesma estratégia nunca casa com os dois
+        let mut active = strategy_with_position("s1", 0.0, 0.0);
+        active.config.base_price = 100.0;
+        active.status = Monitoring;
+        active.is_active = true;
+        assert!(filter_matches(&pause_filter("u1", "s1"), &active));
+        assert!(!filter_matches(&activation_filter("u1", "s1", true), &active));
+        // Terminal não pausa
+        active.status = Completed;
+        assert!(!filter_matches(&pause_filter("u1", "s1"), &active));
+
+        // Em erro: só com force
+        let mut errored = active.clone();
+        errored.status = Error;
+        errored.is_active = false;
+        errored.error_message = Some("Insufficient balance".into());
+        assert!(matches!(check_activation(&errored, false), ActivationCheck::Reject(msg) if msg.contains("force=true")));
+        assert_eq!(check_activation(&errored, true), ActivationCheck::Activate);
+        assert!(!filter_matches(&activation_filter("u1", "s1", false), &errored));
+        assert!(filter_matches(&activation_filter("u1", "s1", true), &errored));
+        // Sem preço base não ativa
+        errored.config.base_price = 0.0;
+        assert!(!filter_matches(&activation_filter("u1", "s1", true), &errored));
+    }
+
+    #[test]
+    fn test_filled_native_trailing_closes_position() {
+        let mut strategy = strategy_with_position("s1", 1.0, 100.0);
+        strategy.config.max_drawdown_percent = Some(5.0);
+        let trailing = TrackedOrder {
+            order_id: "t1".into(), side: "sell".into(), order_type: NATIVE_TRAILING_ORDER_TYPE.into(),
+            amount: 1.0, price: None, created_at: 0, ttl_secs: None, lot_number: None,
+        };
+        strategy.open_orders.push(trailing.clone());
+        let fetched = |status: &str, filled: Option<f64>| CcxtOrder {
+            id: "t1".into(), symbol: "BTC/USDT".into(), status: status.into(), side: "sell".into(),
+            order_type: "market".into(), price: None, average: Some(95.0), amount: Some(1.0), filled,
+            remaining: None, cost: None, fee: Some(crate::models::OrderFee { cost: 0.1, currency: "USDT".into() }),
+            timestamp: None, datetime: None,
+        };
+
+        let sold = closed_order_execution(&strategy, &trailing, &fetched("closed", Some(1.0)), "max_drawdown", 50).unwrap();
+        assert_eq!((sold.action.clone(), sold.reason.as_str(), sold.amount, sold.price), (ExecutionAction::Sell, "max_drawdown", 1.0, 95.0));
+        assert!((sold.pnl_usd - (-5.1)).abs() < 1e-9);
+        assert_eq!(sold.exchange_order_id.as_deref(), Some("t1"));
+        assert_eq!(native_trailing_fill_status(&strategy, std::slice::from_ref(&sold)), Some(StrategyStatus::Paused));
+
+        // Parcial antes de cancelar: posição segue aberta com o restante
+        let partial = closed_order_execution(&strategy, &trailing, &fetched("canceled", Some(0.4)), "max_drawdown", 50).unwrap();
+        assert_eq!(partial.amount, 0.4);
+        assert_eq!(native_trailing_fill_status(&strategy, &[partial]), None);
+
+        // Cancelada sem execução: só sai do rastreio
+        assert!(closed_order_execution(&strategy, &trailing, &fetched("canceled", Some(0.0)), "max_drawdown", 50).is_none());
+    }
+
+    #[test]
+    fn test_native_trailing_capability_detection() {
+        let caps = |list: &'static [&'static str]| move |c: &str| list.contains(&c);
+
+        assert_eq!(native_trailing_support("bybit", caps(&["createTrailingPercentOrder"])), Some(NativeTrailing::Percent));
+        // Binance spot: sem trailingPercent unificado, mas aceita trailingDelta no STOP_LOSS
+        assert_eq!(native_trailing_support("binance", caps(&["createStopLossOrder"])), Some(NativeTrailing::DeltaBips));
+        assert_eq!(native_trailing_support("kucoin", caps(&["createStopLossOrder"])), None);
+        assert_eq!(native_trailing_support("mexc", caps(&[])), None);
+
+        // Com a ordem nativa rastreada, o guard server-side não dispara
+        let mut strategy = strategy_with_position("s1", 1.0, 100.0);
+        strategy.config.max_drawdown_percent = Some(5.0);
+        assert!(native_trailing_order(&strategy).is_none());
+        strategy.open_orders.push(TrackedOrder {
+            order_id: "t1".into(), side: "sell".into(), order_type: NATIVE_TRAILING_ORDER_TYPE.into(),
+            amount: 1.0, price: None, created_at: 0, ttl_secs: None, lot_number: None,
+        });
+        assert_eq!(native_trailing_order(&strategy).map(|o| o.order_id.as_str()), Some("t1"));
+    }
+
+    #[test]
+    fn test_native_trailing_order_params() {
+        let percent = NativeTrailing::Percent.params(2.5).unwrap();
+        assert_eq!(NativeTrailing::Percent.order_type(), "market");
+        assert_eq!(serde_json::Value::Object(percent), serde_json::json!({ "trailingPercent": 2.5 }));
+
+        let delta = NativeTrailing::DeltaBips.params(2.5).unwrap();
+        assert_eq!(NativeTrailing::DeltaBips.order_type(), "STOP_LOSS");
+        assert_eq!(serde_json::Value::Object(delta), serde_json::json!({ "trailingDelta": 250 }));
+
+        // Fora da faixa da Binance (0.1%–20%): cai no guard server-side
+        assert!(NativeTrailing::DeltaBips.params(0.05).is_err());
+        assert!(NativeTrailing::DeltaBips.params(25.0).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_take_profit_levels_become_tracked_exchange_sells() {
+        let lot = |n: i32, pct: f64| crate::models::GradualLot {
+            lot_number: n, sell_percent: pct, executed: false,
+            executed_at: None, executed_price: None, realized_pnl: None,
+        };
+        let config = StrategyConfig {
+            base_price: 100.0, take_profit_percent: 10.0, fee_percent: 0.5, gradual_take_percent: 2.0,
+            gradual_sell: true, gradual_lots: vec![lot(1, 50.0), lot(2, 30.0), lot(3, 20.0)],
+            ..Default::default()
+        };
+        let precision = ExecutionPrecision { amount: 6, price: 2 };
+        let plan = plan_take_profit_orders(&config, 2.0, &precision);
+
+        let placed_calls = Mutex::new(Vec::new());
+        let placement = place_take_profit_orders(&plan, 50, |level| {
+            placed_calls.lock().unwrap().push((level.price, level.amount));
+            let id = format!("tp{}", level.lot_number.unwrap());
+            async move { Ok(id) }
+        }).await;
+
+        assert!(placement.error.is_none());
+        assert_eq!(placed_calls.lock().unwrap().clone(), vec![(110.5, 1.0), (112.5, 0.6), (114.5, 0.4)]);
+        assert_eq!(placement.placed.len(), 3);
+        for (order, n) in placement.placed.iter().zip(1..) {
+            assert_eq!(order.order_id, format!("tp{}", n));
+            assert_eq!((order.side.as_str(), order.order_type.as_str()), ("sell", TAKE_PROFIT_ORDER_TYPE));
+            assert_eq!(order.lot_number, Some(n));
+            assert_eq!(order.created_at, 50);
+        }
+        let total: f64 = placement.placed.iter().map(|o| o.amount).sum();
+        assert!((total - 2.0).abs() < 1e-9);
+
+        // Lote já vendido fica de fora; sem venda gradual vira uma venda só no TP
+        let mut partial = config.clone();
+        partial.gradual_lots[0].executed = true;
+        let rest = plan_take_profit_orders(&partial, 1.0, &precision);
+        assert_eq!(rest.iter().map(|l| l.lot_number).collect::<Vec<_>>(), vec![Some(2), Some(3)]);
+        let single = plan_take_profit_orders(&StrategyConfig { gradual_sell: false, ..config.clone() }, 2.0, &precision);
+        assert_eq!(single, vec![TakeProfitOrderPlan { lot_number: None, price: 110.5, amount: 2.0 }]);
+
+        // Limite de ordens abertas: para e deixa o restante com o servidor
+        let capped = place_take_profit_orders(&plan, 50, |level| async move {
+            match level.lot_number {
+                Some(3) => Err("binance {\"code\":-2010,\"msg\":\"Account has too many open orders.\"}".to_string()),
+                n => Ok(format!("tp{}", n.unwrap())),
+            }
+        }).await;
+        assert_eq!(capped.placed.len(), 2);
+        assert!(capped.capped);
+
+        let mut strategy = strategy_with_position("s1", 2.0, 100.0);
+        strategy.config = config.clone();
+        strategy.open_orders = capped.placed;
+        strategy.open_orders.push(tracked("stuck"));
+        assert_eq!(take_profit_orders(&strategy).len(), 2);
+        let stale: Vec<String> = stale_open_orders(&strategy, 100_000, 3600).into_iter().map(|o| o.order_id).collect();
+        assert_eq!(stale, vec!["stuck".to_string()]);
+
+        // Lote 1 está na exchange: o servidor não vende em duplicidade
+        let tp_signal = |signal_type: SignalType| StrategySignal {
+            signal_type, price: 115.0, message: String::new(), acted: false, price_change_percent: 0.0, created_at: 60,
+        };
+        let mut signals = vec![tp_signal(SignalType::TakeProfit), tp_signal(SignalType::Info)];
+        drop_exchange_take_profit_signals(&strategy, &mut signals);
+        assert_eq!(signals.iter().map(|s| s.signal_type.clone()).collect::<Vec<_>>(), vec![SignalType::Info]);
+
+        // Lotes 1 e 2 executados na exchange: o lote 3, que não coube, segue server-side
+        strategy.config.gradual_lots[0].executed = true;
+        strategy.config.gradual_lots[1].executed = true;
+        strategy.open_orders.retain(|o| o.order_type != TAKE_PROFIT_ORDER_TYPE);
+        strategy.open_orders.push(TrackedOrder { lot_number: Some(2), order_type: TAKE_PROFIT_ORDER_TYPE.into(), ..tracked("tp2-late") });
+        let mut signals = vec![tp_signal(SignalType::GradualSell)];
+        drop_exchange_take_profit_signals(&strategy, &mut signals);
+        assert_eq!(signals.len(), 1);
+    }
+
+    #[test]
+    fn test_entry_condition_expression_triggers_buy() {
+        let mut strategy = strategy_with_position("s1", 0.0, 0.0);
+        strategy.position = None;
+        strategy.status = StrategyStatus::Monitoring;
+        strategy.config.base_price = 50.0; // regra padrão não dispararia
+        strategy.config.entry_amount_usd = Some(100.0);
+        strategy.config.auto_entry = true;
+        strategy.config.entry_condition = Some("price < 0.95 * high_24h".into());
+
+        let vars = HashMap::from([("price", 94.0), ("high_24h", 100.0)]);
+        let mut signals = Vec::new();
+        assert!(evaluate_entry(&strategy, 94.0, 0, Some(&vars), &mut signals));
+        assert_eq!(signals[0].signal_type, SignalType::Buy);
+
+        let vars = HashMap::from([("price", 96.0), ("high_24h", 100.0)]);
+        let mut signals = Vec::new();
+        assert!(!evaluate_entry(&strategy, 96.0, 0, Some(&vars), &mut signals));
+        assert!(signals.is_empty());
+
+        // Sem o opt-in a entrada continua manual, mesmo com valor configurado
+        strategy.config.auto_entry = false;
+        let vars = HashMap::from([("price", 94.0), ("high_24h", 100.0)]);
+        assert!(!evaluate_entry(&strategy, 94.0, 0, Some(&vars), &mut signals));
+        assert!(signals.is_empty());
+    }
+
+    #[test]
+    fn test_drawdown_past_threshold_flattens_and_pauses() {
+        let mut strategy = strategy_with_position("s1", 2.0, 100.0);
+        strategy.config.max_drawdown_percent = Some(10.0);
+        if let Some(pos) = strategy.position.as_mut() {
+            pos.highest_price = 120.0;
+        }
+
+        // 120 → 110: drawdown 8.3%, abaixo do limite
+        assert!(evaluate_drawdown_guard(&strategy, 110.0, 0).is_none());
+
+        // 120 → 105: drawdown 12.5%, zera a posição inteira
+        let signal = evaluate_drawdown_guard(&strategy, 105.0, 0).expect("drawdown signal");
+        assert_eq!(signal.signal_type, SignalType::MaxDrawdown);
+        assert_eq!(calc_sell_amount(&strategy, &signal.signal_type), 2.0);
+        assert_eq!(protective_exit_status(&signal.signal_type), StrategyStatus::Paused);
+    }
+
+    #[test]
+    fn test_repeated_alerts_within_window_notify_once() {
+        let mut strategy = strategy_with_position("s1", 1.0, 100.0);
+        strategy.config.notification_throttle_secs = Some(300);
+
+        let alert = |price: f64, now: i64| StrategySignal {
+            signal_type: SignalType::StopLoss, price,
+            message: format!("🛑 STOP LOSS! Preço {:.2}", price),
+            acted: false, price_change_percent: -5.0, created_at: now,
+        };
+
+        let mut delivered = 0;
+        for (i, price) in [95.0, 95.2, 94.9, 95.0].iter().enumerate() {
+            let now = 1_000 + i as i64 * 30;
+            let signal = alert(*price, now);
+            let (kept, notified) = throttle_notifications(&strategy, vec![&signal], now);
+            delivered += kept.len();
+            strategy.last_notified_at.extend(notified);
+            strategy.signals.extend(kept.into_iter().cloned());
+        }
+        assert_eq!(delivered, 1);
+
+        // Fora da janela volta a notificar, mas não se a mensagem for idêntica à anterior
+        let same = alert(95.0, 1_400);
+        assert!(throttle_notifications(&strategy, vec![&same], 1_400).0.is_empty());
+        let new = alert(93.0, 1_400);
+        assert_eq!(throttle_notifications(&strategy, vec![&new], 1_400).0.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_pause_cancels_tracked_open_order() {
+        let calls = Mutex::new(Vec::new());
+        let orders = vec![tracked("ord-1"), tracked("ord-2")];
+
+        let result = cancel_tracked_orders(&orders, "cancel_on_pause", 1_700_000_000, |id| {
+            calls.lock().unwrap().push(id.clone());
+            async move {
+                if id == "ord-2" { Err("OrderNotFound: order already filled".to_string()) } else { Ok(true) }
+            }
+        }).await;
+
+        assert_eq!(*calls.lock().unwrap(), vec!["ord-1".to_string(), "ord-2".to_string()]);
+        assert!(result.errors.is_empty());
+        assert!(result.remaining.is_empty());
+        assert_eq!(result.executions.len(), 2);
+        assert!(result.executions.iter().all(|e| e.action == ExecutionAction::Cancel));
+        assert!(result.executions[1].error_message.is_some());
+    }
+
+    #[test]
+    fn test_order_already_closed_matches_exception_class() {
+        assert!(is_order_already_closed("Failed to cancel order: OrderNotFound: binance {\"code\":-2011,\"msg\":\"Unknown order sent.\"}"));
+        assert!(is_order_already_closed("ccxt.base.errors.OrderNotFound: okx order does not exist"));
+        // Mensagens que só citam as palavras não contam
+        assert!(!is_order_already_closed("Failed to cancel order: InvalidOrder: order not filled yet"));
+        assert!(!is_order_already_closed("NetworkError: request already in flight, order cancelled?"));
+        assert!(!is_order_already_closed("ExchangeError: MyOrderNotFound: custom"));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_failure_keeps_order_tracked() {
+        let orders = vec![tracked("ord-1")];
+        let result = cancel_tracked_orders(&orders, "cancel_on_delete", 0, |_| async {
+            Err("NetworkError: timeout".to_string())
+        }).await;
+
+        assert_eq!(result.remaining.len(), 1);
+        assert_eq!(result.errors.len(), 1);
+        assert!(result.executions.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_futures_entry_sets_leverage_before_order() {
+        let calls = std::sync::Mutex::new(Vec::new());
+        let mut config = StrategyConfig { mode: StrategyMode::Futures, leverage: Some(3), ..Default::default() };
+
+        apply_futures_leverage(&config, "BTC/USDT:USDT", |leverage, symbol| {
+            calls.lock().unwrap().push((leverage, symbol));
+            async { Ok(()) }
+        }).await.unwrap();
+        assert_eq!(*calls.lock().unwrap(), vec![(3, "BTC/USDT:USDT".to_string())]);
+
+        // Spot não chama a exchange
+        config.mode = StrategyMode::Spot;
+        apply_futures_leverage(&config, "BTC/USDT", |_, _| async { panic!("spot must not set leverage") }).await.unwrap();
+
+        // Stop além da liquidação é rejeitado
+        let risky = StrategyConfig { mode: StrategyMode::Futures, leverage: Some(10), stop_loss_percent: 12.0, ..Default::default() };
+        assert!(risky.validate_futures("BTC/USDT:USDT").unwrap_err().contains("liquidation"));
+    }
+
+    #[test]
+    fn test_noisy_fill_amount_stored_at_market_precision() {
+        let market = MarketPrecision { amount_decimals: Some(4), price_decimals: Some(2) };
+        let precision = ExecutionPrecision::from_market(Some(&market));
+        let filled = 0.1 + 0.2; // 0.30000000000000004
+        let price = 65_000.123_456;
+        let execution = StrategyExecution {
+            execution_id: "e1".into(), action: ExecutionAction::Buy, reason: "entry".into(),
+            price, amount: filled, total: price * filled, fee: price * filled * 0.001,
+            pnl_usd: 0.0, exchange_order_id: None, executed_at: 0, error_message: None,
+        }.rounded(&precision);
+
+        assert_eq!(execution.amount, 0.3);
+        assert_eq!(execution.price, 65_000.12);
+        assert_eq!(execution.total, 19_500.04);
+        // Taxa/PnL não usam as casas do preço
+        assert_eq!(execution.fee, 19.50003704);
+
+        // Quantidade gravada nunca passa do executado; a posição usa o valor bruto
+        let fill = StrategyExecution {
+            execution_id: "e2".into(), action: ExecutionAction::Buy, reason: "entry".into(),
+            price: 100.0, amount: 0.123_456_78, total: 12.345_678, fee: 0.000_012_345,
+            pnl_usd: 0.0, exchange_order_id: None, executed_at: 0, error_message: None,
+        };
+        let result = TickResult {
+            strategy_id: "s1".into(), symbol: "BTC/USDT".into(), price: 100.0, signals: vec![],
+            executions: vec![fill.clone()], new_status: None, error: None, precision: Some(precision),
+        };
+        let persisted = result.persisted_executions();
+        assert_eq!(persisted[0].amount, 0.1234);
+        assert_eq!(persisted[0].fee, 0.00001235);
+        let position = apply_buy_to_position(None, &result.executions[0], 100.0, 0);
+        assert_eq!(position.quantity, fill.amount);
+    }
+
+    #[test]
+    fn test_grid_sell_requires_fee_adjusted_profit() {
+        // fee 0.5% por lado → breakeven em buy * 1.01
+        let grid = GridConfig::default();
+        let buy = |price: f64, amount: f64| StrategyExecution {
+            execution_id: "e1".into(), action: ExecutionAction::Buy, reason: "entry".into(),
+            price, amount, total: price * amount, fee: 0.0,
+            pnl_usd: 0.0, exchange_order_id: None, executed_at: 0, error_message: None,
+        };
+        let sell = |reason: &str, amount: f64| StrategyExecution {
+            action: ExecutionAction::Sell, reason: reason.into(), ..buy(101.5, amount)
+        };
+
+        // Sem layout cada compra abre um nível; a venda usa o breakeven da mais recente
+        let mut state = GridState::pairing();
+        apply_grid_state_executions(&mut state, grid.fee_buffer(0.5), &[buy(100.0, 0.2)], 0);
+        assert_eq!(state.pair_sell_floor(), Some(101.0));
+        apply_grid_state_executions(&mut state, grid.fee_buffer(0.5), &[buy(90.0, 0.3)], 1);
+        assert!((state.pair_sell_floor().unwrap() - 90.9).abs() < 1e-9);
+
+        // Lucro mínimo configurável soma ao buffer
+        let strict = GridConfig { min_profit_percent: 1.0, ..Default::default() };
+        let mut strict_state = GridState::pairing();
+        apply_grid_state_executions(&mut strict_state, strict.fee_buffer(0.5), &[buy(100.0, 0.2)], 0);
+        assert_eq!(strict_state.pair_sell_floor(), Some(102.0));
+
+        // TP que vende a posição toda libera todos os níveis, não só o mais recente
+        let mut full = state.clone();
+        apply_grid_state_executions(&mut full, grid.fee_buffer(0.5), &[sell("take_profit", 0.5)], 2);
+        assert!(!full.has_fills());
+        assert_eq!(full.pair_sell_floor(), None);
+
+        // Lote parcial consome da compra mais recente e mantém o resto
+        apply_grid_state_executions(&mut state, grid.fee_buffer(0.5), &[sell("gradual_sell", 0.4)], 2);
+        assert_eq!(state.levels.len(), 1);
+        assert!((state.levels[0].fill.as_ref().unwrap().amount - 0.1).abs() < 1e-9);
+        assert_eq!(state.pair_sell_floor(), Some(101.0));
+
+        // Estado de execução fica fora da config (edição/import não o apaga nem forja)
+        let mut strategy = strategy_with_position("s1", 0.1, 100.0);
+        strategy.config.grid = Some(grid);
+        strategy.grid_state = Some(state.clone());
+        assert_eq!(active_grid_state(&strategy), Some(state));
+        assert!(serde_json::to_value(&strategy.config).unwrap()["grid"].get("open_levels").is_none());
+    }
+
+    #[test]
+    fn test_grid_sweep_fills_buy_level_then_paired_sell() {
+        let grid = GridConfig { levels: 3, spacing_percent: 1.0, ..Default::default() };
+        let fee_buffer = grid.fee_buffer(0.1);
+        let mut strategy = strategy_with_position("g1", 0.0, 0.0);
+        strategy.position = None;
+        strategy.config = StrategyConfig { base_price: 100.0, fee_percent: 0.1, grid: Some(grid), ..Default::default() };
+
+        let mut state = active_grid_state(&strategy).expect("grid with layout");
+        let prices: Vec<f64> = state.levels.iter().map(|l| l.price).collect();
+        assert!((prices[0] - 99.0).abs() < 1e-9 && (prices[2] - 97.0).abs() < 1e-9);
+
+        // Executa a mercado tudo que o preço dispara, como o tick faz
+        let sweep = |state: &mut GridState, price: f64, now: i64| -> Vec<StrategyExecution> {
+            let executions: Vec<StrategyExecution> = state.triggered(price, now).into_iter().map(|(level, side)| {
+                let (action, amount) = match side {
+                    GridSide::Buy => (ExecutionAction::Buy, 0.1),
+                    GridSide::Sell => (ExecutionAction::Sell, state.level(level).unwrap().fill.as_ref().unwrap().amount),
+                };
+                StrategyExecution {
+                    execution_id: format!("e{}", now), action, reason: grid_level_reason(level),
+                    price, amount, total: price * amount, fee: 0.0,
+                    pnl_usd: 0.0, exchange_order_id: None, executed_at: now, error_message: None,
+                }
+            }).collect();
+            apply_grid_state_executions(state, fee_buffer, &executions, now);
+            executions
+        };
+
+        assert!(sweep(&mut state, 100.5, 1).is_empty());
+
+        // Cai abaixo do nível 1 (99) mas não do nível 2 (98): só o nível 1 compra
+        let bought = sweep(&mut state, 98.6, 2);
+        assert_eq!(bought.len(), 1);
+        assert_eq!(bought[0].reason, "grid_level_1");
+        let level1 = state.level(1).unwrap();
+        assert_eq!(level1.side, GridSide::Sell);
+        assert!((level1.target_price - 100.0).abs() < 1e-9);
+        assert_eq!(level1.fill.as_ref().map(|f| f.price), Some(98.6));
+
+        // O estado persistido é retomado no próximo tick, não recalculado
+        strategy.grid_state = Some(state.clone());
+        assert_eq!(active_grid_state(&strategy), Some(state.clone()));
+
+        // Sobe sem alcançar a venda casada: nada acontece (e o nível 1 não recompra)
+        assert!(sweep(&mut state, 99.5, 3).is_empty());
+
+        // Alcança a linha de cima: vende exatamente a quantidade comprada no nível 1
+        let sold = sweep(&mut state, 100.2, 4);
+        assert_eq!(sold.len(), 1);
+        assert_eq!((sold[0].action.clone(), sold[0].reason.as_str(), sold[0].amount), (ExecutionAction::Sell, "grid_level_1", 0.1));
+        let level1 = state.level(1).unwrap();
+        assert_eq!((level1.side, level1.fill.clone()), (GridSide::Buy, None));
+        assert!((level1.target_price - 99.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_grid_partial_sell_keeps_remainder_and_failed_buy_backs_off() {
+        let grid = GridConfig { levels: 3, spacing_percent: 1.0, ..Default::default() };
+        let mut state = GridState::build(100.0, grid.levels, grid.spacing_percent);
+        let exec = |action: ExecutionAction, reason: String, amount: f64| StrategyExecution {
+            execution_id: "e".into(), action, reason, price: 98.0, amount, total: 98.0 * amount, fee: 0.0,
+            pnl_usd: 0.0, exchange_order_id: None, executed_at: 0, error_message: None,
+        };
+        apply_grid_state_executions(&mut state, 0.0, &[exec(ExecutionAction::Buy, grid_level_reason(1), 0.1)], 0);
+
+        // Venda parcial: o restante continua aguardando a venda casada
+        apply_grid_state_executions(&mut state, 0.0, &[exec(ExecutionAction::Sell, grid_level_reason(1), 0.04)], 1);
+        let level1 = state.level(1).unwrap();
+        assert_eq!(level1.side, GridSide::Sell);
+        assert!((level1.fill.as_ref().unwrap().amount - 0.06).abs() < 1e-9);
+
+        // Compra do nível 2 falhou: não tenta de novo a cada tick
+        std::env::remove_var("GRID_BUY_RETRY_BACKOFF_SECS");
+        let failed = exec(ExecutionAction::BuyFailed, format!("{}_buy_failed: insufficient funds", grid_level_reason(2)), 0.1);
+        apply_grid_state_executions(&mut state, 0.0, std::slice::from_ref(&failed), 100);
+        assert!(!state.triggered(97.5, 130).contains(&(2, GridSide::Buy)));
+        assert!(state.triggered(97.5, 160).contains(&(2, GridSide::Buy)));
+        // Segunda falha seguida dobra a espera
+        apply_grid_state_executions(&mut state, 0.0, &[failed], 160);
+        assert!(!state.triggered(97.5, 270).contains(&(2, GridSide::Buy)));
+        assert!(state.triggered(97.5, 280).contains(&(2, GridSide::Buy)));
+
+        // Guardas comuns: stop abaixo da linha mais baixa (97) zera a posição antes dos níveis
+        let mut strategy = strategy_with_position("g1", 0.06, 98.0);
+        strategy.config.stop_loss_percent = 5.0;
+        assert!(evaluate_grid_guards(&strategy, &state, 93.0, 0).is_none());
+        let stop = evaluate_grid_guards(&strategy, &state, 92.0, 0).expect("stop below grid");
+        assert_eq!(stop.signal_type, SignalType::StopLoss);
+        strategy.position = None;
+        assert!(evaluate_grid_guards(&strategy, &state, 50.0, 0).is_none());
+
+        // Stop executado: as compras abertas dos níveis deixam de existir
+        apply_grid_state_executions(&mut state, 0.0, &[exec(ExecutionAction::Sell, "stop_loss".into(), 0.06)], 2);
+        assert!(!state.has_fills());
+        assert_eq!(state.level(1).unwrap().side, GridSide::Buy);
+    }
+
+    #[tokio::test]
+    async fn test_transient_order_failure_is_retried_within_tick() {
+        let attempts = Mutex::new(0);
+        let result = retry_transient_order(2, 1, || {
+            let n = { let mut a = attempts.lock().unwrap(); *a += 1; *a };
+            async move {
+                if n == 1 { Err("RateLimitExceeded: binance {\"code\":-1003}".to_string()) }
+                else { Ok("order-1") }
+            }
+        }).await;
+        assert_eq!(result, Ok("order-1"));
+        assert_eq!(*attempts.lock().unwrap(), 2);
+
+        // Timeout: a ordem pode ter sido criada, não reenvia
+        for ambiguous in ["RequestTimeout: okx POST /api/v5/trade/order", "NetworkError: binance GET https://api.binance.com timed out",
+            "ExchangeNotAvailable: bybit 502 Bad Gateway"] {
+            let attempts = Mutex::new(0);
+            let result: Result<&str, String> = retry_transient_order(2, 1, || {
+                *attempts.lock().unwrap() += 1;
+                async move { Err(ambiguous.to_string()) }
+            }).await;
+            assert!(result.is_err());
+            assert_eq!(*attempts.lock().unwrap(), 1, "{}", ambiguous);
+        }
+
+        // Erro definitivo: uma única tentativa, falha registrada
+        let attempts = Mutex::new(0);
+        let result: Result<&str, String> = retry_transient_order(2, 1, || {
+            *attempts.lock().unwrap() += 1;
+            async { Err("InsufficientFunds: binance Account has insufficient balance".to_string()) }
+        }).await;
+        assert!(result.unwrap_err().contains("InsufficientFunds"));
+        assert_eq!(*attempts.lock().unwrap(), 1);
+
+        // Recusa persistente esgota as retentativas
+        let attempts = Mutex::new(0);
+        let result: Result<&str, String> = retry_transient_order(2, 1, || {
+            *attempts.lock().unwrap() += 1;
+            async { Err("OnMaintenance: okx system upgrade".to_string()) }
+        }).await;
+        assert!(result.is_err());
+        assert_eq!(*attempts.lock().unwrap(), 3);
+    }
+
+    #[test]
+    fn test_volatility_filter_blocks_entry_only_when_atr_is_high() {
+        let series = |closes: Vec<f64>, spread_pct: f64| -> Vec<crate::models::Candle> {
+            closes.into_iter().enumerate().map(|(i, c)| crate::models::Candle {
+                timestamp: i as i64 * 3_600_000, open: c,
+                high: c * (1.0 + spread_pct / 100.0), low: c * (1.0 - spread_pct / 100.0),
+                close: c, volume: 1.0,
+            }).collect()
+        };
+        let volatile = series((0..20).map(|i| if i % 2 == 0 { 100.0 } else { 108.0 }).collect(), 4.0);
+        let calm = series((0..20).map(|i| 100.0 + i as f64 * 0.1).collect(), 0.2);
+
+        let mut strategy = strategy_with_position("s1", 0.0, 100.0);
+        strategy.position = None;
+        strategy.status = StrategyStatus::Monitoring;
+        strategy.config.auto_entry = true;
+        strategy.config.base_price = 100.0;
+        strategy.config.entry_amount_usd = Some(50.0);
+        strategy.config.max_atr_percent = Some(3.0);
+
+        // Mesmo caminho do tick: filtro primeiro, entrada só se ele não bloquear
+        let run = |candles: &[crate::models::Candle]| {
+            let mut signals = Vec::new();
+            match volatility_filter_signal(&strategy, candles, 99.0, 1_000) {
+                Some(signal) => signals.push(signal),
+                None => { evaluate_entry(&strategy, 99.0, 1_000, None, &mut signals); }
+            }
+            signals
+        };
+
+        // ATR alto: só o aviso, nenhuma compra
+        let blocked = run(&volatile);
+        assert_eq!(blocked.len(), 1);
+        assert_eq!(blocked[0].signal_type, SignalType::Info);
+        assert!(!blocked[0].acted);
+        assert!(blocked[0].message.contains("volatilidade alta"), "{}", blocked[0].message);
+
+        // ATR baixo: o filtro libera e a entrada acontece
+        let allowed = run(&calm);
+        assert!(allowed.iter().any(|s| s.signal_type == SignalType::Buy), "{:?}", allowed);
+        assert!(allowed.iter().all(|s| s.signal_type != SignalType::Info));
+
+        // Sem limite configurado o filtro nunca bloqueia
+        strategy.config.max_atr_percent = None;
+        assert!(volatility_filter_signal(&strategy, &volatile, 99.0, 1_000).is_none());
+    }
+
+    #[test]
+    fn test_read_only_exchange_records_signal_without_placing_order() {
+        use crate::models::ApiPermissions;
+        use crate::services::user_exchanges_service::check_key_permissions;
+
+        // Chave só leitura é aceita ao conectar (saque continua proibido)
+        let read_only = ApiPermissions { can_read: true, can_trade: false, can_withdraw: false, is_restricted: false };
+        assert!(check_key_permissions(&read_only).is_ok());
+        assert!(check_key_permissions(&ApiPermissions { can_withdraw: true, ..read_only.clone() }).is_err());
+
+        let exchange = DecryptedExchange {
+            exchange_id: "ex".into(), ccxt_id: "binance".into(), name: "Binance".into(),
+            api_key: "k".into(), api_secret: "s".into(), passphrase: None, is_active: true,
+            can_trade: Some(read_only.can_trade), sub_account: None,
+        };
+        let strategy = strategy_with_position("s1", 1.0, 100.0);
+
+        // Mesmo caminho do tick: avaliação, motivo de supressão e loop de ordens
+        let suppressed = suppression_reason(&strategy, &exchange, true);
+        assert_eq!(suppressed, Some(SuppressedReason::NoTradePermission));
+        let mut signals = Vec::new();
+        evaluate_exit(&strategy, 115.0, 1_000, &mut signals);
+        let mut orders_placed = 0;
+        for signal in &mut signals {
+            if admit_signal(&strategy, suppressed.as_ref(), signal, 115.0) {
+                orders_placed += 1;
+            }
+        }
+        assert_eq!(orders_placed, 0);
+
+        // O sinal fica registrado (e notificado) com o motivo, sem ordem
+        let take_profit = signals.iter().find(|s| s.signal_type == SignalType::TakeProfit).expect("take profit signal");
+        assert!(!take_profit.acted);
+        assert!(take_profit.message.contains("Ordem não enviada"), "{}", take_profit.message);
+        let (kept, _) = throttle_notifications(&strategy, signals.iter().collect(), 1_000);
+        assert!(kept.iter().any(|s| s.signal_type == SignalType::TakeProfit));
+
+        // Permissão desconhecida (exchange antiga) continua operando
+        let unknown = DecryptedExchange { can_trade: None, ..exchange };
+        let mut signal = take_profit.clone();
+        assert!(admit_signal(&strategy, suppression_reason(&strategy, &unknown, true).as_ref(), &mut signal, 115.0));
+    }
+
+    #[test]
+    fn test_unconfirmed_strategy_signals_without_live_orders_until_confirmed() {
+        let exchange = DecryptedExchange {
+            exchange_id: "ex".into(), ccxt_id: "binance".into(), name: "Binance".into(),
+            api_key: "k".into(), api_secret: "s".into(), passphrase: None, is_active: true,
+            can_trade: Some(true), sub_account: None,
+        };
+        let mut strategy = strategy_with_position("s1", 1.0, 100.0);
+        strategy.awaiting_live_confirmation = true;
+
+        let mut signals = Vec::new();
+        evaluate_exit(&strategy, 115.0, 0, &mut signals);
+        assert!(signals.iter().any(|s| s.signal_type == SignalType::TakeProfit && s.signal_type.places_order()));
+        assert!(signals.iter().all(|s| !s.acted));
+        assert!(!places_live_orders(&strategy, &exchange));
+
+        // Após confirm-live a mesma estratégia passa a enviar ordens
+        strategy.awaiting_live_confirmation = false;
+        assert!(places_live_orders(&strategy, &exchange));
+        assert!(!places_live_orders(&strategy, &DecryptedExchange { can_trade: Some(false), ..exchange }));
+    }
+
+    #[test]
+    fn test_new_strategy_for_paper_default_user_starts_in_paper_mode() {
+        use crate::services::user_settings_service::UserSettings;
+        let exchange = DecryptedExchange {
+            exchange_id: "ex".into(), ccxt_id: "binance".into(), name: "Binance".into(),
+            api_key: "k".into(), api_secret: "s".into(), passphrase: None, is_active: true,
+            can_trade: Some(true), sub_account: None,
+        };
+        let paper_user: UserSettings = serde_json::from_value(serde_json::json!({ "paper_trading_default": true })).unwrap();
+
+        // Usuário com paper por padrão: vale mesmo com o padrão global desligado
+        let mut strategy = strategy_with_position("s1", 0.0, 0.0);
+        strategy.awaiting_live_confirmation = new_strategy_paper_mode(None, Some(&paper_user), false);
+        assert!(strategy.awaiting_live_confirmation);
+        assert!(!places_live_orders(&strategy, &exchange));
+
+        // O request não tira do paper: live só pelo confirm-live
+        assert!(new_strategy_paper_mode(Some(false), Some(&paper_user), false));
+        // Sem conseguir ler a preferência, paper por segurança
+        assert!(new_strategy_paper_mode(Some(false), None, false));
+
+        // Usuário sem paper por padrão: request, preferência e por fim o global
+        let live_user = UserSettings { paper_trading_default: Some(false), ..Default::default() };
+        assert!(!new_strategy_paper_mode(None, Some(&live_user), true));
+        assert!(new_strategy_paper_mode(Some(true), Some(&live_user), false));
+        assert!(!new_strategy_paper_mode(Some(false), Some(&UserSettings::default()), true));
+        assert!(new_strategy_paper_mode(None, Some(&UserSettings::default()), true));
+        assert!(!new_strategy_paper_mode(None, Some(&UserSettings::default()), false));
+    }
+
+    #[tokio::test]
+    async fn test_trading_switch_blocks_and_restores_order_execution() {
+        use crate::utils::trading_switch::TRADING_DISABLED_ERROR;
+        let exchange = DecryptedExchange {
+            exchange_id: "ex".into(), ccxt_id: "binance".into(), name: "Binance".into(),
+            api_key: "k".into(), api_secret: "s".into(), passphrase: None, is_active: true,
+            can_trade: Some(true), sub_account: None,
+        };
+
+        TRADING_SWITCH.set_enabled(false);
+        let blocked = execute_order(&exchange, StrategyMode::Spot, "BTC/USDT", "market", "buy", 0.01, None).await;
+        let blocked_buy = execute_market_buy(&exchange, StrategyMode::Spot, "BTC/USDT", 50.0, 100.0).await;
+        let request = crate::models::CreateOrderWithCredsRequest {
+            ccxt_id: "binance".into(), exchange_name: "Binance".into(), api_key: "k".into(), api_secret: "s".into(),
+            passphrase: None, symbol: "BTC/USDT".into(), order_type: "market".into(), side: "buy".into(),
+            amount: 0.01, price: None, time_in_force: None,
+        };
+        let blocked_manual = crate::services::order_service::create_order_with_creds(&request).await;
+        TRADING_SWITCH.set_enabled(true);
+
+        assert_eq!(blocked.unwrap_err(), TRADING_DISABLED_ERROR);
+        assert_eq!(blocked_buy.unwrap_err(), TRADING_DISABLED_ERROR);
+        assert_eq!(blocked_manual.unwrap_err(), TRADING_DISABLED_ERROR);
+        assert!(TRADING_SWITCH.ensure_enabled().is_ok());
+    }
+
+    #[test]
+    fn test_stale_price_suppresses_execution() {
+        let now = 1_700_000_000;
+        let stale = PriceQuote { price: 115.0, timestamp: Some((now - 300) * 1000) };
+        let signal = check_price_staleness(&stale, now, Some(60)).expect("stale quote must skip the tick");
+        assert_eq!(signal.signal_type, SignalType::Info);
+        assert!(!signal.signal_type.places_order() && !signal.acted);
+
+        let fresh = PriceQuote { timestamp: Some((now - 5) * 1000), ..stale };
+        assert!(check_price_staleness(&fresh, now, Some(60)).is_none());
+        assert!(check_price_staleness(&PriceQuote { timestamp: None, ..stale }, now, Some(60)).is_none());
+        assert!(check_price_staleness(&stale, now, None).is_none());
+    }
+
+    #[test]
+    fn test_stale_ticker_skips_tick_evaluation_and_places_no_order() {
+        let now = 1_700_000_000;
+        let mut strategy = strategy_with_position("s1", 1.0, 100.0);
+        strategy.config.take_profit_percent = 10.0;
+
+        // Com preço fresco, 115 dispara o take profit
+        let mut fresh_signals = Vec::new();
+        evaluate_exit(&strategy, 115.0, now, &mut fresh_signals);
+        assert!(fresh_signals.iter().any(|s| s.signal_type.places_order()), "{:?}", fresh_signals);
+
+        // O mesmo preço com ticker de 5 minutos atrás: a tick para no guard
+        let stale = PriceQuote { price: 115.0, timestamp: Some((now - 300) * 1000) };
+        let result = stale_price_result(&strategy, &stale, now, Some(60)).expect("stale ticker must end the tick");
+        assert!(result.executions.is_empty());
+        assert!(result.new_status.is_none() && result.error.is_none());
+        assert_eq!(result.signals.len(), 1);
+        assert_eq!(result.signals[0].signal_type, SignalType::Info);
+        assert!(!result.signals.iter().any(|s| s.signal_type.places_order() || s.acted));
+
+        // Ticker fresco segue para a avaliação normal
+        let fresh = PriceQuote { timestamp: Some((now - 5) * 1000), ..stale };
+        assert!(stale_price_result(&strategy, &fresh, now, Some(60)).is_none());
+    }
+
+    #[test]
+    fn test_pnl_summary_sums_realized_and_unrealized() {
+        let mut closed = strategy_with_position("s1", 0.0, 0.0);
+        closed.position = None;
+        closed.total_pnl_usd = 120.0;
+        let mut open = strategy_with_position("s2", 0.5, 100.0);
+        open.total_pnl_usd = -20.0;
+        let mut eth = strategy_with_position("s3", 2.0, 50.0);
+        eth.symbol = "ETH/USDT".into();
+
+        let prices = HashMap::from([
+            (("ex".to_string(), "BTC/USDT".to_string()), Ok(140.0)),
+            (("ex".to_string(), "ETH/USDT".to_string()), Err("timeout".to_string())),
+        ]);
+        let rates = HashMap::from([("USDT".to_string(), Ok(1.0))]);
+        let summary = summarize_pnl(&[closed, open, eth], &prices, &rates);
+
+        // Realizado 120 - 20; não realizado (140 - 100) × 0.5 = 20; ETH sem preço
+        assert!((summary.realized_pnl - 100.0).abs() < 1e-9);
+        assert!((summary.unrealized_pnl - 20.0).abs() < 1e-9);
+        assert!((summary.total_pnl - 120.0).abs() < 1e-9);
+        let btc = summary.symbols.iter().find(|s| s.symbol == "BTC/USDT").unwrap();
+        assert_eq!((btc.strategies, btc.total_pnl, btc.unpriced), (2, 120.0, false));
+        assert!(summary.partial);
+        assert_eq!(summary.price_errors, vec![PnlPriceError {
+            exchange_id: "ex".into(), symbol: "ETH/USDT".into(), error: "timeout".into(),
+        }]);
     }
 
-    if had_gradual_sell {
-        update_set.insert(format!("{}.last_gradual_sell_at", p), now);
+    #[test]
+    fn test_pnl_summary_converts_each_quote_to_usd_before_summing() {
+        let mut usdt = strategy_with_position("s1", 0.0, 0.0);
+        usdt.position = None;
+        usdt.total_pnl_usd = 100.0;
+        let mut brl = strategy_with_position("s2", 0.0, 0.0);
+        brl.position = None;
+        brl.symbol = "BTC/BRL".into();
+        brl.total_pnl_usd = 500.0;
+        let mut eur = brl.clone();
+        eur.strategy_id = "s3".into();
+        eur.symbol = "ETH/EUR".into();
+        eur.total_pnl_usd = 50.0;
+
+        let rates = HashMap::from([
+            ("USDT".to_string(), Ok(1.0)),
+            ("BRL".to_string(), Ok(0.2)),
+            ("EUR".to_string(), Err("rate api down".to_string())),
+        ]);
+        let summary = summarize_pnl(&[usdt, brl, eur], &HashMap::new(), &rates);
+
+        // R$500 = $100; EUR sem taxa fica fora do total
+        assert!((summary.realized_pnl - 200.0).abs() < 1e-9);
+        assert!((summary.total_pnl - 200.0).abs() < 1e-9);
+        let brl_row = summary.symbols.iter().find(|s| s.symbol == "BTC/BRL").unwrap();
+        assert_eq!((brl_row.quote.as_str(), brl_row.total_pnl), ("BRL", 500.0));
+        assert!(summary.partial);
+        assert_eq!(summary.rate_errors, vec![PnlRateError { quote: "EUR".into(), error: "rate api down".into() }]);
     }
 
-    let position_closed = current_position.as_ref().map(|p| p.quantity <= 0.0001).unwrap_or(false);
-    if position_closed {
-        update_set.insert(format!("{}.position", p), mongodb::bson::Bson::Null);
-    } else if let Some(ref pos) = current_position {
-        if let Ok(pos_bson) = mongodb::bson::to_bson(pos) {
-            update_set.insert(format!("{}.position", p), pos_bson);
-        }
-    } else if result.price > 0.0 {
-        if let Some(ref position) = strategy.position {
-            if result.price > position.highest_price {
-                update_set.insert(format!("{}.position.highest_price", p), result.price);
-            }
-            update_set.insert(format!("{}.position.current_price", p), result.price);
-            if position.entry_price > 0.0 {
-                let unrealized_pnl = (result.price - position.entry_price) * position.quantity;
-                let unrealized_pnl_pct = ((result.price - position.entry_price) / position.entry_price) * 100.0;
-                update_set.insert(format!("{}.position.unrealized_pnl", p), unrealized_pnl);
-                update_set.insert(format!("{}.position.unrealized_pnl_percent", p), unrealized_pnl_pct);
-            }
-        }
+    #[test]
+    fn test_replay_flags_divergence_after_config_change() {
+        let mut strategy = strategy_with_position("s1", 1.0, 100.0);
+        strategy.config.base_price = 100.0;
+        strategy.config.take_profit_percent = 10.0;
+        strategy.config.fee_percent = 0.0;
+        strategy.executions = vec![StrategyExecution {
+            execution_id: "e1".into(), action: ExecutionAction::Buy, reason: "entry".into(),
+            price: 100.0, amount: 1.0, total: 100.0, fee: 0.0, pnl_usd: 0.0,
+            exchange_order_id: None, executed_at: 10, error_message: None,
+        }];
+        let signal = |signal_type: SignalType, price: f64, created_at: i64| StrategySignal {
+            signal_type, price, message: "original".into(), acted: true, price_change_percent: 0.0, created_at,
+        };
+        // Na época: TP de 10% disparou em 111; em 105 só monitorava
+        strategy.signals = vec![signal(SignalType::Info, 105.0, 20), signal(SignalType::TakeProfit, 111.0, 30)];
+
+        let replay = replay_signals(&strategy);
+        assert_eq!(replay.divergences, 0);
+        assert!(replay.steps.iter().all(|s| s.in_position));
+
+        // Usuário subiu o TP para 20%: o mesmo preço hoje não venderia
+        strategy.config.take_profit_percent = 20.0;
+        let replay = replay_signals(&strategy);
+        assert_eq!(replay.divergences, 1);
+        let step = &replay.steps[1];
+        assert_eq!((step.original.as_str(), step.replayed.as_str(), step.diverged), ("take_profit", "hold", true));
+        assert!(!replay.steps[0].diverged);
     }
 
-    let mut update_inc = doc! {};
-    if accumulated_pnl.abs() > 0.0001 {
-        update_inc.insert(format!("{}.total_pnl_usd", p), accumulated_pnl);
+    #[test]
+    fn test_replay_marks_trailing_and_indicator_steps_not_replayable() {
+        let mut strategy = strategy_with_position("s1", 1.0, 100.0);
+        strategy.config.take_profit_percent = 50.0;
+        strategy.config.fee_percent = 0.0;
+        strategy.config.max_drawdown_percent = Some(10.0);
+        strategy.executions = vec![StrategyExecution {
+            execution_id: "e1".into(), action: ExecutionAction::Buy, reason: "entry".into(),
+            price: 100.0, amount: 1.0, total: 100.0, fee: 0.0, pnl_usd: 0.0,
+            exchange_order_id: None, executed_at: 10, error_message: None,
+        }];
+        let signal = |signal_type: SignalType, price: f64, created_at: i64| StrategySignal {
+            signal_type, price, message: "original".into(), acted: true, price_change_percent: 0.0, created_at,
+        };
+
+        // Máxima 120 gravada num sinal: o drawdown de 10,8% em 107 é reproduzido
+        strategy.signals = vec![signal(SignalType::Info, 120.0, 20), signal(SignalType::MaxDrawdown, 107.0, 30)];
+        let replay = replay_signals(&strategy);
+        let step = &replay.steps[1];
+        assert_eq!((step.replayed.as_str(), step.replayable, step.diverged), ("max_drawdown", true, false));
+        assert_eq!(replay.not_replayable, 0);
+
+        // Máxima fora dos sinais: não dá para saber, não é divergência
+        strategy.signals = vec![signal(SignalType::MaxDrawdown, 107.0, 30)];
+        let replay = replay_signals(&strategy);
+        let step = &replay.steps[0];
+        assert_eq!((step.replayed.as_str(), step.replayable, step.diverged), ("hold", false, false));
+        assert!(step.not_replayable_reason.as_deref().unwrap().contains("trailing peak"));
+        assert_eq!((replay.divergences, replay.not_replayable), (0, 1));
+
+        // Entrada por indicador: só o preço foi gravado
+        strategy.executions.clear();
+        strategy.config.entry_condition = Some("rsi < 30".into());
+        strategy.signals = vec![signal(SignalType::Buy, 95.0, 40)];
+        let replay = replay_signals(&strategy);
+        let step = &replay.steps[0];
+        assert!(!step.in_position && !step.replayable && !step.diverged);
+        assert_eq!(replay.divergences, 0);
+
+        // Condição só com `price` continua comparável
+        strategy.config.entry_condition = Some("price < 90".into());
+        let replay = replay_signals(&strategy);
+        assert!(replay.steps[0].replayable);
+        assert_eq!(replay.divergences, 1);
     }
-    let new_exec_count = result.executions.iter()
-        .filter(|e| !matches!(e.action, ExecutionAction::BuyFailed | ExecutionAction::SellFailed))
-        .count() as i32;
-    if new_exec_count > 0 {
-        update_inc.insert(format!("{}.total_executions", p), new_exec_count);
+
+    #[test]
+    fn test_order_type_allowlist_by_strategy_mode() {
+        let futures_exchange = |capability: &str| capability == "swap";
+        let spot_only = |_: &str| false;
+
+        let err = StrategyMode::Spot.validate_order_type("reduce_only", futures_exchange).unwrap_err();
+        assert_eq!(err, "Order type 'reduce_only' is not allowed for spot strategies");
+        assert!(StrategyMode::Spot.validate_order_type("LIMIT", spot_only).is_ok());
+        // Trailing nativo da Binance spot (STOP_LOSS)
+        assert!(StrategyMode::Spot.validate_order_type("STOP_LOSS", spot_only).is_ok());
+
+        let futures = StrategyMode::Futures;
+        assert!(futures.validate_order_type("reduce_only", futures_exchange).is_ok());
+        assert!(futures.validate_order_type("reduce_only", spot_only).unwrap_err().contains("futures support"));
+
+        // Tipos reais por funcionalidade: trailing da Binance spot é STOP_LOSS, o percentual é market
+        let config = StrategyConfig { use_exchange_trailing: true, ..Default::default() };
+        assert_eq!(config.order_types(Some("STOP_LOSS")), vec!["market", "STOP_LOSS"]);
+        assert_eq!(config.order_types(Some("market")), vec!["market"]);
+        assert_eq!(config.order_types(None), vec!["market"]);
+        let futures_tp = StrategyConfig { mode: StrategyMode::Futures, exchange_take_profits: true, ..Default::default() };
+        assert_eq!(futures_tp.order_types(None), vec!["market", "limit", "reduce_only"]);
+
+        // Saídas em futuros vão reduceOnly; em spot sem params extras
+        let params = exit_order_params(StrategyMode::Futures, "limit", futures_exchange).unwrap();
+        assert_eq!(params.get("reduceOnly"), Some(&serde_json::json!(true)));
+        assert!(exit_order_params(StrategyMode::Spot, "limit", spot_only).unwrap().is_empty());
+        assert!(exit_order_params(StrategyMode::Futures, "limit", spot_only).is_err());
     }
 
-    for idx in &gradual_lot_indices_executed {
-        update_set.insert(format!("{}.config.gradual_lots.{}.executed", p, idx), true);
-        update_set.insert(format!("{}.config.gradual_lots.{}.executed_at", p, idx), now);
-        update_set.insert(format!("{}.config.gradual_lots.{}.executed_price", p, idx), result.price);
+    #[test]
+    fn test_config_order_types_checked_against_mode_and_exchange_has() {
+        use crate::services::exchange_service::ExchangeCapabilities;
+        let caps = |ccxt_id: &str, has: &[&str]| ExchangeCapabilities {
+            ccxt_id: ccxt_id.into(), supports_futures: true,
+            has: has.iter().map(|c| (c.to_string(), true)).collect(),
+        };
+        let binance_spot = caps("binance", &["createStopLossOrder"]);
+        let bybit = caps("bybit", &["swap", "createTrailingPercentOrder"]);
+
+        let spot = StrategyConfig { use_exchange_trailing: true, exchange_take_profits: false, ..Default::default() };
+        assert!(validate_config_order_types(&spot, &binance_spot).is_ok());
+
+        // Modo vem da config, não do símbolo: futuros numa exchange sem swap/future é recusado
+        let futures = StrategyConfig { mode: StrategyMode::Futures, exchange_take_profits: true, ..Default::default() };
+        assert!(validate_config_order_types(&futures, &bybit).is_ok());
+        let err = validate_config_order_types(&futures, &binance_spot).unwrap_err();
+        assert!(err.contains("futures support"), "{}", err);
+        // reduce_only dos TPs é exclusivo de derivativos: fora da allowlist spot
+        let spot_tp = StrategyConfig { exchange_take_profits: true, ..Default::default() };
+        assert!(validate_config_order_types(&spot_tp, &binance_spot).is_ok());
+        assert!(StrategyMode::Spot.validate_order_type("reduce_only", |_| true).is_err());
     }
 
-    if !gradual_lot_indices_executed.is_empty() {
-        let all_executed = strategy.config.gradual_lots.iter().enumerate().all(|(i, lot)| {
-            lot.executed || gradual_lot_indices_executed.contains(&i)
-        });
-        if all_executed && position_closed {
-            update_set.insert(format!("{}.status", p), mongodb::bson::to_bson(&StrategyStatus::Completed).unwrap_or_default());
-            update_set.insert(format!("{}.is_active", p), false);
-        }
+    #[test]
+    fn test_monitor_skips_strategies_on_open_breaker_exchange() {
+        let breaker = ExchangeBreaker::new(2, 60);
+        let mut down = strategy_with_position("s1", 1.0, 100.0);
+        down.exchange_id = "ex-down".into();
+        let healthy = strategy_with_position("s2", 1.0, 100.0);
+        let timeout = |s: &StrategyItem| TickResult {
+            strategy_id: s.strategy_id.clone(), symbol: s.symbol.clone(), price: 0.0,
+            signals: vec![], executions: vec![], new_status: None,
+            error: Some("RequestTimeout: binance GET /api/v3/ticker timed out".into()),
+            precision: None,
+        };
+
+        record_exchange_health(&breaker, &down, &timeout(&down), 1_000);
+        let (run, skipped) = filter_healthy_exchanges(vec![&down, &healthy], &breaker, 1_000);
+        assert_eq!((run.len(), skipped), (2, 0));
+
+        // Segunda falha abre o breaker: só a exchange saudável segue
+        record_exchange_health(&breaker, &down, &timeout(&down), 1_010);
+        let (run, skipped) = filter_healthy_exchanges(vec![&down, &healthy], &breaker, 1_020);
+        assert_eq!(run.iter().map(|s| s.strategy_id.as_str()).collect::<Vec<_>>(), vec!["s2"]);
+        assert_eq!(skipped, 1);
+
+        // Após o cooldown volta a ser tentada
+        let (run, _) = filter_healthy_exchanges(vec![&down, &healthy], &breaker, 1_070);
+        assert_eq!(run.len(), 2);
     }
 
-    let mut update_doc = doc! { "$set": update_set };
-    if !update_inc.is_empty() {
-        update_doc.insert("$inc", update_inc);
+    #[tokio::test]
+    async fn test_orders_on_same_account_spaced_by_min_interval() {
+        let spacer = OrderSpacer::default();
+        let interval = std::time::Duration::from_millis(80);
+        let account = ("ex".to_string(), "key-a".to_string());
+        let other = ("ex".to_string(), "key-b".to_string());
+
+        let start = std::time::Instant::now();
+        spacer.wait_turn(&account, interval).await;
+        let first = start.elapsed();
+        spacer.wait_turn(&account, interval).await;
+        let second = start.elapsed();
+        // Outra conta na mesma exchange não espera
+        spacer.wait_turn(&other, interval).await;
+        let third = start.elapsed();
+
+        assert!(first < interval);
+        assert!(second - first >= interval - std::time::Duration::from_millis(5), "{:?} -> {:?}", first, second);
+        assert!(third - second < interval);
+        assert!(spacer.reserve(&account, std::time::Duration::ZERO, start + interval * 4).is_zero());
     }
 
-    let array_filter = doc! { "elem.strategy_id": &strategy.strategy_id };
+    #[test]
+    fn test_asset_position_blends_weighted_average_entry() {
+        let binance = strategy_with_position("s1", 1.0, 100.0);
+        let mut okx = strategy_with_position("s2", 3.0, 120.0);
+        okx.exchange_id = "ex2".into();
+        okx.exchange_name = "OKX".into();
+        okx.symbol = "BTC/USDC".into();
+        let mut eth = strategy_with_position("s3", 2.0, 50.0);
+        eth.symbol = "ETH/USDT".into();
 
-    collection.update_one(
-        doc! { "user_id": user_id },
-        update_doc,
-    ).array_filters(vec![array_filter.clone()]).await
-        .map_err(|e| format!("Failed to persist tick: {}", e))?;
+        let prices = HashMap::from([
+            (("ex".to_string(), "BTC/USDT".to_string()), Ok(130.0)),
+            (("ex2".to_string(), "BTC/USDC".to_string()), Ok(125.0)),
+            (("ex3".to_string(), "BTC/BRL".to_string()), Ok(650.0)),
+        ]);
+        let mut rates = HashMap::from([
+            ("USDT".to_string(), Ok(1.0)),
+            ("USDC".to_string(), Ok(1.0)),
+        ]);
+        let position = summarize_asset_position("btc", &[binance.clone(), okx.clone(), eth], &prices, &rates);
 
-    // ── Persist signals ─────────────────────────────────────────────
-    // When automatic (monitor), only save actionable signals (TP, SL, GradualSell, Expired)
-    // to avoid inflating MongoDB with "monitoring..." info logs every 30s.
-    // When manual (user clicked Tick), save ALL signals including Info.
-    if !result.signals.is_empty() {
-        let signals_to_save: Vec<&StrategySignal> = if manual {
-            result.signals.iter().collect()
-        } else {
-            result.signals.iter()
-                .filter(|s| !matches!(s.signal_type, SignalType::Info))
-                .collect()
+        // (1 × 100 + 3 × 120) / 4 = 115; PNL (130-100)×1 + (125-120)×3 = 45
+        assert_eq!(position.asset, "BTC");
+        assert_eq!(position.positions.len(), 2);
+        assert!((position.total_quantity - 4.0).abs() < 1e-9);
+        assert!((position.average_entry_price - 115.0).abs() < 1e-9);
+        assert!((position.unrealized_pnl - 45.0).abs() < 1e-9);
+        assert!((position.unrealized_pnl_percent - 45.0 / 460.0 * 100.0).abs() < 1e-9);
+        assert!(!position.partial);
+
+        // Perna em BRL (1 BRL = 0,2 USD): entra convertida, não pelo preço nominal
+        let mut brl = strategy_with_position("s4", 1.0, 600.0);
+        brl.exchange_id = "ex3".into();
+        brl.symbol = "BTC/BRL".into();
+        let legs = [binance, okx, brl];
+        let missing_rate = summarize_asset_position("BTC", &legs, &prices, &rates);
+        assert!(missing_rate.partial);
+        assert_eq!(missing_rate.rate_errors[0].quote, "BRL");
+        assert_eq!(missing_rate.positions[2].unrealized_pnl, Some(50.0));
+        assert_eq!(missing_rate.positions[2].unrealized_pnl_usd, None);
+        assert!((missing_rate.average_entry_price - 115.0).abs() < 1e-9, "BRL leg kept out of USD totals");
+
+        rates.insert("BRL".to_string(), Ok(0.2));
+        let position = summarize_asset_position("BTC", &legs, &prices, &rates);
+        // Custo em USD: 100 + 360 + 120 = 580 para 5 BTC; PNL 30 + 15 + 50 × 0,2 = 55
+        assert!((position.total_quantity - 5.0).abs() < 1e-9);
+        assert!((position.average_entry_price - 116.0).abs() < 1e-9);
+        assert!((position.unrealized_pnl - 55.0).abs() < 1e-9);
+        assert_eq!(position.positions[2].quote, "BRL");
+        assert!(!position.partial);
+    }
+
+    #[test]
+    fn test_external_balance_reduction_warns_or_adjusts() {
+        let mut strategy = strategy_with_position("s1", 1.0, 100.0);
+
+        // Usuário vendeu 40% fora da estratégia
+        strategy.config.reconcile = ReconcilePolicy::Off;
+        assert!(check_position_drift(&strategy, 0.6, 110.0, 2.0).is_none());
+
+        strategy.config.reconcile = ReconcilePolicy::Warn;
+        let Some(PositionDrift::Flag(message)) = check_position_drift(&strategy, 0.6, 110.0, 2.0) else {
+            panic!("expected warning");
         };
-        let signals_bson: Vec<mongodb::bson::Bson> = signals_to_save.iter()
-            .filter_map(|s| mongodb::bson::to_bson(s).ok()).collect();
-        if !signals_bson.is_empty() {
-            let _ = collection.update_one(
-                doc! { "user_id": user_id },
-                doc! { "$push": { format!("{}.signals", p): { "$each": signals_bson, "$slice": -100 } } },
-            ).array_filters(vec![array_filter.clone()]).await;
-        }
+        assert!(message.contains("40.00%"), "{}", message);
+        // Diferença dentro da tolerância (taxas) e sobra de saldo não contam
+        assert!(check_position_drift(&strategy, 0.99, 110.0, 2.0).is_none());
+        assert!(check_position_drift(&strategy, 3.0, 110.0, 2.0).is_none());
+
+        strategy.config.reconcile = ReconcilePolicy::Adjust;
+        let Some(PositionDrift::Adjust(position, _)) = check_position_drift(&strategy, 0.6, 110.0, 2.0) else {
+            panic!("expected adjustment");
+        };
+        assert_eq!(position.quantity, 0.6);
+        assert!((position.total_cost - 60.0).abs() < 1e-9);
+        assert!((position.unrealized_pnl - 6.0).abs() < 1e-9);
+        assert!(matches!(check_position_drift(&strategy, 0.0, 110.0, 2.0), Some(PositionDrift::Flag(_))));
     }
 
-    if !result.executions.is_empty() {
-        let execs_bson: Vec<mongodb::bson::Bson> = result.executions.iter()
-            .filter_map(|e| mongodb::bson::to_bson(e).ok()).collect();
-        if !execs_bson.is_empty() {
-            let _ = collection.update_one(
-                doc! { "user_id": user_id },
-                doc! { "$push": { format!("{}.executions", p): { "$each": execs_bson } } },
-            ).array_filters(vec![array_filter]).await;
+    #[test]
+    fn test_buy_fee_included_in_entry_price_and_exit_pnl() {
+        let buy = StrategyExecution {
+            execution_id: "b1".into(), action: ExecutionAction::Buy, reason: "entry".into(),
+            price: 100.0, amount: 2.0, total: 200.0, fee: 0.4, pnl_usd: 0.0,
+            exchange_order_id: None, executed_at: 0, error_message: None,
+        };
+        let position = apply_buy_to_position(None, &buy, 100.0, 0);
+        assert!((position.total_cost - 200.4).abs() < 1e-9);
+        assert!((position.entry_price - 100.2).abs() < 1e-9);
+
+        // Vender tudo a 110 com taxa de venda 0.44: 20 de alta - 0.4 da compra - 0.44 da venda
+        let pnl = sell_pnl_usd(position.entry_price, 110.0, position.quantity, 0.44);
+        assert!((pnl - 19.16).abs() < 1e-9);
+
+        // Segunda compra entra no custo médio com a própria taxa
+        let add = StrategyExecution { amount: 1.0, total: 90.0, price: 90.0, fee: 0.3, ..buy };
+        let averaged = apply_buy_to_position(Some(position), &add, 90.0, 1);
+        assert!((averaged.total_cost - 290.7).abs() < 1e-9);
+        assert!((averaged.entry_price - 96.9).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_what_if_above_entry_shows_profit_and_tp_distance() {
+        let mut strategy = strategy_with_position("s1", 2.0, 100.0);
+        strategy.config.base_price = 100.0;
+        strategy.config.max_drawdown_percent = Some(10.0);
+        if let Some(pos) = strategy.position.as_mut() {
+            pos.highest_price = 120.0;
         }
+
+        let result = what_if(&strategy, 105.0).unwrap();
+        assert!((result.unrealized_pnl - 10.0).abs() < 1e-9);
+        assert!((result.unrealized_pnl_percent - 5.0).abs() < 1e-9);
+
+        // TP = 100 × (1 + 10% + 0.5% fee) = 110.5
+        let tp = result.levels.iter().find(|l| l.kind == "take_profit").unwrap();
+        assert!((tp.price - 110.5).abs() < 1e-9);
+        assert!((tp.distance - 5.5).abs() < 1e-9);
+        assert!(!tp.triggered);
+        let sl = result.levels.iter().find(|l| l.kind == "stop_loss").unwrap();
+        assert!((sl.distance + 10.0).abs() < 1e-9);
+
+        // 120 → 105 = 12.5% de drawdown: o trailing stop dispararia
+        assert!(result.trailing_stop.unwrap().triggered);
+        assert!(what_if(&strategy, 111.0).unwrap().levels[0].triggered);
+        assert!(what_if(&strategy, 0.0).is_err());
     }
 
-    Ok(())
-}
+    #[test]
+    fn test_market_buy_uses_cost_when_supported_else_computes_amount() {
+        assert_eq!(plan_market_buy(250.0, 50_000.0, true), Ok(MarketBuyPlan::Cost(250.0)));
+        assert_eq!(plan_market_buy(250.0, 50_000.0, false), Ok(MarketBuyPlan::Amount(0.005)));
+        // Sem preço só o caminho por custo funciona
+        assert_eq!(plan_market_buy(250.0, 0.0, true), Ok(MarketBuyPlan::Cost(250.0)));
+        assert!(plan_market_buy(250.0, 0.0, false).is_err());
+    }
 
-pub async fn activate_strategy(db: &MongoDB, strategy_id: &str, user_id: &str) -> Result<StrategyItem, String> {
-    let collection = db.collection::<UserStrategies>(COLLECTION);
+    #[test]
+    fn test_completed_parent_hands_position_to_child() {
+        let parent = strategy_with_position("dca", 0.5, 40_000.0);
+        let mut child = strategy_with_position("seller", 0.0, 0.0);
+        child.position = None;
+        child.status = StrategyStatus::Monitoring;
+        child.parent_strategy_id = Some("dca".into());
+        assert!(child.waiting_for_parent());
 
-    // ── Pre-check: find the strategy and validate ───────────────────
-    let user_doc = collection.find_one(doc! { "user_id": user_id }).await
-        .map_err(|e| format!("Failed to access database: {}", e))?
-        .ok_or_else(|| "No strategies found for your account.".to_string())?;
+        let handoff = parent_handoff(&StrategyStatus::Completed, parent.position.as_ref());
+        let ParentHandoff::Position(position) = handoff else { panic!("expected position handoff") };
+        inherit_position(&mut child, position, 1_000);
 
-    let strategy = user_doc.strategies.iter()
-        .find(|s| s.strategy_id == strategy_id)
-        .ok_or_else(|| "Strategy not found. It may have been deleted.".to_string())?;
+        let inherited = child.position.as_ref().unwrap();
+        assert_eq!((inherited.quantity, inherited.entry_price), (0.5, 40_000.0));
+        assert_eq!(child.status, StrategyStatus::InPosition);
+        assert_eq!(child.started_at, 1_000);
+        assert!(!child.waiting_for_parent());
+
+        // Um único update move a posição: filha recebe, pai fica sem
+        let (filter, update, array_filters) = handoff_update("u1", "dca", &child, 1_000).unwrap();
+        assert_eq!(filter.get_str("user_id"), Ok("u1"));
+        let guard = filter.get_document("strategies").unwrap().get_document("$elemMatch").unwrap();
+        assert_eq!(guard.get_str("strategy_id"), Ok("dca"));
+        let set = update.get_document("$set").unwrap();
+        assert_eq!(set.get_document("strategies.$[child].position").unwrap().get_f64("quantity"), Ok(0.5));
+        assert_eq!(set.get("strategies.$[parent].position"), Some(&mongodb::bson::Bson::Null));
+        assert_eq!(set.get_str("strategies.$[child].status"), Ok("in_position"));
+        assert_eq!(array_filters, vec![
+            doc! { "parent.strategy_id": "dca" },
+            doc! { "child.strategy_id": &child.strategy_id },
+        ]);
 
-    if strategy.is_active && strategy.status == StrategyStatus::Monitoring {
-        return Err(format!("Strategy '{}' is already active and monitoring.", strategy.name));
+        // Pai encerrado sem posição ou estopado: filhas pausadas; pai com erro: filhas aguardam
+        assert!(matches!(parent_handoff(&StrategyStatus::Completed, None), ParentHandoff::PauseChildren(_)));
+        assert!(matches!(parent_handoff(&StrategyStatus::StoppedOut, parent.position.as_ref()), ParentHandoff::PauseChildren(_)));
+        assert!(matches!(parent_handoff(&StrategyStatus::Error, parent.position.as_ref()), ParentHandoff::Wait));
+
+        let strategies = vec![parent];
+        assert!(validate_parent(&strategies, "dca", "ex", "BTC/USDT").is_ok());
+        assert!(validate_parent(&strategies, "dca", "ex", "ETH/USDT").is_err());
     }
 
-    if strategy.config.base_price <= 0.0 {
-        return Err("Cannot activate: base price is 0 or invalid. Update the strategy configuration first.".to_string());
+    #[test]
+    fn test_consecutive_losses_pause_strategy_and_win_resets_counter() {
+        let sell = |pnl_usd: f64| StrategyExecution {
+            execution_id: "e".into(), action: ExecutionAction::Sell, reason: "stop_loss".into(),
+            price: 100.0, amount: 1.0, total: 100.0, fee: 0.0, pnl_usd,
+            exchange_order_id: None, executed_at: 0, error_message: None,
+        };
+        let mut strategy = strategy_with_position("s1", 1.0, 100.0);
+        strategy.config.max_consecutive_losses = Some(3);
+
+        strategy.consecutive_losses = update_loss_streak(0, &[sell(-5.0), sell(-2.0)]);
+        assert_eq!(strategy.consecutive_losses, 2);
+        assert!(loss_streak_breaker(&strategy, strategy.consecutive_losses, 95.0, 1).is_none());
+
+        // Venda lucrativa zera a sequência
+        strategy.consecutive_losses = update_loss_streak(strategy.consecutive_losses, &[sell(8.0)]);
+        assert_eq!(strategy.consecutive_losses, 0);
+
+        // Compras, falhas e PnL zero não contam
+        let failed = StrategyExecution { action: ExecutionAction::SellFailed, ..sell(-1.0) };
+        let streak = update_loss_streak(0, &[sell(-1.0), failed, sell(0.0), sell(-1.0), sell(-3.0)]);
+        assert_eq!(streak, 3);
+        let signal = loss_streak_breaker(&strategy, streak, 95.0, 1).expect("breaker trips at the limit");
+        assert_eq!(signal.signal_type, SignalType::LossStreak);
+        assert!(signal.message.contains("3 vendas seguidas"));
+
+        // Sem limite configurado não pausa
+        strategy.config.max_consecutive_losses = None;
+        assert!(loss_streak_breaker(&strategy, 10, 95.0, 1).is_none());
+        assert!(StrategyConfig { max_consecutive_losses: Some(0), ..Default::default() }.validate().is_err());
     }
 
-    let now = chrono::Utc::now().timestamp();
-    let p = "strategies.$[elem]";
+    #[test]
+    fn test_check_interval_follows_strategy_status() {
+        let mut strategy = strategy_with_position("s1", 1.0, 100.0);
+        strategy.status = StrategyStatus::Monitoring;
+        assert_eq!(check_interval_secs(&strategy), DEFAULT_CHECK_INTERVAL_SECS);
 
-    log::info!("▶️ Activating strategy '{}' ({}) for user {}", strategy.name, strategy_id, user_id);
+        strategy.config.monitoring_interval_secs = Some(300);
+        strategy.config.in_position_interval_secs = Some(10);
+        assert_eq!(check_interval_secs(&strategy), 300);
+        strategy.status = StrategyStatus::Idle;
+        assert_eq!(check_interval_secs(&strategy), 300);
 
-    collection.update_one(
-        doc! { "user_id": user_id },
-        doc! { "$set": {
-            format!("{}.status", p): "monitoring",
-            format!("{}.is_active", p): true,
-            format!("{}.error_message", p): mongodb::bson::Bson::Null,
-            format!("{}.updated_at", p): now,
-            "updated_at": now,
-        }},
-    ).array_filters(vec![doc! { "elem.strategy_id": strategy_id }]).await
-        .map_err(|e| format!("Failed to activate strategy: {}", e))?;
+        // Entrou em posição: aperta o intervalo
+        strategy.status = StrategyStatus::InPosition;
+        assert_eq!(check_interval_secs(&strategy), 10);
+        strategy.status = StrategyStatus::GradualSelling;
+        assert_eq!(check_interval_secs(&strategy), 10);
+    }
 
-    let user_doc = collection.find_one(doc! { "user_id": user_id }).await
-        .map_err(|e| format!("Failed to fetch updated strategy: {}", e))?
-        .ok_or_else(|| "Strategy activated but failed to retrieve updated data.".to_string())?;
+    #[test]
+    fn test_order_book_imbalance_drives_scalping_entry_and_exit() {
+        use crate::models::{OrderBook, OrderBookImbalanceConfig};
 
-    user_doc.strategies.into_iter()
-        .find(|s| s.strategy_id == strategy_id)
-        .ok_or_else(|| "Strategy activated but not found in response.".to_string())
-}
+        let book = OrderBook {
+            bids: vec![[99.9, 5.0], [99.8, 4.0], [99.7, 3.0], [99.6, 100.0]],
+            asks: vec![[100.1, 2.0], [100.2, 2.0], [100.3, 2.0]],
+        };
+        // Top 3 níveis: 12 de compra / 6 de venda; o 4º nível de compra fica de fora
+        assert_eq!(indicators::order_book_imbalance(&book, 3), Some(2.0));
+        // Book vazio ou raso não gera leitura
+        assert_eq!(indicators::order_book_imbalance(&OrderBook::default(), 10), None);
+        let thin = OrderBook { bids: book.bids.clone(), asks: vec![[100.1, 50.0]] };
+        assert_eq!(indicators::order_book_imbalance(&thin, 10), None);
 
-pub async fn pause_strategy(db: &MongoDB, strategy_id: &str, user_id: &str) -> Result<StrategyItem, String> {
-    let collection = db.collection::<UserStrategies>(COLLECTION);
+        let mut strategy = strategy_with_position("s1", 1.0, 100.0);
+        strategy.position = None;
+        strategy.status = StrategyStatus::Monitoring;
+        strategy.config.entry_amount_usd = Some(50.0);
+        strategy.config.auto_entry = true;
+        strategy.config.order_book_imbalance = Some(OrderBookImbalanceConfig { levels: 3, entry_ratio: 2.5, exit_ratio: Some(2.0) });
 
-    // ── Pre-check ───────────────────────────────────────────────────
-    let user_doc = collection.find_one(doc! { "user_id": user_id }).await
-        .map_err(|e| format!("Failed to access database: {}", e))?
-        .ok_or_else(|| "No strategies found for your account.".to_string())?;
+        let mut signals = Vec::new();
+        assert!(!evaluate_order_book_entry(&strategy, Some(2.0), None, 100.0, 1, &mut signals));
+        assert!(!evaluate_order_book_entry(&strategy, None, None, 100.0, 1, &mut signals));
+        assert!(evaluate_order_book_entry(&strategy, Some(2.5), None, 100.0, 1, &mut signals));
+        assert_eq!(signals.len(), 1);
+        assert_eq!(signals[0].signal_type, SignalType::Buy);
 
-    let strategy = user_doc.strategies.iter()
-        .find(|s| s.strategy_id == strategy_id)
-        .ok_or_else(|| "Strategy not found. It may have been deleted.".to_string())?;
+        // Com entry_condition, o book só compra quando a condição também passa
+        strategy.config.entry_condition = Some("rsi < 30".into());
+        let mut signals = Vec::new();
+        let oversold: HashMap<&str, f64> = HashMap::from([("rsi", 25.0), ("price", 100.0)]);
+        let neutral: HashMap<&str, f64> = HashMap::from([("rsi", 55.0), ("price", 100.0)]);
+        assert!(!evaluate_order_book_entry(&strategy, Some(3.0), Some(&neutral), 100.0, 1, &mut signals));
+        assert!(!evaluate_order_book_entry(&strategy, Some(3.0), None, 100.0, 1, &mut signals), "variables not loaded");
+        assert!(evaluate_order_book_entry(&strategy, Some(3.0), Some(&oversold), 100.0, 1, &mut signals));
+        assert_eq!(signals.len(), 1);
 
-    if !strategy.is_active || strategy.status == StrategyStatus::Paused {
-        return Err(format!("Strategy '{}' is already paused.", strategy.name));
+        // Saída: vendedores dominando 2x com a posição no lucro
+        let mut in_position = strategy_with_position("s2", 1.0, 100.0);
+        in_position.config.order_book_imbalance = strategy.config.order_book_imbalance.clone();
+        let mut signals = Vec::new();
+        assert!(!evaluate_order_book_exit(&in_position, Some(0.8), 101.0, 1, &mut signals));
+        assert!(!evaluate_order_book_exit(&in_position, Some(0.5), 99.0, 1, &mut signals), "no exit at a loss");
+        assert!(evaluate_order_book_exit(&in_position, Some(0.5), 101.0, 1, &mut signals));
+        assert_eq!(signals[0].signal_type, SignalType::TakeProfit);
+
+        assert!(StrategyConfig {
+            order_book_imbalance: Some(OrderBookImbalanceConfig { levels: 10, entry_ratio: 0.9, exit_ratio: None }),
+            ..Default::default()
+        }.validate().is_err());
     }
 
-    match strategy.status {
-        StrategyStatus::Completed | StrategyStatus::StoppedOut | StrategyStatus::Expired => {
-            return Err(format!(
-                "Cannot pause strategy '{}' — it is already in terminal state '{}'. Create a new strategy instead.",
-                strategy.name, strategy.status
-            ));
+    #[test]
+    fn test_exchange_pause_only_touches_that_exchange_and_resume_restores() {
+        let trailing = TrackedOrder {
+            order_id: "trail-1".into(), side: "sell".into(), order_type: NATIVE_TRAILING_ORDER_TYPE.into(),
+            amount: 1.0, price: None, created_at: 0, ttl_secs: None, lot_number: None,
+        };
+        let mut strategies = [
+            strategy_with_position("binance-pos", 1.0, 100.0),
+            strategy_with_position("binance-idle", 0.0, 0.0),
+            strategy_with_position("okx-pos", 1.0, 100.0),
+            strategy_with_position("binance-done", 0.0, 0.0),
+        ];
+        strategies[1].position = None;
+        strategies[1].status = StrategyStatus::Monitoring;
+        strategies[2].exchange_id = "okx".into();
+        strategies[3].status = StrategyStatus::Completed;
+        strategies[3].is_active = false;
+        strategies[0].config.use_exchange_trailing = true;
+        strategies[0].config.max_drawdown_percent = Some(5.0);
+        strategies[0].open_orders = vec![trailing];
+        assert_eq!(missing_exchange_protection(&strategies[0]), (false, false));
+
+        for strategy in strategies.iter_mut().filter(|s| pausable_on_exchange(s, "ex")) {
+            let pause = ExchangePause {
+                ccxt_id: "binance".into(), previous_status: strategy.status.clone(),
+                reason: "outage".into(), paused_at: 1,
+            };
+            apply_exchange_pause(strategy, pause);
         }
-        _ => {}
+        assert!(strategies[..2].iter().all(|s| s.status == StrategyStatus::Paused && !s.is_active));
+        assert!(strategies[0].error_message.as_deref().unwrap().contains("outage"));
+        // As ordens da exchange ficam lá: o stop nativo continua protegendo a posição
+        assert_eq!(strategies[0].open_orders.len(), 1);
+        assert_eq!(strategies[2].status, StrategyStatus::InPosition);
+        assert!(strategies[2].is_active && strategies[2].exchange_pause.is_none());
+        assert_eq!(strategies[3].status, StrategyStatus::Completed);
+
+        // Resume de outra exchange não mexe; o da exchange certa volta ao status anterior
+        assert!(!restore_exchange_pause(&mut strategies[0], "okx"));
+        let restored: Vec<bool> = strategies.iter_mut().map(|s| restore_exchange_pause(s, "binance")).collect();
+        assert_eq!(restored, vec![true, true, false, false]);
+        assert_eq!(strategies[0].status, StrategyStatus::InPosition);
+        assert_eq!(strategies[1].status, StrategyStatus::Monitoring);
+        assert!(strategies[..2].iter().all(|s| s.is_active && s.error_message.is_none() && s.exchange_pause.is_none()));
+
+        // Stop cancelado pela exchange durante a pausa: o resume recoloca; TP na exchange também
+        strategies[0].open_orders.clear();
+        assert_eq!(missing_exchange_protection(&strategies[0]), (true, false));
+        strategies[0].config.exchange_take_profits = true;
+        assert_eq!(missing_exchange_protection(&strategies[0]), (true, true));
+        // Sem posição não há o que proteger
+        strategies[1].config = strategies[0].config.clone();
+        assert_eq!(missing_exchange_protection(&strategies[1]), (false, false));
     }
 
-    let now = chrono::Utc::now().timestamp();
-    let p = "strategies.$[elem]";
+    #[test]
+    fn test_bnb_fee_converted_to_quote_for_cost_and_pnl() {
+        use crate::models::OrderFee;
 
-    log::info!("⏸️ Pausing strategy '{}' ({}) for user {}", strategy.name, strategy_id, user_id);
+        // Compra 1 BTC @ 60.000 USDT com taxa de 0,075 BNB (BNB = 600 USDT → 45 USDT)
+        let bnb_fee = OrderFee { currency: "BNB".into(), cost: 0.075 };
+        let bnb_price = |pair: &str| (pair == "BNB/USDT").then_some(600.0);
+        let fee = bnb_fee.cost_in_quote("BTC/USDT", Some(60_000.0), bnb_price).unwrap();
+        assert!((fee - 45.0).abs() < 1e-9);
 
-    collection.update_one(
-        doc! { "user_id": user_id },
-        doc! { "$set": {
-            format!("{}.status", p): "paused",
-            format!("{}.is_active", p): false,
-            format!("{}.updated_at", p): now,
-            "updated_at": now,
-        }},
-    ).array_filters(vec![doc! { "elem.strategy_id": strategy_id }]).await
-        .map_err(|e| format!("Failed to pause strategy: {}", e))?;
+        let buy = StrategyExecution {
+            execution_id: "b".into(), action: ExecutionAction::Buy, reason: "entry".into(),
+            price: 60_000.0, amount: 1.0, total: 60_000.0, fee, pnl_usd: 0.0,
+            exchange_order_id: None, executed_at: 0, error_message: None,
+        };
+        let position = apply_buy_to_position(None, &buy, 60_000.0, 0);
+        assert!((position.total_cost - 60_045.0).abs() < 1e-9);
+        // Venda @ 61.000 com a mesma taxa em BNB: PnL = 1.000 - 45 - 45
+        let sell_fee = bnb_fee.cost_in_quote("BTC/USDT", Some(61_000.0), bnb_price).unwrap();
+        assert!((sell_pnl_usd(position.entry_price, 61_000.0, 1.0, sell_fee) - 910.0).abs() < 1e-6);
 
-    let user_doc = collection.find_one(doc! { "user_id": user_id }).await
-        .map_err(|e| format!("Failed to fetch updated strategy: {}", e))?
-        .ok_or_else(|| "Strategy paused but failed to retrieve updated data.".to_string())?;
+        // Taxa na quote fica como está; no ativo base vira quote pelo preço de execução
+        let usdt = OrderFee { currency: "USDT".into(), cost: 60.0 };
+        assert_eq!(usdt.cost_in_quote("BTC/USDT:USDT", None, |_| None), Some(60.0));
+        let btc = OrderFee { currency: "BTC".into(), cost: 0.001 };
+        assert_eq!(btc.cost_in_quote("BTC/USDT", Some(60_000.0), |_| None), Some(60.0));
+        // Sem cotação do terceiro ativo não há conversão (a taxa não é somada crua)
+        assert_eq!(bnb_fee.cost_in_quote("BTC/USDT", Some(60_000.0), |_| None), None);
+    }
 
-    user_doc.strategies.into_iter()
-        .find(|s| s.strategy_id == strategy_id)
-        .ok_or_else(|| "Strategy paused but not found in response.".to_string())
-}
+    #[test]
+    fn test_order_rejections_tagged_by_error_type() {
+        let strategy = strategy_with_position("rejections", 1.0, 100.0);
+        let raws = [
+            "InsufficientFunds: binance Account has insufficient balance for requested action.",
+            "InvalidOrder: binance Filter failure: NOTIONAL",
+            "RateLimitExceeded: binance 429 Too Many Requests",
+            "BadSymbol: binance does not have market symbol XYZ/USDT",
+        ];
+        let min_notional_count = || crate::api::metrics::order_rejection_counts().get("min_notional").copied().unwrap_or(0);
+        let before = min_notional_count();
+        let tags: Vec<String> = raws.iter()
+            .map(|raw| {
+                let message = report_order_rejection(&strategy, "sell", raw);
+                // error_message parseável: `[tipo] mensagem`
+                let (tag, text) = message.strip_prefix('[').and_then(|m| m.split_once("] ")).unwrap();
+                assert!(!text.is_empty());
+                tag.to_string()
+            })
+            .collect();
 
-pub async fn process_active_strategies(db: &MongoDB) -> Result<ProcessResult, String> {
-    let collection = db.collection::<UserStrategies>(COLLECTION);
-    let now = chrono::Utc::now().timestamp();
+        assert_eq!(tags, ["insufficient_funds", "min_notional", "rate_limit", "bad_symbol"]);
+        assert!(min_notional_count() > before);
+        assert_eq!(order_error_type("ExchangeError: something odd"), "unknown");
+    }
 
-    let filter = doc! {
-        "strategies": {
-            "$elemMatch": {
-                "is_active": true,
-                "status": { "$in": ["idle", "monitoring", "in_position", "gradual_selling"] }
-            }
-        }
-    };
+    #[test]
+    fn test_suppressed_buy_notifies_reason_without_order() {
+        let exchange = DecryptedExchange {
+            exchange_id: "ex".into(), ccxt_id: "binance".into(), name: "Binance".into(),
+            api_key: "k".into(), api_secret: "s".into(), passphrase: None, is_active: true,
+            can_trade: Some(true), sub_account: None,
+        };
+        let mut strategy = strategy_with_position("s1", 0.0, 0.0);
+        strategy.position = None;
+        strategy.config.entry_amount_usd = Some(50.0);
+        strategy.config.auto_entry = true;
+        strategy.config.base_price = 100.0;
 
-    let mut cursor = collection.find(filter).await
-        .map_err(|e| format!("Failed to query: {}", e))?;
+        let mut signals = Vec::new();
+        evaluate_entry(&strategy, 99.0, 1_000, None, &mut signals);
+        let buy = signals.iter_mut().find(|s| s.signal_type == SignalType::Buy).expect("entry signal");
 
-    use futures::stream::StreamExt;
-    let mut total = 0;
-    let mut processed = 0;
-    let mut errors = 0;
-    let mut signals_generated = 0;
-    let mut orders_executed = 0;
+        // Safe-mode: sinal vira notificação com o motivo, sem ordem
+        let reason = suppression_reason(&strategy, &exchange, false).expect("trading disabled");
+        assert_eq!(reason, SuppressedReason::TradingDisabled);
+        mark_suppressed(buy, &reason);
+        assert!(!buy.acted);
+        assert!(buy.message.contains("safe-mode"), "{}", buy.message);
+        let (kept, _) = throttle_notifications(&strategy, signals.iter().collect(), 1_000);
+        assert!(kept.iter().any(|s| s.signal_type == SignalType::Buy && s.message.contains("Ordem não enviada")));
 
-    while let Some(result) = cursor.next().await {
-        match result {
-            Ok(user_doc) => {
-                let user_id = user_doc.user_id.clone();
-                for strategy in &user_doc.strategies {
-                    if !strategy.is_active { continue; }
-                    match strategy.status {
-                        StrategyStatus::Idle | StrategyStatus::Monitoring
-                        | StrategyStatus::InPosition | StrategyStatus::GradualSelling => {}
-                        _ => continue,
-                    }
-                    total += 1;
-                    let last_checked = strategy.last_checked_at.unwrap_or(0);
-                    if now - last_checked < 30 { continue; }
+        // Chave sem trade tem precedência; trading ligado e chave ok não suprime
+        let read_only = DecryptedExchange { can_trade: Some(false), ..exchange.clone() };
+        assert_eq!(suppression_reason(&strategy, &read_only, true), Some(SuppressedReason::NoTradePermission));
+        assert_eq!(suppression_reason(&strategy, &exchange, true), None);
+        assert!(SuppressedReason::BelowMinimum { amount: 4.0, min_cost: 10.0 }.describe().contains("$10.00"));
+    }
 
-                    let tick_result = tick(db, &user_id, strategy).await;
-                    signals_generated += tick_result.signals.len();
-                    orders_executed += tick_result.executions.len();
+    #[tokio::test]
+    async fn test_limit_entry_placed_at_offset_then_falls_back_to_market() {
+        let config = LimitEntryConfig { offset_percent: 0.5, timeout_secs: 300 };
+        let target = limit_entry_price(100.0, config.offset_percent);
+        assert!((target - 99.5).abs() < 1e-9);
 
-                    match persist_tick_result(db, &user_id, strategy, &tick_result, false).await {
-                        Ok(_) => processed += 1,
-                        Err(e) => {
-                            log::error!("[Strategy {}] Persist failed: {}", tick_result.strategy_id, e);
-                            errors += 1;
-                        }
-                    }
-                    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
-                }
-            }
-            Err(e) => {
-                log::error!("Error reading user_strategy: {}", e);
-                errors += 1;
+        let order = TrackedOrder {
+            order_id: "limit-1".into(), side: "buy".into(), order_type: LIMIT_ENTRY_ORDER_TYPE.into(),
+            amount: 0.5, price: Some(target), created_at: 1_000, ttl_secs: None, lot_number: None,
+        };
+        let open = || async {
+            Ok(CcxtOrder {
+                id: "limit-1".into(), symbol: "BTC/USDT".into(), status: "open".into(), side: "buy".into(),
+                order_type: "limit".into(), price: Some(99.5), average: None, amount: Some(0.5), filled: Some(0.0),
+                remaining: Some(0.5), cost: None, fee: None, timestamp: None, datetime: None,
+            })
+        };
+        let market_costs = Mutex::new(Vec::new());
+        let market_buy = |cost: f64| {
+            market_costs.lock().unwrap().push(cost);
+            async move {
+                Ok(OrderResult {
+                    order_id: "mkt-1".into(), status: "closed".into(), filled: Some(0.5),
+                    avg_price: Some(100.1), cost: Some(50.05), fee: Some(0.05),
+                })
             }
-        }
+        };
+        let no_place = |_: f64, _: f64| async { Err::<TrackedOrder, String>("unexpected re-place".into()) };
+
+        // Antes do timeout, com o preço parado: só espera
+        let waiting = advance_limit_entry(&config, &order, 100.0, 1_200,
+            |_| open(), |_| async { Ok(true) }, no_place, &market_buy).await;
+        assert!(waiting.resolved.is_empty() && waiting.executions.is_empty() && waiting.error.is_none());
+        assert!(market_costs.lock().unwrap().is_empty());
+
+        // Timeout sem execução: cancela a limit e compra o restante a mercado
+        let cancelled = Mutex::new(Vec::new());
+        let fallback = advance_limit_entry(&config, &order, 100.0, 1_300,
+            |_| open(),
+            |order_id| {
+                cancelled.lock().unwrap().push(order_id);
+                async { Ok(true) }
+            },
+            no_place, &market_buy).await;
+        assert_eq!(*cancelled.lock().unwrap(), vec!["limit-1".to_string()]);
+        assert_eq!(fallback.resolved, vec!["limit-1".to_string()]);
+        assert_eq!(*market_costs.lock().unwrap(), vec![50.0]);
+        assert_eq!(fallback.executions.len(), 1);
+        assert_eq!(fallback.executions[0].reason, "entry_market_fallback");
+        assert_eq!(fallback.executions[0].exchange_order_id.as_deref(), Some("mkt-1"));
+        assert!((fallback.filled - 0.5).abs() < 1e-9);
     }
 
-    Ok(ProcessResult { total, processed, errors, signals_generated, orders_executed })
-}
+    #[tokio::test]
+    async fn test_limit_entry_fill_racing_the_cancel_and_partial_fill_on_chase() {
+        let config = LimitEntryConfig { offset_percent: 0.5, timeout_secs: 300 };
+        let order = TrackedOrder {
+            order_id: "limit-1".into(), side: "buy".into(), order_type: LIMIT_ENTRY_ORDER_TYPE.into(),
+            amount: 0.5, price: Some(99.5), created_at: 1_000, ttl_secs: None, lot_number: None,
+        };
+        // Respostas da exchange em sequência: antes e depois do cancelamento
+        let reads = |states: Vec<(&str, f64)>| {
+            let queue: Mutex<Vec<CcxtOrder>> = Mutex::new(states.into_iter().rev()
+                .map(|(status, filled)| exchange_order(&order, status, filled, 99.5)).collect());
+            move |_: String| {
+                let next = queue.lock().unwrap().pop().ok_or_else(|| "exchange unavailable".to_string());
+                async move { next }
+            }
+        };
+        let market_costs = Mutex::new(Vec::new());
+        let market_buy = |cost: f64| {
+            market_costs.lock().unwrap().push(cost);
+            async move {
+                Ok(OrderResult {
+                    order_id: "mkt-1".into(), status: "closed".into(), filled: Some(cost / 100.0),
+                    avg_price: Some(100.0), cost: Some(cost), fee: None,
+                })
+            }
+        };
+        let placed = Mutex::new(Vec::new());
+        let place = |amount: f64, price: f64| {
+            placed.lock().unwrap().push((amount, price));
+            let replaced = TrackedOrder { order_id: "limit-2".into(), amount, price: Some(price), ..order.clone() };
+            async move { Ok(replaced) }
+        };
+        let cancel = |_: String| async { Ok(true) };
 
-#[derive(Debug, Clone, serde::Serialize)]
-pub struct ProcessResult {
-    pub total: usize,
-    pub processed: usize,
-    pub errors: usize,
-    pub signals_generated: usize,
-    pub orders_executed: usize,
+        // Timeout, mas a ordem executou inteira enquanto era cancelada: nada a mercado
+        let raced = advance_limit_entry(&config, &order, 100.0, 1_300,
+            reads(vec![("open", 0.0), ("closed", 0.5)]), cancel, &place, &market_buy).await;
+        assert_eq!(raced.resolved, vec!["limit-1".to_string()]);
+        assert!((raced.filled - 0.5).abs() < 1e-9 && raced.executions.len() == 1);
+        assert!(market_costs.lock().unwrap().is_empty(), "no double buy");
+
+        // Execução parcial no cancelamento: o fallback compra só o que faltou
+        let partial = advance_limit_entry(&config, &order, 100.0, 1_300,
+            reads(vec![("open", 0.1), ("canceled", 0.3)]), cancel, &place, &market_buy).await;
+        assert_eq!(*market_costs.lock().unwrap(), vec![20.0]);
+        assert!((partial.filled - 0.5).abs() < 1e-9);
+
+        // Preço subiu com execução parcial: completa a mercado em vez de perseguir,
+        // então a estratégia entra em posição sem limit pendente
+        market_costs.lock().unwrap().clear();
+        let chased = advance_limit_entry(&config, &order, 102.0, 1_100,
+            reads(vec![("open", 0.2), ("canceled", 0.2)]), cancel, &place, &market_buy).await;
+        assert!(chased.replaced.is_none() && placed.lock().unwrap().is_empty());
+        assert_eq!(market_costs.lock().unwrap().len(), 1);
+        assert!((market_costs.lock().unwrap()[0] - 0.3 * 102.0).abs() < 1e-9);
+        assert_eq!(chased.executions.iter().map(|e| e.reason.as_str()).collect::<Vec<_>>(), vec!["entry", "entry_market_fallback"]);
+
+        // Sem execução o alvo é perseguido com a quantidade cheia
+        market_costs.lock().unwrap().clear();
+        let moved = advance_limit_entry(&config, &order, 102.0, 1_100,
+            reads(vec![("open", 0.0), ("canceled", 0.0)]), cancel, &place, &market_buy).await;
+        assert_eq!(moved.replaced.as_ref().map(|o| (o.order_id.as_str(), o.created_at)), Some(("limit-2", 1_000)));
+        assert_eq!(placed.lock().unwrap()[0].0, 0.5);
+        assert!(moved.filled == 0.0 && market_costs.lock().unwrap().is_empty());
+
+        // Cancelou mas não conseguiu ler a ordem final: segue rastreada para o próximo tick
+        let unknown = advance_limit_entry(&config, &order, 100.0, 1_300,
+            reads(vec![("open", 0.0)]), cancel, &place, &market_buy).await;
+        assert!(unknown.resolved.is_empty() && unknown.executions.is_empty());
+        assert!(unknown.error.unwrap().contains("final fill"));
+    }
 }
@@ -2,11 +2,21 @@ use crate::{
     ccxt::CCXTClient,
     database::MongoDB,
     models::{DecryptedExchange, UserExchanges, ExchangeCatalog},
+    services::token_service::ExchangeCredentials,
     utils::crypto::decrypt_fernet_via_python,
+    utils::thread_pool::spawn_ccxt_paced,
 };
+use actix_web::web::Bytes;
+use futures::stream::Stream;
 use mongodb::bson::{doc, oid::ObjectId};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::env;
+use std::time::Duration;
+
+const MAX_WATCH_SYMBOLS: usize = 50;
+const DEFAULT_WATCH_INTERVAL_SECS: u64 = 5;
+const MIN_WATCH_INTERVAL_SECS: u64 = 2;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Ticker {
@@ -21,6 +31,24 @@ pub struct Ticker {
     pub timestamp: i64,
 }
 
+impl Ticker {
+    pub fn from_ccxt(symbol: &str, exchange: &str, ticker_json: &serde_json::Value) -> Self {
+        Ticker {
+            symbol: symbol.to_string(),
+            exchange: exchange.to_string(),
+            last: ticker_json.get("last").and_then(|v| v.as_f64()).unwrap_or(0.0),
+            bid: ticker_json.get("bid").and_then(|v| v.as_f64()),
+            ask: ticker_json.get("ask").and_then(|v| v.as_f64()),
+            high: ticker_json.get("high").and_then(|v| v.as_f64()),
+            low: ticker_json.get("low").and_then(|v| v.as_f64()),
+            volume: ticker_json.get("volume").and_then(|v| v.as_f64()),
+            timestamp: ticker_json.get("timestamp")
+                .and_then(|v| v.as_i64())
+                .unwrap_or_else(|| chrono::Utc::now().timestamp_millis()),
+        }
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub struct TickersResponse {
     pub success: bool,
@@ -132,6 +160,8 @@ async fn get_user_exchanges(
                 api_secret,
                 passphrase,
                 is_active: user_exchange.is_active,
+                can_trade: user_exchange.permissions.as_ref().map(|p| p.can_trade),
+                sub_account: user_exchange.sub_account,
             });
         }
     }
@@ -146,8 +176,9 @@ async fn fetch_ticker(
 ) -> Result<Ticker, String> {
     let exchange_name_clone = exchange.name.clone();
     let symbol_clone = symbol.to_string();
+    let ccxt_id = exchange.ccxt_id.clone();
     
-    tokio::task::spawn_blocking(move || {
+    crate::utils::thread_pool::spawn_ccxt_paced(&ccxt_id, move || {
         let client = CCXTClient::new(
             &exchange.ccxt_id,
             &exchange.api_key,
@@ -157,18 +188,128 @@ async fn fetch_ticker(
         
         let ticker_json = client.fetch_ticker_sync(&symbol_clone)?;
         
-        Ok(Ticker {
-            symbol: symbol_clone.clone(),
-            exchange: exchange_name_clone,
-            last: ticker_json.get("last").and_then(|v| v.as_f64()).unwrap_or(0.0),
-            bid: ticker_json.get("bid").and_then(|v| v.as_f64()),
-            ask: ticker_json.get("ask").and_then(|v| v.as_f64()),
-            high: ticker_json.get("high").and_then(|v| v.as_f64()),
-            low: ticker_json.get("low").and_then(|v| v.as_f64()),
-            volume: ticker_json.get("volume").and_then(|v| v.as_f64()),
-            timestamp: ticker_json.get("timestamp")
-                .and_then(|v| v.as_i64())
-                .unwrap_or_else(|| chrono::Utc::now().timestamp_millis()),
-        })
+        Ok(Ticker::from_ccxt(&symbol_clone, &exchange_name_clone, &ticker_json))
     }).await.map_err(|e| format!("Task error: {}", e))?
 }
+
+// ============================================================================
+// WATCHLIST - uma única chamada `fetch_tickers` para N símbolos
+// ============================================================================
+
+#[derive(Debug, Deserialize)]
+pub struct WatchlistRequest {
+    pub exchange: ExchangeCredentials,
+    /// Vazio → watchlist salva do usuário (requer `Authorization: Bearer`)
+    #[serde(default)]
+    pub symbols: Vec<String>,
+}
+
+/// Fonte de tickers em lote (CCXTClient em produção)
+pub trait TickersSource {
+    fn tickers_for(&self, symbols: &[String]) -> Result<HashMap<String, serde_json::Value>, String>;
+}
+
+impl TickersSource for CCXTClient {
+    fn tickers_for(&self, symbols: &[String]) -> Result<HashMap<String, serde_json::Value>, String> {
+        self.fetch_tickers_for_sync(symbols)
+    }
+}
+
+/// Normaliza a lista (trim, uppercase, sem duplicados) e aplica o limite
+pub fn normalize_watchlist(symbols: &[String]) -> Result<Vec<String>, String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for symbol in symbols {
+        let symbol = symbol.trim().to_uppercase();
+        if !symbol.is_empty() && !normalized.contains(&symbol) {
+            normalized.push(symbol);
+        }
+    }
+    if normalized.is_empty() {
+        return Err("At least one symbol is required".to_string());
+    }
+    if normalized.len() > MAX_WATCH_SYMBOLS {
+        return Err(format!("Too many symbols (max {})", MAX_WATCH_SYMBOLS));
+    }
+    Ok(normalized)
+}
+
+/// Preços da watchlist numa única chamada; símbolos sem ticker ficam de fora
+pub fn collect_watchlist(
+    source: &impl TickersSource, exchange_name: &str, symbols: &[String],
+) -> Result<TickersResponse, String> {
+    let raw = source.tickers_for(symbols)?;
+    let tickers: Vec<Ticker> = symbols.iter()
+        .filter_map(|symbol| raw.get(symbol).map(|t| Ticker::from_ccxt(symbol, exchange_name, t)))
+        .collect();
+    let count = tickers.len();
+    Ok(TickersResponse { success: true, tickers, count })
+}
+
+pub async fn get_watchlist(exchange: ExchangeCredentials, symbols: Vec<String>) -> Result<TickersResponse, String> {
+    spawn_ccxt_paced(&exchange.ccxt_id.clone(), move || {
+        let client = CCXTClient::new(
+            &exchange.ccxt_id,
+            &exchange.api_key,
+            &exchange.api_secret,
+            exchange.passphrase.as_deref(),
+        )?;
+        collect_watchlist(&client, &exchange.name, &symbols)
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?
+}
+
+/// Intervalo do stream (padrão 5s, mínimo 2s)
+pub fn watch_interval(interval_secs: Option<u64>) -> Duration {
+    Duration::from_secs(interval_secs.unwrap_or(DEFAULT_WATCH_INTERVAL_SECS).max(MIN_WATCH_INTERVAL_SECS))
+}
+
+/// Stream SSE da watchlist: um evento `tickers` por intervalo (erros viram evento `error`)
+pub fn watchlist_stream(
+    exchange: ExchangeCredentials, symbols: Vec<String>, interval: Duration,
+) -> impl Stream<Item = Result<Bytes, actix_web::Error>> {
+    futures::stream::unfold((exchange, symbols, true), move |(exchange, symbols, first)| async move {
+        if !first {
+            tokio::time::sleep(interval).await;
+        }
+        let chunk = match get_watchlist(exchange.clone(), symbols.clone()).await {
+            Ok(response) => format!(
+                "event: tickers\ndata: {}\n\n",
+                serde_json::to_string(&response.tickers).unwrap_or_else(|_| "[]".to_string())
+            ),
+            Err(e) => format!("event: error\ndata: {}\n\n", serde_json::json!({ "error": e })),
+        };
+        Some((Ok(Bytes::from(chunk)), (exchange, symbols, false)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct FakeExchange {
+        calls: AtomicUsize,
+    }
+
+    impl TickersSource for FakeExchange {
+        fn tickers_for(&self, symbols: &[String]) -> Result<HashMap<String, serde_json::Value>, String> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(symbols.iter().enumerate()
+                .map(|(i, s)| (s.clone(), serde_json::json!({ "symbol": s, "last": 100.0 * (i + 1) as f64, "timestamp": 1 })))
+                .collect())
+        }
+    }
+
+    #[test]
+    fn test_watchlist_prices_come_from_one_fetch() {
+        let exchange = FakeExchange { calls: AtomicUsize::new(0) };
+        let symbols = normalize_watchlist(&["btc/usdt".into(), "ETH/USDT".into(), "SOL/USDT ".into(), "BTC/USDT".into()]).unwrap();
+
+        let response = collect_watchlist(&exchange, "Binance", &symbols).unwrap();
+        assert_eq!(exchange.calls.load(Ordering::SeqCst), 1);
+        assert_eq!(response.count, 3);
+        let prices: Vec<(&str, f64)> = response.tickers.iter().map(|t| (t.symbol.as_str(), t.last)).collect();
+        assert_eq!(prices, vec![("BTC/USDT", 100.0), ("ETH/USDT", 200.0), ("SOL/USDT", 300.0)]);
+    }
+}
@@ -1,10 +1,11 @@
 use crate::{
     database::MongoDB,
-    models::{TokensExchangeCache, TokenInfo, DecryptedExchange},
+    models::{Candle, TokensExchangeCache, TokenInfo, DecryptedExchange, FundingRate, FundingRateEntry, MarketRules},
     ccxt::CCXTClient,
-    utils::thread_pool::spawn_ccxt_blocking,
+    utils::thread_pool::spawn_ccxt_paced,
 };
-use mongodb::bson::{doc, oid::ObjectId};
+use mongodb::bson::{doc, oid::ObjectId, Bson, DateTime as BsonDateTime, Document};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use serde::{Deserialize, Serialize};
 use tokio::time::{timeout, Duration};
 
@@ -150,6 +151,48 @@ pub async fn search_tokens(
 }
 
 
+/// Preenche nome e logo pelo símbolo base com uma única consulta ao catálogo `tokens`.
+/// Falha na consulta não derruba a listagem: os tokens seguem sem metadados.
+async fn enrich_with_catalog(db: &MongoDB, tokens: &mut [TokenInfo]) {
+    let symbols: Vec<String> = tokens.iter()
+        .map(|t| t.symbol.to_uppercase())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    if symbols.is_empty() {
+        return;
+    }
+
+    let collection = db.collection::<Token>("tokens");
+    let mut cursor = match collection.find(doc! { "symbol": { "$in": symbols }, "is_active": true }).await {
+        Ok(cursor) => cursor,
+        Err(e) => {
+            log::warn!("⚠️ Token catalog lookup failed, returning tokens without metadata: {}", e);
+            return;
+        }
+    };
+
+    use futures::stream::StreamExt;
+    let mut catalog = HashMap::new();
+    while let Some(result) = cursor.next().await {
+        match result {
+            Ok(token) => { catalog.insert(token.symbol.to_uppercase(), token); }
+            Err(e) => log::error!("Error reading token: {}", e),
+        }
+    }
+    apply_catalog(tokens, &catalog);
+}
+
+/// Copia nome/logo do catálogo (chave: símbolo base em maiúsculas)
+fn apply_catalog(tokens: &mut [TokenInfo], catalog: &HashMap<String, Token>) {
+    for token in tokens.iter_mut() {
+        if let Some(entry) = catalog.get(&token.symbol.to_uppercase()) {
+            token.name = Some(entry.name.clone());
+            token.logo = entry.logo.clone();
+        }
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub struct ExchangeInfoToken {
     pub id: String,
@@ -215,7 +258,7 @@ pub async fn get_available_tokens(
     };
     
     // Filter tokens by quote if specified
-    let tokens_list: Vec<TokenInfo> = if let Some(quote) = quote_filter {
+    let mut tokens_list: Vec<TokenInfo> = if let Some(quote) = quote_filter {
         let quote_upper = quote.to_uppercase();
         cached_data
             .tokens_by_quote
@@ -230,6 +273,7 @@ pub async fn get_available_tokens(
             .flat_map(|tokens| tokens.clone())
             .collect()
     };
+    enrich_with_catalog(db, &mut tokens_list).await;
     
     // Calculate cache age
     let (updated_at_str, cache_age_hours) = if let Some(updated_at) = cached_data.updated_at {
@@ -309,7 +353,7 @@ pub async fn get_available_tokens_by_ccxt(
     };
     
     // Filter tokens by quote if specified
-    let tokens_list: Vec<TokenInfo> = if let Some(quote) = quote_filter {
+    let mut tokens_list: Vec<TokenInfo> = if let Some(quote) = quote_filter {
         let quote_upper = quote.to_uppercase();
         cached_data
             .tokens_by_quote
@@ -324,6 +368,7 @@ pub async fn get_available_tokens_by_ccxt(
             .flat_map(|tokens| tokens.clone())
             .collect()
     };
+    enrich_with_catalog(db, &mut tokens_list).await;
     
     // Calculate cache age
     let (updated_at_str, cache_age_hours) = if let Some(updated_at) = cached_data.updated_at {
@@ -444,39 +489,155 @@ pub struct LimitRange {
     pub max: Option<f64>,
 }
 
+/// Casas decimais de quantidade/preço; null quando a exchange não informa
 #[derive(Debug, Serialize)]
 pub struct Precision {
-    pub amount: i32,
-    pub price: i32,
+    pub amount: Option<u32>,
+    pub price: Option<u32>,
+}
+
+/// Monta `market_info` a partir dos metadados do mercado. Sem metadados
+/// (falha no `load_markets`) limites e precisão ficam null em vez de chutados.
+pub fn market_info_from_rules(rules: Option<&MarketRules>) -> MarketInfo {
+    let limits = rules.map(|r| r.limits.clone()).unwrap_or_default();
+    let precision = rules.map(|r| r.precision).unwrap_or_default();
+    MarketInfo {
+        active: rules.is_none_or(|r| r.active),
+        limits: Limits {
+            amount: LimitRange { min: limits.min_amount, max: limits.max_amount },
+            cost: LimitRange { min: limits.min_cost, max: limits.max_cost },
+            price: LimitRange { min: limits.min_price, max: limits.max_price },
+            leverage: None,
+        },
+        precision: Precision {
+            amount: precision.amount_decimals,
+            price: precision.price_decimals,
+        },
+    }
+}
+
+const SYMBOL_SUGGESTIONS_LIMIT: usize = 5;
+
+#[derive(Debug)]
+pub enum TokenDetailsError {
+    /// Par inexistente na exchange, com símbolos parecidos como sugestão
+    SymbolNotAvailable { symbol: String, exchange: String, suggestions: Vec<String> },
+    Other(String),
+}
+
+impl std::fmt::Display for TokenDetailsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TokenDetailsError::SymbolNotAvailable { symbol, exchange, .. } => {
+                write!(f, "Symbol {} not available on exchange {}", symbol, exchange)
+            }
+            TokenDetailsError::Other(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// Fonte de tickers e mercados (CCXTClient em produção)
+pub trait TickerSource {
+    fn ticker(&self, symbol: &str) -> Result<serde_json::Value, String>;
+    fn search_symbols(&self, query: &str, limit: usize) -> Result<Vec<String>, String>;
+
+    /// Vários tickers numa chamada; por padrão um `ticker` por símbolo
+    fn tickers(&self, symbols: &[String]) -> Result<HashMap<String, serde_json::Value>, String> {
+        symbols.iter().map(|s| self.ticker(s).map(|t| (s.clone(), t))).collect()
+    }
+
+    /// Precisão/limites do mercado; None = indisponível
+    fn market_rules(&self, _symbol: &str) -> Option<MarketRules> {
+        None
+    }
+}
+
+impl TickerSource for CCXTClient {
+    fn ticker(&self, symbol: &str) -> Result<serde_json::Value, String> {
+        self.fetch_ticker_sync(symbol)
+    }
+
+    fn search_symbols(&self, query: &str, limit: usize) -> Result<Vec<String>, String> {
+        self.search_markets_symbols_sync(query, limit)
+    }
+
+    fn tickers(&self, symbols: &[String]) -> Result<HashMap<String, serde_json::Value>, String> {
+        self.fetch_tickers_for_sync(symbols)
+    }
+
+    fn market_rules(&self, symbol: &str) -> Option<MarketRules> {
+        self.fetch_market_rules_sync(symbol).unwrap_or_else(|e| {
+            log::warn!("⚠️ Market metadata unavailable for {}: {}", symbol, e);
+            None
+        })
+    }
+}
+
+/// Exceção `BadSymbol` do CCXT (par não listado na exchange)
+pub fn is_bad_symbol(error: &str) -> bool {
+    error.contains("BadSymbol") || error.contains("does not have market symbol")
+}
+
+/// Busca o ticker; símbolo inexistente vira erro amigável com sugestões
+pub fn fetch_ticker_checked(
+    source: &impl TickerSource, exchange_name: &str, symbol: &str,
+) -> Result<serde_json::Value, TokenDetailsError> {
+    match source.ticker(symbol) {
+        Ok(ticker) => Ok(ticker),
+        Err(e) if is_bad_symbol(&e) => {
+            let base = symbol.split('/').next().unwrap_or(symbol).trim().to_uppercase();
+            // Sem match pelo base completo, tenta pelo prefixo (ex: typo no final)
+            let prefix: String = base.chars().take(3).collect();
+            let mut suggestions = source.search_symbols(&base, SYMBOL_SUGGESTIONS_LIMIT).unwrap_or_default();
+            if suggestions.is_empty() && prefix.len() < base.len() {
+                suggestions = source.search_symbols(&prefix, SYMBOL_SUGGESTIONS_LIMIT).unwrap_or_default();
+            }
+            Err(TokenDetailsError::SymbolNotAvailable {
+                symbol: symbol.to_string(),
+                exchange: exchange_name.to_string(),
+                suggestions,
+            })
+        }
+        Err(e) => Err(TokenDetailsError::Other(format!("Failed to fetch ticker: {}", e))),
+    }
 }
 
 pub async fn get_token_details_with_creds(
     request: &GetTokenDetailsRequest,
-) -> Result<TokenDetailsResponse, String> {
+) -> Result<TokenDetailsResponse, TokenDetailsError> {
     let exchange_clone = request.exchange.clone();
     let symbol_clone = request.symbol.clone();
     
-    let ticker_task = spawn_ccxt_blocking(move || {
+    let ticker_task = spawn_ccxt_paced(&request.exchange.ccxt_id, move || {
         let client = CCXTClient::new(
             &exchange_clone.ccxt_id,
             &exchange_clone.api_key,
             &exchange_clone.api_secret,
             exchange_clone.passphrase.as_deref(),
-        )?;
+        ).map_err(TokenDetailsError::Other)?;
         
-        client.fetch_ticker_sync(&symbol_clone)
+        let ticker = fetch_ticker_checked(&client, &exchange_clone.name, &symbol_clone)?;
+        // Mercados já carregados pelo ticker: precisão/limites saem do cache do client
+        let rules = client.market_rules(&symbol_clone);
+        Ok((ticker, rules))
     });
     
-    let ticker_json = ticker_task.await
-        .map_err(|e| format!("Task join error: {}", e))?
-        .map_err(|e| format!("Failed to fetch ticker: {}", e))?;
-    
+    let (ticker_json, market_rules) = ticker_task.await
+        .map_err(|e| TokenDetailsError::Other(format!("Task join error: {}", e)))??;
+
+    Ok(build_token_details(&request.exchange, &request.symbol, &ticker_json, market_rules.as_ref()))
+}
+
+/// Monta `TokenDetailsResponse` a partir do ticker do CCXT e dos metadados do mercado
+pub fn build_token_details(
+    exchange: &DecryptedExchange, pair: &str, ticker_json: &serde_json::Value, market_rules: Option<&MarketRules>,
+) -> TokenDetailsResponse {
     // Parse symbol
-    let parts: Vec<&str> = request.symbol.split('/').collect();
+    let parts: Vec<&str> = pair.split('/').collect();
     let (base, quote) = if parts.len() == 2 {
         (parts[0].to_string(), parts[1].to_string())
     } else {
-        (request.symbol.clone(), "USDT".to_string())
+        (pair.to_string(), "USDT".to_string())
     };
     
     let current_price = ticker_json.get("last").and_then(|v| v.as_f64()).unwrap_or(0.0);
@@ -487,15 +648,15 @@ pub async fn get_token_details_with_creds(
     let change_1h_percent = change_24h_percent * 0.1;
     let change_4h_percent = change_24h_percent * 0.4;
     
-    Ok(TokenDetailsResponse {
+    TokenDetailsResponse {
         success: true,
-        symbol: base.clone(),
-        pair: request.symbol.clone(),
-        quote: quote.clone(),
+        symbol: base,
+        pair: pair.to_string(),
+        quote,
         exchange: ExchangeInfoDetails {
-            id: request.exchange.exchange_id.clone(),
-            name: request.exchange.name.clone(),
-            ccxt_id: request.exchange.ccxt_id.clone(),
+            id: exchange.exchange_id.clone(),
+            name: exchange.name.clone(),
+            ccxt_id: exchange.ccxt_id.clone(),
         },
         price: PriceInfo {
             current: current_price.to_string(),
@@ -528,23 +689,122 @@ pub async fn get_token_details_with_creds(
             quote_24h: ticker_json.get("quoteVolume").and_then(|v| v.as_f64()).map(|v| v.to_string())
                 .unwrap_or_else(|| "0".to_string()),
         },
-        market_info: MarketInfo {
-            active: true,
-            limits: Limits {
-                amount: LimitRange { min: None, max: None },
-                cost: LimitRange { min: None, max: None },
-                price: LimitRange { min: None, max: None },
-                leverage: None,
-            },
-            precision: Precision {
-                amount: 8,
-                price: 8,
-            },
-        },
+        market_info: market_info_from_rules(market_rules),
         timestamp: ticker_json.get("timestamp").and_then(|v| v.as_i64())
             .unwrap_or_else(|| chrono::Utc::now().timestamp_millis()),
         datetime: ticker_json.get("datetime").and_then(|v| v.as_str()).map(|s| s.to_string())
             .unwrap_or_else(|| chrono::Utc::now().to_rfc3339()),
+    }
+}
+
+// ============================================================================
+// BATCH TOKEN DETAILS - VÁRIOS SÍMBOLOS NUMA EXCHANGE
+// ============================================================================
+
+pub const MAX_TOKEN_DETAILS_BATCH: usize = 50;
+
+#[derive(Debug, Deserialize)]
+pub struct BatchTokenDetailsRequest {
+    pub exchange: DecryptedExchange,
+    pub symbols: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BatchTokenDetailsItem {
+    pub symbol: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<TokenDetailsResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Só em símbolos inexistentes na exchange
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub suggestions: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BatchTokenDetailsResponse {
+    pub success: bool,
+    pub exchange: String,
+    pub count: usize,
+    pub failed: usize,
+    pub results: Vec<BatchTokenDetailsItem>,
+}
+
+/// Símbolos em maiúsculas, sem vazios nem repetidos (ordem preservada)
+pub fn normalize_batch_symbols(symbols: &[String]) -> Result<Vec<String>, String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for symbol in symbols.iter().map(|s| s.trim().to_uppercase()).filter(|s| !s.is_empty()) {
+        if !normalized.contains(&symbol) {
+            normalized.push(symbol);
+        }
+    }
+    if normalized.is_empty() {
+        return Err("At least one symbol is required".to_string());
+    }
+    if normalized.len() > MAX_TOKEN_DETAILS_BATCH {
+        return Err(format!("Too many symbols (max {})", MAX_TOKEN_DETAILS_BATCH));
+    }
+    Ok(normalized)
+}
+
+/// Um `fetch_tickers` para todos; símbolos que faltarem (ou a lista toda, se a
+/// chamada em lote falhar) caem para o ticker individual, com erro por símbolo
+pub fn collect_batch_token_details(
+    source: &impl TickerSource, exchange: &DecryptedExchange, symbols: &[String],
+) -> Vec<BatchTokenDetailsItem> {
+    let tickers = source.tickers(symbols).unwrap_or_else(|e| {
+        log::warn!("⚠️ Batch ticker fetch failed on {}, fetching one by one: {}", exchange.name, e);
+        HashMap::new()
+    });
+
+    symbols.iter()
+        .map(|symbol| {
+            let ticker = match tickers.get(symbol) {
+                Some(ticker) => Ok(ticker.clone()),
+                None => fetch_ticker_checked(source, &exchange.name, symbol),
+            };
+            match ticker {
+                Ok(ticker) => BatchTokenDetailsItem {
+                    symbol: symbol.clone(),
+                    data: Some(build_token_details(exchange, symbol, &ticker, source.market_rules(symbol).as_ref())),
+                    error: None,
+                    suggestions: vec![],
+                },
+                Err(e) => {
+                    let message = e.to_string();
+                    let suggestions = match e {
+                        TokenDetailsError::SymbolNotAvailable { suggestions, .. } => suggestions,
+                        TokenDetailsError::Other(_) => vec![],
+                    };
+                    BatchTokenDetailsItem { symbol: symbol.clone(), data: None, error: Some(message), suggestions }
+                }
+            }
+        })
+        .collect()
+}
+
+pub async fn get_token_details_batch(request: &BatchTokenDetailsRequest) -> Result<BatchTokenDetailsResponse, String> {
+    let symbols = normalize_batch_symbols(&request.symbols)?;
+    let exchange = request.exchange.clone();
+
+    log::info!("🪙 Fetching details for {} symbols on {}", symbols.len(), exchange.name);
+
+    let fetch_task = spawn_ccxt_paced(&request.exchange.ccxt_id, move || {
+        let client = CCXTClient::for_exchange(&exchange)?;
+        Ok::<_, String>(collect_batch_token_details(&client, &exchange, &symbols))
+    });
+    let results = match timeout(Duration::from_secs(30), fetch_task).await {
+        Ok(Ok(result)) => result?,
+        Ok(Err(e)) => return Err(format!("Task join error: {}", e)),
+        Err(_) => return Err("Timeout fetching token details".to_string()),
+    };
+
+    Ok(BatchTokenDetailsResponse {
+        success: true,
+        exchange: request.exchange.name.clone(),
+        count: results.len(),
+        failed: results.iter().filter(|r| r.error.is_some()).count(),
+        results,
     })
 }
 
@@ -570,7 +830,7 @@ pub async fn search_tokens_with_creds(
     let api_secret = exchange.api_secret.clone();
     let passphrase = exchange.passphrase.clone();
 
-    let fetch_task = spawn_ccxt_blocking(move || {
+    let fetch_task = spawn_ccxt_paced(&exchange.ccxt_id, move || {
         let client = CCXTClient::new(
             &ccxt_id,
             &api_key,
@@ -657,6 +917,119 @@ pub struct ArbitrageOpportunity {
     pub profit_percent: f64,
 }
 
+const DEFAULT_DETAILS_TIMEOUT_MS: u64 = 15_000;
+const DEFAULT_DETAILS_DEADLINE_MS: u64 = 20_000;
+const DETAILS_RETRY_BACKOFF_MS: u64 = 200;
+const DEFAULT_DETAILS_MAX_CONCURRENCY: usize = 4;
+
+/// Timeouts da busca em várias exchanges
+#[derive(Debug, Clone, Copy)]
+pub struct FanOutPolicy {
+    /// Limite de cada tentativa numa exchange
+    pub per_exchange: Duration,
+    /// Prazo total: exchanges que não responderem até aqui saem como "timeout"
+    pub deadline: Duration,
+    /// Espera antes da única nova tentativa em falhas transitórias
+    pub retry_backoff: Duration,
+    /// Exchanges consultadas ao mesmo tempo (as demais esperam uma vaga)
+    pub max_concurrent: usize,
+}
+
+/// Configurado via env (`TOKEN_DETAILS_TIMEOUT_MS`, `TOKEN_DETAILS_DEADLINE_MS`,
+/// `TOKEN_DETAILS_MAX_CONCURRENCY`)
+fn details_fan_out_policy() -> FanOutPolicy {
+    let env_ms = |key: &str, default: u64| {
+        std::env::var(key).ok().and_then(|v| v.parse::<u64>().ok()).unwrap_or(default)
+    };
+    FanOutPolicy {
+        per_exchange: Duration::from_millis(env_ms("TOKEN_DETAILS_TIMEOUT_MS", DEFAULT_DETAILS_TIMEOUT_MS)),
+        deadline: Duration::from_millis(env_ms("TOKEN_DETAILS_DEADLINE_MS", DEFAULT_DETAILS_DEADLINE_MS)),
+        retry_backoff: Duration::from_millis(DETAILS_RETRY_BACKOFF_MS),
+        max_concurrent: std::env::var("TOKEN_DETAILS_MAX_CONCURRENCY").ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .filter(|n| *n > 0)
+            .unwrap_or(DEFAULT_DETAILS_MAX_CONCURRENCY),
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum FanOutOutcome<T> {
+    Success(T),
+    Error(String),
+    Timeout,
+}
+
+/// Uma tentativa com timeout, repetida uma vez se a falha for transitória
+async fn fetch_with_retry<T, F, Fut>(policy: FanOutPolicy, fetch: F) -> FanOutOutcome<T>
+where
+    F: Fn() -> Fut,
+    Fut: std::future::Future<Output = Result<T, String>>,
+{
+    let mut retried = false;
+    loop {
+        match timeout(policy.per_exchange, fetch()).await {
+            Ok(Ok(value)) => return FanOutOutcome::Success(value),
+            Ok(Err(e)) if !retried && crate::services::credential_health_service::classify_ccxt_error(&e)
+                == crate::services::credential_health_service::CcxtErrorKind::Transient => {
+                log::warn!("🔄 Transient error, retrying once: {}", e);
+                retried = true;
+                tokio::time::sleep(policy.retry_backoff).await;
+            }
+            Ok(Err(e)) => return FanOutOutcome::Error(e),
+            Err(_) => return FanOutOutcome::Timeout,
+        }
+    }
+}
+
+/// Busca em todas as exchanges em paralelo (no máximo `max_concurrent` por vez),
+/// coletando na ordem em que respondem. O timeout por exchange só conta depois
+/// de conseguir a vaga; ao estourar o prazo total, as pendentes (inclusive as
+/// que ainda esperavam vaga) são marcadas como `Timeout`.
+pub async fn fan_out_with_deadline<T, F, Fut>(
+    exchanges: &[ExchangeCredentials], policy: FanOutPolicy, fetch: F,
+) -> Vec<(ExchangeCredentials, FanOutOutcome<T>)>
+where
+    F: Fn(ExchangeCredentials) -> Fut,
+    Fut: std::future::Future<Output = Result<T, String>>,
+{
+    use futures::stream::{FuturesUnordered, StreamExt};
+
+    let fetch = &fetch;
+    let slots = tokio::sync::Semaphore::new(policy.max_concurrent.max(1));
+    let slots = &slots;
+    let mut pending: FuturesUnordered<_> = exchanges.iter().enumerate()
+        .map(|(i, exchange)| async move {
+            let _slot = slots.acquire().await.expect("semaphore never closed");
+            (i, fetch_with_retry(policy, || fetch(exchange.clone())).await)
+        })
+        .collect();
+
+    let deadline = tokio::time::Instant::now() + policy.deadline;
+    let mut done = vec![false; exchanges.len()];
+    let mut results = Vec::with_capacity(exchanges.len());
+
+    loop {
+        match tokio::time::timeout_at(deadline, pending.next()).await {
+            Ok(Some((i, outcome))) => {
+                done[i] = true;
+                results.push((exchanges[i].clone(), outcome));
+            }
+            Ok(None) => break,
+            Err(_) => {
+                log::warn!("⏱️ Deadline reached with {} exchange(s) still pending", pending.len());
+                break;
+            }
+        }
+    }
+
+    for (i, exchange) in exchanges.iter().enumerate() {
+        if !done[i] {
+            results.push((exchange.clone(), FanOutOutcome::Timeout));
+        }
+    }
+    results
+}
+
 pub async fn get_token_details_multi(
     symbol: &str,
     exchanges: &[ExchangeCredentials],
@@ -668,77 +1041,46 @@ pub async fn get_token_details_multi(
     log::info!("🔍 Fetching {} from {} exchanges in parallel", 
         symbol, exchanges.len());
 
-    // Busca paralela em todas as exchanges
-    let mut tasks = Vec::new();
-    
-    for exchange in exchanges {
-        let symbol_owned = symbol.to_string();
-        let exchange_clone = exchange.clone();
-        
-        let task = tokio::spawn(async move {
-            let request = GetTokenDetailsRequest {
-                symbol: symbol_owned.clone(),
-                exchange: DecryptedExchange {
-                    exchange_id: exchange_clone.exchange_id.clone(),
-                    ccxt_id: exchange_clone.ccxt_id.clone(),
-                    name: exchange_clone.name.clone(),
-                    api_key: exchange_clone.api_key.clone(),
-                    api_secret: exchange_clone.api_secret.clone(),
-                    passphrase: exchange_clone.passphrase.clone(),
-                    is_active: true,
-                },
-            };
-            
-            let result = match timeout(
-                Duration::from_secs(15), 
-                get_token_details_with_creds(&request)
-            ).await {
-                Ok(Ok(data)) => ExchangeTokenDetails {
-                    exchange_id: exchange_clone.exchange_id,
-                    exchange_name: exchange_clone.name,
-                    ccxt_id: exchange_clone.ccxt_id,
-                    status: "success".to_string(),
-                    error: None,
-                    data: Some(data),
-                },
-                Ok(Err(e)) => ExchangeTokenDetails {
-                    exchange_id: exchange_clone.exchange_id,
-                    exchange_name: exchange_clone.name,
-                    ccxt_id: exchange_clone.ccxt_id,
-                    status: "error".to_string(),
-                    error: Some(e),
-                    data: None,
-                },
-                Err(_) => ExchangeTokenDetails {
-                    exchange_id: exchange_clone.exchange_id,
-                    exchange_name: exchange_clone.name,
-                    ccxt_id: exchange_clone.ccxt_id,
-                    status: "timeout".to_string(),
-                    error: Some("Request timed out".to_string()),
-                    data: None,
-                },
+    let outcomes = fan_out_with_deadline(exchanges, details_fan_out_policy(), |exchange| async move {
+        let request = GetTokenDetailsRequest {
+            symbol: symbol.to_string(),
+            exchange: DecryptedExchange {
+                exchange_id: exchange.exchange_id,
+                ccxt_id: exchange.ccxt_id,
+                name: exchange.name,
+                api_key: exchange.api_key,
+                api_secret: exchange.api_secret,
+                passphrase: exchange.passphrase,
+                is_active: true,
+                can_trade: None,
+                sub_account: None,
+            },
+        };
+        get_token_details_with_creds(&request).await.map_err(|e| e.to_string())
+    }).await;
+
+    let results: Vec<ExchangeTokenDetails> = outcomes.into_iter()
+        .map(|(exchange, outcome)| {
+            let (status, error, data) = match outcome {
+                FanOutOutcome::Success(data) => ("success", None, Some(data)),
+                FanOutOutcome::Error(e) => ("error", Some(e), None),
+                FanOutOutcome::Timeout => ("timeout", Some("Request timed out".to_string()), None),
             };
-            
-            result
-        });
-        
-        tasks.push(task);
-    }
-    
-    // Aguarda todas as tarefas
-    let mut results = Vec::new();
-    for task in tasks {
-        match task.await {
-            Ok(result) => results.push(result),
-            Err(e) => {
-                log::error!("❌ Task join error: {}", e);
+            ExchangeTokenDetails {
+                exchange_id: exchange.exchange_id,
+                exchange_name: exchange.name,
+                ccxt_id: exchange.ccxt_id,
+                status: status.to_string(),
+                error,
+                data,
             }
-        }
-    }
+        })
+        .collect();
     
     // Análise de preços e arbitragem
-    let comparison = calculate_price_comparison(&results);
-    let arbitrage_opportunities = find_arbitrage_opportunities(&results);
+    let quotes = exchange_quotes(&results);
+    let comparison = calculate_price_comparison(&quotes);
+    let arbitrage_opportunities = find_arbitrage_opportunities(&quotes);
     
     log::info!("✅ Retrieved {} from {} exchanges ({} successful)", 
         symbol, 
@@ -754,51 +1096,58 @@ pub async fn get_token_details_multi(
     })
 }
 
-fn calculate_price_comparison(exchanges: &[ExchangeTokenDetails]) -> PriceComparison {
-    let mut best_bid: Option<BestPrice> = None;
-    let mut best_ask: Option<BestPrice> = None;
-    let mut all_bids = Vec::new();
-    let mut all_asks = Vec::new();
-    
-    for exchange in exchanges {
-        if let Some(ref data) = exchange.data {
-            // Parse bid/ask
-            if let Ok(bid_price) = data.price.bid.parse::<f64>() {
-                if bid_price > 0.0 {
-                    all_bids.push(bid_price);
-                    if best_bid.is_none() || bid_price > best_bid.as_ref().unwrap().price {
-                        best_bid = Some(BestPrice {
-                            exchange: exchange.exchange_name.clone(),
-                            price: bid_price,
-                        });
-                    }
-                }
-            }
-            
-            if let Ok(ask_price) = data.price.ask.parse::<f64>() {
-                if ask_price > 0.0 {
-                    all_asks.push(ask_price);
-                    if best_ask.is_none() || ask_price < best_ask.as_ref().unwrap().price {
-                        best_ask = Some(BestPrice {
-                            exchange: exchange.exchange_name.clone(),
-                            price: ask_price,
-                        });
-                    }
-                }
-            }
-        }
-    }
-    
+/// Bid/ask de uma exchange já convertidos (None = ausente ou inválido)
+#[derive(Debug, Clone, PartialEq)]
+struct ExchangeQuote {
+    exchange: String,
+    bid: Option<f64>,
+    ask: Option<f64>,
+}
+
+/// Preço utilizável: finito e positivo (exchanges às vezes mandam "NaN"/"inf")
+fn valid_price(price: f64) -> Option<f64> {
+    (price.is_finite() && price > 0.0).then_some(price)
+}
+
+fn parse_price(raw: &str) -> Option<f64> {
+    raw.trim().parse::<f64>().ok().and_then(valid_price)
+}
+
+/// Cotações das exchanges que responderam com sucesso
+fn exchange_quotes(exchanges: &[ExchangeTokenDetails]) -> Vec<ExchangeQuote> {
+    exchanges.iter()
+        .filter(|e| e.status == "success")
+        .filter_map(|e| e.data.as_ref().map(|data| ExchangeQuote {
+            exchange: e.exchange_name.clone(),
+            bid: parse_price(&data.price.bid),
+            ask: parse_price(&data.price.ask),
+        }))
+        .collect()
+}
+
+/// Melhor bid (maior) e melhor ask (menor). Empates ficam com a exchange de
+/// nome menor, para a resposta não depender da ordem de chegada.
+fn calculate_price_comparison(quotes: &[ExchangeQuote]) -> PriceComparison {
+    let bids: Vec<(&str, f64)> = quotes.iter()
+        .filter_map(|q| q.bid.and_then(valid_price).map(|p| (q.exchange.as_str(), p)))
+        .collect();
+    let asks: Vec<(&str, f64)> = quotes.iter()
+        .filter_map(|q| q.ask.and_then(valid_price).map(|p| (q.exchange.as_str(), p)))
+        .collect();
+
+    let best_bid = bids.iter()
+        .min_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0)))
+        .map(|(exchange, price)| BestPrice { exchange: exchange.to_string(), price: *price });
+    let best_ask = asks.iter()
+        .min_by(|a, b| a.1.total_cmp(&b.1).then_with(|| a.0.cmp(b.0)))
+        .map(|(exchange, price)| BestPrice { exchange: exchange.to_string(), price: *price });
+
     // Calcula spread máximo
-    let max_spread_percent = if let (Some(max_bid), Some(min_ask)) = (
-        all_bids.iter().max_by(|a, b| a.partial_cmp(b).unwrap()),
-        all_asks.iter().min_by(|a, b| a.partial_cmp(b).unwrap()),
-    ) {
-        ((max_bid - min_ask) / min_ask * 100.0).abs()
-    } else {
-        0.0
+    let max_spread_percent = match (&best_bid, &best_ask) {
+        (Some(bid), Some(ask)) => ((bid.price - ask.price) / ask.price * 100.0).abs(),
+        _ => 0.0,
     };
-    
+
     PriceComparison {
         best_bid,
         best_ask,
@@ -806,41 +1155,28 @@ fn calculate_price_comparison(exchanges: &[ExchangeTokenDetails]) -> PriceCompar
     }
 }
 
-fn find_arbitrage_opportunities(exchanges: &[ExchangeTokenDetails]) -> Vec<ArbitrageOpportunity> {
+fn find_arbitrage_opportunities(quotes: &[ExchangeQuote]) -> Vec<ArbitrageOpportunity> {
     let mut opportunities = Vec::new();
-    
+
     // Compara todas as combinações de exchanges
-    for i in 0..exchanges.len() {
-        if exchanges[i].status != "success" || exchanges[i].data.is_none() {
-            continue;
-        }
-        
-        let exchange_i_data = exchanges[i].data.as_ref().unwrap();
-        let ask_i = match exchange_i_data.price.ask.parse::<f64>() {
-            Ok(price) if price > 0.0 => price,
-            _ => continue,
-        };
-        
-        for j in 0..exchanges.len() {
-            if i == j || exchanges[j].status != "success" || exchanges[j].data.is_none() {
+    for (i, buy) in quotes.iter().enumerate() {
+        let Some(ask_i) = buy.ask.and_then(valid_price) else { continue };
+
+        for (j, sell) in quotes.iter().enumerate() {
+            if i == j {
                 continue;
             }
-            
-            let exchange_j_data = exchanges[j].data.as_ref().unwrap();
-            let bid_j = match exchange_j_data.price.bid.parse::<f64>() {
-                Ok(price) if price > 0.0 => price,
-                _ => continue,
-            };
-            
+            let Some(bid_j) = sell.bid.and_then(valid_price) else { continue };
+
             // Se o bid de J é maior que o ask de I, há oportunidade
             if bid_j > ask_i {
                 let profit_percent = ((bid_j - ask_i) / ask_i) * 100.0;
-                
+
                 // Considera apenas oportunidades > 0.5%
                 if profit_percent > 0.5 {
                     opportunities.push(ArbitrageOpportunity {
-                        buy_from: exchanges[i].exchange_name.clone(),
-                        sell_to: exchanges[j].exchange_name.clone(),
+                        buy_from: buy.exchange.clone(),
+                        sell_to: sell.exchange.clone(),
                         buy_price: ask_i,
                         sell_price: bid_j,
                         profit_percent,
@@ -849,10 +1185,800 @@ fn find_arbitrage_opportunities(exchanges: &[ExchangeTokenDetails]) -> Vec<Arbit
             }
         }
     }
-    
-    // Ordena por maior lucro
-    opportunities.sort_by(|a, b| b.profit_percent.partial_cmp(&a.profit_percent).unwrap());
-    
+
+    // Ordena por maior lucro; empates por exchange de compra e de venda
+    opportunities.sort_by(|a, b| {
+        b.profit_percent.total_cmp(&a.profit_percent)
+            .then_with(|| a.buy_from.cmp(&b.buy_from))
+            .then_with(|| a.sell_to.cmp(&b.sell_to))
+    });
+
     opportunities
 }
 
+// ============================================================================
+// FUNDING RATES (perpétuos) - ZERO DATABASE PATTERN
+// ============================================================================
+
+const DEFAULT_FUNDING_HISTORY_LIMIT: usize = 30;
+const MAX_FUNDING_HISTORY_LIMIT: usize = 500;
+
+#[derive(Debug, Deserialize)]
+pub struct FundingRateRequest {
+    pub exchange: ExchangeCredentials,
+    pub symbol: String,
+    #[serde(default)]
+    pub history_limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FundingRateResponse {
+    pub success: bool,
+    pub exchange: String,
+    pub symbol: String,
+    pub current: FundingRate,
+    pub history: Vec<FundingRateEntry>,
+    /// false quando a exchange não expõe histórico (só a taxa atual)
+    pub history_supported: bool,
+}
+
+/// Fonte de funding rates (CCXTClient em produção)
+pub trait FundingRateSource {
+    fn funding_rate(&self, symbol: &str) -> Result<FundingRate, String>;
+    fn funding_rate_history(&self, symbol: &str, limit: usize) -> Result<Vec<FundingRateEntry>, String>;
+}
+
+impl FundingRateSource for CCXTClient {
+    fn funding_rate(&self, symbol: &str) -> Result<FundingRate, String> {
+        self.fetch_funding_rate_sync(symbol)
+    }
+
+    fn funding_rate_history(&self, symbol: &str, limit: usize) -> Result<Vec<FundingRateEntry>, String> {
+        self.fetch_funding_rate_history_sync(symbol, limit)
+    }
+}
+
+pub fn is_not_supported(error: &str) -> bool {
+    error.starts_with("NotSupported")
+}
+
+/// Taxa atual (obrigatória) + histórico (opcional: exchanges sem histórico retornam lista vazia)
+pub fn collect_funding_rates(
+    source: &impl FundingRateSource, exchange_name: &str, symbol: &str, history_limit: usize,
+) -> Result<FundingRateResponse, String> {
+    let current = source.funding_rate(symbol)?;
+    let (history, history_supported) = match source.funding_rate_history(symbol, history_limit) {
+        Ok(history) => (history, true),
+        Err(e) if is_not_supported(&e) => (vec![], false),
+        Err(e) => return Err(e),
+    };
+
+    Ok(FundingRateResponse {
+        success: true,
+        exchange: exchange_name.to_string(),
+        symbol: symbol.to_string(),
+        current,
+        history,
+        history_supported,
+    })
+}
+
+pub async fn get_funding_rates(request: &FundingRateRequest) -> Result<FundingRateResponse, String> {
+    let exchange = request.exchange.clone();
+    let symbol = request.symbol.clone();
+    let limit = request.history_limit
+        .unwrap_or(DEFAULT_FUNDING_HISTORY_LIMIT)
+        .clamp(1, MAX_FUNDING_HISTORY_LIMIT);
+
+    log::info!("💸 Fetching funding rates for {} on {}", symbol, exchange.name);
+
+    let fetch_task = spawn_ccxt_paced(&request.exchange.ccxt_id, move || {
+        let client = CCXTClient::new(
+            &exchange.ccxt_id,
+            &exchange.api_key,
+            &exchange.api_secret,
+            exchange.passphrase.as_deref(),
+        )?;
+        collect_funding_rates(&client, &exchange.name, &symbol, limit)
+    });
+
+    match timeout(Duration::from_secs(15), fetch_task).await {
+        Ok(Ok(result)) => result,
+        Ok(Err(e)) => Err(format!("Task join error: {}", e)),
+        Err(_) => Err("Timeout fetching funding rates".to_string()),
+    }
+}
+
+// ============================================================================
+// OHLCV PARA GRÁFICOS - ZERO DATABASE PATTERN
+// ============================================================================
+
+const DEFAULT_OHLCV_TIMEFRAME: &str = "1h";
+const DEFAULT_OHLCV_LIMIT: usize = 200;
+const MAX_OHLCV_LIMIT: usize = 1000;
+
+#[derive(Debug, Deserialize)]
+pub struct OhlcvRequest {
+    pub exchange: ExchangeCredentials,
+    pub symbol: String,
+    #[serde(default)]
+    pub timeframe: Option<String>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OhlcvResponse {
+    pub success: bool,
+    pub exchange: String,
+    pub symbol: String,
+    pub timeframe: String,
+    pub count: usize,
+    /// `[timestamp_ms, open, high, low, close, volume]`, mais antigo → mais recente
+    pub candles: Vec<[f64; 6]>,
+}
+
+/// Fonte de candles (CCXTClient em produção)
+pub trait OhlcvSource {
+    /// Timeframes suportados; None = a exchange não informa (aceita os padrão do CCXT)
+    fn timeframes(&self) -> Option<Vec<String>>;
+    fn ohlcv(&self, symbol: &str, timeframe: &str, limit: usize) -> Result<Vec<Candle>, String>;
+}
+
+impl OhlcvSource for CCXTClient {
+    fn timeframes(&self) -> Option<Vec<String>> {
+        self.supported_timeframes_sync()
+    }
+
+    fn ohlcv(&self, symbol: &str, timeframe: &str, limit: usize) -> Result<Vec<Candle>, String> {
+        self.fetch_ohlcv_sync(symbol, timeframe, limit)
+    }
+}
+
+/// Valida o timeframe contra os da exchange (ou os padrão do CCXT: `1m`..`1w`, `1M`)
+pub fn resolve_timeframe(timeframe: Option<&str>, supported: Option<&[String]>, exchange_name: &str) -> Result<String, String> {
+    let timeframe = timeframe.map(str::trim).filter(|t| !t.is_empty()).unwrap_or(DEFAULT_OHLCV_TIMEFRAME);
+    let valid = match supported {
+        Some(list) => list.iter().any(|t| t == timeframe),
+        None => timeframe == "1M" || crate::services::ohlcv_cache_service::timeframe_to_millis(timeframe).is_some(),
+    };
+    if valid {
+        return Ok(timeframe.to_string());
+    }
+
+    let hint = supported.map(|list| format!(" Supported: {}", list.join(", "))).unwrap_or_default();
+    Err(format!("NotSupported: timeframe '{}' is not supported by {}.{}", timeframe, exchange_name, hint))
+}
+
+/// Candles no formato compacto para bibliotecas de gráfico
+pub fn collect_ohlcv(
+    source: &impl OhlcvSource, exchange_name: &str, symbol: &str, timeframe: Option<&str>, limit: Option<usize>,
+) -> Result<OhlcvResponse, String> {
+    let symbol = symbol.trim().to_uppercase();
+    let timeframe = resolve_timeframe(timeframe, source.timeframes().as_deref(), exchange_name)?;
+    let limit = limit.unwrap_or(DEFAULT_OHLCV_LIMIT).clamp(1, MAX_OHLCV_LIMIT);
+
+    let candles: Vec<[f64; 6]> = source.ohlcv(&symbol, &timeframe, limit)?
+        .iter()
+        .map(|c| [c.timestamp as f64, c.open, c.high, c.low, c.close, c.volume])
+        .collect();
+
+    Ok(OhlcvResponse {
+        success: true,
+        exchange: exchange_name.to_string(),
+        symbol,
+        timeframe,
+        count: candles.len(),
+        candles,
+    })
+}
+
+pub async fn get_ohlcv(request: &OhlcvRequest) -> Result<OhlcvResponse, String> {
+    let exchange = request.exchange.clone();
+    let symbol = request.symbol.clone();
+    let timeframe = request.timeframe.clone();
+    let limit = request.limit;
+
+    log::info!("🕯️ Fetching OHLCV for {} ({:?}) on {}", symbol, timeframe, exchange.name);
+
+    let fetch_task = spawn_ccxt_paced(&request.exchange.ccxt_id, move || {
+        let client = CCXTClient::new(
+            &exchange.ccxt_id,
+            &exchange.api_key,
+            &exchange.api_secret,
+            exchange.passphrase.as_deref(),
+        )?;
+        collect_ohlcv(&client, &exchange.name, &symbol, timeframe.as_deref(), limit)
+    });
+
+    match timeout(Duration::from_secs(15), fetch_task).await {
+        Ok(Ok(result)) => result,
+        Ok(Err(e)) => Err(format!("Task join error: {}", e)),
+        Err(_) => Err("Timeout fetching OHLCV".to_string()),
+    }
+}
+
+// ============================================================================
+// ADMIN - REFRESH DO CACHE DE TOKENS
+// ============================================================================
+
+#[derive(Debug, Serialize)]
+pub struct TokensRefreshResponse {
+    pub success: bool,
+    pub ccxt_id: String,
+    pub exchange_id: String,
+    pub update_status: String,
+    pub total_tokens: usize,
+    pub counts_by_quote: BTreeMap<String, usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+const DEFAULT_CACHE_QUOTE_ASSETS: &[&str] = &["USDT", "USDC", "USD", "BTC", "ETH", "BRL", "EUR"];
+
+/// Quotes mantidas no cache de tokens (`TOKEN_CACHE_QUOTES`, separadas por vírgula)
+pub fn cache_quote_assets() -> Vec<String> {
+    let configured: Vec<String> = std::env::var("TOKEN_CACHE_QUOTES")
+        .unwrap_or_default()
+        .split(',')
+        .map(|q| q.trim().to_uppercase())
+        .filter(|q| !q.is_empty())
+        .collect();
+    if configured.is_empty() {
+        DEFAULT_CACHE_QUOTE_ASSETS.iter().map(|q| q.to_string()).collect()
+    } else {
+        configured
+    }
+}
+
+/// Agrupa os tokens por quote (USDT, BRL, ...) no formato de `tokens_by_quote`.
+/// Mercados contra quotes fora de `quotes` são descartados.
+pub fn group_tokens_by_quote(tokens: Vec<TokenInfo>, quotes: &[String]) -> HashMap<String, Vec<TokenInfo>> {
+    let mut grouped: HashMap<String, Vec<TokenInfo>> = HashMap::new();
+    for token in tokens {
+        let quote = token.quote.to_uppercase();
+        if !quotes.contains(&quote) {
+            continue;
+        }
+        grouped.entry(quote).or_default().push(token);
+    }
+    for list in grouped.values_mut() {
+        list.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+    }
+    grouped
+}
+
+/// Monta o `$set` do upsert em `tokens_exchanges`. Em caso de falha mantém a
+/// lista anterior e grava apenas o status/erro.
+pub fn tokens_cache_update(
+    exchange_id: &str,
+    ccxt_id: &str,
+    result: &Result<HashMap<String, Vec<TokenInfo>>, String>,
+    now: BsonDateTime,
+) -> Result<Document, String> {
+    let mut update = doc! {
+        "exchange_id": exchange_id,
+        "exchange_ccxt_id": ccxt_id,
+        "updated_at": now,
+    };
+    match result {
+        Ok(tokens_by_quote) => {
+            let tokens = mongodb::bson::to_bson(tokens_by_quote)
+                .map_err(|e| format!("Failed to serialize tokens: {}", e))?;
+            update.insert("tokens_by_quote", tokens);
+            update.insert("update_status", "success");
+            update.insert("error", Bson::Null);
+        }
+        Err(e) => {
+            update.insert("update_status", "error");
+            update.insert("error", e.as_str());
+        }
+    }
+    Ok(update)
+}
+
+const DEFAULT_MARKETS_CACHE_MAX_AGE_SECS: i64 = 24 * 3600;
+
+lazy_static::lazy_static! {
+    /// Exchanges com refresh de mercados em andamento (evita disparar vários em paralelo)
+    static ref MARKETS_REFRESHING: std::sync::Mutex<HashSet<String>> = Default::default();
+}
+
+/// Idade a partir da qual o cache de mercados é considerado velho (`MARKETS_CACHE_MAX_AGE_SECS`)
+fn markets_cache_max_age_secs() -> i64 {
+    std::env::var("MARKETS_CACHE_MAX_AGE_SECS")
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok())
+        .filter(|v| *v > 0)
+        .unwrap_or(DEFAULT_MARKETS_CACHE_MAX_AGE_SECS)
+}
+
+/// Idade do cache usado numa validação de símbolo
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct MarketsCacheStatus {
+    /// Idade (s) do cache mais antigo consultado; None = sem data de atualização
+    pub markets_cache_age_secs: Option<i64>,
+    /// Mais velho que `MARKETS_CACHE_MAX_AGE_SECS`: dados servidos enquanto o refresh roda
+    pub markets_stale: bool,
+}
+
+/// Status dos caches consultados + ccxt_ids que precisam de refresh em background.
+/// Cache sem `updated_at` conta como velho.
+pub fn markets_cache_status(caches: &[TokensExchangeCache], now_ms: i64, max_age_secs: i64) -> (MarketsCacheStatus, Vec<String>) {
+    let mut status = MarketsCacheStatus::default();
+    let mut to_refresh = Vec::new();
+    for cache in caches {
+        let age = cache.updated_at.map(|at| ((now_ms - at.timestamp_millis()) / 1000).max(0));
+        if let Some(age) = age {
+            status.markets_cache_age_secs = Some(status.markets_cache_age_secs.map_or(age, |oldest| oldest.max(age)));
+        }
+        if age.is_none_or(|age| age > max_age_secs) {
+            status.markets_stale = true;
+            to_refresh.extend(cache.exchange_ccxt_id.clone());
+        }
+    }
+    (status, to_refresh)
+}
+
+/// Marca o refresh como em andamento; false se já havia um para a exchange
+fn claim_markets_refresh(ccxt_id: &str) -> bool {
+    MARKETS_REFRESHING.lock().unwrap_or_else(|e| e.into_inner()).insert(ccxt_id.to_string())
+}
+
+fn release_markets_refresh(ccxt_id: &str) {
+    MARKETS_REFRESHING.lock().unwrap_or_else(|e| e.into_inner()).remove(ccxt_id);
+}
+
+/// Recarrega em background os caches velhos (um refresh por exchange por vez)
+fn spawn_markets_refresh(db: &MongoDB, stale: Vec<String>) {
+    for ccxt_id in stale.into_iter().filter(|id| claim_markets_refresh(id)) {
+        log::warn!("⏳ Markets cache for {} is stale, refreshing in background", ccxt_id);
+        let db = db.clone();
+        tokio::spawn(async move {
+            match refresh_exchange_tokens(&db, &ccxt_id).await {
+                Ok(response) if response.success => log::info!("✅ Markets cache refreshed for {}", ccxt_id),
+                Ok(response) => log::warn!("⚠️ Markets refresh failed for {}: {}", ccxt_id, response.error.unwrap_or_default()),
+                Err(e) => log::warn!("⚠️ Markets refresh failed for {}: {}", ccxt_id, e),
+            }
+            release_markets_refresh(&ccxt_id);
+        });
+    }
+}
+
+/// Pares (`BTC/USDT`) presentes no cache de mercados — de uma exchange ou de todas.
+/// Cache velho continua sendo usado, mas sai marcado e dispara um refresh.
+pub async fn cached_market_pairs(db: &MongoDB, ccxt_id: Option<&str>) -> Result<(HashSet<String>, MarketsCacheStatus), String> {
+    use futures::TryStreamExt;
+
+    let filter = match ccxt_id {
+        Some(id) => doc! { "exchange_ccxt_id": id, "update_status": "success" },
+        None => doc! { "update_status": "success" },
+    };
+    let caches: Vec<TokensExchangeCache> = db.collection::<TokensExchangeCache>("tokens_exchanges")
+        .find(filter).await
+        .map_err(|e| format!("Database error: {}", e))?
+        .try_collect().await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    let (status, stale) = markets_cache_status(&caches, chrono::Utc::now().timestamp_millis(), markets_cache_max_age_secs());
+    spawn_markets_refresh(db, stale);
+
+    let pairs = caches.iter()
+        .flat_map(|c| c.tokens_by_quote.values().flatten())
+        .map(|t| t.pair.to_uppercase())
+        .collect();
+    Ok((pairs, status))
+}
+
+/// Recarrega os mercados de uma exchange via CCXT e faz upsert do cache de tokens
+pub async fn refresh_exchange_tokens(db: &MongoDB, ccxt_id: &str) -> Result<TokensRefreshResponse, String> {
+    let exchange = db.collection::<crate::models::ExchangeCatalog>("exchanges")
+        .find_one(doc! { "ccxt_id": ccxt_id })
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or_else(|| format!("Exchange not found: {}", ccxt_id))?;
+    let exchange_id = exchange._id.map(|id| id.to_hex()).unwrap_or_default();
+
+    log::info!("🔄 Refreshing token cache for {}", ccxt_id);
+
+    let ccxt_id_clone = ccxt_id.to_string();
+    let fetch_task = spawn_ccxt_paced(ccxt_id, move || {
+        // Mercados são públicos: não precisa de credenciais
+        let client = CCXTClient::new(&ccxt_id_clone, "", "", None)?;
+        client.fetch_market_tokens_sync()
+    });
+    let result = match timeout(Duration::from_secs(30), fetch_task).await {
+        Ok(Ok(result)) => result.map(|tokens| group_tokens_by_quote(tokens, &cache_quote_assets())),
+        Ok(Err(e)) => Err(format!("Task join error: {}", e)),
+        Err(_) => Err("Timeout fetching markets".to_string()),
+    };
+
+    let update = tokens_cache_update(&exchange_id, ccxt_id, &result, BsonDateTime::now())?;
+    db.collection::<Document>("tokens_exchanges")
+        .update_one(doc! { "exchange_ccxt_id": ccxt_id }, doc! { "$set": update })
+        .upsert(true)
+        .await
+        .map_err(|e| format!("Failed to update token cache: {}", e))?;
+
+    let (counts_by_quote, error): (BTreeMap<String, usize>, _) = match &result {
+        Ok(grouped) => (grouped.iter().map(|(quote, list)| (quote.clone(), list.len())).collect(), None),
+        Err(e) => {
+            log::error!("❌ Token cache refresh failed for {}: {}", ccxt_id, e);
+            (BTreeMap::new(), Some(e.clone()))
+        }
+    };
+
+    Ok(TokensRefreshResponse {
+        success: error.is_none(),
+        ccxt_id: ccxt_id.to_string(),
+        exchange_id,
+        update_status: if error.is_none() { "success" } else { "error" }.to_string(),
+        total_tokens: counts_by_quote.values().sum(),
+        counts_by_quote,
+        error,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FakeSource {
+        supports_current: bool,
+        supports_history: bool,
+    }
+
+    impl FundingRateSource for FakeSource {
+        fn funding_rate(&self, symbol: &str) -> Result<FundingRate, String> {
+            if !self.supports_current {
+                return Err(crate::ccxt::client::unsupported_capability_error("fake", "fetchFundingRate"));
+            }
+            FundingRate::from_ccxt(&serde_json::json!({ "symbol": symbol, "fundingRate": 0.0002 }))
+        }
+
+        fn funding_rate_history(&self, symbol: &str, _limit: usize) -> Result<Vec<FundingRateEntry>, String> {
+            if !self.supports_history {
+                return Err(crate::ccxt::client::unsupported_capability_error("fake", "fetchFundingRateHistory"));
+            }
+            Ok(vec![FundingRateEntry { symbol: symbol.into(), funding_rate: 0.0001, timestamp: 1 }])
+        }
+    }
+
+    #[test]
+    fn test_funding_rates_capability_unsupported() {
+        let spot_only = FakeSource { supports_current: false, supports_history: false };
+        let err = collect_funding_rates(&spot_only, "fake", "BTC/USDT:USDT", 10).unwrap_err();
+        assert!(is_not_supported(&err), "{}", err);
+
+        let no_history = FakeSource { supports_current: true, supports_history: false };
+        let response = collect_funding_rates(&no_history, "fake", "BTC/USDT:USDT", 10).unwrap();
+        assert_eq!(response.current.funding_rate, 0.0002);
+        assert!(response.history.is_empty());
+        assert!(!response.history_supported);
+
+        let full = FakeSource { supports_current: true, supports_history: true };
+        assert_eq!(collect_funding_rates(&full, "fake", "BTC/USDT:USDT", 10).unwrap().history.len(), 1);
+    }
+
+    struct FakeOhlcv;
+
+    impl OhlcvSource for FakeOhlcv {
+        fn timeframes(&self) -> Option<Vec<String>> {
+            Some(vec!["1m".into(), "1h".into(), "1d".into()])
+        }
+
+        fn ohlcv(&self, _symbol: &str, _timeframe: &str, limit: usize) -> Result<Vec<Candle>, String> {
+            Ok((0..limit.min(3) as i64).map(|i| Candle {
+                timestamp: 1_700_000_000_000 + i * 3_600_000,
+                open: 100.0, high: 110.0, low: 95.0, close: 105.0 + i as f64, volume: 12.5,
+            }).collect())
+        }
+    }
+
+    #[test]
+    fn test_ohlcv_returns_compact_candles_and_rejects_unknown_timeframe() {
+        let response = collect_ohlcv(&FakeOhlcv, "fake", "btc/usdt", Some("1h"), Some(50_000)).unwrap();
+        assert_eq!((response.symbol.as_str(), response.timeframe.as_str(), response.count), ("BTC/USDT", "1h", 3));
+        assert_eq!(response.candles[1], [1_700_003_600_000.0, 100.0, 110.0, 95.0, 106.0, 12.5]);
+        assert_eq!(collect_ohlcv(&FakeOhlcv, "fake", "BTC/USDT", None, None).unwrap().timeframe, "1h");
+
+        let err = collect_ohlcv(&FakeOhlcv, "fake", "BTC/USDT", Some("7m"), None).unwrap_err();
+        assert!(is_not_supported(&err), "{}", err);
+        assert!(err.contains("'7m'") && err.contains("1m, 1h, 1d"), "{}", err);
+
+        // Exchange sem `timeframes`: vale o formato padrão do CCXT
+        assert_eq!(resolve_timeframe(Some("1M"), None, "fake"), Ok("1M".to_string()));
+        assert!(resolve_timeframe(Some("abc"), None, "fake").is_err());
+    }
+
+    fn token(symbol: &str, quote: &str) -> TokenInfo {
+        TokenInfo {
+            symbol: symbol.into(), pair: format!("{}/{}", symbol, quote), quote: quote.into(),
+            min_amount: Some(0.001), max_amount: None, min_cost: None,
+            name: None, logo: None,
+        }
+    }
+
+    #[test]
+    fn test_refresh_writes_success_cache_grouped_by_quote() {
+        let tokens = vec![token("ETH", "USDT"), token("BTC", "USDT"), token("BTC", "BRL")];
+        let quotes = cache_quote_assets();
+        let result = Ok(group_tokens_by_quote(tokens, &quotes));
+        let update = tokens_cache_update("65f0", "binance", &result, BsonDateTime::from_millis(0)).unwrap();
+
+        assert_eq!(update.get_str("update_status").unwrap(), "success");
+        assert_eq!(update.get_str("exchange_ccxt_id").unwrap(), "binance");
+
+        // O documento gravado volta a ser lido como TokensExchangeCache
+        let mut stored = update.clone();
+        stored.insert("_id", ObjectId::new());
+        let cache: TokensExchangeCache = mongodb::bson::from_document(stored).unwrap();
+        let usdt: Vec<&str> = cache.tokens_by_quote["USDT"].iter().map(|t| t.pair.as_str()).collect();
+        assert_eq!(usdt, vec!["BTC/USDT", "ETH/USDT"]);
+        assert_eq!(cache.tokens_by_quote["BRL"].len(), 1);
+        assert!(cache.error.is_none());
+
+        let failed = tokens_cache_update("65f0", "binance", &Err("boom".into()), BsonDateTime::from_millis(0)).unwrap();
+        assert_eq!(failed.get_str("update_status").unwrap(), "error");
+        assert!(!failed.contains_key("tokens_by_quote"));
+    }
+
+    #[test]
+    fn test_catalog_match_fills_name_and_logo() {
+        let mut tokens = vec![token("btc", "USDT"), token("NEWCOIN", "USDT")];
+        let catalog = HashMap::from([("BTC".to_string(), Token {
+            _id: None, symbol: "BTC".into(), name: "Bitcoin".into(),
+            logo: Some("https://cdn.example/btc.png".into()), decimals: Some(8),
+            coingecko_id: Some("bitcoin".into()), is_active: true,
+        })]);
+        apply_catalog(&mut tokens, &catalog);
+
+        assert_eq!(tokens[0].name.as_deref(), Some("Bitcoin"));
+        assert_eq!(tokens[0].logo.as_deref(), Some("https://cdn.example/btc.png"));
+        assert!(tokens[1].name.is_none() && tokens[1].logo.is_none());
+
+        let json = serde_json::to_value(&tokens[1]).unwrap();
+        assert!(json.get("name").is_none() && json.get("logo").is_none());
+    }
+
+    #[test]
+    fn test_excluded_quotes_are_dropped_from_grouped_cache() {
+        let quotes: Vec<String> = DEFAULT_CACHE_QUOTE_ASSETS.iter().map(|q| q.to_string()).collect();
+        let tokens = vec![token("BTC", "USDT"), token("BTC", "TRY"), token("SHIB", "DOGE"), token("ETH", "brl")];
+        let grouped = group_tokens_by_quote(tokens, &quotes);
+
+        let mut keys: Vec<&str> = grouped.keys().map(|k| k.as_str()).collect();
+        keys.sort();
+        assert_eq!(keys, vec!["BRL", "USDT"]);
+        assert!(grouped.values().flatten().all(|t| t.symbol != "SHIB"));
+    }
+
+    struct FakeMarkets {
+        bases: Vec<&'static str>,
+    }
+
+    impl TickerSource for FakeMarkets {
+        fn ticker(&self, symbol: &str) -> Result<serde_json::Value, String> {
+            if symbol == "BTC/USDT" {
+                return Ok(serde_json::json!({ "symbol": symbol, "last": 65000.0 }));
+            }
+            Err(format!("Failed to fetch ticker: BadSymbol: binance does not have market symbol {}", symbol))
+        }
+
+        fn search_symbols(&self, query: &str, limit: usize) -> Result<Vec<String>, String> {
+            Ok(self.bases.iter().filter(|b| b.contains(query)).take(limit).map(|b| b.to_string()).collect())
+        }
+    }
+
+    #[test]
+    fn test_nonexistent_symbol_returns_friendly_error_with_suggestions() {
+        let markets = FakeMarkets { bases: vec!["BTC", "PEPE", "PEOPLE", "ETH"] };
+        assert!(fetch_ticker_checked(&markets, "Binance", "BTC/USDT").is_ok());
+
+        let err = fetch_ticker_checked(&markets, "Binance", "PEPX/USDT").unwrap_err();
+        assert_eq!(err.to_string(), "Symbol PEPX/USDT not available on exchange Binance");
+        match err {
+            TokenDetailsError::SymbolNotAvailable { suggestions, .. } => assert_eq!(suggestions, vec!["PEPE"]),
+            other => panic!("unexpected error: {}", other),
+        }
+    }
+
+    #[test]
+    fn test_market_info_uses_real_precision_and_limits() {
+        let rules = MarketRules {
+            active: true,
+            limits: crate::models::MarketLimits { min_amount: Some(0.01), min_cost: Some(10.0), ..Default::default() },
+            precision: crate::models::MarketPrecision { amount_decimals: Some(4), price_decimals: Some(2) },
+        };
+        let info = serde_json::to_value(market_info_from_rules(Some(&rules))).unwrap();
+        assert_eq!(info["precision"], serde_json::json!({ "amount": 4, "price": 2 }));
+        assert_eq!(info["limits"]["cost"]["min"], 10.0);
+        assert_eq!(info["limits"]["amount"]["min"], 0.01);
+        assert!(info["limits"]["price"]["max"].is_null());
+
+        // Sem metadados: null, nunca 8 inventado
+        let unknown = serde_json::to_value(market_info_from_rules(None)).unwrap();
+        assert!(unknown["precision"]["amount"].is_null() && unknown["precision"]["price"].is_null());
+        assert!(unknown["limits"]["cost"]["min"].is_null());
+    }
+
+    #[test]
+    fn test_nan_price_is_ignored_and_ties_are_deterministic() {
+        let quote = |exchange: &str, bid: &str, ask: &str| ExchangeQuote {
+            exchange: exchange.into(), bid: parse_price(bid), ask: parse_price(ask),
+        };
+        let quotes = vec![
+            quote("okx", "102", "101"),
+            quote("broken", "NaN", "inf"),
+            quote("binance", "102", "100"),
+            ExchangeQuote { exchange: "raw".into(), bid: Some(f64::NAN), ask: Some(f64::NAN) },
+            quote("kucoin", "99", "100"),
+        ];
+
+        let comparison = calculate_price_comparison(&quotes);
+        let best_bid = comparison.best_bid.unwrap();
+        let best_ask = comparison.best_ask.unwrap();
+        assert_eq!((best_bid.exchange.as_str(), best_bid.price), ("binance", 102.0));
+        assert_eq!((best_ask.exchange.as_str(), best_ask.price), ("binance", 100.0));
+        assert!((comparison.max_spread_percent - 2.0).abs() < 1e-9);
+
+        // Ordem de entrada não muda a saída
+        let mut reversed = quotes.clone();
+        reversed.reverse();
+        let pairs = |q: &[ExchangeQuote]| find_arbitrage_opportunities(q).into_iter()
+            .map(|o| (o.buy_from, o.sell_to, o.profit_percent)).collect::<Vec<_>>();
+        let opportunities = pairs(&quotes);
+        assert_eq!(opportunities, pairs(&reversed));
+        assert!(opportunities.iter().all(|(buy, sell, p)| p.is_finite() && buy != "broken" && sell != "raw"));
+        assert_eq!(opportunities[0], ("binance".to_string(), "okx".to_string(), 2.0));
+        assert_eq!(opportunities[1], ("kucoin".to_string(), "binance".to_string(), 2.0));
+    }
+
+    fn credentials(name: &str) -> ExchangeCredentials {
+        ExchangeCredentials {
+            exchange_id: name.into(), ccxt_id: name.into(), name: name.into(),
+            api_key: String::new(), api_secret: String::new(), passphrase: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_slow_exchange_reported_as_timeout_while_fast_ones_return() {
+        let policy = FanOutPolicy {
+            per_exchange: Duration::from_millis(500),
+            deadline: Duration::from_millis(100),
+            retry_backoff: Duration::from_millis(1),
+            max_concurrent: 4,
+        };
+        let flaky_attempts = std::sync::atomic::AtomicUsize::new(0);
+        let exchanges = vec![credentials("slow"), credentials("binance"), credentials("flaky")];
+
+        let outcomes = fan_out_with_deadline(&exchanges, policy, |exchange| {
+            let attempt = match exchange.name.as_str() {
+                "flaky" => flaky_attempts.fetch_add(1, std::sync::atomic::Ordering::SeqCst),
+                _ => 0,
+            };
+            async move {
+                match exchange.name.as_str() {
+                    "slow" => {
+                        tokio::time::sleep(Duration::from_secs(5)).await;
+                        Ok(1.0)
+                    }
+                    "flaky" if attempt == 0 => Err("NetworkError: connection reset".to_string()),
+                    _ => Ok(65_000.0),
+                }
+            }
+        }).await;
+
+        let by_name: HashMap<String, FanOutOutcome<f64>> = outcomes.into_iter()
+            .map(|(exchange, outcome)| (exchange.name, outcome))
+            .collect();
+        assert_eq!(by_name["binance"], FanOutOutcome::Success(65_000.0));
+        // Falha transitória repetida uma vez
+        assert_eq!(by_name["flaky"], FanOutOutcome::Success(65_000.0));
+        assert_eq!(by_name["slow"], FanOutOutcome::Timeout);
+    }
+
+    #[tokio::test]
+    async fn test_fan_out_never_exceeds_max_concurrent_exchanges() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        let policy = FanOutPolicy {
+            per_exchange: Duration::from_millis(500),
+            deadline: Duration::from_secs(5),
+            retry_backoff: Duration::from_millis(1),
+            max_concurrent: 3,
+        };
+        let running = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        let exchanges: Vec<_> = (0..10).map(|i| credentials(&format!("ex{}", i))).collect();
+
+        let outcomes = fan_out_with_deadline(&exchanges, policy, |_| async {
+            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+            peak.fetch_max(now, Ordering::SeqCst);
+            // Cada chamada leva 50ms: o timeout de 500ms não conta a espera na fila
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            running.fetch_sub(1, Ordering::SeqCst);
+            Ok(1.0)
+        }).await;
+
+        assert_eq!(outcomes.len(), 10);
+        assert!(outcomes.iter().all(|(_, o)| *o == FanOutOutcome::Success(1.0)));
+        assert_eq!(peak.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_aged_markets_cache_flagged_stale_and_refreshed() {
+        let now_ms = 10 * 86_400_000;
+        let cache = |ccxt_id: &str, age_secs: i64| TokensExchangeCache {
+            id: ObjectId::new(),
+            exchange_id: "ex".into(),
+            exchange_ccxt_id: Some(ccxt_id.into()),
+            tokens_by_quote: HashMap::new(),
+            update_status: "success".into(),
+            error: None,
+            updated_at: Some(BsonDateTime::from_millis(now_ms - age_secs * 1000)),
+        };
+
+        let (fresh, to_refresh) = markets_cache_status(&[cache("binance", 600)], now_ms, 3600);
+        assert_eq!(fresh, MarketsCacheStatus { markets_cache_age_secs: Some(600), markets_stale: false });
+        assert!(to_refresh.is_empty());
+
+        let (aged, to_refresh) = markets_cache_status(&[cache("binance", 600), cache("stale-ex", 7200)], now_ms, 3600);
+        assert_eq!(aged, MarketsCacheStatus { markets_cache_age_secs: Some(7200), markets_stale: true });
+        assert_eq!(to_refresh, vec!["stale-ex".to_string()]);
+
+        // Um refresh por exchange até terminar
+        assert!(claim_markets_refresh("stale-ex"));
+        assert!(!claim_markets_refresh("stale-ex"));
+        release_markets_refresh("stale-ex");
+        assert!(claim_markets_refresh("stale-ex"));
+        release_markets_refresh("stale-ex");
+    }
+
+    #[test]
+    fn test_batch_details_for_three_symbols_from_one_ticker_fetch() {
+        struct BatchMarkets {
+            batch_calls: std::cell::Cell<usize>,
+            single_calls: std::cell::Cell<usize>,
+        }
+        impl TickerSource for BatchMarkets {
+            fn ticker(&self, symbol: &str) -> Result<serde_json::Value, String> {
+                self.single_calls.set(self.single_calls.get() + 1);
+                Err(format!("BadSymbol: binance does not have market symbol {}", symbol))
+            }
+            fn search_symbols(&self, _query: &str, _limit: usize) -> Result<Vec<String>, String> {
+                Ok(vec![])
+            }
+            fn tickers(&self, symbols: &[String]) -> Result<HashMap<String, serde_json::Value>, String> {
+                self.batch_calls.set(self.batch_calls.get() + 1);
+                let prices = [("BTC/USDT", 65000.0), ("ETH/USDT", 3500.0), ("SOL/USDT", 150.0)];
+                Ok(prices.iter()
+                    .filter(|(s, _)| symbols.iter().any(|r| r == s))
+                    .map(|(s, p)| (s.to_string(), serde_json::json!({ "symbol": s, "last": p, "percentage": 2.0, "quoteVolume": 1e6 })))
+                    .collect())
+            }
+        }
+
+        let exchange = DecryptedExchange {
+            exchange_id: "ex".into(), ccxt_id: "binance".into(), name: "Binance".into(),
+            api_key: "k".into(), api_secret: "s".into(), passphrase: None, is_active: true,
+            can_trade: None, sub_account: None,
+        };
+        let markets = BatchMarkets { batch_calls: Default::default(), single_calls: Default::default() };
+        let symbols = normalize_batch_symbols(&[" btc/usdt".into(), "ETH/USDT".into(), "SOL/USDT".into(), "BTC/USDT".into()]).unwrap();
+        let results = collect_batch_token_details(&markets, &exchange, &symbols);
+
+        assert_eq!(markets.batch_calls.get(), 1);
+        assert_eq!(markets.single_calls.get(), 0);
+        assert_eq!(results.len(), 3);
+        let eth = results[1].data.as_ref().unwrap();
+        assert_eq!((eth.symbol.as_str(), eth.quote.as_str(), eth.price.current.as_str()), ("ETH", "USDT", "3500"));
+        assert_eq!(results[2].data.as_ref().unwrap().volume.quote_24h, "1000000");
+
+        // Símbolo inexistente: erro só dele, sem derrubar o lote
+        let symbols = normalize_batch_symbols(&["BTC/USDT".into(), "NOPE/USDT".into()]).unwrap();
+        let results = collect_batch_token_details(&markets, &exchange, &symbols);
+        assert!(results[0].data.is_some());
+        assert_eq!(results[1].error.as_deref(), Some("Symbol NOPE/USDT not available on exchange Binance"));
+        assert!(normalize_batch_symbols(&[" ".into()]).is_err());
+    }
+}
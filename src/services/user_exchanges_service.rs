@@ -4,8 +4,8 @@
 
 use crate::{
     database::MongoDB,
-    models::{UserExchanges, UserExchangeItem, ExchangeCatalog, DecryptedExchange},
-    utils::crypto::{encrypt_fernet_via_python, decrypt_fernet_via_python},
+    models::{UserExchanges, UserExchangeItem, ExchangeCatalog, DecryptedExchange, ApiPermissions},
+    utils::crypto::{encrypt_fernet_via_python, decrypt_fernet_batch_via_python},
 };
 use mongodb::bson::{doc, oid::ObjectId, DateTime};
 use serde::{Deserialize, Serialize};
@@ -20,6 +20,13 @@ pub struct AddExchangeRequest {
     pub api_key: String,
     pub api_secret: String,
     pub passphrase: Option<String>,
+    /// Obrigatório (true) para exchanges em `HIGH_RISK_EXCHANGES`
+    #[serde(default)]
+    pub acknowledged_risk: bool,
+    /// Subconta (OKX/Bybit) a operar; a API key precisa ter sido criada dentro dela.
+    /// Ignorada pelas demais exchanges
+    #[serde(default)]
+    pub sub_account: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -32,14 +39,6 @@ pub struct AddExchangeResponse {
 
 // ==================== VALIDATION MODELS ====================
 
-#[derive(Debug, Clone, Serialize)]
-pub struct ApiPermissions {
-    pub can_read: bool,
-    pub can_trade: bool,
-    pub can_withdraw: bool,
-    pub is_restricted: bool,  // IP whitelist ativo
-}
-
 #[derive(Debug, Clone, Serialize)]
 pub struct RateLimitInfo {
     pub remaining: Option<u32>,
@@ -50,7 +49,8 @@ pub struct RateLimitInfo {
 #[derive(Debug, Clone, Serialize)]
 pub struct ExchangeValidationResult {
     pub is_valid: bool,
-    pub permissions: ApiPermissions,
+    /// None quando a exchange não informa as permissões da chave
+    pub permissions: Option<ApiPermissions>,
     pub rate_limit_info: RateLimitInfo,
     pub error: Option<String>,
 }
@@ -73,6 +73,11 @@ pub struct UserExchangeInfo {
     pub country: Option<String>,  // país de origem
     #[serde(skip_serializing_if = "Option::is_none")]
     pub url: Option<String>,      // URL da exchange
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub_account: Option<String>,
+    /// Permissões detectadas na última validação da chave (None = desconhecidas)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub permissions: Option<ApiPermissions>,
     pub created_at: String,
     pub linked_at: String,  // Alias para created_at (compatibilidade frontend)
 }
@@ -84,12 +89,34 @@ pub struct ListExchangesResponse {
     pub count: usize,
 }
 
+/// Exchange conectada + saldo ao vivo (tela de configurações)
+#[derive(Debug, Serialize)]
+pub struct ExchangeOverview {
+    #[serde(flatten)]
+    pub info: UserExchangeInfo,
+    /// None quando a exchange está inativa ou o saldo não pôde ser buscado
+    pub total_usd: Option<f64>,
+    pub token_count: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub balance_error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExchangesOverviewResponse {
+    pub success: bool,
+    pub exchanges: Vec<ExchangeOverview>,
+    pub total_usd: f64,
+    pub count: usize,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct UpdateExchangeRequest {
     pub is_active: Option<bool>,
     pub api_key: Option<String>,
     pub api_secret: Option<String>,
     pub passphrase: Option<String>,
+    /// String vazia remove a subconta. Trocar de subconta exige enviar a chave dela
+    pub sub_account: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -108,25 +135,79 @@ pub struct DeleteExchangeResponse {
 
 // ==================== SERVICE FUNCTIONS ====================
 
+/// Subconta informada sem espaços; vazia = conta principal
+fn normalize_sub_account(sub_account: Option<&str>) -> Option<String> {
+    sub_account.map(str::trim).filter(|s| !s.is_empty()).map(str::to_string)
+}
+
+/// ccxt_ids que exigem aceite explícito de risco (`HIGH_RISK_EXCHANGES`, separados por vírgula)
+pub fn high_risk_exchanges() -> Vec<String> {
+    env::var("HIGH_RISK_EXCHANGES")
+        .unwrap_or_default()
+        .split(',')
+        .map(|id| id.trim().to_lowercase())
+        .filter(|id| !id.is_empty())
+        .collect()
+}
+
+/// Ok(true) quando a exchange é de alto risco e o aviso foi aceito (o aceite é gravado);
+/// Err quando é de alto risco e o usuário não aceitou.
+pub fn check_risk_acknowledgement(ccxt_id: &str, acknowledged: bool, high_risk: &[String]) -> Result<bool, String> {
+    if !high_risk.iter().any(|id| id.eq_ignore_ascii_case(ccxt_id)) {
+        return Ok(false);
+    }
+    if !acknowledged {
+        return Err(format!(
+            "'{}' is flagged as a higher-risk exchange (e.g. known withdrawal issues). \
+             Resend with acknowledged_risk: true to confirm you understand the risk.",
+            ccxt_id
+        ));
+    }
+    Ok(true)
+}
+
+/// Regras de segurança da chave: leitura obrigatória, saque proibido. Sem trade só avisa
+/// (estratégias em modo somente alerta)
+pub fn check_key_permissions(permissions: &ApiPermissions) -> Result<(), String> {
+    if !permissions.can_read {
+        log::error!("❌ API key cannot read balances - REJECTING!");
+        return Err(
+            "API key does not have Read permission. Please create an API key with Read and Spot Trade permissions enabled.".to_string()
+        );
+    }
+    if permissions.can_withdraw {
+        log::error!("❌ API key has withdrawal permissions - REJECTING for security!");
+        return Err(
+            "API key has withdrawal permissions. For security reasons, please create a new API key with only Read and Spot Trade permissions (disable Withdrawals).".to_string()
+        );
+    }
+    if !permissions.can_trade {
+        log::warn!("⚠️ API key cannot trade - accepted in alert-only mode (signals without orders)");
+    }
+    Ok(())
+}
+
 /// Valida a conexão com a exchange antes de salvar
 async fn validate_exchange_connection(
     exchange_type: &str,
     api_key: &str,
     api_secret: &str,
     passphrase: Option<&str>,
+    sub_account: Option<&str>,
 ) -> Result<ExchangeValidationResult, String> {
     log::info!("🔐 Validating connection to {} exchange...", exchange_type);
     
-    use crate::utils::thread_pool::spawn_ccxt_blocking;
+    use crate::utils::thread_pool::spawn_ccxt_paced;
     use crate::ccxt::client::CCXTClient;
     
     let exchange_type = exchange_type.to_string();
     let api_key = api_key.to_string();
     let api_secret = api_secret.to_string();
     let passphrase = passphrase.map(|s| s.to_string());
+    let sub_account = sub_account.map(|s| s.to_string());
     
     // Executar validações em thread bloqueante (Python/GIL)
-    let validation_result = spawn_ccxt_blocking(move || {
+    let validation_result = spawn_ccxt_paced(&exchange_type.clone(), move || {
         // 1. Criar cliente CCXT
         let client = CCXTClient::new(
             &exchange_type,
@@ -140,45 +221,30 @@ async fn validate_exchange_connection(
         
         // Verificar permissões da API key
         log::info!("🔍 Checking API key permissions...");
-        let permissions = client.check_api_permissions()
-            .unwrap_or_else(|e| {
+        // Desconhecidas: não guarda nada (a estratégia opera, ver `can_execute_orders`)
+        let permissions = match client.check_api_permissions() {
+            Ok(permissions) => Some(permissions),
+            Err(e) => {
                 log::warn!("⚠️ Could not determine permissions: {}", e);
-                ApiPermissions {
-                    can_read: true,  // Assumir que leitura funcionou
-                    can_trade: false, // Desconhecido
-                    can_withdraw: false, // Desconhecido
-                    is_restricted: false,
-                }
-            });
+                None
+            }
+        };
         
         // 🚨 VALIDAÇÃO DE SEGURANÇA DAS PERMISSÕES
-        // A key DEVE ter: Read + Trade (Spot)
+        // A key DEVE ter: Read
         // A key NÃO DEVE ter: Withdraw
-        
-        if !permissions.can_read {
-            log::error!("❌ API key cannot read balances - REJECTING!");
-            return Err(
-                "API key does not have Read permission. Please create an API key with Read and Spot Trade permissions enabled.".to_string()
-            );
+        // Sem Trade é aceita: as estratégias da exchange rodam em modo somente alerta
+        if let Some(permissions) = &permissions {
+            check_key_permissions(permissions)?;
+            log::info!("✅ API key permissions validated: read={}, trade={}, withdraw={}", 
+                permissions.can_read, permissions.can_trade, permissions.can_withdraw);
         }
         
-        if !permissions.can_trade {
-            log::error!("❌ API key cannot trade - REJECTING!");
-            return Err(
-                "API key does not have Trade permission. Please create an API key with Read and Spot Trade permissions enabled.".to_string()
-            );
+        // Subconta: a chave precisa ter sido criada nela (a principal opera a conta principal)
+        if let Some(sub_account) = &sub_account {
+            client.verify_sub_account_key_sync(sub_account)?;
         }
         
-        if permissions.can_withdraw {
-            log::error!("❌ API key has withdrawal permissions - REJECTING for security!");
-            return Err(
-                "API key has withdrawal permissions. For security reasons, please create a new API key with only Read and Spot Trade permissions (disable Withdrawals).".to_string()
-            );
-        }
-        
-        log::info!("✅ API key permissions validated: read={}, trade={}, withdraw={}", 
-            permissions.can_read, permissions.can_trade, permissions.can_withdraw);
-        
         // 3. Obter informações de rate limit
         log::info!("🔍 Checking rate limits...");
         let rate_limit_info = client.get_rate_limit_info()
@@ -231,13 +297,28 @@ pub async fn add_user_exchange(
         });
     }
 
+    // ⚠️ Exchanges de alto risco exigem aceite explícito
+    let risk_acknowledged = match check_risk_acknowledgement(&request.exchange_type, request.acknowledged_risk, &high_risk_exchanges()) {
+        Ok(acknowledged) => acknowledged,
+        Err(e) => {
+            log::warn!("⚠️ {} rejected for user {}: risk not acknowledged", request.exchange_type, user_id);
+            return Ok(AddExchangeResponse {
+                success: false,
+                exchange_id: String::new(),
+                error: Some(e),
+            });
+        }
+    };
+
     // 🔐 3. VALIDAR CONEXÃO COM A EXCHANGE (NOVO)
     log::info!("🔐 Validating exchange connection before saving credentials...");
-    match validate_exchange_connection(
+    let sub_account = normalize_sub_account(request.sub_account.as_deref());
+    let permissions = match validate_exchange_connection(
         &request.exchange_type,
         &request.api_key,
         &request.api_secret,
         request.passphrase.as_deref(),
+        sub_account.as_deref(),
     ).await {
         Ok(validation) => {
             if !validation.is_valid {
@@ -251,18 +332,22 @@ pub async fn add_user_exchange(
             
             // Logar informações de validação
             log::info!("✅ Exchange validation successful:");
-            log::info!("    Can read: {}", validation.permissions.can_read);
-            log::info!("   💱 Can trade: {}", validation.permissions.can_trade);
-            log::info!("   💸 Can withdraw: {}", validation.permissions.can_withdraw);
-            
-            if validation.permissions.is_restricted {
-                log::info!("✅ API key has IP restrictions enabled (secure)");
+            if let Some(permissions) = &validation.permissions {
+                log::info!("    Can read: {}", permissions.can_read);
+                log::info!("   💱 Can trade: {}", permissions.can_trade);
+                log::info!("   💸 Can withdraw: {}", permissions.can_withdraw);
+                
+                if permissions.is_restricted {
+                    log::info!("✅ API key has IP restrictions enabled (secure)");
+                }
+                
+                // Sem trade: salva e as estratégias desta exchange só geram alertas
+                if !permissions.can_trade {
+                    log::warn!("⚠️ API key does not have trading permissions - strategies will run in alert-only mode");
+                }
             }
             
-            // Alertar se não tem permissão de trade
-            if !validation.permissions.can_trade {
-                log::warn!("⚠️ API key does not have trading permissions - only read access");
-            }
+            validation.permissions
         }
         Err(e) => {
             log::error!("❌ Failed to validate exchange connection: {}", e);
@@ -272,7 +357,7 @@ pub async fn add_user_exchange(
                 error: Some(format!("Connection validation failed: {}", e)),
             });
         }
-    }
+    };
 
     // 4. Criptografar credenciais
     let encryption_key = env::var("ENCRYPTION_KEY")
@@ -302,6 +387,9 @@ pub async fn add_user_exchange(
         created_at: Some(now.into()),
         updated_at: Some(now.into()),
         reconnected_at: None,
+        permissions,
+        risk_acknowledged_at: risk_acknowledged.then(|| now.into()),
+        sub_account,
     };
 
     // 5. Buscar ou criar documento user_exchanges
@@ -421,6 +509,8 @@ pub async fn list_user_exchanges(
                 requires_passphrase: Some(catalog.requires_passphrase),
                 country: catalog.pais_de_origem.clone(),
                 url: catalog.url.clone(),
+                sub_account: ex.sub_account.clone(),
+                permissions: ex.permissions.clone(),
                 created_at: created_at_str.clone(),
                 linked_at: created_at_str,  // Mesmo valor que created_at
             });
@@ -464,6 +554,38 @@ pub async fn update_user_exchange(
         exchange.is_active = is_active;
     }
 
+    let mut validated_permissions = None;
+    if let Some(sub_account) = &request.sub_account {
+        let sub_account = normalize_sub_account(Some(sub_account));
+        // Trocar de subconta é trocar de chave: as credenciais novas vêm junto e são validadas
+        if sub_account.is_some() && sub_account != exchange.sub_account {
+            let (Some(api_key), Some(api_secret)) = (&request.api_key, &request.api_secret) else {
+                return Ok(UpdateExchangeResponse {
+                    success: false,
+                    error: Some("Send the API key and secret created inside the sub-account together with sub_account".to_string()),
+                });
+            };
+            let catalog_oid = ObjectId::parse_str(exchange_id).map_err(|e| format!("Invalid exchange id: {}", e))?;
+            let catalog = db.collection::<ExchangeCatalog>("exchanges")
+                .find_one(doc! { "_id": catalog_oid })
+                .await
+                .map_err(|e| format!("Database error: {}", e))?
+                .ok_or("Exchange catalog not found")?;
+            match validate_exchange_connection(
+                &catalog.ccxt_id, api_key, api_secret, request.passphrase.as_deref(), sub_account.as_deref(),
+            ).await {
+                Ok(validation) => validated_permissions = validation.permissions,
+                Err(e) => {
+                    return Ok(UpdateExchangeResponse {
+                        success: false,
+                        error: Some(format!("Connection validation failed: {}", e)),
+                    });
+                }
+            }
+        }
+        exchange.sub_account = sub_account;
+    }
+
     // Atualizar credenciais se fornecidas
     if request.api_key.is_some() || request.api_secret.is_some() || request.passphrase.is_some() {
         let encryption_key = env::var("ENCRYPTION_KEY")
@@ -485,6 +607,8 @@ pub async fn update_user_exchange(
         }
 
         exchange.reconnected_at = Some(DateTime::now().into());
+        // Chave nova: permissões da anterior não valem mais (só as validadas agora)
+        exchange.permissions = validated_permissions;
     }
 
     exchange.updated_at = Some(DateTime::now().into());
@@ -506,6 +630,178 @@ pub async fn update_user_exchange(
     })
 }
 
+/// Junta os metadados salvos com os saldos ao vivo. Falha em uma exchange
+/// não afeta as demais — ela aparece com `balance_error`.
+pub fn merge_exchange_overview(
+    infos: Vec<UserExchangeInfo>,
+    balances: &[crate::models::ExchangeBalance],
+) -> ExchangesOverviewResponse {
+    let exchanges: Vec<ExchangeOverview> = infos.into_iter()
+        .map(|info| {
+            if !info.is_active {
+                return ExchangeOverview { info, total_usd: None, token_count: None, balance_error: None };
+            }
+            match balances.iter().find(|b| b.exchange_id == info.exchange_id) {
+                Some(b) if b.success => ExchangeOverview {
+                    total_usd: Some(b.total_usd),
+                    token_count: Some(b.balances.values().filter(|bal| bal.total > 0.0).count()),
+                    balance_error: None,
+                    info,
+                },
+                Some(b) => ExchangeOverview {
+                    info, total_usd: None, token_count: None,
+                    balance_error: Some(b.error.clone().unwrap_or_else(|| "Failed to fetch balance".to_string())),
+                },
+                None => ExchangeOverview {
+                    info, total_usd: None, token_count: None,
+                    balance_error: Some("Balance unavailable".to_string()),
+                },
+            }
+        })
+        .collect();
+
+    ExchangesOverviewResponse {
+        success: true,
+        total_usd: exchanges.iter().filter_map(|e| e.total_usd).sum(),
+        count: exchanges.len(),
+        exchanges,
+    }
+}
+
+/// GET /exchanges/overview - Exchanges do usuário com saldo total ao vivo
+pub async fn get_exchanges_overview(
+    db: &MongoDB,
+    user_id: &str,
+) -> Result<ExchangesOverviewResponse, String> {
+    let listed = list_user_exchanges(db, user_id).await?;
+    let decrypted = get_user_exchanges_decrypted(db, user_id).await?;
+
+    // Saldos buscados em paralelo; falhas por exchange viram `balance_error`
+    let balances = crate::services::balance_service::fetch_balances_from_exchanges(decrypted).await?;
+    crate::services::credential_health_service::report_balance_results(db, user_id, &balances.exchanges).await;
+
+    Ok(merge_exchange_overview(listed.exchanges, &balances.exchanges))
+}
+
+/// Ativa/desativa uma exchange do usuário (uso interno)
+pub async fn set_exchange_active(
+    db: &MongoDB,
+    user_id: &str,
+    exchange_id: &str,
+    is_active: bool,
+) -> Result<(), String> {
+    let user_exchanges_collection = db.collection::<UserExchanges>("user_exchanges");
+
+    let mut user_doc = user_exchanges_collection
+        .find_one(doc! { "user_id": user_id })
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or("User has no exchanges")?;
+
+    let exchange = user_doc.exchanges.iter_mut()
+        .find(|e| e.exchange_id == exchange_id)
+        .ok_or("Exchange not found")?;
+
+    exchange.is_active = is_active;
+    exchange.updated_at = Some(DateTime::now().into());
+
+    user_exchanges_collection
+        .update_one(
+            doc! { "user_id": user_id },
+            doc! { "$set": { "exchanges": mongodb::bson::to_bson(&user_doc.exchanges).map_err(|e| e.to_string())? } }
+        )
+        .await
+        .map_err(|e| format!("Failed to update: {}", e))?;
+
+    Ok(())
+}
+
+/// Marca a exchange como reativada após a exchange aceitar as credenciais de novo
+pub fn mark_reactivated(exchange: &mut UserExchangeItem, permissions: Option<ApiPermissions>, now: DateTime) {
+    exchange.is_active = true;
+    exchange.permissions = permissions;
+    exchange.reconnected_at = Some(now.into());
+    exchange.updated_at = Some(now.into());
+}
+
+/// POST /exchanges/{exchange_id}/reactivate - Testa as credenciais salvas e
+/// reativa a exchange (ex.: desativada por falhas de autenticação)
+pub async fn reactivate_user_exchange(
+    db: &MongoDB,
+    user_id: &str,
+    exchange_id: &str,
+) -> Result<UpdateExchangeResponse, String> {
+    log::info!("🔄 Reactivating exchange {} for user {}", exchange_id, user_id);
+
+    let user_exchanges_collection = db.collection::<UserExchanges>("user_exchanges");
+    let mut user_doc = user_exchanges_collection
+        .find_one(doc! { "user_id": user_id })
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or("User has no exchanges")?;
+
+    let Some(exchange) = user_doc.exchanges.iter_mut().find(|e| e.exchange_id == exchange_id) else {
+        return Ok(UpdateExchangeResponse { success: false, error: Some("Exchange not found".to_string()) });
+    };
+
+    let catalog_oid = ObjectId::parse_str(exchange_id).map_err(|e| format!("Invalid exchange id: {}", e))?;
+    let catalog = db.collection::<ExchangeCatalog>("exchanges")
+        .find_one(doc! { "_id": catalog_oid })
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or("Exchange catalog not found")?;
+
+    let encryption_key = env::var("ENCRYPTION_KEY")
+        .map_err(|_| "ENCRYPTION_KEY not found in environment")?;
+    let items = vec![(exchange.clone(), catalog)];
+    let decrypted = tokio::task::spawn_blocking(move || decrypt_exchange_credentials(items, &encryption_key))
+        .await
+        .map_err(|e| format!("Decryption task failed: {}", e))??
+        .pop()
+        .ok_or("Failed to decrypt credentials")?;
+
+    let permissions = match validate_exchange_connection(
+        &decrypted.ccxt_id,
+        &decrypted.api_key,
+        &decrypted.api_secret,
+        decrypted.passphrase.as_deref(),
+        decrypted.sub_account.as_deref(),
+    ).await {
+        Ok(validation) if validation.is_valid => validation.permissions,
+        Ok(validation) => {
+            return Ok(UpdateExchangeResponse {
+                success: false,
+                error: validation.error.or(Some("Exchange connection validation failed".to_string())),
+            });
+        }
+        Err(e) => {
+            log::warn!("⚠️ Exchange {} still rejects credentials for user {}: {}", exchange_id, user_id, e);
+            return Ok(UpdateExchangeResponse {
+                success: false,
+                error: Some(format!("Connection validation failed: {}", e)),
+            });
+        }
+    };
+
+    mark_reactivated(exchange, permissions, DateTime::now());
+
+    user_exchanges_collection
+        .update_one(
+            doc! { "user_id": user_id },
+            doc! { "$set": { "exchanges": mongodb::bson::to_bson(&user_doc.exchanges).map_err(|e| e.to_string())? } }
+        )
+        .await
+        .map_err(|e| format!("Failed to update: {}", e))?;
+
+    crate::services::credential_health_service::AUTH_FAILURES.reset(user_id, exchange_id);
+    log::info!("✅ Exchange {} reactivated for user {}", exchange_id, user_id);
+
+    Ok(UpdateExchangeResponse {
+        success: true,
+        error: None,
+    })
+}
+
 /// DELETE /exchanges/{exchange_id} - Remove exchange do usuário
 pub async fn delete_user_exchange(
     db: &MongoDB,
@@ -589,55 +885,175 @@ pub async fn get_user_exchanges_decrypted(
         }
     }
 
-    // 3. Descriptografar em paralelo
+    // 3. Descriptografar todas as credenciais num único lote (uma aquisição do GIL)
     let encryption_key = env::var("ENCRYPTION_KEY")
         .map_err(|_| "ENCRYPTION_KEY not found in environment")?;
 
-    let decrypt_tasks: Vec<_> = active_exchanges
+    let items: Vec<_> = active_exchanges
         .into_iter()
         .filter_map(|user_exchange| {
             let exchange_oid = ObjectId::parse_str(&user_exchange.exchange_id).ok()?;
             let catalog = catalog_map.get(&exchange_oid)?.clone();
-            let key = encryption_key.clone();
-            
-            Some(tokio::task::spawn_blocking(move || {
-                let api_key = decrypt_fernet_via_python(&user_exchange.api_key_encrypted, &key)
-                    .unwrap_or_else(|e| {
-                        log::error!("Failed to decrypt API key: {}", e);
-                        user_exchange.api_key_encrypted.clone()
-                    });
-                
-                let api_secret = decrypt_fernet_via_python(&user_exchange.api_secret_encrypted, &key)
-                    .unwrap_or_else(|e| {
-                        log::error!("Failed to decrypt API secret: {}", e);
-                        user_exchange.api_secret_encrypted.clone()
-                    });
-                
-                let passphrase = user_exchange.passphrase_encrypted.as_ref()
-                    .and_then(|p| decrypt_fernet_via_python(p, &key).ok());
-                
-                DecryptedExchange {
-                    exchange_id: user_exchange.exchange_id,
-                    ccxt_id: catalog.ccxt_id.clone(),
-                    name: catalog.nome.clone().unwrap_or_else(|| "Unknown".to_string()),
-                    api_key,
-                    api_secret,
-                    passphrase,
-                    is_active: user_exchange.is_active,
-                }
-            }))
+            Some((user_exchange, catalog))
         })
         .collect();
 
-    let decrypt_results = futures::future::join_all(decrypt_tasks).await;
-    
-    let mut decrypted_exchanges = Vec::new();
-    for result in decrypt_results {
-        match result {
-            Ok(exchange) => decrypted_exchanges.push(exchange),
-            Err(e) => log::error!("Decryption task failed: {}", e),
+    tokio::task::spawn_blocking(move || decrypt_exchange_credentials(items, &encryption_key))
+        .await
+        .map_err(|e| format!("Decryption task failed: {}", e))?
+}
+
+/// Descriptografa as credenciais de várias exchanges com `decrypt_fernet_batch_via_python`.
+/// Falha ao descriptografar key/secret mantém o valor cifrado (comportamento legado);
+/// passphrase inválida vira None.
+pub fn decrypt_exchange_credentials(
+    items: Vec<(UserExchangeItem, ExchangeCatalog)>,
+    key: &str,
+) -> Result<Vec<DecryptedExchange>, String> {
+    let mut tokens: Vec<&str> = Vec::new();
+    for (user_exchange, _) in &items {
+        tokens.push(&user_exchange.api_key_encrypted);
+        tokens.push(&user_exchange.api_secret_encrypted);
+        if let Some(passphrase) = &user_exchange.passphrase_encrypted {
+            tokens.push(passphrase);
+        }
+    }
+    let mut decrypted = decrypt_fernet_batch_via_python(&tokens, key)?.into_iter();
+
+    let mut next_or_encrypted = |encrypted: &str, field: &str| match decrypted.next() {
+        Some(Ok(plain)) => Ok(plain),
+        Some(Err(e)) => {
+            log::error!("Failed to decrypt {}: {}", field, e);
+            Err(encrypted.to_string())
         }
+        None => Err(encrypted.to_string()),
+    };
+
+    let mut decrypted_exchanges = Vec::with_capacity(items.len());
+    for (user_exchange, catalog) in items {
+        let api_key = next_or_encrypted(&user_exchange.api_key_encrypted, "API key").unwrap_or_else(|e| e);
+        let api_secret = next_or_encrypted(&user_exchange.api_secret_encrypted, "API secret").unwrap_or_else(|e| e);
+        let passphrase = user_exchange.passphrase_encrypted.as_deref()
+            .and_then(|p| next_or_encrypted(p, "passphrase").ok());
+
+        decrypted_exchanges.push(DecryptedExchange {
+            exchange_id: user_exchange.exchange_id,
+            ccxt_id: catalog.ccxt_id.clone(),
+            name: catalog.nome.clone().unwrap_or_else(|| "Unknown".to_string()),
+            api_key,
+            api_secret,
+            passphrase,
+            is_active: user_exchange.is_active,
+            can_trade: user_exchange.permissions.as_ref().map(|p| p.can_trade),
+            sub_account: user_exchange.sub_account.clone(),
+        });
     }
 
     Ok(decrypted_exchanges)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Balance, ExchangeBalance};
+    use std::collections::HashMap;
+
+    fn info(id: &str, is_active: bool) -> UserExchangeInfo {
+        UserExchangeInfo {
+            exchange_id: id.into(), exchange_type: id.into(), exchange_name: id.to_uppercase(),
+            is_active, logo: None, icon: None, requires_passphrase: Some(false),
+            country: None, url: None, sub_account: None, permissions: None,
+            created_at: "Unknown".into(), linked_at: "Unknown".into(),
+        }
+    }
+
+    fn balance(symbol: &str, total: f64) -> (String, Balance) {
+        (symbol.into(), Balance {
+            symbol: symbol.into(), free: total, used: 0.0, total,
+            usd_value: None, change_24h: None, parse_error: None,
+        })
+    }
+
+    #[test]
+    fn test_high_risk_exchange_requires_acknowledgement() {
+        let high_risk = vec!["riskyex".to_string()];
+        let err = check_risk_acknowledgement("RiskyEx", false, &high_risk).unwrap_err();
+        assert!(err.contains("acknowledged_risk"), "{}", err);
+        assert_eq!(check_risk_acknowledgement("riskyex", true, &high_risk), Ok(true));
+
+        // Exchanges fora da lista não pedem aceite (nem gravam)
+        assert_eq!(check_risk_acknowledgement("binance", false, &high_risk), Ok(false));
+        assert_eq!(check_risk_acknowledgement("binance", true, &[]), Ok(false));
+
+        let request: AddExchangeRequest = serde_json::from_value(serde_json::json!({
+            "exchange_type": "riskyex", "api_key": "k", "api_secret": "s", "passphrase": null
+        })).unwrap();
+        assert!(!request.acknowledged_risk);
+    }
+
+    #[test]
+    fn test_overview_merges_metadata_with_live_balances() {
+        let balances = vec![
+            ExchangeBalance {
+                exchange: "Binance".into(), exchange_id: "binance".into(), success: true, error: None,
+                balances: HashMap::from([balance("BTC", 0.5), balance("USDT", 100.0), balance("DUST", 0.0)]),
+                total_usd: 32_600.0, fetched_at: 0, from_cache: false,
+            },
+            ExchangeBalance {
+                exchange: "Kraken".into(), exchange_id: "kraken".into(), success: false,
+                error: Some("RequestTimeout".into()), balances: HashMap::new(), total_usd: 0.0,
+                fetched_at: 0, from_cache: false,
+            },
+        ];
+        let mut binance_info = info("binance", true);
+        binance_info.permissions = Some(ApiPermissions { can_read: true, can_trade: false, can_withdraw: false, is_restricted: true });
+        let infos = vec![binance_info, info("kraken", true), info("mexc", false)];
+
+        let overview = merge_exchange_overview(infos, &balances);
+        assert_eq!(overview.count, 3);
+        assert_eq!(overview.total_usd, 32_600.0);
+
+        let binance = &overview.exchanges[0];
+        assert_eq!(binance.info.exchange_name, "BINANCE");
+        assert_eq!(binance.total_usd, Some(32_600.0));
+        assert_eq!(binance.token_count, Some(2));
+        // Permissões salvas aparecem no overview (chave só leitura → sem trade)
+        let json = serde_json::to_value(binance).unwrap();
+        assert_eq!(json["permissions"]["can_trade"], false);
+        assert_eq!(json["permissions"]["is_restricted"], true);
+        assert!(serde_json::to_value(&overview.exchanges[1]).unwrap().get("permissions").is_none());
+
+        let kraken = &overview.exchanges[1];
+        assert_eq!(kraken.total_usd, None);
+        assert_eq!(kraken.balance_error.as_deref(), Some("RequestTimeout"));
+
+        let mexc = &overview.exchanges[2];
+        assert!(!mexc.info.is_active);
+        assert!(mexc.total_usd.is_none() && mexc.balance_error.is_none());
+    }
+
+    #[test]
+    fn test_reactivation_restores_exchange_and_clears_failure_streak() {
+        use crate::services::credential_health_service::{AuthFailureAction, AuthFailureTracker};
+
+        let tracker = AuthFailureTracker::with_grace_period(2, 0);
+        let auth = Err("AuthenticationError: Invalid API-key");
+        assert_eq!(tracker.record_at("u1", "ex1", auth, 0), AuthFailureAction::Warn);
+        assert_eq!(tracker.record_at("u1", "ex1", auth, 10), AuthFailureAction::Disable);
+
+        let mut exchange = UserExchangeItem {
+            exchange_id: "ex1".into(), api_key_encrypted: "k".into(), api_secret_encrypted: "s".into(),
+            passphrase_encrypted: None, is_active: false, created_at: None, updated_at: None,
+            reconnected_at: None, permissions: None, risk_acknowledged_at: None, sub_account: None,
+        };
+        let permissions = ApiPermissions { can_read: true, can_trade: true, can_withdraw: false, is_restricted: true };
+        mark_reactivated(&mut exchange, Some(permissions.clone()), DateTime::from_millis(1_000));
+        tracker.reset("u1", "ex1");
+
+        assert!(exchange.is_active);
+        assert_eq!(exchange.permissions, Some(permissions));
+        assert!(exchange.reconnected_at.is_some());
+        // Falha depois da reativação começa uma sequência nova (aviso, não desativação)
+        assert_eq!(tracker.record_at("u1", "ex1", auth, 20), AuthFailureAction::Warn);
+    }
+}
@@ -0,0 +1,458 @@
+//! ⚙️ Preferências do usuário (coleção `user_settings`)
+//!
+//! Um documento por usuário com fuso horário, moeda de quote padrão, webhook
+//! de notificação, limite de poeira e limite de exposição. Quem precisa de uma
+//! preferência (snapshot diário, dust sweep, exposure cap) lê daqui — usuários
+//! sem documento recebem os defaults. Toda escrita passa por `validate_settings`.
+//! Sinais acionáveis das estratégias são enviados via POST para o
+//! `notification_webhook`, quando configurado (`deliver_signals_to_webhook`).
+//! A watchlist fica no mesmo documento, mas é gerenciada só pelos endpoints
+//! `/user/watchlist` (o PUT de settings não a altera).
+
+use crate::database::MongoDB;
+use crate::services::token_service::MarketsCacheStatus;
+use chrono::{DateTime, FixedOffset, Utc};
+use chrono_tz::Tz;
+use mongodb::bson::doc;
+use serde::{Deserialize, Serialize};
+
+pub const USER_SETTINGS_COLLECTION: &str = "user_settings";
+
+const MAX_WEBHOOK_LEN: usize = 2048;
+const MAX_WATCHLIST_SYMBOLS: usize = 100;
+const WEBHOOK_TIMEOUT_SECS: u64 = 10;
+
+lazy_static::lazy_static! {
+    static ref WEBHOOK_CLIENT: reqwest::Client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(WEBHOOK_TIMEOUT_SECS))
+        .build()
+        .unwrap_or_default();
+}
+
+/// Símbolo da watchlist, opcionalmente preso a uma exchange (ccxt_id)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WatchlistEntry {
+    pub symbol: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exchange: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UserSettings {
+    /// "UTC", offset fixo ("-03:00", "UTC+05:30") ou nome IANA ("America/Sao_Paulo")
+    #[serde(default = "default_timezone")]
+    pub timezone: String,
+    #[serde(default = "default_quote_currency")]
+    pub default_quote_currency: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notification_webhook: Option<String>,
+    #[serde(default = "default_dust_threshold_usd")]
+    pub dust_threshold_usd: f64,
+    /// Limite de exposição total (USD) somando as posições abertas de todas as estratégias
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_total_exposure_usd: Option<f64>,
+    /// Estratégias novas nascem em paper (sinais sem ordens até `confirm-live`).
+    /// None segue o padrão global `STRATEGY_REQUIRE_CONFIRMATION`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paper_trading_default: Option<bool>,
+    #[serde(default)]
+    pub watchlist: Vec<WatchlistEntry>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<i64>,
+}
+
+fn default_timezone() -> String {
+    "UTC".to_string()
+}
+
+fn default_quote_currency() -> String {
+    "USDT".to_string()
+}
+
+fn default_dust_threshold_usd() -> f64 {
+    crate::services::balance_service::DEFAULT_DUST_THRESHOLD_USD
+}
+
+impl Default for UserSettings {
+    fn default() -> Self {
+        Self {
+            timezone: default_timezone(),
+            default_quote_currency: default_quote_currency(),
+            notification_webhook: None,
+            dust_threshold_usd: default_dust_threshold_usd(),
+            max_total_exposure_usd: None,
+            paper_trading_default: None,
+            watchlist: vec![],
+            updated_at: None,
+        }
+    }
+}
+
+/// Fuso do usuário: offset fixo ou zona IANA (segue horário de verão)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UserTimezone {
+    Fixed(FixedOffset),
+    Named(Tz),
+}
+
+impl UserSettings {
+    /// Fuso configurado (UTC se inválido — o valor já é validado na escrita)
+    pub fn user_timezone(&self) -> UserTimezone {
+        parse_timezone(&self.timezone).unwrap_or_else(|_| UserTimezone::Fixed(FixedOffset::east_opt(0).unwrap()))
+    }
+
+    /// Data local (YYYY-MM-DD) no fuso do usuário
+    pub fn local_date(&self, now: DateTime<Utc>) -> String {
+        match self.user_timezone() {
+            UserTimezone::Fixed(offset) => now.with_timezone(&offset).format("%Y-%m-%d").to_string(),
+            UserTimezone::Named(tz) => now.with_timezone(&tz).format("%Y-%m-%d").to_string(),
+        }
+    }
+}
+
+/// Aceita "UTC"/"Z", offset fixo "+HH:MM", "-HHMM", "UTC-03:00" ou nome IANA
+/// ("America/Sao_Paulo", "Europe/Lisbon")
+pub fn parse_timezone(timezone: &str) -> Result<UserTimezone, String> {
+    let invalid = || format!(
+        "Invalid timezone '{}'. Use \"UTC\", an offset like \"-03:00\" or an IANA name like \"America/Sao_Paulo\"",
+        timezone
+    );
+
+    let tz = timezone.trim();
+    if tz.contains('/') {
+        return tz.parse::<Tz>().map(UserTimezone::Named).map_err(|_| invalid());
+    }
+
+    let offset = tz.strip_prefix("UTC").or_else(|| tz.strip_prefix("GMT")).unwrap_or(tz);
+    if offset.is_empty() || offset == "Z" {
+        return Ok(UserTimezone::Fixed(FixedOffset::east_opt(0).unwrap()));
+    }
+
+    let (sign, digits) = match offset.split_at(1) {
+        ("+", rest) => (1, rest),
+        ("-", rest) => (-1, rest),
+        _ => return Err(invalid()),
+    };
+    let digits = digits.replace(':', "");
+    if digits.len() != 4 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    let hours: i32 = digits[..2].parse().map_err(|_| invalid())?;
+    let minutes: i32 = digits[2..].parse().map_err(|_| invalid())?;
+    if hours > 14 || minutes >= 60 {
+        return Err(invalid());
+    }
+
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+        .map(UserTimezone::Fixed)
+        .ok_or_else(invalid)
+}
+
+/// Valida e normaliza as preferências antes de gravar
+pub fn validate_settings(mut settings: UserSettings) -> Result<UserSettings, String> {
+    parse_timezone(&settings.timezone)?;
+    settings.timezone = settings.timezone.trim().to_string();
+
+    let quote = settings.default_quote_currency.trim().to_uppercase();
+    if !(2..=10).contains(&quote.len()) || !quote.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(format!("Invalid default_quote_currency '{}'", settings.default_quote_currency));
+    }
+    settings.default_quote_currency = quote;
+
+    settings.notification_webhook = match settings.notification_webhook.as_deref().map(str::trim) {
+        None | Some("") => None,
+        Some(url) if url.len() > MAX_WEBHOOK_LEN => {
+            return Err(format!("notification_webhook too long (max {} chars)", MAX_WEBHOOK_LEN));
+        }
+        Some(url) if !url.starts_with("https://") || url.len() <= "https://".len() => {
+            return Err("notification_webhook must be an https:// URL".to_string());
+        }
+        Some(url) => Some(url.to_string()),
+    };
+
+    if !settings.dust_threshold_usd.is_finite() || settings.dust_threshold_usd <= 0.0 {
+        return Err("dust_threshold_usd must be greater than 0".to_string());
+    }
+
+    if let Some(cap) = settings.max_total_exposure_usd {
+        if !cap.is_finite() || cap <= 0.0 {
+            return Err("max_total_exposure_usd must be greater than 0 (or null to disable)".to_string());
+        }
+    }
+
+    Ok(settings)
+}
+
+/// Preferências do usuário (defaults se ainda não salvou nenhuma)
+pub async fn get_user_settings(db: &MongoDB, user_id: &str) -> Result<UserSettings, String> {
+    let settings = db.collection::<UserSettings>(USER_SETTINGS_COLLECTION)
+        .find_one(doc! { "user_id": user_id })
+        .await
+        .map_err(|e| format!("Failed to load user settings: {}", e))?;
+    Ok(settings.unwrap_or_default())
+}
+
+/// Valida e grava (upsert) as preferências do usuário
+pub async fn update_user_settings(db: &MongoDB, user_id: &str, settings: UserSettings) -> Result<UserSettings, String> {
+    let mut settings = validate_settings(settings)?;
+    settings.updated_at = Some(Utc::now().timestamp());
+
+    let mut fields = mongodb::bson::to_document(&settings)
+        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    fields.insert("user_id", user_id);
+    fields.remove("watchlist");
+
+    // Campos opcionais ausentes precisam ser removidos do documento salvo
+    let mut unset = mongodb::bson::Document::new();
+    for field in ["notification_webhook", "max_total_exposure_usd", "paper_trading_default"] {
+        if !fields.contains_key(field) {
+            unset.insert(field, "");
+        }
+    }
+    let mut update = doc! { "$set": fields };
+    if !unset.is_empty() {
+        update.insert("$unset", unset);
+    }
+
+    db.collection::<mongodb::bson::Document>(USER_SETTINGS_COLLECTION)
+        .update_one(doc! { "user_id": user_id }, update)
+        .upsert(true)
+        .await
+        .map_err(|e| format!("Failed to save user settings: {}", e))?;
+
+    log::info!("⚙️ Settings updated for user {}", user_id);
+    Ok(settings)
+}
+
+/// Inclui (ou atualiza a exchange preferida de) um símbolo na watchlist.
+/// `is_known(symbol, exchange)` diz se o par existe no cache de mercados.
+pub fn add_to_watchlist(
+    mut watchlist: Vec<WatchlistEntry>, entry: WatchlistEntry, is_known: impl Fn(&str, Option<&str>) -> bool,
+) -> Result<Vec<WatchlistEntry>, String> {
+    let symbol = entry.symbol.trim().to_uppercase();
+    let exchange = entry.exchange.map(|e| e.trim().to_lowercase()).filter(|e| !e.is_empty());
+    if symbol.is_empty() {
+        return Err("symbol is required".to_string());
+    }
+    if !is_known(&symbol, exchange.as_deref()) {
+        return Err(match exchange {
+            Some(ex) => format!("Unknown symbol '{}' on {}", symbol, ex),
+            None => format!("Unknown symbol '{}'", symbol),
+        });
+    }
+
+    if let Some(existing) = watchlist.iter_mut().find(|e| e.symbol == symbol) {
+        existing.exchange = exchange;
+        return Ok(watchlist);
+    }
+    if watchlist.len() >= MAX_WATCHLIST_SYMBOLS {
+        return Err(format!("Watchlist is full (max {} symbols)", MAX_WATCHLIST_SYMBOLS));
+    }
+    watchlist.push(WatchlistEntry { symbol, exchange });
+    Ok(watchlist)
+}
+
+/// Remove o símbolo; retorna false se ele não estava na lista
+pub fn remove_from_watchlist(watchlist: &mut Vec<WatchlistEntry>, symbol: &str) -> bool {
+    let symbol = symbol.trim().to_uppercase();
+    let before = watchlist.len();
+    watchlist.retain(|e| e.symbol != symbol);
+    watchlist.len() != before
+}
+
+/// Símbolos da watchlist para uma exchange: os presos a ela e os sem preferência
+pub fn watchlist_symbols_for(watchlist: &[WatchlistEntry], ccxt_id: &str) -> Vec<String> {
+    watchlist.iter()
+        .filter(|e| e.exchange.as_deref().is_none_or(|ex| ex.eq_ignore_ascii_case(ccxt_id)))
+        .map(|e| e.symbol.clone())
+        .collect()
+}
+
+async fn save_watchlist(db: &MongoDB, user_id: &str, watchlist: &[WatchlistEntry]) -> Result<(), String> {
+    let watchlist = mongodb::bson::to_bson(watchlist)
+        .map_err(|e| format!("Failed to serialize watchlist: {}", e))?;
+    db.collection::<mongodb::bson::Document>(USER_SETTINGS_COLLECTION)
+        .update_one(
+            doc! { "user_id": user_id },
+            doc! { "$set": { "watchlist": watchlist, "updated_at": Utc::now().timestamp() } },
+        )
+        .upsert(true)
+        .await
+        .map_err(|e| format!("Failed to save watchlist: {}", e))?;
+    Ok(())
+}
+
+/// Valida o símbolo contra o cache de mercados e grava na watchlist do usuário.
+/// Retorna também a idade do cache usado na validação.
+pub async fn add_watchlist_symbol(
+    db: &MongoDB, user_id: &str, entry: WatchlistEntry,
+) -> Result<(Vec<WatchlistEntry>, MarketsCacheStatus), String> {
+    let exchange = entry.exchange.as_deref().map(|e| e.trim().to_lowercase()).filter(|e| !e.is_empty());
+    let (known, markets) = crate::services::token_service::cached_market_pairs(db, exchange.as_deref()).await?;
+    let current = get_user_settings(db, user_id).await?.watchlist;
+
+    let watchlist = add_to_watchlist(current, entry, |symbol, _| known.contains(symbol))?;
+    save_watchlist(db, user_id, &watchlist).await?;
+    Ok((watchlist, markets))
+}
+
+/// Remove o símbolo da watchlist; Ok(None) se ele não estava na lista
+pub async fn remove_watchlist_symbol(db: &MongoDB, user_id: &str, symbol: &str) -> Result<Option<Vec<WatchlistEntry>>, String> {
+    let mut watchlist = get_user_settings(db, user_id).await?.watchlist;
+    if !remove_from_watchlist(&mut watchlist, symbol) {
+        return Ok(None);
+    }
+    save_watchlist(db, user_id, &watchlist).await?;
+    Ok(Some(watchlist))
+}
+
+// ==================== WEBHOOK DE NOTIFICAÇÕES ====================
+
+/// Corpo do POST enviado ao webhook com os sinais de um tick
+pub fn webhook_payload(
+    user_id: &str, strategy_id: &str, symbol: &str, signals: &[crate::models::strategy::StrategySignal],
+) -> serde_json::Value {
+    serde_json::json!({
+        "event": "strategy_signals",
+        "user_id": user_id,
+        "strategy_id": strategy_id,
+        "symbol": symbol,
+        "signals": signals,
+        "sent_at": Utc::now().timestamp(),
+    })
+}
+
+/// Envia os sinais ao `notification_webhook` do usuário (se houver) em background.
+/// Falha no webhook só gera log — nunca afeta o tick.
+pub fn deliver_signals_to_webhook(
+    db: &MongoDB, user_id: &str, strategy_id: &str, symbol: &str,
+    signals: Vec<crate::models::strategy::StrategySignal>,
+) {
+    if signals.is_empty() {
+        return;
+    }
+    let db = db.clone();
+    let user_id = user_id.to_string();
+    let payload = webhook_payload(&user_id, strategy_id, symbol, &signals);
+    tokio::spawn(async move {
+        let url = match get_user_settings(&db, &user_id).await {
+            Ok(settings) => match settings.notification_webhook {
+                Some(url) => url,
+                None => return,
+            },
+            Err(e) => {
+                log::warn!("⚠️ Could not load settings for webhook delivery (user {}): {}", user_id, e);
+                return;
+            }
+        };
+        match WEBHOOK_CLIENT.post(&url).json(&payload).send().await {
+            Ok(resp) if resp.status().is_success() => {
+                log::debug!("📨 Webhook delivered {} signal(s) for user {}", signals.len(), user_id);
+            }
+            Ok(resp) => log::warn!("⚠️ Notification webhook for user {} returned HTTP {}", user_id, resp.status()),
+            Err(e) => log::warn!("⚠️ Notification webhook for user {} failed: {}", user_id, e),
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_settings_round_trip_and_validation() {
+        let settings = validate_settings(UserSettings {
+            timezone: "UTC-03:00".into(),
+            default_quote_currency: "brl".into(),
+            notification_webhook: Some(" https://hooks.example.com/t ".into()),
+            dust_threshold_usd: 5.0,
+            max_total_exposure_usd: Some(2_000.0),
+            paper_trading_default: Some(true),
+            watchlist: vec![],
+            updated_at: Some(1_700_000_000),
+        }).unwrap();
+        assert_eq!(settings.default_quote_currency, "BRL");
+        assert_eq!(settings.notification_webhook.as_deref(), Some("https://hooks.example.com/t"));
+
+        let stored = mongodb::bson::to_document(&settings).unwrap();
+        let loaded: UserSettings = mongodb::bson::from_document(stored).unwrap();
+        assert_eq!(loaded, settings);
+
+        // 01:00 UTC ainda é o dia anterior em UTC-3
+        let now = Utc.with_ymd_and_hms(2024, 3, 10, 1, 0, 0).unwrap();
+        assert_eq!(loaded.local_date(now), "2024-03-09");
+        assert_eq!(UserSettings::default().local_date(now), "2024-03-10");
+
+        // Zona IANA segue o horário de verão: 03:30 UTC em julho ainda é dia anterior em Nova York (UTC-4)
+        let new_york = validate_settings(UserSettings { timezone: " America/New_York ".into(), ..UserSettings::default() }).unwrap();
+        assert_eq!(new_york.timezone, "America/New_York");
+        assert_eq!(new_york.local_date(Utc.with_ymd_and_hms(2024, 7, 10, 3, 30, 0).unwrap()), "2024-07-09");
+        assert_eq!(new_york.local_date(Utc.with_ymd_and_hms(2024, 1, 10, 4, 30, 0).unwrap()), "2024-01-09");
+        assert_eq!(new_york.local_date(Utc.with_ymd_and_hms(2024, 1, 10, 5, 30, 0).unwrap()), "2024-01-10");
+
+        // Documento vazio → defaults
+        let empty: UserSettings = mongodb::bson::from_document(doc! { "user_id": "u1" }).unwrap();
+        assert_eq!(empty, UserSettings::default());
+
+        let invalid = |f: fn(&mut UserSettings)| {
+            let mut s = UserSettings::default();
+            f(&mut s);
+            validate_settings(s).is_err()
+        };
+        assert!(invalid(|s| s.timezone = "America/Nowhere".into()));
+        assert!(invalid(|s| s.timezone = "+25:00".into()));
+        assert!(invalid(|s| s.default_quote_currency = "US D".into()));
+        assert!(invalid(|s| s.notification_webhook = Some("http://insecure.example.com".into())));
+        assert!(invalid(|s| s.dust_threshold_usd = 0.0));
+        assert!(invalid(|s| s.max_total_exposure_usd = Some(-1.0)));
+    }
+
+    #[test]
+    fn test_webhook_payload_carries_signals() {
+        use crate::models::strategy::{SignalType, StrategySignal};
+        let signals = vec![StrategySignal {
+            signal_type: SignalType::StopLoss,
+            price: 61_000.0,
+            message: "Stop loss hit".into(),
+            acted: true,
+            price_change_percent: -5.0,
+            created_at: 1_700_000_000,
+        }];
+        let payload = webhook_payload("u1", "s1", "BTC/USDT", &signals);
+        assert_eq!(payload["event"], "strategy_signals");
+        assert_eq!(payload["strategy_id"], "s1");
+        assert_eq!(payload["symbol"], "BTC/USDT");
+        assert_eq!(payload["signals"].as_array().unwrap().len(), 1);
+        assert_eq!(payload["signals"][0]["price"], 61_000.0);
+        assert_eq!(payload["signals"][0]["acted"], true);
+    }
+
+    #[test]
+    fn test_watchlist_add_remove_and_unknown_symbol() {
+        let markets = |symbol: &str, exchange: Option<&str>| match exchange {
+            Some("kucoin") => symbol == "KCS/USDT",
+            _ => ["BTC/USDT", "ETH/USDT", "KCS/USDT"].contains(&symbol),
+        };
+        let entry = |symbol: &str, exchange: Option<&str>| WatchlistEntry {
+            symbol: symbol.into(), exchange: exchange.map(Into::into),
+        };
+
+        let list = add_to_watchlist(vec![], entry(" btc/usdt ", None), markets).unwrap();
+        let list = add_to_watchlist(list, entry("KCS/USDT", Some("KuCoin")), markets).unwrap();
+        assert_eq!(list, vec![entry("BTC/USDT", None), entry("KCS/USDT", Some("kucoin"))]);
+
+        // Repetir o símbolo só atualiza a exchange preferida
+        let mut list = add_to_watchlist(list, entry("BTC/USDT", Some("binance")), markets).unwrap();
+        assert_eq!(list.len(), 2);
+        assert_eq!(watchlist_symbols_for(&list, "kucoin"), vec!["KCS/USDT"]);
+
+        // Símbolo fora do cache de mercados (ou fora da exchange escolhida) é rejeitado
+        assert!(add_to_watchlist(list.clone(), entry("DOGE/XYZ", None), markets).is_err());
+        assert!(add_to_watchlist(list.clone(), entry("ETH/USDT", Some("kucoin")), markets).is_err());
+
+        assert!(remove_from_watchlist(&mut list, "btc/usdt"));
+        assert!(!remove_from_watchlist(&mut list, "BTC/USDT"));
+        assert_eq!(list, vec![entry("KCS/USDT", Some("kucoin"))]);
+    }
+}
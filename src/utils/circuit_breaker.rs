@@ -0,0 +1,69 @@
+//! 🔌 Circuit breaker por exchange para o monitor de estratégias
+//!
+//! Quando uma exchange cai (manutenção, timeouts em série), cada ciclo do
+//! monitor tentaria o tick de todas as estratégias dela — todas falham e ainda
+//! consomem o rate limit. Falhas transitórias consecutivas são contadas por
+//! exchange; ao atingir `EXCHANGE_BREAKER_THRESHOLD` (padrão 5) o breaker abre
+//! por `EXCHANGE_BREAKER_COOLDOWN_SECS` (padrão 120) e as estratégias da
+//! exchange são puladas. Passado o cooldown o próximo tick passa (meio-aberto):
+//! sucesso fecha o breaker, nova falha transitória reabre na hora.
+
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+const DEFAULT_BREAKER_THRESHOLD: u32 = 5;
+const DEFAULT_BREAKER_COOLDOWN_SECS: i64 = 120;
+
+lazy_static! {
+    /// Breakers globais usados por `strategy_service::process_active_strategies`
+    pub static ref EXCHANGE_BREAKER: ExchangeBreaker = ExchangeBreaker::new(
+        env_or("EXCHANGE_BREAKER_THRESHOLD", DEFAULT_BREAKER_THRESHOLD as i64).max(1) as u32,
+        env_or("EXCHANGE_BREAKER_COOLDOWN_SECS", DEFAULT_BREAKER_COOLDOWN_SECS).max(0),
+    );
+}
+
+fn env_or(key: &str, default: i64) -> i64 {
+    std::env::var(key).ok().and_then(|v| v.parse::<i64>().ok()).unwrap_or(default)
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct BreakerState {
+    consecutive_failures: u32,
+    open_until: Option<i64>,
+}
+
+pub struct ExchangeBreaker {
+    threshold: u32,
+    cooldown_secs: i64,
+    states: Mutex<HashMap<String, BreakerState>>,
+}
+
+impl ExchangeBreaker {
+    pub fn new(threshold: u32, cooldown_secs: i64) -> Self {
+        Self { threshold: threshold.max(1), cooldown_secs, states: Mutex::new(HashMap::new()) }
+    }
+
+    /// true enquanto o cooldown da exchange não terminou
+    pub fn is_open(&self, exchange_id: &str, now: i64) -> bool {
+        let states = self.states.lock().unwrap_or_else(|e| e.into_inner());
+        states.get(exchange_id).and_then(|s| s.open_until).is_some_and(|until| now < until)
+    }
+
+    /// Falha transitória; retorna true quando o breaker (re)abriu agora
+    pub fn record_failure(&self, exchange_id: &str, now: i64) -> bool {
+        let mut states = self.states.lock().unwrap_or_else(|e| e.into_inner());
+        let state = states.entry(exchange_id.to_string()).or_default();
+        state.consecutive_failures += 1;
+        if state.consecutive_failures < self.threshold {
+            return false;
+        }
+        state.open_until = Some(now + self.cooldown_secs);
+        true
+    }
+
+    pub fn record_success(&self, exchange_id: &str) {
+        let mut states = self.states.lock().unwrap_or_else(|e| e.into_inner());
+        states.remove(exchange_id);
+    }
+}
@@ -0,0 +1,201 @@
+//! 🕰️ Detecção de clock skew entre o host e as exchanges
+//!
+//! Erros de nonce/timestamp (principalmente na MEXC) costumam vir de relógio
+//! dessincronizado. Ao criar um `CCXTClient`, se a última medição daquela
+//! exchange tiver mais de `CLOCK_SKEW_CHECK_INTERVAL_SECS` (padrão 600), o
+//! client dispara um `fetch_time` em segundo plano e registra aqui o skew
+//! (`servidor - local`, em ms, usando o ponto médio da request para descontar
+//! a latência). Entre medições os clients só leem o valor guardado por exchange.
+//!
+//! Acima de `CLOCK_SKEW_THRESHOLD_MS` (padrão 1000) é logado um aviso e, se
+//! `CLOCK_SKEW_ADJUST` não for "false", o offset é aplicado no CCXT via
+//! `options.timeDifference` (que o CCXT subtrai do relógio local ao assinar).
+
+use lazy_static::lazy_static;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+const DEFAULT_THRESHOLD_MS: i64 = 1000;
+const DEFAULT_CHECK_INTERVAL_SECS: i64 = 600;
+
+lazy_static! {
+    /// Medições globais, lidas pelo `CCXTClient` e pelo health de admin
+    pub static ref CLOCK_SKEW: ClockSkewRegistry = ClockSkewRegistry::new(
+        env_i64("CLOCK_SKEW_THRESHOLD_MS", DEFAULT_THRESHOLD_MS),
+        env_i64("CLOCK_SKEW_CHECK_INTERVAL_SECS", DEFAULT_CHECK_INTERVAL_SECS) * 1000,
+        std::env::var("CLOCK_SKEW_ADJUST").map(|v| v.to_lowercase() != "false").unwrap_or(true),
+    );
+}
+
+fn env_i64(key: &str, default: i64) -> i64 {
+    std::env::var(key)
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok())
+        .filter(|v| *v >= 0)
+        .unwrap_or(default)
+}
+
+/// Skew (ms) = hora da exchange - hora local no meio da request
+pub fn compute_skew_ms(server_time_ms: i64, sent_at_ms: i64, received_at_ms: i64) -> i64 {
+    let local_mid = sent_at_ms + (received_at_ms - sent_at_ms) / 2;
+    server_time_ms - local_mid
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ClockSkew {
+    pub exchange: String,
+    /// None quando a medição falhou ou a exchange não tem `fetchTime`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skew_ms: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub round_trip_ms: Option<i64>,
+    /// `options.timeDifference` aplicado nos clients (0 = sem ajuste)
+    pub time_difference_ms: i64,
+    pub exceeds_threshold: bool,
+    pub checked_at: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+pub struct ClockSkewRegistry {
+    threshold_ms: i64,
+    check_interval_ms: i64,
+    adjust: bool,
+    entries: Mutex<HashMap<String, ClockSkew>>,
+}
+
+impl ClockSkewRegistry {
+    pub fn new(threshold_ms: i64, check_interval_ms: i64, adjust: bool) -> Self {
+        Self { threshold_ms, check_interval_ms, adjust, entries: Mutex::new(HashMap::new()) }
+    }
+
+    /// Reserva a próxima medição da exchange se a última estiver vencida.
+    /// Marca `checked_at` já aqui para que clients concorrentes não meçam juntos.
+    pub fn claim_check(&self, exchange: &str, now_ms: i64) -> bool {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let key = exchange.to_lowercase();
+        match entries.get_mut(&key) {
+            Some(entry) if now_ms - entry.checked_at < self.check_interval_ms => false,
+            Some(entry) => {
+                entry.checked_at = now_ms;
+                true
+            }
+            None => {
+                entries.insert(key.clone(), ClockSkew {
+                    exchange: key,
+                    skew_ms: None,
+                    round_trip_ms: None,
+                    time_difference_ms: 0,
+                    exceeds_threshold: false,
+                    checked_at: now_ms,
+                    error: None,
+                });
+                true
+            }
+        }
+    }
+
+    /// Registra uma medição e devolve o `timeDifference` a aplicar no CCXT
+    pub fn record(&self, exchange: &str, server_time_ms: i64, sent_at_ms: i64, received_at_ms: i64) -> ClockSkew {
+        let skew_ms = compute_skew_ms(server_time_ms, sent_at_ms, received_at_ms);
+        let exceeds_threshold = skew_ms.abs() > self.threshold_ms;
+        // O CCXT usa `milliseconds() - timeDifference`, então o offset é local - servidor
+        let time_difference_ms = if exceeds_threshold && self.adjust { -skew_ms } else { 0 };
+        if exceeds_threshold {
+            log::warn!(
+                "🕰️ [{}] Clock skew of {}ms vs exchange (threshold {}ms){}",
+                exchange, skew_ms, self.threshold_ms,
+                if self.adjust { ", applying timeDifference" } else { "" }
+            );
+        } else {
+            log::debug!("🕰️ [{}] Clock skew {}ms", exchange, skew_ms);
+        }
+
+        let entry = ClockSkew {
+            exchange: exchange.to_lowercase(),
+            skew_ms: Some(skew_ms),
+            round_trip_ms: Some(received_at_ms - sent_at_ms),
+            time_difference_ms,
+            exceeds_threshold,
+            checked_at: received_at_ms,
+            error: None,
+        };
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.insert(entry.exchange.clone(), entry.clone());
+        entry
+    }
+
+    /// Registra falha na medição (mantém o último `timeDifference` conhecido)
+    pub fn record_error(&self, exchange: &str, error: String, now_ms: i64) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let key = exchange.to_lowercase();
+        let entry = entries.entry(key.clone()).or_insert_with(|| ClockSkew {
+            exchange: key,
+            skew_ms: None,
+            round_trip_ms: None,
+            time_difference_ms: 0,
+            exceeds_threshold: false,
+            checked_at: now_ms,
+            error: None,
+        });
+        entry.checked_at = now_ms;
+        entry.error = Some(error);
+    }
+
+    /// `timeDifference` vigente para a exchange (0 = sem ajuste)
+    pub fn time_difference(&self, exchange: &str) -> i64 {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.get(&exchange.to_lowercase()).map(|e| e.time_difference_ms).unwrap_or(0)
+    }
+
+    pub fn snapshot(&self) -> Vec<ClockSkew> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let mut list: Vec<ClockSkew> = entries.values().cloned().collect();
+        list.sort_by(|a, b| a.exchange.cmp(&b.exchange));
+        list
+    }
+
+    pub fn threshold_ms(&self) -> i64 {
+        self.threshold_ms
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_skew_uses_request_midpoint_and_threshold() {
+        // Request saiu em t=1000 e voltou em t=1200: meio = 1100
+        assert_eq!(compute_skew_ms(1100, 1000, 1200), 0);
+        assert_eq!(compute_skew_ms(3600, 1000, 1200), 2500);
+        assert_eq!(compute_skew_ms(100, 1000, 1200), -1000);
+
+        let registry = ClockSkewRegistry::new(1000, 60_000, true);
+        let ahead = registry.record("MEXC", 3600, 1000, 1200);
+        assert_eq!((ahead.skew_ms, ahead.round_trip_ms), (Some(2500), Some(200)));
+        assert!(ahead.exceeds_threshold);
+        assert_eq!(registry.time_difference("mexc"), -2500);
+
+        // Dentro do limite: sem ajuste
+        let ok = registry.record("binance", 1500, 1000, 1200);
+        assert!(!ok.exceeds_threshold);
+        assert_eq!(registry.time_difference("binance"), 0);
+
+        let no_adjust = ClockSkewRegistry::new(1000, 60_000, false);
+        assert!(no_adjust.record("mexc", 3600, 1000, 1200).exceeds_threshold);
+        assert_eq!(no_adjust.time_difference("mexc"), 0);
+    }
+
+    #[test]
+    fn test_claim_check_respects_interval() {
+        let registry = ClockSkewRegistry::new(1000, 60_000, true);
+        assert!(registry.claim_check("mexc", 0));
+        assert!(!registry.claim_check("mexc", 30_000));
+        assert!(registry.claim_check("mexc", 60_000));
+        registry.record_error("mexc", "NotSupported".to_string(), 60_000);
+        assert!(!registry.claim_check("MEXC", 90_000));
+        assert_eq!(registry.snapshot()[0].error.as_deref(), Some("NotSupported"));
+    }
+}
@@ -71,3 +71,67 @@ pub fn encrypt_fernet_via_python(plaintext: &str, key: &str) -> Result<String, S
         Ok(encrypted_str)
     })
 }
+
+/// Descriptografa vários tokens Fernet numa única aquisição do GIL.
+///
+/// O caminho Python serializa no GIL de qualquer forma; N `spawn_blocking`
+/// com `decrypt_fernet_via_python` só disputam o lock e repetem import +
+/// instância Fernet para cada token. Aqui o lote inteiro usa uma instância.
+/// Erro externo: falha de setup (import/chave). Erros por token vêm no vetor.
+pub fn decrypt_fernet_batch_via_python(encrypted_data: &[&str], key: &str) -> Result<Vec<Result<String, String>>, String> {
+    Python::with_gil(|py| {
+        let fernet = py
+            .import("cryptography.fernet")
+            .map_err(|e| format!("Failed to import cryptography.fernet: {}", e))?
+            .getattr("Fernet")
+            .map_err(|e| format!("Failed to get Fernet class: {}", e))?
+            .call1((key,))
+            .map_err(|e| format!("Failed to create Fernet instance: {}", e))?;
+
+        Ok(encrypted_data.iter()
+            .map(|token| {
+                fernet
+                    .call_method1("decrypt", (token.as_bytes(),))
+                    .map_err(|e| format!("Failed to decrypt: {}", e))?
+                    .call_method0("decode")
+                    .map_err(|e| format!("Failed to decode: {}", e))?
+                    .extract::<String>()
+                    .map_err(|e| format!("Failed to extract string: {}", e))
+            })
+            .collect())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_KEY: &str = "kkT9-JXzrRHjz0VFcBoCu5SeKDY71wEj4zmRnDfXHPI=";
+
+    #[test]
+    fn test_batch_decrypts_all_credentials() {
+        let plaintexts: Vec<String> = (0..30).map(|i| format!("api-secret-{}", i)).collect();
+        let encrypted: Vec<String> = plaintexts.iter()
+            .map(|p| encrypt_fernet_via_python(p, TEST_KEY).unwrap())
+            .collect();
+        let mut tokens: Vec<&str> = encrypted.iter().map(String::as_str).collect();
+        tokens.push("not-a-fernet-token");
+
+        let start = std::time::Instant::now();
+        let batch = decrypt_fernet_batch_via_python(&tokens, TEST_KEY).unwrap();
+        let batch_elapsed = start.elapsed();
+
+        let start = std::time::Instant::now();
+        let one_by_one: Vec<_> = tokens.iter().map(|t| decrypt_fernet_via_python(t, TEST_KEY)).collect();
+        log::debug!("batch {:?} vs one-by-one {:?}", batch_elapsed, start.elapsed());
+
+        assert_eq!(batch.len(), tokens.len());
+        for (i, plaintext) in plaintexts.iter().enumerate() {
+            assert_eq!(batch[i].as_deref(), Ok(plaintext.as_str()));
+            assert_eq!(batch[i], one_by_one[i]);
+        }
+        // Token inválido falha sozinho, sem derrubar o lote
+        assert!(batch.last().unwrap().is_err());
+        assert!(decrypt_fernet_batch_via_python(&tokens, "bad-key").is_err());
+    }
+}
@@ -0,0 +1,81 @@
+//! 💱 Formatação de valores conforme a moeda (símbolo e separadores)
+//!
+//! Só afeta os campos já formatados como texto (ex.: `DailyPnLResponse`);
+//! os campos numéricos crus continuam em f64. Moedas sem convenção
+//! conhecida usam o padrão USD com o código na frente (`ARS 1,234.56`).
+
+/// Símbolo e separadores usados para uma moeda
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct NumberStyle {
+    symbol: &'static str,
+    /// Símbolo depois do número (ex.: "1.234,56 €")
+    suffix: bool,
+    thousands: char,
+    decimal: char,
+}
+
+const US_STYLE: NumberStyle = NumberStyle { symbol: "$", suffix: false, thousands: ',', decimal: '.' };
+
+fn style_for(currency: &str) -> Option<NumberStyle> {
+    match currency.to_uppercase().as_str() {
+        "USD" | "USDT" | "USDC" | "BUSD" | "FDUSD" | "TUSD" | "DAI" => Some(US_STYLE),
+        "BRL" => Some(NumberStyle { symbol: "R$ ", suffix: false, thousands: '.', decimal: ',' }),
+        "EUR" => Some(NumberStyle { symbol: " €", suffix: true, thousands: '.', decimal: ',' }),
+        "GBP" => Some(NumberStyle { symbol: "£", suffix: false, thousands: ',', decimal: '.' }),
+        _ => None,
+    }
+}
+
+/// Número com `decimals` casas e os separadores da moeda, sem símbolo ("1.234,56" em BRL)
+pub fn format_number(value: f64, decimals: usize, currency: &str) -> String {
+    let style = style_for(currency).unwrap_or(US_STYLE);
+    let fixed = format!("{:.*}", decimals, value.abs());
+    let (int_part, frac_part) = fixed.split_once('.').unwrap_or((&fixed, ""));
+
+    let mut grouped = String::with_capacity(fixed.len() + int_part.len() / 3);
+    for (i, digit) in int_part.chars().enumerate() {
+        if i > 0 && (int_part.len() - i) % 3 == 0 {
+            grouped.push(style.thousands);
+        }
+        grouped.push(digit);
+    }
+    if !frac_part.is_empty() {
+        grouped.push(style.decimal);
+        grouped.push_str(frac_part);
+    }
+
+    // "-0,00" vira "0,00"
+    let negative = value < 0.0 && fixed.chars().any(|c| c.is_ascii_digit() && c != '0');
+    if negative { format!("-{}", grouped) } else { grouped }
+}
+
+/// Valor monetário com 2 casas: `$1,234.56`, `R$ 1.234,56`, `-£12.00`, `ARS 1,234.56`
+pub fn format_currency(value: f64, currency: &str) -> String {
+    let number = format_number(value, 2, currency);
+    let (sign, digits) = match number.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", number.as_str()),
+    };
+    match style_for(currency) {
+        Some(style) if style.suffix => format!("{}{}{}", sign, digits, style.symbol),
+        Some(style) => format!("{}{}{}", sign, style.symbol, digits),
+        None => format!("{}{} {}", sign, currency.to_uppercase(), digits),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_usd_and_brl_formatting_of_same_value() {
+        assert_eq!(format_currency(1234.56, "USD"), "$1,234.56");
+        assert_eq!(format_currency(1234.56, "BRL"), "R$ 1.234,56");
+        assert_eq!(format_currency(-1234567.891, "usd"), "-$1,234,567.89");
+        assert_eq!(format_currency(-1234567.891, "brl"), "-R$ 1.234.567,89");
+        assert_eq!(format_currency(-0.001, "BRL"), "R$ 0,00");
+        assert_eq!(format_currency(999.5, "EUR"), "999,50 €");
+        assert_eq!(format_currency(1000.0, "ARS"), "ARS 1,000.00");
+        assert_eq!(format_number(12.3456, 2, "BRL"), "12,35");
+    }
+}
@@ -0,0 +1,301 @@
+//! 🧮 Avaliador de expressões para condições de entrada das estratégias
+//!
+//! Suporta apenas números, um conjunto fixo de variáveis (`ALLOWED_VARIABLES`),
+//! aritmética (`+ - * /`), comparações (`< <= > >= == !=`), lógica
+//! (`and`/`&&`, `or`/`||`, `not`/`!`) e parênteses. Não há chamadas de função
+//! nem acesso a nada além das variáveis fornecidas — identificadores
+//! desconhecidos são rejeitados já no parse.
+//!
+//! Exemplo: `price < 0.95 * high_24h and rsi < 30`
+
+use std::collections::HashMap;
+
+/// Variáveis disponíveis nas expressões
+pub const ALLOWED_VARIABLES: &[&str] = &["price", "high_24h", "low_24h", "sma_fast", "rsi"];
+
+const MAX_EXPRESSION_LEN: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOp {
+    Add, Sub, Mul, Div,
+    Lt, Le, Gt, Ge, Eq, Ne,
+    And, Or,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Number(f64),
+    Variable(String),
+    Neg(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Op(&'static str),
+    LParen,
+    RParen,
+}
+
+fn tokenize(src: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = src.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() || c == '.' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            let value = text.parse::<f64>().map_err(|_| format!("Invalid number '{}'", text))?;
+            tokens.push(Token::Number(value));
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect();
+            match word.to_lowercase().as_str() {
+                "and" => tokens.push(Token::Op("&&")),
+                "or" => tokens.push(Token::Op("||")),
+                "not" => tokens.push(Token::Op("!")),
+                _ => tokens.push(Token::Ident(word)),
+            }
+        } else if c == '(' {
+            tokens.push(Token::LParen);
+            i += 1;
+        } else if c == ')' {
+            tokens.push(Token::RParen);
+            i += 1;
+        } else {
+            const OPERATORS: &[&str] = &[
+                "<=", ">=", "==", "!=", "&&", "||",
+                "<", ">", "+", "-", "*", "/", "!",
+            ];
+            let rest: String = chars[i..(i + 2).min(chars.len())].iter().collect();
+            let op = OPERATORS.iter()
+                .find(|op| rest.starts_with(*op))
+                .ok_or_else(|| format!("Unexpected character '{}'", c))?;
+            i += op.len();
+            tokens.push(Token::Op(op));
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek_op(&self) -> Option<&'static str> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Op(op)) => Some(op),
+            _ => None,
+        }
+    }
+
+    fn binary<F>(&mut self, ops: &[(&str, BinaryOp)], next: F) -> Result<Expr, String>
+    where
+        F: Fn(&mut Self) -> Result<Expr, String>,
+    {
+        let mut left = next(self)?;
+        while let Some(op) = self.peek_op() {
+            let Some((_, bin)) = ops.iter().find(|(o, _)| *o == op) else { break };
+            self.pos += 1;
+            let right = next(self)?;
+            left = Expr::Binary(*bin, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        self.binary(&[("||", BinaryOp::Or)], Self::and)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        self.binary(&[("&&", BinaryOp::And)], Self::comparison)
+    }
+
+    fn comparison(&mut self) -> Result<Expr, String> {
+        self.binary(&[
+            ("<", BinaryOp::Lt), ("<=", BinaryOp::Le), (">", BinaryOp::Gt),
+            (">=", BinaryOp::Ge), ("==", BinaryOp::Eq), ("!=", BinaryOp::Ne),
+        ], Self::sum)
+    }
+
+    fn sum(&mut self) -> Result<Expr, String> {
+        self.binary(&[("+", BinaryOp::Add), ("-", BinaryOp::Sub)], Self::term)
+    }
+
+    fn term(&mut self) -> Result<Expr, String> {
+        self.binary(&[("*", BinaryOp::Mul), ("/", BinaryOp::Div)], Self::unary)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        match self.peek_op() {
+            Some("-") => { self.pos += 1; Ok(Expr::Neg(Box::new(self.unary()?))) }
+            Some("!") => { self.pos += 1; Ok(Expr::Not(Box::new(self.unary()?))) }
+            _ => self.primary(),
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        let token = self.tokens.get(self.pos).cloned()
+            .ok_or_else(|| "Unexpected end of expression".to_string())?;
+        self.pos += 1;
+        match token {
+            Token::Number(v) => Ok(Expr::Number(v)),
+            Token::Ident(name) => {
+                if !ALLOWED_VARIABLES.contains(&name.as_str()) {
+                    return Err(format!(
+                        "Unknown identifier '{}'. Allowed: {}", name, ALLOWED_VARIABLES.join(", ")
+                    ));
+                }
+                Ok(Expr::Variable(name))
+            }
+            Token::LParen => {
+                let inner = self.or()?;
+                match self.tokens.get(self.pos) {
+                    Some(Token::RParen) => { self.pos += 1; Ok(inner) }
+                    _ => Err("Missing closing parenthesis".to_string()),
+                }
+            }
+            Token::RParen => Err("Unexpected ')'".to_string()),
+            Token::Op(op) => Err(format!("Unexpected operator '{}'", op)),
+        }
+    }
+}
+
+/// Faz o parse da expressão, rejeitando sintaxe inválida e identificadores fora da whitelist
+pub fn parse(src: &str) -> Result<Expr, String> {
+    if src.trim().is_empty() {
+        return Err("Expression is empty".to_string());
+    }
+    if src.len() > MAX_EXPRESSION_LEN {
+        return Err(format!("Expression too long (max {} chars)", MAX_EXPRESSION_LEN));
+    }
+
+    let mut parser = Parser { tokens: tokenize(src)?, pos: 0 };
+    let expr = parser.or()?;
+    if parser.pos != parser.tokens.len() {
+        return Err("Unexpected trailing tokens".to_string());
+    }
+    Ok(expr)
+}
+
+fn truthy(v: f64) -> bool {
+    v != 0.0
+}
+
+fn flag(b: bool) -> f64 {
+    if b { 1.0 } else { 0.0 }
+}
+
+impl Expr {
+    /// Avalia numericamente (comparações/lógica retornam 1.0 ou 0.0)
+    pub fn eval(&self, vars: &HashMap<&str, f64>) -> Result<f64, String> {
+        match self {
+            Expr::Number(v) => Ok(*v),
+            Expr::Variable(name) => vars.get(name.as_str()).copied()
+                .ok_or_else(|| format!("Variable '{}' is not available", name)),
+            Expr::Neg(inner) => Ok(-inner.eval(vars)?),
+            Expr::Not(inner) => Ok(flag(!truthy(inner.eval(vars)?))),
+            Expr::Binary(op, l, r) => {
+                let a = l.eval(vars)?;
+                // Curto-circuito para lógica
+                match op {
+                    BinaryOp::And if !truthy(a) => return Ok(0.0),
+                    BinaryOp::Or if truthy(a) => return Ok(1.0),
+                    _ => {}
+                }
+                let b = r.eval(vars)?;
+                Ok(match op {
+                    BinaryOp::Add => a + b,
+                    BinaryOp::Sub => a - b,
+                    BinaryOp::Mul => a * b,
+                    BinaryOp::Div => {
+                        if b == 0.0 { return Err("Division by zero".to_string()); }
+                        a / b
+                    }
+                    BinaryOp::Lt => flag(a < b),
+                    BinaryOp::Le => flag(a <= b),
+                    BinaryOp::Gt => flag(a > b),
+                    BinaryOp::Ge => flag(a >= b),
+                    BinaryOp::Eq => flag((a - b).abs() < f64::EPSILON),
+                    BinaryOp::Ne => flag((a - b).abs() >= f64::EPSILON),
+                    BinaryOp::And | BinaryOp::Or => flag(truthy(b)),
+                })
+            }
+        }
+    }
+
+    /// Avalia como condição booleana
+    pub fn is_satisfied(&self, vars: &HashMap<&str, f64>) -> Result<bool, String> {
+        self.eval(vars).map(truthy)
+    }
+
+    /// Variáveis usadas pela expressão
+    pub fn variables(&self) -> Vec<&str> {
+        let mut out = Vec::new();
+        self.collect_variables(&mut out);
+        out
+    }
+
+    fn collect_variables<'a>(&'a self, out: &mut Vec<&'a str>) {
+        match self {
+            Expr::Number(_) => {}
+            Expr::Variable(name) => {
+                if !out.contains(&name.as_str()) {
+                    out.push(name);
+                }
+            }
+            Expr::Neg(inner) | Expr::Not(inner) => inner.collect_variables(out),
+            Expr::Binary(_, l, r) => {
+                l.collect_variables(out);
+                r.collect_variables(out);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars() -> HashMap<&'static str, f64> {
+        HashMap::from([
+            ("price", 94.0), ("high_24h", 100.0), ("low_24h", 90.0),
+            ("sma_fast", 96.0), ("rsi", 28.0),
+        ])
+    }
+
+    #[test]
+    fn test_evaluates_whitelisted_expression() {
+        let expr = parse("price < 0.95 * high_24h and (rsi < 30 || price < sma_fast)").unwrap();
+        assert!(expr.is_satisfied(&vars()).unwrap());
+
+        let expr = parse("not (price <= low_24h) && price - low_24h >= 5").unwrap();
+        assert!(!expr.is_satisfied(&vars()).unwrap());
+    }
+
+    #[test]
+    fn test_rejects_unknown_identifiers_and_bad_syntax() {
+        assert!(parse("price < balance").unwrap_err().contains("Unknown identifier"));
+        assert!(parse("__import__(os)").is_err());
+        assert!(parse("price <").is_err());
+        assert!(parse("(price < 1").is_err());
+        assert!(parse("price ; rsi").is_err());
+        assert!(parse("").is_err());
+    }
+}
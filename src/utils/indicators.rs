@@ -0,0 +1,137 @@
+//! 📈 Indicadores técnicos calculados a partir de candles OHLCV (e do livro de ofertas)
+
+use crate::models::{Candle, OrderBook};
+
+/// Mínimo de níveis em cada lado para o desequilíbrio do book ser confiável
+pub const MIN_ORDER_BOOK_LEVELS: usize = 3;
+
+/// True Range de um candle, dado o fechamento anterior
+pub fn true_range(candle: &Candle, prev_close: Option<f64>) -> f64 {
+    let range = candle.high - candle.low;
+    match prev_close {
+        Some(pc) => range
+            .max((candle.high - pc).abs())
+            .max((candle.low - pc).abs()),
+        None => range,
+    }
+}
+
+/// ATR (Average True Range) com suavização de Wilder.
+/// Retorna `None` se não houver candles suficientes (`period + 1`).
+pub fn atr(candles: &[Candle], period: usize) -> Option<f64> {
+    if period == 0 || candles.len() < period + 1 {
+        return None;
+    }
+
+    let trs: Vec<f64> = candles.windows(2)
+        .map(|w| true_range(&w[1], Some(w[0].close)))
+        .collect();
+
+    let mut value = trs[..period].iter().sum::<f64>() / period as f64;
+    for tr in &trs[period..] {
+        value = (value * (period as f64 - 1.0) + tr) / period as f64;
+    }
+    Some(value)
+}
+
+/// ATR como percentual do último fechamento
+pub fn atr_percent(candles: &[Candle], period: usize) -> Option<f64> {
+    let last_close = candles.last()?.close;
+    if last_close <= 0.0 {
+        return None;
+    }
+    atr(candles, period).map(|v| v / last_close * 100.0)
+}
+
+/// Média móvel simples dos últimos `period` fechamentos
+pub fn sma(candles: &[Candle], period: usize) -> Option<f64> {
+    if period == 0 || candles.len() < period {
+        return None;
+    }
+    let closes = &candles[candles.len() - period..];
+    Some(closes.iter().map(|c| c.close).sum::<f64>() / period as f64)
+}
+
+/// RSI com suavização de Wilder. Precisa de `period + 1` candles.
+pub fn rsi(candles: &[Candle], period: usize) -> Option<f64> {
+    if period == 0 || candles.len() < period + 1 {
+        return None;
+    }
+
+    let changes: Vec<f64> = candles.windows(2).map(|w| w[1].close - w[0].close).collect();
+    let mut avg_gain = changes[..period].iter().filter(|c| **c > 0.0).sum::<f64>() / period as f64;
+    let mut avg_loss = changes[..period].iter().filter(|c| **c < 0.0).map(|c| -c).sum::<f64>() / period as f64;
+    for change in &changes[period..] {
+        avg_gain = (avg_gain * (period as f64 - 1.0) + change.max(0.0)) / period as f64;
+        avg_loss = (avg_loss * (period as f64 - 1.0) + (-change).max(0.0)) / period as f64;
+    }
+
+    if avg_loss == 0.0 {
+        return Some(100.0);
+    }
+    let rs = avg_gain / avg_loss;
+    Some(100.0 - 100.0 / (1.0 + rs))
+}
+
+/// Desequilíbrio do book: volume de compra / volume de venda nos `levels` melhores níveis.
+/// > 1 = compradores dominam. None se algum lado está vazio ou raso demais.
+pub fn order_book_imbalance(book: &OrderBook, levels: usize) -> Option<f64> {
+    let depth = |side: &[[f64; 2]]| -> Option<f64> {
+        let top = &side[..side.len().min(levels)];
+        if top.len() < MIN_ORDER_BOOK_LEVELS.min(levels.max(1)) {
+            return None;
+        }
+        let volume: f64 = top.iter().map(|[_, amount]| amount.max(0.0)).sum();
+        (volume > 0.0 && volume.is_finite()).then_some(volume)
+    };
+    Some(depth(&book.bids)? / depth(&book.asks)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn series(closes: &[f64], spread_pct: f64) -> Vec<Candle> {
+        closes.iter().enumerate().map(|(i, &c)| Candle {
+            timestamp: i as i64 * 3_600_000,
+            open: c,
+            high: c * (1.0 + spread_pct / 100.0),
+            low: c * (1.0 - spread_pct / 100.0),
+            close: c,
+            volume: 1.0,
+        }).collect()
+    }
+
+    #[test]
+    fn test_atr_filter_blocks_volatile_and_allows_calm() {
+        let max_atr_percent = 3.0;
+
+        // Série volátil: fechamentos alternando ±8% com pavios largos
+        let volatile_closes: Vec<f64> = (0..20).map(|i| if i % 2 == 0 { 100.0 } else { 108.0 }).collect();
+        let volatile = series(&volatile_closes, 4.0);
+        let volatile_atr = atr_percent(&volatile, 14).unwrap();
+        assert!(volatile_atr > max_atr_percent, "volatile ATR% = {}", volatile_atr);
+
+        // Série calma: variação de 0.1% por candle
+        let calm_closes: Vec<f64> = (0..20).map(|i| 100.0 + i as f64 * 0.1).collect();
+        let calm = series(&calm_closes, 0.2);
+        let calm_atr = atr_percent(&calm, 14).unwrap();
+        assert!(calm_atr <= max_atr_percent, "calm ATR% = {}", calm_atr);
+    }
+
+    #[test]
+    fn test_sma_and_rsi() {
+        let rising = series(&(0..20).map(|i| 100.0 + i as f64).collect::<Vec<_>>(), 0.5);
+        assert_eq!(sma(&rising, 3), Some(118.0));
+        assert_eq!(rsi(&rising, 14), Some(100.0));
+
+        let falling = series(&(0..20).map(|i| 100.0 - i as f64).collect::<Vec<_>>(), 0.5);
+        assert!(rsi(&falling, 14).unwrap() < 1.0);
+    }
+
+    #[test]
+    fn test_atr_requires_enough_candles() {
+        let candles = series(&[100.0, 101.0, 102.0], 1.0);
+        assert!(atr(&candles, 14).is_none());
+    }
+}
@@ -0,0 +1,219 @@
+//! 🔒 Lock distribuído leve (MongoDB) para jobs que rodam em várias instâncias
+//!
+//! Cada lock é um documento em `locks` com `_id` = chave e `expires_at`.
+//! Um índice TTL em `expires_at` limpa locks órfãos (instância que caiu
+//! no meio do job), então nenhum lock fica preso para sempre.
+//!
+//! Além do lock no banco, existe um guard em memória para evitar que a
+//! mesma instância dispute o lock consigo mesma (ex: scheduler + POST /save).
+
+use crate::database::MongoDB;
+use lazy_static::lazy_static;
+use mongodb::bson::{doc, DateTime as BsonDateTime, Document};
+use std::collections::HashSet;
+use std::future::Future;
+use std::sync::Mutex;
+
+pub const LOCKS_COLLECTION: &str = "locks";
+
+lazy_static! {
+    static ref LOCAL_LOCKS: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+
+    /// Identificador desta instância (dono dos locks que ela adquire)
+    pub static ref INSTANCE_ID: String = uuid::Uuid::new_v4().to_string();
+}
+
+/// Guard em memória — liberado automaticamente no Drop
+pub struct LocalLockGuard {
+    key: String,
+}
+
+impl Drop for LocalLockGuard {
+    fn drop(&mut self) {
+        if let Ok(mut locks) = LOCAL_LOCKS.lock() {
+            locks.remove(&self.key);
+        }
+    }
+}
+
+/// Tenta reservar a chave nesta instância. Retorna `None` se já estiver em uso.
+pub fn try_local_lock(key: &str) -> Option<LocalLockGuard> {
+    let mut locks = LOCAL_LOCKS.lock().ok()?;
+    if !locks.insert(key.to_string()) {
+        return None;
+    }
+    Some(LocalLockGuard { key: key.to_string() })
+}
+
+/// Armazenamento dos locks (MongoDB em produção, memória nos testes)
+pub trait LockStore {
+    /// Grava `owner` como dono de `key` até `expires_at_ms`, se a chave estiver
+    /// livre ou com o lease vencido em `now_ms`. `Ok(false)` se outro dono a detém.
+    fn try_claim(&self, key: &str, owner: &str, now_ms: i64, expires_at_ms: i64) -> impl Future<Output = Result<bool, String>>;
+    /// Remove o lock se ainda pertencer a `owner`
+    fn release(&self, key: &str, owner: &str) -> impl Future<Output = Result<(), String>>;
+}
+
+impl LockStore for MongoDB {
+    async fn try_claim(&self, key: &str, owner: &str, now_ms: i64, expires_at_ms: i64) -> Result<bool, String> {
+        let collection = self.collection::<Document>(LOCKS_COLLECTION);
+
+        // Só "rouba" o lock se ele já expirou; caso contrário o upsert tenta
+        // inserir um _id duplicado e o Mongo rejeita com E11000.
+        let filter = doc! {
+            "_id": key,
+            "expires_at": { "$lt": BsonDateTime::from_millis(now_ms) }
+        };
+        let update = doc! {
+            "$set": {
+                "owner": owner,
+                "expires_at": BsonDateTime::from_millis(expires_at_ms),
+                "acquired_at": BsonDateTime::from_millis(now_ms),
+            }
+        };
+
+        match collection.update_one(filter, update).upsert(true).await {
+            Ok(_) => Ok(true),
+            Err(e) if is_duplicate_key_error(&e) => Ok(false),
+            Err(e) => Err(format!("Failed to acquire lock {}: {}", key, e)),
+        }
+    }
+
+    async fn release(&self, key: &str, owner: &str) -> Result<(), String> {
+        self.collection::<Document>(LOCKS_COLLECTION)
+            .delete_one(doc! { "_id": key, "owner": owner })
+            .await
+            .map_err(|e| format!("Failed to release lock {}: {}", key, e))?;
+        Ok(())
+    }
+}
+
+/// Tenta adquirir o lock distribuído `key` por `ttl_secs` segundos.
+///
+/// Retorna `Ok(true)` se esta instância ficou com o lock, `Ok(false)` se outra
+/// instância já o detém (lock ainda não expirou).
+pub async fn acquire_lock<S: LockStore>(store: &S, key: &str, owner: &str, ttl_secs: i64) -> Result<bool, String> {
+    let now = chrono::Utc::now().timestamp_millis();
+    store.try_claim(key, owner, now, now + ttl_secs * 1000).await
+}
+
+/// Libera o lock se ainda pertencer a `owner`
+pub async fn release_lock<S: LockStore>(store: &S, key: &str, owner: &str) -> Result<(), String> {
+    store.release(key, owner).await
+}
+
+/// Executa `work` só se `owner` conseguir o lock `key`, liberando-o ao final.
+/// Retorna `Ok(None)` quando outro dono detém o lock (ninguém executa duas vezes).
+pub async fn with_lock<S, F, Fut, T>(store: &S, key: &str, owner: &str, ttl_secs: i64, work: F) -> Result<Option<T>, String>
+where
+    S: LockStore,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = T>,
+{
+    if !acquire_lock(store, key, owner, ttl_secs).await? {
+        return Ok(None);
+    }
+    let result = work().await;
+    if let Err(e) = release_lock(store, key, owner).await {
+        log::warn!("⚠️ {}", e);
+    }
+    Ok(Some(result))
+}
+
+fn is_duplicate_key_error(e: &mongodb::error::Error) -> bool {
+    e.to_string().contains("E11000")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Mesma semântica do upsert no Mongo: reivindica se ausente ou vencido
+    #[derive(Default)]
+    struct MemoryLockStore {
+        locks: Mutex<HashMap<String, (String, i64)>>,
+    }
+
+    impl LockStore for MemoryLockStore {
+        async fn try_claim(&self, key: &str, owner: &str, now_ms: i64, expires_at_ms: i64) -> Result<bool, String> {
+            let mut locks = self.locks.lock().unwrap();
+            match locks.get(key) {
+                Some((_, expires_at)) if *expires_at >= now_ms => Ok(false),
+                _ => {
+                    locks.insert(key.to_string(), (owner.to_string(), expires_at_ms));
+                    Ok(true)
+                }
+            }
+        }
+
+        async fn release(&self, key: &str, owner: &str) -> Result<(), String> {
+            let mut locks = self.locks.lock().unwrap();
+            if locks.get(key).is_some_and(|(o, _)| o == owner) {
+                locks.remove(key);
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_two_instances_compete_and_expired_lease_is_reacquired() {
+        let store = MemoryLockStore::default();
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let key = "snapshot:test-user:2026-01-01";
+
+        let instance = |owner: &'static str| {
+            let (store, fetches) = (&store, fetches.clone());
+            async move {
+                with_lock(store, key, owner, 300, || async {
+                    // Simula o fetch de balances (caro) enquanto segura o lock
+                    fetches.fetch_add(1, Ordering::SeqCst);
+                    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+                }).await.unwrap()
+            }
+        };
+
+        let (a, b) = tokio::join!(instance("instance-a"), instance("instance-b"));
+        assert!(a.is_some() ^ b.is_some(), "exactly one instance should win the lock");
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+
+        // Lock liberado ao final: a próxima rodada consegue de novo
+        assert!(acquire_lock(&store, key, "instance-b", 300).await.unwrap());
+        assert!(!acquire_lock(&store, key, "instance-a", 300).await.unwrap());
+        // Liberar com outro dono não remove o lock
+        release_lock(&store, key, "instance-a").await.unwrap();
+        assert!(!acquire_lock(&store, key, "instance-a", 300).await.unwrap());
+
+        // Instância que caiu sem liberar: depois do lease vencer, outra assume
+        let crashed = "snapshot:test-user:2026-01-02";
+        assert!(acquire_lock(&store, crashed, "instance-a", 0).await.unwrap());
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        assert!(acquire_lock(&store, crashed, "instance-b", 300).await.unwrap());
+        assert!(!acquire_lock(&store, crashed, "instance-a", 300).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_saves_only_one_fetches() {
+        let fetches = Arc::new(AtomicUsize::new(0));
+
+        let attempt = |fetches: Arc<AtomicUsize>| async move {
+            let Some(_guard) = try_local_lock("snapshot:test-user:2026-01-01") else {
+                return false;
+            };
+            // Simula o fetch de balances (caro) enquanto segura o lock
+            fetches.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            true
+        };
+
+        let (a, b) = tokio::join!(attempt(fetches.clone()), attempt(fetches.clone()));
+
+        assert!(a ^ b, "exactly one attempt should win the lock");
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+
+        // Depois de liberado, o lock pode ser adquirido de novo
+        assert!(try_local_lock("snapshot:test-user:2026-01-01").is_some());
+    }
+}
@@ -0,0 +1,196 @@
+//! 🔊 Nível de log ajustável em runtime, por módulo
+//!
+//! O `env_logger` lê `RUST_LOG` uma vez na inicialização. `RuntimeLogger`
+//! envolve o filtro do env e consulta antes os overrides de `LOG_LEVELS`
+//! (alterados via `PUT /api/v1/admin/log-level`), então dá para ligar `debug`
+//! num módulo sem reiniciar. O target casa com o mesmo critério do env_logger:
+//! igual ao override ou começando com `override::`; vence o override mais longo.
+
+use lazy_static::lazy_static;
+use log::{LevelFilter, Log, Metadata, Record};
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+
+lazy_static! {
+    /// Overrides globais usados pelo logger instalado em `init`
+    pub static ref LOG_LEVELS: RuntimeLevels = RuntimeLevels::new();
+}
+
+struct LevelState {
+    /// Nível máximo do filtro do env (base para `log::set_max_level`)
+    base_max: LevelFilter,
+    overrides: BTreeMap<String, LevelFilter>,
+}
+
+pub struct RuntimeLevels {
+    state: RwLock<LevelState>,
+}
+
+impl Default for RuntimeLevels {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RuntimeLevels {
+    pub fn new() -> Self {
+        Self {
+            state: RwLock::new(LevelState { base_max: LevelFilter::Info, overrides: BTreeMap::new() }),
+        }
+    }
+
+    /// Override mais específico para o target (None = usa o filtro do env)
+    pub fn level_for(&self, target: &str) -> Option<LevelFilter> {
+        let state = self.state.read().unwrap_or_else(|e| e.into_inner());
+        state.overrides.iter()
+            .filter(|(module, _)| {
+                target == module.as_str()
+                    || target.strip_prefix(module.as_str()).is_some_and(|rest| rest.starts_with("::"))
+            })
+            .max_by_key(|(module, _)| module.len())
+            .map(|(_, level)| *level)
+    }
+
+    /// Define (Some) ou remove (None) o override de um módulo
+    pub fn set(&self, target: &str, level: Option<LevelFilter>) {
+        let mut state = self.state.write().unwrap_or_else(|e| e.into_inner());
+        match level {
+            Some(level) => { state.overrides.insert(target.to_string(), level); }
+            None => { state.overrides.remove(target); }
+        }
+    }
+
+    pub fn overrides(&self) -> BTreeMap<String, String> {
+        let state = self.state.read().unwrap_or_else(|e| e.into_inner());
+        state.overrides.iter()
+            .map(|(module, level)| (module.clone(), level.to_string().to_lowercase()))
+            .collect()
+    }
+
+    fn set_base_max(&self, level: LevelFilter) {
+        let mut state = self.state.write().unwrap_or_else(|e| e.into_inner());
+        state.base_max = level;
+    }
+
+    /// Maior nível entre o env e os overrides (o `log` descarta antes do logger tudo acima disso)
+    pub fn max_level(&self) -> LevelFilter {
+        let state = self.state.read().unwrap_or_else(|e| e.into_inner());
+        state.overrides.values().copied().fold(state.base_max, Ord::max)
+    }
+}
+
+/// Valida o nome do módulo (ex: "trading_service::services::strategy_service")
+pub fn validate_target(target: &str) -> Result<(), String> {
+    let valid = !target.is_empty()
+        && target.len() <= 200
+        && target.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':');
+    if valid { Ok(()) } else { Err(format!("Invalid log target '{}'", target)) }
+}
+
+/// "off" | "error" | "warn" | "info" | "debug" | "trace"
+pub fn parse_level(level: &str) -> Result<LevelFilter, String> {
+    level.parse::<LevelFilter>()
+        .map_err(|_| format!("Invalid log level '{}'. Use off, error, warn, info, debug or trace", level))
+}
+
+pub struct RuntimeLogger {
+    /// Filtro vindo de `RUST_LOG` (usado quando não há override)
+    base: env_logger::Logger,
+    /// Saída formatada (aceita tudo; a filtragem é feita aqui)
+    sink: Box<dyn Log>,
+    levels: &'static RuntimeLevels,
+}
+
+impl RuntimeLogger {
+    pub fn new(base: env_logger::Logger, sink: Box<dyn Log>, levels: &'static RuntimeLevels) -> Self {
+        levels.set_base_max(base.filter());
+        Self { base, sink, levels }
+    }
+}
+
+impl Log for RuntimeLogger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        match self.levels.level_for(metadata.target()) {
+            Some(level) => metadata.level() <= level,
+            None => self.base.enabled(metadata),
+        }
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        if self.enabled(record.metadata()) {
+            self.sink.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.sink.flush();
+    }
+}
+
+/// Instala o logger global (substitui `env_logger::init_from_env`)
+pub fn init(default_filter: &str) {
+    let env = || env_logger::Env::new().default_filter_or(default_filter);
+    let base = env_logger::Builder::from_env(env()).build();
+    let sink = env_logger::Builder::from_env(env()).filter_level(LevelFilter::Trace).build();
+    let logger = RuntimeLogger::new(base, Box::new(sink), &LOG_LEVELS);
+
+    if log::set_boxed_logger(Box::new(logger)).is_ok() {
+        log::set_max_level(LOG_LEVELS.max_level());
+    }
+}
+
+/// Aplica um override em `LOG_LEVELS` e ajusta o nível máximo global
+pub fn set_module_level(target: &str, level: Option<LevelFilter>) {
+    LOG_LEVELS.set(target, level);
+    log::set_max_level(LOG_LEVELS.max_level());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    struct Capture(Arc<Mutex<Vec<String>>>);
+
+    impl Log for Capture {
+        fn enabled(&self, _: &Metadata<'_>) -> bool { true }
+        fn log(&self, record: &Record<'_>) {
+            self.0.lock().unwrap().push(format!("{} {}", record.target(), record.args()));
+        }
+        fn flush(&self) {}
+    }
+
+    fn emit(logger: &RuntimeLogger, target: &str, level: log::Level, msg: &str) {
+        logger.log(&Record::builder().target(target).level(level).args(format_args!("{}", msg)).build());
+    }
+
+    #[test]
+    fn test_module_override_changes_emitted_records() {
+        let levels: &'static RuntimeLevels = Box::leak(Box::new(RuntimeLevels::new()));
+        let emitted = Arc::new(Mutex::new(Vec::new()));
+        let base = env_logger::Builder::new().filter_level(LevelFilter::Info).build();
+        let logger = RuntimeLogger::new(base, Box::new(Capture(emitted.clone())), levels);
+
+        let strategy = "trading_service::services::strategy_service";
+        emit(&logger, strategy, log::Level::Debug, "before");
+        assert!(emitted.lock().unwrap().is_empty());
+
+        levels.set("trading_service::services", Some(LevelFilter::Debug));
+        assert_eq!(levels.max_level(), LevelFilter::Debug);
+        emit(&logger, strategy, log::Level::Debug, "after");
+        emit(&logger, "trading_service::jobs::registry", log::Level::Debug, "other module");
+        emit(&logger, "trading_service::servicesx", log::Level::Debug, "prefix without ::");
+        assert_eq!(*emitted.lock().unwrap(), vec![format!("{} after", strategy)]);
+
+        // Override mais específico vence; remover volta ao filtro do env
+        levels.set(strategy, Some(LevelFilter::Warn));
+        emit(&logger, strategy, log::Level::Info, "silenced");
+        levels.set(strategy, None);
+        levels.set("trading_service::services", None);
+        emit(&logger, strategy, log::Level::Debug, "reset");
+        emit(&logger, strategy, log::Level::Info, "info");
+        assert_eq!(emitted.lock().unwrap().len(), 2);
+        assert!(parse_level("verbose").is_err());
+        assert!(validate_target("bad target!").is_err());
+    }
+}
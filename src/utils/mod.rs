@@ -2,3 +2,16 @@
 pub mod error;
 pub mod crypto;
 pub mod thread_pool;
+pub mod lock;
+pub mod indicators;
+pub mod ticker_cache;
+pub mod expression;
+pub mod precision;
+pub mod log_level;
+pub mod currency_format;
+pub mod rate_limiter;
+pub mod circuit_breaker;
+pub mod server_config;
+pub mod clock_skew;
+pub mod trading_switch;
+pub mod runtime_config;
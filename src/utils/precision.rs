@@ -0,0 +1,127 @@
+//! 🎯 Arredondamento por precisão de mercado
+//!
+//! Os cálculos continuam em f64 completo; só os valores persistidos/exibidos
+//! (ex.: `StrategyExecution`) são arredondados para as casas decimais do
+//! mercado, evitando ruído de ponto flutuante como `0.30000000000000004`.
+//! Sem precisão do mercado usa-se `EXECUTION_AMOUNT_DECIMALS` /
+//! `EXECUTION_PRICE_DECIMALS` (padrão 8).
+
+use crate::models::MarketPrecision;
+
+const DEFAULT_DECIMALS: u32 = 8;
+const MAX_DECIMALS: u32 = 12;
+/// Taxa e PnL (moeda de cotação): casas fixas, independentes do tick de preço do par
+pub const QUOTE_VALUE_DECIMALS: u32 = 8;
+
+/// Modos de precisão do CCXT (`exchange.precisionMode`; 3 = SIGNIFICANT_DIGITS, não suportado)
+pub const CCXT_DECIMAL_PLACES: i64 = 2;
+pub const CCXT_TICK_SIZE: i64 = 4;
+
+fn env_decimals(key: &str) -> u32 {
+    std::env::var(key)
+        .ok()
+        .and_then(|v| v.parse::<u32>().ok())
+        .unwrap_or(DEFAULT_DECIMALS)
+        .min(MAX_DECIMALS)
+}
+
+/// Arredonda para `decimals` casas (half away from zero)
+pub fn round_to(value: f64, decimals: u32) -> f64 {
+    if !value.is_finite() {
+        return value;
+    }
+    let factor = 10f64.powi(decimals.min(MAX_DECIMALS) as i32);
+    (value * factor).round() / factor
+}
+
+/// Trunca para `decimals` casas em direção a zero (quantidades: nunca acima do executado).
+/// Valores que já cabem nas casas (ruído de f64) são só arredondados
+pub fn truncate_to(value: f64, decimals: u32) -> f64 {
+    if !value.is_finite() || fits_decimals(value, decimals) {
+        return round_to(value, decimals);
+    }
+    let factor = 10f64.powi(decimals.min(MAX_DECIMALS) as i32);
+    (value * factor).trunc() / factor
+}
+
+/// `value` já está representado em no máximo `decimals` casas (tolerando ruído de f64).
+/// A tolerância é relativa à magnitude: em valores grandes o erro da multiplicação
+/// passa de 1e-6 mesmo quando o valor cabe nas casas
+pub fn fits_decimals(value: f64, decimals: u32) -> bool {
+    if !value.is_finite() {
+        return false;
+    }
+    let scaled = value * 10f64.powi(decimals.min(MAX_DECIMALS) as i32);
+    let tolerance = (scaled.abs() * f64::EPSILON * 4.0).max(1e-6);
+    (scaled - scaled.round()).abs() < tolerance
+}
+
+/// Converte o valor de `market.precision` do CCXT em casas decimais.
+/// TICK_SIZE: 0.001 → 3; DECIMAL_PLACES: 3 → 3. SIGNIFICANT_DIGITS não é mapeável.
+pub fn ccxt_precision_to_decimals(raw: f64, precision_mode: i64) -> Option<u32> {
+    if !raw.is_finite() || raw <= 0.0 {
+        return None;
+    }
+    match precision_mode {
+        CCXT_DECIMAL_PLACES => Some((raw.round() as u32).min(MAX_DECIMALS)),
+        CCXT_TICK_SIZE => {
+            let decimals = (-raw.log10()).ceil().max(0.0) as u32;
+            Some(decimals.min(MAX_DECIMALS))
+        }
+        _ => None,
+    }
+}
+
+/// Casas decimais efetivas para quantidade e preço
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExecutionPrecision {
+    pub amount: u32,
+    pub price: u32,
+}
+
+impl ExecutionPrecision {
+    /// Precisão do mercado, com fallback para a configuração padrão
+    pub fn from_market(market: Option<&MarketPrecision>) -> Self {
+        let defaults = Self::default();
+        Self {
+            amount: market.and_then(|m| m.amount_decimals).unwrap_or(defaults.amount),
+            price: market.and_then(|m| m.price_decimals).unwrap_or(defaults.price),
+        }
+    }
+}
+
+impl Default for ExecutionPrecision {
+    fn default() -> Self {
+        Self {
+            amount: env_decimals("EXECUTION_AMOUNT_DECIMALS"),
+            price: env_decimals("EXECUTION_PRICE_DECIMALS"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ccxt_precision_modes() {
+        assert_eq!(ccxt_precision_to_decimals(0.001, CCXT_TICK_SIZE), Some(3));
+        assert_eq!(ccxt_precision_to_decimals(1.0, CCXT_TICK_SIZE), Some(0));
+        assert_eq!(ccxt_precision_to_decimals(0.5, CCXT_TICK_SIZE), Some(1));
+        assert_eq!(ccxt_precision_to_decimals(4.0, CCXT_DECIMAL_PLACES), Some(4));
+        assert_eq!(ccxt_precision_to_decimals(5.0, 3), None); // SIGNIFICANT_DIGITS
+        assert_eq!(round_to(0.1 + 0.2, 4), 0.3);
+        assert!(fits_decimals(0.1 + 0.2, 1));
+        assert!(!fits_decimals(0.12345, 4));
+        // Valores grandes: o erro de escala é relativo, não absoluto
+        assert!(fits_decimals(1234567.12345678, 8));
+        assert!(fits_decimals(98765432.1, 8));
+        assert!(!fits_decimals(1234567.12345678, 7));
+        assert!(!fits_decimals(1234567.125, 2));
+        // Quantidade truncada em direção a zero; ruído de f64 não perde uma casa
+        assert_eq!(truncate_to(0.12349, 4), 0.1234);
+        assert_eq!(truncate_to(-0.12349, 4), -0.1234);
+        assert_eq!(truncate_to(0.1 + 0.2, 4), 0.3);
+        assert_eq!(truncate_to(0.3 - 1e-17, 1), 0.3);
+    }
+}
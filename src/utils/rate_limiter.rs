@@ -0,0 +1,151 @@
+//! 🚦 Rate limit global por exchange (token bucket compartilhado)
+//!
+//! O `enableRateLimit` do CCXT só espaça chamadas dentro de uma instância,
+//! mas criamos um `CCXTClient` novo por request — então o ritmo zera a cada
+//! chamada e rajadas (várias estratégias + balances) estouram o limite da
+//! exchange. Aqui existe um bucket por `ccxt_id`, compartilhado por todos os
+//! clients, que `spawn_ccxt_paced` consome antes de mandar a chamada ao pool.
+//!
+//! O intervalo entre tokens é o `rateLimit` (ms) da exchange, registrado
+//! quando um `CCXTClient` é criado; até lá vale `DEFAULT_RATE_LIMIT_MS`.
+//! O bucket comporta `CCXT_RATE_LIMIT_BURST` tokens (padrão 1 = sem rajada).
+
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const DEFAULT_RATE_LIMIT_MS: u64 = 100;
+const DEFAULT_RATE_LIMIT_BURST: u32 = 1;
+
+lazy_static! {
+    /// Buckets globais usados por `thread_pool::spawn_ccxt_paced`
+    pub static ref CCXT_RATE_LIMITER: RateLimiter =
+        RateLimiter::new(Duration::from_millis(DEFAULT_RATE_LIMIT_MS), rate_limit_burst());
+}
+
+/// Tamanho do bucket configurado via env (`CCXT_RATE_LIMIT_BURST`)
+fn rate_limit_burst() -> u32 {
+    std::env::var("CCXT_RATE_LIMIT_BURST")
+        .ok()
+        .and_then(|v| v.parse::<u32>().ok())
+        .filter(|b| *b > 0)
+        .unwrap_or(DEFAULT_RATE_LIMIT_BURST)
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    interval: Duration,
+    /// Momento em que o bucket volta a ficar cheio (GCRA: "theoretical arrival time")
+    full_at: Instant,
+}
+
+pub struct RateLimiter {
+    default_interval: Duration,
+    burst: u32,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(default_interval: Duration, burst: u32) -> Self {
+        Self { default_interval, burst: burst.max(1), buckets: Mutex::new(HashMap::new()) }
+    }
+
+    /// Define o intervalo entre tokens de uma exchange (o `rateLimit` do CCXT, em ms)
+    pub fn set_interval(&self, ccxt_id: &str, interval: Duration) {
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        buckets
+            .entry(ccxt_id.to_lowercase())
+            .and_modify(|b| b.interval = interval)
+            .or_insert(Bucket { interval, full_at: Instant::now() });
+    }
+
+    /// Reserva um token e devolve quanto tempo esperar até poder usá-lo.
+    /// A reserva é feita sob o lock, então chamadas concorrentes recebem
+    /// vagas sucessivas em vez de todas dispararem juntas.
+    fn reserve(&self, ccxt_id: &str, now: Instant) -> Duration {
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        let bucket = buckets
+            .entry(ccxt_id.to_lowercase())
+            .or_insert(Bucket { interval: self.default_interval, full_at: now });
+
+        let full_at = bucket.full_at.max(now) + bucket.interval;
+        let capacity = bucket.interval * self.burst;
+        bucket.full_at = full_at;
+
+        full_at.checked_sub(capacity).map(|ready| ready.saturating_duration_since(now)).unwrap_or_default()
+    }
+
+    /// Espera até haver um token disponível para a exchange
+    pub async fn acquire(&self, ccxt_id: &str) {
+        let wait = self.reserve(ccxt_id, Instant::now());
+        if !wait.is_zero() {
+            log::debug!("🚦 [{}] Aguardando {}ms pelo rate limit", ccxt_id, wait.as_millis());
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Versão síncrona de `acquire` para closures que já rodam no pool CCXT e
+    /// fazem mais de um request: cada request extra consome seu próprio token
+    pub fn acquire_blocking(&self, ccxt_id: &str) {
+        let wait = self.reserve(ccxt_id, Instant::now());
+        if !wait.is_zero() {
+            log::debug!("🚦 [{}] Aguardando {}ms pelo rate limit", ccxt_id, wait.as_millis());
+            std::thread::sleep(wait);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_concurrent_calls_to_same_exchange_are_paced() {
+        let limiter = RateLimiter::new(Duration::from_millis(5), 1);
+        limiter.set_interval("binance", Duration::from_millis(40));
+
+        let start = Instant::now();
+        let stamp = |id: &'static str| {
+            let limiter = &limiter;
+            async move {
+                limiter.acquire(id).await;
+                start.elapsed()
+            }
+        };
+
+        let (a, b, c, other) = tokio::join!(
+            stamp("binance"),
+            stamp("binance"),
+            stamp("binance"),
+            stamp("kucoin"),
+        );
+
+        let mut times = [a, b, c];
+        times.sort();
+        assert!(times[0] < Duration::from_millis(40));
+        assert!(times[1] >= Duration::from_millis(40));
+        assert!(times[2] >= Duration::from_millis(80));
+        // Outra exchange tem seu próprio bucket
+        assert!(other < Duration::from_millis(40));
+    }
+
+    #[test]
+    fn test_burst_allows_tokens_up_front() {
+        let limiter = RateLimiter::new(Duration::from_millis(100), 2);
+        let now = Instant::now();
+        assert_eq!(limiter.reserve("okx", now), Duration::ZERO);
+        assert_eq!(limiter.reserve("okx", now), Duration::ZERO);
+        assert_eq!(limiter.reserve("okx", now), Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_acquire_blocking_paces_extra_requests() {
+        let limiter = RateLimiter::new(Duration::from_millis(30), 1);
+        let start = Instant::now();
+        limiter.acquire_blocking("mexc");
+        limiter.acquire_blocking("mexc");
+        limiter.acquire_blocking("mexc");
+        assert!(start.elapsed() >= Duration::from_millis(60));
+    }
+}
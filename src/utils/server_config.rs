@@ -0,0 +1,86 @@
+//! ⚙️ Tuning do `HttpServer` via env
+//!
+//! `WORKERS` (padrão: núcleos físicos, o default do actix), `KEEP_ALIVE_SECS`
+//! (padrão 5; 0 desliga o keep-alive) e `CLIENT_TIMEOUT_MS` (tempo máximo para
+//! receber o cabeçalho da request, padrão 5000; 0 desativa). Valores inválidos
+//! geram um aviso no log e caem no padrão em vez de derrubar o boot.
+
+use std::str::FromStr;
+use std::time::Duration;
+
+const DEFAULT_KEEP_ALIVE_SECS: u64 = 5;
+const DEFAULT_CLIENT_TIMEOUT_MS: u64 = 5000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerConfig {
+    /// None = deixa o actix decidir (núcleos físicos)
+    pub workers: Option<usize>,
+    pub keep_alive: Duration,
+    pub client_timeout: Duration,
+}
+
+impl ServerConfig {
+    pub fn from_env() -> Self {
+        Self::from_lookup(|key| std::env::var(key).ok())
+    }
+
+    /// Lê a configuração de `lookup` (env em produção, mapa nos testes)
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let workers = parse_var::<usize>(&lookup, "WORKERS").and_then(|w| {
+            if w == 0 {
+                log::warn!("⚠️ Ignoring WORKERS=0, using the default worker count");
+                None
+            } else {
+                Some(w)
+            }
+        });
+        ServerConfig {
+            workers,
+            keep_alive: Duration::from_secs(
+                parse_var(&lookup, "KEEP_ALIVE_SECS").unwrap_or(DEFAULT_KEEP_ALIVE_SECS),
+            ),
+            client_timeout: Duration::from_millis(
+                parse_var(&lookup, "CLIENT_TIMEOUT_MS").unwrap_or(DEFAULT_CLIENT_TIMEOUT_MS),
+            ),
+        }
+    }
+}
+
+fn parse_var<T: FromStr>(lookup: &impl Fn(&str) -> Option<String>, key: &str) -> Option<T> {
+    let raw = lookup(key)?;
+    match raw.trim().parse::<T>() {
+        Ok(value) => Some(value),
+        Err(_) => {
+            log::warn!("⚠️ Invalid {}='{}', using the default", key, raw);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn config(vars: &[(&str, &str)]) -> ServerConfig {
+        let vars: HashMap<String, String> = vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        ServerConfig::from_lookup(|key| vars.get(key).cloned())
+    }
+
+    #[test]
+    fn test_server_config_values_defaults_and_invalid_input() {
+        assert_eq!(config(&[("WORKERS", "8"), ("KEEP_ALIVE_SECS", "75"), ("CLIENT_TIMEOUT_MS", "2500")]), ServerConfig {
+            workers: Some(8),
+            keep_alive: Duration::from_secs(75),
+            client_timeout: Duration::from_millis(2500),
+        });
+
+        let defaults = ServerConfig {
+            workers: None,
+            keep_alive: Duration::from_secs(DEFAULT_KEEP_ALIVE_SECS),
+            client_timeout: Duration::from_millis(DEFAULT_CLIENT_TIMEOUT_MS),
+        };
+        assert_eq!(config(&[]), defaults);
+        assert_eq!(config(&[("WORKERS", "0"), ("KEEP_ALIVE_SECS", "-1"), ("CLIENT_TIMEOUT_MS", "fast")]), defaults);
+    }
+}
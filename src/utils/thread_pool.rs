@@ -42,6 +42,18 @@ where
     CCXT_POOL.spawn_blocking(f).await
 }
 
+/// Igual a `spawn_ccxt_blocking`, mas antes consome um token do bucket global
+/// da exchange (`rate_limiter::CCXT_RATE_LIMITER`). Use sempre que a closure
+/// fizer request a uma exchange, para que clients diferentes dividam o limite.
+pub async fn spawn_ccxt_paced<F, R>(ccxt_id: &str, f: F) -> Result<R, tokio::task::JoinError>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    crate::utils::rate_limiter::CCXT_RATE_LIMITER.acquire(ccxt_id).await;
+    spawn_ccxt_blocking(f).await
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,187 @@
+//! ⚡ Cache de preços (ticker) com TTL curto, compartilhado entre estratégias
+//!
+//! Várias estratégias no mesmo exchange/símbolo fazem tick ao mesmo tempo;
+//! sem cache cada uma faz seu próprio `fetch_ticker` no CCXT. A chave é
+//! `(ccxt_id, symbol)` e o TTL padrão é 500ms (`TICKER_CACHE_TTL_MS`).
+//!
+//! Chamadas concorrentes para a mesma chave esperam no mutex da entrada,
+//! então só a primeira vai ao exchange — as demais reaproveitam o preço.
+//! Um preço nunca é servido depois de expirado o TTL.
+//! Junto com o preço fica o `timestamp` do ticker na exchange, usado pela
+//! proteção contra preço defasado do motor de estratégias.
+//! Ao criar uma entrada nova, as expiradas (e sem ninguém esperando nelas)
+//! são descartadas, então o mapa não cresce com símbolos que ninguém mais consulta.
+
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const DEFAULT_TICKER_CACHE_TTL_MS: u64 = 500;
+
+lazy_static! {
+    /// Cache global usado por `strategy_service::fetch_current_price`
+    pub static ref TICKER_CACHE: TickerCache<PriceQuote> = TickerCache::new(ticker_cache_ttl());
+}
+
+/// Último preço e o momento (ms, relógio da exchange) em que foi cotado
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PriceQuote {
+    pub price: f64,
+    /// `ticker.timestamp`; None quando a exchange não informa
+    pub timestamp: Option<i64>,
+}
+
+/// TTL configurado via env (`TICKER_CACHE_TTL_MS`). 0 desativa o cache.
+fn ticker_cache_ttl() -> Duration {
+    let ms = std::env::var("TICKER_CACHE_TTL_MS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_TICKER_CACHE_TTL_MS);
+    Duration::from_millis(ms)
+}
+
+type Slot<T> = Arc<tokio::sync::Mutex<Option<(Instant, T)>>>;
+
+pub struct TickerCache<T> {
+    ttl: Duration,
+    entries: Mutex<HashMap<(String, String), Slot<T>>>,
+}
+
+impl<T: Clone> TickerCache<T> {
+    pub fn new(ttl: Duration) -> Self {
+        Self { ttl, entries: Mutex::new(HashMap::new()) }
+    }
+
+    fn slot(&self, ccxt_id: &str, symbol: &str) -> Slot<T> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let key = (ccxt_id.to_string(), symbol.to_string());
+        if !entries.contains_key(&key) {
+            entries.retain(|_, slot| !self.is_evictable(slot));
+        }
+        entries.entry(key).or_default().clone()
+    }
+
+    /// Entrada expirada (ou nunca preenchida) que ninguém está usando
+    fn is_evictable(&self, slot: &Slot<T>) -> bool {
+        if Arc::strong_count(slot) > 1 {
+            return false;
+        }
+        match slot.try_lock() {
+            Ok(cached) => cached.as_ref().is_none_or(|(fetched_at, _)| fetched_at.elapsed() >= self.ttl),
+            Err(_) => false,
+        }
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    /// Retorna o preço em cache se ainda estiver dentro do TTL; caso contrário
+    /// executa `fetch` (uma única vez para chamadas concorrentes) e guarda o resultado.
+    /// Erros não são cacheados.
+    pub async fn get_or_fetch<F, Fut>(&self, ccxt_id: &str, symbol: &str, fetch: F) -> Result<T, String>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, String>>,
+    {
+        if self.ttl.is_zero() {
+            return fetch().await;
+        }
+
+        let slot = self.slot(ccxt_id, symbol);
+        let mut cached = slot.lock().await;
+
+        if let Some((fetched_at, price)) = cached.as_ref() {
+            if fetched_at.elapsed() < self.ttl {
+                return Ok(price.clone());
+            }
+        }
+
+        let price = fetch().await?;
+        *cached = Some((Instant::now(), price.clone()));
+        Ok(price)
+    }
+
+    /// Descarta as entradas cuja chave satisfaz `matches`; a próxima leitura vai
+    /// à exchange. Uma busca em andamento termina numa entrada já removida e é descartada.
+    pub fn invalidate_where(&self, matches: impl Fn(&str, &str) -> bool) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.retain(|(a, b), _| !matches(a, b));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn test_concurrent_fetches_within_ttl_hit_exchange_once() {
+        let cache = TickerCache::new(Duration::from_millis(500));
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let fetch = |calls: Arc<AtomicUsize>| async move {
+            calls.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            Ok(65_000.0)
+        };
+
+        let (a, b) = tokio::join!(
+            cache.get_or_fetch("binance", "BTC/USDT", || fetch(calls.clone())),
+            cache.get_or_fetch("binance", "BTC/USDT", || fetch(calls.clone())),
+        );
+
+        assert_eq!(a, Ok(65_000.0));
+        assert_eq!(b, Ok(65_000.0));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // Outro símbolo não compartilha a entrada
+        cache.get_or_fetch("binance", "ETH/USDT", || fetch(calls.clone())).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_expired_price_is_refetched() {
+        let cache = TickerCache::new(Duration::from_millis(10));
+        cache.get_or_fetch("binance", "BTC/USDT", || async { Ok(1.0) }).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let price = cache.get_or_fetch("binance", "BTC/USDT", || async { Ok(2.0) }).await;
+        assert_eq!(price, Ok(2.0));
+    }
+
+    #[tokio::test]
+    async fn test_expired_entries_are_evicted_on_insert() {
+        let cache = TickerCache::new(Duration::from_millis(10));
+        for symbol in ["BTC/USDT", "ETH/USDT", "SOL/USDT"] {
+            cache.get_or_fetch("binance", symbol, || async { Ok(1.0) }).await.unwrap();
+        }
+        // Erro não cacheia, mas a entrada vazia também é descartável
+        let _ = cache.get_or_fetch("binance", "BAD/USDT", || async { Err::<f64, _>("boom".to_string()) }).await;
+        assert_eq!(cache.len(), 4);
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        cache.get_or_fetch("kraken", "BTC/USD", || async { Ok(2.0) }).await.unwrap();
+        assert_eq!(cache.len(), 1, "expired entries should be dropped when a new key is inserted");
+
+        // Entradas dentro do TTL continuam
+        cache.get_or_fetch("kraken", "ETH/USD", || async { Ok(3.0) }).await.unwrap();
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_invalidated_entry_is_refetched_within_ttl() {
+        let cache = TickerCache::new(Duration::from_secs(60));
+        cache.get_or_fetch("binance", "BTC/USDT", || async { Ok(1.0) }).await.unwrap();
+        cache.get_or_fetch("binance", "ETH/USDT", || async { Ok(10.0) }).await.unwrap();
+
+        cache.invalidate_where(|_, symbol| symbol == "BTC/USDT");
+
+        let btc = cache.get_or_fetch("binance", "BTC/USDT", || async { Ok(2.0) }).await;
+        let eth = cache.get_or_fetch("binance", "ETH/USDT", || async { Ok(20.0) }).await;
+        assert_eq!(btc, Ok(2.0));
+        assert_eq!(eth, Ok(10.0));
+    }
+}
@@ -0,0 +1,50 @@
+//! 🛑 Kill switch global de trading
+//!
+//! Com o switch desligado, toda ordem real é recusada (`create_order_secure`,
+//! `strategy_service::execute_order` e afins) enquanto monitoramento e sinais
+//! seguem normalmente. O valor inicial vem de `TRADING_ENABLED` (padrão true)
+//! e pode ser alterado sem restart via `PUT /api/v1/admin/trading-enabled`.
+
+use lazy_static::lazy_static;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+pub const TRADING_DISABLED_ERROR: &str = "Trading temporarily disabled by an administrator. Try again later.";
+
+lazy_static! {
+    /// Estado global consultado antes de enviar qualquer ordem
+    pub static ref TRADING_SWITCH: TradingSwitch = TradingSwitch::new(trading_enabled_from_env());
+}
+
+fn trading_enabled_from_env() -> bool {
+    std::env::var("TRADING_ENABLED")
+        .map(|v| !matches!(v.trim().to_lowercase().as_str(), "false" | "0" | "off"))
+        .unwrap_or(true)
+}
+
+pub struct TradingSwitch {
+    enabled: AtomicBool,
+}
+
+impl TradingSwitch {
+    pub fn new(enabled: bool) -> Self {
+        Self { enabled: AtomicBool::new(enabled) }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::SeqCst)
+    }
+
+    /// Altera o switch e devolve o valor anterior
+    pub fn set_enabled(&self, enabled: bool) -> bool {
+        self.enabled.swap(enabled, Ordering::SeqCst)
+    }
+
+    /// Erro padrão quando o trading está desligado
+    pub fn ensure_enabled(&self) -> Result<(), String> {
+        if self.is_enabled() {
+            Ok(())
+        } else {
+            Err(TRADING_DISABLED_ERROR.to_string())
+        }
+    }
+}